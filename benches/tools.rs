@@ -7,6 +7,7 @@
 //! - `truncate_head_10k_lines`: <1ms
 //! - `truncate_tail_10k_lines`: <1ms
 //! - `sse_parse_100_events`: <100μs
+//! - `streaming_accumulation/push`: O(1) per delta, independent of answer length
 
 use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
 use std::fmt::Write as _;
@@ -113,6 +114,64 @@ fn bench_sse_parsing(c: &mut Criterion) {
     group.finish();
 }
 
+// ============================================================================
+// Streaming Accumulation Benchmarks
+// ============================================================================
+
+fn build_deltas(delta_count: usize) -> Vec<String> {
+    (0..delta_count)
+        .map(|i| {
+            if i % 8 == 7 {
+                "token\n".to_string()
+            } else {
+                "token ".to_string()
+            }
+        })
+        .collect()
+}
+
+fn bench_streaming_accumulation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("streaming_accumulation");
+
+    for delta_count in [1_000, 10_000, 100_000] {
+        let deltas = build_deltas(delta_count);
+        group.throughput(Throughput::Elements(delta_count as u64));
+        group.bench_with_input(
+            BenchmarkId::new("push", delta_count),
+            &deltas,
+            |b, deltas| {
+                b.iter(|| {
+                    let mut buffer = pi::interactive::StreamingBuffer::new();
+                    for delta in deltas {
+                        buffer.push_str(black_box(delta));
+                    }
+                    black_box(buffer.line_count())
+                });
+            },
+        );
+
+        let mut buffer = pi::interactive::StreamingBuffer::new();
+        for delta in &deltas {
+            buffer.push_str(delta);
+        }
+        group.bench_with_input(
+            BenchmarkId::new("render_lines", delta_count),
+            &buffer,
+            |b, buffer| {
+                b.iter(|| {
+                    let mut bytes = 0usize;
+                    for line in buffer.lines() {
+                        bytes += black_box(line).len();
+                    }
+                    bytes
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
 // ============================================================================
 // Criterion Groups
 // ============================================================================
@@ -122,5 +181,6 @@ criterion_group!(
     bench_truncate_head,
     bench_truncate_tail,
     bench_sse_parsing,
+    bench_streaming_accumulation,
 );
 criterion_main!(benches);
@@ -0,0 +1,12 @@
+//! Gemini streamGenerateContent decoder over raw SSE bytes.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&chunk_len, body)) = data.split_first() else {
+        return;
+    };
+    pi::providers::gemini::fuzz::process_sse_bytes(body, usize::from(chunk_len));
+});
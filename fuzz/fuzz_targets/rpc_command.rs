@@ -0,0 +1,11 @@
+//! RPC request decoder: one stdin line through the command parsing helpers.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = std::str::from_utf8(data) {
+        pi::rpc::fuzz::decode_command_line(line);
+    }
+});
@@ -0,0 +1,21 @@
+//! Session JSONL parser: header + entries, then the tree walks used on resume.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pi::session::Session;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(content) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok((session, _diagnostics)) = Session::from_jsonl_str(content) else {
+        return;
+    };
+
+    let _ = session.to_messages_for_current_path();
+    let _ = session.branch_summary();
+    for leaf in session.list_leaves() {
+        let _ = session.get_path_to_entry(&leaf);
+    }
+});
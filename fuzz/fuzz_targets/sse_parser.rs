@@ -0,0 +1,23 @@
+//! SSE parser: arbitrary text fed in two pieces, then flushed.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pi::sse::SseParser;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let split = text
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .nth(text.len() / 2)
+        .unwrap_or(text.len());
+    let (head, tail) = text.split_at(split);
+
+    let mut parser = SseParser::new();
+    let _ = parser.feed(head);
+    let _ = parser.feed(tail);
+    let _ = parser.flush();
+});
@@ -0,0 +1,17 @@
+//! Theme loader: JSON parse + validation, then style derivation.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pi::theme::Theme;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(content) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(theme) = Theme::from_json_str(content) {
+        let _ = theme.is_light();
+        let _ = theme.tui_styles();
+        let _ = theme.glamour_style_config();
+    }
+});
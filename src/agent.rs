@@ -12,6 +12,7 @@
 //! 4. If tool calls: execute tools, append results, goto 3
 //! 5. If done: return final message
 
+use crate::compaction::{self, ResolvedCompactionSettings};
 use crate::error::{Error, Result};
 use crate::extension_events::{InputEventOutcome, apply_input_event_response};
 use crate::extension_tools::collect_extension_tool_wrappers;
@@ -20,23 +21,36 @@ use crate::extensions::{
     JsExtensionRuntimeHandle,
 };
 use crate::extensions_js::PiJsRuntimeConfig;
+use crate::hooks::{HookEvent, Hooks};
 use crate::model::{
-    AssistantMessage, AssistantMessageEvent, ContentBlock, ImageContent, Message, StopReason,
-    StreamEvent, TextContent, ToolCall, ToolResultMessage, Usage, UserContent, UserMessage,
+    AbortReason, AssistantMessage, AssistantMessageEvent, ContentBlock, ImageContent, Message,
+    StopReason, StreamEvent, TextContent, ToolCall, ToolResultMessage, Usage, UserContent,
+    UserMessage,
 };
-use crate::provider::{Context, Provider, StreamOptions, ToolDef};
-use crate::session::{Session, SessionHandle};
-use crate::tools::{Tool, ToolOutput, ToolRegistry, ToolUpdate};
+use crate::permissions::{PermissionAction, PermissionPolicy};
+use crate::provider::{
+    Context, HostedTool, ModelCost, Provider, RequestInjection, StreamOptions, ToolDef,
+    retryable_failure,
+};
+use crate::request_scheduler::{RequestPriority, acquire_global};
+use crate::session::{Session, SessionHandle, SessionMessage, session_message_to_model};
+use crate::tools::{Tool, ToolOutput, ToolRegistry, ToolUpdate, WriteAccess, truncate_head};
 use asupersync::sync::{Mutex, Notify};
 use chrono::Utc;
 use futures::FutureExt;
 use futures::StreamExt;
 use futures::future::BoxFuture;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tracing::Instrument as _;
 
 // ============================================================================
 // Agent Configuration
@@ -45,7 +59,8 @@ use std::sync::atomic::{AtomicBool, Ordering};
 /// Configuration for the agent.
 #[derive(Debug, Clone)]
 pub struct AgentConfig {
-    /// System prompt to use for all requests.
+    /// System prompt to use for all requests. `{{cwd}}`, `{{date}}`, `{{git_branch}}`, `{{os}}`
+    /// and `{{project_context}}` are filled in fresh on each request.
     pub system_prompt: Option<String>,
 
     /// Maximum tool call iterations before stopping.
@@ -53,6 +68,33 @@ pub struct AgentConfig {
 
     /// Default stream options.
     pub stream_options: StreamOptions,
+
+    /// Append a diagnostic to failed tool results, allowing this many consecutive retries per
+    /// tool before telling the model to change approach. A tool that fails again after that
+    /// stops the run with [`AbortReason::ToolFailure`]. `None` leaves errors untouched.
+    pub tool_retry_diagnostics: Option<u32>,
+
+    /// Headers and query parameters rendered into every provider request.
+    pub request_injection: Option<RequestInjection>,
+
+    /// Byte limits on tool result text, by tool name (`*` for tools not listed). Longer
+    /// results are cut with a continuation note; the full text stays in the result's details.
+    pub tool_output_limits: HashMap<String, usize>,
+
+    /// How long a tool may run, by tool name (`*` for tools not listed). A tool still running
+    /// at its limit is cancelled, killing any processes it started, and returns an error result.
+    pub tool_timeouts: HashMap<String, Duration>,
+
+    /// Prices of the model, for filling in `Usage.cost` when the provider reports none.
+    pub pricing: Option<ModelCost>,
+
+    /// Advertise tools but never run them: each call is answered with a canned result whose
+    /// details record the [`DryRunCall`] it would have made.
+    pub dry_run: bool,
+
+    /// Refuse a tool call made with the same arguments this many times in a row, answering
+    /// with an error that tells the model to change approach. `None` never refuses.
+    pub loop_detection: Option<u32>,
 }
 
 impl Default for AgentConfig {
@@ -61,6 +103,382 @@ impl Default for AgentConfig {
             system_prompt: None,
             max_tool_iterations: 50,
             stream_options: StreamOptions::default(),
+            tool_retry_diagnostics: None,
+            request_injection: None,
+            tool_output_limits: HashMap::new(),
+            tool_timeouts: HashMap::new(),
+            pricing: None,
+            dry_run: false,
+            loop_detection: None,
+        }
+    }
+}
+
+/// Default token budget shared by all pinned files (estimated at ~4 characters per token).
+pub const DEFAULT_PINNED_BUDGET_TOKENS: usize = 8_000;
+
+/// Tool definitions estimated above this many tokens trigger a warning by default.
+pub const DEFAULT_TOOL_DEFS_WARN_TOKENS: u64 = 6_000;
+
+/// How many of the enabled tools each request declares, and when their size is worth a warning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolExposure {
+    /// Warn once when the declared definitions exceed this many estimated tokens.
+    pub warn_tokens: Option<u64>,
+    /// Lazy exposure: declare only these tools plus tools the conversation names; the rest are
+    /// listed by name in the system prompt. `None` declares every enabled tool.
+    pub core_tools: Option<Vec<String>>,
+}
+
+impl Default for ToolExposure {
+    fn default() -> Self {
+        Self {
+            warn_tokens: Some(DEFAULT_TOOL_DEFS_WARN_TOKENS),
+            core_tools: None,
+        }
+    }
+}
+
+/// Which part of the history each request sends. Compaction still summarizes what is dropped
+/// from the session; a strategy only limits what the model sees. Cuts fall on user messages so
+/// tool calls stay with their results, and the current turn is always sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ContextStrategy {
+    /// Send the whole history.
+    #[default]
+    Full,
+    /// Send the newest turns that fit in `max_tokens` (estimated).
+    SlidingWindow {
+        #[serde(alias = "maxTokens")]
+        max_tokens: u64,
+    },
+    /// Send the first `first` and the last `last` turns, dropping the middle.
+    KeepFirstAndLast { first: usize, last: usize },
+}
+
+impl ContextStrategy {
+    /// The messages of `messages` this strategy sends.
+    pub fn apply(self, messages: &[Message]) -> Vec<Message> {
+        let turn_starts = messages
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| matches!(message, Message::User(_)))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        match self {
+            Self::Full => messages.to_vec(),
+            Self::SlidingWindow { max_tokens } => {
+                let Some(&last_start) = turn_starts.last() else {
+                    return messages.to_vec();
+                };
+                let mut start = last_start;
+                let mut tokens = messages[start..].iter().map(message_tokens).sum::<u64>();
+                for &turn_start in turn_starts.iter().rev().skip(1) {
+                    let turn = messages[turn_start..start]
+                        .iter()
+                        .map(message_tokens)
+                        .sum::<u64>();
+                    if tokens + turn > max_tokens {
+                        return messages[start..].to_vec();
+                    }
+                    tokens += turn;
+                    start = turn_start;
+                }
+                messages.to_vec()
+            }
+            Self::KeepFirstAndLast { first, last } => {
+                let last = last.max(1);
+                if turn_starts.len() <= first + last {
+                    return messages.to_vec();
+                }
+                let head_end = turn_starts[first];
+                let tail_start = turn_starts[turn_starts.len() - last];
+                messages[..head_end]
+                    .iter()
+                    .chain(&messages[tail_start..])
+                    .cloned()
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Estimated prompt tokens of one message (~4 characters per token, ~1200 per image).
+fn message_tokens(message: &Message) -> u64 {
+    let block_chars = |blocks: &[ContentBlock]| {
+        blocks
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Image(_) => 4800,
+                block => serde_json::to_string(block).map_or(0, |json| json.len()),
+            })
+            .sum::<usize>()
+    };
+    let chars = match message {
+        Message::User(user) => match &user.content {
+            UserContent::Text(text) => text.len(),
+            UserContent::Blocks(blocks) => block_chars(blocks),
+        },
+        Message::Assistant(assistant) => block_chars(&assistant.content),
+        Message::ToolResult(result) => block_chars(&result.content),
+        Message::Custom(custom) => custom.content.len(),
+    };
+    u64::try_from(chars.div_ceil(4)).unwrap_or(u64::MAX)
+}
+
+/// Estimated prompt tokens of one tool definition (~4 characters per token).
+pub fn tool_def_tokens(tool: &ToolDef) -> u64 {
+    let chars = tool.name.len() + tool.description.len() + tool.parameters.to_string().len();
+    u64::try_from(chars.div_ceil(4)).unwrap_or(u64::MAX)
+}
+
+/// Whether `text` contains `word` delimited by non-identifier characters.
+fn mentions_word(text: &str, word: &str) -> bool {
+    let is_ident = |ch: char| ch.is_alphanumeric() || ch == '_' || ch == '-';
+    text.match_indices(word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
+
+/// At most `limit` bytes of the text file at `path`, cut back to a character boundary, and
+/// whether the file goes on past them.
+fn read_text_prefix(path: &Path, limit: usize) -> std::io::Result<(String, bool)> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?
+        .take(u64::try_from(limit).unwrap_or(u64::MAX).saturating_add(1))
+        .read_to_end(&mut bytes)?;
+    let truncated = bytes.len() > limit;
+    bytes.truncate(limit);
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok((text, truncated)),
+        // Only a character split by the limit is dropped; anything else is not text.
+        Err(err) if truncated && err.utf8_error().error_len().is_none() => {
+            let valid = err.utf8_error().valid_up_to();
+            Ok((
+                String::from_utf8_lossy(&err.as_bytes()[..valid]).into_owned(),
+                true,
+            ))
+        }
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )),
+    }
+}
+
+/// Best-effort exit code from tool output text (e.g. bash's "Command exited with code N").
+fn parse_exit_code(text: &str) -> Option<i32> {
+    let marker = "exited with code ";
+    let start = text.rfind(marker)? + marker.len();
+    let digits: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '-')
+        .collect();
+    digits.parse().ok()
+}
+
+/// Conventional meaning of a POSIX shell exit status.
+const fn exit_code_meaning(code: i32) -> &'static str {
+    match code {
+        1 => "general failure reported by the command",
+        2 => "misuse of a shell builtin or invalid arguments",
+        126 => "command found but not executable (check permissions)",
+        127 => "command not found (check spelling and PATH)",
+        130 => "interrupted (SIGINT)",
+        137 => "killed (SIGKILL; often out of memory or a timeout)",
+        143 => "terminated (SIGTERM)",
+        129..=192 => "killed by a signal",
+        _ => "command-specific failure; check its documentation",
+    }
+}
+
+/// The text blocks of `blocks`, one per line.
+pub(crate) fn blocks_text(blocks: &[ContentBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Default for `loop_detection`: identical calls in a row before the next one is refused.
+pub const DEFAULT_LOOP_DETECTION_THRESHOLD: u32 = 3;
+
+/// Error result for a tool call refused by loop detection.
+fn repeated_tool_call_message(tool_name: &str, repeats: u32) -> String {
+    format!(
+        "Loop detected: `{tool_name}` was called {repeats} times in a row with identical arguments, so this call was not run. Repeating it will not give a different result; change the arguments or try another approach."
+    )
+}
+
+/// Diagnostic appended to a failed tool result when `tool_retry_diagnostics` is enabled.
+fn tool_error_diagnostic(tool_name: &str, output: &ToolOutput, failures: u32, max: u32) -> String {
+    let text = blocks_text(&output.content);
+    let hints = Error::tool(tool_name, text.clone()).hints();
+
+    let mut lines = vec![format!("[diagnostic] {} ({})", hints.summary, hints.code)];
+    if let Some(code) = parse_exit_code(&text) {
+        lines.push(format!("Exit code {code}: {}", exit_code_meaning(code)));
+    }
+    if let Some(hint) = hints.hints.first() {
+        lines.push(format!("Suggestion: {hint}"));
+    }
+    let retries_used = failures - 1;
+    if retries_used < max {
+        lines.push(format!(
+            "Fix the cause before calling `{tool_name}` again ({} of {max} retries left).",
+            max - retries_used
+        ));
+    } else {
+        lines.push(format!(
+            "`{tool_name}` failed {failures} times in a row; try another approach or ask the user."
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Cut a tool result's text to `max_bytes` on a line boundary, keeping the full text in
+/// `details.fullOutput` and telling the model how much was left out.
+///
+/// Text blocks are kept in order until the budget runs out; the block it runs out in is cut and
+/// carries the note, later text blocks are dropped, and other blocks stay where they are.
+fn truncate_tool_output(tool_call: &ToolCall, output: &mut ToolOutput, max_bytes: usize) {
+    let full = blocks_text(&output.content);
+    if full.len() <= max_bytes {
+        return;
+    }
+    let mut budget = max_bytes;
+    let mut kept_bytes = 0;
+    let mut kept_lines = 0;
+    let mut cut = None;
+    for (index, block) in output.content.iter().enumerate() {
+        let ContentBlock::Text(text) = block else {
+            continue;
+        };
+        let truncation = truncate_head(&text.text, usize::MAX, budget);
+        if !truncation.truncated {
+            // `full` joins text blocks with a newline.
+            let used = text.text.len() + 1;
+            budget = budget.saturating_sub(used);
+            kept_bytes += used;
+            kept_lines += truncation.total_lines;
+            continue;
+        }
+        let mut kept = truncation.content;
+        if truncation.first_line_exceeds_limit {
+            let mut end = budget;
+            while !text.text.is_char_boundary(end) {
+                end -= 1;
+            }
+            kept = text.text[..end].to_string();
+        }
+        kept_bytes += kept.len();
+        kept_lines += truncation.output_lines;
+        cut = Some((index, kept));
+        break;
+    }
+    let Some((cut_index, kept)) = cut else {
+        return;
+    };
+
+    let omitted = full.len() - kept_bytes;
+    let note = if tool_call.name == "read" {
+        let offset = tool_call
+            .arguments
+            .get("offset")
+            .and_then(Value::as_u64)
+            .and_then(|offset| usize::try_from(offset).ok())
+            .unwrap_or(1);
+        format!(
+            "[Output truncated, {omitted} more bytes, use offset={} to continue]",
+            offset + kept_lines
+        )
+    } else {
+        format!("[Output truncated, {omitted} more bytes not shown]")
+    };
+
+    if let Some(ContentBlock::Text(text)) = output.content.get_mut(cut_index) {
+        text.text = format!("{kept}\n\n{note}");
+    }
+    let mut index = 0;
+    output.content.retain(|block| {
+        let keep = index <= cut_index || !matches!(block, ContentBlock::Text(_));
+        index += 1;
+        keep
+    });
+    let full = Value::String(full);
+    output.details = Some(match output.details.take() {
+        Some(Value::Object(mut details)) => {
+            details.insert("fullOutput".to_string(), full);
+            Value::Object(details)
+        }
+        Some(details) => json!({ "details": details, "fullOutput": full }),
+        None => json!({ "fullOutput": full }),
+    });
+}
+
+/// What a tool call would have done, recorded in place of running it in dry-run mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunCall {
+    pub tool_name: String,
+    /// Shell command, for `bash`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Files or directories the call names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    pub arguments: Value,
+}
+
+impl DryRunCall {
+    pub fn new(tool_name: &str, arguments: &Value) -> Self {
+        let paths = ["path", "paths"]
+            .iter()
+            .filter_map(|key| arguments.get(key))
+            .flat_map(|value| match value {
+                Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+                value => value.as_str().into_iter().collect::<Vec<_>>(),
+            })
+            .map(str::to_string)
+            .collect();
+        Self {
+            tool_name: tool_name.to_string(),
+            command: arguments
+                .get("command")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            paths,
+            arguments: arguments.clone(),
+        }
+    }
+
+    /// One line: `bash: cargo test`, `write: src/lib.rs`, or the tool and its arguments.
+    pub fn describe(&self) -> String {
+        let target = self.command.clone().unwrap_or_else(|| {
+            if self.paths.is_empty() {
+                self.arguments.to_string()
+            } else {
+                self.paths.join(", ")
+            }
+        });
+        format!("{}: {target}", self.tool_name)
+    }
+
+    fn output(&self) -> ToolOutput {
+        ToolOutput {
+            content: vec![ContentBlock::Text(TextContent::new(format!(
+                "Dry run: `{}` was not executed.",
+                self.describe()
+            )))],
+            details: Some(json!({ "dryRun": self })),
+            is_error: false,
         }
     }
 }
@@ -68,6 +486,237 @@ impl Default for AgentConfig {
 /// Async fetcher for queued messages (steering or follow-up).
 pub type MessageFetcher = Arc<dyn Fn() -> BoxFuture<'static, Vec<Message>> + Send + Sync + 'static>;
 
+/// Tools that change the workspace; the default permission policy asks before running them.
+pub const APPROVAL_REQUIRED_TOOLS: &[&str] = &["bash", "write", "edit"];
+
+/// A tool call waiting for the user's decision.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolApprovalRequest {
+    pub tool_call_id: String,
+    pub tool_name: String,
+    pub args: Value,
+    /// Target path for file tools; `None` for `bash`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl ToolApprovalRequest {
+    fn from_tool_call(tool_call: &ToolCall) -> Self {
+        Self {
+            tool_call_id: tool_call.id.clone(),
+            tool_name: tool_call.name.clone(),
+            args: tool_call.arguments.clone(),
+            path: tool_call
+                .arguments
+                .get("path")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        }
+    }
+
+    /// The rule remembered for an "always" answer: `tool:path` for file tools, else the tool.
+    #[must_use]
+    pub fn always_rule(&self) -> String {
+        self.path.as_ref().map_or_else(
+            || self.tool_name.clone(),
+            |path| format!("{}:{path}", self.tool_name),
+        )
+    }
+
+    /// Whether a remembered rule (a tool name or `tool:path`) covers this request.
+    #[must_use]
+    pub fn matches_rule(&self, rule: &str) -> bool {
+        match rule.split_once(':') {
+            Some((tool, path)) => tool == self.tool_name && self.path.as_deref() == Some(path),
+            None => rule == self.tool_name,
+        }
+    }
+}
+
+/// The user's answer to a [`ToolApprovalRequest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolApprovalDecision {
+    Allow,
+    Deny,
+    /// Allow now and remember the decision for this tool (and path).
+    AlwaysAllow,
+    /// Run a `write`/`edit`, then let the user keep or revert its change hunk by hunk.
+    Review,
+}
+
+/// Async approver consulted when the permission policy answers `ask`.
+pub type ToolApprover =
+    Arc<dyn Fn(ToolApprovalRequest) -> BoxFuture<'static, ToolApprovalDecision> + Send + Sync>;
+
+/// Tools whose change can be reviewed hunk by hunk after [`ToolApprovalDecision::Review`].
+pub const HUNK_REVIEW_TOOLS: &[&str] = &["write", "edit"];
+
+/// A finished file change the user asked to review hunk by hunk.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkReviewRequest {
+    pub tool_call_id: String,
+    pub tool_name: String,
+    pub path: String,
+}
+
+/// Async reviewer for [`ToolApprovalDecision::Review`]. Resolves to a note for the model
+/// describing which hunks were reverted, or `None` when the change was kept as is.
+pub type HunkReviewer =
+    Arc<dyn Fn(HunkReviewRequest) -> BoxFuture<'static, Option<String>> + Send + Sync>;
+
+/// Custom session entry recording a proposed plan and whether it was approved.
+pub const PLAN_ENTRY_TYPE: &str = "plan";
+
+/// Custom session entry recording a plan step starting or finishing.
+pub const PLAN_STEP_ENTRY_TYPE: &str = "plan_step";
+
+/// Instructions for the planning turn of [`AgentSession::run_planned`], which has no tools.
+const PLANNING_PROMPT: &str = "Plan how to do the task below before doing any of it. \
+Answer only with a JSON object of the form {\"steps\": [\"...\"]}: a short list of concrete \
+steps, each one sentence, in the order they should be done. Do not start the work yet.";
+
+/// A step-by-step plan proposed by the model in plan mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<String>,
+}
+
+impl Plan {
+    /// Read a plan from the model's answer: a `{"steps": [...]}` object (fenced or not), or
+    /// failing that a numbered or bulleted list.
+    pub fn parse(text: &str) -> Option<Self> {
+        if let (Some(start), Some(end)) = (text.find('{'), text.rfind('}')) {
+            if let Ok(plan) = serde_json::from_str::<Self>(&text[start..=end]) {
+                let steps = plan
+                    .steps
+                    .into_iter()
+                    .map(|step| step.trim().to_string())
+                    .filter(|step| !step.is_empty())
+                    .collect::<Vec<_>>();
+                return (!steps.is_empty()).then_some(Self { steps });
+            }
+        }
+        let steps = text
+            .lines()
+            .filter_map(|line| list_item(line.trim()))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        (!steps.is_empty()).then_some(Self { steps })
+    }
+}
+
+/// The text of a `1. step`, `1) step`, `- step` or `* step` line.
+fn list_item(line: &str) -> Option<&str> {
+    let item = if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        item
+    } else {
+        let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+        if rest.len() == line.len() {
+            return None;
+        }
+        rest.strip_prefix(". ")
+            .or_else(|| rest.strip_prefix(") "))?
+    };
+    let item = item.trim();
+    (!item.is_empty()).then_some(item)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStepStatus {
+    Started,
+    Done,
+    /// The step's run ended with an error or was aborted; later steps do not run.
+    Failed,
+}
+
+/// Async approval gate for plan mode. Resolves to the plan to carry out (possibly edited),
+/// or `None` to reject it.
+pub type PlanApprover = Arc<dyn Fn(Plan) -> BoxFuture<'static, Option<Plan>> + Send + Sync>;
+
+/// Interception points in the agent loop for library users: logging, redaction, or prompt
+/// injection defenses without forking the loop. Added middleware runs in order, each seeing
+/// the previous one's changes; every method defaults to doing nothing.
+pub trait Middleware: Send + Sync {
+    /// Inspect or rewrite the context just before it is sent to the provider.
+    fn on_context_build(&self, _context: &mut Context) {}
+
+    /// Inspect or rewrite a provider stream event before the agent applies it.
+    fn on_stream_event(&self, _event: &mut StreamEvent) {}
+
+    /// Inspect or rewrite a tool's output before it is recorded and shown to the model.
+    fn on_tool_result(&self, _tool_call: &ToolCall, _output: &mut ToolOutput, _is_error: bool) {}
+}
+
+/// Compact the history mid-run, before a provider call, once the last response's context
+/// usage leaves less than `settings.reserve_tokens` of `context_window`.
+#[derive(Debug, Clone)]
+pub struct AutoCompaction {
+    pub context_window: u32,
+    pub settings: ResolvedCompactionSettings,
+}
+
+/// Retries for provider requests that fail with 429 (rate limited), 529 (overloaded), or
+/// another 5xx before any output streamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Wait before retry `attempt` (1-based): the server's requested delay when it sent one,
+    /// otherwise `base_delay` doubled per attempt, capped at `max_delay`, with its upper half
+    /// scaled by `jitter` (0..=1) so clients that failed together spread out.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>, jitter: f64) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after;
+        }
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let backoff = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        backoff / 2 + (backoff / 2).mul_f64(jitter.clamp(0.0, 1.0))
+    }
+}
+
+/// Where a message sits in the history the run started from, or among the run's own messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessagePosition {
+    History(usize),
+    Run(usize),
+}
+
+/// An auto-compaction made during a run, to record as a session `Compaction` entry.
+#[derive(Debug, Clone)]
+pub struct RunCompaction {
+    pub summary: String,
+    pub tokens_before: u64,
+    pub details: Option<Value>,
+    /// First message kept after the summary.
+    pub first_kept: MessagePosition,
+    /// Number of run messages that existed when it happened; the entry goes after them.
+    pub after_run_messages: usize,
+}
+
+/// Bookkeeping that lets a run be recorded after auto-compaction shrank the history.
+#[derive(Debug, Default)]
+struct RunHistory {
+    /// History length when the run started; later messages belong to the run.
+    base: usize,
+    /// Added to a message's index to get its position in history-then-run order. Once the
+    /// run has compacted, index 0 is the summary and has no position.
+    offset: usize,
+    /// Run messages dropped from memory by auto-compaction, oldest first.
+    dropped: Vec<Message>,
+    compactions: Vec<RunCompaction>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum QueueMode {
     All,
@@ -193,6 +842,9 @@ pub enum AgentEvent {
         messages: Vec<Message>,
         #[serde(skip_serializing_if = "Option::is_none")]
         error: Option<String>,
+        /// Why the run stopped early, when it did.
+        #[serde(rename = "abortReason", skip_serializing_if = "Option::is_none")]
+        abort_reason: Option<AbortReason>,
     },
     /// Turn lifecycle start (assistant response + tool calls).
     TurnStart {
@@ -230,6 +882,68 @@ pub enum AgentEvent {
         tool_name: String,
         args: serde_json::Value,
     },
+    /// The permission policy answered `ask`; the tool call waits for the approver's decision.
+    PermissionRequest {
+        #[serde(rename = "toolCallId")]
+        tool_call_id: String,
+        #[serde(rename = "toolName")]
+        tool_name: String,
+        args: serde_json::Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        /// The policy rule that asked, or `None` for the default.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rule: Option<String>,
+    },
+    /// Older messages were summarized mid-run to make room in the context window.
+    AutoCompaction {
+        #[serde(rename = "tokensBefore")]
+        tokens_before: u64,
+        #[serde(rename = "messagesCompacted")]
+        messages_compacted: usize,
+    },
+    /// A provider request failed with a retryable status; it is sent again after `delay_ms`.
+    RetryScheduled {
+        attempt: u32,
+        #[serde(rename = "maxAttempts")]
+        max_attempts: u32,
+        #[serde(rename = "delayMs")]
+        delay_ms: u64,
+        #[serde(rename = "errorMessage")]
+        error_message: String,
+    },
+    /// The declared tool definitions exceed the configured size (emitted once per agent).
+    ToolDefinitionsOversized {
+        /// Estimated tokens of all declared definitions.
+        tokens: u64,
+        threshold: u64,
+        /// The largest definitions, as `name (~tokens)`.
+        #[serde(rename = "largestTools")]
+        largest_tools: Vec<String>,
+    },
+    /// The run is held by its [`PauseHandle`]; `pending_tool_calls` is empty between turns.
+    Paused {
+        #[serde(rename = "pendingToolCalls")]
+        pending_tool_calls: Vec<ToolCall>,
+    },
+    /// A paused run continues.
+    Resumed,
+    /// The same tool call was made `repeats` times in a row; this one was refused.
+    LoopDetected {
+        #[serde(rename = "toolCallId")]
+        tool_call_id: String,
+        #[serde(rename = "toolName")]
+        tool_name: String,
+        repeats: u32,
+    },
+    /// Plan mode produced a plan; it waits for the [`PlanApprover`].
+    PlanReady { plan: Plan },
+    /// A step of an approved plan started or finished.
+    PlanStep {
+        index: usize,
+        step: String,
+        status: PlanStepStatus,
+    },
     /// Tool execution update.
     ToolExecutionUpdate {
         #[serde(rename = "toolCallId")]
@@ -271,6 +985,8 @@ pub struct AbortSignal {
 #[derive(Debug)]
 struct AbortSignalInner {
     aborted: AtomicBool,
+    /// Set by the first abort.
+    reason: std::sync::OnceLock<AbortReason>,
     notify: Notify,
 }
 
@@ -280,6 +996,7 @@ impl AbortHandle {
     pub fn new() -> (Self, AbortSignal) {
         let inner = Arc::new(AbortSignalInner {
             aborted: AtomicBool::new(false),
+            reason: std::sync::OnceLock::new(),
             notify: Notify::new(),
         });
         (
@@ -290,8 +1007,14 @@ impl AbortHandle {
         )
     }
 
-    /// Trigger an abort.
+    /// Trigger an abort on the user's behalf.
     pub fn abort(&self) {
+        self.abort_with(AbortReason::User);
+    }
+
+    /// Trigger an abort for `reason`; later aborts keep the first reason.
+    pub fn abort_with(&self, reason: AbortReason) {
+        let _ = self.inner.reason.set(reason);
         if !self.inner.aborted.swap(true, Ordering::SeqCst) {
             self.inner.notify.notify_waiters();
         }
@@ -305,6 +1028,12 @@ impl AbortSignal {
         self.inner.aborted.load(Ordering::SeqCst)
     }
 
+    /// Why the abort was requested; `None` until it is.
+    #[must_use]
+    pub fn reason(&self) -> Option<AbortReason> {
+        self.inner.reason.get().copied()
+    }
+
     async fn wait(&self) {
         if self.is_aborted() {
             return;
@@ -319,84 +1048,571 @@ impl AbortSignal {
     }
 }
 
-/// The agent runtime that orchestrates LLM calls and tool execution.
-pub struct Agent {
-    /// The LLM provider.
-    provider: Arc<dyn Provider>,
-
-    /// Tool registry.
-    tools: ToolRegistry,
-
-    /// Agent configuration.
-    config: AgentConfig,
-
-    /// Optional extension manager for tool/event hooks.
-    extensions: Option<ExtensionManager>,
-
-    /// Message history.
-    messages: Vec<Message>,
-
-    /// Steering message fetcher (interrupt).
-    steering_fetcher: Option<MessageFetcher>,
-
-    /// Follow-up message fetcher (after idle).
-    follow_up_fetcher: Option<MessageFetcher>,
+/// Handle to pause an agent run before its next tool calls (or its next turn).
+///
+/// While paused the loop emits [`AgentEvent::Paused`] with the tool calls it is about to run
+/// and waits; a client can inspect them, cancel some with [`PauseHandle::cancel_tool_call`],
+/// and then [`PauseHandle::resume`]. An abort still ends a paused run.
+#[derive(Debug, Clone, Default)]
+pub struct PauseHandle {
+    inner: Arc<PauseState>,
+}
 
-    /// Internal queue for steering/follow-up messages.
-    message_queue: MessageQueue,
+#[derive(Debug)]
+struct PauseState {
+    paused: AtomicBool,
+    /// Tool call ids to skip when the loop resumes.
+    cancelled: std::sync::Mutex<HashSet<String>>,
+    notify: Notify,
 }
 
-impl Agent {
-    /// Create a new agent with the given provider and tools.
-    pub fn new(provider: Arc<dyn Provider>, tools: ToolRegistry, config: AgentConfig) -> Self {
+impl Default for PauseState {
+    fn default() -> Self {
         Self {
-            provider,
-            tools,
-            config,
-            extensions: None,
-            messages: Vec::new(),
-            steering_fetcher: None,
-            follow_up_fetcher: None,
-            message_queue: MessageQueue::new(QueueMode::OneAtATime, QueueMode::OneAtATime),
+            paused: AtomicBool::new(false),
+            cancelled: std::sync::Mutex::new(HashSet::new()),
+            notify: Notify::new(),
         }
     }
+}
 
-    /// Get the current message history.
+impl PauseHandle {
     #[must_use]
-    pub fn messages(&self) -> &[Message] {
-        &self.messages
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// Clear the message history.
-    pub fn clear_messages(&mut self) {
-        self.messages.clear();
+    /// Pause at the next pause point; takes effect even when the agent is idle.
+    pub fn pause(&self) {
+        self.inner.paused.store(true, Ordering::SeqCst);
     }
 
-    /// Add a message to the history.
-    pub fn add_message(&mut self, message: Message) {
-        self.messages.push(message);
+    /// Let a paused run continue.
+    pub fn resume(&self) {
+        if self.inner.paused.swap(false, Ordering::SeqCst) {
+            self.inner.notify.notify_waiters();
+        }
     }
 
-    /// Replace the message history.
-    pub fn replace_messages(&mut self, messages: Vec<Message>) {
-        self.messages = messages;
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::SeqCst)
     }
 
-    /// Replace the provider implementation (used for model/provider switching).
-    pub fn set_provider(&mut self, provider: Arc<dyn Provider>) {
-        self.provider = provider;
+    /// Skip the pending tool call `id`; it gets a "cancelled" error result instead of running.
+    pub fn cancel_tool_call(&self, id: impl Into<String>) {
+        if let Ok(mut cancelled) = self.inner.cancelled.lock() {
+            cancelled.insert(id.into());
+        }
     }
 
-    /// Configure async fetchers for queued steering/follow-up messages.
-    pub fn set_message_fetchers(
-        &mut self,
-        steering: Option<MessageFetcher>,
-        follow_up: Option<MessageFetcher>,
-    ) {
+    fn take_cancelled(&self, id: &str) -> bool {
+        self.inner
+            .cancelled
+            .lock()
+            .is_ok_and(|mut cancelled| cancelled.remove(id))
+    }
+
+    fn clear_cancelled(&self) {
+        if let Ok(mut cancelled) = self.inner.cancelled.lock() {
+            cancelled.clear();
+        }
+    }
+
+    async fn wait_resumed(&self) {
+        while self.is_paused() {
+            self.inner.notify.notified().await;
+        }
+    }
+
+    /// Wait until resumed, or until `abort` fires.
+    async fn wait_for_resume(&self, abort: Option<&AbortSignal>) {
+        let Some(signal) = abort else {
+            self.wait_resumed().await;
+            return;
+        };
+        let resumed = self.wait_resumed().fuse();
+        let aborted = signal.wait().fuse();
+        futures::pin_mut!(resumed, aborted);
+        futures::future::select(resumed, aborted).await;
+    }
+}
+
+/// The events of one run as a [`Stream`](futures::Stream), for consumers that prefer async
+/// iteration (or `select!`) over a callback. See [`Agent::run_stream`].
+///
+/// The run only advances while the stream is polled, so a slow consumer holds the agent back
+/// instead of queueing events without bound. The stream ends after `AgentEnd`; the run's result
+/// is then available from [`AgentEventStream::take_result`].
+pub struct AgentEventStream<'a> {
+    run: Option<BoxFuture<'a, Result<AssistantMessage>>>,
+    events: Arc<std::sync::Mutex<VecDeque<AgentEvent>>>,
+    result: Option<Result<AssistantMessage>>,
+}
+
+impl<'a> AgentEventStream<'a> {
+    /// Start a run whose events are queued for the stream; `start` receives the callback.
+    fn new<F>(start: impl FnOnce(Arc<dyn Fn(AgentEvent) + Send + Sync>) -> F) -> Self
+    where
+        F: std::future::Future<Output = Result<AssistantMessage>> + Send + 'a,
+    {
+        let events = Arc::new(std::sync::Mutex::new(VecDeque::new()));
+        let sink = Arc::clone(&events);
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(move |event| {
+            if let Ok(mut events) = sink.lock() {
+                events.push_back(event);
+            }
+        });
+        Self {
+            run: Some(Box::pin(start(on_event))),
+            events,
+            result: None,
+        }
+    }
+
+    fn pop_event(&self) -> Option<AgentEvent> {
+        self.events.lock().ok()?.pop_front()
+    }
+
+    /// The run's final message or error, once the stream has ended.
+    pub const fn take_result(&mut self) -> Option<Result<AssistantMessage>> {
+        self.result.take()
+    }
+}
+
+impl futures::Stream for AgentEventStream<'_> {
+    type Item = AgentEvent;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<AgentEvent>> {
+        use std::task::Poll;
+        loop {
+            if let Some(event) = self.pop_event() {
+                return Poll::Ready(Some(event));
+            }
+            let Some(run) = self.run.as_mut() else {
+                return Poll::Ready(None);
+            };
+            match run.poll_unpin(cx) {
+                Poll::Ready(result) => {
+                    self.run = None;
+                    self.result = Some(result);
+                }
+                // Events emitted before the run blocked are delivered now; the run's waker
+                // brings us back for the rest.
+                Poll::Pending => {
+                    return self
+                        .pop_event()
+                        .map_or(Poll::Pending, |event| Poll::Ready(Some(event)));
+                }
+            }
+        }
+    }
+}
+
+/// The agent runtime that orchestrates LLM calls and tool execution.
+pub struct Agent {
+    /// The LLM provider.
+    provider: Arc<dyn Provider>,
+
+    /// Tool registry.
+    tools: ToolRegistry,
+
+    /// Agent configuration.
+    config: AgentConfig,
+
+    /// Optional extension manager for tool/event hooks.
+    extensions: Option<ExtensionManager>,
+
+    /// Message history.
+    messages: Vec<Message>,
+
+    /// Steering message fetcher (interrupt).
+    steering_fetcher: Option<MessageFetcher>,
+
+    /// Follow-up message fetcher (after idle).
+    follow_up_fetcher: Option<MessageFetcher>,
+
+    /// Rules deciding which tool calls run, are refused, or go to the approver.
+    permission_policy: PermissionPolicy,
+
+    /// Approver for tool calls the policy asks about; `None` runs them unprompted.
+    tool_approver: Option<ToolApprover>,
+
+    /// Reviewer for file changes approved with [`ToolApprovalDecision::Review`].
+    hunk_reviewer: Option<HunkReviewer>,
+
+    /// Internal queue for steering/follow-up messages.
+    message_queue: MessageQueue,
+
+    /// Consecutive failures per tool name, for `tool_retry_diagnostics`.
+    tool_failure_streaks: HashMap<String, u32>,
+
+    /// The latest tool call's name and arguments, and how many times in a row it was made.
+    repeated_tool_call: Option<(String, Value, u32)>,
+
+    /// Files whose current contents are added to every request (`/pin`).
+    pinned_files: Vec<PathBuf>,
+
+    /// Token budget shared by all pinned files.
+    pinned_budget_tokens: usize,
+
+    /// Priority of this agent's requests in the global request scheduler.
+    request_priority: RequestPriority,
+
+    /// Mid-run compaction when the context nears the model limit; `None` never compacts.
+    auto_compaction: Option<AutoCompaction>,
+
+    /// Backoff for retryable provider failures; `None` fails on the first error.
+    retry_policy: Option<RetryPolicy>,
+
+    /// Tools the provider runs itself, declared after the local tools.
+    hosted_tools: Vec<HostedTool>,
+
+    /// Which tools each request declares and when their size warrants a warning.
+    tool_exposure: ToolExposure,
+
+    /// Whether the oversized tool definitions warning was already emitted.
+    tool_defs_warned: bool,
+
+    /// Which part of the history each request sends.
+    context_strategy: ContextStrategy,
+
+    /// Shell hooks run at lifecycle points.
+    hooks: Hooks,
+
+    /// Context from `post_turn` hooks, added to the next request's system prompt.
+    hook_context: Vec<String>,
+
+    /// Interceptors run in the order they were added.
+    middleware: Vec<Arc<dyn Middleware>>,
+
+    /// Messages and auto-compactions of the current (or last) run.
+    run_history: RunHistory,
+
+    /// Pauses the loop before tool calls and between turns.
+    pause: PauseHandle,
+
+    /// Model that titles unnamed sessions after their first reply; `None` leaves them unnamed.
+    auto_title: Option<AutoTitle>,
+
+    /// Session ID whose title was last requested, so each session is asked for once.
+    titled_session: Option<String>,
+}
+
+/// The model for auto titles; the agent's own provider and key when `provider` is `None`.
+struct AutoTitle {
+    provider: Option<Arc<dyn Provider>>,
+    api_key: Option<String>,
+}
+
+/// A title to generate for a session that just got its first reply; see
+/// [`Agent::take_title_request`].
+pub struct TitleRequest {
+    provider: Arc<dyn Provider>,
+    api_key: Option<String>,
+    messages: Vec<SessionMessage>,
+}
+
+impl TitleRequest {
+    /// Ask the model for the title and set it as the session name, unless the session was
+    /// named in the meantime. Returns the title that was set.
+    pub async fn apply(self, session: &Mutex<Session>, save: bool) -> Result<Option<String>> {
+        let title = compaction::generate_session_title(
+            &self.messages,
+            self.provider,
+            self.api_key.as_deref().unwrap_or_default(),
+        )
+        .await?;
+        let cx = crate::agent_cx::AgentCx::for_request();
+        let mut session = session
+            .lock(cx.cx())
+            .await
+            .map_err(|e| Error::session(e.to_string()))?;
+        if session.get_name().is_some() {
+            return Ok(None);
+        }
+        session.set_name(&title);
+        if save {
+            session.save().await?;
+        }
+        Ok(Some(title))
+    }
+}
+
+impl Agent {
+    /// Create a new agent with the given provider and tools.
+    pub fn new(provider: Arc<dyn Provider>, tools: ToolRegistry, config: AgentConfig) -> Self {
+        Self {
+            provider,
+            tools,
+            config,
+            extensions: None,
+            messages: Vec::new(),
+            steering_fetcher: None,
+            follow_up_fetcher: None,
+            permission_policy: PermissionPolicy::default(),
+            tool_approver: None,
+            hunk_reviewer: None,
+            message_queue: MessageQueue::new(QueueMode::OneAtATime, QueueMode::OneAtATime),
+            tool_failure_streaks: HashMap::new(),
+            repeated_tool_call: None,
+            pinned_files: Vec::new(),
+            pinned_budget_tokens: DEFAULT_PINNED_BUDGET_TOKENS,
+            request_priority: RequestPriority::Interactive,
+            auto_compaction: None,
+            retry_policy: None,
+            hosted_tools: Vec::new(),
+            tool_exposure: ToolExposure::default(),
+            tool_defs_warned: false,
+            context_strategy: ContextStrategy::Full,
+            hooks: Hooks::default(),
+            hook_context: Vec::new(),
+            middleware: Vec::new(),
+            run_history: RunHistory::default(),
+            pause: PauseHandle::new(),
+            auto_title: None,
+            titled_session: None,
+        }
+    }
+
+    /// Handle to pause this agent's runs and cancel pending tool calls.
+    pub fn pause_handle(&self) -> PauseHandle {
+        self.pause.clone()
+    }
+
+    /// Retry provider requests that fail with a rate limit, overload, or server error.
+    pub fn set_retry_policy(&mut self, retry_policy: Option<RetryPolicy>) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Declare provider-hosted tools (code execution, web search) on every request.
+    pub fn set_hosted_tools(&mut self, hosted_tools: Vec<HostedTool>) {
+        self.hosted_tools = hosted_tools;
+    }
+
+    /// Choose which tools requests declare; resets the oversized-definitions warning.
+    pub fn set_tool_exposure(&mut self, tool_exposure: ToolExposure) {
+        self.tool_exposure = tool_exposure;
+        self.tool_defs_warned = false;
+    }
+
+    /// Set which part of the history each request sends.
+    pub const fn set_context_strategy(&mut self, strategy: ContextStrategy) {
+        self.context_strategy = strategy;
+    }
+
+    pub fn set_hooks(&mut self, hooks: Hooks) {
+        self.hooks = hooks;
+    }
+
+    /// Title unnamed sessions after their first reply, using `model` (a provider and its API
+    /// key) or, when `None`, the agent's current model.
+    pub fn enable_auto_title(&mut self, model: Option<(Arc<dyn Provider>, Option<String>)>) {
+        let (provider, api_key) = model.unzip();
+        self.auto_title = Some(AutoTitle {
+            provider,
+            api_key: api_key.flatten(),
+        });
+    }
+
+    /// The title to generate for `session` once its first exchange is complete: only when
+    /// auto titles are enabled, the session has no name, and it was not asked for before.
+    pub fn take_title_request(&mut self, session: &Session) -> Option<TitleRequest> {
+        let auto_title = self.auto_title.as_ref()?;
+        if session.get_name().is_some()
+            || self.titled_session.as_deref() == Some(session.header.id.as_str())
+        {
+            return None;
+        }
+        let mut messages = Vec::new();
+        let mut answered = false;
+        for entry in session.entries_for_current_path() {
+            let crate::session::SessionEntry::Message(entry) = entry else {
+                continue;
+            };
+            // The title is saved, so incognito messages stay out of it.
+            if entry
+                .base
+                .id
+                .as_deref()
+                .is_some_and(|id| session.is_ephemeral(id))
+            {
+                continue;
+            }
+            messages.push(entry.message.clone());
+            if let SessionMessage::Assistant { message } = &entry.message {
+                if !blocks_text(&message.content).trim().is_empty() {
+                    answered = true;
+                    break;
+                }
+            }
+        }
+        if !answered {
+            return None;
+        }
+
+        let (provider, api_key) = match &auto_title.provider {
+            Some(provider) => (Arc::clone(provider), auto_title.api_key.clone()),
+            None => (self.provider(), self.config.stream_options.api_key.clone()),
+        };
+        self.titled_session = Some(session.header.id.clone());
+        Some(TitleRequest {
+            provider,
+            api_key,
+            messages,
+        })
+    }
+
+    /// Stack `middleware` after any added earlier.
+    pub fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) {
+        self.middleware.push(middleware);
+    }
+
+    /// Compact the history mid-run when the context nears the model limit.
+    pub fn set_auto_compaction(&mut self, auto_compaction: Option<AutoCompaction>) {
+        self.auto_compaction = auto_compaction;
+    }
+
+    /// Follow a model switch: auto-compaction measures against the new model's window.
+    pub fn set_context_window(&mut self, context_window: u32) {
+        if let Some(auto) = self.auto_compaction.as_mut() {
+            auto.context_window = context_window;
+        }
+    }
+
+    /// Follow a model switch: usage is priced at the new model's rates.
+    pub fn set_pricing(&mut self, pricing: Option<ModelCost>) {
+        self.config.pricing = pricing;
+    }
+
+    /// Set the priority of this agent's requests when a request scheduler is installed.
+    pub fn set_request_priority(&mut self, priority: RequestPriority) {
+        self.request_priority = priority;
+    }
+
+    /// Pin a file so its contents are re-read into the system prompt on every request.
+    ///
+    /// Returns `false` if the file was already pinned.
+    pub fn pin_file(&mut self, path: PathBuf) -> bool {
+        if self.pinned_files.contains(&path) {
+            return false;
+        }
+        self.pinned_files.push(path);
+        true
+    }
+
+    /// Unpin a file. Returns `false` if it was not pinned.
+    pub fn unpin_file(&mut self, path: &Path) -> bool {
+        let before = self.pinned_files.len();
+        self.pinned_files.retain(|pinned| pinned != path);
+        self.pinned_files.len() != before
+    }
+
+    /// Unpin every file.
+    pub fn clear_pinned_files(&mut self) {
+        self.pinned_files.clear();
+    }
+
+    /// Currently pinned files, in pin order.
+    #[must_use]
+    pub fn pinned_files(&self) -> &[PathBuf] {
+        &self.pinned_files
+    }
+
+    /// Set the token budget shared by all pinned files.
+    pub fn set_pinned_budget_tokens(&mut self, tokens: usize) {
+        self.pinned_budget_tokens = tokens;
+    }
+
+    /// System prompt section holding the current contents of pinned files.
+    ///
+    /// Files are read fresh on every call so edits made by tools or the user are picked up.
+    /// Earlier pins get first claim on the budget; content beyond it is not read at all.
+    fn pinned_files_section(&self) -> Option<String> {
+        if self.pinned_files.is_empty() {
+            return None;
+        }
+        let mut remaining_chars = self.pinned_budget_tokens.saturating_mul(4);
+        let mut section = String::from(
+            "# Pinned files\n\nThe user pinned these files. Their contents below are current as of this request.\n",
+        );
+        for path in &self.pinned_files {
+            let body = match read_text_prefix(path, remaining_chars) {
+                Ok((content, false)) => {
+                    remaining_chars -= content.len();
+                    content
+                }
+                Ok((content, true)) => {
+                    remaining_chars = 0;
+                    format!("{content}\n[... truncated to fit the pinned-file budget ...]")
+                }
+                Err(err) => format!("[unreadable: {err}]"),
+            };
+            let _ = write!(
+                section,
+                "\n## {}\n\n```\n{}\n```\n",
+                path.display(),
+                body.trim_end()
+            );
+        }
+        Some(section)
+    }
+
+    /// Get the current message history.
+    #[must_use]
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Clear the message history.
+    pub fn clear_messages(&mut self) {
+        self.messages.clear();
+    }
+
+    /// Add a message to the history.
+    pub fn add_message(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    /// Replace the message history.
+    pub fn replace_messages(&mut self, messages: Vec<Message>) {
+        self.messages = messages;
+    }
+
+    /// Replace the provider implementation (used for model/provider switching).
+    pub fn set_provider(&mut self, provider: Arc<dyn Provider>) {
+        self.provider = provider;
+    }
+
+    /// Configure async fetchers for queued steering/follow-up messages.
+    pub fn set_message_fetchers(
+        &mut self,
+        steering: Option<MessageFetcher>,
+        follow_up: Option<MessageFetcher>,
+    ) {
         self.steering_fetcher = steering;
         self.follow_up_fetcher = follow_up;
     }
 
+    /// Replace the rules consulted before each tool call.
+    pub fn set_permission_policy(&mut self, policy: PermissionPolicy) {
+        self.permission_policy = policy;
+    }
+
+    /// Ask before tool calls the permission policy marks `ask` (by default `bash`, `write`,
+    /// and `edit`).
+    pub fn set_tool_approver(&mut self, approver: Option<ToolApprover>) {
+        self.tool_approver = approver;
+    }
+
+    /// Review `write`/`edit` changes hunk by hunk when the approver answers `Review`.
+    pub fn set_hunk_reviewer(&mut self, reviewer: Option<HunkReviewer>) {
+        self.hunk_reviewer = reviewer;
+    }
+
     /// Extend the tool registry with additional tools (e.g. extension-registered tools).
     pub fn extend_tools<I>(&mut self, tools: I)
     where
@@ -405,6 +1621,20 @@ impl Agent {
         self.tools.extend(tools);
     }
 
+    /// Registered tool names with whether each is currently enabled, in registration order.
+    pub fn tool_states(&self) -> Vec<(String, bool)> {
+        self.tools
+            .tools()
+            .iter()
+            .map(|t| (t.name().to_string(), self.tools.is_enabled(t.name())))
+            .collect()
+    }
+
+    /// Enable or disable a tool for subsequent turns. Returns `false` if the tool is unknown.
+    pub fn set_tool_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.tools.set_enabled(name, enabled)
+    }
+
     /// Queue a steering message (delivered after tool completion).
     pub fn queue_steering(&mut self, message: Message) -> u64 {
         self.message_queue.push_steering(message)
@@ -434,38 +1664,211 @@ impl Agent {
         &self.config.stream_options
     }
 
+    /// The system prompt sent with each request (without pinned files).
+    pub fn system_prompt(&self) -> Option<&str> {
+        self.config.system_prompt.as_deref()
+    }
+
+    /// Replace the system prompt used for subsequent requests.
+    pub fn set_system_prompt(&mut self, prompt: Option<String>) {
+        self.config.system_prompt = prompt;
+    }
+
     pub const fn stream_options_mut(&mut self) -> &mut StreamOptions {
         &mut self.config.stream_options
     }
 
     /// Build tool definitions for the API.
     fn build_tool_defs(&self) -> Vec<ToolDef> {
+        let mentions = self
+            .tool_exposure
+            .core_tools
+            .as_ref()
+            .map(|_| self.tool_mentions_text());
         self.tools
-            .tools()
-            .iter()
+            .enabled_tools()
+            .filter(|t| self.is_tool_exposed(t.name(), mentions.as_deref()))
             .map(|t| ToolDef {
                 name: t.name().to_string(),
                 description: t.description().to_string(),
                 parameters: t.parameters(),
+                hosted: None,
             })
+            .chain(self.hosted_tools.iter().copied().map(ToolDef::hosted))
             .collect()
     }
 
-    /// Build context for a completion request.
-    fn build_context(&self) -> Context {
-        Context {
-            system_prompt: self.config.system_prompt.clone(),
-            messages: self.messages.clone(),
-            tools: self.build_tool_defs(),
+    /// Whether a request declares `name`: always, unless lazy exposure leaves it out.
+    fn is_tool_exposed(&self, name: &str, mentions: Option<&str>) -> bool {
+        let (Some(core), Some(mentions)) = (&self.tool_exposure.core_tools, mentions) else {
+            return true;
+        };
+        core.iter().any(|core_name| core_name == name) || mentions_word(mentions, name)
+    }
+
+    /// Conversation text that can name a tool: user and assistant text plus called tool names.
+    fn tool_mentions_text(&self) -> String {
+        let mut text = String::new();
+        for message in &self.messages {
+            let blocks = match message {
+                Message::User(user) => match &user.content {
+                    UserContent::Text(content) => {
+                        text.push_str(content);
+                        text.push('\n');
+                        continue;
+                    }
+                    UserContent::Blocks(blocks) => blocks,
+                },
+                Message::Assistant(assistant) => &assistant.content,
+                Message::ToolResult(_) | Message::Custom(_) => continue,
+            };
+            for block in blocks {
+                match block {
+                    ContentBlock::Text(content) => text.push_str(&content.text),
+                    ContentBlock::ToolCall(call) => text.push_str(&call.name),
+                    _ => continue,
+                }
+                text.push('\n');
+            }
         }
+        text
     }
 
-    /// Run the agent with a user message.
-    ///
-    /// Returns a stream of events and the final assistant message.
-    pub async fn run(
+    /// System prompt note naming the enabled tools lazy exposure left out.
+    fn deferred_tools_section(&self, declared: &[ToolDef]) -> Option<String> {
+        self.tool_exposure.core_tools.as_ref()?;
+        let deferred = self
+            .tools
+            .enabled_tools()
+            .map(|tool| tool.name())
+            .filter(|name| !declared.iter().any(|tool| tool.name == *name))
+            .collect::<Vec<_>>();
+        if deferred.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "# More tools\n\nThese tools are available but not declared, to keep requests small: {}. Ask for one by name and it will be declared on the next request.",
+            deferred.join(", ")
+        ))
+    }
+
+    /// Emit a one-time warning when the declared tool definitions exceed the threshold.
+    fn check_tool_defs_size(
         &mut self,
-        user_input: impl Into<String>,
+        tools: &[ToolDef],
+        on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
+    ) {
+        let mut costs = tools
+            .iter()
+            .map(|tool| (tool_def_tokens(tool), tool.name.as_str()))
+            .collect::<Vec<_>>();
+        let tokens = costs.iter().map(|(cost, _)| cost).sum::<u64>();
+        tracing::debug!(tools = tools.len(), tokens, "tool definitions in request");
+        let Some(threshold) = self.tool_exposure.warn_tokens else {
+            return;
+        };
+        if self.tool_defs_warned || tokens <= threshold {
+            return;
+        }
+        self.tool_defs_warned = true;
+        costs.sort_by(|a, b| b.0.cmp(&a.0));
+        on_event(AgentEvent::ToolDefinitionsOversized {
+            tokens,
+            threshold,
+            largest_tools: costs
+                .iter()
+                .take(3)
+                .map(|(cost, name)| format!("{name} (~{cost})"))
+                .collect(),
+        });
+    }
+
+    /// Add `post_turn` and `pre_request` hook context to the request's system prompt.
+    async fn apply_pre_request_hooks(&mut self, context: &mut Context) {
+        let mut sections = std::mem::take(&mut self.hook_context);
+        if self.hooks.has(HookEvent::PreRequest) {
+            let payload = json!({
+                "model": self.provider.model_id(),
+                "messageCount": context.messages.len(),
+                "tools": context.tools.iter().map(|tool| tool.name.as_str()).collect::<Vec<_>>(),
+            });
+            sections.extend(
+                self.hooks
+                    .run(HookEvent::PreRequest, None, payload)
+                    .await
+                    .context,
+            );
+        }
+        if sections.is_empty() {
+            return;
+        }
+        let hook_text = sections.join("\n\n");
+        context.system_prompt = Some(match context.system_prompt.take() {
+            Some(prompt) => format!("{prompt}\n\n{hook_text}"),
+            None => hook_text,
+        });
+    }
+
+    /// Run `post_turn` hooks for a finished turn, keeping their context for the next request.
+    async fn run_post_turn_hooks(
+        &mut self,
+        turn_index: usize,
+        message: &AssistantMessage,
+        tool_results: &[ToolResultMessage],
+    ) {
+        if !self.hooks.has(HookEvent::PostTurn) {
+            return;
+        }
+        let payload = json!({
+            "turnIndex": turn_index,
+            "text": blocks_text(&message.content),
+            "toolCalls": tool_results.iter().map(|result| result.tool_name.as_str()).collect::<Vec<_>>(),
+        });
+        let outcome = self.hooks.run(HookEvent::PostTurn, None, payload).await;
+        self.hook_context.extend(outcome.context);
+    }
+
+    /// Stream options for the next request, with `request_injection` templates rendered.
+    fn request_stream_options(&self) -> Cow<'_, StreamOptions> {
+        self.config.request_injection.as_ref().map_or(
+            Cow::Borrowed(&self.config.stream_options),
+            |injection| {
+                Cow::Owned(injection.apply(
+                    &self.config.stream_options,
+                    self.provider.name(),
+                    self.provider.model_id(),
+                ))
+            },
+        )
+    }
+
+    /// Build context for a completion request.
+    fn build_context(&self) -> Context {
+        let tools = self.build_tool_defs();
+        let system_prompt = [
+            self.config
+                .system_prompt
+                .as_deref()
+                .map(expand_system_prompt),
+            self.pinned_files_section(),
+            self.deferred_tools_section(&tools),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(|prompt, section| format!("{prompt}\n\n{section}"));
+        Context {
+            system_prompt,
+            messages: self.context_strategy.apply(&self.messages),
+            tools,
+        }
+    }
+
+    /// Run the agent with a user message.
+    ///
+    /// Returns a stream of events and the final assistant message.
+    pub async fn run(
+        &mut self,
+        user_input: impl Into<String>,
         on_event: impl Fn(AgentEvent) + Send + Sync + 'static,
     ) -> Result<AssistantMessage> {
         self.run_with_abort(user_input, None, on_event).await
@@ -526,7 +1929,26 @@ impl Agent {
         self.run_loop(Vec::new(), Arc::new(on_event), abort).await
     }
 
-    fn build_abort_message(&self, partial: Option<AssistantMessage>) -> AssistantMessage {
+    /// Like [`Self::run_with_abort`], with the events as a stream instead of a callback.
+    pub fn run_stream(
+        &mut self,
+        user_input: impl Into<String>,
+        abort: Option<AbortSignal>,
+    ) -> AgentEventStream<'_> {
+        let user_input = user_input.into();
+        AgentEventStream::new(move |on_event| {
+            self.run_with_abort(user_input, abort, move |event| on_event(event))
+        })
+    }
+
+    fn build_abort_message(
+        &self,
+        partial: Option<AssistantMessage>,
+        abort: Option<&AbortSignal>,
+    ) -> AssistantMessage {
+        let reason = abort
+            .and_then(AbortSignal::reason)
+            .unwrap_or(AbortReason::User);
         let mut message = partial.unwrap_or_else(|| AssistantMessage {
             content: Vec::new(),
             api: self.provider.api().to_string(),
@@ -534,18 +1956,78 @@ impl Agent {
             model: self.provider.model_id().to_string(),
             usage: Usage::default(),
             stop_reason: StopReason::Aborted,
-            error_message: Some("Aborted".to_string()),
+            abort_reason: None,
+            error_message: None,
             timestamp: Utc::now().timestamp_millis(),
         });
         message.stop_reason = StopReason::Aborted;
-        message.error_message = Some("Aborted".to_string());
+        message.abort_reason = Some(reason);
+        message.error_message = Some(reason.abort_message().to_string());
         message.timestamp = Utc::now().timestamp_millis();
         message
     }
 
+    /// Hold the run while the pause handle is set, announcing the tool calls about to run.
+    async fn wait_if_paused(
+        &self,
+        pending_tool_calls: &[ToolCall],
+        on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
+        abort: Option<&AbortSignal>,
+    ) {
+        if !self.pause.is_paused() {
+            return;
+        }
+        on_event(AgentEvent::Paused {
+            pending_tool_calls: pending_tool_calls.to_vec(),
+        });
+        self.pause.wait_for_resume(abort).await;
+        on_event(AgentEvent::Resumed);
+    }
+
+    /// [`Self::run_loop_inner`] in an `agent.run` span carrying the run's token totals.
+    async fn run_loop(
+        &mut self,
+        prompts: Vec<Message>,
+        on_event: Arc<dyn Fn(AgentEvent) + Send + Sync>,
+        abort: Option<AbortSignal>,
+    ) -> Result<AssistantMessage> {
+        let span = tracing::info_span!(
+            "agent.run",
+            provider = %self.provider.name(),
+            model = %self.provider.model_id(),
+            turns = tracing::field::Empty,
+            input_tokens = tracing::field::Empty,
+            output_tokens = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let result = self
+            .run_loop_inner(prompts, on_event, abort)
+            .instrument(span.clone())
+            .await;
+        let run_messages = self
+            .messages
+            .get(self.run_history.base..)
+            .unwrap_or_default();
+        let (turns, input_tokens, output_tokens) = run_messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::Assistant(assistant) => Some(&assistant.usage),
+                _ => None,
+            })
+            .fold((0_u64, 0_u64, 0_u64), |(turns, input, output), usage| {
+                (turns + 1, input + usage.input, output + usage.output)
+            });
+        span.record("turns", turns);
+        span.record("input_tokens", input_tokens);
+        span.record("output_tokens", output_tokens);
+        span.record("duration_ms", elapsed_ms(started));
+        result
+    }
+
     /// The main agent loop.
     #[allow(clippy::too_many_lines)]
-    async fn run_loop(
+    async fn run_loop_inner(
         &mut self,
         prompts: Vec<Message>,
         on_event: Arc<dyn Fn(AgentEvent) + Send + Sync>,
@@ -561,6 +2043,12 @@ impl Agent {
         let mut turn_index: usize = 0;
         let mut new_messages: Vec<Message> = Vec::new();
         let mut last_assistant: Option<AssistantMessage> = None;
+        self.pause.clear_cancelled();
+        self.repeated_tool_call = None;
+        self.run_history = RunHistory {
+            base: self.messages.len(),
+            ..RunHistory::default()
+        };
 
         let agent_start_event = AgentEvent::AgentStart {
             session_id: session_id.clone(),
@@ -595,9 +2083,11 @@ impl Agent {
                 on_event(turn_start_event.clone());
                 self.dispatch_extension_lifecycle_event(&turn_start_event)
                     .await;
+                self.wait_if_paused(&[], &on_event, abort.as_ref()).await;
 
                 if abort.as_ref().is_some_and(AbortSignal::is_aborted) {
-                    let abort_message = self.build_abort_message(last_assistant.clone());
+                    let abort_message =
+                        self.build_abort_message(last_assistant.clone(), abort.as_ref());
                     let message = Message::Assistant(abort_message.clone());
                     if !matches!(self.messages.last(), Some(Message::Assistant(_))) {
                         self.messages.push(message.clone());
@@ -627,6 +2117,7 @@ impl Agent {
                                 .clone()
                                 .unwrap_or_else(|| "Aborted".to_string()),
                         ),
+                        abort_reason: abort_message.abort_reason,
                     };
                     on_event(agent_end_event.clone());
                     self.dispatch_extension_lifecycle_event(&agent_end_event)
@@ -643,6 +2134,7 @@ impl Agent {
                     on_event(AgentEvent::MessageEnd { message });
                 }
 
+                self.auto_compact_if_needed(&on_event).await;
                 let assistant_message = self
                     .stream_assistant_response(&on_event, abort.clone())
                     .await?;
@@ -668,6 +2160,7 @@ impl Agent {
                         session_id: session_id.clone(),
                         messages: new_messages.clone(),
                         error: assistant_message.error_message.clone(),
+                        abort_reason: assistant_message.abort_reason,
                     };
                     on_event(agent_end_event.clone());
                     self.dispatch_extension_lifecycle_event(&agent_end_event)
@@ -688,7 +2181,12 @@ impl Agent {
                         );
                         let mut stop_message = assistant_message.clone();
                         stop_message.stop_reason = StopReason::Error;
+                        stop_message.abort_reason = Some(AbortReason::BudgetExceeded);
                         stop_message.error_message = Some(error_message.clone());
+                        // The saved message keeps its tool calls but records why they never ran.
+                        if let Some(Message::Assistant(last)) = self.messages.last_mut() {
+                            last.abort_reason = Some(AbortReason::BudgetExceeded);
+                        }
 
                         let turn_end_event = AgentEvent::TurnEnd {
                             session_id: session_id.clone(),
@@ -704,6 +2202,7 @@ impl Agent {
                             session_id: session_id.clone(),
                             messages: new_messages.clone(),
                             error: Some(error_message),
+                            abort_reason: Some(AbortReason::BudgetExceeded),
                         };
                         on_event(agent_end_event.clone());
                         self.dispatch_extension_lifecycle_event(&agent_end_event)
@@ -712,6 +2211,8 @@ impl Agent {
                         return Ok(stop_message);
                     }
 
+                    self.wait_if_paused(&tool_calls, &on_event, abort.as_ref())
+                        .await;
                     let outcome = self
                         .execute_tool_calls(
                             &tool_calls,
@@ -739,6 +2240,29 @@ impl Agent {
                 on_event(turn_end_event.clone());
                 self.dispatch_extension_lifecycle_event(&turn_end_event)
                     .await;
+                self.run_post_turn_hooks(current_turn_index, &assistant_message, &tool_results)
+                    .await;
+
+                if let Some(tool_name) = self.exhausted_tool() {
+                    self.tool_failure_streaks.remove(&tool_name);
+                    let error_message =
+                        format!("`{tool_name}` kept failing after its retries were used up");
+                    let mut stop_message = assistant_message.clone();
+                    stop_message.stop_reason = StopReason::Error;
+                    stop_message.abort_reason = Some(AbortReason::ToolFailure);
+                    stop_message.error_message = Some(error_message.clone());
+
+                    let agent_end_event = AgentEvent::AgentEnd {
+                        session_id: session_id.clone(),
+                        messages: new_messages.clone(),
+                        error: Some(error_message),
+                        abort_reason: Some(AbortReason::ToolFailure),
+                    };
+                    on_event(agent_end_event.clone());
+                    self.dispatch_extension_lifecycle_event(&agent_end_event)
+                        .await;
+                    return Ok(stop_message);
+                }
 
                 turn_index = turn_index.saturating_add(1);
 
@@ -766,6 +2290,7 @@ impl Agent {
             session_id: session_id.clone(),
             messages: new_messages.clone(),
             error: None,
+            abort_reason: None,
         };
         on_event(agent_end_event.clone());
         self.dispatch_extension_lifecycle_event(&agent_end_event)
@@ -773,6 +2298,182 @@ impl Agent {
         Ok(final_message)
     }
 
+    /// Wait before retry `attempt` of a failed provider request, with the retry limit; `None`
+    /// when `err` is not retryable or the retries are used up.
+    fn retry_delay(&self, err: &Error, attempt: u32) -> Option<(Duration, u32)> {
+        let policy = self.retry_policy?;
+        if attempt > policy.max_retries {
+            return None;
+        }
+        let (_status, retry_after) = retryable_failure(err)?;
+        Some((
+            policy.delay(attempt, retry_after, jitter_fraction()),
+            policy.max_retries,
+        ))
+    }
+
+    /// Context tokens reported by the latest successful response.
+    fn last_context_tokens(&self) -> u64 {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::Assistant(assistant)
+                    if !matches!(
+                        assistant.stop_reason,
+                        StopReason::Error | StopReason::Aborted
+                    ) =>
+                {
+                    Some(&assistant.usage)
+                }
+                _ => None,
+            })
+            .map_or(0, |usage| {
+                usage.input + usage.cache_read + usage.cache_write + usage.output
+            })
+    }
+
+    /// Summarize the oldest messages and keep only the summary plus recent context when the
+    /// last response left too little room for the next one. Failures leave history untouched.
+    async fn auto_compact_if_needed(&mut self, on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>) {
+        let Some(auto) = self
+            .auto_compaction
+            .clone()
+            .filter(|auto| auto.context_window > 0)
+        else {
+            return;
+        };
+        if !compaction::should_compact(
+            self.last_context_tokens(),
+            auto.context_window,
+            &auto.settings,
+        ) {
+            return;
+        }
+        // Without an explicit key the provider resolves its own credentials, as it does for
+        // regular requests.
+        let api_key = self
+            .config
+            .stream_options
+            .api_key
+            .clone()
+            .unwrap_or_default();
+        let result = match compaction::compact_messages(
+            &self.messages,
+            Arc::clone(&self.provider),
+            &api_key,
+            auto.settings,
+        )
+        .await
+        {
+            Ok(Some(result)) => result,
+            Ok(None) => return,
+            Err(err) => {
+                tracing::warn!("auto-compaction failed; continuing with full history: {err}");
+                return;
+            }
+        };
+        let Some(summary_message) = session_message_to_model(&SessionMessage::CompactionSummary {
+            summary: result.summary.clone(),
+            tokens_before: result.tokens_before,
+        }) else {
+            return;
+        };
+
+        let history = &mut self.run_history;
+        let cut = result.first_kept_index;
+        let first_kept_position = cut + history.offset;
+        let first_kept = if first_kept_position < history.base {
+            MessagePosition::History(first_kept_position)
+        } else {
+            MessagePosition::Run(first_kept_position - history.base)
+        };
+        let after_run_messages =
+            (self.messages.len() + history.offset).saturating_sub(history.base);
+        // An earlier summary from this run is not a run message.
+        let skip = usize::from(!history.compactions.is_empty());
+        for (index, message) in self.messages.drain(..cut).enumerate().skip(skip) {
+            if index + history.offset >= history.base {
+                history.dropped.push(message);
+            }
+        }
+        self.messages.insert(0, summary_message);
+        history.offset += cut - 1;
+        history.compactions.push(RunCompaction {
+            summary: result.summary,
+            tokens_before: result.tokens_before,
+            details: compaction::compaction_details_to_value(&result.details).ok(),
+            first_kept,
+            after_run_messages,
+        });
+
+        on_event(AgentEvent::AutoCompaction {
+            tokens_before: result.tokens_before,
+            messages_compacted: cut,
+        });
+    }
+
+    /// Append the last run to `session`: its messages, including any auto-compaction dropped
+    /// from memory, and a `Compaction` entry after the messages each compaction saw.
+    pub fn record_run(&mut self, session: &mut Session) {
+        let history = std::mem::replace(
+            &mut self.run_history,
+            RunHistory {
+                base: self.messages.len(),
+                ..RunHistory::default()
+            },
+        );
+        let first_current = if history.compactions.is_empty() {
+            history.base
+        } else {
+            history.base.saturating_sub(history.offset).max(1)
+        };
+        let history_ids = if history.compactions.is_empty() {
+            Vec::new()
+        } else {
+            session
+                .messages_with_entry_ids_for_current_path()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect()
+        };
+
+        let mut messages = history.dropped.into_iter().chain(
+            self.messages
+                .get(first_current..)
+                .unwrap_or_default()
+                .iter()
+                .cloned(),
+        );
+        let mut compactions = history.compactions.into_iter().peekable();
+        let mut run_ids: Vec<String> = Vec::new();
+        loop {
+            while let Some(compaction) =
+                compactions.next_if(|compaction| compaction.after_run_messages <= run_ids.len())
+            {
+                let first_kept_id = match compaction.first_kept {
+                    MessagePosition::History(index) => history_ids.get(index).cloned().flatten(),
+                    MessagePosition::Run(index) => run_ids.get(index).cloned(),
+                };
+                let Some(first_kept_id) = first_kept_id else {
+                    tracing::warn!("auto-compaction cut point has no session entry; not recorded");
+                    continue;
+                };
+                session.append_compaction(
+                    compaction.summary,
+                    first_kept_id,
+                    compaction.tokens_before,
+                    compaction.details,
+                    None,
+                );
+            }
+            let Some(message) = messages.next() else {
+                break;
+            };
+            run_ids.push(session.append_model_message(message));
+        }
+    }
+
     async fn fetch_messages(&self, fetcher: Option<&MessageFetcher>) -> Vec<Message> {
         if let Some(fetcher) = fetcher {
             (fetcher)().await
@@ -819,19 +2520,87 @@ impl Agent {
         messages
     }
 
-    /// Stream an assistant response and emit message events.
-    #[allow(clippy::too_many_lines)]
+    /// Stream an assistant response and emit message events, in an `agent.request` span
+    /// carrying the response's usage and stop reason.
     async fn stream_assistant_response(
         &mut self,
         on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
         abort: Option<AbortSignal>,
     ) -> Result<AssistantMessage> {
-        // Build context and stream completion
-        let context = self.build_context();
-        let mut stream = self
-            .provider
-            .stream(&context, &self.config.stream_options)
-            .await?;
+        let span = tracing::info_span!(
+            "agent.request",
+            provider = %self.provider.name(),
+            model = %self.provider.model_id(),
+            input_tokens = tracing::field::Empty,
+            output_tokens = tracing::field::Empty,
+            cache_read_tokens = tracing::field::Empty,
+            stop_reason = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let result = self
+            .stream_assistant_response_inner(on_event, abort)
+            .instrument(span.clone())
+            .await;
+        if let Ok(message) = &result {
+            span.record("input_tokens", message.usage.input);
+            span.record("output_tokens", message.usage.output);
+            span.record("cache_read_tokens", message.usage.cache_read);
+            span.record("stop_reason", tracing::field::debug(message.stop_reason));
+        }
+        span.record("duration_ms", elapsed_ms(started));
+        result
+    }
+
+    #[allow(clippy::too_many_lines)]
+    async fn stream_assistant_response_inner(
+        &mut self,
+        on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
+        abort: Option<AbortSignal>,
+    ) -> Result<AssistantMessage> {
+        // Build context and stream completion. The scheduler slot is held until the response
+        // has been consumed.
+        let mut context = self.build_context();
+        self.check_tool_defs_size(&context.tools, on_event);
+        self.apply_pre_request_hooks(&mut context).await;
+        for middleware in &self.middleware {
+            middleware.on_context_build(&mut context);
+        }
+        let mut attempt = 0;
+        let (mut stream, _permit) = loop {
+            let permit = acquire_global(self.provider.name(), self.request_priority).await;
+            let result = {
+                let options = self.request_stream_options();
+                self.provider.stream(&context, &options).await
+            };
+            let err = match result {
+                Ok(stream) => break (stream, permit),
+                Err(err) => err,
+            };
+            // Give the scheduler slot back while waiting.
+            drop(permit);
+            attempt += 1;
+            let Some((delay, max_retries)) = self.retry_delay(&err, attempt) else {
+                return Err(err);
+            };
+            on_event(AgentEvent::RetryScheduled {
+                attempt,
+                max_attempts: max_retries,
+                delay_ms: u64::try_from(delay.as_millis()).unwrap_or(u64::MAX),
+                error_message: err.to_string(),
+            });
+            if !sleep_unless_aborted(delay, abort.as_ref()).await {
+                let abort_message = self.build_abort_message(None, abort.as_ref());
+                on_event(AgentEvent::MessageUpdate {
+                    message: Message::Assistant(abort_message.clone()),
+                    assistant_message_event: Box::new(AssistantMessageEvent::Error {
+                        reason: StopReason::Aborted,
+                        error: abort_message.clone(),
+                    }),
+                });
+                return Ok(self.finalize_assistant_message(abort_message, on_event, false));
+            }
+        };
 
         let mut partial_message: Option<AssistantMessage> = None;
         let mut added_partial = false;
@@ -844,7 +2613,8 @@ impl Agent {
 
                 match futures::future::select(abort_fut, event_fut).await {
                     futures::future::Either::Left(((), _event_fut)) => {
-                        let abort_message = self.build_abort_message(partial_message.take());
+                        let abort_message =
+                            self.build_abort_message(partial_message.take(), Some(signal));
                         on_event(AgentEvent::MessageUpdate {
                             message: Message::Assistant(abort_message.clone()),
                             assistant_message_event: Box::new(AssistantMessageEvent::Error {
@@ -867,7 +2637,10 @@ impl Agent {
             let Some(event_result) = event_result else {
                 break;
             };
-            let event = event_result?;
+            let mut event = event_result?;
+            for middleware in &self.middleware {
+                middleware.on_stream_event(&mut event);
+            }
 
             match event {
                 StreamEvent::Start { partial } => {
@@ -1099,7 +2872,10 @@ impl Agent {
                 StreamEvent::Done { message, .. } => {
                     return Ok(self.finalize_assistant_message(message, on_event, added_partial));
                 }
-                StreamEvent::Error { error, .. } => {
+                StreamEvent::Error { mut error, .. } => {
+                    if error.stop_reason == StopReason::Error && error.abort_reason.is_none() {
+                        error.abort_reason = Some(AbortReason::ProviderFailure);
+                    }
                     return Ok(self.finalize_assistant_message(error, on_event, added_partial));
                 }
             }
@@ -1129,10 +2905,13 @@ impl Agent {
 
     fn finalize_assistant_message(
         &mut self,
-        message: AssistantMessage,
+        mut message: AssistantMessage,
         on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
         added_partial: bool,
     ) -> AssistantMessage {
+        if let Some(pricing) = &self.config.pricing {
+            price_usage(&mut message.usage, pricing);
+        }
         if added_partial {
             if let Some(last) = self.messages.last_mut() {
                 *last = Message::Assistant(message.clone());
@@ -1164,6 +2943,31 @@ impl Agent {
             if abort.as_ref().is_some_and(AbortSignal::is_aborted) {
                 break;
             }
+            if self.pause.take_cancelled(&tool_call.id) {
+                let cancelled = self.skip_tool_call(
+                    tool_call,
+                    on_event,
+                    new_messages,
+                    "Tool call cancelled by the user.",
+                );
+                results.push(cancelled);
+                continue;
+            }
+            if let Some(repeats) = self.detect_tool_call_loop(tool_call) {
+                on_event(AgentEvent::LoopDetected {
+                    tool_call_id: tool_call.id.clone(),
+                    tool_name: tool_call.name.clone(),
+                    repeats,
+                });
+                let refused = self.skip_tool_call(
+                    tool_call,
+                    on_event,
+                    new_messages,
+                    &repeated_tool_call_message(&tool_call.name, repeats),
+                );
+                results.push(refused);
+                continue;
+            }
 
             on_event(AgentEvent::ToolExecutionStart {
                 tool_call_id: tool_call.id.clone(),
@@ -1173,7 +2977,7 @@ impl Agent {
 
             let tool_execution = self.execute_tool(tool_call, on_event);
 
-            let (output, is_error) = if let Some(signal) = abort.as_ref() {
+            let (mut output, is_error) = if let Some(signal) = abort.as_ref() {
                 use futures::future::{Either, select};
 
                 let tool_fut = tool_execution.fuse();
@@ -1198,6 +3002,22 @@ impl Agent {
                 tool_execution.await
             };
 
+            // The limit applies to what the tool produced, so the failure diagnostic and
+            // middleware additions below are never cut off.
+            if let Some(&limit) = self
+                .config
+                .tool_output_limits
+                .get(&tool_call.name)
+                .or_else(|| self.config.tool_output_limits.get("*"))
+            {
+                truncate_tool_output(tool_call, &mut output, limit);
+            }
+            let aborted = abort.as_ref().is_some_and(AbortSignal::is_aborted);
+            self.record_tool_outcome(&tool_call.name, &mut output, is_error && !aborted);
+            for middleware in &self.middleware {
+                middleware.on_tool_result(tool_call, &mut output, is_error);
+            }
+
             // Emit a final update so UIs can render tool output even if the tool
             // doesn't stream incremental updates.
             on_event(AgentEvent::ToolExecutionUpdate {
@@ -1246,7 +3066,12 @@ impl Agent {
 
                 // Skip remaining tool calls
                 for skipped in tool_calls.iter().skip(index + 1) {
-                    let skipped_result = self.skip_tool_call(skipped, on_event, new_messages);
+                    let skipped_result = self.skip_tool_call(
+                        skipped,
+                        on_event,
+                        new_messages,
+                        "Skipped due to queued user message.",
+                    );
                     results.push(skipped_result);
                 }
                 break;
@@ -1259,36 +3084,161 @@ impl Agent {
         })
     }
 
+    /// [`Self::execute_tool_inner`] in an `agent.tool` span.
     async fn execute_tool(
         &self,
         tool_call: &ToolCall,
         on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
     ) -> (ToolOutput, bool) {
-        let extensions = self.extensions.clone();
+        let span = tracing::info_span!(
+            "agent.tool",
+            tool_name = %tool_call.name,
+            tool_call_id = %tool_call.id,
+            is_error = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let (output, is_error) = self
+            .execute_tool_inner(tool_call, on_event)
+            .instrument(span.clone())
+            .await;
+        span.record("is_error", is_error);
+        span.record("duration_ms", elapsed_ms(started));
+        (output, is_error)
+    }
 
-        let (mut output, is_error) = if let Some(extensions) = &extensions {
-            match Self::dispatch_tool_call_hook(extensions, tool_call).await {
-                Some(blocked_output) => (blocked_output, true),
-                None => self.execute_tool_without_hooks(tool_call, on_event).await,
+    async fn execute_tool_inner(
+        &self,
+        tool_call: &ToolCall,
+        on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
+    ) -> (ToolOutput, bool) {
+        if self.config.dry_run {
+            return (
+                DryRunCall::new(&tool_call.name, &tool_call.arguments).output(),
+                false,
+            );
+        }
+        let extensions = self.extensions.clone();
+        let mut tool_call = Cow::Borrowed(tool_call);
+        let mut hook_context = Vec::new();
+
+        let mut blocked = None;
+        if self.hooks.has(HookEvent::PreToolUse) {
+            let payload = json!({
+                "toolName": tool_call.name,
+                "toolCallId": tool_call.id,
+                "arguments": tool_call.arguments,
+            });
+            let outcome = self
+                .hooks
+                .run(HookEvent::PreToolUse, Some(&tool_call.name), payload)
+                .await;
+            if let Some(arguments) = outcome.arguments {
+                tool_call.to_mut().arguments = arguments;
             }
-        } else {
-            self.execute_tool_without_hooks(tool_call, on_event).await
+            blocked = outcome
+                .block
+                .map(|reason| Self::tool_call_blocked_output(Some(&reason)));
+            hook_context = outcome.context;
+        }
+        if let (None, Some(extensions)) = (&blocked, &extensions) {
+            match Self::dispatch_tool_call_hook(extensions, &tool_call).await {
+                Ok(Some(input)) => tool_call.to_mut().arguments = input,
+                Ok(None) => {}
+                Err(blocked_output) => blocked = Some(blocked_output),
+            }
+        }
+        let tool_call = tool_call.as_ref();
+        let mut review = false;
+        if blocked.is_none() {
+            match self.request_tool_approval(tool_call, on_event).await {
+                Ok(wants_review) => review = wants_review,
+                Err(denied) => blocked = Some(denied),
+            }
+        }
+        let (mut output, is_error) = match blocked {
+            Some(blocked_output) => (blocked_output, true),
+            None => self.execute_tool_with_timeout(tool_call, on_event).await,
         };
+        if review && !is_error {
+            self.review_hunks(tool_call, &mut output).await;
+        }
 
         if let Some(extensions) = &extensions {
             Self::apply_tool_result_hook(extensions, tool_call, &mut output, is_error).await;
         }
 
+        if self.hooks.has(HookEvent::PostToolUse) {
+            let payload = json!({
+                "toolName": tool_call.name,
+                "toolCallId": tool_call.id,
+                "arguments": tool_call.arguments,
+                "result": blocks_text(&output.content),
+                "isError": is_error,
+            });
+            let outcome = self
+                .hooks
+                .run(HookEvent::PostToolUse, Some(&tool_call.name), payload)
+                .await;
+            hook_context.extend(outcome.context);
+        }
+        if !hook_context.is_empty() {
+            output.content.push(ContentBlock::Text(TextContent::new(
+                hook_context.join("\n\n"),
+            )));
+        }
+
         (output, is_error)
     }
 
+    /// Run a tool, cancelling it after its configured timeout. Dropping the tool's future drops
+    /// its process guards, which kill the commands it started.
+    async fn execute_tool_with_timeout(
+        &self,
+        tool_call: &ToolCall,
+        on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
+    ) -> (ToolOutput, bool) {
+        use futures::future::{Either, select};
+
+        let Some(&timeout) = self
+            .config
+            .tool_timeouts
+            .get(&tool_call.name)
+            .or_else(|| self.config.tool_timeouts.get("*"))
+        else {
+            return self.execute_tool_without_hooks(tool_call, on_event).await;
+        };
+        let tool_fut = self.execute_tool_without_hooks(tool_call, on_event).fuse();
+        let timeout_fut = asupersync::time::sleep(asupersync::time::wall_now(), timeout).fuse();
+        futures::pin_mut!(tool_fut, timeout_fut);
+        match select(tool_fut, timeout_fut).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => (
+                ToolOutput {
+                    content: vec![ContentBlock::Text(TextContent::new(format!(
+                        "Tool `{}` timed out after {} and was cancelled",
+                        tool_call.name,
+                        format_timeout(timeout)
+                    )))],
+                    details: None,
+                    is_error: true,
+                },
+                true,
+            ),
+        }
+    }
+
     async fn execute_tool_without_hooks(
         &self,
         tool_call: &ToolCall,
         on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
     ) -> (ToolOutput, bool) {
         // Find the tool
-        let Some(tool) = self.tools.get(&tool_call.name) else {
+        let Some(tool) = self
+            .tools
+            .get(&tool_call.name)
+            .filter(|_| self.tools.is_enabled(&tool_call.name))
+        else {
             return (Self::tool_not_found_output(&tool_call.name), true);
         };
 
@@ -1333,6 +3283,55 @@ impl Agent {
         }
     }
 
+    /// Count `tool_call` against the run of identical calls before it. Returns the run's
+    /// length once it reaches the `loop_detection` threshold.
+    fn detect_tool_call_loop(&mut self, tool_call: &ToolCall) -> Option<u32> {
+        let threshold = self.config.loop_detection?;
+        let repeats = match &mut self.repeated_tool_call {
+            Some((name, arguments, count))
+                if *name == tool_call.name && *arguments == tool_call.arguments =>
+            {
+                *count += 1;
+                *count
+            }
+            last => {
+                *last = Some((tool_call.name.clone(), tool_call.arguments.clone(), 1));
+                1
+            }
+        };
+        (repeats >= threshold).then_some(repeats)
+    }
+
+    /// A tool that failed again after its diagnostic said the retries were used up.
+    fn exhausted_tool(&self) -> Option<String> {
+        let max_retries = self.config.tool_retry_diagnostics?;
+        self.tool_failure_streaks
+            .iter()
+            .find(|(_, failures)| **failures > max_retries + 1)
+            .map(|(tool_name, _)| tool_name.clone())
+    }
+
+    /// Track consecutive failures for `tool_name` and, when enabled, append a diagnostic
+    /// to a failed result so the model sees what went wrong and how many retries remain.
+    fn record_tool_outcome(&mut self, tool_name: &str, output: &mut ToolOutput, failed: bool) {
+        if !failed {
+            self.tool_failure_streaks.remove(tool_name);
+            return;
+        }
+        let Some(max_retries) = self.config.tool_retry_diagnostics else {
+            return;
+        };
+        let streak = self
+            .tool_failure_streaks
+            .entry(tool_name.to_string())
+            .or_insert(0);
+        *streak += 1;
+        let diagnostic = tool_error_diagnostic(tool_name, output, *streak, max_retries);
+        output
+            .content
+            .push(ContentBlock::Text(TextContent::new(diagnostic)));
+    }
+
     fn tool_not_found_output(tool_name: &str) -> ToolOutput {
         ToolOutput {
             content: vec![ContentBlock::Text(TextContent::new(format!(
@@ -1343,41 +3342,147 @@ impl Agent {
         }
     }
 
+    /// Run the extensions' `tool_call` hook: the blocked output, or replacement arguments.
     async fn dispatch_tool_call_hook(
         extensions: &ExtensionManager,
         tool_call: &ToolCall,
-    ) -> Option<ToolOutput> {
+    ) -> std::result::Result<Option<Value>, ToolOutput> {
         match extensions
             .dispatch_tool_call(tool_call, EXTENSION_EVENT_TIMEOUT_MS)
             .await
         {
             Ok(Some(result)) if result.block => {
-                Some(Self::tool_call_blocked_output(result.reason.as_deref()))
+                Err(Self::tool_call_blocked_output(result.reason.as_deref()))
             }
-            Ok(_) => None,
+            Ok(Some(result)) => Ok(result.input.filter(Value::is_object)),
+            Ok(None) => Ok(None),
             Err(err) => {
                 tracing::warn!("tool_call extension hook failed (fail-open): {err}");
-                None
+                Ok(None)
             }
         }
     }
 
-    fn tool_call_blocked_output(reason: Option<&str>) -> ToolOutput {
-        let reason = reason.map(str::trim).filter(|reason| !reason.is_empty());
-        let message = reason.map_or_else(
-            || "Tool execution was blocked by an extension".to_string(),
-            |reason| format!("Tool execution blocked: {reason}"),
-        );
-
-        ToolOutput {
-            content: vec![ContentBlock::Text(TextContent::new(message))],
-            details: None,
-            is_error: true,
-        }
-    }
-
-    async fn apply_tool_result_hook(
-        extensions: &ExtensionManager,
+    /// Check the permission policy and, when it asks, the approver. Returns the blocked output
+    /// on denial, else whether the user asked to review the resulting change hunk by hunk.
+    async fn request_tool_approval(
+        &self,
+        tool_call: &ToolCall,
+        on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
+    ) -> std::result::Result<bool, ToolOutput> {
+        let decision = self
+            .permission_policy
+            .decide(&tool_call.name, &tool_call.arguments);
+        let outside_path = match self.write_access(tool_call) {
+            Some((WriteAccess::Denied, path)) => {
+                return Err(ToolOutput {
+                    content: vec![ContentBlock::Text(TextContent::new(format!(
+                        "Tool execution denied: {path} is a system path"
+                    )))],
+                    details: None,
+                    is_error: true,
+                });
+            }
+            Some((WriteAccess::NeedsApproval, path)) => Some(path),
+            _ => None,
+        };
+        match decision.action {
+            PermissionAction::Allow if outside_path.is_none() => return Ok(false),
+            PermissionAction::Deny => {
+                let rule = decision.rule.unwrap_or_default();
+                return Err(ToolOutput {
+                    content: vec![ContentBlock::Text(TextContent::new(format!(
+                        "Tool execution denied by permission rule `{rule}`"
+                    )))],
+                    details: None,
+                    is_error: true,
+                });
+            }
+            PermissionAction::Allow | PermissionAction::Ask => {}
+        }
+        // Without an approver, the tool itself refuses a path outside the writable roots.
+        let Some(approver) = self.tool_approver.as_ref() else {
+            return Ok(false);
+        };
+        let request = ToolApprovalRequest::from_tool_call(tool_call);
+        on_event(AgentEvent::PermissionRequest {
+            tool_call_id: request.tool_call_id.clone(),
+            tool_name: request.tool_name.clone(),
+            args: request.args.clone(),
+            path: request.path.clone(),
+            rule: if outside_path.is_some() {
+                Some("outside the writable roots".to_string())
+            } else {
+                decision.rule
+            },
+        });
+        let decision = approver(request).await;
+        if let (Some(path), Some(scope)) = (outside_path, self.tools.write_scope())
+            && decision != ToolApprovalDecision::Deny
+        {
+            scope.approve_once(path);
+        }
+        match decision {
+            ToolApprovalDecision::Allow | ToolApprovalDecision::AlwaysAllow => Ok(false),
+            ToolApprovalDecision::Review => {
+                Ok(HUNK_REVIEW_TOOLS.contains(&tool_call.name.as_str()))
+            }
+            ToolApprovalDecision::Deny => Err(ToolOutput {
+                content: vec![ContentBlock::Text(TextContent::new(
+                    "Tool execution denied by the user",
+                ))],
+                details: None,
+                is_error: true,
+            }),
+        }
+    }
+
+    /// How the registry's write scope treats a `write`/`edit` call, with its path.
+    fn write_access<'a>(&self, tool_call: &'a ToolCall) -> Option<(WriteAccess, &'a str)> {
+        if !matches!(tool_call.name.as_str(), "write" | "edit") {
+            return None;
+        }
+        let path = tool_call.arguments.get("path").and_then(Value::as_str)?;
+        let scope = self.tools.write_scope()?;
+        Some((scope.check(path), path))
+    }
+
+    /// Hand a finished file change to the hunk reviewer and tell the model what was reverted.
+    async fn review_hunks(&self, tool_call: &ToolCall, output: &mut ToolOutput) {
+        let Some(reviewer) = self.hunk_reviewer.as_ref() else {
+            return;
+        };
+        let Some(path) = tool_call.arguments.get("path").and_then(Value::as_str) else {
+            return;
+        };
+        let request = HunkReviewRequest {
+            tool_call_id: tool_call.id.clone(),
+            tool_name: tool_call.name.clone(),
+            path: path.to_string(),
+        };
+        if let Some(note) = reviewer(request).await {
+            output
+                .content
+                .push(ContentBlock::Text(TextContent::new(note)));
+        }
+    }
+
+    fn tool_call_blocked_output(reason: Option<&str>) -> ToolOutput {
+        let reason = reason.map(str::trim).filter(|reason| !reason.is_empty());
+        let message = reason.map_or_else(
+            || "Tool execution was blocked by an extension".to_string(),
+            |reason| format!("Tool execution blocked: {reason}"),
+        );
+
+        ToolOutput {
+            content: vec![ContentBlock::Text(TextContent::new(message))],
+            details: None,
+            is_error: true,
+        }
+    }
+
+    async fn apply_tool_result_hook(
+        extensions: &ExtensionManager,
         tool_call: &ToolCall,
         output: &mut ToolOutput,
         is_error: bool,
@@ -1404,11 +3509,10 @@ impl Agent {
         tool_call: &ToolCall,
         on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
         new_messages: &mut Vec<Message>,
+        reason: &str,
     ) -> ToolResultMessage {
         let output = ToolOutput {
-            content: vec![ContentBlock::Text(TextContent::new(
-                "Skipped due to queued user message.",
-            ))],
+            content: vec![ContentBlock::Text(TextContent::new(reason))],
             details: None,
             is_error: true,
         };
@@ -2256,11 +4360,96 @@ mod extensions_integration_tests {
             }
         });
     }
+
+    #[test]
+    fn system_prompt_placeholders_expand_against_the_project() {
+        let project = tempfile::tempdir().expect("tempdir");
+        let global = tempfile::tempdir().expect("tempdir");
+        let sub = project.path().join("sub");
+        std::fs::create_dir_all(project.path().join(".git")).expect("mkdir .git");
+        std::fs::create_dir_all(&sub).expect("mkdir sub");
+        std::fs::write(
+            project.path().join(".git/HEAD"),
+            "ref: refs/heads/feature/x\n",
+        )
+        .expect("write HEAD");
+        std::fs::write(project.path().join("PI.md"), "Use tabs.").expect("write PI.md");
+
+        let prompt = expand_prompt_variables(
+            "In {{cwd}} on {{ git_branch }}, {{unknown}} {{os}}\n{{project_context}}{{",
+            |name| prompt_variable(name, &sub, global.path()),
+        );
+        assert!(
+            prompt.starts_with(&format!(
+                "In {} on feature/x, {{{{unknown}}}} ",
+                sub.display()
+            )),
+            "{prompt}"
+        );
+        assert!(prompt.contains(std::env::consts::OS), "{prompt}");
+        assert!(prompt.contains("# Project Context"), "{prompt}");
+        assert!(prompt.contains("Use tabs."), "{prompt}");
+        assert!(prompt.ends_with("{{"), "{prompt}");
+
+        std::fs::write(project.path().join(".git/HEAD"), "0123456789abcdef\n").expect("write HEAD");
+        assert_eq!(git_branch(&sub).as_deref(), Some("0123456"));
+        assert_eq!(expand_system_prompt("no placeholders"), "no placeholders");
+    }
+
+    #[test]
+    fn build_context_rereads_pinned_files_within_budget() {
+        let temp_dir = tempfile::tempdir().expect("tempdir");
+        let notes = temp_dir.path().join("notes.md");
+        let big = temp_dir.path().join("big.txt");
+        std::fs::write(&notes, "first draft").expect("write notes");
+        std::fs::write(&big, "x".repeat(100)).expect("write big");
+
+        let config = AgentConfig {
+            system_prompt: Some("base prompt".to_string()),
+            ..AgentConfig::default()
+        };
+        let mut agent = Agent::new(
+            Arc::new(NoopProvider),
+            ToolRegistry::from_tools(Vec::new()),
+            config,
+        );
+        agent.set_pinned_budget_tokens(5);
+        assert!(agent.pin_file(notes.clone()));
+        assert!(!agent.pin_file(notes.clone()));
+        assert!(agent.pin_file(big.clone()));
+
+        std::fs::write(&notes, "second draft").expect("rewrite notes");
+        let prompt = agent.build_context().system_prompt.expect("system prompt");
+        assert!(
+            prompt.starts_with("base prompt\n\n# Pinned files"),
+            "{prompt}"
+        );
+        assert!(prompt.contains("second draft"), "{prompt}");
+        assert!(!prompt.contains("first draft"));
+        assert!(prompt.contains(&format!("## {}", big.display())));
+        assert!(prompt.contains("\nxxxxxxxx\n[... truncated"), "{prompt}");
+
+        // The budget can end inside a multi-byte character.
+        std::fs::write(&notes, format!("{}\u{e9}", "a".repeat(19))).expect("rewrite notes");
+        let prompt = agent.build_context().system_prompt.expect("system prompt");
+        assert!(
+            prompt.contains(&format!("\n{}\n[... truncated", "a".repeat(19))),
+            "{prompt}"
+        );
+
+        assert!(agent.unpin_file(&notes));
+        agent.clear_pinned_files();
+        assert_eq!(
+            agent.build_context().system_prompt.as_deref(),
+            Some("base prompt")
+        );
+    }
 }
 
 #[cfg(test)]
 mod abort_tests {
     use super::*;
+    use crate::model::Cost;
     use crate::session::Session;
     use crate::tools::ToolRegistry;
     use asupersync::runtime::RuntimeBuilder;
@@ -2320,6 +4509,7 @@ mod abort_tests {
                 model: self.model_id().to_string(),
                 usage: Usage::default(),
                 stop_reason: StopReason::Stop,
+                abort_reason: None,
                 error_message: None,
                 timestamp: 0,
             };
@@ -2373,12 +4563,347 @@ mod abort_tests {
             assert_eq!(message.error_message.as_deref(), Some("Aborted"));
         });
     }
+
+    #[test]
+    fn abort_reason_reaches_the_message_and_agent_end() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let handle = runtime.handle();
+
+        let started = Arc::new(Notify::new());
+        let started_wait = started.notified();
+
+        let (abort_handle, abort_signal) = AbortHandle::new();
+
+        let provider = Arc::new(HangingProvider);
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let agent = Agent::new(provider, tools, AgentConfig::default());
+        let mut agent_session = AgentSession::new(agent, Session::in_memory(), false);
+
+        let end_reason = Arc::new(std::sync::Mutex::new(None));
+        let end_reason_capture = Arc::clone(&end_reason);
+        let started_tx = Arc::clone(&started);
+        let join = handle.spawn(async move {
+            agent_session
+                .run_text_with_abort("hello".to_string(), Some(abort_signal), move |event| {
+                    match event {
+                        AgentEvent::MessageStart {
+                            message: Message::Assistant(_),
+                        } => started_tx.notify_waiters(),
+                        AgentEvent::AgentEnd { abort_reason, .. } => {
+                            *end_reason_capture.lock().unwrap() = abort_reason;
+                        }
+                        _ => {}
+                    }
+                })
+                .await
+        });
+
+        runtime.block_on(async move {
+            started_wait.await;
+            abort_handle.abort_with(AbortReason::Timeout);
+            // A later abort does not replace the first reason.
+            abort_handle.abort();
+
+            let message = join.await.expect("run_text_with_abort");
+            assert_eq!(message.stop_reason, StopReason::Aborted);
+            assert_eq!(message.abort_reason, Some(AbortReason::Timeout));
+            assert_eq!(message.error_message.as_deref(), Some("Aborted: timed out"));
+            assert_eq!(*end_reason.lock().unwrap(), Some(AbortReason::Timeout));
+        });
+    }
+
+    #[test]
+    fn paused_run_lists_pending_tool_calls_and_skips_cancelled_ones() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let workspace = tempfile::tempdir().expect("tempdir");
+        std::fs::write(workspace.path().join("a.txt"), "alpha").expect("write a.txt");
+        let read = |id: &str, path: &str| {
+            ContentBlock::ToolCall(ToolCall {
+                id: id.to_string(),
+                name: "read".to_string(),
+                arguments: serde_json::json!({ "path": path }),
+                thought_signature: None,
+            })
+        };
+        let reply = |content: Vec<ContentBlock>, stop_reason: StopReason| AssistantMessage {
+            content,
+            api: "test-api".to_string(),
+            provider: "test-provider".to_string(),
+            model: "test-model".to_string(),
+            usage: Usage::default(),
+            stop_reason,
+            abort_reason: None,
+            error_message: None,
+            timestamp: 0,
+        };
+        let provider = crate::replay::ReplayProvider::new([
+            reply(
+                vec![read("call-a", "a.txt"), read("call-b", "b.txt")],
+                StopReason::ToolUse,
+            ),
+            reply(
+                vec![ContentBlock::Text(TextContent::new("done"))],
+                StopReason::Stop,
+            ),
+        ]);
+        let tools = ToolRegistry::new(&["read"], workspace.path(), None);
+        let mut agent = Agent::new(Arc::new(provider), tools, AgentConfig::default());
+        let pause = agent.pause_handle();
+        let pending = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let seen = Arc::clone(&pending);
+        runtime.block_on(async {
+            agent
+                .run("read both", move |event| match event {
+                    AgentEvent::MessageEnd {
+                        message: Message::Assistant(message),
+                    } if message.stop_reason == StopReason::ToolUse => pause.pause(),
+                    AgentEvent::Paused { pending_tool_calls } => {
+                        seen.lock()
+                            .unwrap()
+                            .extend(pending_tool_calls.into_iter().map(|call| call.id));
+                        pause.cancel_tool_call("call-b");
+                        pause.resume();
+                    }
+                    _ => {}
+                })
+                .await
+                .expect("run");
+        });
+
+        assert_eq!(*pending.lock().unwrap(), vec!["call-a", "call-b"]);
+        let results = agent
+            .messages()
+            .iter()
+            .filter_map(|message| match message {
+                Message::ToolResult(result) => Some(result),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        assert!(!results[0].is_error);
+        assert!(blocks_text(&results[0].content).contains("alpha"));
+        assert!(results[1].is_error);
+        assert_eq!(
+            blocks_text(&results[1].content),
+            "Tool call cancelled by the user."
+        );
+    }
+
+    #[test]
+    fn identical_tool_calls_in_a_row_are_refused_as_a_loop() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let workspace = tempfile::tempdir().expect("tempdir");
+        std::fs::write(workspace.path().join("a.txt"), "alpha").expect("write a.txt");
+        let reply = |content: ContentBlock, stop_reason: StopReason| AssistantMessage {
+            content: vec![content],
+            api: "test-api".to_string(),
+            provider: "test-provider".to_string(),
+            model: "test-model".to_string(),
+            usage: Usage::default(),
+            stop_reason,
+            abort_reason: None,
+            error_message: None,
+            timestamp: 0,
+        };
+        let read = |id: &str| {
+            reply(
+                ContentBlock::ToolCall(ToolCall {
+                    id: id.to_string(),
+                    name: "read".to_string(),
+                    arguments: serde_json::json!({ "path": "a.txt" }),
+                    thought_signature: None,
+                }),
+                StopReason::ToolUse,
+            )
+        };
+        let provider = crate::replay::ReplayProvider::new([
+            read("call-1"),
+            read("call-2"),
+            read("call-3"),
+            reply(
+                ContentBlock::Text(TextContent::new("giving up")),
+                StopReason::Stop,
+            ),
+        ]);
+        let tools = ToolRegistry::new(&["read"], workspace.path(), None);
+        let config = AgentConfig {
+            loop_detection: Some(3),
+            ..AgentConfig::default()
+        };
+        let mut agent = Agent::new(Arc::new(provider), tools, config);
+        let detected = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let seen = Arc::clone(&detected);
+        runtime.block_on(async {
+            agent
+                .run("read it", move |event| {
+                    if let AgentEvent::LoopDetected {
+                        tool_call_id,
+                        repeats,
+                        ..
+                    } = event
+                    {
+                        seen.lock().unwrap().push((tool_call_id, repeats));
+                    }
+                })
+                .await
+                .expect("run");
+        });
+
+        assert_eq!(*detected.lock().unwrap(), [("call-3".to_string(), 3)]);
+        let results = agent
+            .messages()
+            .iter()
+            .filter_map(|message| match message {
+                Message::ToolResult(result) => Some(result),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+        assert!(!results[1].is_error);
+        assert!(results[2].is_error);
+        assert!(
+            blocks_text(&results[2].content)
+                .starts_with("Loop detected: `read` was called 3 times")
+        );
+    }
+
+    #[test]
+    fn tool_failing_past_its_retries_stops_the_run() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let workspace = tempfile::tempdir().expect("tempdir");
+        let reply = |content: ContentBlock, stop_reason: StopReason| AssistantMessage {
+            content: vec![content],
+            api: "test-api".to_string(),
+            provider: "test-provider".to_string(),
+            model: "test-model".to_string(),
+            usage: Usage::default(),
+            stop_reason,
+            abort_reason: None,
+            error_message: None,
+            timestamp: 0,
+        };
+        let read_missing = |id: &str| {
+            reply(
+                ContentBlock::ToolCall(ToolCall {
+                    id: id.to_string(),
+                    name: "read".to_string(),
+                    arguments: serde_json::json!({ "path": "missing.txt" }),
+                    thought_signature: None,
+                }),
+                StopReason::ToolUse,
+            )
+        };
+        let provider = crate::replay::ReplayProvider::new([
+            read_missing("call-1"),
+            read_missing("call-2"),
+            read_missing("call-3"),
+            read_missing("call-4"),
+            reply(
+                ContentBlock::Text(TextContent::new("never sent")),
+                StopReason::Stop,
+            ),
+        ]);
+        let tools = ToolRegistry::new(&["read"], workspace.path(), None);
+        let config = AgentConfig {
+            tool_retry_diagnostics: Some(2),
+            ..AgentConfig::default()
+        };
+        let mut agent = Agent::new(Arc::new(provider), tools, config);
+        let end_reason = Arc::new(std::sync::Mutex::new(None));
+
+        let seen = Arc::clone(&end_reason);
+        let message = runtime.block_on(async {
+            agent
+                .run("read it", move |event| {
+                    if let AgentEvent::AgentEnd { abort_reason, .. } = event {
+                        *seen.lock().unwrap() = abort_reason;
+                    }
+                })
+                .await
+                .expect("run")
+        });
+
+        // One try plus two retries get diagnostics; the fourth failure ends the run.
+        assert_eq!(message.stop_reason, StopReason::Error);
+        assert_eq!(message.abort_reason, Some(AbortReason::ToolFailure));
+        assert_eq!(*end_reason.lock().unwrap(), Some(AbortReason::ToolFailure));
+        let failures = agent
+            .messages()
+            .iter()
+            .filter(|message| matches!(message, Message::ToolResult(result) if result.is_error))
+            .count();
+        assert_eq!(failures, 4);
+    }
+
+    #[test]
+    fn usage_is_priced_from_model_pricing_unless_the_provider_did() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let reply = |cost_total: f64| AssistantMessage {
+            content: vec![ContentBlock::Text(TextContent::new("done"))],
+            api: "test-api".to_string(),
+            provider: "test-provider".to_string(),
+            model: "test-model".to_string(),
+            usage: Usage {
+                input: 1_000_000,
+                output: 200_000,
+                cache_read: 500_000,
+                cost: Cost {
+                    total: cost_total,
+                    ..Cost::default()
+                },
+                ..Usage::default()
+            },
+            stop_reason: StopReason::Stop,
+            abort_reason: None,
+            error_message: None,
+            timestamp: 0,
+        };
+        let config = AgentConfig {
+            pricing: Some(ModelCost {
+                input: 3.0,
+                output: 15.0,
+                cache_read: 0.3,
+                cache_write: 3.75,
+            }),
+            ..AgentConfig::default()
+        };
+        let provider = crate::replay::ReplayProvider::new([reply(0.0), reply(9.0)]);
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let mut agent = Agent::new(Arc::new(provider), tools, config);
+
+        runtime.block_on(async {
+            let priced = agent.run("first", |_| {}).await.expect("run");
+            let cost = priced.usage.cost;
+            assert!((cost.input - 3.0).abs() < 1e-9);
+            assert!((cost.output - 3.0).abs() < 1e-9);
+            assert!((cost.cache_read - 0.15).abs() < 1e-9);
+            assert!((cost.total - 6.15).abs() < 1e-9);
+            assert!((cost.cache_savings - 1.35).abs() < 1e-9);
+
+            let reported = agent.run("second", |_| {}).await.expect("run");
+            assert!((reported.usage.cost.total - 9.0).abs() < 1e-9);
+            assert!((reported.usage.cost.cache_savings - 1.35).abs() < 1e-9);
+        });
+    }
 }
 
 #[cfg(test)]
 mod turn_event_tests {
     use super::*;
-    use crate::session::Session;
+    use crate::provider::http_status_error;
+    use crate::session::{Session, SessionEntry};
     use crate::tools::ToolRegistry;
     use asupersync::runtime::RuntimeBuilder;
     use async_trait::async_trait;
@@ -2386,6 +4911,7 @@ mod turn_event_tests {
     use std::path::Path;
     use std::pin::Pin;
     use std::sync::Mutex;
+    use std::sync::atomic::AtomicUsize;
 
     fn assistant_message(text: &str) -> AssistantMessage {
         AssistantMessage {
@@ -2395,6 +4921,7 @@ mod turn_event_tests {
             model: "test-model".to_string(),
             usage: Usage::default(),
             stop_reason: StopReason::Stop,
+            abort_reason: None,
             error_message: None,
             timestamp: 0,
         }
@@ -2438,89 +4965,1241 @@ mod turn_event_tests {
     }
 
     #[test]
-    fn turn_events_wrap_assistant_response() {
+    fn run_stream_yields_the_run_events_then_the_result() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+
+        let provider = Arc::new(SingleShotProvider);
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let mut agent = Agent::new(provider, tools, AgentConfig::default());
+
+        runtime.block_on(async move {
+            let mut stream = agent.run_stream("hello", None);
+            let mut events = Vec::new();
+            while let Some(event) = stream.next().await {
+                events.push(event);
+            }
+            assert!(matches!(
+                events.first(),
+                Some(AgentEvent::AgentStart { .. })
+            ));
+            assert!(matches!(events.last(), Some(AgentEvent::AgentEnd { .. })));
+            assert!(
+                events
+                    .iter()
+                    .any(|event| matches!(event, AgentEvent::TurnEnd { .. }))
+            );
+
+            let message = stream.take_result().expect("finished").expect("run");
+            assert_eq!(message.stop_reason, StopReason::Stop);
+            assert!(stream.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn turn_events_wrap_assistant_response() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let handle = runtime.handle();
+
+        let provider = Arc::new(SingleShotProvider);
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let agent = Agent::new(provider, tools, AgentConfig::default());
+        let session = Session::in_memory();
+        let mut agent_session = AgentSession::new(agent, session, false);
+
+        let events: Arc<Mutex<Vec<AgentEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let events_capture = Arc::clone(&events);
+
+        let join = handle.spawn(async move {
+            agent_session
+                .run_text("hello".to_string(), move |event| {
+                    events_capture.lock().unwrap().push(event);
+                })
+                .await
+                .expect("run_text")
+        });
+
+        runtime.block_on(async move {
+            let message = join.await;
+            assert_eq!(message.stop_reason, StopReason::Stop);
+
+            let events = events.lock().unwrap();
+            let turn_start_indices = events
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, event)| {
+                    matches!(event, AgentEvent::TurnStart { .. }).then_some(idx)
+                })
+                .collect::<Vec<_>>();
+            let turn_end_indices = events
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, event)| {
+                    matches!(event, AgentEvent::TurnEnd { .. }).then_some(idx)
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(turn_start_indices.len(), 1);
+            assert_eq!(turn_end_indices.len(), 1);
+            assert!(turn_start_indices[0] < turn_end_indices[0]);
+
+            let assistant_message_end = events
+                .iter()
+                .enumerate()
+                .find_map(|(idx, event)| match event {
+                    AgentEvent::MessageEnd {
+                        message: Message::Assistant(_),
+                    } => Some(idx),
+                    _ => None,
+                })
+                .expect("assistant message end");
+
+            assert!(assistant_message_end < turn_end_indices[0]);
+
+            let (message_is_assistant, tool_results_empty) = {
+                let turn_end_event = &events[turn_end_indices[0]];
+                assert!(
+                    matches!(turn_end_event, AgentEvent::TurnEnd { .. }),
+                    "Expected TurnEnd event, got {turn_end_event:?}"
+                );
+                match turn_end_event {
+                    AgentEvent::TurnEnd {
+                        message,
+                        tool_results,
+                        ..
+                    } => (
+                        matches!(message, Message::Assistant(_)),
+                        tool_results.is_empty(),
+                    ),
+                    _ => (false, false),
+                }
+            };
+            drop(events);
+            assert!(message_is_assistant);
+            assert!(tool_results_empty);
+        });
+    }
+
+    type Respond =
+        Box<dyn Fn(usize, &Context) -> crate::error::Result<AssistantMessage> + Send + Sync>;
+
+    /// Answers the `n`th request (from 0) with `respond(n, context)` and records the messages
+    /// of every request it was sent.
+    struct ScriptedProvider {
+        respond: Respond,
+        calls: AtomicUsize,
+        contexts: Mutex<Vec<Vec<Message>>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(
+            respond: impl Fn(usize, &Context) -> crate::error::Result<AssistantMessage>
+            + Send
+            + Sync
+            + 'static,
+        ) -> Self {
+            Self {
+                respond: Box::new(respond),
+                calls: AtomicUsize::new(0),
+                contexts: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Answers every request with `text`.
+        fn replying(text: &'static str) -> Self {
+            Self::new(move |_, _| Ok(assistant_message(text)))
+        }
+    }
+
+    #[async_trait]
+    #[allow(clippy::unnecessary_literal_bound)]
+    impl Provider for ScriptedProvider {
+        fn name(&self) -> &str {
+            "test-provider"
+        }
+
+        fn api(&self) -> &str {
+            "test-api"
+        }
+
+        fn model_id(&self) -> &str {
+            "test-model"
+        }
+
+        async fn stream(
+            &self,
+            context: &Context,
+            _options: &StreamOptions,
+        ) -> crate::error::Result<
+            Pin<Box<dyn Stream<Item = crate::error::Result<StreamEvent>> + Send>>,
+        > {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            self.contexts.lock().unwrap().push(context.messages.clone());
+            let message = (self.respond)(call, context)?;
+            let events = vec![
+                Ok(StreamEvent::Start {
+                    partial: assistant_message(""),
+                }),
+                Ok(StreamEvent::Done {
+                    reason: message.stop_reason,
+                    message,
+                }),
+            ];
+            Ok(Box::pin(futures::stream::iter(events)))
+        }
+    }
+
+    #[test]
+    fn steering_sent_mid_run_is_appended_before_next_provider_call() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let handle = runtime.handle();
+
+        let provider = Arc::new(ScriptedProvider::replying("ok"));
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let mut agent = Agent::new(provider.clone(), tools, AgentConfig::default());
+
+        // Nothing is queued at the start of the turn; the correction arrives while the
+        // first response is streaming and is picked up at the next delivery boundary.
+        let fetches = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let steering: MessageFetcher = Arc::new(move || {
+            let call = fetches.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                if call == 1 {
+                    vec![Message::User(UserMessage {
+                        content: UserContent::Text("use rg instead".to_string()),
+                        timestamp: 0,
+                    })]
+                } else {
+                    Vec::new()
+                }
+            })
+        });
+        agent.set_message_fetchers(Some(steering), None);
+        let mut agent_session = AgentSession::new(agent, Session::in_memory(), false);
+
+        let join = handle.spawn(async move {
+            agent_session
+                .run_text("find usages".to_string(), |_| {})
+                .await
+                .expect("run_text")
+        });
+
+        runtime.block_on(async move {
+            let message = join.await;
+            assert_eq!(message.stop_reason, StopReason::Stop);
+        });
+
+        let contexts = provider.contexts.lock().unwrap();
+        assert_eq!(contexts.len(), 2, "steering should trigger a second call");
+        assert!(matches!(
+            contexts[1].last(),
+            Some(Message::User(UserMessage {
+                content: UserContent::Text(text),
+                ..
+            })) if text == "use rg instead"
+        ));
+    }
+    #[test]
+    fn denied_tool_call_is_blocked_after_approval_request() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let mut agent = Agent::new(Arc::new(SingleShotProvider), tools, AgentConfig::default());
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let asked_by_approver = Arc::clone(&asked);
+        let approver: ToolApprover = Arc::new(move |request: ToolApprovalRequest| {
+            asked_by_approver
+                .lock()
+                .unwrap()
+                .push(request.always_rule());
+            Box::pin(async { ToolApprovalDecision::Deny })
+        });
+        agent.set_tool_approver(Some(approver));
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_sink = Arc::clone(&events);
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(move |event| {
+            events_sink.lock().unwrap().push(event);
+        });
+        let call = |name: &str, arguments: Value| ToolCall {
+            id: format!("call-{name}"),
+            name: name.to_string(),
+            arguments,
+            thought_signature: None,
+        };
+
+        runtime.block_on(async {
+            let (output, is_error) = agent
+                .execute_tool(&call("edit", json!({ "path": "src/lib.rs" })), &on_event)
+                .await;
+            assert!(is_error);
+            assert!(matches!(
+                output.content.as_slice(),
+                [ContentBlock::Text(text)] if text.text.contains("denied by the user")
+            ));
+
+            // Read-only tools never reach the approver.
+            let (_, is_error) = agent
+                .execute_tool(&call("read", json!({ "path": "src/lib.rs" })), &on_event)
+                .await;
+            assert!(is_error, "read is not registered in this test");
+        });
+
+        assert_eq!(*asked.lock().unwrap(), vec!["edit:src/lib.rs".to_string()]);
+        let events = events.lock().unwrap();
+        assert!(matches!(
+            events.first(),
+            Some(AgentEvent::PermissionRequest { tool_name, path: Some(path), .. })
+                if tool_name == "edit" && path == "src/lib.rs"
+        ));
+    }
+
+    #[test]
+    fn tool_timeout_cancels_a_slow_tool_with_an_error_result() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let workspace = tempfile::tempdir().expect("tempdir");
+        let tools = ToolRegistry::new(&["bash"], workspace.path(), None);
+        let config = AgentConfig {
+            tool_timeouts: HashMap::from([("bash".to_string(), Duration::from_millis(200))]),
+            ..AgentConfig::default()
+        };
+        let mut agent = Agent::new(Arc::new(SingleShotProvider), tools, config);
+        agent.set_permission_policy(PermissionPolicy::new(vec![
+            crate::permissions::PermissionRule::new("bash", PermissionAction::Allow),
+        ]));
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(|_| {});
+        let call = ToolCall {
+            id: "call-bash".to_string(),
+            name: "bash".to_string(),
+            arguments: json!({ "command": "sleep 10" }),
+            thought_signature: None,
+        };
+
+        let started = std::time::Instant::now();
+        let (output, is_error) = runtime.block_on(agent.execute_tool(&call, &on_event));
+        assert!(is_error);
+        assert_eq!(
+            blocks_text(&output.content),
+            "Tool `bash` timed out after 200ms and was cancelled"
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn dry_run_records_the_call_without_running_it() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let workspace = tempfile::tempdir().expect("tempdir");
+        let tools = ToolRegistry::new(&["bash", "write"], workspace.path(), None);
+        let config = AgentConfig {
+            dry_run: true,
+            ..AgentConfig::default()
+        };
+        let agent = Agent::new(Arc::new(SingleShotProvider), tools, config);
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(|_| {});
+        let call = |name: &str, arguments: Value| ToolCall {
+            id: format!("call-{name}"),
+            name: name.to_string(),
+            arguments,
+            thought_signature: None,
+        };
+
+        runtime.block_on(async {
+            let (output, is_error) = agent
+                .execute_tool(
+                    &call("bash", json!({ "command": "touch ran.txt" })),
+                    &on_event,
+                )
+                .await;
+            assert!(!is_error);
+            assert_eq!(
+                blocks_text(&output.content),
+                "Dry run: `bash: touch ran.txt` was not executed."
+            );
+            assert_eq!(
+                output.details.unwrap()["dryRun"]["command"],
+                "touch ran.txt"
+            );
+
+            let (output, _) = agent
+                .execute_tool(
+                    &call("write", json!({ "path": "notes.md", "content": "hi" })),
+                    &on_event,
+                )
+                .await;
+            let recorded: DryRunCall =
+                serde_json::from_value(output.details.unwrap()["dryRun"].clone()).unwrap();
+            assert_eq!(recorded.paths, ["notes.md"]);
+            assert_eq!(recorded.describe(), "write: notes.md");
+        });
+        assert!(!workspace.path().join("ran.txt").exists());
+        assert!(!workspace.path().join("notes.md").exists());
+    }
+
+    #[test]
+    fn writes_outside_the_workspace_need_a_root_or_approval() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let workspace = tempfile::tempdir().expect("tempdir");
+        let notes = tempfile::tempdir().expect("tempdir");
+        let elsewhere = tempfile::tempdir().expect("tempdir");
+        let config = crate::config::Config {
+            writable_roots: Some(vec![notes.path().display().to_string()]),
+            ..crate::config::Config::default()
+        };
+        let tools = ToolRegistry::new(&["write"], workspace.path(), Some(&config));
+        let mut agent = Agent::new(Arc::new(SingleShotProvider), tools, AgentConfig::default());
+        agent.set_permission_policy(PermissionPolicy::new(vec![
+            crate::permissions::PermissionRule::new("write", PermissionAction::Allow),
+        ]));
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(|_| {});
+        let write = |path: &Path| ToolCall {
+            id: "call-write".to_string(),
+            name: "write".to_string(),
+            arguments: json!({ "path": path.display().to_string(), "content": "hi" }),
+            thought_signature: None,
+        };
+        let outside = elsewhere.path().join("todo.md");
+
+        runtime.block_on(async {
+            let (_, is_error) = agent
+                .execute_tool(&write(&notes.path().join("today.md")), &on_event)
+                .await;
+            assert!(!is_error, "writable roots need no approval");
+
+            let (output, is_error) = agent.execute_tool(&write(&outside), &on_event).await;
+            assert!(is_error);
+            assert!(blocks_text(&output.content).contains("outside the workspace"));
+        });
+        assert!(!outside.exists());
+
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let asked_by_approver = Arc::clone(&asked);
+        let approver: ToolApprover = Arc::new(move |request: ToolApprovalRequest| {
+            asked_by_approver.lock().unwrap().push(request.tool_name);
+            Box::pin(async { ToolApprovalDecision::Allow })
+        });
+        agent.set_tool_approver(Some(approver));
+        runtime.block_on(async {
+            let (_, is_error) = agent.execute_tool(&write(&outside), &on_event).await;
+            assert!(!is_error, "an approved write goes through");
+
+            let (output, is_error) = agent
+                .execute_tool(&write(Path::new("/etc/pi-test")), &on_event)
+                .await;
+            assert!(is_error);
+            assert!(blocks_text(&output.content).contains("system path"));
+        });
+        assert!(outside.exists());
+        assert_eq!(
+            asked.lock().unwrap().len(),
+            1,
+            "system paths are never offered"
+        );
+    }
+
+    #[test]
+    fn permission_policy_denies_or_skips_the_approver() {
+        use crate::permissions::PermissionRule;
+
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let mut agent = Agent::new(Arc::new(SingleShotProvider), tools, AgentConfig::default());
+        agent.set_permission_policy(PermissionPolicy::new(vec![
+            PermissionRule::new("bash", PermissionAction::Allow).with_command("git status*"),
+            PermissionRule::new("bash", PermissionAction::Deny).with_command("rm *"),
+        ]));
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let asked_by_approver = Arc::clone(&asked);
+        let approver: ToolApprover = Arc::new(move |request: ToolApprovalRequest| {
+            asked_by_approver
+                .lock()
+                .unwrap()
+                .push(request.args["command"].clone());
+            Box::pin(async { ToolApprovalDecision::Allow })
+        });
+        agent.set_tool_approver(Some(approver));
+
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(|_| {});
+        let bash = |command: &str| ToolCall {
+            id: "call-bash".to_string(),
+            name: "bash".to_string(),
+            arguments: json!({ "command": command }),
+            thought_signature: None,
+        };
+
+        runtime.block_on(async {
+            let (output, is_error) = agent.execute_tool(&bash("rm -rf build"), &on_event).await;
+            assert!(is_error);
+            assert!(matches!(
+                output.content.as_slice(),
+                [ContentBlock::Text(text)] if text.text.contains("permission rule `bash \"rm *\"`")
+            ));
+
+            // Allowed and default-ask calls both reach the (unregistered) tool.
+            let _ = agent.execute_tool(&bash("git status"), &on_event).await;
+            let _ = agent.execute_tool(&bash("ls"), &on_event).await;
+        });
+
+        assert_eq!(*asked.lock().unwrap(), vec![json!("ls")]);
+    }
+
+    #[test]
+    fn tool_hooks_block_rewrite_and_add_context() {
+        use crate::hooks::HookConfig;
+
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let mut agent = Agent::new(Arc::new(SingleShotProvider), tools, AgentConfig::default());
+        let hook = |event, command: &str| HookConfig {
+            event,
+            matcher: Some("bash".to_string()),
+            command: command.to_string(),
+            timeout_ms: None,
+        };
+        agent.set_hooks(Hooks::new(
+            vec![
+                hook(
+                    HookEvent::PreToolUse,
+                    r#"grep -q '"rm ' && echo 'no deletes' >&2 && exit 2; echo '{"arguments": {"command": "ls -la"}}'"#,
+                ),
+                hook(HookEvent::PostToolUse, "echo 'checked by post hook'"),
+            ],
+            Path::new("."),
+        ));
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let asked_by_approver = Arc::clone(&asked);
+        let approver: ToolApprover = Arc::new(move |request: ToolApprovalRequest| {
+            asked_by_approver
+                .lock()
+                .unwrap()
+                .push(request.args["command"].clone());
+            Box::pin(async { ToolApprovalDecision::Allow })
+        });
+        agent.set_tool_approver(Some(approver));
+
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(|_| {});
+        let bash = |command: &str| ToolCall {
+            id: "call-bash".to_string(),
+            name: "bash".to_string(),
+            arguments: json!({ "command": command }),
+            thought_signature: None,
+        };
+
+        runtime.block_on(async {
+            let (output, is_error) = agent.execute_tool(&bash("rm -rf build"), &on_event).await;
+            assert!(is_error);
+            assert!(blocks_text(&output.content).contains("Tool execution blocked: no deletes"));
+
+            let (output, _) = agent.execute_tool(&bash("ls"), &on_event).await;
+            assert!(blocks_text(&output.content).ends_with("checked by post hook"));
+        });
+
+        // The approver sees the arguments the hook rewrote.
+        assert_eq!(*asked.lock().unwrap(), vec![json!("ls -la")]);
+    }
+
+    #[test]
+    fn reviewed_file_change_reports_reverted_hunks_to_the_model() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let temp_dir = tempfile::tempdir().expect("tempdir");
+
+        let tools = ToolRegistry::new(&["write", "bash"], temp_dir.path(), None);
+        let mut agent = Agent::new(Arc::new(SingleShotProvider), tools, AgentConfig::default());
+        let approver: ToolApprover = Arc::new(|_request: ToolApprovalRequest| {
+            Box::pin(async { ToolApprovalDecision::Review })
+        });
+        agent.set_tool_approver(Some(approver));
+        let reviewed = Arc::new(Mutex::new(Vec::new()));
+        let reviewed_by_reviewer = Arc::clone(&reviewed);
+        let reviewer: HunkReviewer = Arc::new(move |request: HunkReviewRequest| {
+            reviewed_by_reviewer.lock().unwrap().push(request.path);
+            Box::pin(async { Some("User rejected hunk 1, which was reverted.".to_string()) })
+        });
+        agent.set_hunk_reviewer(Some(reviewer));
+
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(|_| {});
+        let call = |name: &str, arguments: Value| ToolCall {
+            id: format!("call-{name}"),
+            name: name.to_string(),
+            arguments,
+            thought_signature: None,
+        };
+
+        runtime.block_on(async {
+            let (output, is_error) = agent
+                .execute_tool(
+                    &call("write", json!({ "path": "notes.txt", "content": "hi\n" })),
+                    &on_event,
+                )
+                .await;
+            assert!(!is_error);
+            assert!(matches!(
+                output.content.last(),
+                Some(ContentBlock::Text(text)) if text.text.contains("hunk 1, which was reverted")
+            ));
+
+            // `bash` has no hunks to review; `Review` simply allows it.
+            let (_, is_error) = agent
+                .execute_tool(&call("bash", json!({ "command": "true" })), &on_event)
+                .await;
+            assert!(!is_error);
+        });
+
+        assert_eq!(*reviewed.lock().unwrap(), vec!["notes.txt".to_string()]);
+    }
+
+    /// Answers summarization requests with `SUMMARY`; otherwise calls a tool with a nearly
+    /// full context once, then finishes.
+    fn context_filling_provider() -> ScriptedProvider {
+        ScriptedProvider::new(|call, context| {
+            let summarizing = context
+                .system_prompt
+                .as_deref()
+                .is_some_and(|prompt| prompt.contains("summarization"));
+            if summarizing {
+                return Ok(assistant_message("SUMMARY"));
+            }
+            if call > 0 {
+                return Ok(assistant_message("done"));
+            }
+            let mut message = assistant_message("");
+            message.content = vec![ContentBlock::ToolCall(ToolCall {
+                id: "call-ls".to_string(),
+                name: "ls".to_string(),
+                arguments: json!({}),
+                thought_signature: None,
+            })];
+            message.usage.input = 90_000;
+            message.stop_reason = StopReason::ToolUse;
+            Ok(message)
+        })
+    }
+
+    fn message_text(message: &Message) -> String {
+        let blocks = match message {
+            Message::User(user) => match &user.content {
+                UserContent::Text(text) => return text.clone(),
+                UserContent::Blocks(blocks) => blocks.as_slice(),
+            },
+            Message::Assistant(assistant) => assistant.content.as_slice(),
+            _ => &[],
+        };
+        blocks
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn auto_compaction_summarizes_history_before_the_next_call() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+
+        let provider = Arc::new(context_filling_provider());
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        // No API key: providers that read one from the environment or OAuth still compact.
+        let mut agent = Agent::new(provider.clone(), tools, AgentConfig::default());
+        agent.set_auto_compaction(Some(AutoCompaction {
+            context_window: 100_000,
+            settings: ResolvedCompactionSettings {
+                enabled: true,
+                reserve_tokens: 16_384,
+                keep_recent_tokens: 10,
+            },
+        }));
+
+        let mut session = Session::in_memory();
+        session.append_model_message(Message::User(UserMessage {
+            content: UserContent::Text("first task".to_string()),
+            timestamp: 0,
+        }));
+        session.append_model_message(Message::Assistant(assistant_message("done first")));
+        agent.replace_messages(session.to_messages_for_current_path());
+
+        let compactions = Arc::new(Mutex::new(Vec::new()));
+        let compactions_capture = Arc::clone(&compactions);
+        runtime.block_on(async {
+            let message = agent
+                .run("second task", move |event| {
+                    if let AgentEvent::AutoCompaction {
+                        messages_compacted, ..
+                    } = event
+                    {
+                        compactions_capture.lock().unwrap().push(messages_compacted);
+                    }
+                })
+                .await
+                .expect("run");
+            assert_eq!(message_text(&Message::Assistant(message)), "done");
+        });
+
+        assert_eq!(compactions.lock().unwrap().len(), 1);
+        let contexts = provider.contexts.lock().unwrap();
+        let last_context = contexts.last().expect("second call");
+        assert!(message_text(&last_context[0]).contains("SUMMARY"));
+        assert!(last_context.len() < contexts[0].len() + 2);
+        drop(contexts);
+
+        // Every message is persisted, followed by the compaction that replaced the oldest.
+        agent.record_run(&mut session);
+        let persisted = session
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry, SessionEntry::Message(_)))
+            .count();
+        assert_eq!(persisted, 6);
+        assert!(
+            session
+                .entries
+                .iter()
+                .any(|entry| matches!(entry, SessionEntry::Compaction(_)))
+        );
+        let messages = session.to_messages_for_current_path();
+        assert!(message_text(&messages[0]).contains("SUMMARY"));
+        assert_eq!(message_text(messages.last().expect("last")), "done");
+        assert_eq!(messages.len(), agent.messages().len());
+    }
+
+    #[test]
+    fn tool_approval_rules_match_tool_or_tool_and_path() {
+        let request = ToolApprovalRequest {
+            tool_call_id: "call-1".to_string(),
+            tool_name: "write".to_string(),
+            args: json!({ "path": "a.txt" }),
+            path: Some("a.txt".to_string()),
+        };
+        assert!(request.matches_rule("write"));
+        assert!(request.matches_rule("write:a.txt"));
+        assert!(!request.matches_rule("write:b.txt"));
+        assert!(!request.matches_rule("edit:a.txt"));
+        assert_eq!(request.always_rule(), "write:a.txt");
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap_and_honors_retry_after() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(10),
+        };
+        assert_eq!(policy.delay(1, None, 1.0), Duration::from_secs(2));
+        assert_eq!(policy.delay(2, None, 1.0), Duration::from_secs(4));
+        assert_eq!(policy.delay(3, None, 0.0), Duration::from_secs(4));
+        assert_eq!(policy.delay(4, None, 1.0), Duration::from_secs(10));
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(8)), 0.5),
+            Duration::from_secs(8)
+        );
+    }
+
+    #[test]
+    fn overloaded_provider_is_retried_with_retry_events() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+
+        // Overloaded twice, then answers.
+        let provider = Arc::new(ScriptedProvider::new(|call, _| {
+            if call < 2 {
+                return Err(http_status_error("Test", 529, &[], "overloaded"));
+            }
+            Ok(assistant_message("recovered"))
+        }));
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let mut config = AgentConfig::default();
+        config.stream_options.api_key = Some("test-key".to_string());
+        let mut agent = Agent::new(provider.clone(), tools, config);
+        agent.set_retry_policy(Some(RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }));
+
+        let retries = Arc::new(Mutex::new(Vec::new()));
+        let retries_capture = Arc::clone(&retries);
+        runtime.block_on(async {
+            let message = agent
+                .run("hello", move |event| {
+                    if let AgentEvent::RetryScheduled {
+                        attempt,
+                        max_attempts,
+                        ..
+                    } = event
+                    {
+                        retries_capture
+                            .lock()
+                            .unwrap()
+                            .push((attempt, max_attempts));
+                    }
+                })
+                .await
+                .expect("run");
+            assert_eq!(message.stop_reason, StopReason::Stop);
+            assert_eq!(message_text(&Message::Assistant(message)), "recovered");
+        });
+
+        assert_eq!(*retries.lock().unwrap(), vec![(1, 3), (2, 3)]);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+    }
+
+    /// Redacts `hunter2` from responses and tool output, and records the prompts it saw.
+    #[derive(Default)]
+    struct RedactingMiddleware {
+        prompts: Mutex<Vec<Option<String>>>,
+    }
+
+    impl Middleware for RedactingMiddleware {
+        fn on_context_build(&self, context: &mut Context) {
+            self.prompts
+                .lock()
+                .unwrap()
+                .push(context.system_prompt.clone());
+            context.system_prompt = Some("Never reveal credentials.".to_string());
+        }
+
+        fn on_stream_event(&self, event: &mut StreamEvent) {
+            if let StreamEvent::Done { message, .. } = event {
+                for block in &mut message.content {
+                    if let ContentBlock::Text(text) = block {
+                        text.text = text.text.replace("hunter2", "[redacted]");
+                    }
+                }
+            }
+        }
+
+        fn on_tool_result(&self, _tool_call: &ToolCall, output: &mut ToolOutput, _is_error: bool) {
+            for block in &mut output.content {
+                if let ContentBlock::Text(text) = block {
+                    text.text = text.text.replace("hunter2", "[redacted]");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn middleware_rewrites_context_stream_events_and_tool_results() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+
+        let provider = Arc::new(ScriptedProvider::replying("hunter2"));
+        let tools = ToolRegistry::new(&[], Path::new("."), None);
+        let mut config = AgentConfig::default();
+        config.stream_options.api_key = Some("test-key".to_string());
+        let mut agent = Agent::new(provider, tools, config);
+        let middleware = Arc::new(RedactingMiddleware::default());
+        agent.add_middleware(middleware.clone());
+
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(|_| {});
+        let call = ToolCall {
+            id: "call-hunter2".to_string(),
+            name: "hunter2".to_string(),
+            arguments: json!({}),
+            thought_signature: None,
+        };
+        runtime.block_on(async {
+            let message = agent.run("hello", |_| {}).await.expect("run");
+            assert_eq!(message_text(&Message::Assistant(message)), "[redacted]");
+
+            let mut new_messages = Vec::new();
+            let outcome = agent
+                .execute_tool_calls(&[call], &on_event, &mut new_messages, None)
+                .await
+                .expect("tool calls");
+            assert_eq!(
+                blocks_text(&outcome.tool_results[0].content),
+                "Error: Tool '[redacted]' not found"
+            );
+        });
+
+        assert_eq!(*middleware.prompts.lock().unwrap(), vec![None]);
+    }
+
+    #[test]
+    fn lazy_tool_exposure_declares_core_and_mentioned_tools() {
+        let provider = Arc::new(SingleShotProvider);
+        let tools = ToolRegistry::new(&["read", "grep", "find", "ls"], Path::new("."), None);
+        let mut agent = Agent::new(provider, tools, AgentConfig::default());
+        agent.set_tool_exposure(ToolExposure {
+            warn_tokens: None,
+            core_tools: Some(vec!["read".to_string()]),
+        });
+        agent.add_message(Message::User(UserMessage {
+            content: UserContent::Text("use grep to find the handler".to_string()),
+            timestamp: 0,
+        }));
+
+        let context = agent.build_context();
+        let declared = context
+            .tools
+            .iter()
+            .map(|tool| tool.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(declared, vec!["read", "grep", "find"]);
+        let prompt = context.system_prompt.expect("deferred tools section");
+        assert!(prompt.contains("# More tools"));
+        assert!(prompt.contains(": ls."));
+
+        agent.set_tool_exposure(ToolExposure::default());
+        let context = agent.build_context();
+        assert_eq!(context.tools.len(), 4);
+        assert_eq!(context.system_prompt, None);
+    }
+
+    #[test]
+    fn context_strategies_trim_history_on_turn_boundaries() {
+        let provider = Arc::new(SingleShotProvider);
+        let mut agent = Agent::new(
+            provider,
+            ToolRegistry::from_tools(Vec::new()),
+            AgentConfig::default(),
+        );
+        for turn in 0..5 {
+            agent.add_message(Message::User(UserMessage {
+                content: UserContent::Text(format!("question {turn} {}", "x".repeat(40))),
+                timestamp: 0,
+            }));
+            agent.add_message(Message::Assistant(assistant_message(&format!(
+                "answer {turn}"
+            ))));
+        }
+        let sent = |agent: &Agent| {
+            agent
+                .build_context()
+                .messages
+                .iter()
+                .map(message_text)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sent(&agent).len(), 10);
+
+        agent.set_context_strategy(ContextStrategy::KeepFirstAndLast { first: 1, last: 2 });
+        let kept = sent(&agent);
+        assert_eq!(kept.len(), 6);
+        assert!(kept[0].starts_with("question 0"));
+        assert!(kept[2].starts_with("question 3"));
+        assert_eq!(kept[5], "answer 4");
+
+        // Each turn is ~22 estimated tokens, so 50 tokens fit the newest two turns.
+        agent.set_context_strategy(ContextStrategy::SlidingWindow { max_tokens: 50 });
+        let window = sent(&agent);
+        assert_eq!(window.len(), 4);
+        assert!(window[0].starts_with("question 3"));
+
+        // The current turn is sent even when it alone exceeds the window.
+        agent.set_context_strategy(ContextStrategy::SlidingWindow { max_tokens: 1 });
+        assert_eq!(sent(&agent).len(), 2);
+
+        let strategy: ContextStrategy =
+            serde_json::from_value(json!({ "type": "sliding-window", "maxTokens": 80_000 }))
+                .expect("strategy");
+        assert_eq!(
+            strategy,
+            ContextStrategy::SlidingWindow { max_tokens: 80_000 }
+        );
+    }
+
+    #[test]
+    fn oversized_tool_definitions_warn_once() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+
+        let provider = Arc::new(SingleShotProvider);
+        let tools = ToolRegistry::new(&["read", "bash"], Path::new("."), None);
+        let mut config = AgentConfig::default();
+        config.stream_options.api_key = Some("test-key".to_string());
+        let mut agent = Agent::new(provider, tools, config);
+        agent.set_tool_exposure(ToolExposure {
+            warn_tokens: Some(10),
+            core_tools: None,
+        });
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        runtime.block_on(async {
+            for prompt in ["first", "second"] {
+                let warnings_capture = Arc::clone(&warnings);
+                agent
+                    .run(prompt, move |event| {
+                        if let AgentEvent::ToolDefinitionsOversized {
+                            threshold,
+                            largest_tools,
+                            ..
+                        } = event
+                        {
+                            warnings_capture
+                                .lock()
+                                .unwrap()
+                                .push((threshold, largest_tools));
+                        }
+                    })
+                    .await
+                    .expect("run");
+            }
+        });
+
+        let warnings = warnings.lock().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].0, 10);
+        assert_eq!(warnings[0].1.len(), 2);
+    }
+
+    #[test]
+    fn plan_mode_runs_each_approved_step_and_records_progress() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let provider = crate::replay::ReplayProvider::new(
+            [
+                "Here is the plan:\n```json\n{\"steps\": [\"Say hi\", \"Say bye\"]}\n```",
+                "hi",
+                "bye",
+            ]
+            .map(assistant_message),
+        );
+        let tools = ToolRegistry::new(&["read"], Path::new("."), None);
+        let agent = Agent::new(Arc::new(provider), tools, AgentConfig::default());
+        let session = Arc::new(asupersync::sync::Mutex::new(Session::in_memory()));
+        let mut agent_session = AgentSession::new(agent, Arc::clone(&session), false);
+        let approver: PlanApprover = Arc::new(|plan| Box::pin(async move { Some(plan) }));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+
+        let last = runtime.block_on(async {
+            agent_session
+                .run_planned("greet".to_string(), approver, None, move |event| {
+                    if let AgentEvent::PlanReady { .. } | AgentEvent::PlanStep { .. } = event {
+                        sink.lock()
+                            .unwrap()
+                            .push(serde_json::to_value(&event).unwrap());
+                    }
+                })
+                .await
+                .expect("run_planned")
+        });
+
+        assert_eq!(blocks_text(&last.content), "bye");
+        assert_eq!(
+            agent_session.agent.tools.tools().len(),
+            1,
+            "tools come back after planning"
+        );
+        let events = events.lock().unwrap();
+        assert_eq!(events[0]["type"], "plan_ready");
+        assert_eq!(events[0]["plan"]["steps"][1], "Say bye");
+        let statuses = events[1..]
+            .iter()
+            .map(|event| format!("{} {}", event["index"], event["status"].as_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, ["0 started", "0 done", "1 started", "1 done"]);
+
+        let session = session.try_lock().expect("session");
+        let custom = session
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                SessionEntry::Custom(custom) => Some(custom.custom_type.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            custom,
+            [
+                PLAN_ENTRY_TYPE,
+                PLAN_STEP_ENTRY_TYPE,
+                PLAN_STEP_ENTRY_TYPE,
+                PLAN_STEP_ENTRY_TYPE,
+                PLAN_STEP_ENTRY_TYPE
+            ]
+        );
+    }
+
+    #[test]
+    fn fork_branches_a_copy_of_the_session_at_an_entry() {
         let runtime = RuntimeBuilder::current_thread()
             .build()
             .expect("runtime build");
-        let handle = runtime.handle();
-
-        let provider = Arc::new(SingleShotProvider);
-        let tools = ToolRegistry::new(&[], Path::new("."), None);
-        let agent = Agent::new(provider, tools, AgentConfig::default());
-        let session = Session::in_memory();
-        let mut agent_session = AgentSession::new(agent, session, false);
-
-        let events: Arc<Mutex<Vec<AgentEvent>>> = Arc::new(Mutex::new(Vec::new()));
-        let events_capture = Arc::clone(&events);
+        let provider =
+            crate::replay::ReplayProvider::new(["one", "two", "three"].map(assistant_message));
+        let agent = Agent::new(
+            Arc::new(provider),
+            ToolRegistry::from_tools(Vec::new()),
+            AgentConfig::default(),
+        );
+        let session = Arc::new(asupersync::sync::Mutex::new(Session::in_memory()));
+        let mut agent_session = AgentSession::new(agent, Arc::clone(&session), false);
 
-        let join = handle.spawn(async move {
+        let fork = runtime.block_on(async {
             agent_session
-                .run_text("hello".to_string(), move |event| {
-                    events_capture.lock().unwrap().push(event);
-                })
+                .run_text("first".to_string(), |_| {})
                 .await
-                .expect("run_text")
+                .expect("first");
+            agent_session
+                .run_text("second".to_string(), |_| {})
+                .await
+                .expect("second");
+            let first_reply = session
+                .try_lock()
+                .expect("session")
+                .entries_for_current_path()[1]
+                .base_id()
+                .cloned()
+                .expect("entry id");
+
+            let mut fork = agent_session
+                .fork(&first_reply, ToolRegistry::from_tools(Vec::new()))
+                .await
+                .expect("fork");
+            assert!(
+                agent_session
+                    .fork("missing", ToolRegistry::from_tools(Vec::new()))
+                    .await
+                    .is_err()
+            );
+            let reply = fork
+                .run_text("instead".to_string(), |_| {})
+                .await
+                .expect("fork run");
+            assert_eq!(blocks_text(&reply.content), "three");
+            fork
         });
 
-        runtime.block_on(async move {
-            let message = join.await;
-            assert_eq!(message.stop_reason, StopReason::Stop);
-
-            let events = events.lock().unwrap();
-            let turn_start_indices = events
-                .iter()
-                .enumerate()
-                .filter_map(|(idx, event)| {
-                    matches!(event, AgentEvent::TurnStart { .. }).then_some(idx)
-                })
-                .collect::<Vec<_>>();
-            let turn_end_indices = events
+        let texts = |messages: &[Message]| {
+            messages
                 .iter()
-                .enumerate()
-                .filter_map(|(idx, event)| {
-                    matches!(event, AgentEvent::TurnEnd { .. }).then_some(idx)
+                .map(|message| match message {
+                    Message::User(user) => match &user.content {
+                        UserContent::Text(text) => text.clone(),
+                        UserContent::Blocks(blocks) => blocks_text(blocks),
+                    },
+                    Message::Assistant(assistant) => blocks_text(&assistant.content),
+                    _ => String::new(),
                 })
-                .collect::<Vec<_>>();
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            texts(fork.agent.messages()),
+            ["first", "one", "instead", "three"]
+        );
+        let original = session.try_lock().expect("session");
+        assert_eq!(
+            texts(&original.to_messages_for_current_path()),
+            ["first", "one", "second", "two"]
+        );
+        let forked = fork.session.try_lock().expect("fork session");
+        assert_ne!(forked.header.id, original.header.id);
+        assert_eq!(forked.entries.len(), original.entries.len() + 2);
+    }
 
-            assert_eq!(turn_start_indices.len(), 1);
-            assert_eq!(turn_end_indices.len(), 1);
-            assert!(turn_start_indices[0] < turn_end_indices[0]);
+    #[test]
+    fn unnamed_sessions_are_titled_once_after_the_first_reply() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let provider = crate::replay::ReplayProvider::new(
+            ["Found it.", "\"Fix the flaky parser test.\"", "Done."].map(assistant_message),
+        );
+        let mut agent = Agent::new(
+            Arc::new(provider),
+            ToolRegistry::from_tools(Vec::new()),
+            AgentConfig::default(),
+        );
+        agent.enable_auto_title(None);
+        let session = Arc::new(asupersync::sync::Mutex::new(Session::in_memory()));
+        let mut agent_session = AgentSession::new(agent, Arc::clone(&session), false);
 
-            let assistant_message_end = events
-                .iter()
-                .enumerate()
-                .find_map(|(idx, event)| match event {
-                    AgentEvent::MessageEnd {
-                        message: Message::Assistant(_),
-                    } => Some(idx),
-                    _ => None,
-                })
-                .expect("assistant message end");
+        runtime.block_on(async {
+            agent_session
+                .run_text("why does the parser test fail?".to_string(), |_| {})
+                .await
+                .expect("first");
+            agent_session
+                .run_text("fix it".to_string(), |_| {})
+                .await
+                .expect("second");
+        });
 
-            assert!(assistant_message_end < turn_end_indices[0]);
+        let session = session.try_lock().expect("session");
+        assert_eq!(
+            session.get_name().as_deref(),
+            Some("Fix the flaky parser test")
+        );
+        assert_eq!(session.to_messages_for_current_path().len(), 4);
+    }
 
-            let (message_is_assistant, tool_results_empty) = {
-                let turn_end_event = &events[turn_end_indices[0]];
-                assert!(
-                    matches!(turn_end_event, AgentEvent::TurnEnd { .. }),
-                    "Expected TurnEnd event, got {turn_end_event:?}"
-                );
-                match turn_end_event {
-                    AgentEvent::TurnEnd {
-                        message,
-                        tool_results,
-                        ..
-                    } => (
-                        matches!(message, Message::Assistant(_)),
-                        tool_results.is_empty(),
-                    ),
-                    _ => (false, false),
-                }
-            };
-            drop(events);
-            assert!(message_is_assistant);
-            assert!(tool_results_empty);
+    #[test]
+    fn auto_titles_leave_out_incognito_messages() {
+        let mut agent = Agent::new(
+            Arc::new(crate::replay::ReplayProvider::new(Vec::new())),
+            ToolRegistry::from_tools(Vec::new()),
+            AgentConfig::default(),
+        );
+        agent.enable_auto_title(None);
+        let user = |text: &str| SessionMessage::User {
+            content: UserContent::Text(text.to_string()),
+            timestamp: Some(0),
+        };
+        let mut session = Session::in_memory();
+        session.set_incognito(true);
+        session.append_message(user("my token is hunter2"));
+        session.append_message(SessionMessage::Assistant {
+            message: assistant_message("Noted."),
+        });
+        session.set_incognito(false);
+        assert!(agent.take_title_request(&session).is_none());
+
+        session.append_message(user("rename the parser module"));
+        session.append_message(SessionMessage::Assistant {
+            message: assistant_message("Renamed."),
         });
+        let request = agent.take_title_request(&session).expect("title request");
+        assert_eq!(request.messages.len(), 2);
+        assert!(
+            request
+                .messages
+                .iter()
+                .all(|message| !format!("{message:?}").contains("hunter2"))
+        );
+    }
+
+    #[test]
+    fn plan_parses_json_or_a_numbered_list() {
+        assert_eq!(
+            Plan::parse("{\"steps\": [\"Read the code\", \" \", \"Fix it\"]}"),
+            Some(Plan {
+                steps: vec!["Read the code".to_string(), "Fix it".to_string()],
+            })
+        );
+        assert_eq!(
+            Plan::parse("Plan:\n1. Read the code\n2) Fix it\n\nThat's all.").map(|plan| plan.steps),
+            Some(vec!["Read the code".to_string(), "Fix it".to_string()])
+        );
+        assert_eq!(Plan::parse("I will just do it."), None);
     }
 }
 
@@ -2633,6 +6312,45 @@ impl AgentSession {
         Ok(())
     }
 
+    /// Branch the conversation at `entry_id` into a new, independent session.
+    ///
+    /// The fork gets a copy of the session tree moved to `entry_id` with
+    /// [`Session::create_branch_from`], saved as a new session file that names this one as its
+    /// parent, and a new agent on the same provider and configuration whose history is the
+    /// path to that entry. `tools` equips the new agent. This session is left as it was.
+    pub async fn fork(&self, entry_id: &str, tools: ToolRegistry) -> Result<Self> {
+        let cx = crate::agent_cx::AgentCx::for_request();
+        let session = self
+            .session
+            .lock(cx.cx())
+            .await
+            .map_err(|e| Error::session(e.to_string()))?;
+        let mut forked = if self.save_enabled {
+            Session::create_with_dir(session.session_dir.clone())
+        } else {
+            Session::in_memory()
+        };
+        forked.header.parent_session = session.path.as_ref().map(|p| p.display().to_string());
+        forked.header.provider.clone_from(&session.header.provider);
+        forked.header.model_id.clone_from(&session.header.model_id);
+        forked
+            .header
+            .thinking_level
+            .clone_from(&session.header.thinking_level);
+        forked.entries.clone_from(&session.entries);
+        drop(session);
+        if !forked.create_branch_from(entry_id) {
+            return Err(Error::session(format!("Entry not found: {entry_id}")));
+        }
+
+        let mut agent = Agent::new(self.agent.provider(), tools, self.agent.config.clone());
+        agent.stream_options_mut().session_id = Some(forked.header.id.clone());
+        agent.replace_messages(forked.to_messages_for_current_path());
+        let mut fork = Self::new(agent, Arc::new(Mutex::new(forked)), self.save_enabled);
+        fork.persist_session().await?;
+        Ok(fork)
+    }
+
     pub async fn run_text(
         &mut self,
         input: String,
@@ -2664,6 +6382,17 @@ impl AgentSession {
         }
     }
 
+    /// Like [`Self::run_text_with_abort`], with the events as a stream instead of a callback.
+    pub fn run_stream(
+        &mut self,
+        input: String,
+        abort: Option<AbortSignal>,
+    ) -> AgentEventStream<'_> {
+        AgentEventStream::new(move |on_event| {
+            self.run_text_with_abort(input, abort, move |event| on_event(event))
+        })
+    }
+
     pub async fn run_with_content(
         &mut self,
         content: Vec<ContentBlock>,
@@ -2768,9 +6497,8 @@ impl AgentSession {
             session.to_messages_for_current_path()
         };
         self.agent.replace_messages(history);
-        let start_len = self.agent.messages().len();
         let result = self.agent.run_with_abort(input, abort, on_event).await?;
-        self.persist_new_messages(start_len).await?;
+        self.persist_new_messages().await?;
         Ok(result)
     }
 
@@ -2790,30 +6518,152 @@ impl AgentSession {
             session.to_messages_for_current_path()
         };
         self.agent.replace_messages(history);
-        let start_len = self.agent.messages().len();
         let result = self
             .agent
             .run_with_content_with_abort(content, abort, on_event)
             .await?;
-        self.persist_new_messages(start_len).await?;
+        self.persist_new_messages().await?;
         Ok(result)
     }
 
-    async fn persist_new_messages(&mut self, start_len: usize) -> Result<()> {
-        let new_messages = self.agent.messages()[start_len..].to_vec();
-        {
+    /// Plan, then execute: a turn without tools proposes a [`Plan`], `approver` accepts
+    /// (possibly edited) or rejects it, and each approved step then runs as its own prompt.
+    /// The plan and the progress of every step are recorded as custom session entries.
+    pub async fn run_planned(
+        &mut self,
+        input: String,
+        approver: PlanApprover,
+        abort: Option<AbortSignal>,
+        on_event: impl Fn(AgentEvent) + Send + Sync + 'static,
+    ) -> Result<AssistantMessage> {
+        let on_event: Arc<dyn Fn(AgentEvent) + Send + Sync> = Arc::new(on_event);
+        let forward = |on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>| {
+            let on_event = Arc::clone(on_event);
+            move |event| on_event(event)
+        };
+
+        let tools = std::mem::replace(&mut self.agent.tools, ToolRegistry::from_tools(Vec::new()));
+        let hosted_tools = std::mem::take(&mut self.agent.hosted_tools);
+        let planning = self
+            .run_agent_with_text(
+                format!("{PLANNING_PROMPT}\n\nTask: {input}"),
+                abort.clone(),
+                forward(&on_event),
+            )
+            .await;
+        self.agent.tools = tools;
+        self.agent.hosted_tools = hosted_tools;
+        let planning = planning?;
+        if matches!(
+            planning.stop_reason,
+            StopReason::Error | StopReason::Aborted
+        ) {
+            return Ok(planning);
+        }
+        let Some(plan) = Plan::parse(&blocks_text(&planning.content)) else {
+            return Err(Error::validation(
+                "The model did not answer with a plan; rephrase the task or run it without plan mode",
+            ));
+        };
+
+        on_event(AgentEvent::PlanReady { plan: plan.clone() });
+        let approved = approver(plan.clone()).await;
+        self.append_plan_entry(
+            PLAN_ENTRY_TYPE,
+            json!({
+                "task": input,
+                "steps": approved.as_ref().unwrap_or(&plan).steps,
+                "approved": approved.is_some(),
+            }),
+        )
+        .await?;
+        let Some(plan) = approved else {
+            return Ok(planning);
+        };
+
+        let total = plan.steps.len();
+        let mut last = planning;
+        for (index, step) in plan.steps.iter().enumerate() {
+            self.record_plan_step(index, step, PlanStepStatus::Started, &on_event)
+                .await?;
+            last = self
+                .run_agent_with_text(
+                    format!(
+                        "Carry out step {} of {total} of the approved plan: {step}\n\
+                         Do only this step.",
+                        index + 1
+                    ),
+                    abort.clone(),
+                    forward(&on_event),
+                )
+                .await?;
+            let failed = matches!(last.stop_reason, StopReason::Error | StopReason::Aborted);
+            let status = if failed {
+                PlanStepStatus::Failed
+            } else {
+                PlanStepStatus::Done
+            };
+            self.record_plan_step(index, step, status, &on_event)
+                .await?;
+            if failed {
+                break;
+            }
+        }
+        Ok(last)
+    }
+
+    async fn record_plan_step(
+        &self,
+        index: usize,
+        step: &str,
+        status: PlanStepStatus,
+        on_event: &Arc<dyn Fn(AgentEvent) + Send + Sync>,
+    ) -> Result<()> {
+        self.append_plan_entry(
+            PLAN_STEP_ENTRY_TYPE,
+            json!({ "index": index, "step": step, "status": status }),
+        )
+        .await?;
+        on_event(AgentEvent::PlanStep {
+            index,
+            step: step.to_string(),
+            status,
+        });
+        Ok(())
+    }
+
+    async fn append_plan_entry(&self, custom_type: &str, data: Value) -> Result<()> {
+        let cx = crate::agent_cx::AgentCx::for_request();
+        let mut session = self
+            .session
+            .lock(cx.cx())
+            .await
+            .map_err(|e| Error::session(e.to_string()))?;
+        session.append_custom_entry(custom_type.to_string(), Some(data));
+        if self.save_enabled {
+            session.save().await?;
+        }
+        Ok(())
+    }
+
+    async fn persist_new_messages(&mut self) -> Result<()> {
+        let title_request = {
             let cx = crate::agent_cx::AgentCx::for_request();
             let mut session = self
                 .session
                 .lock(cx.cx())
                 .await
                 .map_err(|e| Error::session(e.to_string()))?;
-            for message in new_messages {
-                session.append_model_message(message);
-            }
+            self.agent.record_run(&mut session);
             if self.save_enabled {
                 session.save().await?;
             }
+            self.agent.take_title_request(&session)
+        };
+        if let Some(request) = title_request {
+            if let Err(err) = request.apply(&self.session, self.save_enabled).await {
+                tracing::warn!("Failed to generate a session title: {err}");
+            }
         }
         Ok(())
     }
@@ -2823,6 +6673,143 @@ impl AgentSession {
 // Helper Functions
 // ============================================================================
 
+/// Expand `{{name}}` placeholders in the system prompt against the current directory.
+fn expand_system_prompt(template: &str) -> String {
+    if !template.contains("{{") {
+        return template.to_string();
+    }
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let global_dir = crate::config::Config::global_dir();
+    expand_prompt_variables(template, |name| prompt_variable(name, &cwd, &global_dir))
+}
+
+/// Replace each `{{name}}` in `template` with `value(name)`; unknown names are left as written.
+fn expand_prompt_variables(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let placeholder = &rest[start..start + 2 + end + 2];
+        match value(after[..end].trim()) {
+            Some(text) => expanded.push_str(&text),
+            None => expanded.push_str(placeholder),
+        }
+        rest = &after[end + 2..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Runtime values for system prompt placeholders:
+/// - `cwd`: the working directory
+/// - `date`: today's local date, `YYYY-MM-DD`
+/// - `git_branch`: the checked-out branch, or the short commit when detached
+/// - `os`: the operating system and architecture
+/// - `project_context`: the `PI.md`/`AGENTS.md`/`CLAUDE.md` files of the project
+fn prompt_variable(name: &str, cwd: &Path, global_dir: &Path) -> Option<String> {
+    match name {
+        "cwd" => Some(cwd.display().to_string()),
+        "date" => Some(chrono::Local::now().format("%Y-%m-%d").to_string()),
+        "git_branch" => Some(git_branch(cwd).unwrap_or_else(|| "(none)".to_string())),
+        "os" => Some(format!(
+            "{} ({})",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )),
+        "project_context" => {
+            Some(crate::app::project_context_section(cwd, global_dir).unwrap_or_default())
+        }
+        _ => None,
+    }
+}
+
+/// Branch checked out in the repository holding `dir`, read from `HEAD` without running git.
+fn git_branch(dir: &Path) -> Option<String> {
+    let dot_git = dir
+        .ancestors()
+        .map(|dir| dir.join(".git"))
+        .find(|path| path.exists())?;
+    let git_dir = if dot_git.is_file() {
+        // A worktree or submodule: `.git` holds `gitdir: <path>`.
+        let contents = std::fs::read_to_string(&dot_git).ok()?;
+        let target = PathBuf::from(contents.strip_prefix("gitdir:")?.trim());
+        dot_git.parent()?.join(target)
+    } else {
+        dot_git
+    };
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            Some(
+                reference
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(reference)
+                    .to_string(),
+            )
+        }
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// Milliseconds since `started`, for span durations.
+fn elapsed_ms(started: Instant) -> u64 {
+    u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Fill in the cost of `usage` from `pricing` unless the provider already reported one.
+/// Gateway-reported spend is kept alongside the local figures.
+fn price_usage(usage: &mut Usage, pricing: &ModelCost) {
+    if usage.cost.total > 0.0 {
+        usage.cost.cache_savings = pricing.cache_savings(usage.cache_read, usage.cache_write);
+        return;
+    }
+    let gateway = usage.cost.gateway;
+    let gateway_budget_remaining = usage.cost.gateway_budget_remaining;
+    usage.cost = pricing.usage_cost(usage);
+    usage.cost.gateway = gateway;
+    usage.cost.gateway_budget_remaining = gateway_budget_remaining;
+}
+
+/// `90s`, or `250ms` for limits that are not whole seconds.
+fn format_timeout(timeout: Duration) -> String {
+    if timeout.subsec_nanos() == 0 {
+        format!("{}s", timeout.as_secs())
+    } else {
+        format!("{}ms", timeout.as_millis())
+    }
+}
+
+/// Sleep for `delay`; `false` when `abort` fires first.
+async fn sleep_unless_aborted(delay: Duration, abort: Option<&AbortSignal>) -> bool {
+    let sleep_fut = asupersync::time::sleep(asupersync::time::wall_now(), delay).fuse();
+    let Some(signal) = abort else {
+        sleep_fut.await;
+        return true;
+    };
+    let abort_fut = signal.wait().fuse();
+    futures::pin_mut!(sleep_fut, abort_fut);
+    matches!(
+        futures::future::select(sleep_fut, abort_fut).await,
+        futures::future::Either::Left(_)
+    )
+}
+
+/// A value in `0..=1` for backoff jitter, from the process's per-hasher random keys.
+fn jitter_fraction() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    f64::from(u32::try_from(bits >> 32).unwrap_or(0)) / f64::from(u32::MAX)
+}
+
 /// Extract tool calls from content blocks.
 fn extract_tool_calls(content: &[ContentBlock]) -> Vec<ToolCall> {
     content
@@ -2976,4 +6963,131 @@ mod tests {
         assert_eq!(remaining.len(), 1);
         assert_user_text(&remaining[0], "s2");
     }
+
+    fn error_output(text: &str) -> ToolOutput {
+        ToolOutput {
+            content: vec![ContentBlock::Text(TextContent::new(text))],
+            details: None,
+            is_error: true,
+        }
+    }
+
+    #[test]
+    fn tool_error_diagnostic_explains_exit_code_and_remaining_retries() {
+        let output = error_output(
+            "Error: Tool error: bash: sh: foo: not found\n\nCommand exited with code 127",
+        );
+        let diagnostic = tool_error_diagnostic("bash", &output, 1, 2);
+        assert!(diagnostic.contains("PI_TOOL_001"), "{diagnostic}");
+        assert!(diagnostic.contains("Exit code 127: command not found"));
+        assert!(diagnostic.contains("Suggestion: Check PATH"));
+        assert!(diagnostic.contains("(2 of 2 retries left)"));
+
+        let exhausted = tool_error_diagnostic("bash", &output, 3, 2);
+        assert!(exhausted.contains("failed 3 times in a row"), "{exhausted}");
+        assert!(!exhausted.contains("retries left"));
+    }
+
+    #[test]
+    fn long_tool_output_is_truncated_with_a_continuation_note() {
+        let call = ToolCall {
+            id: "call-read".to_string(),
+            name: "read".to_string(),
+            arguments: json!({ "path": "big.txt", "offset": 10 }),
+            thought_signature: None,
+        };
+        let mut output = ToolOutput {
+            content: vec![ContentBlock::Text(TextContent::new(
+                "one\ntwo\nthree\nfour",
+            ))],
+            details: Some(json!({ "lines": 4 })),
+            is_error: false,
+        };
+        truncate_tool_output(&call, &mut output, 8);
+        assert!(matches!(
+            output.content.as_slice(),
+            [ContentBlock::Text(text)]
+                if text.text == "one\ntwo\n\n[Output truncated, 11 more bytes, use offset=12 to continue]"
+        ));
+        assert_eq!(
+            output.details,
+            Some(json!({ "lines": 4, "fullOutput": "one\ntwo\nthree\nfour" }))
+        );
+
+        let mut short = error_output("fits");
+        truncate_tool_output(&call, &mut short, 8);
+        assert_eq!(short.details, None);
+    }
+
+    #[test]
+    fn truncation_leaves_non_text_blocks_in_place() {
+        let call = ToolCall {
+            id: "call-bash".to_string(),
+            name: "bash".to_string(),
+            arguments: json!({ "command": "render" }),
+            thought_signature: None,
+        };
+        let image = ContentBlock::Image(ImageContent {
+            data: "AAAA".to_string(),
+            mime_type: "image/png".to_string(),
+        });
+        let mut output = ToolOutput {
+            content: vec![
+                ContentBlock::Text(TextContent::new("ab")),
+                image.clone(),
+                ContentBlock::Text(TextContent::new("one\ntwo\nthree")),
+                ContentBlock::Text(TextContent::new("tail")),
+            ],
+            details: None,
+            is_error: false,
+        };
+        truncate_tool_output(&call, &mut output, 10);
+
+        assert_eq!(output.content.len(), 3);
+        assert!(matches!(&output.content[0], ContentBlock::Text(text) if text.text == "ab"));
+        assert!(matches!(&output.content[1], ContentBlock::Image(_)));
+        assert!(matches!(
+            &output.content[2],
+            ContentBlock::Text(text)
+                if text.text == "one\ntwo\n\n[Output truncated, 11 more bytes not shown]"
+        ));
+    }
+
+    #[test]
+    fn record_tool_outcome_counts_consecutive_failures_per_tool() {
+        let config = AgentConfig {
+            tool_retry_diagnostics: Some(1),
+            ..AgentConfig::default()
+        };
+        let mut agent = Agent::new(
+            Arc::new(crate::replay::ReplayProvider::new([])),
+            ToolRegistry::from_tools(Vec::new()),
+            config,
+        );
+
+        let mut first = error_output("Error: boom");
+        agent.record_tool_outcome("bash", &mut first, true);
+        assert_eq!(first.content.len(), 2);
+        let mut second = error_output("Error: boom");
+        agent.record_tool_outcome("bash", &mut second, true);
+        let ContentBlock::Text(text) = &second.content[1] else {
+            panic!("expected diagnostic text");
+        };
+        assert!(text.text.contains("failed 2 times in a row"));
+
+        // Success resets the streak; aborted or non-failing results get no diagnostic.
+        let mut ok = ToolOutput {
+            content: Vec::new(),
+            details: None,
+            is_error: false,
+        };
+        agent.record_tool_outcome("bash", &mut ok, false);
+        assert!(ok.content.is_empty());
+        let mut third = error_output("Error: boom");
+        agent.record_tool_outcome("bash", &mut third, true);
+        let ContentBlock::Text(text) = &third.content[1] else {
+            panic!("expected diagnostic text");
+        };
+        assert!(text.text.contains("(1 of 1 retries left)"));
+    }
 }
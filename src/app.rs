@@ -136,8 +136,6 @@ pub fn build_system_prompt(
     let custom_prompt = resolve_prompt_input(cli.system_prompt.as_deref(), "system prompt");
     let append_prompt =
         resolve_prompt_input(cli.append_system_prompt.as_deref(), "append system prompt");
-    let context_files = load_project_context_files(cwd, global_dir);
-
     let mut prompt =
         custom_prompt.unwrap_or_else(|| default_system_prompt(enabled_tools, package_dir));
 
@@ -146,11 +144,11 @@ pub fn build_system_prompt(
         prompt.push_str(&append_prompt);
     }
 
-    if !context_files.is_empty() {
-        prompt.push_str("\n\n# Project Context\n\n");
-        prompt.push_str("Project-specific instructions and guidelines:\n\n");
-        for file in &context_files {
-            let _ = write!(prompt, "## {}\n\n{}\n\n", file.path, file.content);
+    // A prompt that places `{{project_context}}` itself gets it expanded on each request.
+    if !prompt.contains("{{project_context}}") {
+        if let Some(section) = project_context_section(cwd, global_dir) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&section);
         }
     }
 
@@ -275,6 +273,23 @@ fn default_system_prompt(enabled_tools: &[&str], package_dir: &Path) -> String {
     )
 }
 
+/// The `# Project Context` prompt section built from the global context file and those in `cwd`
+/// and its ancestors, outermost first. `None` when there are no context files.
+pub fn project_context_section(cwd: &Path, global_dir: &Path) -> Option<String> {
+    use std::fmt::Write as _;
+
+    let context_files = load_project_context_files(cwd, global_dir);
+    if context_files.is_empty() {
+        return None;
+    }
+    let mut section =
+        String::from("# Project Context\n\nProject-specific instructions and guidelines:\n\n");
+    for file in &context_files {
+        let _ = write!(section, "## {}\n\n{}\n\n", file.path, file.content);
+    }
+    Some(section)
+}
+
 fn load_project_context_files(cwd: &Path, global_dir: &Path) -> Vec<ContextFile> {
     let mut context_files = Vec::new();
     let mut seen = HashSet::new();
@@ -305,7 +320,7 @@ fn load_project_context_files(cwd: &Path, global_dir: &Path) -> Vec<ContextFile>
 }
 
 fn load_context_file_from_dir(dir: &Path) -> Option<ContextFile> {
-    let candidates = ["AGENTS.md", "CLAUDE.md"];
+    let candidates = ["PI.md", "AGENTS.md", "CLAUDE.md"];
     for filename in candidates {
         let path = dir.join(filename);
         if path.exists() {
@@ -496,23 +511,13 @@ fn parse_thinking_level_opt(value: &str) -> Option<model::ThinkingLevel> {
 }
 
 fn last_model_from_session(session: &Session) -> Option<(String, String)> {
-    for entry in session.entries.iter().rev() {
-        if let crate::session::SessionEntry::ModelChange(change) = entry {
-            return Some((change.provider.clone(), change.model_id.clone()));
-        }
-    }
-    None
+    session.model_for_current_path()
 }
 
 fn last_thinking_level(session: &Session) -> Option<model::ThinkingLevel> {
-    for entry in session.entries.iter().rev() {
-        if let crate::session::SessionEntry::ThinkingLevelChange(change) = entry {
-            if let Some(level) = parse_thinking_level_opt(&change.thinking_level) {
-                return Some(level);
-            }
-        }
-    }
-    None
+    session
+        .thinking_level_for_current_path()
+        .and_then(|level| parse_thinking_level_opt(&level))
 }
 
 pub fn update_session_for_selection(session: &mut Session, selection: &ModelSelection) {
@@ -637,6 +642,7 @@ pub fn build_stream_options(
     };
 
     options.thinking_level = Some(selection.thinking_level);
+    options.cache_retention = config.prompt_cache();
 
     if let Some(budgets) = &config.thinking_budgets {
         let defaults = ThinkingBudgets::default();
@@ -579,6 +579,10 @@ const fn builtin_slash_commands() -> &'static [BuiltinSlashCommand] {
             name: "session",
             description: "Show session info",
         },
+        BuiltinSlashCommand {
+            name: "stats",
+            description: "Show totals for a branch",
+        },
         BuiltinSlashCommand {
             name: "settings",
             description: "Show current settings summary",
@@ -591,13 +595,17 @@ const fn builtin_slash_commands() -> &'static [BuiltinSlashCommand] {
             name: "resume",
             description: "Pick and resume a previous session",
         },
+        BuiltinSlashCommand {
+            name: "sessions",
+            description: "Switch to another session for this project",
+        },
         BuiltinSlashCommand {
             name: "new",
             description: "Start a new session",
         },
         BuiltinSlashCommand {
             name: "copy",
-            description: "Copy last assistant message to clipboard",
+            description: "Copy last assistant message (or `code` for its last code block)",
         },
         BuiltinSlashCommand {
             name: "name",
@@ -615,6 +623,38 @@ const fn builtin_slash_commands() -> &'static [BuiltinSlashCommand] {
             name: "tree",
             description: "Show session branch tree summary",
         },
+        BuiltinSlashCommand {
+            name: "timeline",
+            description: "Browse session entries; inspect, jump to, or branch from one",
+        },
+        BuiltinSlashCommand {
+            name: "branch",
+            description: "Branch the session from a previous entry",
+        },
+        BuiltinSlashCommand {
+            name: "label",
+            description: "Label the current entry as a checkpoint",
+        },
+        BuiltinSlashCommand {
+            name: "labels",
+            description: "List labeled checkpoints",
+        },
+        BuiltinSlashCommand {
+            name: "goto",
+            description: "Switch to a labeled checkpoint",
+        },
+        BuiltinSlashCommand {
+            name: "incognito",
+            description: "Keep new messages in memory only",
+        },
+        BuiltinSlashCommand {
+            name: "pin",
+            description: "Keep a file's contents in every request",
+        },
+        BuiltinSlashCommand {
+            name: "pins",
+            description: "List or unpin pinned files",
+        },
         BuiltinSlashCommand {
             name: "fork",
             description: "Branch from a previous user message",
@@ -631,6 +671,34 @@ const fn builtin_slash_commands() -> &'static [BuiltinSlashCommand] {
             name: "share",
             description: "Export to a temp HTML file and show path",
         },
+        BuiltinSlashCommand {
+            name: "system",
+            description: "Show or edit the system prompt",
+        },
+        BuiltinSlashCommand {
+            name: "tools",
+            description: "List tools or enable/disable one",
+        },
+        BuiltinSlashCommand {
+            name: "save",
+            description: "Checkpoint the conversation and worktree",
+        },
+        BuiltinSlashCommand {
+            name: "restore",
+            description: "Return conversation and files to a checkpoint",
+        },
+        BuiltinSlashCommand {
+            name: "attach",
+            description: "Attach files matching a glob to the next message",
+        },
+        BuiltinSlashCommand {
+            name: "note",
+            description: "Add a note that is never sent to the model",
+        },
+        BuiltinSlashCommand {
+            name: "notes",
+            description: "List this session's notes",
+        },
     ]
 }
 
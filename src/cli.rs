@@ -2,6 +2,12 @@
 
 use clap::{Parser, Subcommand};
 
+/// Tools enabled when neither `--tools` nor a settings `tool_profile` picks them.
+pub const DEFAULT_TOOLS: &str = "read,bash,edit,write";
+
+/// Tools available to `pi ask`: reading and searching only.
+pub const ASK_TOOLS: &str = "read,grep,find,ls";
+
 /// Pi - AI coding agent CLI
 #[derive(Parser, Debug)]
 #[allow(clippy::struct_excessive_bools)] // CLI flags are naturally boolean
@@ -14,6 +20,16 @@ use clap::{Parser, Subcommand};
   pi -r                                Resume from session picker
   pi -p \"what is 2+2\"                 Print mode (non-interactive)
   pi --model claude-opus-4 \"help\"     Use specific model
+
+Exit codes:
+  0    success
+  1    unclassified failure
+  2    invalid command-line usage
+  3    provider authentication failed or API key missing
+  4    budget exceeded (e.g. tool iteration limit)
+  5    tool failure
+  6    validation or configuration error
+  130  aborted (Ctrl+C)
 ")]
 pub struct Cli {
     // === Help & Version ===
@@ -44,7 +60,7 @@ pub struct Cli {
     pub thinking: Option<String>,
 
     // === System Prompt ===
-    /// Override system prompt
+    /// Override system prompt (text or file path; `{{cwd}}`-style placeholders are expanded)
     #[arg(long)]
     pub system_prompt: Option<String>,
 
@@ -69,6 +85,10 @@ pub struct Cli {
     #[arg(long)]
     pub session_dir: Option<String>,
 
+    /// Start a new session from ~/.pi/agent/templates/<NAME>.json
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["continue", "resume", "session"])]
+    pub template: Option<String>,
+
     /// Don't save session (ephemeral)
     #[arg(long)]
     pub no_session: bool,
@@ -82,17 +102,29 @@ pub struct Cli {
     #[arg(short = 'p', long)]
     pub print: bool,
 
+    /// With --print: show a step-by-step plan for approval before doing the work
+    #[arg(long)]
+    pub plan: bool,
+
+    /// Show the tool calls the model makes without running them
+    #[arg(long)]
+    pub dry_run: bool,
+
     /// Force verbose startup
     #[arg(long)]
     pub verbose: bool,
 
+    /// Export tracing spans to this OTLP/HTTP collector (builds with the `otlp` feature)
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
     // === Tools ===
     /// Disable all built-in tools
     #[arg(long)]
     pub no_tools: bool,
 
     /// Specific tools to enable (comma-separated: read,bash,edit,write,grep,find,ls)
-    #[arg(long, default_value = "read,bash,edit,write")]
+    #[arg(long, default_value = DEFAULT_TOOLS)]
     pub tools: String,
 
     // === Extensions ===
@@ -158,9 +190,19 @@ pub struct Cli {
 
 #[cfg(test)]
 mod tests {
-    use super::Cli;
+    use super::{Cli, Commands, SessionsCommand, SummaryFormat};
     use clap::Parser;
 
+    #[test]
+    fn parse_template_flag_rejects_resuming_a_session() {
+        let cli = Cli::parse_from(["pi", "--template", "review"]);
+        assert_eq!(cli.template.as_deref(), Some("review"));
+        assert!(Cli::try_parse_from(["pi", "--template", "review", "--continue"]).is_err());
+        assert!(
+            Cli::try_parse_from(["pi", "--template", "review", "--session", "a.jsonl"]).is_err()
+        );
+    }
+
     #[test]
     fn parse_resource_flags_and_mode() {
         let cli = Cli::parse_from([
@@ -198,6 +240,222 @@ mod tests {
         assert_eq!(cli.file_args(), vec!["a.txt", "b.md"]);
         assert_eq!(cli.message_args(), vec!["hello", "world"]);
     }
+
+    #[test]
+    fn tool_profile_applies_only_without_explicit_tools() {
+        let profile = vec!["read".to_string(), "grep".to_string()];
+        let cli = Cli::parse_from(["pi"]);
+        assert_eq!(
+            cli.enabled_tools_with_profile(Some(&profile)),
+            vec!["read", "grep"]
+        );
+        assert_eq!(
+            cli.enabled_tools_with_profile(None),
+            vec!["read", "bash", "edit", "write"]
+        );
+
+        let cli = Cli::parse_from(["pi", "--tools", "ls"]);
+        assert_eq!(cli.enabled_tools_with_profile(Some(&profile)), vec!["ls"]);
+        let cli = Cli::parse_from(["pi", "--no-tools"]);
+        assert!(cli.enabled_tools_with_profile(Some(&profile)).is_empty());
+
+        let cli = Cli::parse_from(["pi", "config", "--explain"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config { explain: true })
+        ));
+    }
+
+    #[test]
+    fn ask_runs_read_only_print_mode_without_a_session() {
+        let mut cli = Cli::parse_from(["pi", "ask", "where", "is", "@src/main.rs", "parsed?"]);
+        assert!(cli.apply_ask_command());
+        assert!(cli.command.is_none());
+        assert!(cli.print);
+        assert!(cli.no_session);
+        assert_eq!(cli.enabled_tools(), vec!["read", "grep", "find", "ls"]);
+        assert_eq!(cli.file_args(), vec!["src/main.rs"]);
+        assert_eq!(cli.message_args(), vec!["where", "is", "parsed?"]);
+        // A tool profile from settings does not widen the read-only set.
+        let profile = vec!["bash".to_string()];
+        assert_eq!(cli.enabled_tools_with_profile(Some(&profile)).len(), 4);
+
+        assert!(cli.no_extensions);
+
+        let mut cli = Cli::parse_from(["pi", "-e", "ext.js", "ask", "why?"]);
+        assert!(cli.apply_ask_command());
+        assert!(cli.extension.is_empty());
+
+        let mut cli = Cli::parse_from(["pi", "ask", "--save", "why?"]);
+        assert!(cli.apply_ask_command());
+        assert!(!cli.no_session);
+
+        let mut cli = Cli::parse_from(["pi", "list"]);
+        assert!(!cli.apply_ask_command());
+        assert!(matches!(cli.command, Some(Commands::List)));
+    }
+
+    #[test]
+    fn parse_sessions_import() {
+        let cli = Cli::parse_from(["pi", "sessions", "import", "--from-markdown", "notes.md"]);
+        let Some(Commands::Sessions {
+            command:
+                SessionsCommand::Import {
+                    from_markdown,
+                    from_claude_code,
+                    name,
+                },
+        }) = cli.command
+        else {
+            panic!("expected sessions import");
+        };
+        assert_eq!(from_markdown.as_deref(), Some("notes.md"));
+        assert_eq!(from_claude_code, None);
+        assert_eq!(name, None);
+
+        assert!(Cli::try_parse_from(["pi", "sessions", "import"]).is_err());
+        let cli = Cli::parse_from([
+            "pi",
+            "sessions",
+            "export",
+            "abc123",
+            "--to-claude-code",
+            "out.jsonl",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Sessions {
+                command: SessionsCommand::Export { .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_sessions_bundle_and_unbundle() {
+        let cli = Cli::parse_from([
+            "pi",
+            "sessions",
+            "bundle",
+            "abc123",
+            "--pin",
+            "NOTES.md",
+            "--pin",
+            "plan.md",
+            "-o",
+            "task.pibundle",
+        ]);
+        let Some(Commands::Sessions {
+            command:
+                SessionsCommand::Bundle {
+                    session,
+                    output,
+                    pins,
+                },
+        }) = cli.command
+        else {
+            panic!("expected sessions bundle");
+        };
+        assert_eq!(session, "abc123");
+        assert_eq!(output.as_deref(), Some("task.pibundle"));
+        assert_eq!(pins, vec!["NOTES.md", "plan.md"]);
+
+        let cli = Cli::parse_from(["pi", "sessions", "unbundle", "task.pibundle"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Sessions {
+                command: SessionsCommand::Unbundle {
+                    overwrite: false,
+                    ..
+                },
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_sessions_repair() {
+        let cli = Cli::parse_from(["pi", "sessions", "repair", "s.jsonl"]);
+        let Some(Commands::Sessions {
+            command: SessionsCommand::Repair { session },
+        }) = cli.command
+        else {
+            panic!("expected sessions repair");
+        };
+        assert_eq!(session, "s.jsonl");
+    }
+
+    #[test]
+    fn parse_sessions_prune_needs_a_criterion() {
+        let cli = Cli::parse_from([
+            "pi",
+            "sessions",
+            "prune",
+            "--older-than-days",
+            "30",
+            "--archive",
+            "old",
+        ]);
+        let Some(Commands::Sessions {
+            command:
+                SessionsCommand::Prune {
+                    older_than_days,
+                    min_messages,
+                    archive,
+                    dry_run,
+                    ..
+                },
+        }) = cli.command
+        else {
+            panic!("expected sessions prune");
+        };
+        assert_eq!(older_than_days, Some(30));
+        assert_eq!(min_messages, None);
+        assert_eq!(archive.as_deref(), Some("old"));
+        assert!(!dry_run);
+
+        assert!(Cli::try_parse_from(["pi", "sessions", "prune", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn parse_replay_with_speed() {
+        let cli = Cli::parse_from(["pi", "replay", "s.jsonl", "--speed", "4"]);
+        let Some(Commands::Replay {
+            session,
+            realtime,
+            speed,
+            max_pause,
+        }) = cli.command
+        else {
+            panic!("expected replay");
+        };
+        assert_eq!(session, "s.jsonl");
+        assert!(!realtime);
+        assert_eq!(speed, Some(4.0));
+        assert!((max_pause - 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn parse_sessions_summarize() {
+        let cli = Cli::parse_from(["pi", "sessions", "summarize", "abc123"]);
+        let Some(Commands::Sessions {
+            command: SessionsCommand::Summarize { session, format },
+        }) = cli.command
+        else {
+            panic!("expected sessions summarize");
+        };
+        assert_eq!(session, "abc123");
+        assert_eq!(format, SummaryFormat::Pr);
+
+        let cli = Cli::parse_from(["pi", "sessions", "summarize", "s.jsonl", "--format", "json"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Sessions {
+                command: SessionsCommand::Summarize {
+                    format: SummaryFormat::Json,
+                    ..
+                },
+            })
+        ));
+    }
 }
 
 /// Package management subcommands
@@ -231,7 +489,208 @@ pub enum Commands {
     List,
 
     /// Open configuration UI
-    Config,
+    Config {
+        /// Show the effective default model, thinking level, and tool profile with their sources
+        #[arg(long)]
+        explain: bool,
+    },
+
+    /// Run a YAML suite of prompts with assertions against one or more models
+    Eval {
+        /// Suite file (YAML or JSON)
+        suite: String,
+        /// Model to evaluate as provider/model (repeatable; overrides the suite's list)
+        #[arg(long = "model", action = clap::ArgAction::Append)]
+        models: Vec<String>,
+        /// Replay provider responses from this directory, recording any that are missing
+        #[arg(long)]
+        cassettes: Option<String>,
+        /// Emit the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Answer a question about the codebase with read-only tools and print the answer
+    Ask {
+        /// Keep the session on disk instead of discarding it
+        #[arg(long)]
+        save: bool,
+        /// The question, plus optional @file references
+        #[arg(required = true, trailing_var_arg = true)]
+        question: Vec<String>,
+    },
+
+    /// Play a saved session back in the terminal
+    Replay {
+        /// Session file path or session ID (a unique prefix is enough)
+        session: String,
+        /// Pause between entries as they were originally spaced
+        #[arg(long)]
+        realtime: bool,
+        /// Play the original timing this many times faster (implies --realtime)
+        #[arg(long, value_name = "FACTOR")]
+        speed: Option<f64>,
+        /// Longest pause between two entries, in seconds
+        #[arg(long, value_name = "SECS", default_value_t = 3.0)]
+        max_pause: f64,
+    },
+
+    /// Check that each configured provider endpoint is reachable
+    Doctor {
+        /// Emit the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Inspect the stable error codes printed with CLI and RPC errors
+    Errors {
+        #[command(subcommand)]
+        command: ErrorsCommand,
+    },
+
+    /// Maintain the session index used by the picker and search
+    Index {
+        #[command(subcommand)]
+        command: IndexCommand,
+    },
+
+    /// Work with saved sessions
+    Sessions {
+        #[command(subcommand)]
+        command: SessionsCommand,
+    },
+}
+
+/// `pi sessions` subcommands
+#[derive(Subcommand, Debug)]
+pub enum SessionsCommand {
+    /// Summarize a session's transcript and file changes
+    Summarize {
+        /// Session file path or session ID (a unique prefix is enough)
+        session: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = SummaryFormat::Pr)]
+        format: SummaryFormat,
+    },
+    /// Create a session from a conversation written elsewhere, to continue it in pi
+    #[command(group(
+        clap::ArgGroup::new("source")
+            .required(true)
+            .args(["from_markdown", "from_claude_code"])
+    ))]
+    Import {
+        /// Markdown file with `### User` / `### Assistant` sections
+        #[arg(long, value_name = "FILE")]
+        from_markdown: Option<String>,
+        /// Claude Code transcript (`~/.claude/projects/<project>/<session>.jsonl`)
+        #[arg(long, value_name = "FILE")]
+        from_claude_code: Option<String>,
+        /// Session name (defaults to the transcript's summary or the file name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Show what two branches of a session added since they diverged
+    Diff {
+        /// Session file path or session ID (a unique prefix is enough)
+        session: String,
+        /// Leaf entry ID or label of the first branch
+        left: String,
+        /// Leaf entry ID or label of the second branch
+        right: String,
+        /// Emit the diff as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a session out in another tool's format
+    Export {
+        /// Session file path or session ID (a unique prefix is enough)
+        session: String,
+        /// Claude Code JSONL transcript to write
+        #[arg(long, value_name = "FILE")]
+        to_claude_code: String,
+    },
+    /// Pack a session, the files it changed, and pinned files into one portable archive
+    Bundle {
+        /// Session file path or session ID (a unique prefix is enough)
+        session: String,
+        /// Archive to write (defaults to `<session id>.pibundle` in the current directory)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+        /// Also include this file, relative to the session's directory (repeatable)
+        #[arg(long = "pin", value_name = "PATH", action = clap::ArgAction::Append)]
+        pins: Vec<String>,
+    },
+    /// Restore a bundle into the current directory as a new session
+    Unbundle {
+        /// Archive written by `pi sessions bundle`
+        bundle: String,
+        /// Replace local files whose contents differ from the bundled ones
+        #[arg(long)]
+        overwrite: bool,
+    },
+    /// Drop a session file's corrupted lines, keeping what can be recovered (backs up first)
+    Repair {
+        /// Session file path or session ID (a unique prefix is enough)
+        session: String,
+    },
+    /// Delete or archive old, short, or excess sessions and update the session index
+    #[command(group(
+        clap::ArgGroup::new("criteria")
+            .required(true)
+            .multiple(true)
+            .args(["older_than_days", "min_messages", "max_total_mb"])
+    ))]
+    Prune {
+        /// Sessions not modified for this many days
+        #[arg(long, value_name = "DAYS")]
+        older_than_days: Option<u64>,
+        /// Sessions with fewer messages than this
+        #[arg(long, value_name = "N")]
+        min_messages: Option<u64>,
+        /// Prune the least recently used sessions until the rest fit in this many megabytes
+        #[arg(long, value_name = "MB")]
+        max_total_mb: Option<u64>,
+        /// Move pruned sessions into this directory as `.jsonl.zst` instead of deleting them
+        #[arg(long, value_name = "DIR")]
+        archive: Option<String>,
+        /// List what would be pruned without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Output formats for `pi sessions summarize`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryFormat {
+    /// Markdown PR description (pipe into `gh pr create --body-file -`)
+    Pr,
+    /// The same fields as JSON, including a suggested title
+    Json,
+}
+
+/// `pi errors` subcommands
+#[derive(Subcommand, Debug)]
+pub enum ErrorsCommand {
+    /// List every error code with its summary
+    List {
+        /// Emit a JSON array instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// `pi index` subcommands
+#[derive(Subcommand, Debug)]
+pub enum IndexCommand {
+    /// Prune stale rows, analyze, and compact the index (safe while sessions are active)
+    Vacuum {
+        /// Rows checked per batch before yielding the index lock
+        #[arg(long, default_value_t = 200)]
+        batch_size: usize,
+        /// Pause between batches in milliseconds
+        #[arg(long, default_value_t = 50)]
+        pause_ms: u64,
+    },
 }
 
 impl Cli {
@@ -261,4 +720,35 @@ impl Cli {
             self.tools.split(',').map(str::trim).collect()
         }
     }
+
+    /// Rewrite `pi ask` as a one-shot print run with [`ASK_TOOLS`], no extensions, and no saved
+    /// session.
+    ///
+    /// Returns `false` (leaving `self` untouched) for any other subcommand.
+    pub fn apply_ask_command(&mut self) -> bool {
+        let Some(Commands::Ask { save, question }) = self.command.take() else {
+            return false;
+        };
+        self.print = true;
+        self.no_tools = false;
+        self.tools = ASK_TOOLS.to_string();
+        // Extension tools can write files or run commands; keep the profile read-only.
+        self.no_extensions = true;
+        self.extension.clear();
+        self.no_session = self.no_session || !save;
+        self.args.extend(question);
+        true
+    }
+
+    /// Enabled tools, using the settings `tool_profile` unless `--tools`/`--no-tools` was given.
+    ///
+    /// `--tools` counts as given only when it differs from the built-in default list.
+    pub fn enabled_tools_with_profile<'a>(&'a self, profile: Option<&'a [String]>) -> Vec<&'a str> {
+        match profile {
+            Some(tools) if !self.no_tools && self.tools == DEFAULT_TOOLS => {
+                tools.iter().map(String::as_str).collect()
+            }
+            _ => self.enabled_tools(),
+        }
+    }
 }
@@ -0,0 +1,514 @@
+//! Heuristic language detection for code blocks the model left untagged.
+//!
+//! Detection looks at the code first: a shebang, JSON that parses, then keyword markers
+//! scored per language. When the code is too short to tell, or two languages score the
+//! same, the languages of files the session touched break the tie. The result tags the
+//! fence for the markdown renderer and picks a file extension for `/copy code`.
+
+use std::path::Path;
+
+/// Languages pi can name, with their file extensions (the first is the preferred one).
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["rs"]),
+    ("python", &["py", "pyi"]),
+    ("typescript", &["ts", "tsx", "mts", "cts"]),
+    ("javascript", &["js", "mjs", "cjs", "jsx"]),
+    ("go", &["go"]),
+    ("cpp", &["cpp", "cc", "cxx", "hpp", "hh"]),
+    ("c", &["c", "h"]),
+    ("java", &["java"]),
+    ("ruby", &["rb"]),
+    ("php", &["php"]),
+    ("bash", &["sh", "bash", "zsh"]),
+    ("sql", &["sql"]),
+    ("html", &["html", "htm"]),
+    ("css", &["css"]),
+    ("json", &["json"]),
+    ("yaml", &["yaml", "yml"]),
+    ("toml", &["toml"]),
+    ("diff", &["diff", "patch"]),
+    ("markdown", &["md"]),
+];
+
+/// Substrings that suggest a language; each marker found adds one point.
+const MARKERS: &[(&str, &[&str])] = &[
+    (
+        "rust",
+        &[
+            "fn ",
+            "let mut ",
+            "impl ",
+            "pub fn ",
+            "use std::",
+            "::new(",
+            "-> Result<",
+            "#[derive",
+            "&self",
+            "println!",
+            "Some(",
+            "Ok(",
+            "=> {",
+            "mod ",
+            "&str",
+        ],
+    ),
+    (
+        "python",
+        &[
+            "def ",
+            "import ",
+            "from ",
+            "self.",
+            "elif ",
+            "print(",
+            "__init__",
+            "None",
+            "True:",
+            "    return",
+            "lambda ",
+            "):\n",
+        ],
+    ),
+    (
+        "javascript",
+        &[
+            "function ",
+            "const ",
+            "=> ",
+            "console.log",
+            "require(",
+            "module.exports",
+            "export default",
+            "let ",
+            "===",
+            "async ",
+            "await ",
+        ],
+    ),
+    (
+        "go",
+        &[
+            "package ",
+            "func ",
+            ":= ",
+            "fmt.",
+            "import (",
+            "err != nil",
+            "go func",
+            "chan ",
+        ],
+    ),
+    (
+        "c",
+        &[
+            "#include",
+            "int main(",
+            "printf(",
+            "->",
+            "malloc(",
+            "NULL",
+            "void ",
+        ],
+    ),
+    (
+        "java",
+        &[
+            "public class",
+            "public static void",
+            "System.out",
+            "private ",
+            "@Override",
+            "new ArrayList",
+        ],
+    ),
+    (
+        "ruby",
+        &["def ", "end\n", "puts ", "require '", "do |", ".each"],
+    ),
+    (
+        "bash",
+        &[
+            "$ ", "echo ", "export ", "sudo ", "cd ", "cargo ", "npm ", "git ", "if [", "fi\n",
+            "done\n", "| grep", "&& ",
+        ],
+    ),
+    (
+        "sql",
+        &[
+            "SELECT ",
+            "INSERT INTO",
+            "CREATE TABLE",
+            "UPDATE ",
+            " FROM ",
+            "WHERE ",
+            "JOIN ",
+        ],
+    ),
+    (
+        "html",
+        &["<div", "<html", "</", "<!DOCTYPE", "<span", "<body"],
+    ),
+    (
+        "css",
+        &["{\n", "color:", "margin:", "padding:", "display:", "px;"],
+    ),
+];
+
+/// Markers that turn a `javascript` guess into `typescript`.
+const TYPESCRIPT_MARKERS: &[&str] = &[
+    ": string",
+    ": number",
+    ": boolean",
+    "interface ",
+    "export type ",
+    " as const",
+    "<T>",
+];
+
+/// Markers that turn a `c` guess into `cpp`.
+const CPP_MARKERS: &[&str] = &[
+    "std::",
+    "nullptr",
+    "template<",
+    "#include <iostream>",
+    "class ",
+];
+
+/// Points a language needs before content alone decides.
+const MIN_SCORE: usize = 2;
+
+/// The language for a file path's extension, e.g. `src/main.rs` → `rust`.
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(language, _)| *language)
+}
+
+/// The preferred file extension for a language name or alias (`rs`, `py`, `ts`, ...).
+pub fn extension_for(language: &str) -> Option<&'static str> {
+    let language = language.trim().to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(name, extensions)| *name == language || extensions.contains(&language.as_str()))
+        .map(|(_, extensions)| extensions[0])
+}
+
+/// Languages of `paths`, most common first (ties keep the order they first appear in).
+pub fn languages_for_paths<'a>(paths: impl IntoIterator<Item = &'a str>) -> Vec<&'static str> {
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for language in paths.into_iter().filter_map(language_for_path) {
+        match counts.iter_mut().find(|(known, _)| *known == language) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((language, 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1));
+    counts.into_iter().map(|(language, _)| language).collect()
+}
+
+fn shebang_language(first_line: &str) -> Option<&'static str> {
+    let interpreter = first_line.strip_prefix("#!")?;
+    if interpreter.contains("python") {
+        Some("python")
+    } else if interpreter.contains("node") {
+        Some("javascript")
+    } else if interpreter.contains("ruby") {
+        Some("ruby")
+    } else if ["bash", "sh", "zsh"]
+        .iter()
+        .any(|shell| interpreter.trim_end().ends_with(shell))
+    {
+        Some("bash")
+    } else {
+        None
+    }
+}
+
+/// Whether `code` is a unified diff.
+fn is_diff(code: &str) -> bool {
+    code.starts_with("diff --git")
+        || (code.contains("\n@@ ") && (code.starts_with("--- ") || code.contains("\n+++ ")))
+}
+
+/// Whether the code has a `[section]` and every other line is `key = value`.
+fn is_toml(code: &str) -> bool {
+    let mut lines = code
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    code.lines()
+        .any(|line| line.starts_with('[') && line.trim_end().ends_with(']'))
+        && lines.all(|line| {
+            (line.starts_with('[') && line.ends_with(']'))
+                || line
+                    .split_once(" = ")
+                    .is_some_and(|(key, _)| !key.is_empty() && !key.contains(' '))
+        })
+}
+
+/// Whether every non-empty, non-comment line is a YAML key or list item.
+fn is_yaml(code: &str) -> bool {
+    let mut lines = code
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .peekable();
+    lines.peek().is_some()
+        && lines.all(|line| {
+            let line = line.trim();
+            !line.ends_with(';')
+                && !line.ends_with('{')
+                && (line.starts_with("- ")
+                    || line.split_once(':').is_some_and(|(key, _)| {
+                        !key.is_empty()
+                            && key
+                                .chars()
+                                .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.'))
+                    }))
+        })
+}
+
+/// Guess the language of `code`. `context` lists the languages of files the session touched,
+/// most relevant first; it breaks ties and decides for code too short to score.
+pub fn detect_language(code: &str, context: &[&'static str]) -> Option<&'static str> {
+    let code = code.trim_matches('\n');
+    let first_line = code.lines().next()?.trim();
+    if let Some(language) = shebang_language(first_line) {
+        return Some(language);
+    }
+    if first_line.starts_with("<?php") {
+        return Some("php");
+    }
+    if is_diff(code) {
+        return Some("diff");
+    }
+    if (code.starts_with('{') || code.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(code).is_ok()
+    {
+        return Some("json");
+    }
+    if is_toml(code) {
+        return Some("toml");
+    }
+    if is_yaml(code) {
+        return Some("yaml");
+    }
+
+    let code_with_newline = format!("{code}\n");
+    let scores = MARKERS
+        .iter()
+        .map(|(language, markers)| {
+            let score = markers
+                .iter()
+                .filter(|marker| code_with_newline.contains(*marker))
+                .count();
+            (*language, score)
+        })
+        .collect::<Vec<_>>();
+    let best = scores.iter().map(|(_, score)| *score).max().unwrap_or(0);
+    let leaders = scores
+        .iter()
+        .filter(|(_, score)| *score == best)
+        .map(|(language, _)| *language)
+        .collect::<Vec<_>>();
+
+    let from_context = context.iter().copied().find(|language| {
+        let family = match *language {
+            "typescript" => "javascript",
+            "cpp" => "c",
+            other => other,
+        };
+        leaders.contains(&family)
+    });
+    let guess = if best >= MIN_SCORE && leaders.len() == 1 {
+        leaders[0]
+    } else if best >= MIN_SCORE {
+        from_context.unwrap_or(leaders[0])
+    } else {
+        return context.first().copied();
+    };
+
+    let refined = match guess {
+        "javascript"
+            if from_context == Some("typescript")
+                || TYPESCRIPT_MARKERS
+                    .iter()
+                    .any(|marker| code.contains(marker)) =>
+        {
+            "typescript"
+        }
+        "c" if from_context == Some("cpp")
+            || CPP_MARKERS.iter().any(|marker| code.contains(marker)) =>
+        {
+            "cpp"
+        }
+        other => other,
+    };
+    Some(refined)
+}
+
+/// A fenced code block found in markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// The fence's info string; empty when the block is untagged.
+    pub language: String,
+    pub code: String,
+}
+
+/// Fenced code blocks in `markdown`, in order.
+pub fn code_blocks(markdown: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(&str, String, Vec<&str>)> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        match open.as_mut() {
+            Some((fence, _, lines)) => {
+                if trimmed.trim_end() == *fence {
+                    let (_, language, lines) = open.take().unwrap_or_default();
+                    blocks.push(CodeBlock {
+                        language,
+                        code: lines.join("\n"),
+                    });
+                } else {
+                    lines.push(line);
+                }
+            }
+            None => {
+                if let Some(fence) = ["```", "~~~"]
+                    .into_iter()
+                    .find(|fence| trimmed.starts_with(fence))
+                {
+                    let language = trimmed[fence.len()..].trim().to_string();
+                    open = Some((fence, language, Vec::new()));
+                }
+            }
+        }
+    }
+    blocks
+}
+
+/// Add a detected language to every untagged fence in `markdown` so the renderer can
+/// highlight it; blocks that cannot be identified are left as they are.
+pub fn tag_untagged_code_blocks(markdown: &str, context: &[&'static str]) -> String {
+    if !markdown.contains("```") && !markdown.contains("~~~") {
+        return markdown.to_string();
+    }
+    let mut languages = code_blocks(markdown)
+        .into_iter()
+        .map(|block| {
+            if block.language.is_empty() {
+                detect_language(&block.code, context)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>()
+        .into_iter();
+
+    let mut out = String::with_capacity(markdown.len() + 16);
+    let mut open_fence: Option<&str> = None;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match open_fence {
+            Some(fence) => {
+                if trimmed.trim_end() == fence {
+                    open_fence = None;
+                }
+                out.push_str(line);
+            }
+            None => {
+                let Some(fence) = ["```", "~~~"]
+                    .into_iter()
+                    .find(|fence| trimmed.starts_with(fence))
+                else {
+                    out.push_str(line);
+                    continue;
+                };
+                open_fence = Some(fence);
+                let detected = languages.next().flatten();
+                match detected {
+                    Some(language) if trimmed[fence.len()..].trim().is_empty() => {
+                        let indent = &line[..line.len() - trimmed.len()];
+                        out.push_str(indent);
+                        out.push_str(fence);
+                        out.push_str(language);
+                        if line.ends_with('\n') {
+                            out.push('\n');
+                        }
+                    }
+                    _ => out.push_str(line),
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_languages_from_content() {
+        let rust = "fn main() {\n    let mut total = 0;\n    println!(\"{total}\");\n}";
+        assert_eq!(detect_language(rust, &[]), Some("rust"));
+        let python = "def greet(name):\n    print(f\"hi {name}\")\n    return None";
+        assert_eq!(detect_language(python, &[]), Some("python"));
+        let typescript = "const add = (a: number, b: number) => a + b;\nexport default add;";
+        assert_eq!(detect_language(typescript, &[]), Some("typescript"));
+        assert_eq!(
+            detect_language("#!/usr/bin/env bash\nset -e", &[]),
+            Some("bash")
+        );
+        assert_eq!(detect_language("{\"a\": [1, 2]}", &[]), Some("json"));
+        assert_eq!(
+            detect_language("[package]\nname = \"pi\"\nedition = \"2024\"", &[]),
+            Some("toml")
+        );
+        assert_eq!(
+            detect_language("name: build\non:\n  - push", &[]),
+            Some("yaml")
+        );
+        assert_eq!(
+            detect_language("--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b", &[]),
+            Some("diff")
+        );
+    }
+
+    #[test]
+    fn session_files_decide_short_or_ambiguous_code() {
+        assert_eq!(detect_language("x = 1", &[]), None);
+        assert_eq!(detect_language("x = 1", &["python"]), Some("python"));
+        // A bare `let` could be Rust or JavaScript; a Rust project settles it.
+        let ambiguous = "let total = items.len();";
+        assert_eq!(detect_language(ambiguous, &["rust"]), Some("rust"));
+        assert_eq!(detect_language(ambiguous, &[]), None);
+    }
+
+    #[test]
+    fn tags_only_untagged_fences() {
+        let markdown = "Run:\n\n```\ncargo test && git status\n```\n\n```python\nx = 1\n```\n\n```\n???\n```\n";
+        assert_eq!(
+            tag_untagged_code_blocks(markdown, &[]),
+            "Run:\n\n```bash\ncargo test && git status\n```\n\n```python\nx = 1\n```\n\n```\n???\n```\n"
+        );
+        let blocks = code_blocks(markdown);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[1].language, "python");
+    }
+
+    #[test]
+    fn maps_paths_and_extensions() {
+        assert_eq!(language_for_path("src/main.rs"), Some("rust"));
+        assert_eq!(language_for_path("web/App.TSX"), Some("typescript"));
+        assert_eq!(language_for_path("Makefile"), None);
+        assert_eq!(extension_for("python"), Some("py"));
+        assert_eq!(extension_for("yml"), Some("yaml"));
+        assert_eq!(extension_for("brainfuck"), None);
+        assert_eq!(
+            languages_for_paths(["a.py", "src/lib.rs", "src/main.rs", "README"]),
+            vec!["rust", "python"]
+        );
+    }
+}
@@ -13,7 +13,10 @@ use crate::model::{
     Usage, UserContent, UserMessage,
 };
 use crate::provider::{Context, Provider, StreamOptions};
-use crate::session::{SessionEntry, SessionMessage, session_message_to_model};
+use crate::request_scheduler::{RequestPriority, acquire_global};
+use crate::session::{
+    EntryBase, MessageEntry, SessionEntry, SessionMessage, session_message_to_model,
+};
 use futures::StreamExt;
 use serde::Serialize;
 use serde_json::Value;
@@ -86,6 +89,39 @@ impl FileOperations {
     pub fn read_files(&self) -> impl Iterator<Item = &str> {
         self.read.iter().map(String::as_str)
     }
+
+    /// Collect file operations along a branch, including files carried forward by
+    /// pi-generated compactions.
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a SessionEntry>) -> Self {
+        let mut file_ops = Self::default();
+        for entry in entries {
+            match entry {
+                SessionEntry::Message(entry) => {
+                    extract_file_ops_from_message(&entry.message, &mut file_ops);
+                }
+                SessionEntry::Compaction(entry) if !entry.from_hook.unwrap_or(false) => {
+                    let Some(details) = entry.details.as_ref() else {
+                        continue;
+                    };
+                    for (key, set) in [
+                        ("readFiles", &mut file_ops.read),
+                        ("modifiedFiles", &mut file_ops.edited),
+                    ] {
+                        if let Some(items) = details.get(key).and_then(Value::as_array) {
+                            set.extend(items.iter().filter_map(Value::as_str).map(String::from));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        file_ops
+    }
+
+    /// Written or edited files, sorted.
+    pub fn modified_files(&self) -> Vec<String> {
+        compute_file_lists(self).1
+    }
 }
 
 fn extract_file_ops_from_message(message: &SessionMessage, file_ops: &mut FileOperations) {
@@ -219,7 +255,8 @@ fn estimate_context_tokens(messages: &[SessionMessage]) -> ContextUsageEstimate
     }
 }
 
-fn should_compact(
+/// Whether `context_tokens` leaves less than `reserve_tokens` of headroom in the window.
+pub fn should_compact(
     context_tokens: u64,
     context_window: u32,
     settings: &ResolvedCompactionSettings,
@@ -250,6 +287,7 @@ fn estimate_tokens(message: &SessionMessage) -> u64 {
                                 .map(|s| s.len())
                                 .unwrap_or_default();
                         }
+                        ContentBlock::HostedTool(hosted) => chars += hosted.block.to_string().len(),
                     }
                 }
             }
@@ -266,6 +304,7 @@ fn estimate_tokens(message: &SessionMessage) -> u64 {
                             .map(|s| s.len())
                             .unwrap_or_default();
                     }
+                    ContentBlock::HostedTool(hosted) => chars += hosted.block.to_string().len(),
                 }
             }
         }
@@ -281,6 +320,7 @@ fn estimate_tokens(message: &SessionMessage) -> u64 {
                             .map(|s| s.len())
                             .unwrap_or_default();
                     }
+                    ContentBlock::HostedTool(hosted) => chars += hosted.block.to_string().len(),
                 }
             }
         }
@@ -452,6 +492,13 @@ const UPDATE_SUMMARIZATION_PROMPT: &str = "The messages above are NEW conversati
 
 const TURN_PREFIX_SUMMARIZATION_PROMPT: &str = "This is the PREFIX of a turn that was too large to keep. The SUFFIX (recent work) is retained.\n\nSummarize the prefix to provide context for the retained suffix:\n\n## Original Request\n[What did the user ask for in this turn?]\n\n## Early Progress\n- [Key decisions and work done in the prefix]\n\n## Context for Suffix\n- [Information needed to understand the retained recent work]\n\nBe concise. Focus on what's needed to understand the kept suffix.";
 
+const TITLE_SYSTEM_PROMPT: &str = "You name conversations between a user and an AI coding assistant. Reply with the title only: no quotes, no trailing punctuation, no explanation.";
+
+const TITLE_PROMPT: &str = "Write a title of at most 6 words for the conversation above, describing the task the user is working on.";
+
+/// Most words kept from a generated session title.
+pub const SESSION_TITLE_MAX_WORDS: usize = 6;
+
 fn serialize_conversation(messages: &[Message]) -> String {
     let mut parts: Vec<String> = Vec::new();
 
@@ -515,6 +562,7 @@ fn serialize_conversation(messages: &[Message]) -> String {
                             );
                             tool_calls.push(format!("{}({args_str})", call.name));
                         }
+                        ContentBlock::HostedTool(hosted) => text_parts.push(hosted.summary()),
                         ContentBlock::Image(_) => {}
                     }
                 }
@@ -559,6 +607,7 @@ async fn complete_simple(
     api_key: &str,
     reserve_tokens: u32,
     max_tokens_factor: f64,
+    thinking_level: ThinkingLevel,
 ) -> Result<AssistantMessage> {
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
     let max_tokens = (f64::from(reserve_tokens) * max_tokens_factor).floor() as u32;
@@ -574,12 +623,14 @@ async fn complete_simple(
     };
 
     let options = StreamOptions {
-        api_key: Some(api_key.to_string()),
+        // An empty key leaves the provider to resolve credentials itself (environment, OAuth).
+        api_key: (!api_key.is_empty()).then(|| api_key.to_string()),
         max_tokens: Some(max_tokens),
-        thinking_level: Some(ThinkingLevel::High),
+        thinking_level: Some(thinking_level),
         ..Default::default()
     };
 
+    let _permit = acquire_global(provider.name(), RequestPriority::Background).await;
     let mut stream = provider.stream(&context, &options).await?;
     let mut final_message: Option<AssistantMessage> = None;
 
@@ -649,6 +700,7 @@ async fn generate_summary(
         api_key,
         settings.reserve_tokens,
         0.8,
+        ThinkingLevel::High,
     )
     .await?;
 
@@ -687,6 +739,7 @@ async fn generate_turn_prefix_summary(
         api_key,
         settings.reserve_tokens,
         0.5,
+        ThinkingLevel::High,
     )
     .await?;
 
@@ -817,6 +870,62 @@ pub fn prepare_compaction(
     })
 }
 
+/// Ask the model for a short title for a conversation (typically its first exchange).
+pub async fn generate_session_title(
+    messages: &[SessionMessage],
+    provider: Arc<dyn Provider>,
+    api_key: &str,
+) -> Result<String> {
+    let llm_messages = messages
+        .iter()
+        .filter_map(session_message_to_model)
+        .collect::<Vec<_>>();
+    let conversation_text = serialize_conversation(&llm_messages);
+    let prompt_text =
+        format!("<conversation>\n{conversation_text}\n</conversation>\n\n{TITLE_PROMPT}");
+
+    let assistant = complete_simple(
+        provider,
+        TITLE_SYSTEM_PROMPT,
+        prompt_text,
+        api_key,
+        256,
+        1.0,
+        ThinkingLevel::Off,
+    )
+    .await?;
+    let text = assistant
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    clean_session_title(&text).ok_or_else(|| Error::api("The model returned an empty title"))
+}
+
+/// The first non-empty line of a model's title reply, without a `Title:` prefix, quotes, or
+/// trailing punctuation, cut to [`SESSION_TITLE_MAX_WORDS`] words.
+pub fn clean_session_title(text: &str) -> Option<String> {
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .or_else(|| line.strip_prefix("title:"))
+        .unwrap_or(line);
+    let decoration = |c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '`' | '*' | '#');
+    let line = line
+        .trim_start_matches(decoration)
+        .trim_end_matches(|c: char| decoration(c) || matches!(c, '.' | '!' | ':' | ';' | ','));
+    let title = line
+        .split_whitespace()
+        .take(SESSION_TITLE_MAX_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
 pub async fn summarize_entries(
     entries: &[SessionEntry],
     provider: Arc<dyn Provider>,
@@ -915,6 +1024,59 @@ pub async fn compact(
     })
 }
 
+/// Result of [`compact_messages`]: the summary and where the kept messages start.
+#[derive(Debug, Clone)]
+pub struct MessageCompaction {
+    pub summary: String,
+    /// Index of the first message kept after the summary.
+    pub first_kept_index: usize,
+    pub tokens_before: u64,
+    pub details: CompactionDetails,
+}
+
+/// Compact an in-memory history the way [`prepare_compaction`] and [`compact`] compact a
+/// session path. Returns `None` when there is nothing before a valid cut point.
+pub async fn compact_messages(
+    messages: &[Message],
+    provider: Arc<dyn Provider>,
+    api_key: &str,
+    settings: ResolvedCompactionSettings,
+) -> Result<Option<MessageCompaction>> {
+    // Stand-in entries whose ids are the message indices.
+    let entries = messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            SessionEntry::Message(MessageEntry {
+                base: EntryBase::new(
+                    index.checked_sub(1).map(|p| p.to_string()),
+                    index.to_string(),
+                ),
+                message: SessionMessage::from(message.clone()),
+                redaction: None,
+            })
+        })
+        .collect::<Vec<_>>();
+    let Some(preparation) = prepare_compaction(&entries, settings) else {
+        return Ok(None);
+    };
+    let first_kept_index = preparation
+        .first_kept_entry_id
+        .parse::<usize>()
+        .map_err(|e| Error::session(format!("Compaction cut point: {e}")))?;
+    if first_kept_index == 0 {
+        return Ok(None);
+    }
+
+    let result = compact(preparation, provider, api_key, None).await?;
+    Ok(Some(MessageCompaction {
+        summary: result.summary,
+        first_kept_index,
+        tokens_before: result.tokens_before,
+        details: result.details,
+    }))
+}
+
 pub fn compaction_details_to_value(details: &CompactionDetails) -> Result<Value> {
     serde_json::to_value(details).map_err(|e| Error::session(format!("Compaction details: {e}")))
 }
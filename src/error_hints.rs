@@ -33,7 +33,9 @@ pub struct ErrorHint {
 pub fn hints_for_error(error: &Error) -> ErrorHint {
     match error {
         Error::Config(msg) => config_hints(msg),
-        Error::SessionNotFound { .. } | Error::Session(_) => session_hints(error),
+        Error::SessionNotFound { .. } | Error::SessionConflict { .. } | Error::Session(_) => {
+            session_hints(error)
+        }
         Error::Auth(msg) => auth_hints(msg),
         Error::Provider { message, .. } => provider_hints(message),
         Error::Tool { tool, message } => tool_hints(tool, message),
@@ -43,6 +45,7 @@ pub fn hints_for_error(error: &Error) -> ErrorHint {
         Error::Json(err) => json_hints(err),
         Error::Sqlite(err) => sqlite_hints(err),
         Error::Aborted => aborted_hints(),
+        Error::BudgetExceeded(_) => budget_hints(),
         Error::Api(msg) => api_hints(msg),
     }
 }
@@ -95,6 +98,11 @@ fn session_hints(error: &Error) -> ErrorHint {
             ],
             context_fields: &["path"],
         },
+        Error::SessionConflict { .. } => ErrorHint {
+            summary: "Shared session was changed by another client",
+            hints: &["Reopen it with 'pi --session remote:<file name>' to get their version"],
+            context_fields: &["location"],
+        },
         Error::Session(msg) if msg.contains("corrupted") || msg.contains("invalid") => ErrorHint {
             summary: "Session file is corrupted or invalid",
             hints: &[
@@ -446,6 +454,17 @@ const fn aborted_hints() -> ErrorHint {
     }
 }
 
+const fn budget_hints() -> ErrorHint {
+    ErrorHint {
+        summary: "Run stopped after exhausting its budget",
+        hints: &[
+            "Split the task into smaller prompts",
+            "Continue the session with 'pi -c' to pick up where it stopped",
+        ],
+        context_fields: &[],
+    }
+}
+
 fn api_hints(msg: &str) -> ErrorHint {
     if msg.contains("401") {
         return ErrorHint {
@@ -500,6 +519,9 @@ pub fn format_error_with_hints(error: &Error) -> String {
         }
     }
 
+    // Stable code last so wrapper scripts can match `^Error code: ` without parsing prose.
+    let _ = writeln!(&mut output, "\nError code: {}", error.code());
+
     output
 }
 
@@ -594,6 +616,13 @@ mod tests {
         assert!(formatted.contains("Suggestions:"));
     }
 
+    #[test]
+    fn test_format_error_with_hints_ends_with_error_code() {
+        let error = Error::auth("API key not set");
+        let formatted = format_error_with_hints(&error);
+        assert!(formatted.trim_end().ends_with("Error code: PI_AUTH_001"));
+    }
+
     #[test]
     fn test_format_error_with_hints_includes_api_key_suggestion() {
         let error = Error::auth("API key not set");
@@ -0,0 +1,543 @@
+//! Declarative prompt evaluation (`pi eval`).
+//!
+//! A suite is a YAML (or JSON) file listing prompts and the assertions each run must satisfy.
+//! Every case runs once per model in a fresh temporary directory seeded with the case's files:
+//! - `regex` / `notRegex` match the final assistant text
+//! - `jsonSchema` validates the final text parsed as JSON (a fenced block is unwrapped)
+//! - `file` checks the working directory after tools ran (existence, substring, regex)
+//!
+//! ```yaml
+//! name: refactors
+//! models: [anthropic/claude-sonnet-4-5, openai/gpt-4o]
+//! tools: [read, edit]
+//! cases:
+//!   - name: rename function
+//!     files: { src/lib.rs: "fn foo() {}\n" }
+//!     prompt: Rename foo to bar in src/lib.rs
+//!     expect:
+//!       - file: { path: src/lib.rs, contains: "fn bar()" }
+//!       - notRegex: "(?i)error"
+//! ```
+
+use crate::agent::{Agent, AgentConfig, AgentEvent};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::model::{ContentBlock, Message, ThinkingLevel};
+use crate::models::ModelEntry;
+use crate::provider::{Provider, StreamOptions};
+use crate::tools::ToolRegistry;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Tools enabled when a suite does not list its own.
+pub const DEFAULT_EVAL_TOOLS: &[&str] = &["read", "bash", "edit", "write"];
+
+/// A suite of evaluation cases.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalSuite {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Models to run every case against, as `provider/model`.
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Replaces the default system prompt.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    #[serde(default)]
+    pub thinking: Option<ThinkingLevel>,
+    #[serde(default)]
+    pub max_tool_iterations: Option<usize>,
+    pub cases: Vec<EvalCase>,
+}
+
+/// A single prompt and its expectations.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalCase {
+    pub name: String,
+    pub prompt: String,
+    /// Files written to the working directory before the run, keyed by relative path.
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    #[serde(default)]
+    pub expect: Vec<EvalAssertion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EvalAssertion {
+    Regex(String),
+    NotRegex(String),
+    JsonSchema(Value),
+    File(FileAssertion),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileAssertion {
+    pub path: String,
+    /// Defaults to `true` unless only absence is being asserted.
+    #[serde(default)]
+    pub exists: Option<bool>,
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+}
+
+impl EvalSuite {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path).map_err(|err| {
+            Error::validation(format!(
+                "Failed to read eval suite {}: {err}",
+                path.display()
+            ))
+        })?;
+        Self::parse(&raw).map_err(|err| {
+            Error::validation(format!("Invalid eval suite {}: {err}", path.display()))
+        })
+    }
+
+    /// Parse a suite from YAML. JSON is valid YAML, so JSON suites work too.
+    pub fn parse(raw: &str) -> std::result::Result<Self, String> {
+        let docs = YamlLoader::load_from_str(raw).map_err(|err| err.to_string())?;
+        let doc = docs.into_iter().next().ok_or("empty document")?;
+        let suite: Self =
+            serde_json::from_value(yaml_to_json(doc)?).map_err(|err| err.to_string())?;
+        if suite.cases.is_empty() {
+            return Err("suite has no cases".to_string());
+        }
+        Ok(suite)
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("eval")
+    }
+}
+
+fn yaml_to_json(yaml: Yaml) -> std::result::Result<Value, String> {
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Boolean(value) => Value::Bool(value),
+        Yaml::Integer(value) => Value::from(value),
+        Yaml::Real(raw) => raw
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map_or(Value::String(raw), Value::Number),
+        Yaml::String(value) => Value::String(value),
+        Yaml::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(yaml_to_json)
+                .collect::<std::result::Result<_, _>>()?,
+        ),
+        Yaml::Hash(map) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in map {
+                let key = match key {
+                    Yaml::String(key) => key,
+                    Yaml::Integer(key) => key.to_string(),
+                    Yaml::Boolean(key) => key.to_string(),
+                    Yaml::Real(key) => key,
+                    other => return Err(format!("unsupported mapping key: {other:?}")),
+                };
+                object.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(object)
+        }
+        Yaml::Alias(_) | Yaml::BadValue => return Err("unsupported YAML value".to_string()),
+    })
+}
+
+/// Check `assertions` against the final assistant text and the working directory.
+///
+/// Returns one message per failed assertion.
+pub fn check_assertions(assertions: &[EvalAssertion], text: &str, workdir: &Path) -> Vec<String> {
+    let mut failures = Vec::new();
+    for assertion in assertions {
+        if let Err(failure) = check_assertion(assertion, text, workdir) {
+            failures.push(failure);
+        }
+    }
+    failures
+}
+
+fn check_assertion(
+    assertion: &EvalAssertion,
+    text: &str,
+    workdir: &Path,
+) -> std::result::Result<(), String> {
+    match assertion {
+        EvalAssertion::Regex(pattern) => {
+            if compile(pattern)?.is_match(text) {
+                Ok(())
+            } else {
+                Err(format!("response does not match /{pattern}/"))
+            }
+        }
+        EvalAssertion::NotRegex(pattern) => {
+            if compile(pattern)?.is_match(text) {
+                Err(format!("response matches /{pattern}/"))
+            } else {
+                Ok(())
+            }
+        }
+        EvalAssertion::JsonSchema(schema) => {
+            let instance: Value = serde_json::from_str(unfence_json(text))
+                .map_err(|err| format!("response is not JSON: {err}"))?;
+            let validator = jsonschema::validator_for(schema)
+                .map_err(|err| format!("invalid JSON schema: {err}"))?;
+            let errors = validator
+                .iter_errors(&instance)
+                .map(|err| err.to_string())
+                .collect::<Vec<_>>();
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(format!(
+                    "response does not match schema: {}",
+                    errors.join("; ")
+                ))
+            }
+        }
+        EvalAssertion::File(file) => check_file(file, workdir),
+    }
+}
+
+fn check_file(file: &FileAssertion, workdir: &Path) -> std::result::Result<(), String> {
+    let path = workdir.join(&file.path);
+    let expect_exists = file.exists.unwrap_or(true);
+    if !path.exists() {
+        return if expect_exists {
+            Err(format!("{} does not exist", file.path))
+        } else {
+            Ok(())
+        };
+    }
+    if !expect_exists {
+        return Err(format!("{} exists", file.path));
+    }
+    if file.contains.is_none() && file.regex.is_none() {
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|err| format!("failed to read {}: {err}", file.path))?;
+    if let Some(needle) = &file.contains {
+        if !content.contains(needle.as_str()) {
+            return Err(format!("{} does not contain {needle:?}", file.path));
+        }
+    }
+    if let Some(pattern) = &file.regex {
+        if !compile(pattern)?.is_match(&content) {
+            return Err(format!("{} does not match /{pattern}/", file.path));
+        }
+    }
+    Ok(())
+}
+
+fn compile(pattern: &str) -> std::result::Result<Regex, String> {
+    Regex::new(pattern).map_err(|err| format!("invalid regex /{pattern}/: {err}"))
+}
+
+/// Strip a surrounding Markdown code fence, if any.
+fn unfence_json(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// A model to run cases against.
+pub struct EvalTarget {
+    pub entry: ModelEntry,
+    pub provider: Arc<dyn Provider>,
+    pub api_key: Option<String>,
+}
+
+/// Outcome of one case on one model.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalResult {
+    pub case: String,
+    pub model: String,
+    pub passed: bool,
+    pub failures: Vec<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+    pub duration_ms: u64,
+}
+
+/// Run `case` against `target` in a fresh temporary directory.
+pub async fn run_case(
+    suite: &EvalSuite,
+    case: &EvalCase,
+    target: &EvalTarget,
+    system_prompt: &str,
+    config: &Config,
+) -> EvalResult {
+    let model = format!("{}/{}", target.entry.model.provider, target.entry.model.id);
+    let started = Instant::now();
+    let mut result = EvalResult {
+        case: case.name.clone(),
+        model,
+        passed: false,
+        failures: Vec::new(),
+        input_tokens: 0,
+        output_tokens: 0,
+        cost: 0.0,
+        duration_ms: 0,
+    };
+
+    let workdir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            result
+                .failures
+                .push(format!("failed to create workdir: {err}"));
+            return result;
+        }
+    };
+    for (relative, content) in &case.files {
+        let path = workdir.path().join(relative);
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, content));
+        if let Err(err) = written {
+            result
+                .failures
+                .push(format!("failed to write {relative}: {err}"));
+            return result;
+        }
+    }
+
+    let tools = suite.tools.as_ref().map_or_else(
+        || DEFAULT_EVAL_TOOLS.to_vec(),
+        |tools| tools.iter().map(String::as_str).collect(),
+    );
+    let agent_config = AgentConfig {
+        system_prompt: Some(
+            suite
+                .system_prompt
+                .clone()
+                .unwrap_or_else(|| system_prompt.to_string()),
+        ),
+        max_tool_iterations: suite.max_tool_iterations.unwrap_or(50),
+        stream_options: StreamOptions {
+            api_key: target.api_key.clone(),
+            headers: target.entry.headers.clone(),
+            thinking_level: suite.thinking,
+            ..Default::default()
+        },
+        ..AgentConfig::default()
+    };
+    let mut agent = Agent::new(
+        Arc::clone(&target.provider),
+        ToolRegistry::new(&tools, workdir.path(), Some(config)),
+        agent_config,
+    );
+
+    let usage = Arc::new(Mutex::new((0_u64, 0_u64, 0_u64, 0_u64)));
+    let usage_events = Arc::clone(&usage);
+    let outcome = agent
+        .run(case.prompt.clone(), move |event| {
+            if let AgentEvent::MessageEnd {
+                message: Message::Assistant(message),
+            } = event
+            {
+                if let Ok(mut totals) = usage_events.lock() {
+                    totals.0 += message.usage.input;
+                    totals.1 += message.usage.output;
+                    totals.2 += message.usage.cache_read;
+                    totals.3 += message.usage.cache_write;
+                }
+            }
+        })
+        .await;
+
+    let (input, output, cache_read, cache_write) =
+        usage.lock().map_or((0, 0, 0, 0), |totals| *totals);
+    result.input_tokens = input;
+    result.output_tokens = output;
+    result.cost = target
+        .entry
+        .model
+        .calculate_cost(input, output, cache_read, cache_write);
+
+    match outcome {
+        Ok(message) => {
+            let text = message
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            if let Some(error) = message.error_message {
+                result.failures.push(format!("run failed: {error}"));
+            }
+            result
+                .failures
+                .extend(check_assertions(&case.expect, &text, workdir.path()));
+        }
+        Err(err) => result.failures.push(format!("run failed: {err}")),
+    }
+
+    result.passed = result.failures.is_empty();
+    result.duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    result
+}
+
+/// Results of a suite run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalReport {
+    pub suite: String,
+    pub results: Vec<EvalResult>,
+}
+
+impl EvalReport {
+    pub fn failed(&self) -> usize {
+        self.results.iter().filter(|result| !result.passed).count()
+    }
+
+    pub fn total_cost(&self) -> f64 {
+        self.results.iter().map(|result| result.cost).sum()
+    }
+
+    /// Plain-text report: one line per run, then per-model totals.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let case_width = self
+            .results
+            .iter()
+            .map(|result| result.case.len())
+            .max()
+            .unwrap_or(0);
+        let model_width = self
+            .results
+            .iter()
+            .map(|result| result.model.len())
+            .max()
+            .unwrap_or(0);
+        for result in &self.results {
+            let _ = writeln!(
+                out,
+                "{}  {:case_width$}  {:model_width$}  ${:.4}  {:.1}s",
+                if result.passed { "PASS" } else { "FAIL" },
+                result.case,
+                result.model,
+                result.cost,
+                result.duration_ms as f64 / 1000.0,
+            );
+            for failure in &result.failures {
+                let _ = writeln!(out, "      - {failure}");
+            }
+        }
+
+        let mut per_model: BTreeMap<&str, (usize, usize, f64)> = BTreeMap::new();
+        for result in &self.results {
+            let entry = per_model.entry(&result.model).or_default();
+            entry.0 += usize::from(result.passed);
+            entry.1 += 1;
+            entry.2 += result.cost;
+        }
+        out.push('\n');
+        for (model, (passed, total, cost)) in per_model {
+            let _ = writeln!(out, "{model}: {passed}/{total} passed, ${cost:.4}");
+        }
+        let _ = writeln!(
+            out,
+            "{}: {}/{} passed, ${:.4} total",
+            self.suite,
+            self.results.len() - self.failed(),
+            self.results.len(),
+            self.total_cost()
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_yaml_suite_with_all_assertion_kinds() {
+        let suite = EvalSuite::parse(
+            r#"
+name: smoke
+models: [anthropic/claude-sonnet-4-5]
+cases:
+  - name: answer
+    prompt: Reply with JSON
+    files:
+      notes.txt: hello
+    expect:
+      - regex: "ok"
+      - notRegex: "(?i)sorry"
+      - jsonSchema: { type: object, required: [ok] }
+      - file: { path: notes.txt, contains: hello }
+"#,
+        )
+        .expect("suite");
+        assert_eq!(suite.display_name(), "smoke");
+        assert_eq!(suite.models, vec!["anthropic/claude-sonnet-4-5"]);
+        let case = &suite.cases[0];
+        assert_eq!(
+            case.files.get("notes.txt").map(String::as_str),
+            Some("hello")
+        );
+        assert_eq!(case.expect.len(), 4);
+        assert!(matches!(case.expect[2], EvalAssertion::JsonSchema(_)));
+
+        assert!(EvalSuite::parse("name: empty\ncases: []\n").is_err());
+    }
+
+    #[test]
+    fn assertions_check_text_json_and_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(dir.path().join("out.txt"), "fn bar() {}\n").expect("write");
+        let suite = EvalSuite::parse(
+            r#"
+cases:
+  - name: checks
+    prompt: x
+    expect:
+      - regex: '"ok": true'
+      - notRegex: error
+      - jsonSchema: { type: object, required: [ok], properties: { ok: { type: boolean } } }
+      - file: { path: out.txt, regex: "fn bar" }
+      - file: { path: missing.txt, exists: false }
+"#,
+        )
+        .expect("suite");
+        let expect = &suite.cases[0].expect;
+
+        let text = "```json\n{\"ok\": true}\n```";
+        assert!(check_assertions(expect, text, dir.path()).is_empty());
+
+        let failures = check_assertions(expect, "error: {\"ok\": 1}", dir.path());
+        assert_eq!(failures.len(), 3, "{failures:?}");
+        assert!(failures[0].contains("does not match"));
+        assert!(failures[1].contains("matches /error/"));
+        assert!(failures[2].contains("not JSON"));
+    }
+}
@@ -113,6 +113,9 @@ pub struct ToolCallEventResult {
 
     /// Reason for blocking (shown to user).
     pub reason: Option<String>,
+
+    /// Replacement tool arguments (if None, use original).
+    pub input: Option<Value>,
 }
 
 /// Result from a tool_result event handler.
@@ -334,6 +337,7 @@ mod tests {
                 model: "test".to_string(),
                 usage: crate::model::Usage::default(),
                 stop_reason: crate::model::StopReason::Stop,
+                abort_reason: None,
                 error_message: None,
                 timestamp: 0,
             }
@@ -452,7 +456,8 @@ mod tests {
             result,
             ToolCallEventResult {
                 block: false,
-                reason: Some("nope".to_string())
+                reason: Some("nope".to_string()),
+                input: None,
             }
         );
     }
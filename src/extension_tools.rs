@@ -278,6 +278,7 @@ mod tests {
                     model: "test-model".to_string(),
                     usage: Usage::default(),
                     stop_reason: StopReason::Stop,
+                    abort_reason: None,
                     error_message: None,
                     timestamp: 0,
                 }
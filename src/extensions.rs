@@ -1505,6 +1505,10 @@ pub struct RegisterPayload {
     pub slash_commands: Vec<Value>,
     #[serde(default)]
     pub event_hooks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub themes: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status_segments: Vec<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -2899,6 +2903,8 @@ mod wasm_host {
                 tools: Vec::new(),
                 slash_commands: Vec::new(),
                 event_hooks: Vec::new(),
+                themes: Vec::new(),
+                status_segments: Vec::new(),
             }
         }
 
@@ -3482,6 +3488,70 @@ impl WasmExtensionHost {
 /// Timeout for extension events in milliseconds.
 pub const EXTENSION_EVENT_TIMEOUT_MS: u64 = 5000;
 
+/// Timeout for rendering a single extension status-bar segment in milliseconds.
+pub const STATUS_SEGMENT_TIMEOUT_MS: u64 = 2000;
+
+/// Longest text (in chars) an extension status-bar segment may display.
+pub const STATUS_SEGMENT_MAX_CHARS: usize = 48;
+
+/// A status-bar segment registered via `pi.registerStatusSegment`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusSegmentSpec {
+    pub id: String,
+    /// Re-render period, if the segment asked to be polled.
+    pub interval_ms: Option<u64>,
+    /// Extension event names that trigger a re-render.
+    pub events: Vec<String>,
+}
+
+impl StatusSegmentSpec {
+    fn from_value(value: &Value) -> Option<Self> {
+        let id = value.get("id").and_then(Value::as_str)?.trim();
+        if id.is_empty() {
+            return None;
+        }
+        let events = value
+            .get("events")
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            id: id.to_string(),
+            interval_ms: value.get("intervalMs").and_then(Value::as_u64),
+            events,
+        })
+    }
+
+    #[must_use]
+    pub fn refreshes_on(&self, event: &str) -> bool {
+        self.events.iter().any(|name| name == event)
+    }
+}
+
+/// Collapse rendered segment text to a single short line.
+fn clamp_status_segment_text(text: &str) -> String {
+    let line = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    let line = line.trim();
+    if line.chars().count() <= STATUS_SEGMENT_MAX_CHARS {
+        return line.to_string();
+    }
+    let mut out = line
+        .chars()
+        .take(STATUS_SEGMENT_MAX_CHARS - 1)
+        .collect::<String>();
+    out.push('…');
+    out
+}
+
 /// Event names for the extension lifecycle.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExtensionEventName {
@@ -3664,6 +3734,10 @@ struct JsExtensionSnapshot {
     #[serde(default)]
     event_hooks: Vec<String>,
     #[serde(default)]
+    themes: Vec<Value>,
+    #[serde(default)]
+    status_segments: Vec<Value>,
+    #[serde(default)]
     active_tools: Option<Vec<String>>,
 }
 
@@ -3709,6 +3783,12 @@ enum JsRuntimeCommand {
         timeout_ms: u64,
         reply: oneshot::Sender<Result<Value>>,
     },
+    RenderStatusSegment {
+        segment_id: String,
+        ctx_payload: Value,
+        timeout_ms: u64,
+        reply: oneshot::Sender<Result<Value>>,
+    },
 }
 
 #[derive(Clone)]
@@ -3821,6 +3901,22 @@ impl JsExtensionRuntimeHandle {
                             .await;
                             let _ = reply.send(&cx, result);
                         }
+                        JsRuntimeCommand::RenderStatusSegment {
+                            segment_id,
+                            ctx_payload,
+                            timeout_ms,
+                            reply,
+                        } => {
+                            let result = render_extension_status_segment(
+                                &js_runtime,
+                                &host,
+                                &segment_id,
+                                ctx_payload,
+                                timeout_ms,
+                            )
+                            .await;
+                            let _ = reply.send(&cx, result);
+                        }
                     }
                 }
             });
@@ -3971,6 +4067,32 @@ impl JsExtensionRuntimeHandle {
             .await
             .map_err(|_| Error::extension("JS extension runtime task cancelled"))?
     }
+
+    pub async fn render_status_segment(
+        &self,
+        segment_id: String,
+        ctx_payload: Value,
+        timeout_ms: u64,
+    ) -> Result<Value> {
+        let cx = Cx::for_request();
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.sender
+            .send(
+                &cx,
+                JsRuntimeCommand::RenderStatusSegment {
+                    segment_id,
+                    ctx_payload,
+                    timeout_ms,
+                    reply: reply_tx,
+                },
+            )
+            .await
+            .map_err(|_| Error::extension("JS extension runtime channel closed"))?;
+        reply_rx
+            .recv(&cx)
+            .await
+            .map_err(|_| Error::extension("JS extension runtime task cancelled"))?
+    }
 }
 
 #[allow(clippy::future_not_send)]
@@ -4118,6 +4240,30 @@ async fn execute_extension_command(
     await_js_task(runtime, host, &task_id, Duration::from_millis(timeout_ms)).await
 }
 
+#[allow(clippy::future_not_send)]
+async fn render_extension_status_segment(
+    runtime: &PiJsRuntime,
+    host: &JsRuntimeHost,
+    segment_id: &str,
+    ctx_payload: Value,
+    timeout_ms: u64,
+) -> Result<Value> {
+    let task_id = format!("task-status-{}", Uuid::new_v4());
+    runtime
+        .with_ctx(|ctx| {
+            let global = ctx.globals();
+            let render_fn: rquickjs::Function<'_> = global.get("__pi_render_status_segment")?;
+            let task_start: rquickjs::Function<'_> = global.get("__pi_task_start")?;
+            let ctx_js = json_to_js(&ctx, &ctx_payload)?;
+            let promise: rquickjs::Value<'_> = render_fn.call((segment_id.to_string(), ctx_js))?;
+            let _task: String = task_start.call((task_id.clone(), promise))?;
+            Ok(())
+        })
+        .await?;
+
+    await_js_task(runtime, host, &task_id, Duration::from_millis(timeout_ms)).await
+}
+
 #[allow(clippy::future_not_send)]
 async fn pump_js_runtime_once(runtime: &PiJsRuntime, host: &JsRuntimeHost) -> Result<bool> {
     let mut pending = runtime.drain_hostcall_requests();
@@ -5109,6 +5255,8 @@ impl ExtensionManager {
                 slash_commands,
                 providers,
                 event_hooks,
+                themes,
+                status_segments,
                 active_tools: ext_active_tools,
             } = snapshot;
             let _ = providers;
@@ -5128,6 +5276,8 @@ impl ExtensionManager {
                 tools,
                 slash_commands,
                 event_hooks,
+                themes,
+                status_segments,
             });
         }
 
@@ -5192,6 +5342,72 @@ impl ExtensionManager {
         commands
     }
 
+    /// Themes contributed by extensions, in registration order. Invalid specs are skipped.
+    pub fn list_themes(&self) -> Vec<crate::theme::Theme> {
+        let guard = self.inner.lock().unwrap();
+        let specs = guard
+            .extensions
+            .iter()
+            .flat_map(|ext| ext.themes.iter().cloned())
+            .collect::<Vec<_>>();
+        drop(guard);
+
+        specs
+            .into_iter()
+            .filter_map(
+                |spec| match crate::theme::Theme::from_json_str(&spec.to_string()) {
+                    Ok(theme) => Some(theme),
+                    Err(err) => {
+                        tracing::warn!("Ignoring invalid extension theme: {err}");
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+
+    pub fn list_status_segments(&self) -> Vec<StatusSegmentSpec> {
+        let guard = self.inner.lock().unwrap();
+        guard
+            .extensions
+            .iter()
+            .flat_map(|ext| ext.status_segments.iter())
+            .filter_map(StatusSegmentSpec::from_value)
+            .collect()
+    }
+
+    /// Render one status-bar segment, returning its text clamped to a single short line.
+    pub async fn render_status_segment(&self, segment_id: &str) -> Result<String> {
+        let runtime = self
+            .js_runtime()
+            .ok_or_else(|| Error::extension("JS extension runtime not configured"))?;
+
+        let (has_ui, cwd_override) = {
+            let guard = self.inner.lock().unwrap();
+            (guard.ui_sender.is_some(), guard.cwd.clone())
+        };
+        let mut ctx = serde_json::Map::new();
+        ctx.insert("hasUI".to_string(), Value::Bool(has_ui));
+        if let Some(cwd) = cwd_override.or_else(|| {
+            std::env::current_dir()
+                .ok()
+                .map(|p| p.display().to_string())
+        }) {
+            ctx.insert("cwd".to_string(), Value::String(cwd));
+        }
+
+        let value = runtime
+            .render_status_segment(
+                segment_id.to_string(),
+                Value::Object(ctx),
+                STATUS_SEGMENT_TIMEOUT_MS,
+            )
+            .await?;
+        Ok(clamp_status_segment_text(
+            value.as_str().unwrap_or_default(),
+        ))
+    }
+
     pub async fn request_ui(
         &self,
         mut request: ExtensionUiRequest,
@@ -5561,6 +5777,15 @@ pub fn extension_event_from_agent(
         AgentEvent::ToolExecutionStart { .. } => ExtensionEventName::ToolExecutionStart,
         AgentEvent::ToolExecutionUpdate { .. } => ExtensionEventName::ToolExecutionUpdate,
         AgentEvent::ToolExecutionEnd { .. } => ExtensionEventName::ToolExecutionEnd,
+        AgentEvent::PermissionRequest { .. }
+        | AgentEvent::AutoCompaction { .. }
+        | AgentEvent::RetryScheduled { .. }
+        | AgentEvent::ToolDefinitionsOversized { .. }
+        | AgentEvent::Paused { .. }
+        | AgentEvent::Resumed
+        | AgentEvent::LoopDetected { .. }
+        | AgentEvent::PlanReady { .. }
+        | AgentEvent::PlanStep { .. } => return None,
     };
 
     let payload = serde_json::to_value(event).ok();
@@ -5633,6 +5858,8 @@ mod tests {
                         tools: Vec::new(),
                         slash_commands: Vec::new(),
                         event_hooks: Vec::new(),
+                        themes: Vec::new(),
+                        status_segments: Vec::new(),
                     }),
                 },
             ),
@@ -5843,6 +6070,56 @@ mod tests {
         assert!(matches!(msg.body, ExtensionBody::Register(_)));
     }
 
+    #[test]
+    fn registered_themes_and_status_segments_are_listed() {
+        let manager = ExtensionManager::new();
+        let mut theme = serde_json::to_value(crate::theme::Theme::dark()).unwrap();
+        theme["name"] = json!("ci-dark");
+        manager.register(RegisterPayload {
+            name: "ci".to_string(),
+            version: "0.1.0".to_string(),
+            api_version: PROTOCOL_VERSION.to_string(),
+            capabilities: Vec::new(),
+            capability_manifest: None,
+            tools: Vec::new(),
+            slash_commands: Vec::new(),
+            event_hooks: Vec::new(),
+            themes: vec![theme, json!({ "name": "broken" })],
+            status_segments: vec![
+                json!({ "id": "ci", "intervalMs": 30000, "events": ["agent_end"] }),
+                json!({ "id": "  " }),
+            ],
+        });
+
+        let themes = manager.list_themes();
+        assert_eq!(themes.len(), 1);
+        assert_eq!(themes[0].name, "ci-dark");
+
+        let segments = manager.list_status_segments();
+        assert_eq!(
+            segments,
+            vec![StatusSegmentSpec {
+                id: "ci".to_string(),
+                interval_ms: Some(30_000),
+                events: vec!["agent_end".to_string()],
+            }]
+        );
+        assert!(segments[0].refreshes_on("agent_end"));
+        assert!(!segments[0].refreshes_on("agent_start"));
+    }
+
+    #[test]
+    fn status_segment_text_is_single_short_line() {
+        assert_eq!(
+            clamp_status_segment_text("\n  CI: passing \nmore"),
+            "CI: passing"
+        );
+        let long = "x".repeat(STATUS_SEGMENT_MAX_CHARS + 10);
+        let clamped = clamp_status_segment_text(&long);
+        assert_eq!(clamped.chars().count(), STATUS_SEGMENT_MAX_CHARS);
+        assert!(clamped.ends_with('…'));
+    }
+
     #[test]
     fn reject_invalid_version() {
         let json = r#"
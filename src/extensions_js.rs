@@ -2582,6 +2582,7 @@ const __pi_tool_index = new Map();      // tool_name -> { extensionId, spec, exe
 const __pi_command_index = new Map();   // command_name -> { extensionId, name, description, handler }
 const __pi_hook_index = new Map();      // event_name -> [{ extensionId, handler }, ...]
 const __pi_provider_index = new Map();  // provider_id -> { extensionId, spec }
+const __pi_status_segment_index = new Map(); // segment_id -> { extensionId, render }
 
 // Async task tracking for Rust-driven calls (tool exec, command exec, event dispatch).
 // task_id -> { status: 'pending'|'resolved'|'rejected', value?, error? }
@@ -2647,6 +2648,8 @@ function __pi_get_or_create_extension(extension_id, meta) {
             commands: new Map(),
             hooks: new Map(),
             providers: new Map(),
+            themes: new Map(),
+            statusSegments: new Map(),
             shortcuts: new Map(),
             flags: new Map(),
             flagValues: new Map(),
@@ -2854,6 +2857,51 @@ function __pi_register_shortcut(key, spec) {
     ext.shortcuts.set(keyId, entry);
 }
 
+function __pi_register_theme(spec) {
+    const ext = __pi_current_extension_or_throw();
+    if (!spec || typeof spec !== 'object') {
+        throw new Error('registerTheme: spec must be an object');
+    }
+    const name = String(spec.name || '').trim();
+    if (!name) {
+        throw new Error('registerTheme: spec.name is required');
+    }
+
+    // Themes are plain data; round-trip through JSON so later mutation by the
+    // extension does not leak into the snapshot.
+    ext.themes.set(name, JSON.parse(JSON.stringify({ ...spec, name: name })));
+}
+
+function __pi_register_status_segment(segment_id, spec) {
+    const ext = __pi_current_extension_or_throw();
+    const id = String(segment_id || '').trim();
+    if (!id) {
+        throw new Error('registerStatusSegment: id is required');
+    }
+    if (!spec || typeof spec !== 'object') {
+        throw new Error('registerStatusSegment: spec must be an object');
+    }
+    if (typeof spec.render !== 'function') {
+        throw new Error('registerStatusSegment: spec.render must be a function');
+    }
+
+    if (__pi_status_segment_index.has(id)) {
+        const existing = __pi_status_segment_index.get(id);
+        if (existing && existing.extensionId !== ext.id) {
+            throw new Error(`registerStatusSegment: segment id collision: ${id}`);
+        }
+    }
+
+    const intervalMs = Number(spec.intervalMs);
+    const entry = {
+        id: id,
+        intervalMs: Number.isFinite(intervalMs) && intervalMs > 0 ? Math.floor(intervalMs) : null,
+        events: Array.isArray(spec.events) ? spec.events.map((name) => String(name)) : [],
+    };
+    ext.statusSegments.set(id, entry);
+    __pi_status_segment_index.set(id, { extensionId: ext.id, render: spec.render });
+}
+
 function __pi_register_hook(event_name, handler) {
     const ext = __pi_current_extension_or_throw();
     const eventName = String(event_name || '').trim();
@@ -2985,6 +3033,9 @@ function __pi_snapshot_extensions() {
             event_hooks.push(String(key));
         }
 
+        const themes = Array.from(ext.themes.values());
+        const status_segments = Array.from(ext.statusSegments.values());
+
         out.push({
             id: id,
             name: ext.name,
@@ -2994,6 +3045,8 @@ function __pi_snapshot_extensions() {
             slash_commands: commands,
             providers: providers,
             event_hooks: event_hooks,
+            themes: themes,
+            status_segments: status_segments,
             active_tools: Array.isArray(ext.activeTools) ? ext.activeTools.slice() : null,
         });
     }
@@ -3263,6 +3316,18 @@ async function __pi_execute_command(command_name, args, ctx_payload) {
     return await __pi_with_extension_async(record.extensionId, () => record.handler(args, ctx));
 }
 
+async function __pi_render_status_segment(segment_id, ctx_payload) {
+    const id = String(segment_id || '').trim();
+    const record = __pi_status_segment_index.get(id);
+    if (!record) {
+        throw new Error(`Unknown status segment: ${id}`);
+    }
+
+    const ctx = __pi_make_extension_ctx(ctx_payload);
+    const text = await __pi_with_extension_async(record.extensionId, () => record.render(ctx));
+    return text === undefined || text === null ? '' : String(text);
+}
+
 // Complete a hostcall (called from Rust)
 function __pi_complete_hostcall(call_id, outcome) {
     const pending = __pi_pending_hostcalls.get(call_id);
@@ -3437,6 +3502,8 @@ const pi = {
     registerCommand: __pi_register_command,
     registerProvider: __pi_register_provider,
     registerShortcut: __pi_register_shortcut,
+    registerTheme: __pi_register_theme,
+    registerStatusSegment: __pi_register_status_segment,
     on: __pi_register_hook,
     registerFlag: __pi_register_flag,
     getFlag: __pi_get_flag,
@@ -0,0 +1,373 @@
+//! Token-aware shaping of file contents returned by the `read` tool.
+//!
+//! Shaping is opt-in (`file_content` in settings, with per-language overrides) and
+//! works on whole lines, so every line that is kept retains its original line number:
+//! - Full-line comments are dropped (doc comments and shebangs are kept)
+//! - Trailing whitespace is trimmed and runs of blank lines collapse to one
+//! - Runs of identical lines collapse to the first line plus a repeat marker
+
+use crate::config::FileContentSettings;
+use std::path::Path;
+
+/// Shortest run of identical lines that is collapsed.
+const MIN_REPEAT_RUN: usize = 3;
+
+/// Resolved shaping options for a single file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileContentOptions {
+    pub strip_comments: bool,
+    pub strip_whitespace: bool,
+    pub collapse_repeats: bool,
+}
+
+impl FileContentOptions {
+    /// Resolve options for `path`: global settings first, then the matching language entry.
+    ///
+    /// Language entries are keyed by language name (`rust`, `python`, ...) or file extension.
+    pub fn resolve(settings: Option<&FileContentSettings>, path: &Path) -> Self {
+        let Some(settings) = settings else {
+            return Self::default();
+        };
+        let options = Self::default().merged(settings);
+        let Some(languages) = &settings.languages else {
+            return options;
+        };
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        language_for_path(path)
+            .and_then(|language| languages.get(language))
+            .or_else(|| extension.as_deref().and_then(|ext| languages.get(ext)))
+            .map_or(options, |rules| options.merged(rules))
+    }
+
+    fn merged(self, settings: &FileContentSettings) -> Self {
+        Self {
+            strip_comments: settings.strip_comments.unwrap_or(self.strip_comments),
+            strip_whitespace: settings.strip_whitespace.unwrap_or(self.strip_whitespace),
+            collapse_repeats: settings.collapse_repeats.unwrap_or(self.collapse_repeats),
+        }
+    }
+
+    pub const fn is_enabled(self) -> bool {
+        self.strip_comments || self.strip_whitespace || self.collapse_repeats
+    }
+}
+
+/// Map a file extension to the language name used for comment syntax and settings.
+pub fn language_for_path(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
+        "go" => "go",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "rb" => "ruby",
+        "sh" | "bash" | "zsh" => "shell",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "lua" => "lua",
+        "sql" => "sql",
+        _ => return None,
+    })
+}
+
+struct CommentSyntax {
+    line: &'static str,
+    block: Option<(&'static str, &'static str)>,
+    /// Prefixes of comments that carry meaning (docs, shebangs) and are never dropped.
+    keep: &'static [&'static str],
+}
+
+fn comment_syntax(language: &str) -> Option<CommentSyntax> {
+    let syntax = match language {
+        "rust" => CommentSyntax {
+            line: "//",
+            block: Some(("/*", "*/")),
+            keep: &["///", "//!", "/**", "/*!"],
+        },
+        "javascript" | "typescript" | "go" | "c" | "cpp" | "java" | "kotlin" | "swift" => {
+            CommentSyntax {
+                line: "//",
+                block: Some(("/*", "*/")),
+                keep: &["/**"],
+            }
+        }
+        "python" | "ruby" | "shell" | "toml" | "yaml" => CommentSyntax {
+            line: "#",
+            block: None,
+            keep: &["#!"],
+        },
+        "lua" | "sql" => CommentSyntax {
+            line: "--",
+            block: None,
+            keep: &[],
+        },
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// A line of shaped output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShapedLine<'a> {
+    /// A kept line with its original 1-based line number.
+    Line { number: usize, text: &'a str },
+    /// The previous line repeated `count` more times.
+    Repeated { count: usize },
+}
+
+/// Result of shaping a range of lines.
+#[derive(Debug, Clone, Default)]
+pub struct ShapedContent<'a> {
+    pub lines: Vec<ShapedLine<'a>>,
+    pub omitted_comments: usize,
+    pub omitted_blank: usize,
+    pub omitted_repeats: usize,
+}
+
+impl ShapedContent<'_> {
+    /// Render in the `read` tool's `cat -n` style, returning the text and, for each output
+    /// line, the last original line number it covers.
+    pub fn render(&self, line_num_width: usize) -> (String, Vec<usize>) {
+        let mut rendered = Vec::with_capacity(self.lines.len());
+        let mut covered = Vec::with_capacity(self.lines.len());
+        let mut last = 0;
+        for line in &self.lines {
+            match line {
+                ShapedLine::Line { number, text } => {
+                    last = *number;
+                    rendered.push(format!("{number:>line_num_width$}→{text}"));
+                }
+                ShapedLine::Repeated { count } => {
+                    last += count;
+                    rendered.push(format!(
+                        "{:>line_num_width$}→[previous line repeated {count} more times]",
+                        ""
+                    ));
+                }
+            }
+            covered.push(last);
+        }
+        (rendered.join("\n"), covered)
+    }
+
+    /// Note describing what was omitted, if anything.
+    pub fn summary(&self) -> Option<String> {
+        let parts = [
+            (self.omitted_comments, "comment"),
+            (self.omitted_blank, "blank"),
+            (self.omitted_repeats, "repeated"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, kind)| format!("{count} {kind}"))
+        .collect::<Vec<_>>();
+        if parts.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "[Shaped for context: {} lines omitted. Line numbers match the file; use bash (sed -n) for exact text before editing.]",
+            parts.join(", ")
+        ))
+    }
+}
+
+/// Shape `lines`, numbering them from `first_number`.
+pub fn shape_lines<'a>(
+    lines: &[&'a str],
+    first_number: usize,
+    language: Option<&str>,
+    options: FileContentOptions,
+) -> ShapedContent<'a> {
+    let syntax = if options.strip_comments {
+        language.and_then(comment_syntax)
+    } else {
+        None
+    };
+    let mut shaped = ShapedContent::default();
+    let mut in_block_comment = false;
+    let mut previous_blank = false;
+
+    for (idx, raw) in lines.iter().enumerate() {
+        let number = first_number + idx;
+        let mut text = raw.strip_suffix('\r').unwrap_or(raw);
+        if options.strip_whitespace {
+            text = text.trim_end();
+        }
+        let trimmed = text.trim_start();
+
+        if let Some(syntax) = &syntax {
+            if in_block_comment || is_comment_line(trimmed, syntax, &mut in_block_comment) {
+                if let Some((_, close)) = syntax.block {
+                    if in_block_comment && trimmed.contains(close) {
+                        in_block_comment = false;
+                    }
+                }
+                shaped.omitted_comments += 1;
+                continue;
+            }
+        }
+
+        let blank = trimmed.is_empty();
+        if options.strip_whitespace && blank && (previous_blank || shaped.lines.is_empty()) {
+            shaped.omitted_blank += 1;
+            continue;
+        }
+        previous_blank = blank;
+        shaped.lines.push(ShapedLine::Line { number, text });
+    }
+
+    if options.collapse_repeats {
+        collapse_repeats(&mut shaped);
+    }
+    shaped
+}
+
+/// Whether `trimmed` is a droppable full-line comment. Opening a multi-line block comment
+/// sets `in_block` so the following lines are dropped until it closes.
+fn is_comment_line(trimmed: &str, syntax: &CommentSyntax, in_block: &mut bool) -> bool {
+    if syntax.keep.iter().any(|prefix| trimmed.starts_with(prefix)) {
+        return false;
+    }
+    if trimmed.starts_with(syntax.line) {
+        return true;
+    }
+    let Some((open, close)) = syntax.block else {
+        return false;
+    };
+    let Some(rest) = trimmed.strip_prefix(open) else {
+        return false;
+    };
+    match rest.find(close) {
+        // Only drop the line when nothing but the comment is on it.
+        Some(end) => rest[end + close.len()..].trim().is_empty(),
+        None => {
+            *in_block = true;
+            true
+        }
+    }
+}
+
+fn collapse_repeats(shaped: &mut ShapedContent<'_>) {
+    let mut collapsed = Vec::with_capacity(shaped.lines.len());
+    let mut idx = 0;
+    while idx < shaped.lines.len() {
+        let current = shaped.lines[idx].clone();
+        let ShapedLine::Line { text, .. } = current else {
+            collapsed.push(current);
+            idx += 1;
+            continue;
+        };
+        let run = shaped.lines[idx..]
+            .iter()
+            .take_while(
+                |line| matches!(line, ShapedLine::Line { text: other, .. } if *other == text),
+            )
+            .count();
+        collapsed.push(current);
+        if run >= MIN_REPEAT_RUN && !text.trim().is_empty() {
+            collapsed.push(ShapedLine::Repeated { count: run - 1 });
+            shaped.omitted_repeats += run - 1;
+        } else {
+            collapsed.extend(shaped.lines[idx + 1..idx + run].iter().cloned());
+        }
+        idx += run;
+    }
+    shaped.lines = collapsed;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn all_options() -> FileContentOptions {
+        FileContentOptions {
+            strip_comments: true,
+            strip_whitespace: true,
+            collapse_repeats: true,
+        }
+    }
+
+    #[test]
+    fn strips_comments_but_keeps_docs_and_original_line_numbers() {
+        let source = [
+            "/// Adds numbers.",
+            "fn add(a: i32, b: i32) -> i32 {",
+            "    // plain comment",
+            "    /* block",
+            "       still block */",
+            "    a + b /* inline */",
+            "}",
+        ];
+        let shaped = shape_lines(&source, 1, Some("rust"), all_options());
+        let (text, covered) = shaped.render(5);
+        assert_eq!(
+            text,
+            "    1→/// Adds numbers.\n    2→fn add(a: i32, b: i32) -> i32 {\n    6→    a + b /* inline */\n    7→}"
+        );
+        assert_eq!(covered, vec![1, 2, 6, 7]);
+        assert_eq!(shaped.omitted_comments, 3);
+    }
+
+    #[test]
+    fn collapses_blank_runs_and_repeated_lines() {
+        let source = [
+            "x = 1   ", "", "", "", "pad()", "pad()", "pad()", "pad()", "y = 2",
+        ];
+        let shaped = shape_lines(&source, 10, Some("python"), all_options());
+        let (text, covered) = shaped.render(5);
+        assert_eq!(
+            text,
+            "   10→x = 1\n   11→\n   14→pad()\n     →[previous line repeated 3 more times]\n   18→y = 2"
+        );
+        assert_eq!(covered, vec![10, 11, 14, 17, 18]);
+        assert_eq!(shaped.omitted_blank, 2);
+        assert_eq!(shaped.omitted_repeats, 3);
+        let summary = shaped.summary().expect("summary");
+        assert!(
+            summary.contains("2 blank, 3 repeated lines omitted"),
+            "{summary}"
+        );
+    }
+
+    #[test]
+    fn language_overrides_apply_by_name_or_extension() {
+        let settings = FileContentSettings {
+            strip_whitespace: Some(true),
+            languages: Some(HashMap::from([
+                (
+                    "rust".to_string(),
+                    FileContentSettings {
+                        strip_comments: Some(true),
+                        ..FileContentSettings::default()
+                    },
+                ),
+                (
+                    "md".to_string(),
+                    FileContentSettings {
+                        strip_whitespace: Some(false),
+                        ..FileContentSettings::default()
+                    },
+                ),
+            ])),
+            ..FileContentSettings::default()
+        };
+
+        let rust = FileContentOptions::resolve(Some(&settings), Path::new("src/lib.rs"));
+        assert!(rust.strip_comments && rust.strip_whitespace);
+        let markdown = FileContentOptions::resolve(Some(&settings), Path::new("README.md"));
+        assert!(!markdown.is_enabled());
+        let python = FileContentOptions::resolve(Some(&settings), Path::new("main.py"));
+        assert!(python.strip_whitespace && !python.strip_comments);
+        assert!(!FileContentOptions::resolve(None, Path::new("src/lib.rs")).is_enabled());
+    }
+}
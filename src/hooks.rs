@@ -0,0 +1,416 @@
+//! Lifecycle hooks: shell commands run at fixed points of the agent loop (the `hooks` setting).
+//!
+//! Events:
+//! - `pre_tool_use`: before a tool call runs; can block it or replace its arguments
+//! - `post_tool_use`: after a tool call; can append context to the result the model sees
+//! - `pre_request`: before each provider request; can append context to the system prompt
+//! - `post_turn`: after each assistant turn; its context is added to the next request
+//!
+//! A hook gets the event as JSON on stdin (`event`, `cwd`, and for tool events `toolName`,
+//! `toolCallId`, `arguments`, plus `result`/`isError` after the call). It answers through its
+//! exit status and stdout:
+//! - exit 0 with a JSON object: `{"decision": "block", "reason": ..., "arguments": {...},
+//!   "context": "..."}`, every field optional
+//! - exit 0 with other output: the output is context
+//! - exit 2: block the tool call, with stderr as the reason
+//! - anything else, including a timeout: logged and ignored
+//!
+//! JS extensions hook the same points with `tool_call` (which may also return `input` to
+//! replace the arguments), `tool_result`, `turn_start`, and `turn_end`.
+
+use asupersync::Cx;
+use asupersync::channel::oneshot;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::io::{Read as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest a hook may run unless its `timeoutMs` says otherwise.
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Exit status a hook uses to block a tool call.
+const BLOCK_EXIT_CODE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    PreToolUse,
+    PostToolUse,
+    PreRequest,
+    PostTurn,
+}
+
+impl HookEvent {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::PreToolUse => "pre_tool_use",
+            Self::PostToolUse => "post_tool_use",
+            Self::PreRequest => "pre_request",
+            Self::PostTurn => "post_turn",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookConfig {
+    pub event: HookEvent,
+    /// Tool name glob for tool events (`*` or unset for every tool).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    /// Run through `sh -c` in the working directory.
+    pub command: String,
+    #[serde(default, alias = "timeout_ms", skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+impl HookConfig {
+    /// Whether this hook runs for `event` on a call to `tool_name` (`None` outside tool events).
+    pub fn matches(&self, event: HookEvent, tool_name: Option<&str>) -> bool {
+        if self.event != event {
+            return false;
+        }
+        let (Some(pattern), Some(tool_name)) = (&self.matcher, tool_name) else {
+            return true;
+        };
+        glob::Pattern::new(pattern.trim()).is_ok_and(|pattern| pattern.matches(tool_name))
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or(DEFAULT_HOOK_TIMEOUT, Duration::from_millis)
+    }
+}
+
+/// What the hooks for one event asked for, combined in configuration order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookOutcome {
+    /// Reason from the first hook that blocked; later hooks do not run.
+    pub block: Option<String>,
+    /// Replacement tool arguments; each hook sees the previous hook's replacement.
+    pub arguments: Option<Value>,
+    /// Context from every hook, in order.
+    pub context: Vec<String>,
+}
+
+/// A hook's answer, parsed from its exit status and output.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+struct HookResponse {
+    #[serde(default)]
+    decision: Option<String>,
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    arguments: Option<Value>,
+    #[serde(default)]
+    context: Option<String>,
+}
+
+fn parse_response(code: Option<i32>, stdout: &str, stderr: &str) -> Option<HookResponse> {
+    match code {
+        Some(0) => {}
+        Some(BLOCK_EXIT_CODE) => {
+            return Some(HookResponse {
+                decision: Some("block".to_string()),
+                reason: Some(stderr.trim().to_string()),
+                ..HookResponse::default()
+            });
+        }
+        _ => return None,
+    }
+    let stdout = stdout.trim();
+    if stdout.starts_with('{') {
+        if let Ok(response) = serde_json::from_str(stdout) {
+            return Some(response);
+        }
+    }
+    Some(HookResponse {
+        context: (!stdout.is_empty()).then(|| stdout.to_string()),
+        ..HookResponse::default()
+    })
+}
+
+/// The configured hooks and the directory they run in.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    hooks: Vec<HookConfig>,
+    cwd: PathBuf,
+}
+
+impl Hooks {
+    pub fn new(hooks: Vec<HookConfig>, cwd: &Path) -> Self {
+        Self {
+            hooks,
+            cwd: cwd.to_path_buf(),
+        }
+    }
+
+    /// Whether any hook runs for `event`, so callers can skip building its payload.
+    pub fn has(&self, event: HookEvent) -> bool {
+        self.hooks.iter().any(|hook| hook.event == event)
+    }
+
+    /// Run the hooks for `event` in order. `payload` is sent as the event JSON, with `event`,
+    /// `cwd`, and the current `arguments` filled in.
+    pub async fn run(
+        &self,
+        event: HookEvent,
+        tool_name: Option<&str>,
+        payload: Value,
+    ) -> HookOutcome {
+        let mut outcome = HookOutcome::default();
+        let mut payload = match payload {
+            Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        payload.insert("event".to_string(), json!(event.as_str()));
+        payload.insert("cwd".to_string(), json!(self.cwd.display().to_string()));
+
+        for hook in self
+            .hooks
+            .iter()
+            .filter(|hook| hook.matches(event, tool_name))
+        {
+            if let Some(arguments) = &outcome.arguments {
+                payload.insert("arguments".to_string(), arguments.clone());
+            }
+            let input = Value::Object(payload.clone()).to_string();
+            let response = match run_command(&hook.command, &self.cwd, input, hook.timeout()).await
+            {
+                Ok((code, stdout, stderr)) => {
+                    let response = parse_response(code, &stdout, &stderr);
+                    if response.is_none() {
+                        tracing::warn!(
+                            "{} hook `{}` failed (exit {code:?}): {}",
+                            event.as_str(),
+                            hook.command,
+                            stderr.trim()
+                        );
+                    }
+                    response
+                }
+                Err(err) => {
+                    tracing::warn!("{} hook `{}` failed: {err}", event.as_str(), hook.command);
+                    None
+                }
+            };
+            let Some(response) = response else {
+                continue;
+            };
+            if let Some(context) = response.context.filter(|text| !text.trim().is_empty()) {
+                outcome.context.push(context);
+            }
+            if event != HookEvent::PreToolUse {
+                continue;
+            }
+            if response.arguments.as_ref().is_some_and(Value::is_object) {
+                outcome.arguments = response.arguments;
+            }
+            if response.decision.as_deref() == Some("block") {
+                let reason = response
+                    .reason
+                    .filter(|reason| !reason.is_empty())
+                    .unwrap_or_else(|| format!("blocked by hook `{}`", hook.command));
+                outcome.block = Some(reason);
+                break;
+            }
+        }
+        outcome
+    }
+}
+
+/// Run `command` with `input` on stdin; returns the exit code (`None` when killed) and output.
+async fn run_command(
+    command: &str,
+    cwd: &Path,
+    input: String,
+    timeout: Duration,
+) -> std::result::Result<(Option<i32>, String, String), String> {
+    let command = command.to_string();
+    let cwd = cwd.to_path_buf();
+    let (tx, rx) = oneshot::channel();
+    thread::spawn(move || {
+        let result = (|| {
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .current_dir(&cwd)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|err| err.to_string())?;
+            let mut stdout = child.stdout.take().ok_or("Missing stdout pipe")?;
+            let mut stderr = child.stderr.take().ok_or("Missing stderr pipe")?;
+            let stdout_handle = thread::spawn(move || {
+                let mut buf = Vec::new();
+                let _ = stdout.read_to_end(&mut buf);
+                buf
+            });
+            let stderr_handle = thread::spawn(move || {
+                let mut buf = Vec::new();
+                let _ = stderr.read_to_end(&mut buf);
+                buf
+            });
+            // Written on its own thread so a hook that never reads its input cannot hold up
+            // the timeout below; dropping the pipe afterwards sends EOF.
+            if let Some(mut stdin) = child.stdin.take() {
+                thread::spawn(move || {
+                    // A hook that ignores its input may exit before reading it.
+                    let _ = stdin.write_all(input.as_bytes());
+                });
+            }
+
+            let start = Instant::now();
+            let status = loop {
+                if let Some(status) = child.try_wait().map_err(|err| err.to_string())? {
+                    break Some(status);
+                }
+                if start.elapsed() >= timeout {
+                    crate::tools::kill_process_tree(Some(child.id()));
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break None;
+                }
+                thread::sleep(Duration::from_millis(10));
+            };
+            let stdout =
+                String::from_utf8_lossy(&stdout_handle.join().unwrap_or_default()).to_string();
+            let stderr =
+                String::from_utf8_lossy(&stderr_handle.join().unwrap_or_default()).to_string();
+            Ok((status.and_then(|status| status.code()), stdout, stderr))
+        })();
+        let cx = Cx::for_request();
+        let _ = tx.send(&cx, result);
+    });
+
+    let cx = Cx::for_request();
+    rx.recv(&cx)
+        .await
+        .map_err(|_| "hook task cancelled".to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asupersync::runtime::RuntimeBuilder;
+
+    fn hook(event: HookEvent, matcher: Option<&str>, command: &str) -> HookConfig {
+        HookConfig {
+            event,
+            matcher: matcher.map(str::to_string),
+            command: command.to_string(),
+            timeout_ms: None,
+        }
+    }
+
+    #[test]
+    fn matchers_select_tools_by_glob() {
+        let bash_only = hook(HookEvent::PreToolUse, Some("bash"), "true");
+        assert!(bash_only.matches(HookEvent::PreToolUse, Some("bash")));
+        assert!(!bash_only.matches(HookEvent::PreToolUse, Some("write")));
+        assert!(!bash_only.matches(HookEvent::PostToolUse, Some("bash")));
+        assert!(
+            hook(HookEvent::PreToolUse, Some("*"), "true")
+                .matches(HookEvent::PreToolUse, Some("edit"))
+        );
+        assert!(hook(HookEvent::PreRequest, None, "true").matches(HookEvent::PreRequest, None));
+    }
+
+    #[test]
+    fn responses_follow_exit_status_and_output() {
+        assert_eq!(
+            parse_response(Some(2), "", "no force pushes\n"),
+            Some(HookResponse {
+                decision: Some("block".to_string()),
+                reason: Some("no force pushes".to_string()),
+                ..HookResponse::default()
+            })
+        );
+        assert_eq!(
+            parse_response(Some(0), "branch: main\n", "").and_then(|r| r.context),
+            Some("branch: main".to_string())
+        );
+        let json =
+            parse_response(Some(0), r#"{"arguments": {"command": "ls"}}"#, "").expect("response");
+        assert_eq!(json.arguments, Some(json!({ "command": "ls" })));
+        assert_eq!(parse_response(Some(1), "ignored", "boom"), None);
+        assert_eq!(parse_response(None, "", ""), None);
+    }
+
+    #[test]
+    fn hooks_chain_arguments_and_stop_at_a_block() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let hooks = Hooks::new(
+            vec![
+                hook(
+                    HookEvent::PreToolUse,
+                    Some("bash"),
+                    r#"echo '{"arguments": {"command": "ls -la"}, "context": "rewritten"}'"#,
+                ),
+                hook(
+                    HookEvent::PreToolUse,
+                    None,
+                    r#"grep -q '"ls -la"' && echo 'saw it' >&2 && exit 2"#,
+                ),
+                hook(HookEvent::PreToolUse, None, "echo unreachable"),
+            ],
+            Path::new("."),
+        );
+
+        let outcome = runtime.block_on(hooks.run(
+            HookEvent::PreToolUse,
+            Some("bash"),
+            json!({ "toolName": "bash", "arguments": { "command": "ls" } }),
+        ));
+        assert_eq!(outcome.arguments, Some(json!({ "command": "ls -la" })));
+        assert_eq!(outcome.block.as_deref(), Some("saw it"));
+        assert_eq!(outcome.context, vec!["rewritten".to_string()]);
+    }
+
+    #[test]
+    fn large_input_does_not_block_hooks_that_ignore_stdin() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        // Well past the 64 KiB a pipe buffers, so writing it in line would block.
+        let input = "x".repeat(256 * 1024);
+
+        let (status, stdout, _) = runtime
+            .block_on(run_command(
+                "echo ready",
+                Path::new("."),
+                input.clone(),
+                DEFAULT_HOOK_TIMEOUT,
+            ))
+            .expect("run hook");
+        assert_eq!((status, stdout.trim()), (Some(0), "ready"));
+
+        let start = Instant::now();
+        let (status, _, _) = runtime
+            .block_on(run_command(
+                "sleep 10",
+                Path::new("."),
+                input.clone(),
+                Duration::from_millis(200),
+            ))
+            .expect("run hook");
+        assert_eq!(status, None, "the hook should time out");
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let (status, stdout, _) = runtime
+            .block_on(run_command(
+                "wc -c",
+                Path::new("."),
+                input,
+                DEFAULT_HOOK_TIMEOUT,
+            ))
+            .expect("run hook");
+        assert_eq!((status, stdout.trim()), (Some(0), "262144"));
+    }
+}
@@ -54,6 +54,10 @@ impl Client {
         RequestBuilder::new(self, Method::Get, url)
     }
 
+    pub fn put(&self, url: &str) -> RequestBuilder<'_> {
+        RequestBuilder::new(self, Method::Put, url)
+    }
+
     #[must_use]
     pub fn with_vcr(mut self, recorder: VcrRecorder) -> Self {
         self.vcr = Some(recorder);
@@ -75,6 +79,7 @@ impl Default for Client {
 enum Method {
     Get,
     Post,
+    Put,
 }
 
 impl Method {
@@ -82,6 +87,7 @@ impl Method {
         match self {
             Self::Get => "GET",
             Self::Post => "POST",
+            Self::Put => "PUT",
         }
     }
 }
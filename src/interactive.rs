@@ -12,7 +12,7 @@
 //! - **Markdown rendering**: Assistant responses rendered with syntax highlighting
 
 use asupersync::Cx;
-use asupersync::channel::mpsc;
+use asupersync::channel::{mpsc, oneshot};
 use asupersync::runtime::RuntimeHandle;
 use asupersync::sync::Mutex;
 use asupersync::time::{sleep, wall_now};
@@ -22,7 +22,8 @@ use bubbles::spinner::{SpinnerModel, spinners};
 use bubbles::textarea::TextArea;
 use bubbles::viewport::Viewport;
 use bubbletea::{
-    Cmd, KeyMsg, KeyType, Message, Model as BubbleteaModel, Program, WindowSizeMsg, batch, quit,
+    Cmd, KeyMsg, KeyType, Message, Model as BubbleteaModel, Program, QuitMsg, WindowSizeMsg, batch,
+    quit,
 };
 use chrono::Utc;
 use crossterm::{cursor, terminal};
@@ -30,9 +31,10 @@ use futures::future::BoxFuture;
 use glamour::{Renderer as MarkdownRenderer, StyleConfig as GlamourStyleConfig};
 use glob::Pattern;
 use serde_json::{Value, json};
+use unicode_width::UnicodeWidthChar;
 use url::Url;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::ffi::OsString;
 use std::fmt::Write as _;
 use std::path::{Path, PathBuf};
@@ -40,12 +42,16 @@ use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::agent::{AbortHandle, Agent, AgentEvent, QueueMode};
+use crate::agent::{
+    AbortHandle, Agent, AgentEvent, HUNK_REVIEW_TOOLS, HunkReviewRequest, HunkReviewer,
+    PauseHandle, QueueMode, ToolApprovalDecision, ToolApprovalRequest, ToolApprover,
+};
 use crate::autocomplete::{
     AutocompleteCatalog, AutocompleteItem, AutocompleteItemKind, AutocompleteProvider,
     AutocompleteResponse,
 };
-use crate::config::{Config, SettingsScope};
+use crate::config::{Config, SettingSource, SettingsScope, ThinkingDisplay};
+use crate::error::Error;
 use crate::extension_events::{InputEventOutcome, apply_input_event_response};
 use crate::extensions::{
     EXTENSION_EVENT_TIMEOUT_MS, ExtensionEventName, ExtensionManager, ExtensionSession,
@@ -54,17 +60,26 @@ use crate::extensions::{
 use crate::keybindings::{AppAction, KeyBinding, KeyBindings};
 use crate::model::{
     AssistantMessageEvent, ContentBlock, ImageContent, Message as ModelMessage, StopReason,
-    TextContent, ThinkingLevel, Usage, UserContent, UserMessage,
+    TextContent, ThinkingLevel, ToolCall, Usage, UserContent, UserMessage,
 };
+use crate::model_selector::ModelSelectorOverlay;
 use crate::models::{ModelEntry, ModelRegistry, default_models_path};
+use crate::notifications::{Notification, Notifier};
 use crate::package_manager::PackageManager;
+use crate::provider::gateway_cost_drift;
+use crate::provider_health::{self, HealthStatus, ProviderHealth};
 use crate::providers;
 use crate::resources::{DiagnosticKind, ResourceCliOptions, ResourceDiagnostic, ResourceLoader};
 use crate::session::{Session, SessionEntry, SessionMessage, bash_execution_to_text};
 use crate::session_index::{SessionIndex, SessionMeta};
 use crate::session_picker::delete_session_file;
 use crate::theme::{Theme, TuiStyles};
-use crate::tools::{process_file_arguments, resolve_read_path};
+use crate::tools::{
+    DiffHunk, ProcessedFiles, ToolUpdate, apply_accepted_hunks, diff_hunks, format_size,
+    process_file_arguments, resolve_read_path, truncate_head, truncate_tail,
+};
+use crate::tui::PiConsole;
+use crate::worktree_snapshot::WorktreeSnapshot;
 
 #[cfg(all(feature = "clipboard", feature = "image-resize"))]
 use arboard::Clipboard as ArboardClipboard;
@@ -89,6 +104,7 @@ pub enum SlashCommand {
     History,
     Export,
     Session,
+    Stats,
     Settings,
     Theme,
     Resume,
@@ -98,18 +114,58 @@ pub enum SlashCommand {
     Hotkeys,
     Changelog,
     Tree,
+    Timeline,
+    Branch,
+    Label,
+    Labels,
+    Goto,
+    Incognito,
+    Pin,
+    Pins,
     Fork,
     Compact,
     Reload,
     Share,
+    System,
+    Tools,
+    Save,
+    Restore,
+    Attach,
+    Note,
+    Notes,
+    Tour,
 }
 
 impl PiApp {
     /// Scroll the conversation viewport to the bottom.
     fn scroll_to_bottom(&mut self) {
         let content = self.build_conversation_content();
+        self.conversation_line_count = content.lines().count();
         self.conversation_viewport.set_content(&content);
         self.conversation_viewport.goto_bottom();
+        self.unseen_lines = 0;
+    }
+
+    /// Whether the viewport shows the last line of the conversation.
+    fn viewport_at_bottom(&self) -> bool {
+        self.conversation_viewport.y_offset() + self.conversation_viewport.height
+            >= self.conversation_line_count
+    }
+
+    /// Refresh the viewport for streamed output, following it only when the user is already
+    /// at the bottom. Otherwise the scroll position stays put and new lines are counted.
+    fn follow_conversation(&mut self) {
+        let was_at_bottom = self.viewport_at_bottom();
+        let content = self.build_conversation_content();
+        let line_count = content.lines().count();
+        self.conversation_viewport.set_content(&content);
+        if was_at_bottom {
+            self.conversation_viewport.goto_bottom();
+            self.unseen_lines = 0;
+        } else {
+            self.unseen_lines += line_count.saturating_sub(self.conversation_line_count);
+        }
+        self.conversation_line_count = line_count;
     }
 
     fn scroll_to_last_match(&mut self, needle: &str) {
@@ -130,10 +186,40 @@ impl PiApp {
         }
     }
 
+    /// Find a theme contributed by an extension.
+    fn extension_theme(&self, name: &str) -> Option<Theme> {
+        let name = name.trim();
+        self.extensions
+            .as_ref()?
+            .list_themes()
+            .into_iter()
+            .find(|theme| !name.is_empty() && theme.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Like [`Theme::resolve_spec`], falling back to extension themes.
+    fn resolve_theme_spec(&self, spec: &str) -> crate::error::Result<Theme> {
+        Theme::resolve_spec(spec, &self.cwd).or_else(|err| self.extension_theme(spec).ok_or(err))
+    }
+
+    /// Like [`Theme::resolve`], falling back to extension themes.
+    fn resolve_configured_theme(&self) -> Theme {
+        let spec = self.config.theme.as_deref().unwrap_or_default();
+        match Theme::resolve_spec(spec, &self.cwd) {
+            Ok(theme) => theme,
+            Err(_) => self
+                .extension_theme(spec)
+                .unwrap_or_else(|| Theme::resolve(&self.config, &self.cwd)),
+        }
+    }
+
     fn apply_theme(&mut self, theme: Theme) {
         self.theme = theme;
         self.styles = self.theme.tui_styles();
         self.markdown_style = self.theme.glamour_style_config();
+        self.conversation_cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clear();
         self.spinner =
             SpinnerModel::with_spinner(spinners::dot()).style(self.styles.accent.clone());
 
@@ -145,12 +231,16 @@ impl PiApp {
         let mut names = Vec::new();
         names.push("dark".to_string());
         names.push("light".to_string());
+        names.push("solarized".to_string());
 
         for path in Theme::discover_themes(&self.cwd) {
             if let Ok(theme) = Theme::load(&path) {
                 names.push(theme.name);
             }
         }
+        if let Some(manager) = &self.extensions {
+            names.extend(manager.list_themes().into_iter().map(|theme| theme.name));
+        }
 
         names.sort_by_key(|a| a.to_ascii_lowercase());
         names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
@@ -164,7 +254,7 @@ impl PiApp {
             };
             let _ = writeln!(output, "{marker}{name}");
         }
-        output.push_str("\nUse /theme <name> to switch");
+        output.push_str("\nUse /theme <name> to switch, /theme preview <name> to try one out");
         output
     }
 
@@ -364,7 +454,7 @@ impl PiApp {
                     json!({ "hide_thinking_block": next }),
                 ) {
                     self.config.hide_thinking_block = Some(next);
-                    self.thinking_visible = !next;
+                    self.thinking_display = self.config.thinking_display();
                     self.scroll_to_bottom();
                     self.status_message =
                         Some(format!("Updated hideThinkingBlock: {}", bool_label(next)));
@@ -481,11 +571,25 @@ impl PiApp {
 
         let total_tokens = self.total_usage.total_tokens;
         let total_cost = self.total_usage.cost.total;
-        let cost_str = if total_cost > 0.0 {
+        let mut cost_str = if total_cost > 0.0 {
             format!("${total_cost:.4}")
         } else {
             "$0.0000".to_string()
         };
+        if let Some(gateway) = self.total_usage.cost.gateway {
+            let _ = write!(cost_str, "\n  gatewayCost: ${gateway:.4}");
+        }
+        if let Some(remaining) = self.total_usage.cost.gateway_budget_remaining {
+            let _ = write!(cost_str, "\n  gatewayBudgetRemaining: ${remaining:.4}");
+        }
+        let usage = &self.total_usage;
+        if usage.cache_read > 0 || usage.cache_write > 0 {
+            let _ = write!(
+                cost_str,
+                "\n  cache: {} read, {} written (saved ${:.4})",
+                usage.cache_read, usage.cache_write, usage.cost.cache_savings
+            );
+        }
 
         format!(
             "Session info:\n  file: {file}\n  id: {id}\n  name: {name}\n  model: {model}\n  thinking: {thinking}\n  messageCount: {message_count}\n  tokens: {total_tokens}\n  cost: {cost_str}",
@@ -494,6 +598,22 @@ impl PiApp {
         )
     }
 
+    /// Compare a turn's gateway-reported cost with the local estimate from model pricing.
+    fn warn_on_gateway_cost_drift(&mut self, usage: &Usage, gateway: f64) {
+        let local = self.model_entry.model.calculate_cost(
+            usage.input,
+            usage.output,
+            usage.cache_read,
+            usage.cache_write,
+        );
+        if let Some(drift) = gateway_cost_drift(gateway, local) {
+            self.status_message = Some(format!(
+                "Gateway cost ${gateway:.4} differs from local estimate ${local:.4} ({:+.0}%)",
+                drift * 100.0
+            ));
+        }
+    }
+
     fn format_settings_summary(&self) -> String {
         let theme_setting = self
             .config
@@ -548,6 +668,7 @@ impl PiApp {
             "  hideThinkingBlock: {}",
             bool_label(hide_thinking_block)
         );
+        let _ = writeln!(output, "  ui.thinking: {}", self.thinking_display.as_str());
         let _ = writeln!(
             output,
             "  showHardwareCursor: {}",
@@ -613,6 +734,22 @@ impl PiApp {
         }
     }
 
+    fn resolve_pin_path(&self, raw: &str) -> PathBuf {
+        let path = PathBuf::from(raw);
+        if path.is_absolute() {
+            path
+        } else {
+            self.cwd.join(path)
+        }
+    }
+
+    fn display_pin_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.cwd)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
     fn spawn_save_session(&self) {
         if !self.save_enabled {
             return;
@@ -643,6 +780,7 @@ impl PiApp {
     fn maybe_trigger_autocomplete(&mut self) {
         if self.agent_state != AgentState::Idle
             || self.session_picker.is_some()
+            || self.model_selector.is_some()
             || self.settings_ui.is_some()
         {
             self.autocomplete.close();
@@ -692,13 +830,27 @@ impl PiApp {
             );
         }
 
+        // Keep the reader's place: follow the bottom if they were there, otherwise keep the
+        // same relative position in the re-wrapped transcript.
+        let was_at_bottom = self.viewport_at_bottom();
+        let previous_offset = self.conversation_viewport.y_offset();
+        let previous_lines = self.conversation_line_count.max(1);
+
         let viewport_height = self.term_height.saturating_sub(9);
         let mut viewport = Viewport::new(self.term_width.saturating_sub(2), viewport_height);
         viewport.mouse_wheel_enabled = true;
         viewport.mouse_wheel_delta = 3;
         self.conversation_viewport = viewport;
 
-        self.scroll_to_bottom();
+        if was_at_bottom {
+            self.scroll_to_bottom();
+            return;
+        }
+        let content = self.build_conversation_content();
+        self.conversation_line_count = content.lines().count();
+        self.conversation_viewport.set_content(&content);
+        self.conversation_viewport
+            .set_y_offset(previous_offset * self.conversation_line_count / previous_lines);
     }
 
     fn accept_autocomplete(&mut self, item: &AutocompleteItem) {
@@ -767,49 +919,313 @@ impl PiApp {
         }
 
         let pasted: String = key.runes.iter().collect();
-        let Some((insert, count)) = self.normalize_pasted_paths(&pasted) else {
-            return false;
+        let Some(files) = self.detect_dropped_files(&pasted) else {
+            return self.insert_pasted_text(&pasted);
         };
 
+        self.offer_file_drop(pasted, files);
+        true
+    }
+
+    /// Ask whether to attach dropped files; Enter attaches, Esc keeps the raw text.
+    fn offer_file_drop(&mut self, raw: String, files: Vec<DroppedFile>) {
+        let summary = files
+            .iter()
+            .map(|file| format!("{} ({})", file.display, file.kind.label()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.status_message = Some(format!(
+            "Attach {summary}? Enter: attach, Esc: paste as text"
+        ));
+        self.pending_file_drop = Some(PendingFileDrop { raw, files });
+    }
+
+    /// Resolve a pending file-drop offer: attach the files or insert the raw pasted text.
+    fn resolve_file_drop(&mut self, pending: PendingFileDrop, attach: bool) {
+        if !attach {
+            if !self.insert_pasted_text(&pending.raw) {
+                self.input.insert_string(pending.raw.trim());
+            }
+            self.status_message = None;
+            return;
+        }
+
+        let mut insert = pending
+            .files
+            .iter()
+            .map(|file| match file.kind {
+                DropKind::Image | DropKind::Inline => format_file_ref(&file.display),
+                DropKind::Reference => format!("`{}`", file.display),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        insert.push(' ');
         self.input.insert_string(&insert);
-        if count > 0 {
+
+        let count = pending.files.len();
+        let mut status = format!("Attached {count} file{}", if count == 1 { "" } else { "s" });
+        if pending
+            .files
+            .iter()
+            .any(|file| file.kind == DropKind::Reference)
+        {
+            status.push_str(" (large files are referenced by path, not inlined)");
+        }
+        self.status_message = Some(status);
+    }
+
+    /// Show a destructive tool call; the next y/n/a key answers it.
+    fn prompt_tool_approval(&mut self, request: ToolApprovalRequest) {
+        let target = request.path.clone().unwrap_or_else(|| {
+            request
+                .args
+                .get("command")
+                .and_then(Value::as_str)
+                .map(|command| truncate(command.lines().next().unwrap_or_default(), 60))
+                .unwrap_or_default()
+        });
+        let review = if HUNK_REVIEW_TOOLS.contains(&request.tool_name.as_str()) {
+            ", r: review hunks"
+        } else {
+            ""
+        };
+        self.status_message = Some(format!(
+            "Allow {} `{target}`? y: yes, n: no, a: always{review}",
+            request.tool_name
+        ));
+        if self.last_key_at.elapsed() >= self.config.notification_unattended_after() {
+            self.notify(Notification::permission_request(
+                &request.tool_name,
+                &target,
+            ));
+        }
+        self.tool_approval_prompt = Some(request);
+    }
+
+    /// Send the user's answer back to the waiting agent; "always" is saved to project settings.
+    fn resolve_tool_approval(
+        &mut self,
+        request: &ToolApprovalRequest,
+        decision: ToolApprovalDecision,
+    ) {
+        self.status_message = Some(match decision {
+            ToolApprovalDecision::Allow => format!("Allowed {}", request.tool_name),
+            ToolApprovalDecision::Deny => format!("Denied {}", request.tool_name),
+            ToolApprovalDecision::AlwaysAllow => self.remember_tool_approval(request.always_rule()),
+            ToolApprovalDecision::Review => {
+                // Snapshot the file now so the finished change can be split into hunks.
+                let path = request.path.as_deref().unwrap_or_default();
+                let before =
+                    std::fs::read_to_string(resolve_read_path(path, &self.cwd)).unwrap_or_default();
+                self.review_snapshots
+                    .insert(request.tool_call_id.clone(), before);
+                format!(
+                    "Allowed {}; review its hunks when it finishes",
+                    request.tool_name
+                )
+            }
+        });
+
+        let sender = self
+            .pending_tool_approvals
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.remove(&request.tool_call_id));
+        if let Some(sender) = sender {
+            let cx = Cx::for_request();
+            let _ = sender.send(&cx, decision);
+        }
+    }
+
+    /// Open hunk review for a finished `write`/`edit`, or reply at once when nothing changed.
+    fn start_hunk_review(&mut self, request: HunkReviewRequest) {
+        let old_content = self
+            .review_snapshots
+            .remove(&request.tool_call_id)
+            .unwrap_or_default();
+        let path = resolve_read_path(&request.path, &self.cwd);
+        let new_content = std::fs::read_to_string(&path).unwrap_or_default();
+        let hunks = diff_hunks(&old_content, &new_content);
+        if hunks.is_empty() {
+            self.reply_hunk_review(&request.tool_call_id, None);
+            return;
+        }
+        self.hunk_review = Some(HunkReview {
+            decisions: vec![None; hunks.len()],
+            request,
+            path,
+            old_content,
+            new_content,
+            hunks,
+            cursor: 0,
+        });
+        self.show_hunk_review_status();
+    }
+
+    fn show_hunk_review_status(&mut self) {
+        if let Some(review) = &self.hunk_review {
             self.status_message = Some(format!(
-                "Attached {} file{}",
-                count,
-                if count == 1 { "" } else { "s" }
+                "Hunk {} of {} in {}: y keep, n revert, j/k move, Enter finish",
+                review.cursor + 1,
+                review.hunks.len(),
+                review.request.path
             ));
         }
+    }
+
+    /// Keep (`true`) or revert (`false`) the current hunk and move on; finishes after the last
+    /// undecided hunk.
+    fn decide_hunk(&mut self, keep: bool) {
+        let Some(review) = self.hunk_review.as_mut() else {
+            return;
+        };
+        review.decisions[review.cursor] = Some(keep);
+        let next = review
+            .decisions
+            .iter()
+            .skip(review.cursor)
+            .position(Option::is_none)
+            .map(|offset| review.cursor + offset)
+            .or_else(|| review.decisions.iter().position(Option::is_none));
+        if let Some(next) = next {
+            review.cursor = next;
+            self.show_hunk_review_status();
+        } else {
+            self.finish_hunk_review();
+        }
+    }
+
+    /// Write back only the kept hunks and tell the agent what was reverted.
+    fn finish_hunk_review(&mut self) {
+        let Some(review) = self.hunk_review.take() else {
+            return;
+        };
+        let accepted: Vec<bool> = review
+            .decisions
+            .iter()
+            .map(|decision| decision.unwrap_or(true))
+            .collect();
+        let mut note = hunk_review_summary(&review.request.path, &review.hunks, &accepted);
+        if note.is_some() {
+            let content = apply_accepted_hunks(&review.old_content, &review.new_content, &accepted);
+            if let Err(err) = std::fs::write(&review.path, content) {
+                note = Some(format!(
+                    "The user asked to revert part of the change to `{}`, but writing the file failed ({err}); it still contains the full change.",
+                    review.request.path
+                ));
+            }
+        }
+        let kept = accepted.iter().filter(|keep| **keep).count();
+        self.status_message = Some(format!(
+            "Kept {kept} of {} hunks in {}",
+            accepted.len(),
+            review.request.path
+        ));
+        self.reply_hunk_review(&review.request.tool_call_id, note);
+    }
+
+    fn reply_hunk_review(&self, tool_call_id: &str, note: Option<String>) {
+        let sender = self
+            .pending_hunk_reviews
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.remove(tool_call_id));
+        if let Some(sender) = sender {
+            let cx = Cx::for_request();
+            let _ = sender.send(&cx, note);
+        }
+    }
+
+    /// One answer during hunk review, typed (screen reader mode) or mapped from a key.
+    fn handle_hunk_review_input(&mut self, input: &str) {
+        match input.to_lowercase().as_str() {
+            "y" | "yes" => self.decide_hunk(true),
+            "n" | "no" => self.decide_hunk(false),
+            "j" => self.move_hunk_cursor(1),
+            "k" => self.move_hunk_cursor(-1),
+            "" | "done" => self.finish_hunk_review(),
+            _ => self.show_hunk_review_status(),
+        }
+    }
+
+    fn move_hunk_cursor(&mut self, delta: isize) {
+        if let Some(review) = self.hunk_review.as_mut() {
+            review.cursor = review
+                .cursor
+                .saturating_add_signed(delta)
+                .min(review.hunks.len() - 1);
+        }
+        self.show_hunk_review_status();
+    }
+
+    fn remember_tool_approval(&mut self, rule: String) -> String {
+        let rules = {
+            let Ok(mut rules) = self.tool_approval_rules.lock() else {
+                return format!("Allowed {rule}");
+            };
+            if !rules.contains(&rule) {
+                rules.push(rule.clone());
+            }
+            rules.clone()
+        };
+        self.config
+            .tool_approval
+            .get_or_insert_with(Default::default)
+            .always_allow = Some(rules.clone());
+        if self.persist_project_settings_patch(
+            "toolApproval",
+            json!({ "tool_approval": { "always_allow": rules } }),
+        ) {
+            format!("Always allowing {rule} (saved to project settings)")
+        } else {
+            self.status_message.clone().unwrap_or_default()
+        }
+    }
+
+    /// Insert a multi-line paste verbatim, switching to multi-line mode so the
+    /// embedded newlines don't submit the message.
+    fn insert_pasted_text(&mut self, pasted: &str) -> bool {
+        let text = pasted.replace("\r\n", "\n").replace('\r', "\n");
+        if !text.contains('\n') {
+            return false;
+        }
+
+        self.input.insert_string(&text);
+        if self.input_mode == InputMode::SingleLine {
+            self.input_mode = InputMode::MultiLine;
+            self.input.set_height(6);
+        }
+        let lines = text.trim_end_matches('\n').lines().count();
+        self.status_message = Some(format!(
+            "Pasted {} line{}",
+            lines,
+            if lines == 1 { "" } else { "s" }
+        ));
         true
     }
 
-    fn normalize_pasted_paths(&self, pasted: &str) -> Option<(String, usize)> {
-        let mut refs = Vec::new();
+    /// Files named by pasted text, if every non-empty line is the path of an existing file.
+    fn detect_dropped_files(&self, pasted: &str) -> Option<Vec<DroppedFile>> {
+        let mut files = Vec::new();
         for line in pasted.lines() {
             let trimmed = line.trim();
             if trimmed.is_empty() {
                 continue;
             }
-            let path = self.normalize_pasted_path(trimmed)?;
-            refs.push(path);
-        }
-
-        if refs.is_empty() {
-            return None;
-        }
-
-        let mut insert = refs
-            .iter()
-            .map(|path| format_file_ref(path))
-            .collect::<Vec<_>>()
-            .join(" ");
-        if !insert.ends_with(' ') {
-            insert.push(' ');
+            let resolved = self.normalize_pasted_path(trimmed)?;
+            if !resolved.is_file() {
+                return None;
+            }
+            files.push(DroppedFile {
+                display: path_for_display(&resolved, &self.cwd),
+                kind: DropKind::classify(&resolved),
+            });
         }
 
-        Some((insert, refs.len()))
+        (!files.is_empty()).then_some(files)
     }
 
-    fn normalize_pasted_path(&self, raw: &str) -> Option<String> {
+    fn normalize_pasted_path(&self, raw: &str) -> Option<PathBuf> {
         let trimmed = raw.trim();
         if trimmed.is_empty() || trimmed.starts_with('@') {
             return None;
@@ -823,7 +1239,7 @@ impl PiApp {
             return None;
         }
 
-        Some(path_for_display(&resolved, &self.cwd))
+        Some(resolved)
     }
 
     fn insert_file_ref_path(&mut self, path: &Path) {
@@ -955,6 +1371,7 @@ impl PiApp {
                     }
                 };
                 agent_guard.replace_messages(messages_for_agent);
+                agent_guard.stream_options_mut().session_id = Some(new_session_id.clone());
             }
 
             let (messages, usage) = {
@@ -994,20 +1411,77 @@ impl PiApp {
         None
     }
 
+    /// The settings layer whose `default_provider`/`default_model` names the current model.
+    fn resolve_model_default_source(&self) -> Option<(String, SettingSource)> {
+        let default = format!(
+            "{}/{}",
+            self.config.default_provider.as_deref()?,
+            self.config.default_model.as_deref()?
+        );
+        if default != self.model {
+            return None;
+        }
+        let config_path = std::env::var_os("PI_CONFIG_PATH").map(PathBuf::from);
+        let explained =
+            Config::explain_with_roots(config_path.as_deref(), &Config::global_dir(), &self.cwd)
+                .ok()?;
+        explained
+            .iter()
+            .find(|setting| setting.key == "default_model")
+            .map(|setting| (default, setting.source))
+    }
+
     fn render_header(&self) -> String {
-        let model_label = format!("({})", self.model);
-        format!(
-            "  {} {}\n",
+        let model_label = match &self.model_default_source {
+            Some((model, source)) if *model == self.model => {
+                format!("({} · {} default)", self.model, source.label())
+            }
+            _ => format!("({})", self.model),
+        };
+        let mut header = format!(
+            "  {} {}",
             self.styles.title.render("Pi"),
             self.styles.muted.render(&model_label)
-        )
+        );
+        if self.config.status_line_show_cwd() {
+            let _ = write!(
+                header,
+                "  {}",
+                self.styles.muted.render(&display_cwd(&self.cwd))
+            );
+        }
+        if let Some(git) = &self.git_status {
+            let style = if git.dirty || git.staged {
+                &self.styles.warning
+            } else {
+                &self.styles.accent
+            };
+            let _ = write!(header, "  {}", style.render(&git.label()));
+        }
+        let thinking_level = self.current_thinking_level();
+        if thinking_level != ThinkingLevel::Off {
+            let _ = write!(
+                header,
+                "  {}",
+                self.styles
+                    .accent
+                    .render(&format!("thinking: {thinking_level}"))
+            );
+        }
+        if self
+            .session
+            .try_lock()
+            .is_ok_and(|guard| guard.is_incognito())
+        {
+            let _ = write!(header, "  {}", self.styles.warning_bold.render("incognito"));
+        }
+        header.push('\n');
+        header
     }
 
-    fn render_input(&self) -> String {
-        let mut output = String::new();
-
-        let thinking_level = self
-            .session
+    /// Thinking level from the session header, falling back to the configured default.
+    fn current_thinking_level(&self) -> ThinkingLevel {
+        self.session
             .try_lock()
             .ok()
             .and_then(|guard| guard.header.thinking_level.clone())
@@ -1018,7 +1492,57 @@ impl PiApp {
                     .as_deref()
                     .and_then(|level| level.parse::<ThinkingLevel>().ok())
             })
-            .unwrap_or(ThinkingLevel::Off);
+            .unwrap_or(ThinkingLevel::Off)
+    }
+
+    /// Replace the agent's system prompt and record the change in the session.
+    fn set_system_prompt(&mut self, prompt: &str) {
+        let Ok(mut agent_guard) = self.agent.try_lock() else {
+            self.status_message = Some("Agent busy; try again".to_string());
+            return;
+        };
+        if agent_guard.system_prompt() == Some(prompt) {
+            self.status_message = Some("System prompt unchanged".to_string());
+            return;
+        }
+        let previous = agent_guard.system_prompt().map(ToString::to_string);
+        agent_guard.set_system_prompt(Some(prompt.to_string()));
+        drop(agent_guard);
+
+        if let Ok(mut session_guard) = self.session.try_lock() {
+            session_guard.append_custom_entry(
+                "system_prompt".to_string(),
+                Some(json!({ "systemPrompt": prompt, "previous": previous })),
+            );
+            drop(session_guard);
+            self.spawn_save_session();
+        }
+
+        self.status_message = Some("System prompt updated".to_string());
+    }
+
+    /// Apply `level` to the agent's stream options and record it in the session.
+    fn set_thinking_level(&mut self, level: ThinkingLevel) {
+        let Ok(mut session_guard) = self.session.try_lock() else {
+            self.status_message = Some("Session busy; try again".to_string());
+            return;
+        };
+        session_guard.header.thinking_level = Some(level.to_string());
+        session_guard.append_thinking_level_change(level.to_string());
+        drop(session_guard);
+        self.spawn_save_session();
+
+        if let Ok(mut agent_guard) = self.agent.try_lock() {
+            agent_guard.stream_options_mut().thinking_level = Some(level);
+        }
+
+        self.status_message = Some(format!("Thinking level: {level}"));
+    }
+
+    fn render_input(&self) -> String {
+        let mut output = String::new();
+
+        let thinking_level = self.current_thinking_level();
 
         let input_text = self.input.value();
         let is_bash_mode = parse_bash_command(&input_text).is_some();
@@ -1108,7 +1632,9 @@ impl PiApp {
 
     fn render_footer(&self) -> String {
         let total_cost = self.total_usage.cost.total;
-        let cost_str = if total_cost > 0.0 {
+        let cost_str = if let Some(gateway) = self.total_usage.cost.gateway {
+            format!(" (${gateway:.4} gateway)")
+        } else if total_cost > 0.0 {
             format!(" (${total_cost:.4})")
         } else {
             String::new()
@@ -1135,7 +1661,41 @@ impl PiApp {
         if footer.chars().count() > max_width {
             footer = truncate(&footer, max_width);
         }
-        format!("\n  {}\n", self.styles.muted.render(&footer))
+        if self.status_segments.is_empty() {
+            return format!("\n  {}\n", self.styles.muted.render(&footer));
+        }
+
+        let segments = self
+            .status_segments
+            .values()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("  ·  ");
+        format!(
+            "\n  {}\n  {}\n",
+            self.styles
+                .muted_bold
+                .render(&truncate(&segments, max_width)),
+            self.styles.muted.render(&footer)
+        )
+    }
+
+    fn render_typeahead_queue(&self) -> Option<String> {
+        let next = self.pending_inputs.front()?;
+        let max_preview = self.term_width.saturating_sub(24).max(20);
+        let preview = match next {
+            PendingInput::Text(text) => queued_message_preview(text, max_preview),
+            PendingInput::Content(content) => {
+                queued_message_preview(&content_blocks_to_text(content), max_preview)
+            }
+        };
+        Some(format!(
+            "  {} {}\n",
+            self.styles
+                .muted_bold
+                .render(&format!("queued: {}", self.pending_inputs.len())),
+            self.styles.muted.render(&format!("next → {preview}"))
+        ))
     }
 
     fn render_pending_message_queue(&self) -> Option<String> {
@@ -1314,21 +1874,311 @@ impl PiApp {
         output
     }
 
-    fn render_session_picker(&self, picker: &SessionPickerOverlay) -> String {
-        let mut output = String::new();
+    /// Render tool output, colorizing added/removed lines when it contains a diff.
+    fn render_tool_text(&self, text: &str) -> String {
+        if !looks_like_diff(text) {
+            return self.styles.muted.render(text);
+        }
+        text.lines()
+            .map(|line| match classify_diff_line(line) {
+                DiffLineKind::Added => self.styles.diff_added.render(line),
+                DiffLineKind::Removed => self.styles.diff_removed.render(line),
+                DiffLineKind::Hunk => self.styles.diff_hunk.render(line),
+                DiffLineKind::Context => self.styles.muted.render(line),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-        let _ = writeln!(
-            output,
-            "\n  {}\n",
-            self.styles.title.render("Select a session to resume")
-        );
+    /// Session spend in USD, preferring the cost reported by a gateway.
+    fn session_spend(&self) -> f64 {
+        self.total_usage
+            .cost
+            .gateway
+            .unwrap_or(self.total_usage.cost.total)
+    }
 
-        if picker.sessions.is_empty() {
-            let _ = writeln!(
-                output,
-                "  {}",
-                self.styles
-                    .muted
+    /// Send `notification` in the background if a configured sink wants it.
+    fn notify(&self, notification: Notification) {
+        if !self.notifier.wants(notification.kind) {
+            return;
+        }
+        let notifier = self.notifier.clone();
+        self.runtime_handle.spawn(async move {
+            notifier.send(&notification).await;
+        });
+    }
+
+    /// Send one `budget` notification when session spend crosses new thresholds.
+    fn check_budget_notifications(&mut self) {
+        let spent = self.session_spend();
+        let thresholds = self.config.notification_budget_thresholds();
+        let crossed = thresholds
+            .iter()
+            .filter(|threshold| **threshold <= spent)
+            .count();
+        if crossed > self.budget_thresholds_notified {
+            self.budget_thresholds_notified = crossed;
+            self.notify(Notification::budget(spent, thresholds[crossed - 1]));
+        }
+    }
+
+    /// Apply the `budget` policy: once session spend passes the limit, switch to the
+    /// configured cheaper model (once per session) and note it in the transcript.
+    fn check_budget_downgrade(&mut self) {
+        if self.budget_downgraded {
+            return;
+        }
+        let Some((limit, target)) = self.config.budget_downgrade() else {
+            return;
+        };
+        let spent = self.session_spend();
+        if spent < limit {
+            return;
+        }
+
+        let target_lower = target.to_lowercase();
+        let Some(next) = self
+            .available_models
+            .iter()
+            .chain(&self.model_scope)
+            .find(|entry| {
+                let full = format!("{}/{}", entry.model.provider, entry.model.id);
+                full.to_lowercase() == target_lower || entry.model.id.to_lowercase() == target_lower
+            })
+            .cloned()
+        else {
+            self.budget_downgraded = true;
+            self.status_message = Some(format!(
+                "Budget of ${limit:.2} reached, but downgrade model {target} is not available"
+            ));
+            return;
+        };
+        if model_entry_matches(&next, &self.model_entry) {
+            self.budget_downgraded = true;
+            return;
+        }
+
+        let previous = self.model.clone();
+        self.switch_model(next);
+        if self.model == previous {
+            // Agent or session was busy; the next check retries.
+            return;
+        }
+        self.budget_downgraded = true;
+
+        let notice = format!(
+            "Session spend ${spent:.2} passed the ${limit:.2} budget; switched from {previous} to {}.",
+            self.model
+        );
+        if let Ok(mut session_guard) = self.session.try_lock() {
+            session_guard.append_custom_entry(
+                "budget_downgrade".to_string(),
+                Some(json!({
+                    "from": previous,
+                    "to": self.model,
+                    "spentUsd": spent,
+                    "limitUsd": limit,
+                })),
+            );
+            drop(session_guard);
+            self.spawn_save_session();
+        }
+        self.messages.push(ConversationMessage {
+            role: MessageRole::System,
+            content: notice,
+            thinking: None,
+        });
+        self.status_message = Some(format!("Budget reached: switched model to {}", self.model));
+        self.scroll_to_bottom();
+    }
+
+    /// Make `next` the active model and record the change in the session.
+    fn switch_model(&mut self, next: ModelEntry) {
+        if next.model.provider == self.model_entry.model.provider
+            && next.model.id == self.model_entry.model.id
+        {
+            self.status_message = Some(format!("Current model: {}", self.model));
+            return;
+        }
+
+        let provider_impl = match providers::create_provider(&next) {
+            Ok(provider_impl) => provider_impl,
+            Err(err) => {
+                self.status_message = Some(err.to_string());
+                return;
+            }
+        };
+
+        let Ok(mut agent_guard) = self.agent.try_lock() else {
+            self.status_message = Some("Agent busy; try again".to_string());
+            return;
+        };
+        agent_guard.set_provider(provider_impl);
+        agent_guard.set_context_window(next.model.context_window);
+        agent_guard.set_pricing(Some(next.model.cost.clone()));
+        drop(agent_guard);
+
+        let Ok(mut session_guard) = self.session.try_lock() else {
+            self.status_message = Some("Session busy; try again".to_string());
+            return;
+        };
+        session_guard.header.provider = Some(next.model.provider.clone());
+        session_guard.header.model_id = Some(next.model.id.clone());
+        session_guard.append_model_change(next.model.provider.clone(), next.model.id.clone());
+        drop(session_guard);
+        self.spawn_save_session();
+
+        self.model = format!("{}/{}", next.model.provider, next.model.id);
+        if let Ok(mut guard) = self.model_entry_shared.lock() {
+            *guard = next.clone();
+        }
+        self.model_entry = next;
+
+        self.status_message = Some(format!("Switched model: {}", self.model));
+    }
+
+    fn handle_model_selector_key(&mut self, key: &KeyMsg) -> Option<Cmd> {
+        let selector = self.model_selector.as_mut()?;
+        match key.key_type {
+            KeyType::Up => selector.select_prev(),
+            KeyType::Down => selector.select_next(),
+            KeyType::PgUp => selector.select_page_up(),
+            KeyType::PgDown => selector.select_page_down(),
+            KeyType::Backspace => selector.pop_char(),
+            KeyType::Runes => selector.push_chars(key.runes.iter().copied()),
+            KeyType::Enter => {
+                let selected = selector.selected_item().cloned();
+                self.model_selector = None;
+                let next = selected.and_then(|key| {
+                    self.available_models
+                        .iter()
+                        .chain(&self.model_scope)
+                        .find(|entry| {
+                            entry.model.provider == key.provider && entry.model.id == key.id
+                        })
+                        .cloned()
+                });
+                match next {
+                    Some(next) => self.switch_model(next),
+                    None => self.status_message = Some("No matching model".to_string()),
+                }
+            }
+            KeyType::Esc => {
+                self.model_selector = None;
+                self.status_message = Some(format!("Current model: {}", self.model));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn render_model_selector(&self, selector: &ModelSelectorOverlay) -> String {
+        let mut output = String::new();
+
+        let _ = writeln!(output, "\n  {}", self.styles.title.render("Select a model"));
+        let query = if selector.query().is_empty() {
+            self.styles.muted_italic.render("type to filter")
+        } else {
+            selector.query().to_string()
+        };
+        let _ = writeln!(
+            output,
+            "  {} {query}\n",
+            self.styles.accent_bold.render(">")
+        );
+
+        if selector.filtered_len() == 0 {
+            let _ = writeln!(
+                output,
+                "  {}",
+                self.styles.muted.render("No matching models.")
+            );
+        } else {
+            let _ = writeln!(
+                output,
+                "  {:<44}  {:>8}  {}",
+                self.styles.muted_bold.render("Model"),
+                self.styles.muted_bold.render("Context"),
+                self.styles.muted_bold.render("$/M in/out")
+            );
+
+            let offset = selector.scroll_offset();
+            let end = (offset + selector.max_visible()).min(selector.filtered_len());
+            for idx in offset..end {
+                let Some(key) = selector.item_at(idx) else {
+                    continue;
+                };
+                let is_selected = idx == selector.selected_index();
+                let prefix = if is_selected { ">" } else { " " };
+                let mut name = key.full_id();
+                if key.provider == self.model_entry.model.provider
+                    && key.id == self.model_entry.model.id
+                {
+                    name.push_str(" (current)");
+                } else if key.scoped {
+                    name.push_str(" *");
+                }
+                if let Some(status) = self
+                    .provider_health
+                    .get(&key.provider)
+                    .filter(|status| **status != HealthStatus::Healthy)
+                {
+                    let _ = write!(name, " [{}]", status.as_str());
+                }
+                let row = format!(
+                    " {name:<44}  {:>8}  {}",
+                    key.context_label(),
+                    key.pricing_label()
+                );
+                let rendered = if is_selected {
+                    self.styles.selection.render(&row)
+                } else {
+                    row
+                };
+                let _ = writeln!(output, "{prefix} {rendered}");
+            }
+
+            if selector.filtered_len() > end - offset {
+                let _ = writeln!(
+                    output,
+                    "  {}",
+                    self.styles.muted.render(&format!(
+                        "({}-{} of {})",
+                        offset + 1,
+                        end,
+                        selector.filtered_len()
+                    ))
+                );
+            }
+        }
+
+        output.push('\n');
+        let _ = writeln!(
+            output,
+            "  {}",
+            self.styles
+                .muted_italic
+                .render("Type to filter  ↑/↓: navigate  Enter: switch  Esc: cancel  * = scoped")
+        );
+        output
+    }
+
+    fn render_session_picker(&self, picker: &SessionPickerOverlay) -> String {
+        let mut output = String::new();
+
+        let _ = writeln!(
+            output,
+            "\n  {}\n",
+            self.styles.title.render("Select a session to resume")
+        );
+
+        if picker.sessions.is_empty() {
+            let _ = writeln!(
+                output,
+                "  {}",
+                self.styles
+                    .muted
                     .render("No sessions found for this project.")
             );
         } else {
@@ -1363,8 +2213,13 @@ impl PiApp {
                     .collect::<String>();
                 let messages = session.message_count.to_string();
                 let id = &session.id[..8.min(session.id.len())];
+                let current = if picker.is_current(session) {
+                    " (current)"
+                } else {
+                    ""
+                };
 
-                let row = format!(" {time:<20}  {name:<30}  {messages:<8}  {id}");
+                let row = format!(" {time:<20}  {name:<30}  {messages:<8}  {id}{current}");
                 let rendered = if is_selected {
                     self.styles.selection.render(&row)
                 } else {
@@ -1416,6 +2271,53 @@ impl PiApp {
         output
     }
 
+    fn render_hunk_review(&self, review: &HunkReview) -> String {
+        let mut output = String::new();
+        let title = format!(
+            "Review {} · hunk {} of {}",
+            review.request.path,
+            review.cursor + 1,
+            review.hunks.len()
+        );
+        let _ = writeln!(output, "\n  {}\n", self.styles.title.render(&title));
+        let marks = review
+            .decisions
+            .iter()
+            .enumerate()
+            .map(|(idx, decision)| {
+                let mark = match decision {
+                    Some(true) => "✓",
+                    Some(false) => "✗",
+                    None => "·",
+                };
+                if idx == review.cursor {
+                    format!("[{mark}]")
+                } else {
+                    format!(" {mark} ")
+                }
+            })
+            .collect::<String>();
+        let _ = writeln!(output, "  {}", self.styles.muted.render(&marks));
+        let hunk = &review.hunks[review.cursor];
+        let _ = writeln!(output, "  {}", self.styles.diff_hunk.render(&hunk.header));
+        for line in hunk.text.lines() {
+            let rendered = match classify_diff_line(line) {
+                DiffLineKind::Added => self.styles.diff_added.render(line),
+                DiffLineKind::Removed => self.styles.diff_removed.render(line),
+                DiffLineKind::Hunk | DiffLineKind::Context => self.styles.muted.render(line),
+            };
+            let _ = writeln!(output, "  {rendered}");
+        }
+        let _ = writeln!(
+            output,
+            "\n  {}",
+            self.styles
+                .muted
+                .render("y keep · n revert · j/k move · Enter finish (undecided hunks are kept)")
+        );
+        output
+    }
+
     fn render_settings_ui(&self, settings_ui: &SettingsUiState) -> String {
         let mut output = String::new();
 
@@ -1622,6 +2524,27 @@ fn parse_extension_command(input: &str) -> Option<(String, Vec<String>)> {
     Some((cmd.to_string(), args))
 }
 
+/// Save a `!` command to the session; unless excluded, the model sees it on the next turn.
+async fn record_bash_execution(
+    agent: &Mutex<Agent>,
+    session: &Mutex<Session>,
+    save_enabled: bool,
+    bash_message: SessionMessage,
+) {
+    let cx = Cx::for_request();
+    if let Some(message) = crate::session::session_message_to_model(&bash_message) {
+        if let Ok(mut agent_guard) = agent.lock(&cx).await {
+            agent_guard.add_message(message);
+        }
+    }
+    if let Ok(mut session_guard) = session.lock(&cx).await {
+        session_guard.append_message(bash_message);
+        if save_enabled {
+            let _ = session_guard.save().await;
+        }
+    }
+}
+
 fn parse_bash_command(input: &str) -> Option<(String, bool)> {
     let trimmed = input.trim_start();
     if trimmed.starts_with("!!") {
@@ -1643,6 +2566,28 @@ fn parse_bash_command(input: &str) -> Option<(String, bool)> {
     }
 }
 
+/// Transcript note for a provider request that will be retried, e.g. "Anthropic HTTP 529;
+/// retrying in 8s (attempt 1 of 3)".
+fn retry_notice(attempt: u32, max_attempts: u32, delay_ms: u64, error_message: &str) -> String {
+    let error = error_message
+        .trim_start_matches("Provider error: ")
+        .splitn(3, ": ")
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{error}; retrying in {}s (attempt {attempt} of {max_attempts})",
+        delay_ms.div_ceil(1000)
+    )
+}
+
+fn tool_defs_notice(tokens: u64, threshold: u64, largest_tools: &[String]) -> String {
+    format!(
+        "Tool definitions take ~{tokens} tokens per request (warning above {threshold}); largest: {}. Set toolDefinitions.lazy to declare fewer tools",
+        largest_tools.join(", ")
+    )
+}
+
 fn user_content_to_text(content: &UserContent) -> String {
     match content {
         UserContent::Text(text) => text.clone(),
@@ -1658,6 +2603,11 @@ fn assistant_content_to_text(content: &[ContentBlock]) -> (String, Option<String
         match block {
             ContentBlock::Text(t) => text.push_str(&t.text),
             ContentBlock::Thinking(t) => thinking.push_str(&t.thinking),
+            // Provider-run tools have no tool result message; show what they did inline.
+            ContentBlock::HostedTool(hosted) => {
+                text.push_str(&hosted.summary());
+                text.push_str("\n\n");
+            }
             _ => {}
         }
     }
@@ -1678,6 +2628,105 @@ fn build_user_message(text: String) -> ModelMessage {
     })
 }
 
+/// Approver that auto-allows remembered rules and otherwise asks the TUI, waiting for its reply.
+fn tool_approver(
+    rules: Arc<StdMutex<Vec<String>>>,
+    pending: Arc<StdMutex<HashMap<String, oneshot::Sender<ToolApprovalDecision>>>>,
+    event_tx: mpsc::Sender<PiMsg>,
+) -> ToolApprover {
+    Arc::new(move |request: ToolApprovalRequest| {
+        let remembered = rules
+            .lock()
+            .is_ok_and(|rules| rules.iter().any(|rule| request.matches_rule(rule)));
+        if remembered {
+            return Box::pin(async { ToolApprovalDecision::AlwaysAllow });
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let id = request.tool_call_id.clone();
+        if let Ok(mut pending) = pending.lock() {
+            pending.insert(id.clone(), tx);
+        }
+        if event_tx
+            .try_send(PiMsg::ToolApprovalRequest(request))
+            .is_err()
+        {
+            if let Ok(mut pending) = pending.lock() {
+                pending.remove(&id);
+            }
+            return Box::pin(async { ToolApprovalDecision::Deny });
+        }
+        Box::pin(async move {
+            let cx = Cx::for_request();
+            // A dropped reply (e.g. the TUI shut down) counts as a denial.
+            rx.recv(&cx).await.unwrap_or(ToolApprovalDecision::Deny)
+        })
+    })
+}
+
+/// Reviewer that hands a finished file change to the TUI and waits for the user's verdict.
+fn hunk_reviewer(
+    pending: Arc<StdMutex<HashMap<String, oneshot::Sender<Option<String>>>>>,
+    event_tx: mpsc::Sender<PiMsg>,
+) -> HunkReviewer {
+    Arc::new(move |request: HunkReviewRequest| {
+        let (tx, rx) = oneshot::channel();
+        let id = request.tool_call_id.clone();
+        if let Ok(mut pending) = pending.lock() {
+            pending.insert(id.clone(), tx);
+        }
+        if event_tx.try_send(PiMsg::HunkReview(request)).is_err() {
+            if let Ok(mut pending) = pending.lock() {
+                pending.remove(&id);
+            }
+            return Box::pin(async { None });
+        }
+        Box::pin(async move {
+            let cx = Cx::for_request();
+            // A dropped reply keeps the change as written.
+            rx.recv(&cx).await.ok().flatten()
+        })
+    })
+}
+
+/// "hunk 2" or "hunks 1, 3" for the 1-based hunk numbers in `numbers`.
+fn hunk_list(numbers: &[usize]) -> String {
+    let list = numbers
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if numbers.len() == 1 {
+        format!("hunk {list}")
+    } else {
+        format!("hunks {list}")
+    }
+}
+
+/// Note for the model after a hunk review; `None` when every hunk was kept.
+fn hunk_review_summary(path: &str, hunks: &[DiffHunk], accepted: &[bool]) -> Option<String> {
+    let (kept, reverted): (Vec<usize>, Vec<usize>) =
+        (1..=accepted.len()).partition(|number| accepted[number - 1]);
+    if reverted.is_empty() {
+        return None;
+    }
+    let headers = reverted
+        .iter()
+        .filter_map(|number| hunks.get(number - 1))
+        .map(|hunk| hunk.header.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let kept = if kept.is_empty() {
+        "kept no hunks".to_string()
+    } else {
+        format!("kept {}", hunk_list(&kept))
+    };
+    Some(format!(
+        "The user reviewed this change to `{path}` hunk by hunk: {kept} and rejected {} ({headers}). The rejected hunks were reverted on disk; do not reapply them unless asked.",
+        hunk_list(&reverted)
+    ))
+}
+
 fn content_blocks_to_text(blocks: &[ContentBlock]) -> String {
     let mut output = String::new();
     for block in blocks {
@@ -1692,6 +2741,7 @@ fn content_blocks_to_text(blocks: &[ContentBlock]) -> String {
             ContentBlock::ToolCall(call) => {
                 push_line(&mut output, &format!("[tool call: {}]", call.name));
             }
+            ContentBlock::HostedTool(hosted) => push_line(&mut output, &hosted.summary()),
         }
     }
     output
@@ -1856,6 +2906,19 @@ fn path_for_display(path: &Path, cwd: &Path) -> String {
     )
 }
 
+/// Working directory for the status line, with the home directory shown as `~`.
+fn display_cwd(cwd: &Path) -> String {
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(rest) = cwd.strip_prefix(&home) {
+            if rest.as_os_str().is_empty() {
+                return "~".to_string();
+            }
+            return format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display());
+        }
+    }
+    cwd.display().to_string()
+}
+
 fn format_file_ref(path: &str) -> String {
     if path.chars().any(char::is_whitespace) {
         if !path.contains('"') {
@@ -1903,7 +2966,11 @@ fn format_tool_output(
     show_images: bool,
 ) -> Option<String> {
     let mut output = tool_content_blocks_to_text(content, show_images);
-    if output.trim().is_empty() {
+    if let Some(diff) = details.and_then(details_diff) {
+        if !output.contains(diff) {
+            push_line(&mut output, diff);
+        }
+    } else if output.trim().is_empty() {
         if let Some(details) = details {
             output = pretty_json(details);
         }
@@ -1915,6 +2982,52 @@ fn format_tool_output(
     }
 }
 
+/// The `diff` string that edit-style tools attach to their result details.
+fn details_diff(details: &Value) -> Option<&str> {
+    details
+        .get("diff")
+        .and_then(Value::as_str)
+        .filter(|diff| !diff.trim().is_empty())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffLineKind {
+    Added,
+    Removed,
+    Hunk,
+    Context,
+}
+
+/// Classify a line of tool output that may belong to a diff.
+///
+/// Understands both standard unified diffs (`--- a`, `+++ b`, `@@ … @@`) and the numbered
+/// format produced by the edit tool (`+12 new line`, `-12 old line`).
+fn classify_diff_line(line: &str) -> DiffLineKind {
+    if line.starts_with("@@") || line.starts_with("+++ ") || line.starts_with("--- ") {
+        DiffLineKind::Hunk
+    } else if line.starts_with('+') {
+        DiffLineKind::Added
+    } else if line.starts_with('-') {
+        DiffLineKind::Removed
+    } else {
+        DiffLineKind::Context
+    }
+}
+
+/// Whether `text` contains a diff worth colorizing.
+fn looks_like_diff(text: &str) -> bool {
+    let unified_header = text.lines().any(|line| line.starts_with("@@ "))
+        || (text.lines().any(|line| line.starts_with("--- "))
+            && text.lines().any(|line| line.starts_with("+++ ")));
+    let numbered = text.lines().any(|line| {
+        line.strip_prefix(['+', '-'])
+            .map(str::trim_start)
+            .and_then(|rest| rest.split_once(' ').or(Some((rest, ""))))
+            .is_some_and(|(num, _)| !num.is_empty() && num.bytes().all(|b| b.is_ascii_digit()))
+    });
+    unified_header || numbered
+}
+
 fn tool_content_blocks_to_text(blocks: &[ContentBlock], show_images: bool) -> String {
     let mut output = String::new();
     let mut hidden_images = 0usize;
@@ -1935,6 +3048,7 @@ fn tool_content_blocks_to_text(blocks: &[ContentBlock], show_images: bool) -> St
             ContentBlock::ToolCall(call) => {
                 push_line(&mut output, &format!("[tool call: {}]", call.name));
             }
+            ContentBlock::HostedTool(hosted) => push_line(&mut output, &hosted.summary()),
         }
     }
 
@@ -2047,9 +3161,145 @@ fn push_line(out: &mut String, line: &str) {
     out.push_str(line);
 }
 
-fn truncate(s: &str, max_len: usize) -> String {
-    if max_len == 0 {
-        return String::new();
+/// Hard-wrap `text` so no line is wider than `width` columns. Continuation lines keep the
+/// original indentation and any ANSI styling that was active at the break.
+fn reflow_to_width(text: &str, width: usize) -> String {
+    if width < 8 {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    for (idx, line) in text.split('\n').enumerate() {
+        if idx > 0 {
+            out.push('\n');
+        }
+        reflow_line(line, width, &mut out);
+    }
+    out
+}
+
+fn reflow_line(line: &str, width: usize, out: &mut String) {
+    if styled_width(line) <= width {
+        out.push_str(line);
+        return;
+    }
+    let indent_width = line.chars().take_while(|ch| *ch == ' ').count();
+    let indent = if indent_width * 2 > width {
+        String::new()
+    } else {
+        " ".repeat(indent_width)
+    };
+
+    let mut active_styles: Vec<String> = Vec::new();
+    let mut used = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            let mut sequence = String::from(ch);
+            if chars.peek() == Some(&'[') {
+                for next in chars.by_ref() {
+                    sequence.push(next);
+                    if next.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            if sequence == "\x1b[0m" || sequence == "\x1b[m" {
+                active_styles.clear();
+            } else if sequence.ends_with('m') {
+                active_styles.push(sequence.clone());
+            }
+            out.push_str(&sequence);
+            continue;
+        }
+        let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
+        if used + ch_width > width && used > indent.len() {
+            if !active_styles.is_empty() {
+                out.push_str("\x1b[0m");
+            }
+            out.push('\n');
+            out.push_str(&indent);
+            for style in &active_styles {
+                out.push_str(style);
+            }
+            used = indent.len();
+        }
+        out.push(ch);
+        used += ch_width;
+    }
+}
+
+/// Display width of a line, ignoring ANSI escape sequences.
+fn styled_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        width += UnicodeWidthChar::width(ch).unwrap_or(0);
+    }
+    width
+}
+
+/// Push `line` onto `out` in chunks of at most `width` display columns.
+fn wrap_plain_line(line: &str, width: usize, out: &mut Vec<String>) {
+    let mut current = String::new();
+    let mut current_width = 0;
+    for ch in line.chars() {
+        let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
+        if current_width + ch_width > width && !current.is_empty() {
+            out.push(std::mem::take(&mut current));
+            current_width = 0;
+        }
+        current.push(ch);
+        current_width += ch_width;
+    }
+    out.push(current);
+}
+
+/// Clip a line that may contain ANSI styling to `width` columns, padding it with spaces.
+fn fit_styled_line(line: &str, width: usize) -> String {
+    let mut out = String::with_capacity(line.len() + width);
+    let mut used = 0;
+    let mut styled = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            // Copy the escape sequence through without counting its width.
+            styled = true;
+            out.push(ch);
+            if chars.peek() == Some(&'[') {
+                for next in chars.by_ref() {
+                    out.push(next);
+                    if next.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
+        if used + ch_width > width {
+            break;
+        }
+        out.push(ch);
+        used += ch_width;
+    }
+    if styled {
+        out.push_str("\x1b[0m");
+    }
+    out.push_str(&" ".repeat(width - used));
+    out
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    if max_len == 0 {
+        return String::new();
     }
 
     let count = s.chars().count();
@@ -2161,6 +3411,304 @@ fn queued_message_preview(text: &str, max_len: usize) -> String {
     truncate(first_line, max_len)
 }
 
+/// Render `/tools` output: one line per registered tool with its enabled state.
+fn format_tool_states(states: &[(String, bool)]) -> String {
+    let mut output = String::from("Tools:\n");
+    if states.is_empty() {
+        output.push_str("  (no tools registered)\n");
+    }
+    for (name, enabled) in states {
+        let marker = if *enabled { "[x]" } else { "[ ]" };
+        let _ = writeln!(output, "  {marker} {name}");
+    }
+    output.push_str(
+        "\nUse /tools disable <name> or /tools enable <name> to change a tool for this session",
+    );
+    output
+}
+
+/// Most files a single `/attach` may add.
+const ATTACH_MAX_FILES: usize = 50;
+
+/// Per-file limits for `/attach`; longer text files are cut at the first limit reached.
+const ATTACH_FILE_MAX_LINES: usize = 1000;
+const ATTACH_FILE_MAX_BYTES: usize = 32 * 1024;
+
+/// A file queued by `/attach` for the next message.
+#[derive(Debug, Clone)]
+struct Attachment {
+    display: String,
+    /// Bytes of text sent to the model, after truncation.
+    text_bytes: usize,
+    /// Why the file is not sent verbatim (truncated, binary), shown in the preview.
+    note: Option<String>,
+    text: String,
+    image: Option<ImageContent>,
+}
+
+/// Expand an `/attach` pattern relative to `cwd` into regular files, sorted.
+fn expand_attach_pattern(pattern: &str, cwd: &Path) -> std::result::Result<Vec<PathBuf>, String> {
+    let full = if Path::new(pattern).is_absolute() {
+        pattern.to_string()
+    } else {
+        cwd.join(pattern).to_string_lossy().into_owned()
+    };
+    let paths = glob::glob(&full).map_err(|err| format!("Invalid pattern {pattern}: {err}"))?;
+    let mut files: Vec<PathBuf> = paths
+        .filter_map(std::result::Result::ok)
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Read one attachment: images become image blocks, binary files are referenced by path,
+/// and text is cut to [`ATTACH_FILE_MAX_LINES`] / [`ATTACH_FILE_MAX_BYTES`].
+fn load_attachment(path: &Path, cwd: &Path, auto_resize: bool) -> crate::error::Result<Attachment> {
+    let display = path.strip_prefix(cwd).unwrap_or(path).display().to_string();
+    let bytes = std::fs::read(path)
+        .map_err(|err| Error::validation(format!("Could not read {}: {err}", path.display())))?;
+
+    if crate::tools::detect_supported_image_mime_type_from_bytes(&bytes).is_some() {
+        let processed = process_file_arguments(&[path.display().to_string()], cwd, auto_resize)?;
+        return Ok(Attachment {
+            display,
+            text_bytes: processed.text.len(),
+            note: Some("image".to_string()),
+            text: processed.text,
+            image: processed.images.into_iter().next(),
+        });
+    }
+
+    let path_str = path.display();
+    if bytes.contains(&0) {
+        let text = format!(
+            "<file name=\"{path_str}\">[binary file, {} bytes; not attached]</file>\n",
+            bytes.len()
+        );
+        return Ok(Attachment {
+            display,
+            text_bytes: text.len(),
+            note: Some("binary, referenced by path".to_string()),
+            text,
+            image: None,
+        });
+    }
+
+    let content = String::from_utf8_lossy(&bytes);
+    let truncation = truncate_head(&content, ATTACH_FILE_MAX_LINES, ATTACH_FILE_MAX_BYTES);
+    let mut text = format!("<file name=\"{path_str}\">\n{}", truncation.content);
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    let note = truncation.truncated.then(|| {
+        format!(
+            "truncated to {} of {} lines",
+            truncation.output_lines, truncation.total_lines
+        )
+    });
+    if let Some(note) = &note {
+        let _ = writeln!(text, "[{note}]");
+    }
+    text.push_str("</file>\n");
+    Ok(Attachment {
+        display,
+        text_bytes: text.len(),
+        note,
+        text,
+        image: None,
+    })
+}
+
+/// Largest text file (in bytes) a file drop inlines; bigger files are referenced by path.
+const FILE_DROP_INLINE_MAX_BYTES: u64 = 256 * 1024;
+
+/// How a dropped file is attached to the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DropKind {
+    /// Sent as an image block (via `@path`).
+    Image,
+    /// Inlined as text content (via `@path`).
+    Inline,
+    /// Too large or binary: mentioned by path so the model can read it with tools.
+    Reference,
+}
+
+impl DropKind {
+    fn classify(path: &Path) -> Self {
+        let Ok(meta) = std::fs::metadata(path) else {
+            return Self::Reference;
+        };
+        let mut head = Vec::with_capacity(8192);
+        if let Ok(file) = std::fs::File::open(path) {
+            let _ = std::io::Read::read_to_end(&mut std::io::Read::take(file, 8192), &mut head);
+        }
+        if crate::tools::detect_supported_image_mime_type_from_bytes(&head).is_some() {
+            return Self::Image;
+        }
+        if meta.len() > FILE_DROP_INLINE_MAX_BYTES || head.contains(&0) {
+            return Self::Reference;
+        }
+        Self::Inline
+    }
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Inline => "inline",
+            Self::Reference => "reference",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DroppedFile {
+    display: String,
+    kind: DropKind,
+}
+
+/// A file drop waiting for the user to confirm (Enter) or decline (Esc).
+#[derive(Debug, Clone)]
+struct PendingFileDrop {
+    raw: String,
+    files: Vec<DroppedFile>,
+}
+
+/// Output lines of a `!` command shown at once in the preview pane.
+const BASH_PREVIEW_LINES: usize = 12;
+
+/// Tail of a previewed `!` command's output attached when it is sent to the model.
+const BASH_SEND_TAIL_LINES: usize = 200;
+const BASH_SEND_TAIL_BYTES: usize = 16 * 1024;
+
+/// A finished `!` command; Enter sends a tail of its output to the model, Esc discards it.
+#[derive(Debug, Clone)]
+pub struct BashPreview {
+    pub command: String,
+    pub output: String,
+    pub exit_code: i32,
+    pub cancelled: bool,
+    pub truncated: bool,
+    pub full_output_path: Option<String>,
+    pub elapsed: std::time::Duration,
+    /// Lines scrolled up from the end of the output.
+    pub scroll: usize,
+}
+
+/// The `height` lines of `output` ending `scroll` lines before its end, with the number of
+/// lines hidden above and below.
+fn bash_preview_window(output: &str, scroll: usize, height: usize) -> (Vec<&str>, usize, usize) {
+    let lines = output.lines().collect::<Vec<_>>();
+    let end = lines
+        .len()
+        .saturating_sub(scroll)
+        .max(height.min(lines.len()));
+    let start = end.saturating_sub(height);
+    (lines[start..end].to_vec(), start, lines.len() - end)
+}
+
+/// Pages of the onboarding tour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TourStep {
+    Welcome,
+    Keys,
+    SlashCommands,
+    Models,
+    Permissions,
+}
+
+impl TourStep {
+    const ALL: [Self; 5] = [
+        Self::Welcome,
+        Self::Keys,
+        Self::SlashCommands,
+        Self::Models,
+        Self::Permissions,
+    ];
+
+    const fn title(self) -> &'static str {
+        match self {
+            Self::Welcome => "Welcome to pi",
+            Self::Keys => "Keys",
+            Self::SlashCommands => "Slash commands",
+            Self::Models => "Models",
+            Self::Permissions => "Permissions",
+        }
+    }
+}
+
+/// The onboarding tour shown on first launch (and by `/tour`).
+#[derive(Debug, Clone, Default)]
+struct OnboardingTour {
+    step: usize,
+    /// Persist `onboarding_tour: false` when the tour is closed early.
+    dont_show_again: bool,
+}
+
+impl OnboardingTour {
+    fn current(&self) -> TourStep {
+        TourStep::ALL[self.step.min(TourStep::ALL.len() - 1)]
+    }
+
+    const fn is_last(&self) -> bool {
+        self.step + 1 >= TourStep::ALL.len()
+    }
+}
+
+/// A paused run's pending tool calls, each of which can be cancelled before resuming.
+#[derive(Debug, Clone)]
+struct PausedRun {
+    tool_calls: Vec<ToolCall>,
+    cancelled: Vec<bool>,
+    cursor: usize,
+}
+
+impl PausedRun {
+    fn new(tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            cancelled: vec![false; tool_calls.len()],
+            tool_calls,
+            cursor: 0,
+        }
+    }
+}
+
+/// A finished file change being reviewed hunk by hunk.
+#[derive(Debug, Clone)]
+struct HunkReview {
+    request: HunkReviewRequest,
+    path: PathBuf,
+    old_content: String,
+    new_content: String,
+    hunks: Vec<DiffHunk>,
+    /// `Some(true)` keeps a hunk, `Some(false)` reverts it; undecided hunks are kept.
+    decisions: Vec<Option<bool>>,
+    cursor: usize,
+}
+
+/// Number of recent sessions listed on the welcome dashboard.
+const WELCOME_RECENT_SESSIONS: usize = 5;
+
+/// Custom session entry recording the thinking display mode chosen with Ctrl+T.
+const THINKING_DISPLAY_CUSTOM_TYPE: &str = "thinking_display";
+
+/// Narrowest terminal that fits the thinking side panel next to the conversation.
+const THINKING_PANEL_MIN_WIDTH: usize = 80;
+
+/// Floor for extension status segment polling, so a bad `intervalMs` cannot spin the runtime.
+const STATUS_SEGMENT_MIN_INTERVAL_MS: u64 = 1000;
+
+/// Render one extension status segment, logging (and dropping) failures.
+async fn render_status_segment_text(manager: &ExtensionManager, id: &str) -> Option<String> {
+    match manager.render_status_segment(id).await {
+        Ok(text) => Some(text),
+        Err(err) => {
+            tracing::warn!("Status segment '{id}' failed to render: {err}");
+            None
+        }
+    }
+}
+
 /// Run the interactive mode.
 #[allow(clippy::too_many_arguments)]
 pub async fn run_interactive(
@@ -2178,13 +3726,14 @@ pub async fn run_interactive(
     cwd: PathBuf,
     runtime_handle: RuntimeHandle,
 ) -> anyhow::Result<()> {
+    let screen_reader = config.screen_reader();
     let show_hardware_cursor = config.show_hardware_cursor.unwrap_or_else(|| {
         std::env::var("PI_HARDWARE_CURSOR")
             .ok()
             .is_some_and(|val| val == "1")
     });
     let mut stdout = std::io::stdout();
-    if show_hardware_cursor {
+    if show_hardware_cursor || screen_reader {
         let _ = crossterm::execute!(stdout, cursor::Show);
     } else {
         let _ = crossterm::execute!(stdout, cursor::Hide);
@@ -2193,10 +3742,11 @@ pub async fn run_interactive(
     let (event_tx, event_rx) = mpsc::channel::<PiMsg>(1024);
     let (ui_tx, ui_rx) = std::sync::mpsc::channel::<Message>();
 
+    let event_ui_tx = ui_tx.clone();
     runtime_handle.spawn(async move {
         let cx = Cx::for_request();
         while let Ok(msg) = event_rx.recv(&cx).await {
-            let _ = ui_tx.send(Message::new(msg));
+            let _ = event_ui_tx.send(Message::new(msg));
         }
     });
 
@@ -2215,16 +3765,18 @@ pub async fn run_interactive(
         });
     }
 
-    let (messages, usage) = {
+    let (messages, usage, corruption_warning) = {
         let cx = Cx::for_request();
         let guard = session
             .lock(&cx)
             .await
             .map_err(|e| anyhow::anyhow!("Failed to lock session: {e}"))?;
-        load_conversation_from_session(&guard)
+        let (messages, usage) = load_conversation_from_session(&guard);
+        (messages, usage, session_corruption_warning(&guard))
     };
 
-    let app = PiApp::new(
+    let show_tour = !screen_reader && messages.is_empty() && config.show_onboarding_tour();
+    let mut app = PiApp::new(
         agent,
         session,
         config,
@@ -2243,24 +3795,239 @@ pub async fn run_interactive(
         messages,
         usage,
     );
+    if show_tour {
+        app.start_onboarding_tour();
+    }
+    if corruption_warning.is_some() {
+        app.status_message = corruption_warning;
+    }
+
+    if screen_reader {
+        return run_plain(app, &ui_tx, &ui_rx);
+    }
+    drop(ui_tx);
 
-    Program::new(app)
+    let app = Program::new(app)
         .with_alt_screen()
         .with_input_receiver(ui_rx)
         .run()?;
 
     let _ = crossterm::execute!(std::io::stdout(), cursor::Show);
-    println!("Goodbye!");
+    PiConsole::new().render_panel(&app.exit_summary().to_content(), "Session summary");
+    Ok(())
+}
+
+/// Screen reader mode: drive the app line by line on the normal terminal, printing new
+/// transcript output as plain text instead of redrawing the full-screen view.
+fn run_plain(
+    mut app: PiApp,
+    ui_tx: &std::sync::mpsc::Sender<Message>,
+    ui_rx: &std::sync::mpsc::Receiver<Message>,
+) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let stdin_tx = ui_tx.clone();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else { break };
+            if stdin_tx
+                .send(Message::new(PiMsg::PlainInput(line)))
+                .is_err()
+            {
+                return;
+            }
+        }
+        // End of input quits like /exit.
+        let _ = stdin_tx.send(Message::new(PiMsg::PlainInput("/exit".to_string())));
+    });
+    let interrupt_tx = ui_tx.clone();
+    if let Err(err) = ctrlc::set_handler(move || {
+        let _ = interrupt_tx.send(Message::new(KeyMsg::from_type(KeyType::CtrlC)));
+    }) {
+        eprintln!("Warning: Failed to install Ctrl+C handler: {err}");
+    }
+
+    let mut stdout = std::io::stdout();
+    let mut printer = PlainPrinter::new(&app);
+    let _ = writeln!(
+        stdout,
+        "Pi, screen reader mode. Model: {}. Type a message and press Enter. /help lists commands, /exit quits.",
+        app.model
+    );
+    if !app.messages.is_empty() {
+        let _ = writeln!(
+            stdout,
+            "Resumed session with {} earlier messages.",
+            app.messages.len()
+        );
+    }
+
+    let mut queued: VecDeque<Message> = VecDeque::new();
+    if !app.pending_inputs.is_empty() {
+        queued.push_back(Message::new(PiMsg::RunPending));
+    }
+    loop {
+        let msg = match queued.pop_front() {
+            Some(msg) => msg,
+            None => match ui_rx.recv() {
+                Ok(msg) => msg,
+                Err(_) => break,
+            },
+        };
+        if msg.is::<QuitMsg>() {
+            break;
+        }
+        let cmd = BubbleteaModel::update(&mut app, msg);
+        app.dismiss_overlays_for_plain_mode();
+        printer.flush(&app, &mut stdout);
+        let _ = stdout.flush();
+        if let Some(next) = cmd.and_then(Cmd::execute) {
+            queued.push_back(next);
+        }
+    }
+
+    let _ = writeln!(
+        stdout,
+        "Session summary:\n{}",
+        app.exit_summary().to_content()
+    );
     Ok(())
 }
 
+/// Tracks what screen reader mode has already printed, so each update only adds new lines.
+struct PlainPrinter {
+    printed_messages: usize,
+    streamed_lines: usize,
+    last_status: Option<String>,
+    last_tool: Option<String>,
+    /// Tool call id and hunk index of the last hunk read out during review.
+    last_hunk: Option<(String, usize)>,
+}
+
+impl PlainPrinter {
+    fn new(app: &PiApp) -> Self {
+        Self {
+            printed_messages: app.messages.len(),
+            streamed_lines: 0,
+            last_status: None,
+            last_tool: None,
+            last_hunk: None,
+        }
+    }
+
+    fn flush(&mut self, app: &PiApp, out: &mut impl std::io::Write) {
+        // /clear, /new, and session switches shrink the transcript.
+        if app.messages.len() < self.printed_messages {
+            self.printed_messages = app.messages.len();
+            self.streamed_lines = 0;
+        }
+        for message in &app.messages[self.printed_messages..] {
+            match message.role {
+                MessageRole::User => {}
+                MessageRole::Assistant => {
+                    for line in message.content.lines().skip(self.streamed_lines) {
+                        let _ = writeln!(out, "{line}");
+                    }
+                    self.streamed_lines = 0;
+                }
+                MessageRole::Tool => {
+                    let _ = writeln!(out, "Tool output:\n{}", message.content.trim_end());
+                }
+                MessageRole::System | MessageRole::Error => {
+                    let _ = writeln!(out, "{}", message.content.trim_end());
+                }
+            }
+        }
+        self.printed_messages = app.messages.len();
+
+        // Stream finished lines of the in-progress reply as they arrive.
+        let complete = app.current_response.complete_line_count();
+        if complete > self.streamed_lines {
+            for line in app
+                .current_response
+                .complete_lines()
+                .skip(self.streamed_lines)
+            {
+                let _ = writeln!(out, "{line}");
+            }
+            self.streamed_lines = complete;
+        }
+
+        if app.current_tool != self.last_tool {
+            if let Some(tool) = &app.current_tool {
+                let _ = writeln!(out, "Running {tool}.");
+            }
+            self.last_tool.clone_from(&app.current_tool);
+        }
+        let hunk = app
+            .hunk_review
+            .as_ref()
+            .map(|review| (review.request.tool_call_id.clone(), review.cursor));
+        if hunk != self.last_hunk {
+            if let Some(review) = &app.hunk_review {
+                let current = &review.hunks[review.cursor];
+                let _ = write!(out, "{}\n{}", current.header, current.text);
+            }
+            self.last_hunk = hunk;
+        }
+        if app.status_message != self.last_status {
+            if let Some(status) = &app.status_message {
+                let _ = writeln!(out, "Status: {status}");
+            }
+            self.last_status.clone_from(&app.status_message);
+        }
+    }
+}
+
+/// Languages of the files edited or read on the session's current branch, edits first.
+fn session_code_languages(session: &Session) -> Vec<&'static str> {
+    let file_ops =
+        crate::compaction::FileOperations::from_entries(session.entries_for_current_path());
+    let modified = file_ops.modified_files();
+    crate::code_lang::languages_for_paths(
+        modified
+            .iter()
+            .map(String::as_str)
+            .chain(file_ops.read_files()),
+    )
+}
+
+/// Status line warning about corrupted lines skipped when the session was opened.
+fn session_corruption_warning(session: &Session) -> Option<String> {
+    let skipped = session.skipped_entries().len();
+    if skipped == 0 {
+        return None;
+    }
+    let repair = session.path.as_ref().map_or_else(String::new, |path| {
+        format!(
+            "; `pi sessions repair {}` recovers what it can",
+            path.display()
+        )
+    });
+    Some(format!(
+        "Skipped {skipped} corrupted session {}{repair}",
+        if skipped == 1 { "entry" } else { "entries" }
+    ))
+}
+
 fn load_conversation_from_session(session: &Session) -> (Vec<ConversationMessage>, Usage) {
     let mut messages = Vec::new();
     let mut usage = Usage::default();
 
     for entry in session.entries_for_current_path() {
-        let SessionEntry::Message(message_entry) = entry else {
-            continue;
+        let message_entry = match entry {
+            SessionEntry::Message(message_entry) => message_entry,
+            SessionEntry::Custom(custom) => {
+                if let Some(text) = custom.note_text() {
+                    messages.push(ConversationMessage {
+                        role: MessageRole::System,
+                        content: format!("Note: {text}"),
+                        thinking: None,
+                    });
+                }
+                continue;
+            }
+            _ => continue,
         };
 
         match &message_entry.message {
@@ -2283,10 +4050,17 @@ fn load_conversation_from_session(session: &Session) -> (Vec<ConversationMessage
             SessionMessage::ToolResult {
                 tool_name,
                 content,
+                details,
                 is_error,
                 ..
             } => {
-                let (text, _) = assistant_content_to_text(content);
+                let (mut text, _) = assistant_content_to_text(content);
+                if let Some(diff) = details.as_ref().and_then(details_diff) {
+                    if !text.contains(diff) {
+                        text.push('\n');
+                        text.push_str(diff);
+                    }
+                }
                 let prefix = if *is_error {
                     "Tool error"
                 } else {
@@ -2411,6 +4185,13 @@ fn add_usage(total: &mut Usage, delta: &Usage) {
     total.cost.cache_read += delta.cost.cache_read;
     total.cost.cache_write += delta.cost.cache_write;
     total.cost.total += delta.cost.total;
+    total.cost.cache_savings += delta.cost.cache_savings;
+    if let Some(gateway) = delta.cost.gateway {
+        *total.cost.gateway.get_or_insert(0.0) += gateway;
+    }
+    if delta.cost.gateway_budget_remaining.is_some() {
+        total.cost.gateway_budget_remaining = delta.cost.gateway_budget_remaining;
+    }
 }
 
 fn format_extension_ui_prompt(request: &ExtensionUiRequest) -> String {
@@ -2582,19 +4363,36 @@ impl SlashCommand {
             "/history" | "/hist" => Self::History,
             "/export" => Self::Export,
             "/session" | "/info" => Self::Session,
+            "/stats" => Self::Stats,
             "/settings" => Self::Settings,
             "/theme" => Self::Theme,
-            "/resume" | "/r" => Self::Resume,
+            "/resume" | "/r" | "/sessions" => Self::Resume,
             "/new" => Self::New,
             "/copy" | "/cp" => Self::Copy,
             "/name" => Self::Name,
             "/hotkeys" | "/keys" | "/keybindings" => Self::Hotkeys,
             "/changelog" => Self::Changelog,
             "/tree" => Self::Tree,
+            "/timeline" => Self::Timeline,
+            "/branch" => Self::Branch,
+            "/label" => Self::Label,
+            "/labels" => Self::Labels,
+            "/goto" => Self::Goto,
+            "/incognito" => Self::Incognito,
+            "/pin" => Self::Pin,
+            "/pins" => Self::Pins,
             "/fork" => Self::Fork,
             "/compact" => Self::Compact,
             "/reload" => Self::Reload,
             "/share" => Self::Share,
+            "/system" => Self::System,
+            "/tools" => Self::Tools,
+            "/save" => Self::Save,
+            "/restore" => Self::Restore,
+            "/attach" => Self::Attach,
+            "/note" => Self::Note,
+            "/notes" => Self::Notes,
+            "/tour" => Self::Tour,
             _ => return None,
         };
 
@@ -2608,25 +4406,42 @@ impl SlashCommand {
   /login [provider]  - OAuth login (currently: anthropic)
   /logout [provider] - Remove stored OAuth credentials
   /clear, /cls       - Clear conversation history
-  /model, /m [id|provider/id] - Show or change the current model
+  /model, /m [id|provider/id] - Pick or change the current model
   /thinking, /t [level] - Set thinking level (off/minimal/low/medium/high/xhigh)
   /scoped-models [patterns|clear] - Show or set scoped models for cycling
   /history, /hist    - Show input history
   /export [path]     - Export conversation to HTML
   /session, /info    - Show session info (path, tokens, cost)
+  /stats [id|label]  - Show message, tool call, token, and cost totals for a branch
   /settings          - Open settings selector
-  /theme [name]      - List or switch themes (dark/light/custom)
-  /resume, /r        - Pick and resume a previous session
+  /theme [name]      - List or switch themes (preview <name> | revert)
+  /resume [n], /sessions - Switch to another session for this project (alias: /r)
   /new               - Start a new session
-  /copy, /cp         - Copy last assistant message to clipboard
+  /copy [code], /cp  - Copy last assistant message (or its last code block) to clipboard
   /name <name>       - Set session display name
   /hotkeys, /keys    - Show keyboard shortcuts
   /changelog         - Show changelog entries
   /tree              - Show session branch tree summary
+  /timeline [id|index] - Browse every entry; inspect, jump to, or branch from one
+  /branch [id|index] - Pick an entry and branch the session from it
+  /label <name>      - Label the current entry as a checkpoint
+  /labels            - List labeled checkpoints
+  /goto <label>      - Switch the session to a labeled checkpoint
+  /save [name]       - Checkpoint the conversation and worktree (no name: list checkpoints)
+  /restore [name]    - Return conversation and files to a checkpoint (no name: list)
+  /attach <glob>     - Attach matching files to the next message (clear: drop them)
+  /note <text>       - Add a note to the session (shown in exports, never sent to the model)
+  /notes             - List this session's notes
+  /tour              - Show the onboarding tour
+  /incognito [on|off] - Keep new messages in memory only (not saved to disk)
+  /pin <path>        - Keep a file's current contents in every request
+  /pins [remove <n|path>|clear] - List or unpin pinned files
   /fork [id|index]   - Fork from a user message (default: last on current path)
   /compact [notes]   - Compact older context with optional instructions
   /reload            - Reload skills/prompts from disk
   /share             - Upload session HTML to a secret GitHub gist and show URL
+  /system [edit]     - Show the system prompt, or edit it for the rest of the session
+  /tools [enable|disable|toggle <name>] - List tools, or turn one on/off for this session
   /exit, /quit, /q   - Exit Pi
 
   Tips:
@@ -2640,22 +4455,116 @@ impl SlashCommand {
     }
 }
 
-/// Custom message types for async agent events.
+/// What this TUI run did, printed as a panel when pi exits.
 #[derive(Debug, Clone)]
-pub enum PiMsg {
-    /// Agent started processing.
-    AgentStart,
-    /// Trigger processing of the next queued input (CLI startup messages).
-    RunPending,
-    /// Text delta from assistant.
-    TextDelta(String),
-    /// Thinking delta from assistant.
-    ThinkingDelta(String),
-    /// Tool execution started.
-    ToolStart { name: String, tool_id: String },
-    /// Tool execution update (streaming output).
-    ToolUpdate {
-        name: String,
+pub struct ExitSummary {
+    pub turns: usize,
+    pub tool_calls: usize,
+    pub usage: Usage,
+    pub wall_time: std::time::Duration,
+    pub session_path: Option<PathBuf>,
+}
+
+impl ExitSummary {
+    /// Panel body: one `label: value` row per line.
+    pub fn to_content(&self) -> String {
+        let cost = self.usage.cost.gateway.unwrap_or(self.usage.cost.total);
+        let secs = self.wall_time.as_secs();
+        let wall_time = if secs < 60 {
+            format!("{secs}s")
+        } else if secs < 3600 {
+            format!("{}m {:02}s", secs / 60, secs % 60)
+        } else {
+            format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+        };
+        let session = self.session_path.as_ref().map_or_else(
+            || "not saved".to_string(),
+            |path| path.display().to_string(),
+        );
+        format!(
+            "Turns:      {}\nTool calls: {}\nTokens:     {} ({} in / {} out)\nCost:       ${cost:.4}\nWall time:  {wall_time}\nSession:    {session}",
+            self.turns,
+            self.tool_calls,
+            self.usage.total_tokens,
+            self.usage.input,
+            self.usage.output,
+        )
+    }
+}
+
+/// An error plus its remediation hints, shown as a panel in the conversation.
+#[derive(Debug, Clone)]
+pub struct ErrorPanel {
+    pub message: String,
+    pub code: &'static str,
+    pub summary: String,
+    pub hints: Vec<String>,
+    pub context: Vec<(String, String)>,
+}
+
+impl ErrorPanel {
+    pub fn from_error(err: &Error) -> Self {
+        let message = err.to_string();
+        let hints = err.hints();
+        // `details` usually repeats the message verbatim; drop it when it does.
+        let context = hints
+            .context
+            .into_iter()
+            .filter(|(key, value)| {
+                !value.is_empty() && !(key == "details" && message.contains(value.as_str()))
+            })
+            .collect();
+        Self {
+            message,
+            code: hints.code,
+            summary: hints.summary,
+            hints: hints.hints,
+            context,
+        }
+    }
+
+    /// Plain-text form stored in the conversation (and shown in exports/copies).
+    ///
+    /// Sections are separated by blank lines: header, `- ` hints, then `key: value` context.
+    fn to_content(&self) -> String {
+        let mut out = format!(
+            "Error: {}\n{} ({})",
+            self.message.replace("\n\n", "\n").trim_end(),
+            self.summary,
+            self.code
+        );
+        if !self.hints.is_empty() {
+            out.push('\n');
+            for hint in &self.hints {
+                let _ = write!(out, "\n- {hint}");
+            }
+        }
+        if !self.context.is_empty() {
+            out.push('\n');
+            for (key, value) in &self.context {
+                let _ = write!(out, "\n{key}: {value}");
+            }
+        }
+        out
+    }
+}
+
+/// Custom message types for async agent events.
+#[derive(Debug, Clone)]
+pub enum PiMsg {
+    /// Agent started processing.
+    AgentStart,
+    /// Trigger processing of the next queued input (CLI startup messages).
+    RunPending,
+    /// Text delta from assistant.
+    TextDelta(String),
+    /// Thinking delta from assistant.
+    ThinkingDelta(String),
+    /// Tool execution started.
+    ToolStart { name: String, tool_id: String },
+    /// Tool execution update (streaming output).
+    ToolUpdate {
+        name: String,
         tool_id: String,
         content: Vec<ContentBlock>,
         details: Option<Value>,
@@ -2672,17 +4581,26 @@ pub enum PiMsg {
         stop_reason: StopReason,
         error_message: Option<String>,
     },
+    /// The run is paused before these tool calls (none when paused between turns).
+    AgentPaused(Vec<ToolCall>),
     /// Agent error.
     AgentError(String),
+    /// Agent error with remediation hints, rendered as an error panel.
+    AgentFailure(ErrorPanel),
+    /// A finished turn has been persisted.
+    TurnSaved,
     /// Non-error system message.
     System(String),
+    /// System note added while a run continues (unlike `System`, the run state is untouched).
+    Notice(String),
     /// Update last user message content (input transform/redaction).
     UpdateLastUserMessage(String),
+    /// Latest output of a running `!` command (streamed into the viewport).
+    BashOutput(String),
     /// Bash command result (non-agent).
-    BashResult {
-        display: String,
-        content_for_agent: Option<Vec<ContentBlock>>,
-    },
+    BashResult { display: String },
+    /// A finished `!` command to preview before sending its output to the model.
+    BashPreview(Box<BashPreview>),
     /// Replace conversation state from session (compaction/fork).
     ConversationReset {
         messages: Vec<ConversationMessage>,
@@ -2699,6 +4617,18 @@ pub enum PiMsg {
     },
     /// Extension UI request (select/confirm/input/editor/notify).
     ExtensionUiRequest(ExtensionUiRequest),
+    /// A destructive tool call is waiting for y/n/always.
+    ToolApprovalRequest(ToolApprovalRequest),
+    /// A finished file change is waiting for hunk-by-hunk review.
+    HunkReview(HunkReviewRequest),
+    /// Background provider reachability results.
+    ProviderHealth(Vec<ProviderHealth>),
+    /// A line typed in screen reader mode.
+    PlainInput(String),
+    /// Freshly rendered text for an extension status-bar segment.
+    StatusSegment { id: String, text: String },
+    /// Refreshed git state for the header status line.
+    GitStatus(Option<GitStatusLine>),
 }
 
 // ============================================================================
@@ -2731,6 +4661,12 @@ struct TreeSelectorState {
     current_leaf_id: Option<String>,
     last_selected_id: Option<String>,
     parent_by_id: HashMap<String, Option<String>>,
+    /// Opened via `/branch`: Enter branches from the selected entry instead of navigating.
+    branch_mode: bool,
+    /// Opened via `/timeline`: Tab shows the selected entry in full and `b` branches from it.
+    timeline: bool,
+    /// Full content of the selected entry while the timeline detail pane is open.
+    detail: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -2797,6 +4733,9 @@ impl TreeSelectorState {
             current_leaf_id,
             last_selected_id: None,
             parent_by_id: HashMap::new(),
+            branch_mode: false,
+            timeline: false,
+            detail: None,
         };
 
         state.rebuild(session);
@@ -2881,6 +4820,112 @@ impl TreeSelectorState {
 
         self.rows.len().saturating_sub(1)
     }
+
+    fn refresh_detail(&mut self, session: &Session) {
+        self.detail = self
+            .rows
+            .get(self.selected)
+            .and_then(|row| session.get_entry(&row.id))
+            .map(timeline_entry_detail);
+    }
+}
+
+/// Full content of a session entry for the `/timeline` detail pane.
+fn timeline_entry_detail(entry: &SessionEntry) -> String {
+    let base = entry.base();
+    let mut out = format!(
+        "{} · {}",
+        base.id.as_deref().unwrap_or("(no id)"),
+        base.timestamp
+    );
+    if let Some(parent_id) = &base.parent_id {
+        let _ = write!(out, " · parent {parent_id}");
+    }
+    let body = match entry {
+        SessionEntry::Message(message_entry) => match &message_entry.message {
+            SessionMessage::User { content, .. } => {
+                format!("user:\n{}", user_content_to_text(content))
+            }
+            SessionMessage::Assistant { message } => {
+                let mut body = format!("assistant ({}/{}):", message.provider, message.model);
+                for block in &message.content {
+                    let line = match block {
+                        ContentBlock::Text(text) => text.text.clone(),
+                        ContentBlock::Thinking(thinking) => {
+                            format!("[thinking] {}", thinking.thinking)
+                        }
+                        ContentBlock::ToolCall(call) => {
+                            format!("→ {} {}", call.name, call.arguments)
+                        }
+                        ContentBlock::Image(image) => format!("[image: {}]", image.mime_type),
+                        ContentBlock::HostedTool(hosted) => hosted.summary(),
+                    };
+                    push_line(&mut body, &line);
+                }
+                body
+            }
+            SessionMessage::ToolResult {
+                tool_name,
+                content,
+                is_error,
+                ..
+            } => format!(
+                "tool_result: {tool_name}{}\n{}",
+                if *is_error { " (error)" } else { "" },
+                content_blocks_to_text(content)
+            ),
+            SessionMessage::Custom {
+                custom_type,
+                content,
+                ..
+            } => format!("custom:{custom_type}:\n{content}"),
+            SessionMessage::BashExecution {
+                command,
+                output,
+                exit_code,
+                ..
+            } => format!("bash: {command} (exit {exit_code})\n{output}"),
+            SessionMessage::BranchSummary { summary, .. } => format!("branch_summary:\n{summary}"),
+            SessionMessage::CompactionSummary { summary, .. } => {
+                format!("compaction_summary:\n{summary}")
+            }
+        },
+        SessionEntry::Compaction(entry) => format!(
+            "compaction: {} tokens before, kept from {}\n{}",
+            entry.tokens_before, entry.first_kept_entry_id, entry.summary
+        ),
+        SessionEntry::BranchSummary(entry) => {
+            format!("branch_summary from {}:\n{}", entry.from_id, entry.summary)
+        }
+        SessionEntry::ModelChange(entry) => format!("model: {}/{}", entry.provider, entry.model_id),
+        SessionEntry::ThinkingLevelChange(entry) => format!("thinking: {}", entry.thinking_level),
+        SessionEntry::Label(entry) => format!(
+            "label: {} -> {}",
+            entry.target_id,
+            entry.label.as_deref().unwrap_or("(cleared)")
+        ),
+        SessionEntry::SessionInfo(entry) => format!(
+            "session_info: {}",
+            entry.name.as_deref().unwrap_or("(unnamed)")
+        ),
+        SessionEntry::Custom(entry) => entry.note_text().map_or_else(
+            || {
+                format!(
+                    "custom: {}\n{}",
+                    entry.custom_type,
+                    entry
+                        .data
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default()
+                )
+            },
+            |text| format!("note:\n{text}"),
+        ),
+    };
+    out.push('\n');
+    out.push_str(body.trim_end());
+    out
 }
 
 fn resolve_tree_selector_initial_id(session: &Session, args: &str) -> Option<String> {
@@ -3006,11 +5051,23 @@ fn build_tree_selector_rows(
                 SessionMessage::Assistant { message } => {
                     let (text, _) = assistant_content_to_text(&message.content);
                     let preview = truncate_inline(text.trim(), 60);
-                    if preview.is_empty() {
-                        ("assistant".to_string(), None)
+                    let mut display = if preview.is_empty() {
+                        "assistant".to_string()
                     } else {
-                        (format!("assistant: \"{preview}\""), None)
+                        format!("assistant: \"{preview}\"")
+                    };
+                    let tool_names = message
+                        .content
+                        .iter()
+                        .filter_map(|block| match block {
+                            ContentBlock::ToolCall(call) => Some(call.name.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+                    if !tool_names.is_empty() {
+                        let _ = write!(display, " → {}", tool_names.join(", "));
                     }
+                    (display, None)
                 }
                 SessionMessage::ToolResult { tool_name, .. } => {
                     (format!("tool_result: {tool_name}"), None)
@@ -3048,7 +5105,10 @@ fn build_tree_selector_rows(
                 ),
                 None,
             ),
-            SessionEntry::Custom(entry) => (format!("[custom: {}]", entry.custom_type), None),
+            SessionEntry::Custom(entry) => match entry.note_text() {
+                Some(text) => (format!("[note: {text}]"), None),
+                None => (format!("[custom: {}]", entry.custom_type), None),
+            },
         }
     }
 
@@ -3273,6 +5333,48 @@ fn collect_tree_branch_entries(
     (entries_rev, boundary)
 }
 
+/// Plain-text summary of the path abandoned by `/branch`, listing the user prompts it held.
+fn describe_abandoned_branch(entries: &[SessionEntry]) -> String {
+    const MAX_PROMPTS: usize = 5;
+    const MAX_PROMPT_CHARS: usize = 80;
+
+    let prompts = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            SessionEntry::Message(message) => match &message.message {
+                SessionMessage::User { content, .. } => {
+                    let text = user_content_to_text(content);
+                    let line = text.lines().next().unwrap_or("").trim().to_string();
+                    (!line.is_empty()).then_some(line)
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut summary = format!(
+        "Branched away from {} entr{} ({} user prompt{}).",
+        entries.len(),
+        if entries.len() == 1 { "y" } else { "ies" },
+        prompts.len(),
+        if prompts.len() == 1 { "" } else { "s" },
+    );
+    for prompt in prompts.iter().take(MAX_PROMPTS) {
+        let truncated: String = prompt.chars().take(MAX_PROMPT_CHARS).collect();
+        let ellipsis = if prompt.chars().count() > MAX_PROMPT_CHARS {
+            "…"
+        } else {
+            ""
+        };
+        let _ = write!(summary, "\n- {truncated}{ellipsis}");
+    }
+    if prompts.len() > MAX_PROMPTS {
+        let _ = write!(summary, "\n- … and {} more", prompts.len() - MAX_PROMPTS);
+    }
+    summary
+}
+
 /// State of the agent processing.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AgentState {
@@ -3472,12 +5574,36 @@ pub struct PiApp {
 
     // Conversation state
     messages: Vec<ConversationMessage>,
-    current_response: String,
+    current_response: StreamingBuffer,
     current_thinking: String,
-    thinking_visible: bool,
+    /// Line count of the conversation content last handed to the viewport.
+    conversation_line_count: usize,
+    /// Lines that arrived below the viewport while the user was scrolled up.
+    unseen_lines: usize,
+    /// Rendered blocks of finished messages, reused across frames.
+    conversation_cache: StdMutex<ConversationRenderCache>,
+    thinking_display: ThinkingDisplay,
+    /// Stream thinking into a side panel (Alt+T) instead of inline.
+    thinking_panel: bool,
     tools_expanded: bool,
     current_tool: Option<String>,
     pending_tool_output: Option<String>,
+    // Output so far of a running `!` command
+    bash_live_output: Option<String>,
+    // When the running `!` command started
+    bash_started_at: Option<std::time::Instant>,
+    // A finished `!` command waiting to be sent to the model or discarded
+    bash_preview: Option<BashPreview>,
+    // Languages of files the session touched, for tagging untagged code blocks
+    code_languages: Vec<&'static str>,
+    // The onboarding tour, while it is open
+    onboarding_tour: Option<OnboardingTour>,
+    // Pauses the agent before its next tool calls (Alt+P)
+    pause_handle: PauseHandle,
+    // The paused run's pending tool calls, while paused
+    paused_run: Option<PausedRun>,
+    // Output token rate for the processing indicator (reset on each agent run)
+    stream_rate: Option<StreamRate>,
 
     // Session and config
     session: Arc<Mutex<Session>>,
@@ -3517,6 +5643,9 @@ pub struct PiApp {
     // OAuth login flow state (awaiting code paste)
     pending_oauth: Option<PendingOAuth>,
 
+    // `/system edit` in progress: the next submit replaces the system prompt
+    editing_system_prompt: bool,
+
     // Extension system
     extensions: Option<ExtensionManager>,
 
@@ -3534,11 +5663,66 @@ pub struct PiApp {
     // Session picker overlay for /resume
     session_picker: Option<SessionPickerOverlay>,
 
+    // Model picker overlay for bare /model
+    model_selector: Option<ModelSelectorOverlay>,
+
     // Settings UI overlay for /settings
     settings_ui: Option<SettingsUiState>,
 
     // Tree navigation UI state (for /tree command)
     tree_ui: Option<TreeUiState>,
+
+    // Ctrl+S steering prompt text while it is open
+    steering_prompt: Option<String>,
+
+    // Header status line (git branch / dirty state), refreshed after tool runs
+    git_status: Option<GitStatusLine>,
+    // Set while a background git status read is running
+    git_status_refreshing: Arc<AtomicBool>,
+
+    // Extension status-bar segments (segment id -> latest rendered text)
+    status_segments: BTreeMap<String, String>,
+
+    // Most recent sessions for this project, listed on the welcome dashboard
+    recent_sessions: Vec<SessionMeta>,
+
+    // Dropped/pasted file paths awaiting confirmation before they are attached
+    pending_file_drop: Option<PendingFileDrop>,
+
+    // Tool approval: remembered "always" rules, the agent's waiting replies, and the prompt shown
+    tool_approval_rules: Arc<StdMutex<Vec<String>>>,
+    pending_tool_approvals: Arc<StdMutex<HashMap<String, oneshot::Sender<ToolApprovalDecision>>>>,
+    tool_approval_prompt: Option<ToolApprovalRequest>,
+
+    // Hunk review: file contents before each reviewed call, the agent's waiting replies, and
+    // the review in progress
+    review_snapshots: HashMap<String, String>,
+    pending_hunk_reviews: Arc<StdMutex<HashMap<String, oneshot::Sender<Option<String>>>>>,
+    hunk_review: Option<HunkReview>,
+
+    // Settings layer that pinned the startup model ("provider/id", source), shown in the header
+    model_default_source: Option<(String, SettingSource)>,
+
+    // Latest reachability per provider, shown as badges in the model picker
+    provider_health: HashMap<String, HealthStatus>,
+
+    // Files queued by `/attach`, sent with the next message
+    pending_attachments: Vec<Attachment>,
+
+    // Set once the `budget` policy has switched this session to its cheaper model
+    budget_downgraded: bool,
+
+    // Notification sinks, how many budget thresholds were already announced, and the last
+    // key press (approvals only notify once the terminal has been left alone)
+    notifier: Notifier,
+    budget_thresholds_notified: usize,
+    last_key_at: std::time::Instant,
+
+    // Exit summary: when this run started, what it spent, and how many turns/tool calls ran
+    started_at: std::time::Instant,
+    run_usage: Usage,
+    turns_completed: usize,
+    tool_calls: usize,
 }
 
 /// Autocomplete dropdown state.
@@ -3629,6 +5813,8 @@ struct SessionPickerOverlay {
     status_message: Option<String>,
     /// Base directory for session storage (used for index cleanup).
     sessions_root: Option<PathBuf>,
+    /// File of the session currently open in the TUI, marked in the list.
+    current_path: Option<PathBuf>,
 }
 
 /// Settings selector overlay state for /settings command.
@@ -3712,6 +5898,7 @@ impl SessionPickerOverlay {
             confirm_delete: false,
             status_message: None,
             sessions_root: None,
+            current_path: None,
         }
     }
 
@@ -3723,6 +5910,7 @@ impl SessionPickerOverlay {
             confirm_delete: false,
             status_message: None,
             sessions_root,
+            current_path: None,
         }
     }
 
@@ -3745,6 +5933,12 @@ impl SessionPickerOverlay {
         self.sessions.get(self.selected)
     }
 
+    fn is_current(&self, session: &SessionMeta) -> bool {
+        self.current_path
+            .as_deref()
+            .is_some_and(|current| Path::new(&session.path) == current)
+    }
+
     /// Returns the scroll offset for the dropdown view.
     const fn scroll_offset(&self) -> usize {
         if self.selected < self.max_visible {
@@ -3938,6 +6132,282 @@ pub struct ConversationMessage {
     pub thinking: Option<String>,
 }
 
+/// Append-only accumulator for streamed assistant text.
+///
+/// Completed lines are stored as separate chunks, byte for byte as streamed, and
+/// only the trailing partial line is mutated, so appending a delta costs
+/// O(delta) regardless of how long the answer already is. The indented display
+/// text of completed lines is built as they arrive (with any `\r` before the
+/// newline dropped), so a frame copies it instead of re-formatting every line.
+#[derive(Debug, Clone, Default)]
+pub struct StreamingBuffer {
+    lines: Vec<String>,
+    partial: String,
+    len: usize,
+    display: String,
+}
+
+impl StreamingBuffer {
+    /// Indentation of streamed lines in the conversation view.
+    const INDENT: &'static str = "  ";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a streamed delta.
+    pub fn push_str(&mut self, delta: &str) {
+        self.len += delta.len();
+        let mut rest = delta;
+        while let Some(idx) = rest.find('\n') {
+            self.partial.push_str(&rest[..idx]);
+            let line = std::mem::take(&mut self.partial);
+            self.display.push_str(Self::INDENT);
+            self.display.push_str(strip_cr(&line));
+            self.display.push('\n');
+            self.lines.push(line);
+            rest = &rest[idx + 1..];
+        }
+        self.partial.push_str(rest);
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Total byte length of all appended deltas.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of lines, matching `str::lines().count()` on the joined text.
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + usize::from(!self.partial.is_empty())
+    }
+
+    /// Iterate over the accumulated lines without joining them, as `str::lines` would.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.complete_lines()
+            .chain((!self.partial.is_empty()).then_some(self.partial.as_str()))
+    }
+
+    /// Lines already ended by a newline (the trailing partial line is excluded).
+    pub fn complete_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(|line| strip_cr(line))
+    }
+
+    pub fn complete_line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Append the indented display text: cached completed lines plus the partial line.
+    pub fn write_display(&self, out: &mut String) {
+        out.push_str(&self.display);
+        if !self.partial.is_empty() {
+            out.push_str(Self::INDENT);
+            out.push_str(&self.partial);
+            out.push('\n');
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.partial.clear();
+        self.display.clear();
+        self.len = 0;
+    }
+
+    /// Join the accumulated text into a single string and reset the buffer.
+    ///
+    /// The result is exactly the concatenation of every delta pushed.
+    pub fn take(&mut self) -> String {
+        let mut out = String::with_capacity(self.len);
+        for line in self.lines.drain(..) {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out.push_str(&self.partial);
+        self.partial.clear();
+        self.display.clear();
+        self.len = 0;
+        out
+    }
+}
+
+/// A completed line without the `\r` of a CRLF ending.
+fn strip_cr(line: &str) -> &str {
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Rendered conversation blocks, one per finished message.
+///
+/// Markdown rendering dominates frame time on long sessions, so a block is only re-rendered
+/// when its message changes. Everything is dropped when the layout inputs (width, thinking
+/// and tool visibility) change or the theme is switched.
+#[derive(Debug, Default)]
+struct ConversationRenderCache {
+    layout: Option<(usize, ThinkingDisplay, bool)>,
+    blocks: Vec<(u64, String)>,
+}
+
+impl ConversationRenderCache {
+    fn clear(&mut self) {
+        self.layout = None;
+        self.blocks.clear();
+    }
+}
+
+fn conversation_message_fingerprint(msg: &ConversationMessage) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (msg.role as u8).hash(&mut hasher);
+    msg.content.hash(&mut hasher);
+    msg.thinking.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Git state shown in the header status line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitStatusLine {
+    /// Current branch, or `None` when HEAD is detached.
+    pub branch: Option<String>,
+    /// Unstaged or untracked changes in the working tree.
+    pub dirty: bool,
+    /// Changes staged in the index.
+    pub staged: bool,
+}
+
+impl GitStatusLine {
+    /// Query `git status` for `cwd`. Returns `None` outside a git work tree.
+    pub fn read(cwd: &Path) -> Option<Self> {
+        let output = std::process::Command::new("git")
+            .args(["status", "--porcelain=v1", "--branch"])
+            .current_dir(cwd)
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(Self::parse_porcelain(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    /// Parse `git status --porcelain=v1 --branch` output.
+    pub fn parse_porcelain(text: &str) -> Self {
+        let mut status = Self::default();
+        for line in text.lines() {
+            if let Some(header) = line.strip_prefix("## ") {
+                let name = header.split("...").next().unwrap_or(header).trim();
+                let name = name.strip_prefix("No commits yet on ").unwrap_or(name);
+                if !name.starts_with("HEAD (no branch)") {
+                    status.branch = Some(name.to_string());
+                }
+                continue;
+            }
+            let mut codes = line.chars();
+            let (Some(index), Some(worktree)) = (codes.next(), codes.next()) else {
+                continue;
+            };
+            if index == '?' || index == '!' {
+                status.dirty = true;
+                continue;
+            }
+            if index != ' ' {
+                status.staged = true;
+            }
+            if worktree != ' ' {
+                status.dirty = true;
+            }
+        }
+        status
+    }
+
+    /// Compact label, e.g. `main*+` (`*` dirty, `+` staged).
+    pub fn label(&self) -> String {
+        let mut label = self
+            .branch
+            .clone()
+            .unwrap_or_else(|| "(detached)".to_string());
+        if self.dirty {
+            label.push('*');
+        }
+        if self.staged {
+            label.push('+');
+        }
+        label
+    }
+}
+
+/// Output token rate while the agent streams, estimated from delta sizes (~4 chars per token).
+#[derive(Debug)]
+struct StreamRate {
+    started: std::time::Instant,
+    /// Recent deltas as (arrival, estimated tokens), pruned to [`Self::WINDOW`].
+    recent: VecDeque<(std::time::Instant, usize)>,
+}
+
+impl StreamRate {
+    /// Rate window: long enough to smooth bursty chunks, short enough to show a stall.
+    const WINDOW: std::time::Duration = std::time::Duration::from_secs(3);
+
+    fn new(now: std::time::Instant) -> Self {
+        Self {
+            started: now,
+            recent: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, now: std::time::Instant, delta: &str) {
+        self.recent
+            .push_back((now, delta.chars().count().div_ceil(4)));
+        while self
+            .recent
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > Self::WINDOW)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    /// `"42 tok/s · 12s"`, or `None` during the first second (too little data to be useful).
+    fn label(&self, now: std::time::Instant) -> Option<String> {
+        let elapsed = now.duration_since(self.started);
+        if elapsed < std::time::Duration::from_secs(1) {
+            return None;
+        }
+        let tokens: usize = self
+            .recent
+            .iter()
+            .filter(|(at, _)| now.duration_since(*at) <= Self::WINDOW)
+            .map(|(_, tokens)| tokens)
+            .sum();
+        let window = elapsed.min(Self::WINDOW).as_secs_f64();
+        let rate = f64::from(u32::try_from(tokens).unwrap_or(u32::MAX)) / window;
+
+        let secs = elapsed.as_secs();
+        let elapsed = if secs < 60 {
+            format!("{secs}s")
+        } else {
+            format!("{}m {:02}s", secs / 60, secs % 60)
+        };
+        Some(format!("{rate:.0} tok/s · {elapsed}"))
+    }
+}
+
+/// Compact age for checkpoint listings, e.g. `5m ago`.
+fn format_age(seconds: i64) -> String {
+    match seconds {
+        ..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86_399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86_400),
+    }
+}
+
 /// Role of a message.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageRole {
@@ -3945,6 +6415,8 @@ pub enum MessageRole {
     Assistant,
     Tool,
     System,
+    /// An error panel (see [`ErrorPanel`]).
+    Error,
 }
 
 impl PiApp {
@@ -3980,7 +6452,11 @@ impl PiApp {
         let editor_padding_x = config.editor_padding_x.unwrap_or(0).min(3) as usize;
         let autocomplete_max_visible =
             config.autocomplete_max_visible.unwrap_or(5).clamp(3, 20) as usize;
-        let thinking_visible = !config.hide_thinking_block.unwrap_or(false);
+        let thinking_display = config.thinking_display();
+        let code_languages = session
+            .try_lock()
+            .map(|guard| session_code_languages(&guard))
+            .unwrap_or_default();
 
         // Configure text area for input
         let mut input = TextArea::new();
@@ -4020,6 +6496,7 @@ impl PiApp {
 
         let mut agent = agent;
         agent.set_queue_modes(steering_mode, follow_up_mode);
+        let pause_handle = agent.pause_handle();
         {
             let steering_queue = Arc::clone(&message_queue);
             let follow_up_queue = Arc::clone(&message_queue);
@@ -4055,7 +6532,23 @@ impl PiApp {
             );
         }
 
-        let keybindings = keybindings_override.unwrap_or_else(|| {
+        let notifier = config.notifier();
+        let tool_approval_rules = Arc::new(StdMutex::new(config.tool_approval_always_allow()));
+        let pending_tool_approvals = Arc::new(StdMutex::new(HashMap::new()));
+        let pending_hunk_reviews = Arc::new(StdMutex::new(HashMap::new()));
+        if config.tool_approval_enabled() {
+            agent.set_tool_approver(Some(tool_approver(
+                Arc::clone(&tool_approval_rules),
+                Arc::clone(&pending_tool_approvals),
+                event_tx.clone(),
+            )));
+            agent.set_hunk_reviewer(Some(hunk_reviewer(
+                Arc::clone(&pending_hunk_reviews),
+                event_tx.clone(),
+            )));
+        }
+
+        let mut keybindings = keybindings_override.unwrap_or_else(|| {
             // Load keybindings from user config (with defaults as fallback).
             let keybindings_result = KeyBindings::load_from_user_config();
             if keybindings_result.has_warnings() {
@@ -4066,6 +6559,24 @@ impl PiApp {
             }
             keybindings_result.bindings
         });
+        // The `keybindings` section of settings.json takes precedence over keybindings.json.
+        // Entries are applied per settings file so warnings name the file that set them.
+        if let Some(overrides) = config.keybindings.clone() {
+            let mut by_source: BTreeMap<PathBuf, HashMap<String, Value>> = BTreeMap::new();
+            for (action, value) in overrides {
+                let source = config
+                    .keybinding_sources
+                    .get(&action)
+                    .cloned()
+                    .unwrap_or_else(|| Config::global_dir().join("settings.json"));
+                by_source.entry(source).or_default().insert(action, value);
+            }
+            for (source, overrides) in by_source {
+                for warning in keybindings.apply_overrides(overrides, &source) {
+                    tracing::warn!("Keybindings warning: {warning}");
+                }
+            }
+        }
 
         // Initialize autocomplete with catalog from resources
         let autocomplete_catalog = AutocompleteCatalog::from_resources(&resources);
@@ -4085,12 +6596,24 @@ impl PiApp {
             term_height,
             editor_padding_x,
             messages,
-            current_response: String::new(),
+            current_response: StreamingBuffer::new(),
             current_thinking: String::new(),
-            thinking_visible,
+            conversation_line_count: 0,
+            unseen_lines: 0,
+            conversation_cache: StdMutex::new(ConversationRenderCache::default()),
+            thinking_display,
+            thinking_panel: config.thinking_panel(),
             tools_expanded: true,
             current_tool: None,
             pending_tool_output: None,
+            bash_live_output: None,
+            bash_started_at: None,
+            bash_preview: None,
+            code_languages,
+            onboarding_tour: None,
+            pause_handle,
+            paused_run: None,
+            stream_rate: None,
             session,
             config,
             theme,
@@ -4117,15 +6640,63 @@ impl PiApp {
             abort_handle: None,
             bash_running: false,
             pending_oauth: None,
+            editing_system_prompt: false,
             extensions,
             keybindings,
             last_ctrlc_time: None,
             last_escape_time: None,
             autocomplete,
             session_picker: None,
+            model_selector: None,
             settings_ui: None,
             tree_ui: None,
+            steering_prompt: None,
+            git_status: None,
+            git_status_refreshing: Arc::new(AtomicBool::new(false)),
+            status_segments: BTreeMap::new(),
+            recent_sessions: Vec::new(),
+            pending_file_drop: None,
+            tool_approval_rules,
+            pending_tool_approvals,
+            tool_approval_prompt: None,
+            review_snapshots: HashMap::new(),
+            pending_hunk_reviews,
+            hunk_review: None,
+            model_default_source: None,
+            provider_health: HashMap::new(),
+            pending_attachments: Vec::new(),
+            budget_downgraded: false,
+            notifier,
+            budget_thresholds_notified: 0,
+            last_key_at: std::time::Instant::now(),
+            started_at: std::time::Instant::now(),
+            run_usage: Usage::default(),
+            turns_completed: 0,
+            tool_calls: 0,
         };
+        app.model_default_source = app.resolve_model_default_source();
+        app.budget_downgraded = app
+            .session
+            .try_lock()
+            .is_ok_and(|session| session.latest_custom_data("budget_downgrade").is_some());
+        // Thresholds a resumed session had already passed were announced back then.
+        let spent = app.session_spend();
+        app.budget_thresholds_notified = app
+            .config
+            .notification_budget_thresholds()
+            .iter()
+            .filter(|threshold| **threshold <= spent)
+            .count();
+        if std::env::var_os("PI_TEST_MODE").is_none() {
+            app.start_provider_health_checks();
+        }
+        if app.config.status_line_show_git() {
+            app.git_status = GitStatusLine::read(&app.cwd);
+        }
+        app.restore_thinking_display();
+        if app.messages.is_empty() {
+            app.refresh_recent_sessions();
+        }
 
         if let Some(manager) = app.extensions.clone() {
             let session_handle = Arc::new(InteractiveExtensionSession {
@@ -4137,6 +6708,14 @@ impl PiApp {
                 save_enabled: app.save_enabled,
             });
             manager.set_session(session_handle);
+
+            if app
+                .extension_theme(app.config.theme.as_deref().unwrap_or_default())
+                .is_some()
+            {
+                app.apply_theme(app.resolve_configured_theme());
+            }
+            app.start_status_segments(&manager);
         }
 
         app.scroll_to_bottom();
@@ -4189,7 +6768,13 @@ impl PiApp {
         if let Some(key) = msg.downcast_ref::<KeyMsg>() {
             // Clear status message on any key press
             self.status_message = None;
-            if key.key_type != KeyType::Esc {
+            self.last_key_at = std::time::Instant::now();
+            // Double-escape tracking follows whatever key is bound to interrupt.
+            if !KeyBinding::from_bubbletea_key(key).is_some_and(|binding| {
+                self.keybindings
+                    .get_bindings(AppAction::Interrupt)
+                    .contains(&binding)
+            }) {
                 self.last_escape_time = None;
             }
 
@@ -4198,6 +6783,28 @@ impl PiApp {
                 return self.handle_tree_ui_key(key);
             }
 
+            // The onboarding tour captures all input while open.
+            if self.onboarding_tour.is_some() {
+                self.handle_onboarding_tour_key(key);
+                return None;
+            }
+
+            // A paused run's tool call list captures all input until resumed.
+            if self.paused_run.is_some() {
+                self.handle_paused_run_key(key);
+                return None;
+            }
+
+            // Ctrl+S steering prompt captures all input while open.
+            if self.steering_prompt.is_some() {
+                return self.handle_steering_prompt_key(key);
+            }
+
+            // /model picker captures all input while open.
+            if self.model_selector.is_some() {
+                return self.handle_model_selector_key(key);
+            }
+
             // /settings modal captures all input while active.
             if self.settings_ui.is_some() {
                 let mut settings_ui = self
@@ -4331,7 +6938,12 @@ impl PiApp {
                     KeyType::Enter => {
                         // Load the selected session
                         if let Some(session_meta) = picker.selected_session().cloned() {
+                            let is_current = picker.is_current(&session_meta);
                             self.session_picker = None;
+                            if is_current {
+                                self.status_message = Some("Already in this session".to_string());
+                                return None;
+                            }
                             return self.load_session_from_path(&session_meta.path);
                         }
                         self.session_picker = None;
@@ -4358,16 +6970,90 @@ impl PiApp {
                 }
             }
 
-            // Handle autocomplete navigation when dropdown is open.
-            //
-            // IMPORTANT: Enter submits the current editor contents; Tab accepts autocomplete.
-            if self.autocomplete.open {
-                match key.key_type {
-                    KeyType::Up => {
-                        self.autocomplete.select_prev();
-                        return None;
-                    }
-                    KeyType::Down => {
+            // Hunk review takes j/k (or ↓/↑) to move, y/n to keep or revert, Enter/Esc to finish.
+            if self.hunk_review.is_some() && self.input.value().is_empty() {
+                let input = match key.key_type {
+                    KeyType::Down => "j".to_string(),
+                    KeyType::Up => "k".to_string(),
+                    KeyType::Enter | KeyType::Esc => String::new(),
+                    KeyType::Runes => key.runes.iter().collect(),
+                    _ => "?".to_string(),
+                };
+                self.handle_hunk_review_input(&input);
+                return None;
+            }
+
+            // A finished `!` preview takes Enter/s to send, d/Esc to discard, ↑/↓ to scroll.
+            if self.bash_preview.is_some() && self.input.value().is_empty() {
+                let rune = |ch: char| key.key_type == KeyType::Runes && key.runes == [ch];
+                if key.key_type == KeyType::Enter || rune('s') {
+                    self.resolve_bash_preview(true);
+                    return None;
+                }
+                if key.key_type == KeyType::Esc || rune('d') {
+                    self.resolve_bash_preview(false);
+                    return None;
+                }
+                let page = isize::try_from(BASH_PREVIEW_LINES).unwrap_or(isize::MAX);
+                let delta = match key.key_type {
+                    KeyType::Up => Some(1),
+                    KeyType::Down => Some(-1),
+                    KeyType::PgUp => Some(page),
+                    KeyType::PgDown => Some(-page),
+                    _ => None,
+                };
+                if let Some(delta) = delta {
+                    self.scroll_bash_preview(delta);
+                    return None;
+                }
+            }
+
+            // A tool approval prompt takes y/n/a/r (Esc denies) while the editor is empty.
+            if let Some(request) = self.tool_approval_prompt.take() {
+                let reviewable = HUNK_REVIEW_TOOLS.contains(&request.tool_name.as_str());
+                let decision = if self.input.value().is_empty() {
+                    match key.key_type {
+                        KeyType::Runes if key.runes == ['y'] => Some(ToolApprovalDecision::Allow),
+                        KeyType::Runes if key.runes == ['n'] => Some(ToolApprovalDecision::Deny),
+                        KeyType::Runes if key.runes == ['a'] => {
+                            Some(ToolApprovalDecision::AlwaysAllow)
+                        }
+                        KeyType::Runes if reviewable && key.runes == ['r'] => {
+                            Some(ToolApprovalDecision::Review)
+                        }
+                        KeyType::Esc => Some(ToolApprovalDecision::Deny),
+                        _ => None,
+                    }
+                } else {
+                    None
+                };
+                if let Some(decision) = decision {
+                    self.resolve_tool_approval(&request, decision);
+                    return None;
+                }
+                self.prompt_tool_approval(request);
+            }
+
+            // A file-drop offer is answered by the next key: Esc keeps the raw text, anything
+            // else attaches (Enter is consumed, other keys then apply as usual).
+            if let Some(pending) = self.pending_file_drop.take() {
+                let attach = key.key_type != KeyType::Esc;
+                self.resolve_file_drop(pending, attach);
+                if matches!(key.key_type, KeyType::Enter | KeyType::Esc) {
+                    return None;
+                }
+            }
+
+            // Handle autocomplete navigation when dropdown is open.
+            //
+            // IMPORTANT: Enter submits the current editor contents; Tab accepts autocomplete.
+            if self.autocomplete.open {
+                match key.key_type {
+                    KeyType::Up => {
+                        self.autocomplete.select_prev();
+                        return None;
+                    }
+                    KeyType::Down => {
                         self.autocomplete.select_next();
                         return None;
                     }
@@ -4404,7 +7090,7 @@ impl PiApp {
                 let candidates = self.keybindings.matching_actions(&binding);
                 if let Some(action) = self.resolve_action(&candidates) {
                     // Dispatch action based on current state
-                    if let Some(cmd) = self.handle_action(action, key) {
+                    if let Some(cmd) = self.handle_action(action) {
                         return Some(cmd);
                     }
                     // Action was handled but returned None (no command needed)
@@ -4427,6 +7113,9 @@ impl PiApp {
             self.maybe_trigger_autocomplete();
 
             result
+        } else if msg.downcast_ref::<KeyMsg>().is_some() {
+            // While processing, keys still reach the editor so the user can type ahead.
+            BubbleteaModel::update(&mut self.input, msg)
         } else {
             // While processing, forward to spinner
             self.spinner.update(msg)
@@ -4447,6 +7136,16 @@ impl PiApp {
             output.push_str(&self.render_footer());
             return output;
         }
+        if let Some(tour) = &self.onboarding_tour {
+            output.push_str(&self.render_onboarding_tour(tour));
+            output.push_str(&self.render_footer());
+            return output;
+        }
+        if let Some(paused) = &self.paused_run {
+            output.push_str(&self.render_paused_run(paused));
+            output.push_str(&self.render_footer());
+            return output;
+        }
 
         // Build conversation content for viewport
         let conversation_content = self.build_conversation_content();
@@ -4454,9 +7153,7 @@ impl PiApp {
         // Update viewport content (we can't mutate self in view, so we render with current offset)
         // The viewport will be updated in update() when new messages arrive
         let viewport_content = if conversation_content.is_empty() {
-            self.styles
-                .muted_italic
-                .render("  Welcome to Pi! Type a message to begin, or /help for commands.")
+            self.render_welcome()
         } else {
             conversation_content
         };
@@ -4469,7 +7166,11 @@ impl PiApp {
             .min(conversation_lines.len().saturating_sub(1));
         let end = (start + self.conversation_viewport.height).min(conversation_lines.len());
         let visible_lines = conversation_lines.get(start..end).unwrap_or(&[]);
-        output.push_str(&visible_lines.join("\n"));
+        if let Some(panel_width) = self.thinking_panel_width() {
+            output.push_str(&self.join_thinking_panel(visible_lines, panel_width));
+        } else {
+            output.push_str(&visible_lines.join("\n"));
+        }
         output.push('\n');
 
         // Scroll indicator
@@ -4480,6 +7181,14 @@ impl PiApp {
             let percent = (start * 100).checked_div(total).map_or(100, |p| p.min(100));
             let indicator = format!("  [{percent}%] ↑/↓ PgUp/PgDn to scroll");
             output.push_str(&self.styles.muted.render(&indicator));
+            if self.unseen_lines > 0 {
+                let unseen = format!(
+                    "  {} new line{} ↓",
+                    self.unseen_lines,
+                    if self.unseen_lines == 1 { "" } else { "s" }
+                );
+                output.push_str(&self.styles.accent_bold.render(&unseen));
+            }
             output.push('\n');
         }
 
@@ -4504,34 +7213,68 @@ impl PiApp {
             output.push_str(&self.render_session_picker(picker));
         }
 
+        // Model picker overlay (if open)
+        if let Some(ref selector) = self.model_selector {
+            output.push_str(&self.render_model_selector(selector));
+        }
+
         // Settings overlay (if open)
         if let Some(ref settings_ui) = self.settings_ui {
             output.push_str(&self.render_settings_ui(settings_ui));
         }
 
-        // Input area (only when idle and no overlay open)
-        if self.agent_state == AgentState::Idle
-            && self.session_picker.is_none()
-            && self.settings_ui.is_none()
-        {
-            output.push_str(&self.render_input());
+        if let Some(ref review) = self.hunk_review {
+            output.push_str(&self.render_hunk_review(review));
+        }
 
-            // Autocomplete dropdown (if open)
-            if self.autocomplete.open && !self.autocomplete.items.is_empty() {
-                output.push_str(&self.render_autocomplete_dropdown());
-            }
-        } else if self.agent_state != AgentState::Idle {
+        if self.agent_state != AgentState::Idle {
             // Show spinner when processing
             let _ = write!(
                 output,
-                "\n  {} {}\n",
+                "\n  {} {}",
                 self.spinner.view(),
                 self.styles.accent.render("Processing...")
             );
+            if let Some(rate) = self
+                .stream_rate
+                .as_ref()
+                .and_then(|rate| rate.label(std::time::Instant::now()))
+            {
+                let _ = write!(output, "  {}", self.styles.muted.render(&rate));
+            }
+            output.push('\n');
 
             if let Some(pending_queue) = self.render_pending_message_queue() {
                 output.push_str(&pending_queue);
             }
+            if let Some(typeahead) = self.render_typeahead_queue() {
+                output.push_str(&typeahead);
+            }
+        }
+
+        if let Some(steering) = &self.steering_prompt {
+            let _ = write!(
+                output,
+                "\n  {} {}\n  {}\n",
+                self.styles.accent_bold.render("steer ›"),
+                steering,
+                self.styles
+                    .muted
+                    .render("Enter: send before the next model call  Esc: cancel")
+            );
+        }
+
+        // Input area (stays open while processing so the user can type ahead)
+        if self.session_picker.is_none()
+            && self.model_selector.is_none()
+            && self.settings_ui.is_none()
+        {
+            output.push_str(&self.render_input());
+
+            // Autocomplete dropdown (if open)
+            if self.autocomplete.open && !self.autocomplete.items.is_empty() {
+                output.push_str(&self.render_autocomplete_dropdown());
+            }
         }
 
         // Footer with usage stats
@@ -4540,6 +7283,220 @@ impl PiApp {
         normalize_raw_terminal_newlines(output)
     }
 
+    /// Open the onboarding tour at its first step.
+    pub fn start_onboarding_tour(&mut self) {
+        self.onboarding_tour = Some(OnboardingTour::default());
+    }
+
+    /// The first key bound to `action`, for the tour text.
+    fn key_label(&self, action: AppAction) -> String {
+        self.keybindings
+            .get_bindings(action)
+            .first()
+            .map_or_else(|| "(unbound)".to_string(), ToString::to_string)
+    }
+
+    fn onboarding_step_text(&self, step: TourStep) -> String {
+        match step {
+            TourStep::Welcome => {
+                "pi is a coding agent that works in this directory: it reads files, \
+                 runs commands, and edits code to carry out your requests.\n\n\
+                 Type a request and press Enter. This tour covers the keys, slash commands, \
+                 models, and when pi asks before acting."
+                    .to_string()
+            }
+            TourStep::Keys => format!(
+                "{submit}  send the message\n{newline}  insert a new line\n\
+                 {interrupt}  interrupt the agent\nPgUp/PgDn  scroll the conversation\n\
+                 {exit}  quit\n\n/hotkeys lists every binding; override them in \
+                 ~/.pi/agent/keybindings.json.",
+                submit = self.key_label(AppAction::Submit),
+                newline = self.key_label(AppAction::NewLine),
+                interrupt = self.key_label(AppAction::Interrupt),
+                exit = self.key_label(AppAction::Exit),
+            ),
+            TourStep::SlashCommands => "Type / to list commands; Tab completes them.\n\n\
+                 /help      every command\n\
+                 /resume    continue an earlier session\n\
+                 /compact   summarize older history to free context\n\
+                 /tree      browse and switch branches of the conversation\n\
+                 /tour      show this tour again"
+                .to_string(),
+            TourStep::Models => format!(
+                "{select} opens the model picker, or use /model <id> to switch directly.\n\
+                 {cycle} steps the thinking level. The header shows the active model and \
+                 the footer its token use and cost.",
+                select = self.key_label(AppAction::SelectModel),
+                cycle = self.key_label(AppAction::CycleThinkingLevel),
+            ),
+            TourStep::Permissions => "bash, write and edit wait for your answer before they run:\n\
+                 y  allow once    n  deny    a  always allow (saved in project settings)\n\n\
+                 Reading and searching run without asking. `permissions` rules in \
+                 settings.json allow or deny calls up front."
+                .to_string(),
+        }
+    }
+
+    fn render_onboarding_tour(&self, tour: &OnboardingTour) -> String {
+        let step = tour.current();
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "\n  {} {}",
+            self.styles.accent_bold.render(step.title()),
+            self.styles
+                .muted
+                .render(&format!("({}/{})", tour.step + 1, TourStep::ALL.len()))
+        );
+        out.push('\n');
+        let width = self.term_width.saturating_sub(4).max(20);
+        let mut lines = Vec::new();
+        for line in self.onboarding_step_text(step).lines() {
+            wrap_plain_line(line, width, &mut lines);
+        }
+        for line in lines {
+            let _ = writeln!(out, "  {line}");
+        }
+        let checkbox = if tour.dont_show_again { "[x]" } else { "[ ]" };
+        let _ = writeln!(out, "\n  {checkbox} Don't show again");
+        let next = if tour.is_last() { "finish" } else { "next" };
+        let _ = writeln!(
+            out,
+            "\n  {}",
+            self.styles.muted.render(&format!(
+                "Enter/→: {next} · ←: back · d: don't show again · Esc: close"
+            ))
+        );
+        out
+    }
+
+    fn handle_onboarding_tour_key(&mut self, key: &KeyMsg) {
+        let Some(tour) = self.onboarding_tour.as_mut() else {
+            return;
+        };
+        match key.key_type {
+            KeyType::Enter | KeyType::Right | KeyType::Space => {
+                if tour.is_last() {
+                    self.finish_onboarding_tour(true);
+                } else {
+                    tour.step += 1;
+                }
+            }
+            KeyType::Left => tour.step = tour.step.saturating_sub(1),
+            KeyType::Esc => self.finish_onboarding_tour(false),
+            KeyType::Runes if key.runes == ['d'] => {
+                tour.dont_show_again = !tour.dont_show_again;
+            }
+            _ => {}
+        }
+    }
+
+    /// Close the tour; finishing it or ticking "don't show again" stops it showing at launch.
+    fn finish_onboarding_tour(&mut self, completed: bool) {
+        let Some(tour) = self.onboarding_tour.take() else {
+            return;
+        };
+        if !completed && !tour.dont_show_again {
+            self.status_message = Some("Tour closed. /tour shows it again.".to_string());
+            return;
+        }
+        self.config.onboarding_tour = Some(false);
+        let global_dir = Config::global_dir();
+        let patch = json!({ "onboarding_tour": false });
+        self.status_message = Some(
+            match Config::patch_settings_with_roots(
+                SettingsScope::Global,
+                &global_dir,
+                &self.cwd,
+                patch,
+            ) {
+                Ok(_) => "Tour done. /tour shows it again.".to_string(),
+                Err(err) => {
+                    tracing::warn!("Failed to persist onboarding_tour: {err}");
+                    format!("Tour done (not saved: {err})")
+                }
+            },
+        );
+    }
+
+    fn render_paused_run(&self, paused: &PausedRun) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "\n  {}",
+            self.styles.accent_bold.render("Agent paused")
+        );
+        out.push('\n');
+        if paused.tool_calls.is_empty() {
+            let _ = writeln!(out, "  Paused between turns; no tool calls are pending.");
+        }
+        let width = self.term_width.saturating_sub(12).max(20);
+        for (index, call) in paused.tool_calls.iter().enumerate() {
+            let marker = if index == paused.cursor { ">" } else { " " };
+            let state = if paused.cancelled[index] {
+                "[cancel]"
+            } else {
+                "[run]   "
+            };
+            let args = truncate(&call.arguments.to_string(), width);
+            let _ = writeln!(out, "  {marker} {state} {} {args}", call.name);
+        }
+        let _ = writeln!(
+            out,
+            "\n  {}",
+            self.styles
+                .muted
+                .render("↑/↓: select · x: cancel/keep · Enter: resume · Esc: abort the run")
+        );
+        out
+    }
+
+    fn handle_paused_run_key(&mut self, key: &KeyMsg) {
+        let Some(paused) = self.paused_run.as_mut() else {
+            return;
+        };
+        match key.key_type {
+            KeyType::Up => paused.cursor = paused.cursor.saturating_sub(1),
+            KeyType::Down => {
+                paused.cursor = (paused.cursor + 1).min(paused.tool_calls.len().saturating_sub(1));
+            }
+            KeyType::Runes if key.runes == ['x'] => {
+                if let Some(cancelled) = paused.cancelled.get_mut(paused.cursor) {
+                    *cancelled = !*cancelled;
+                }
+            }
+            KeyType::Enter => self.resume_paused_run(),
+            KeyType::Esc => {
+                self.paused_run = None;
+                if let Some(handle) = &self.abort_handle {
+                    handle.abort();
+                }
+                self.status_message = Some("Aborting request...".to_string());
+            }
+            _ => {}
+        }
+    }
+
+    /// Cancel the tool calls marked in the paused list and let the run continue.
+    fn resume_paused_run(&mut self) {
+        let Some(paused) = self.paused_run.take() else {
+            return;
+        };
+        let mut cancelled = 0usize;
+        for (call, cancel) in paused.tool_calls.iter().zip(&paused.cancelled) {
+            if *cancel {
+                self.pause_handle.cancel_tool_call(call.id.clone());
+                cancelled += 1;
+            }
+        }
+        self.pause_handle.resume();
+        self.status_message = Some(if cancelled == 0 {
+            "Resumed".to_string()
+        } else {
+            format!("Resumed; cancelled {cancelled} tool call(s)")
+        });
+    }
+
     #[allow(clippy::too_many_lines)]
     fn handle_tree_ui_key(&mut self, key: &KeyMsg) -> Option<Cmd> {
         let tree_ui = self.tree_ui.take()?;
@@ -4547,8 +7504,29 @@ impl PiApp {
         match tree_ui {
             TreeUiState::Selector(mut selector) => {
                 match key.key_type {
-                    KeyType::Up => selector.move_selection(-1),
-                    KeyType::Down => selector.move_selection(1),
+                    KeyType::Up | KeyType::Down => {
+                        selector.move_selection(if key.key_type == KeyType::Up { -1 } else { 1 });
+                        if selector.detail.is_some() {
+                            if let Ok(session_guard) = self.session.try_lock() {
+                                selector.refresh_detail(&session_guard);
+                            }
+                        }
+                    }
+                    KeyType::Tab if selector.timeline => {
+                        if selector.detail.take().is_none() {
+                            if let Ok(session_guard) = self.session.try_lock() {
+                                selector.refresh_detail(&session_guard);
+                            }
+                        }
+                    }
+                    KeyType::Runes if selector.timeline && key.runes == ['b'] => {
+                        if let Some(row) = selector.rows.get(selector.selected) {
+                            let entry_id = row.id.clone();
+                            self.branch_session_from(&entry_id);
+                            return None;
+                        }
+                    }
+                    KeyType::Esc if selector.detail.is_some() => selector.detail = None,
                     KeyType::CtrlU => {
                         selector.user_only = !selector.user_only;
                         if let Ok(session_guard) = self.session.try_lock() {
@@ -4575,6 +7553,11 @@ impl PiApp {
                         let selected = selector.rows[selector.selected].clone();
                         selector.last_selected_id = Some(selected.id.clone());
 
+                        if selector.branch_mode {
+                            self.branch_session_from(&selected.id);
+                            return None;
+                        }
+
                         let (new_leaf_id, editor_text) = if let Some(text) = selected.resubmit_text
                         {
                             (selected.parent_id.clone(), Some(text))
@@ -4715,6 +7698,44 @@ impl PiApp {
         None
     }
 
+    fn handle_steering_prompt_key(&mut self, key: &KeyMsg) -> Option<Cmd> {
+        let mut text = self.steering_prompt.take().unwrap_or_default();
+        match key.key_type {
+            KeyType::Esc | KeyType::CtrlC => {
+                self.status_message = Some("Steering cancelled".to_string());
+                return None;
+            }
+            KeyType::Backspace => {
+                text.pop();
+            }
+            KeyType::Enter => {
+                let text = text.trim();
+                if text.is_empty() {
+                    self.status_message = Some("Steering cancelled".to_string());
+                    return None;
+                }
+                if self.agent_state == AgentState::Idle {
+                    // The run finished while typing; send it as the next prompt instead.
+                    self.pending_inputs
+                        .push_back(PendingInput::Text(text.to_string()));
+                    return Some(Cmd::new(|| Message::new(PiMsg::RunPending)));
+                }
+                let expanded = self.resources.expand_input(text);
+                self.history.push(text.to_string());
+                if let Ok(mut queue) = self.message_queue.lock() {
+                    queue.push_steering(expanded);
+                }
+                self.status_message =
+                    Some("Steering sent: applied before the next model call".to_string());
+                return None;
+            }
+            KeyType::Runes => text.extend(key.runes.iter().copied()),
+            _ => {}
+        }
+        self.steering_prompt = Some(text);
+        None
+    }
+
     #[allow(clippy::too_many_lines)]
     fn start_tree_navigation(
         &mut self,
@@ -4766,6 +7787,7 @@ impl PiApp {
             self.current_tool = None;
             self.abort_handle = None;
             self.status_message = Some(format!("Switched to {status_leaf}"));
+            self.apply_branch_model_settings();
             self.scroll_to_bottom();
 
             if let Some(text) = pending.editor_text {
@@ -4944,55 +7966,398 @@ impl PiApp {
         });
     }
 
-    fn view_tree_ui(tree_ui: &TreeUiState, styles: &TuiStyles) -> String {
-        match tree_ui {
-            TreeUiState::Selector(state) => Self::view_tree_selector(state, styles),
-            TreeUiState::SummaryPrompt(state) => Self::view_tree_summary_prompt(state, styles),
-            TreeUiState::CustomPrompt(state) => Self::view_tree_custom_prompt(state, styles),
-        }
-    }
+    /// Branch the session from `entry_id` (the `/branch` picker).
+    ///
+    /// Unlike `/tree`, the selected entry always becomes the branch point and the
+    /// abandoned path is recorded as a `BranchSummary` entry without calling the model.
+    fn branch_session_from(&mut self, entry_id: &str) {
+        // Lock the agent first so the session is never branched without its history following.
+        let Ok(mut agent_guard) = self.agent.try_lock() else {
+            self.status_message = Some("Agent busy; try again".to_string());
+            return;
+        };
+        let Ok(mut session_guard) = self.session.try_lock() else {
+            self.status_message = Some("Session busy; try again".to_string());
+            return;
+        };
 
-    fn view_tree_selector(state: &TreeSelectorState, styles: &TuiStyles) -> String {
-        let mut out = String::new();
-        let _ = writeln!(out, "  {}", styles.title.render("Session Tree"));
+        let old_leaf_id = session_guard.leaf_id.clone();
+        if old_leaf_id.as_deref() == Some(entry_id) {
+            self.status_message = Some("Already on that branch".to_string());
+            return;
+        }
 
-        let filters = format!(
-            "  Filters: user-only={}  show-all={}",
-            if state.user_only { "on" } else { "off" },
-            if state.show_all { "on" } else { "off" }
-        );
-        let _ = writeln!(out, "{}", styles.muted.render(&filters));
-        out.push('\n');
+        let (abandoned, summary_from_id) =
+            collect_tree_branch_entries(&session_guard, old_leaf_id.as_deref(), Some(entry_id));
+        if !session_guard.create_branch_from(entry_id) {
+            self.status_message = Some(format!("Branch target not found: {entry_id}"));
+            return;
+        }
+        if !abandoned.is_empty() {
+            session_guard.append_branch_summary(
+                summary_from_id,
+                describe_abandoned_branch(&abandoned),
+                None,
+                None,
+            );
+        }
 
-        if state.rows.is_empty() {
-            let _ = writeln!(out, "  {}", styles.muted_italic.render("(no entries)"));
-        } else {
-            let start = state.scroll.min(state.rows.len().saturating_sub(1));
-            let end = (start + state.max_visible_lines).min(state.rows.len());
+        let (messages, usage) = load_conversation_from_session(&session_guard);
+        let agent_messages = session_guard.to_messages_for_current_path();
+        let leaf_count = session_guard.branch_summary().leaf_count;
+        drop(session_guard);
+        agent_guard.replace_messages(agent_messages);
+        drop(agent_guard);
 
-            for (idx, row) in state.rows.iter().enumerate().take(end).skip(start) {
-                let prefix = if idx == state.selected { ">" } else { " " };
-                let rendered = if idx == state.selected {
-                    styles.selection.render(&row.display)
-                } else {
-                    row.display.clone()
-                };
-                let _ = writeln!(out, "{prefix} {rendered}");
-            }
-        }
+        self.spawn_save_session();
 
-        out.push('\n');
-        let _ = writeln!(
-            out,
-            "  {}",
-            styles.muted.render(
-                "↑/↓: navigate  Enter: select  Esc: cancel  Ctrl+U: user-only  Ctrl+O: show-all"
-            )
-        );
-        out
+        self.messages = messages;
+        self.total_usage = usage;
+        self.current_response.clear();
+        self.current_thinking.clear();
+        self.current_tool = None;
+        self.abort_handle = None;
+        self.status_message = Some(format!(
+            "Branched from {entry_id} ({leaf_count} branches in session)"
+        ));
+        self.apply_branch_model_settings();
+        self.scroll_to_bottom();
+        self.input.focus();
     }
 
-    fn view_tree_summary_prompt(state: &TreeSummaryPromptState, styles: &TuiStyles) -> String {
+    /// Restore the model and thinking level recorded on the current branch when they differ
+    /// from the active ones, so a branch keeps running under the settings it was created with.
+    ///
+    /// Returns a note describing what changed.
+    fn restore_branch_model_settings(&mut self) -> Option<String> {
+        let (branch_model, branch_thinking) = {
+            let guard = self.session.try_lock().ok()?;
+            (
+                guard.model_for_current_path(),
+                guard.thinking_level_for_current_path(),
+            )
+        };
+        let mut changes = Vec::new();
+
+        if let Some((provider, model_id)) = branch_model {
+            if provider != self.model_entry.model.provider || model_id != self.model_entry.model.id
+            {
+                let entry = self
+                    .available_models
+                    .iter()
+                    .find(|entry| entry.model.provider == provider && entry.model.id == model_id)
+                    .cloned();
+                let provider_impl = entry
+                    .as_ref()
+                    .and_then(|entry| providers::create_provider(entry).ok());
+                match (entry, provider_impl, self.agent.try_lock()) {
+                    (Some(entry), Some(provider_impl), Ok(mut agent_guard)) => {
+                        agent_guard.set_provider(provider_impl);
+                        agent_guard.set_context_window(entry.model.context_window);
+                        agent_guard.set_pricing(Some(entry.model.cost.clone()));
+                        drop(agent_guard);
+                        if let Ok(mut session_guard) = self.session.try_lock() {
+                            session_guard.header.provider = Some(provider.clone());
+                            session_guard.header.model_id = Some(model_id.clone());
+                        }
+                        self.model_entry = entry.clone();
+                        if let Ok(mut guard) = self.model_entry_shared.lock() {
+                            *guard = entry;
+                        }
+                        self.model = format!("{provider}/{model_id}");
+                        changes.push(format!("model {}", self.model));
+                    }
+                    _ => changes.push(format!(
+                        "branch model {provider}/{model_id} unavailable, keeping {}",
+                        self.model
+                    )),
+                }
+            }
+        }
+
+        if let Some(level) = branch_thinking.and_then(|level| level.parse::<ThinkingLevel>().ok()) {
+            let changed = self.session.try_lock().ok().is_some_and(|mut guard| {
+                let current = guard
+                    .header
+                    .thinking_level
+                    .as_deref()
+                    .and_then(|current| current.parse::<ThinkingLevel>().ok())
+                    .unwrap_or(ThinkingLevel::Off);
+                guard.header.thinking_level = Some(level.to_string());
+                current != level
+            });
+            if changed {
+                if let Ok(mut agent_guard) = self.agent.try_lock() {
+                    agent_guard.stream_options_mut().thinking_level = Some(level);
+                }
+                changes.push(format!("thinking {level}"));
+            }
+        }
+
+        (!changes.is_empty()).then(|| format!("Branch settings: {}", changes.join(", ")))
+    }
+
+    /// Append the branch settings note (if any) to the status line.
+    fn apply_branch_model_settings(&mut self) {
+        if let Some(note) = self.restore_branch_model_settings() {
+            self.status_message = Some(match self.status_message.take() {
+                Some(status) => format!("{status} · {note}"),
+                None => note,
+            });
+        }
+    }
+
+    /// Summarize older turns with the model, append a `Compaction` entry, and replace the
+    /// agent's in-memory history with the summary plus the kept recent messages.
+    ///
+    /// Runs for `/compact`; automatic compaction happens inside the agent loop.
+    #[allow(clippy::too_many_lines)]
+    fn start_compaction(&mut self, custom_instructions: Option<String>) {
+        let Ok(agent_guard) = self.agent.try_lock() else {
+            self.status_message = Some("Agent busy; try again".to_string());
+            return;
+        };
+        let provider = agent_guard.provider();
+        let api_key_opt = agent_guard.stream_options().api_key.clone();
+        drop(agent_guard);
+
+        let Some(api_key) = api_key_opt else {
+            self.status_message = Some("No API key configured; cannot run compaction".to_string());
+            return;
+        };
+
+        let event_tx = self.event_tx.clone();
+        let session = Arc::clone(&self.session);
+        let agent = Arc::clone(&self.agent);
+        let extensions = self.extensions.clone();
+        let runtime_handle = self.runtime_handle.clone();
+        let reserve_tokens = self.config.compaction_reserve_tokens();
+        let keep_recent_tokens = self.config.compaction_keep_recent_tokens();
+        let is_compacting = Arc::clone(&self.extension_compacting);
+
+        self.agent_state = AgentState::Processing;
+        self.status_message = Some("Compacting session...".to_string());
+        self.extension_compacting.store(true, Ordering::SeqCst);
+
+        runtime_handle.spawn(async move {
+            let cx = Cx::for_request();
+
+            let (session_id, path_entries) = {
+                let mut guard = match session.lock(&cx).await {
+                    Ok(guard) => guard,
+                    Err(err) => {
+                        is_compacting.store(false, Ordering::SeqCst);
+                        let _ = event_tx
+                            .try_send(PiMsg::AgentError(format!("Failed to lock session: {err}")));
+                        return;
+                    }
+                };
+                guard.ensure_entry_ids();
+                let session_id = guard.header.id.clone();
+                let entries = guard
+                    .entries_for_current_path()
+                    .into_iter()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (session_id, entries)
+            };
+
+            if let Some(manager) = extensions.clone() {
+                let cancelled = manager
+                    .dispatch_cancellable_event(
+                        ExtensionEventName::SessionBeforeCompact,
+                        Some(json!({
+                            "sessionId": session_id,
+                            "notes": custom_instructions.as_deref(),
+                            "automatic": false,
+                        })),
+                        EXTENSION_EVENT_TIMEOUT_MS,
+                    )
+                    .await
+                    .unwrap_or(false);
+                if cancelled {
+                    is_compacting.store(false, Ordering::SeqCst);
+                    let _ = event_tx.try_send(PiMsg::System(
+                        "Compaction cancelled by extension".to_string(),
+                    ));
+                    return;
+                }
+            }
+
+            let settings = crate::compaction::ResolvedCompactionSettings {
+                enabled: true,
+                reserve_tokens,
+                keep_recent_tokens,
+            };
+            let Some(prep) = crate::compaction::prepare_compaction(&path_entries, settings) else {
+                is_compacting.store(false, Ordering::SeqCst);
+                let _ = event_tx.try_send(PiMsg::System(
+                    "Nothing to compact (already compacted or too little history)".to_string(),
+                ));
+                return;
+            };
+
+            let result = match crate::compaction::compact(
+                prep,
+                Arc::clone(&provider),
+                &api_key,
+                custom_instructions.as_deref(),
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(err) => {
+                    is_compacting.store(false, Ordering::SeqCst);
+                    let _ =
+                        event_tx.try_send(PiMsg::AgentError(format!("Compaction failed: {err}")));
+                    return;
+                }
+            };
+
+            let details = crate::compaction::compaction_details_to_value(&result.details).ok();
+
+            let messages_for_agent = {
+                let mut guard = match session.lock(&cx).await {
+                    Ok(guard) => guard,
+                    Err(err) => {
+                        is_compacting.store(false, Ordering::SeqCst);
+                        let _ = event_tx
+                            .try_send(PiMsg::AgentError(format!("Failed to lock session: {err}")));
+                        return;
+                    }
+                };
+
+                guard.append_compaction(
+                    result.summary.clone(),
+                    result.first_kept_entry_id.clone(),
+                    result.tokens_before,
+                    details,
+                    None,
+                );
+                let _ = guard.save().await;
+                guard.to_messages_for_current_path()
+            };
+
+            {
+                let mut agent_guard = match agent.lock(&cx).await {
+                    Ok(guard) => guard,
+                    Err(err) => {
+                        is_compacting.store(false, Ordering::SeqCst);
+                        let _ = event_tx
+                            .try_send(PiMsg::AgentError(format!("Failed to lock agent: {err}")));
+                        return;
+                    }
+                };
+                agent_guard.replace_messages(messages_for_agent);
+            }
+
+            let (messages, usage) = {
+                let guard = match session.lock(&cx).await {
+                    Ok(guard) => guard,
+                    Err(err) => {
+                        is_compacting.store(false, Ordering::SeqCst);
+                        let _ = event_tx
+                            .try_send(PiMsg::AgentError(format!("Failed to lock session: {err}")));
+                        return;
+                    }
+                };
+                load_conversation_from_session(&guard)
+            };
+
+            is_compacting.store(false, Ordering::SeqCst);
+            let status = format!(
+                "Compaction complete ({} tokens before)",
+                result.tokens_before
+            );
+            let _ = event_tx.try_send(PiMsg::ConversationReset {
+                messages,
+                usage,
+                status: Some(status),
+            });
+
+            if let Some(manager) = extensions {
+                let _ = manager
+                    .dispatch_event(
+                        ExtensionEventName::SessionCompact,
+                        Some(json!({
+                            "tokensBefore": result.tokens_before,
+                            "firstKeptEntryId": result.first_kept_entry_id,
+                            "automatic": false,
+                        })),
+                    )
+                    .await;
+            }
+        });
+    }
+
+    fn view_tree_ui(tree_ui: &TreeUiState, styles: &TuiStyles) -> String {
+        match tree_ui {
+            TreeUiState::Selector(state) => Self::view_tree_selector(state, styles),
+            TreeUiState::SummaryPrompt(state) => Self::view_tree_summary_prompt(state, styles),
+            TreeUiState::CustomPrompt(state) => Self::view_tree_custom_prompt(state, styles),
+        }
+    }
+
+    fn view_tree_selector(state: &TreeSelectorState, styles: &TuiStyles) -> String {
+        let mut out = String::new();
+        let title = if state.branch_mode {
+            "Branch From Entry"
+        } else if state.timeline {
+            "Session Timeline"
+        } else {
+            "Session Tree"
+        };
+        let _ = writeln!(out, "  {}", styles.title.render(title));
+
+        let filters = format!(
+            "  Filters: user-only={}  show-all={}",
+            if state.user_only { "on" } else { "off" },
+            if state.show_all { "on" } else { "off" }
+        );
+        let _ = writeln!(out, "{}", styles.muted.render(&filters));
+        out.push('\n');
+
+        if state.rows.is_empty() {
+            let _ = writeln!(out, "  {}", styles.muted_italic.render("(no entries)"));
+        } else {
+            let start = state.scroll.min(state.rows.len().saturating_sub(1));
+            let end = (start + state.max_visible_lines).min(state.rows.len());
+
+            for (idx, row) in state.rows.iter().enumerate().take(end).skip(start) {
+                let prefix = if idx == state.selected { ">" } else { " " };
+                let rendered = if idx == state.selected {
+                    styles.selection.render(&row.display)
+                } else {
+                    row.display.clone()
+                };
+                let _ = writeln!(out, "{prefix} {rendered}");
+            }
+        }
+
+        if let Some(detail) = &state.detail {
+            out.push('\n');
+            let lines = detail.lines().collect::<Vec<_>>();
+            let shown = state.max_visible_lines.max(1);
+            for line in lines.iter().take(shown) {
+                let _ = writeln!(out, "  {line}");
+            }
+            if lines.len() > shown {
+                let more = format!("… {} more lines", lines.len() - shown);
+                let _ = writeln!(out, "  {}", styles.muted.render(&more));
+            }
+        }
+
+        out.push('\n');
+        let help = if state.timeline {
+            "↑/↓: navigate  Tab: details  Enter: jump  b: branch  Esc: close  Ctrl+U: user-only  Ctrl+O: show-all"
+        } else {
+            "↑/↓: navigate  Enter: select  Esc: cancel  Ctrl+U: user-only  Ctrl+O: show-all"
+        };
+        let _ = writeln!(out, "  {}", styles.muted.render(help));
+        out
+    }
+
+    fn view_tree_summary_prompt(state: &TreeSummaryPromptState, styles: &TuiStyles) -> String {
         let mut out = String::new();
         let _ = writeln!(out, "  {}", styles.title.render("Branch Summary"));
         out.push('\n');
@@ -5054,98 +8419,486 @@ impl PiApp {
         out
     }
 
-    /// Build the conversation content string for the viewport.
-    fn build_conversation_content(&self) -> String {
+    /// Re-read which languages the session works in; keeps the old list while the session is busy.
+    fn refresh_code_languages(&mut self) {
+        if let Ok(guard) = self.session.try_lock() {
+            self.code_languages = session_code_languages(&guard);
+        }
+    }
+
+    /// Render one finished message as a block of conversation content.
+    fn render_conversation_message(&self, msg: &ConversationMessage) -> String {
         let mut output = String::new();
+        match msg.role {
+            MessageRole::User => {
+                let _ = write!(
+                    output,
+                    "\n  {} {}\n",
+                    self.styles.accent_bold.render("You:"),
+                    msg.content
+                );
+            }
+            MessageRole::Assistant => {
+                let _ = write!(
+                    output,
+                    "\n  {}\n",
+                    self.styles.success_bold.render("Assistant:")
+                );
 
-        for msg in &self.messages {
-            match msg.role {
-                MessageRole::User => {
-                    let _ = write!(
-                        output,
-                        "\n  {} {}\n",
-                        self.styles.accent_bold.render("You:"),
-                        msg.content
-                    );
+                // Render thinking if present
+                if let Some(thinking) = &msg.thinking {
+                    self.render_thinking(&mut output, thinking);
                 }
-                MessageRole::Assistant => {
-                    let _ = write!(
-                        output,
-                        "\n  {}\n",
-                        self.styles.success_bold.render("Assistant:")
-                    );
 
-                    // Render thinking if present
-                    if self.thinking_visible {
-                        if let Some(thinking) = &msg.thinking {
-                            let truncated = truncate(thinking, 100);
-                            let _ = writeln!(
-                                output,
-                                "  {}",
-                                self.styles
-                                    .muted_italic
-                                    .render(&format!("Thinking: {truncated}"))
-                            );
-                        }
-                    }
+                // Render markdown content
+                let rendered = MarkdownRenderer::new()
+                    .with_style_config(self.markdown_style.clone())
+                    .with_word_wrap(self.conversation_width().saturating_sub(6).max(40))
+                    .render(&crate::code_lang::tag_untagged_code_blocks(
+                        &msg.content,
+                        &self.code_languages,
+                    ));
+                for line in rendered.lines() {
+                    let _ = writeln!(output, "  {line}");
+                }
+            }
+            MessageRole::Tool => {
+                if self.tools_expanded {
+                    let rendered = self.render_tool_text(&msg.content);
+                    let _ = write!(output, "\n  {rendered}\n");
+                } else {
+                    let header = msg.content.lines().next().unwrap_or("Tool output");
+                    let collapsed = format!("{} (collapsed)", header.trim_end());
+                    let rendered = self.styles.muted_italic.render(&collapsed);
+                    let _ = write!(output, "\n  {rendered}\n");
+                }
+            }
+            MessageRole::System => {
+                let _ = write!(output, "\n  {}\n", self.styles.warning.render(&msg.content));
+            }
+            MessageRole::Error => {
+                output.push('\n');
+                self.render_error_panel(&mut output, &msg.content);
+            }
+        }
+        output
+    }
 
-                    // Render markdown content
-                    let rendered = MarkdownRenderer::new()
-                        .with_style_config(self.markdown_style.clone())
-                        .with_word_wrap(self.term_width.saturating_sub(6).max(40))
-                        .render(&msg.content);
-                    for line in rendered.lines() {
-                        let _ = writeln!(output, "  {line}");
+    /// Render an [`ErrorPanel`] from its stored text: a bordered block with the error and
+    /// summary, bulleted hints, and muted context.
+    fn render_error_panel(&self, output: &mut String, content: &str) {
+        let border = self.styles.error_bold.render("│");
+        for (section_idx, section) in content.split("\n\n").enumerate() {
+            if section_idx > 0 {
+                let _ = writeln!(output, "  {border}");
+            }
+            for (line_idx, line) in section.lines().enumerate() {
+                let styled = if section_idx == 0 {
+                    if line_idx == 0 {
+                        self.styles.error_bold.render(line)
+                    } else {
+                        line.to_string()
                     }
+                } else if let Some(hint) = line.strip_prefix("- ") {
+                    format!("• {hint}")
+                } else {
+                    self.styles.muted.render(line)
+                };
+                let _ = writeln!(output, "  {border} {styled}");
+            }
+        }
+    }
+
+    /// Sessions for this project, newest first, excluding the current one.
+    fn recent_project_sessions(&self) -> Vec<SessionMeta> {
+        let (override_dir, current_path) = self
+            .session
+            .try_lock()
+            .map(|guard| (guard.session_dir.clone(), guard.path.clone()))
+            .unwrap_or_default();
+        let mut sessions =
+            crate::session_picker::list_sessions_for_project(&self.cwd, override_dir.as_deref());
+        if let Some(current) = current_path {
+            sessions.retain(|meta| Path::new(&meta.path) != current.as_path());
+        }
+        sessions
+    }
+
+    fn refresh_recent_sessions(&mut self) {
+        let mut sessions = self.recent_project_sessions();
+        sessions.truncate(WELCOME_RECENT_SESSIONS);
+        self.recent_sessions = sessions;
+    }
+
+    /// Welcome dashboard shown while the conversation is empty.
+    fn render_welcome(&self) -> String {
+        let mut output = String::new();
+        let _ = writeln!(
+            output,
+            "{}",
+            self.styles
+                .muted_italic
+                .render("  Welcome to Pi! Type a message to begin, or /help for commands.")
+        );
+        let _ = writeln!(
+            output,
+            "\n  {} {}",
+            self.styles.muted.render("Model:"),
+            self.model
+        );
+
+        if !self.recent_sessions.is_empty() {
+            let _ = writeln!(
+                output,
+                "\n  {}",
+                self.styles.muted.render("Recent sessions:")
+            );
+            let now_ms = Utc::now().timestamp_millis();
+            for (idx, meta) in self.recent_sessions.iter().enumerate() {
+                let title = meta
+                    .name
+                    .as_deref()
+                    .filter(|name| !name.trim().is_empty())
+                    .map_or_else(
+                        || meta.id.chars().take(8).collect::<String>(),
+                        ToString::to_string,
+                    );
+                let age = format_age(now_ms.saturating_sub(meta.last_modified_ms) / 1000);
+                let _ = writeln!(
+                    output,
+                    "    {}. {}  {}",
+                    idx + 1,
+                    truncate(&title, 48),
+                    self.styles
+                        .muted
+                        .render(&format!("{} msgs, {age}", meta.message_count))
+                );
+            }
+        }
+
+        let resume_hint = if self.recent_sessions.is_empty() {
+            "/resume: resume"
+        } else {
+            "/resume [n]: resume"
+        };
+        let _ = write!(
+            output,
+            "\n  {}",
+            self.styles.muted.render(&format!(
+                "{resume_hint}  |  /new: start fresh  |  Ctrl+L: pick model"
+            ))
+        );
+        output
+    }
+
+    /// Render a thinking block according to the current `ui.thinking` mode.
+    fn render_thinking(&self, output: &mut String, thinking: &str) {
+        match self.thinking_display {
+            ThinkingDisplay::Off => {}
+            ThinkingDisplay::Summary => {
+                let first_line = thinking
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or_default();
+                let truncated = truncate(first_line, 100);
+                let _ = writeln!(
+                    output,
+                    "  {}",
+                    self.styles
+                        .muted_italic
+                        .render(&format!("Thinking: {truncated}"))
+                );
+            }
+            ThinkingDisplay::Full => {
+                let _ = writeln!(output, "  {}", self.styles.muted_italic.render("Thinking:"));
+                for line in thinking.trim_end().lines() {
+                    let _ = writeln!(
+                        output,
+                        "  {}",
+                        self.styles.muted_italic.render(&format!("│ {line}"))
+                    );
                 }
-                MessageRole::Tool => {
-                    if self.tools_expanded {
-                        let rendered = self.styles.muted.render(&msg.content);
-                        let _ = write!(output, "\n  {rendered}\n");
+            }
+        }
+    }
+
+    /// Switch the thinking display mode and remember it for this session.
+    fn set_thinking_display(&mut self, display: ThinkingDisplay) {
+        self.thinking_display = display;
+        if let Ok(mut session_guard) = self.session.try_lock() {
+            session_guard.append_custom_entry(
+                THINKING_DISPLAY_CUSTOM_TYPE.to_string(),
+                Some(json!({ "mode": display.as_str() })),
+            );
+            drop(session_guard);
+            self.spawn_save_session();
+        }
+        let content = self.build_conversation_content();
+        self.conversation_viewport.set_content(&content);
+        self.status_message = Some(format!("Thinking: {}", display.as_str()));
+    }
+
+    /// Columns taken by the thinking side panel, if it is open and the terminal is wide enough.
+    fn thinking_panel_width(&self) -> Option<usize> {
+        (self.thinking_panel && self.term_width >= THINKING_PANEL_MIN_WIDTH)
+            .then(|| self.term_width * 2 / 5)
+    }
+
+    /// Width available to the conversation column.
+    fn conversation_width(&self) -> usize {
+        self.term_width - self.thinking_panel_width().unwrap_or(0)
+    }
+
+    fn toggle_thinking_panel(&mut self) {
+        self.thinking_panel = !self.thinking_panel;
+        let content = self.build_conversation_content();
+        self.conversation_viewport.set_content(&content);
+        self.status_message = Some(
+            match (self.thinking_panel, self.thinking_panel_width()) {
+                (false, _) => "Thinking panel hidden",
+                (true, Some(_)) => "Thinking panel shown",
+                (true, None) => "Thinking panel needs a terminal at least 80 columns wide",
+            }
+            .to_string(),
+        );
+    }
+
+    /// Panel lines: a header, then the tail of the streaming (or latest) thinking, wrapped.
+    fn render_thinking_panel(&self, width: usize, height: usize) -> Vec<String> {
+        let inner = width.saturating_sub(3).max(1);
+        let thinking = if self.current_thinking.is_empty() {
+            self.messages
+                .iter()
+                .rev()
+                .find_map(|msg| msg.thinking.as_deref())
+                .unwrap_or_default()
+        } else {
+            self.current_thinking.as_str()
+        };
+
+        let mut wrapped = Vec::new();
+        for line in thinking.trim_end().lines() {
+            wrap_plain_line(line, inner, &mut wrapped);
+        }
+        let body_height = height.saturating_sub(1);
+        let skip = wrapped.len().saturating_sub(body_height);
+
+        let mut lines = Vec::with_capacity(height);
+        lines.push(self.styles.muted_bold.render("Thinking"));
+        if wrapped.is_empty() {
+            lines.push(self.styles.muted_italic.render("(no thinking yet)"));
+        }
+        lines.extend(
+            wrapped
+                .iter()
+                .skip(skip)
+                .map(|line| self.styles.muted_italic.render(line)),
+        );
+        lines.truncate(height);
+        lines
+    }
+
+    /// Lay the thinking panel out to the right of the visible conversation lines.
+    fn join_thinking_panel(&self, conversation: &[&str], panel_width: usize) -> String {
+        let main_width = self.term_width.saturating_sub(panel_width);
+        let panel = self.render_thinking_panel(panel_width, conversation.len());
+        let separator = self.styles.border.render("│");
+        conversation
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                format!(
+                    "{} {separator} {}",
+                    fit_styled_line(line, main_width.saturating_sub(1)),
+                    panel.get(idx).map_or("", String::as_str)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Use the session's saved thinking display mode, or the configured default.
+    fn restore_thinking_display(&mut self) {
+        let saved = self.session.try_lock().ok().and_then(|guard| {
+            guard
+                .latest_custom_data(THINKING_DISPLAY_CUSTOM_TYPE)
+                .and_then(|data| data.get("mode"))
+                .and_then(Value::as_str)
+                .and_then(ThinkingDisplay::parse)
+        });
+        self.thinking_display = saved.unwrap_or_else(|| self.config.thinking_display());
+    }
+
+    /// Build the conversation content string for the viewport.
+    fn build_conversation_content(&self) -> String {
+        let mut output = String::new();
+        let mut cache = self
+            .conversation_cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let layout = (
+            self.conversation_width(),
+            self.thinking_display,
+            self.tools_expanded,
+        );
+        if cache.layout != Some(layout) {
+            cache.clear();
+            cache.layout = Some(layout);
+        }
+        cache.blocks.truncate(self.messages.len());
+
+        for (idx, msg) in self.messages.iter().enumerate() {
+            let fingerprint = conversation_message_fingerprint(msg);
+            match cache.blocks.get(idx) {
+                Some((cached, block)) if *cached == fingerprint => output.push_str(block),
+                _ => {
+                    let block = reflow_to_width(
+                        &self.render_conversation_message(msg),
+                        self.transcript_width(),
+                    );
+                    output.push_str(&block);
+                    if idx < cache.blocks.len() {
+                        cache.blocks[idx] = (fingerprint, block);
                     } else {
-                        let header = msg.content.lines().next().unwrap_or("Tool output");
-                        let collapsed = format!("{} (collapsed)", header.trim_end());
-                        let rendered = self.styles.muted_italic.render(&collapsed);
-                        let _ = write!(output, "\n  {rendered}\n");
+                        cache.blocks.push((fingerprint, block));
                     }
                 }
-                MessageRole::System => {
-                    let _ = write!(output, "\n  {}\n", self.styles.warning.render(&msg.content));
-                }
             }
         }
+        drop(cache);
 
         // Add current streaming response
+        let mut live_output = String::new();
         if !self.current_response.is_empty() || !self.current_thinking.is_empty() {
             let _ = write!(
-                output,
+                live_output,
                 "\n  {}\n",
                 self.styles.success_bold.render("Assistant:")
             );
 
-            // Show thinking if present
-            if self.thinking_visible && !self.current_thinking.is_empty() {
-                let truncated = truncate(&self.current_thinking, 100);
+            // Show thinking if present (the side panel streams it instead when open)
+            if !self.current_thinking.is_empty() && self.thinking_panel_width().is_none() {
+                self.render_thinking(&mut live_output, &self.current_thinking);
+            }
+
+            // Show response (no markdown rendering while streaming)
+            self.current_response.write_display(&mut live_output);
+        }
+
+        if let Some(live) = &self.bash_live_output {
+            let elapsed = self
+                .bash_started_at
+                .map(|started| started.elapsed())
+                .unwrap_or_default();
+            let _ = write!(
+                live_output,
+                "\n  {} {}\n",
+                self.styles.warning_bold.render("Running:"),
+                self.styles
+                    .muted
+                    .render(&format!("{:.1}s", elapsed.as_secs_f64()))
+            );
+            let (lines, above, _) = bash_preview_window(live, 0, BASH_PREVIEW_LINES);
+            if above > 0 {
+                let _ = writeln!(
+                    live_output,
+                    "  {}",
+                    self.styles
+                        .muted
+                        .render(&format!("… {above} earlier lines"))
+                );
+            }
+            for line in lines {
+                let _ = writeln!(live_output, "  {}", self.styles.muted.render(line));
+            }
+        }
+
+        if let Some(preview) = &self.bash_preview {
+            let status = if preview.cancelled {
+                "cancelled".to_string()
+            } else {
+                format!("exit {}", preview.exit_code)
+            };
+            let header = format!(
+                "Ran `{}` in {:.1}s ({status})",
+                preview.command,
+                preview.elapsed.as_secs_f64()
+            );
+            let header_style = if preview.exit_code == 0 && !preview.cancelled {
+                &self.styles.success_bold
+            } else {
+                &self.styles.error_bold
+            };
+            let _ = write!(live_output, "\n  {}\n", header_style.render(&header));
+            let (lines, above, below) =
+                bash_preview_window(&preview.output, preview.scroll, BASH_PREVIEW_LINES);
+            if above > 0 {
+                let _ = writeln!(
+                    live_output,
+                    "  {}",
+                    self.styles.muted.render(&format!("… {above} lines above"))
+                );
+            }
+            for line in lines {
+                let _ = writeln!(live_output, "  {line}");
+            }
+            if below > 0 {
                 let _ = writeln!(
-                    output,
+                    live_output,
                     "  {}",
-                    self.styles
-                        .muted_italic
-                        .render(&format!("Thinking: {truncated}"))
+                    self.styles.muted.render(&format!("… {below} lines below"))
                 );
             }
-
-            // Show response (no markdown rendering while streaming)
-            if !self.current_response.is_empty() {
-                for line in self.current_response.lines() {
-                    let _ = writeln!(output, "  {line}");
-                }
-            }
+            let _ = writeln!(
+                live_output,
+                "  {}",
+                self.styles
+                    .muted
+                    .render("Enter/s: send to model · d/Esc: discard · ↑/↓ PgUp/PgDn: scroll")
+            );
         }
+        output.push_str(&reflow_to_width(&live_output, self.transcript_width()));
 
         output
     }
 
+    /// Widest transcript line that fits the conversation column without terminal wrapping.
+    fn transcript_width(&self) -> usize {
+        self.conversation_width().saturating_sub(2)
+    }
+
+    /// End the current turn with an error message in the conversation.
+    /// Drop a pause the finished run never reached, so the next run starts unpaused.
+    fn clear_pause(&mut self) {
+        self.paused_run = None;
+        self.pause_handle.resume();
+    }
+
+    fn finish_with_error(&mut self, role: MessageRole, content: String) -> Option<Cmd> {
+        self.stream_rate = None;
+        self.current_response.clear();
+        self.current_thinking.clear();
+        self.messages.push(ConversationMessage {
+            role,
+            content,
+            thinking: None,
+        });
+        self.agent_state = AgentState::Idle;
+        self.current_tool = None;
+        self.abort_handle = None;
+        self.clear_pause();
+        self.extension_streaming.store(false, Ordering::SeqCst);
+        self.extension_compacting.store(false, Ordering::SeqCst);
+        self.input.focus();
+
+        if self.pending_inputs.is_empty() {
+            None
+        } else {
+            Some(Cmd::new(|| Message::new(PiMsg::RunPending)))
+        }
+    }
+
     /// Handle custom Pi messages from the agent.
     #[allow(clippy::too_many_lines)]
     fn handle_pi_message(&mut self, msg: PiMsg) -> Option<Cmd> {
@@ -5155,17 +8908,28 @@ impl PiApp {
                 self.current_response.clear();
                 self.current_thinking.clear();
                 self.extension_streaming.store(true, Ordering::SeqCst);
+                self.stream_rate = Some(StreamRate::new(std::time::Instant::now()));
+                self.refresh_status_segments(ExtensionEventName::AgentStart);
             }
             PiMsg::RunPending => {
                 return self.run_next_pending();
             }
             PiMsg::TextDelta(text) => {
+                if let Some(rate) = &mut self.stream_rate {
+                    rate.record(std::time::Instant::now(), &text);
+                }
                 self.current_response.push_str(&text);
+                self.follow_conversation();
             }
             PiMsg::ThinkingDelta(text) => {
+                if let Some(rate) = &mut self.stream_rate {
+                    rate.record(std::time::Instant::now(), &text);
+                }
                 self.current_thinking.push_str(&text);
+                self.follow_conversation();
             }
             PiMsg::ToolStart { name, .. } => {
+                self.tool_calls += 1;
                 self.agent_state = AgentState::ToolRunning;
                 self.current_tool = Some(name);
                 self.pending_tool_output = None;
@@ -5187,13 +8951,15 @@ impl PiApp {
             PiMsg::ToolEnd { .. } => {
                 self.agent_state = AgentState::Processing;
                 self.current_tool = None;
+                self.refresh_git_status();
+                self.refresh_status_segments(ExtensionEventName::ToolExecutionEnd);
                 if let Some(output) = self.pending_tool_output.take() {
                     self.messages.push(ConversationMessage {
                         role: MessageRole::Tool,
                         content: output,
                         thinking: None,
                     });
-                    self.scroll_to_bottom();
+                    self.follow_conversation();
                 }
             }
             PiMsg::AgentDone {
@@ -5201,12 +8967,15 @@ impl PiApp {
                 stop_reason,
                 error_message,
             } => {
+                self.stream_rate = None;
+                self.refresh_status_segments(ExtensionEventName::AgentEnd);
+                self.refresh_code_languages();
                 // Finalize the response
                 let had_response = !self.current_response.is_empty();
                 if had_response {
                     self.messages.push(ConversationMessage {
                         role: MessageRole::Assistant,
-                        content: std::mem::take(&mut self.current_response),
+                        content: self.current_response.take(),
                         thinking: if self.current_thinking.is_empty() {
                             None
                         } else {
@@ -5216,19 +8985,51 @@ impl PiApp {
                 }
 
                 // Update usage
+                self.turns_completed += 1;
                 if let Some(u) = usage {
+                    add_usage(&mut self.run_usage, &u);
                     self.total_usage.input += u.input;
                     self.total_usage.output += u.output;
+                    self.total_usage.cache_read += u.cache_read;
+                    self.total_usage.cache_write += u.cache_write;
                     self.total_usage.total_tokens += u.total_tokens;
                     self.total_usage.cost.total += u.cost.total;
+                    self.total_usage.cost.cache_savings += u.cost.cache_savings;
+                    if let Some(gateway) = u.cost.gateway {
+                        *self.total_usage.cost.gateway.get_or_insert(0.0) += gateway;
+                        self.warn_on_gateway_cost_drift(&u, gateway);
+                    }
+                    if u.cost.gateway_budget_remaining.is_some() {
+                        self.total_usage.cost.gateway_budget_remaining =
+                            u.cost.gateway_budget_remaining;
+                    }
                 }
 
                 self.agent_state = AgentState::Idle;
                 self.current_tool = None;
                 self.abort_handle = None;
+                self.clear_pause();
                 self.extension_streaming.store(false, Ordering::SeqCst);
                 self.extension_compacting.store(false, Ordering::SeqCst);
 
+                self.check_budget_notifications();
+                // An abort means someone is watching; queued input means the work goes on.
+                if stop_reason != StopReason::Aborted && self.pending_inputs.is_empty() {
+                    let answer = if had_response {
+                        self.messages
+                            .last()
+                            .map(|message| message.content.clone())
+                            .unwrap_or_default()
+                    } else {
+                        String::new()
+                    };
+                    self.notify(Notification::run_ended(
+                        stop_reason,
+                        error_message.as_deref(),
+                        &answer,
+                    ));
+                }
+
                 if stop_reason == StopReason::Aborted {
                     self.status_message = Some("Request aborted".to_string());
                 } else if stop_reason == StopReason::Error {
@@ -5243,6 +9044,8 @@ impl PiApp {
                     }
                 }
 
+                self.follow_conversation();
+
                 // Re-focus input
                 self.input.focus();
 
@@ -5250,29 +9053,20 @@ impl PiApp {
                     return Some(Cmd::new(|| Message::new(PiMsg::RunPending)));
                 }
             }
+            PiMsg::AgentPaused(tool_calls) => {
+                self.paused_run = Some(PausedRun::new(tool_calls));
+                self.status_message = Some("Agent paused".to_string());
+            }
             PiMsg::AgentError(error) => {
-                self.current_response.clear();
-                self.current_thinking.clear();
                 let content = if error.contains('\n') || error.starts_with("Error:") {
                     error
                 } else {
                     format!("Error: {error}")
                 };
-                self.messages.push(ConversationMessage {
-                    role: MessageRole::System,
-                    content,
-                    thinking: None,
-                });
-                self.agent_state = AgentState::Idle;
-                self.current_tool = None;
-                self.abort_handle = None;
-                self.extension_streaming.store(false, Ordering::SeqCst);
-                self.extension_compacting.store(false, Ordering::SeqCst);
-                self.input.focus();
-
-                if !self.pending_inputs.is_empty() {
-                    return Some(Cmd::new(|| Message::new(PiMsg::RunPending)));
-                }
+                return self.finish_with_error(MessageRole::System, content);
+            }
+            PiMsg::AgentFailure(panel) => {
+                return self.finish_with_error(MessageRole::Error, panel.to_content());
             }
             PiMsg::UpdateLastUserMessage(content) => {
                 if let Some(message) = self
@@ -5285,6 +9079,14 @@ impl PiApp {
                 }
                 self.scroll_to_bottom();
             }
+            PiMsg::Notice(message) => {
+                self.messages.push(ConversationMessage {
+                    role: MessageRole::System,
+                    content: message,
+                    thinking: None,
+                });
+                self.follow_conversation();
+            }
             PiMsg::System(message) => {
                 self.messages.push(ConversationMessage {
                     role: MessageRole::System,
@@ -5301,21 +9103,35 @@ impl PiApp {
                     return Some(Cmd::new(|| Message::new(PiMsg::RunPending)));
                 }
             }
-            PiMsg::BashResult {
-                display,
-                content_for_agent,
-            } => {
+            PiMsg::BashOutput(output) => {
+                self.bash_live_output = Some(output);
+                self.follow_conversation();
+            }
+            PiMsg::BashPreview(preview) => {
                 self.bash_running = false;
+                self.bash_live_output = None;
+                self.bash_started_at = None;
                 self.current_tool = None;
                 self.agent_state = AgentState::Idle;
+                self.refresh_git_status();
+                self.bash_preview = Some(*preview);
+                self.scroll_to_bottom();
+                self.input.focus();
 
-                if let Some(content) = content_for_agent {
-                    self.scroll_to_bottom();
-                    return self.submit_content(content);
+                if !self.pending_inputs.is_empty() {
+                    return Some(Cmd::new(|| Message::new(PiMsg::RunPending)));
                 }
+            }
+            PiMsg::BashResult { display } => {
+                self.bash_running = false;
+                self.bash_live_output = None;
+                self.bash_started_at = None;
+                self.current_tool = None;
+                self.agent_state = AgentState::Idle;
+                self.refresh_git_status();
 
                 self.messages.push(ConversationMessage {
-                    role: MessageRole::System,
+                    role: MessageRole::Tool,
                     content: display,
                     thinking: None,
                 });
@@ -5339,6 +9155,12 @@ impl PiApp {
                 self.current_tool = None;
                 self.abort_handle = None;
                 self.status_message = status;
+                self.apply_branch_model_settings();
+                self.restore_thinking_display();
+                self.refresh_code_languages();
+                if self.messages.is_empty() {
+                    self.refresh_recent_sessions();
+                }
                 self.scroll_to_bottom();
                 self.input.focus();
             }
@@ -5355,7 +9177,7 @@ impl PiApp {
                 self.autocomplete.provider.set_catalog(autocomplete_catalog);
                 self.autocomplete.close();
                 self.resources = resources;
-                self.apply_theme(Theme::resolve(&self.config, &self.cwd));
+                self.apply_theme(self.resolve_configured_theme());
                 self.agent_state = AgentState::Idle;
                 self.current_tool = None;
                 self.abort_handle = None;
@@ -5373,10 +9195,218 @@ impl PiApp {
             PiMsg::ExtensionUiRequest(request) => {
                 return self.handle_extension_ui_request(request);
             }
+            PiMsg::ToolApprovalRequest(request) => {
+                self.prompt_tool_approval(request);
+            }
+            PiMsg::HunkReview(request) => self.start_hunk_review(request),
+            PiMsg::PlainInput(line) => return self.handle_plain_input(line.trim()),
+            PiMsg::ProviderHealth(results) => {
+                self.provider_health = provider_health::status_by_provider(&results);
+            }
+            PiMsg::GitStatus(status) => {
+                self.git_status = status;
+            }
+            PiMsg::StatusSegment { id, text } => {
+                if text.is_empty() {
+                    self.status_segments.remove(&id);
+                } else {
+                    self.status_segments.insert(id, text);
+                }
+            }
+            PiMsg::TurnSaved => {
+                if self.agent_state == AgentState::Idle {
+                    self.check_budget_downgrade();
+                }
+            }
         }
         None
     }
 
+    /// Move the session leaf to `target_id` (no branch summary). Returns `false` if already there.
+    fn navigate_to_entry(&mut self, target_id: String) -> bool {
+        let Ok(session_guard) = self.session.try_lock() else {
+            self.status_message = Some("Session busy; try again".to_string());
+            return true;
+        };
+        if session_guard.labelable_leaf_id().as_deref() == Some(target_id.as_str()) {
+            return false;
+        }
+
+        let old_leaf_id = session_guard.leaf_id.clone();
+        let (entries_to_summarize, summary_from_id) = collect_tree_branch_entries(
+            &session_guard,
+            old_leaf_id.as_deref(),
+            Some(target_id.as_str()),
+        );
+        let session_id = session_guard.header.id.clone();
+        drop(session_guard);
+
+        let pending = PendingTreeNavigation {
+            session_id,
+            old_leaf_id,
+            selected_entry_id: target_id.clone(),
+            new_leaf_id: Some(target_id),
+            editor_text: None,
+            entries_to_summarize,
+            summary_from_id,
+            api_key_present: false,
+        };
+        self.start_tree_navigation(pending, TreeSummaryChoice::NoSummary, None);
+        true
+    }
+
+    /// List `/save` checkpoints, newest last, with their ages.
+    fn show_notes(&mut self) {
+        let Ok(session_guard) = self.session.try_lock() else {
+            self.status_message = Some("Session busy; try again".to_string());
+            return;
+        };
+        let notes = session_guard.notes();
+        drop(session_guard);
+        if notes.is_empty() {
+            self.status_message = Some("No notes yet (use /note <text> to add one)".to_string());
+            return;
+        }
+
+        let now = Utc::now();
+        let mut content = String::from("Notes:\n");
+        for note in &notes {
+            let age = chrono::DateTime::parse_from_rfc3339(&note.timestamp).map_or_else(
+                |_| "unknown age".to_string(),
+                |saved| format_age(now.signed_duration_since(saved).num_seconds()),
+            );
+            let _ = writeln!(content, "  {age}: {}", note.text);
+        }
+
+        self.messages.push(ConversationMessage {
+            role: MessageRole::System,
+            content: content.trim_end().to_string(),
+            thinking: None,
+        });
+        self.scroll_to_bottom();
+    }
+
+    fn show_checkpoints(&mut self) {
+        let Ok(session_guard) = self.session.try_lock() else {
+            self.status_message = Some("Session busy; try again".to_string());
+            return;
+        };
+        let checkpoints = session_guard.checkpoints();
+        drop(session_guard);
+        if checkpoints.is_empty() {
+            self.status_message =
+                Some("No checkpoints yet (use /save <name> to add one)".to_string());
+            return;
+        }
+
+        let now = Utc::now();
+        let mut content = String::from("Checkpoints (use /restore <name>):\n");
+        for checkpoint in &checkpoints {
+            let age = chrono::DateTime::parse_from_rfc3339(&checkpoint.timestamp).map_or_else(
+                |_| "unknown age".to_string(),
+                |saved| format_age(now.signed_duration_since(saved).num_seconds()),
+            );
+            let files = match checkpoint
+                .worktree
+                .as_ref()
+                .map(WorktreeSnapshot::from_value)
+            {
+                None => "conversation only",
+                Some(snapshot) if snapshot.stash.is_some() => "with uncommitted changes",
+                Some(_) => "clean worktree",
+            };
+            let _ = writeln!(content, "  {} - {age} ({files})", checkpoint.name);
+        }
+
+        self.messages.push(ConversationMessage {
+            role: MessageRole::System,
+            content: content.trim_end().to_string(),
+            thinking: None,
+        });
+        self.scroll_to_bottom();
+    }
+
+    /// Re-read git state off the UI thread and post it back as `PiMsg::GitStatus`.
+    fn refresh_git_status(&self) {
+        if !self.config.status_line_show_git() {
+            return;
+        }
+        // A burst of tool calls would otherwise start one `git` process per call.
+        if self.git_status_refreshing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let cwd = self.cwd.clone();
+        let event_tx = self.event_tx.clone();
+        let refreshing = Arc::clone(&self.git_status_refreshing);
+        std::thread::spawn(move || {
+            let status = GitStatusLine::read(&cwd);
+            refreshing.store(false, Ordering::Release);
+            let _ = event_tx.try_send(PiMsg::GitStatus(status));
+        });
+    }
+
+    /// Render every extension status segment once, then keep polled segments fresh.
+    /// Probe each configured provider in the background; results label the model picker.
+    fn start_provider_health_checks(&self) {
+        let models = self.available_models.clone();
+        if models.is_empty() {
+            return;
+        }
+        let event_tx = self.event_tx.clone();
+        self.runtime_handle.spawn(async move {
+            let results = provider_health::check_models(&models).await;
+            let _ = event_tx.try_send(PiMsg::ProviderHealth(results));
+        });
+    }
+
+    fn start_status_segments(&self, manager: &ExtensionManager) {
+        for segment in manager.list_status_segments() {
+            self.render_status_segment(manager, segment.id.clone());
+
+            let Some(interval_ms) = segment.interval_ms else {
+                continue;
+            };
+            let interval =
+                std::time::Duration::from_millis(interval_ms.max(STATUS_SEGMENT_MIN_INTERVAL_MS));
+            let manager = manager.clone();
+            let event_tx = self.event_tx.clone();
+            self.runtime_handle.spawn(async move {
+                loop {
+                    sleep(wall_now(), interval).await;
+                    if let Some(text) = render_status_segment_text(&manager, &segment.id).await {
+                        let _ = event_tx.try_send(PiMsg::StatusSegment {
+                            id: segment.id.clone(),
+                            text,
+                        });
+                    }
+                }
+            });
+        }
+    }
+
+    /// Re-render the status segments that subscribed to `event`.
+    fn refresh_status_segments(&self, event: ExtensionEventName) {
+        let Some(manager) = &self.extensions else {
+            return;
+        };
+        let event = event.to_string();
+        for segment in manager.list_status_segments() {
+            if segment.refreshes_on(&event) {
+                self.render_status_segment(manager, segment.id);
+            }
+        }
+    }
+
+    fn render_status_segment(&self, manager: &ExtensionManager, id: String) {
+        let manager = manager.clone();
+        let event_tx = self.event_tx.clone();
+        self.runtime_handle.spawn(async move {
+            if let Some(text) = render_status_segment_text(&manager, &id).await {
+                let _ = event_tx.try_send(PiMsg::StatusSegment { id, text });
+            }
+        });
+    }
+
     fn handle_extension_ui_request(&mut self, request: ExtensionUiRequest) -> Option<Cmd> {
         if request.expects_response() {
             self.extension_ui_queue.push_back(request);
@@ -5534,10 +9564,20 @@ impl PiApp {
             }
             let next = self.pending_inputs.pop_front()?;
 
+            // Submitting clears the editor; keep whatever the user is typing ahead.
+            let draft = self.input.value();
+            let draft_mode = self.input_mode;
             let cmd = match next {
                 PendingInput::Text(text) => self.submit_message(&text),
                 PendingInput::Content(content) => self.submit_content(content),
             };
+            if !draft.is_empty() && self.input.value().is_empty() {
+                self.input.set_value(&draft);
+                if draft_mode == InputMode::MultiLine {
+                    self.input_mode = InputMode::MultiLine;
+                    self.input.set_height(6);
+                }
+            }
 
             if cmd.is_some() {
                 return cmd;
@@ -5588,6 +9628,29 @@ impl PiApp {
         self.status_message = Some(format!("Queued {label} message"));
     }
 
+    /// Queue the editor contents to run after the current turn finishes.
+    fn queue_typeahead_input(&mut self) {
+        let raw_text = self.input.value();
+        let trimmed = raw_text.trim();
+        if trimmed.is_empty() {
+            self.status_message = Some("No input to queue".to_string());
+            return;
+        }
+
+        self.pending_inputs
+            .push_back(PendingInput::Text(trimmed.to_string()));
+
+        // Clear input and reset to single-line mode
+        self.input.reset();
+        self.input_mode = InputMode::SingleLine;
+        self.input.set_height(3);
+
+        self.status_message = Some(format!(
+            "Queued message ({} queued)",
+            self.pending_inputs.len()
+        ));
+    }
+
     fn restore_queued_messages_to_editor(&mut self, abort: bool) -> usize {
         let (steering, follow_up) = self
             .message_queue
@@ -5595,6 +9658,15 @@ impl PiApp {
             .map_or_else(|_| (Vec::new(), Vec::new()), |mut queue| queue.clear_all());
         let mut all = steering;
         all.extend(follow_up);
+        // Type-ahead text goes back to the editor too; attachments stay queued.
+        let mut kept = VecDeque::new();
+        for pending in self.pending_inputs.drain(..) {
+            match pending {
+                PendingInput::Text(text) => all.push(text),
+                content @ PendingInput::Content(_) => kept.push_back(content),
+            }
+        }
+        self.pending_inputs = kept;
         if all.is_empty() {
             if abort {
                 self.abort_agent();
@@ -5696,7 +9768,8 @@ impl PiApp {
                         return;
                     }
                     Err(err) => {
-                        let _ = event_tx.try_send(PiMsg::AgentError(err.to_string()));
+                        let _ =
+                            event_tx.try_send(PiMsg::AgentFailure(ErrorPanel::from_error(&err)));
                         return;
                     }
                 }
@@ -5714,7 +9787,6 @@ impl PiApp {
                     return;
                 }
             };
-            let previous_len = agent_guard.messages().len();
 
             let event_sender = event_tx.clone();
             let extensions = extensions.clone();
@@ -5765,6 +9837,35 @@ impl PiApp {
                             tool_id: tool_call_id.clone(),
                             is_error: *is_error,
                         }),
+                        AgentEvent::AutoCompaction {
+                            tokens_before,
+                            messages_compacted,
+                        } => Some(PiMsg::Notice(format!(
+                            "Context near limit; summarized {messages_compacted} older messages ({tokens_before} tokens before)"
+                        ))),
+                        AgentEvent::ToolDefinitionsOversized {
+                            tokens,
+                            threshold,
+                            largest_tools,
+                        } => Some(PiMsg::Notice(tool_defs_notice(
+                            *tokens,
+                            *threshold,
+                            largest_tools,
+                        ))),
+                        AgentEvent::RetryScheduled {
+                            attempt,
+                            max_attempts,
+                            delay_ms,
+                            error_message,
+                        } => Some(PiMsg::Notice(retry_notice(
+                            *attempt,
+                            *max_attempts,
+                            *delay_ms,
+                            error_message,
+                        ))),
+                        AgentEvent::Paused { pending_tool_calls } => {
+                            Some(PiMsg::AgentPaused(pending_tool_calls.clone()))
+                        }
                         AgentEvent::AgentEnd { messages, .. } => {
                             let last = last_assistant_message(messages);
                             let mut usage = Usage::default();
@@ -5810,10 +9911,6 @@ impl PiApp {
                 })
                 .await;
 
-            let new_messages: Vec<crate::model::Message> =
-                agent_guard.messages()[previous_len..].to_vec();
-            drop(agent_guard);
-
             let mut session_guard = match session.lock(&cx).await {
                 Ok(guard) => guard,
                 Err(err) => {
@@ -5822,9 +9919,9 @@ impl PiApp {
                     return;
                 }
             };
-            for message in new_messages {
-                session_guard.append_model_message(message);
-            }
+            agent_guard.record_run(&mut session_guard);
+            let title_request = agent_guard.take_title_request(&session_guard);
+            drop(agent_guard);
             let mut save_error = None;
 
             if save_enabled {
@@ -5837,10 +9934,20 @@ impl PiApp {
             if let Some(err) = save_error {
                 let _ = event_tx.try_send(PiMsg::AgentError(err));
             }
+            if let Some(request) = title_request {
+                if let Err(err) = request.apply(&session, save_enabled).await {
+                    tracing::warn!("Failed to generate a session title: {err}");
+                }
+            }
 
-            if let Err(err) = result {
-                let formatted = crate::error_hints::format_error_with_hints(&err);
-                let _ = event_tx.try_send(PiMsg::AgentError(formatted));
+            match result {
+                Ok(_) => {
+                    let _ = event_tx.try_send(PiMsg::TurnSaved);
+                }
+                Err(err) => {
+                    let formatted = crate::error_hints::format_error_with_hints(&err);
+                    let _ = event_tx.try_send(PiMsg::AgentError(formatted));
+                }
             }
         });
 
@@ -5858,6 +9965,10 @@ impl PiApp {
             return None;
         }
 
+        if self.bash_preview.is_some() {
+            self.resolve_bash_preview(false);
+        }
+
         self.bash_running = true;
         self.agent_state = AgentState::ToolRunning;
         self.current_tool = Some("bash".to_string());
@@ -5867,8 +9978,13 @@ impl PiApp {
         self.input_mode = InputMode::SingleLine;
         self.input.set_height(3);
 
+        self.bash_live_output = Some(String::new());
+        self.bash_started_at = Some(std::time::Instant::now());
+        self.scroll_to_bottom();
+
         let event_tx = self.event_tx.clone();
         let session = Arc::clone(&self.session);
+        let agent = Arc::clone(&self.agent);
         let save_enabled = self.save_enabled;
         let cwd = self.cwd.clone();
         let shell_path = self.config.shell_path.clone();
@@ -5876,7 +9992,19 @@ impl PiApp {
         let runtime_handle = self.runtime_handle.clone();
 
         runtime_handle.spawn(async move {
-            let cx = Cx::for_request();
+            let started = std::time::Instant::now();
+            let output_tx = event_tx.clone();
+            let on_update = move |update: ToolUpdate| {
+                let text = update
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text(text) => Some(text.text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<String>();
+                let _ = output_tx.try_send(PiMsg::BashOutput(text));
+            };
             let result = crate::tools::run_bash_command(
                 &cwd,
                 shell_path.as_deref(),
@@ -5884,61 +10012,128 @@ impl PiApp {
                 &command,
                 None,
                 None,
+                Some(&on_update),
             )
             .await;
 
-            match result {
-                Ok(result) => {
-                    let display =
-                        bash_execution_to_text(&command, &result.output, 0, false, false, None);
-
-                    if exclude_from_context {
-                        let mut extra = HashMap::new();
-                        extra.insert("excludeFromContext".to_string(), Value::Bool(true));
-
-                        let bash_message = SessionMessage::BashExecution {
-                            command: command.clone(),
-                            output: result.output.clone(),
-                            exit_code: result.exit_code,
-                            cancelled: Some(result.cancelled),
-                            truncated: Some(result.truncated),
-                            full_output_path: result.full_output_path.clone(),
-                            timestamp: Some(Utc::now().timestamp_millis()),
-                            extra,
-                        };
-
-                        if let Ok(mut session_guard) = session.lock(&cx).await {
-                            session_guard.append_message(bash_message);
-                            if save_enabled {
-                                let _ = session_guard.save().await;
-                            }
-                        }
-
-                        let mut display = display;
-                        display.push_str("\n\n[Output excluded from model context]");
-                        let _ = event_tx.try_send(PiMsg::BashResult {
-                            display,
-                            content_for_agent: None,
-                        });
-                    } else {
-                        let content_for_agent =
-                            vec![ContentBlock::Text(TextContent::new(display.clone()))];
-                        let _ = event_tx.try_send(PiMsg::BashResult {
-                            display,
-                            content_for_agent: Some(content_for_agent),
-                        });
-                    }
-                }
+            let result = match result {
+                Ok(result) => result,
                 Err(err) => {
                     let _ = event_tx.try_send(PiMsg::BashResult {
                         display: format!("Bash command failed: {err}"),
-                        content_for_agent: None,
                     });
+                    return;
                 }
+            };
+
+            // `!` output waits in the preview until the user sends or discards it.
+            if !exclude_from_context {
+                let preview = BashPreview {
+                    command,
+                    output: result.output,
+                    exit_code: result.exit_code.unwrap_or(-1),
+                    cancelled: result.cancelled,
+                    truncated: result.truncated,
+                    full_output_path: result.full_output_path,
+                    elapsed: started.elapsed(),
+                    scroll: 0,
+                };
+                let _ = event_tx.try_send(PiMsg::BashPreview(Box::new(preview)));
+                return;
             }
+
+            let mut display = bash_execution_to_text(
+                &command,
+                &result.output,
+                result.exit_code.unwrap_or(-1),
+                result.cancelled,
+                result.truncated,
+                result.full_output_path.as_deref(),
+            );
+            display.push_str("\n\n[Output excluded from model context]");
+            let mut extra = HashMap::new();
+            extra.insert("excludeFromContext".to_string(), Value::Bool(true));
+            let bash_message = SessionMessage::BashExecution {
+                command: command.clone(),
+                output: result.output.clone(),
+                exit_code: result.exit_code.unwrap_or(-1),
+                cancelled: Some(result.cancelled),
+                truncated: Some(result.truncated),
+                full_output_path: result.full_output_path.clone(),
+                timestamp: Some(Utc::now().timestamp_millis()),
+                extra,
+            };
+            record_bash_execution(&agent, &session, save_enabled, bash_message).await;
+
+            let _ = event_tx.try_send(PiMsg::BashResult { display });
+        });
+
+        None
+    }
+
+    /// Scroll the `!` preview by `delta` lines (positive scrolls toward the start).
+    fn scroll_bash_preview(&mut self, delta: isize) {
+        if let Some(preview) = self.bash_preview.as_mut() {
+            let max_scroll = preview
+                .output
+                .lines()
+                .count()
+                .saturating_sub(BASH_PREVIEW_LINES);
+            preview.scroll = preview.scroll.saturating_add_signed(delta).min(max_scroll);
+        }
+    }
+
+    /// Send the previewed `!` output's tail to the model, or discard it; either way the
+    /// command moves from the preview into the transcript.
+    fn resolve_bash_preview(&mut self, send: bool) {
+        let Some(preview) = self.bash_preview.take() else {
+            return;
+        };
+        let (output, truncated) = if send {
+            let tail = truncate_tail(&preview.output, BASH_SEND_TAIL_LINES, BASH_SEND_TAIL_BYTES);
+            (tail.content, preview.truncated || tail.truncated)
+        } else {
+            (preview.output.clone(), preview.truncated)
+        };
+        let mut display = bash_execution_to_text(
+            &preview.command,
+            &output,
+            preview.exit_code,
+            preview.cancelled,
+            truncated,
+            preview.full_output_path.as_deref(),
+        );
+        if !send {
+            display.push_str("\n\n[Discarded; not sent to the model]");
+        }
+        self.messages.push(ConversationMessage {
+            role: MessageRole::Tool,
+            content: display,
+            thinking: None,
         });
+        self.scroll_to_bottom();
+        if !send {
+            self.status_message = Some(format!("Discarded output of `{}`", preview.command));
+            return;
+        }
+        self.status_message = Some(format!("Sent output of `{}` to the model", preview.command));
 
-        None
+        let bash_message = SessionMessage::BashExecution {
+            command: preview.command,
+            output,
+            exit_code: preview.exit_code,
+            cancelled: Some(preview.cancelled),
+            truncated: Some(truncated),
+            full_output_path: preview.full_output_path,
+            timestamp: Some(Utc::now().timestamp_millis()),
+            extra: HashMap::new(),
+        };
+        let agent = Arc::clone(&self.agent);
+        let session = Arc::clone(&self.session);
+        let save_enabled = self.save_enabled;
+        self.runtime_handle.spawn(async move {
+            record_bash_execution(&agent, &session, save_enabled, bash_message).await;
+        });
     }
 
     /// Submit a message to the agent.
@@ -5969,6 +10164,16 @@ impl PiApp {
             return self.submit_oauth_code(message, pending);
         }
 
+        if self.editing_system_prompt {
+            self.editing_system_prompt = false;
+            self.set_system_prompt(message);
+            self.input.reset();
+            self.input_mode = InputMode::SingleLine;
+            self.input.set_height(3);
+            self.input.focus();
+            return None;
+        }
+
         if let Some((command, exclude_from_context)) = parse_bash_command(message) {
             return self.submit_bash_command(message, command, exclude_from_context);
         }
@@ -5994,23 +10199,22 @@ impl PiApp {
             self.resources.expand_input(message_without_refs.trim())
         };
 
-        if !file_refs.is_empty() {
-            let auto_resize = self
-                .config
-                .images
-                .as_ref()
-                .and_then(|images| images.auto_resize)
-                .unwrap_or(true);
-
-            let processed = match process_file_arguments(&file_refs, &self.cwd, auto_resize) {
-                Ok(processed) => processed,
-                Err(err) => {
-                    self.status_message = Some(err.to_string());
-                    return None;
+        if !file_refs.is_empty() || !self.pending_attachments.is_empty() {
+            let processed = if file_refs.is_empty() {
+                ProcessedFiles::default()
+            } else {
+                match process_file_arguments(&file_refs, &self.cwd, self.auto_resize_images()) {
+                    Ok(processed) => processed,
+                    Err(err) => {
+                        self.status_message = Some(err.to_string());
+                        return None;
+                    }
                 }
             };
 
-            let mut text = processed.text;
+            let attachments = std::mem::take(&mut self.pending_attachments);
+            let mut text: String = attachments.iter().map(|a| a.text.as_str()).collect();
+            text.push_str(&processed.text);
             if !message_for_agent.trim().is_empty() {
                 text.push_str(&message_for_agent);
             }
@@ -6019,7 +10223,11 @@ impl PiApp {
             if !text.trim().is_empty() {
                 content.push(ContentBlock::Text(TextContent::new(text)));
             }
-            for image in processed.images {
+            for image in attachments
+                .into_iter()
+                .filter_map(|attachment| attachment.image)
+                .chain(processed.images)
+            {
                 content.push(ContentBlock::Image(image));
             }
 
@@ -6083,7 +10291,8 @@ impl PiApp {
                         return;
                     }
                     Err(err) => {
-                        let _ = event_tx.try_send(PiMsg::AgentError(err.to_string()));
+                        let _ =
+                            event_tx.try_send(PiMsg::AgentFailure(ErrorPanel::from_error(&err)));
                         return;
                     }
                 }
@@ -6101,7 +10310,6 @@ impl PiApp {
                     return;
                 }
             };
-            let previous_len = agent_guard.messages().len();
 
             let event_sender = event_tx.clone();
             let extensions = extensions.clone();
@@ -6152,6 +10360,35 @@ impl PiApp {
                                 tool_id: tool_call_id.clone(),
                                 is_error: *is_error,
                             }),
+                            AgentEvent::AutoCompaction {
+                                tokens_before,
+                                messages_compacted,
+                            } => Some(PiMsg::Notice(format!(
+                                "Context near limit; summarized {messages_compacted} older messages ({tokens_before} tokens before)"
+                            ))),
+                            AgentEvent::ToolDefinitionsOversized {
+                                tokens,
+                                threshold,
+                                largest_tools,
+                            } => Some(PiMsg::Notice(tool_defs_notice(
+                                *tokens,
+                                *threshold,
+                                largest_tools,
+                            ))),
+                            AgentEvent::RetryScheduled {
+                                attempt,
+                                max_attempts,
+                                delay_ms,
+                                error_message,
+                            } => Some(PiMsg::Notice(retry_notice(
+                                *attempt,
+                                *max_attempts,
+                                *delay_ms,
+                                error_message,
+                            ))),
+                            AgentEvent::Paused { pending_tool_calls } => {
+                                Some(PiMsg::AgentPaused(pending_tool_calls.clone()))
+                            }
                             AgentEvent::AgentEnd { messages, .. } => {
                                 let last = last_assistant_message(messages);
                                 let mut usage = Usage::default();
@@ -6247,6 +10484,35 @@ impl PiApp {
                                     tool_id: tool_call_id.clone(),
                                     is_error: *is_error,
                                 }),
+                                AgentEvent::AutoCompaction {
+                                    tokens_before,
+                                    messages_compacted,
+                                } => Some(PiMsg::Notice(format!(
+                                    "Context near limit; summarized {messages_compacted} older messages ({tokens_before} tokens before)"
+                                ))),
+                                AgentEvent::ToolDefinitionsOversized {
+                                    tokens,
+                                    threshold,
+                                    largest_tools,
+                                } => Some(PiMsg::Notice(tool_defs_notice(
+                                    *tokens,
+                                    *threshold,
+                                    largest_tools,
+                                ))),
+                                AgentEvent::RetryScheduled {
+                                    attempt,
+                                    max_attempts,
+                                    delay_ms,
+                                    error_message,
+                                } => Some(PiMsg::Notice(retry_notice(
+                                    *attempt,
+                                    *max_attempts,
+                                    *delay_ms,
+                                    error_message,
+                                ))),
+                                AgentEvent::Paused { pending_tool_calls } => {
+                                    Some(PiMsg::AgentPaused(pending_tool_calls.clone()))
+                                }
                                 AgentEvent::AgentEnd { messages, .. } => {
                                     let last = last_assistant_message(messages);
                                     let mut usage = Usage::default();
@@ -6293,10 +10559,6 @@ impl PiApp {
                     .await
             };
 
-            let new_messages: Vec<crate::model::Message> =
-                agent_guard.messages()[previous_len..].to_vec();
-            drop(agent_guard);
-
             let mut session_guard = match session.lock(&cx).await {
                 Ok(guard) => guard,
                 Err(err) => {
@@ -6305,9 +10567,9 @@ impl PiApp {
                     return;
                 }
             };
-            for message in new_messages {
-                session_guard.append_model_message(message);
-            }
+            agent_guard.record_run(&mut session_guard);
+            let title_request = agent_guard.take_title_request(&session_guard);
+            drop(agent_guard);
             let mut save_error = None;
 
             if save_enabled {
@@ -6320,9 +10582,14 @@ impl PiApp {
             if let Some(err) = save_error {
                 let _ = event_tx.try_send(PiMsg::AgentError(err));
             }
+            if let Some(request) = title_request {
+                if let Err(err) = request.apply(&session, save_enabled).await {
+                    tracing::warn!("Failed to generate a session title: {err}");
+                }
+            }
 
             if let Err(err) = result {
-                let _ = event_tx.try_send(PiMsg::AgentError(err.to_string()));
+                let _ = event_tx.try_send(PiMsg::AgentFailure(ErrorPanel::from_error(&err)));
             }
         });
 
@@ -6636,6 +10903,8 @@ impl PiApp {
             return;
         };
         agent_guard.set_provider(provider_impl);
+        agent_guard.set_context_window(next.model.context_window);
+        agent_guard.set_pricing(Some(next.model.cost.clone()));
         drop(agent_guard);
 
         let Ok(mut session_guard) = self.session.try_lock() else {
@@ -6659,6 +10928,206 @@ impl PiApp {
         self.status_message = Some(format!("Switched model: {}", self.model));
     }
 
+    /// A line entered in screen reader mode: answers a pending prompt, is queued while the
+    /// agent is busy, or is submitted like the editor's Enter.
+    fn handle_plain_input(&mut self, line: &str) -> Option<Cmd> {
+        if self.hunk_review.is_some() {
+            self.handle_hunk_review_input(line);
+            return None;
+        }
+        if self.bash_preview.is_some() {
+            match line.to_lowercase().as_str() {
+                "" | "s" | "send" => {
+                    self.resolve_bash_preview(true);
+                    return None;
+                }
+                "d" | "discard" => {
+                    self.resolve_bash_preview(false);
+                    return None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(request) = self.tool_approval_prompt.take() {
+            let decision = match line.to_lowercase().as_str() {
+                "y" | "yes" => ToolApprovalDecision::Allow,
+                "n" | "no" => ToolApprovalDecision::Deny,
+                "a" | "always" => ToolApprovalDecision::AlwaysAllow,
+                "r" | "review" if HUNK_REVIEW_TOOLS.contains(&request.tool_name.as_str()) => {
+                    ToolApprovalDecision::Review
+                }
+                _ => {
+                    self.prompt_tool_approval(request);
+                    return None;
+                }
+            };
+            self.resolve_tool_approval(&request, decision);
+            return None;
+        }
+        if line.is_empty() {
+            return None;
+        }
+        if self.agent_state != AgentState::Idle {
+            self.pending_inputs
+                .push_back(PendingInput::Text(line.to_string()));
+            self.status_message =
+                Some("Queued; it runs when the current reply finishes".to_string());
+            return None;
+        }
+        self.submit_message(line)
+    }
+
+    /// Full-screen pickers cannot be used in screen reader mode; close them and say how to
+    /// get the same result with command arguments.
+    fn dismiss_overlays_for_plain_mode(&mut self) {
+        let hint = if self.model_selector.take().is_some() {
+            "Model picker is not available here; use /model provider/id"
+        } else if self.session_picker.take().is_some() {
+            "Session picker is not available here; use /resume <n>"
+        } else if self.settings_ui.take().is_some() {
+            "Settings menu is not available here; edit settings.json instead"
+        } else if self.tree_ui.take().is_some() {
+            "Tree navigator is not available here; use /branch <index> or /goto <label>"
+        } else {
+            return;
+        };
+        self.status_message = Some(hint.to_string());
+    }
+
+    /// `images.auto_resize`, on by default.
+    fn auto_resize_images(&self) -> bool {
+        self.config
+            .images
+            .as_ref()
+            .and_then(|images| images.auto_resize)
+            .unwrap_or(true)
+    }
+
+    /// `/attach <glob>`: queue matching files for the next message and preview their size.
+    fn attach_files(&mut self, pattern: &str) {
+        if pattern.is_empty() {
+            self.status_message = Some(if self.pending_attachments.is_empty() {
+                "Usage: /attach <glob> (e.g. /attach src/**/*.rs)".to_string()
+            } else {
+                let names = self
+                    .pending_attachments
+                    .iter()
+                    .map(|attachment| attachment.display.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("Attached to next message: {names}")
+            });
+            return;
+        }
+        if pattern == "clear" {
+            let count = self.pending_attachments.len();
+            self.pending_attachments.clear();
+            self.status_message = Some(format!(
+                "Removed {count} attachment{}",
+                if count == 1 { "" } else { "s" }
+            ));
+            return;
+        }
+
+        let files = match expand_attach_pattern(pattern, &self.cwd) {
+            Ok(files) => files,
+            Err(err) => {
+                self.status_message = Some(err);
+                return;
+            }
+        };
+        if files.is_empty() {
+            self.status_message = Some(format!("No files match {pattern}"));
+            return;
+        }
+        if files.len() > ATTACH_MAX_FILES {
+            self.status_message = Some(format!(
+                "{pattern} matches {} files; narrow it to at most {ATTACH_MAX_FILES}",
+                files.len()
+            ));
+            return;
+        }
+
+        let auto_resize = self.auto_resize_images();
+        let mut added = Vec::new();
+        for path in files {
+            let attachment = match load_attachment(&path, &self.cwd, auto_resize) {
+                Ok(attachment) => attachment,
+                Err(err) => {
+                    self.status_message = Some(err.to_string());
+                    return;
+                }
+            };
+            if !self
+                .pending_attachments
+                .iter()
+                .any(|pending| pending.display == attachment.display)
+            {
+                added.push(attachment);
+            }
+        }
+        if added.is_empty() {
+            self.status_message = Some(format!("Files matching {pattern} are already attached"));
+            return;
+        }
+
+        let total_bytes: usize = added.iter().map(|attachment| attachment.text_bytes).sum();
+        let count = added.len();
+        let mut preview = format!(
+            "Attached {count} file{} to your next message ({}, ~{} tokens):",
+            if count == 1 { "" } else { "s" },
+            format_size(total_bytes),
+            total_bytes.div_ceil(4)
+        );
+        for attachment in &added {
+            let _ = write!(
+                preview,
+                "\n  {} ({}",
+                attachment.display,
+                format_size(attachment.text_bytes)
+            );
+            if let Some(note) = &attachment.note {
+                let _ = write!(preview, ", {note}");
+            }
+            preview.push(')');
+        }
+        self.messages.push(ConversationMessage {
+            role: MessageRole::System,
+            content: preview,
+            thinking: None,
+        });
+        self.pending_attachments.extend(added);
+        self.status_message = Some(format!(
+            "{} file{} will be sent with your next message (/attach clear to drop)",
+            self.pending_attachments.len(),
+            if self.pending_attachments.len() == 1 {
+                ""
+            } else {
+                "s"
+            }
+        ));
+        self.scroll_to_bottom();
+    }
+
+    /// Totals for this run, shown when the TUI exits.
+    pub fn exit_summary(&self) -> ExitSummary {
+        let session_path = if self.save_enabled {
+            self.session
+                .try_lock()
+                .ok()
+                .and_then(|session| session.path.clone())
+        } else {
+            None
+        };
+        ExitSummary {
+            turns: self.turns_completed,
+            tool_calls: self.tool_calls,
+            usage: self.run_usage.clone(),
+            wall_time: self.started_at.elapsed(),
+            session_path,
+        }
+    }
+
     fn quit_cmd(&mut self) -> Cmd {
         if let Some(manager) = &self.extensions {
             manager.clear_ui_sender();
@@ -6676,7 +11145,7 @@ impl PiApp {
     /// Returns `Some(Cmd)` if a command should be executed,
     /// `None` if the action was handled without a command.
     #[allow(clippy::too_many_lines)]
-    fn handle_action(&mut self, action: AppAction, key: &KeyMsg) -> Option<Cmd> {
+    fn handle_action(&mut self, action: AppAction) -> Option<Cmd> {
         match action {
             // =========================================================
             // Application actions
@@ -6696,14 +11165,20 @@ impl PiApp {
                     }
                     return None;
                 }
-                if key.key_type == KeyType::Esc {
-                    let (triggered, cmd) = self.handle_double_escape_action();
-                    if triggered {
-                        return cmd;
-                    }
+                let (triggered, cmd) = self.handle_double_escape_action();
+                if triggered {
+                    return cmd;
+                }
+                if self.editing_system_prompt {
+                    self.editing_system_prompt = false;
+                    self.input.reset();
+                    self.input_mode = InputMode::SingleLine;
+                    self.input.set_height(3);
+                    self.status_message = Some("System prompt unchanged".to_string());
+                    return None;
                 }
                 // When idle, Escape exits multi-line mode (but does NOT quit)
-                if key.key_type == KeyType::Esc && self.input_mode == InputMode::MultiLine {
+                if self.input_mode == InputMode::MultiLine {
                     self.input_mode = InputMode::SingleLine;
                     self.input.set_height(3);
                     self.status_message = Some("Single-line mode".to_string());
@@ -6778,6 +11253,18 @@ impl PiApp {
                 }
                 None
             }
+            AppAction::PauseAgent => {
+                if self.agent_state == AgentState::Idle {
+                    self.status_message = Some("Nothing to pause".to_string());
+                } else if self.pause_handle.is_paused() {
+                    self.pause_handle.resume();
+                    self.status_message = Some("Pause cancelled".to_string());
+                } else {
+                    self.pause_handle.pause();
+                    self.status_message = Some("Pausing before the next tool calls...".to_string());
+                }
+                None
+            }
             AppAction::ExternalEditor => {
                 // Ctrl+G: Open external editor with current input
                 if self.agent_state != AgentState::Idle {
@@ -6807,22 +11294,37 @@ impl PiApp {
                 self.cycle_model(-1);
                 None
             }
+            AppAction::CycleThinkingLevel => {
+                if self.agent_state != AgentState::Idle {
+                    self.status_message =
+                        Some("Cannot change thinking level while processing".to_string());
+                    return None;
+                }
+                self.set_thinking_level(self.current_thinking_level().next());
+                None
+            }
 
             // =========================================================
             // Text input actions
             // =========================================================
             AppAction::Submit => {
-                // Enter: Submit when idle, queue steering when busy
-                if self.agent_state != AgentState::Idle {
-                    self.queue_input(QueuedMessageKind::Steering);
-                    return None;
-                }
+                // Enter: Submit when idle, queue type-ahead when busy
                 if self.input_mode == InputMode::MultiLine {
                     // In multi-line mode, Enter inserts a newline (Alt+Enter submits).
                     self.input.insert_rune('\n');
                     return None;
                 }
+                if self.agent_state != AgentState::Idle {
+                    self.queue_typeahead_input();
+                    return None;
+                }
                 let value = self.input.value();
+                // A bare file path (dropped without bracketed paste) is an attachment, not prose.
+                if let Some(files) = self.detect_dropped_files(&value) {
+                    self.input.reset();
+                    self.offer_file_drop(value, files);
+                    return None;
+                }
                 if !value.trim().is_empty() {
                     return self.submit_message(value.trim());
                 }
@@ -6883,6 +11385,9 @@ impl PiApp {
             }
             AppAction::PageDown => {
                 self.conversation_viewport.page_down();
+                if self.viewport_at_bottom() {
+                    self.unseen_lines = 0;
+                }
                 None
             }
 
@@ -6928,6 +11433,14 @@ impl PiApp {
             // =========================================================
             // Message queue actions
             // =========================================================
+            AppAction::Steer => {
+                if self.agent_state == AgentState::Idle {
+                    self.status_message = Some("Nothing to steer: the agent is idle".to_string());
+                } else {
+                    self.steering_prompt = Some(String::new());
+                }
+                None
+            }
             AppAction::Dequeue => {
                 let restored = self.restore_queued_messages_to_editor(false);
                 if restored == 0 {
@@ -6945,14 +11458,11 @@ impl PiApp {
             // Display actions
             // =========================================================
             AppAction::ToggleThinking => {
-                self.thinking_visible = !self.thinking_visible;
-                let content = self.build_conversation_content();
-                self.conversation_viewport.set_content(&content);
-                self.status_message = Some(if self.thinking_visible {
-                    "Thinking shown".to_string()
-                } else {
-                    "Thinking hidden".to_string()
-                });
+                self.set_thinking_display(self.thinking_display.next());
+                None
+            }
+            AppAction::ThinkingPanel => {
+                self.toggle_thinking_panel();
                 None
             }
             AppAction::ExpandTools => {
@@ -7005,18 +11515,22 @@ impl PiApp {
             | AppAction::PageDown
             | AppAction::CycleModelForward
             | AppAction::CycleModelBackward
+            | AppAction::CycleThinkingLevel
             | AppAction::ToggleThinking
+            | AppAction::ThinkingPanel
             | AppAction::ExpandTools
             | AppAction::FollowUp
             | AppAction::NewLine
             | AppAction::Submit
             | AppAction::Dequeue
+            | AppAction::Steer
             | AppAction::Interrupt
             | AppAction::Clear
             | AppAction::Copy
             | AppAction::PasteImage
             | AppAction::Suspend
             | AppAction::ExternalEditor
+            | AppAction::PauseAgent
             | AppAction::Tab => true,
 
             // Other actions pass through to TextArea
@@ -7142,13 +11656,20 @@ impl PiApp {
                 None
             }
             SlashCommand::Model => {
-                if args.trim().is_empty() {
-                    self.status_message = Some(format!("Current model: {}", self.model));
+                if self.agent_state != AgentState::Idle {
+                    self.status_message = Some("Cannot switch models while processing".to_string());
                     return None;
                 }
 
-                if self.agent_state != AgentState::Idle {
-                    self.status_message = Some("Cannot switch models while processing".to_string());
+                if args.trim().is_empty() {
+                    let mut selector =
+                        ModelSelectorOverlay::new(&self.model_scope, &self.available_models);
+                    selector.set_max_visible(self.term_height.saturating_sub(14).clamp(3, 12));
+                    if selector.filtered_len() == 0 {
+                        self.status_message = Some(format!("Current model: {}", self.model));
+                        return None;
+                    }
+                    self.model_selector = Some(selector);
                     return None;
                 }
 
@@ -7214,47 +11735,7 @@ impl PiApp {
                 }
 
                 let next = matches.into_iter().next().expect("matches is non-empty");
-
-                if next.model.provider == self.model_entry.model.provider
-                    && next.model.id == self.model_entry.model.id
-                {
-                    self.status_message = Some(format!("Current model: {}", self.model));
-                    return None;
-                }
-
-                let provider_impl = match providers::create_provider(&next) {
-                    Ok(provider_impl) => provider_impl,
-                    Err(err) => {
-                        self.status_message = Some(err.to_string());
-                        return None;
-                    }
-                };
-
-                let Ok(mut agent_guard) = self.agent.try_lock() else {
-                    self.status_message = Some("Agent busy; try again".to_string());
-                    return None;
-                };
-                agent_guard.set_provider(provider_impl);
-                drop(agent_guard);
-
-                let Ok(mut session_guard) = self.session.try_lock() else {
-                    self.status_message = Some("Session busy; try again".to_string());
-                    return None;
-                };
-                session_guard.header.provider = Some(next.model.provider.clone());
-                session_guard.header.model_id = Some(next.model.id.clone());
-                session_guard
-                    .append_model_change(next.model.provider.clone(), next.model.id.clone());
-                drop(session_guard);
-                self.spawn_save_session();
-
-                self.model_entry = next.clone();
-                if let Ok(mut guard) = self.model_entry_shared.lock() {
-                    *guard = next.clone();
-                }
-                self.model = format!("{}/{}", next.model.provider, next.model.id);
-
-                self.status_message = Some(format!("Switched model: {}", self.model));
+                self.switch_model(next);
                 None
             }
             SlashCommand::Thinking => {
@@ -7278,20 +11759,105 @@ impl PiApp {
                     }
                 };
 
-                let Ok(mut session_guard) = self.session.try_lock() else {
-                    self.status_message = Some("Session busy; try again".to_string());
+                self.set_thinking_level(level);
+                None
+            }
+            SlashCommand::System => {
+                let Ok(agent_guard) = self.agent.try_lock() else {
+                    self.status_message = Some("Agent busy; try again".to_string());
                     return None;
                 };
-                session_guard.header.thinking_level = Some(level.to_string());
-                session_guard.append_thinking_level_change(level.to_string());
-                drop(session_guard);
-                self.spawn_save_session();
+                let current = agent_guard.system_prompt().map(ToString::to_string);
+                drop(agent_guard);
 
-                if let Ok(mut agent_guard) = self.agent.try_lock() {
-                    agent_guard.stream_options_mut().thinking_level = Some(level);
+                match args.trim() {
+                    "" => {
+                        let content = current.map_or_else(
+                            || "No system prompt is set.".to_string(),
+                            |prompt| format!("System prompt:\n\n{prompt}"),
+                        );
+                        self.messages.push(ConversationMessage {
+                            role: MessageRole::System,
+                            content,
+                            thinking: None,
+                        });
+                        self.scroll_to_bottom();
+                    }
+                    "edit" => {
+                        if self.agent_state != AgentState::Idle {
+                            self.status_message =
+                                Some("Cannot edit the system prompt while processing".to_string());
+                            return None;
+                        }
+                        self.editing_system_prompt = true;
+                        self.input.set_value(current.as_deref().unwrap_or_default());
+                        self.input_mode = InputMode::MultiLine;
+                        self.input.set_height(6);
+                        self.input.focus();
+                        self.status_message = Some(
+                            "Editing system prompt: Alt+Enter to apply, Esc to cancel".to_string(),
+                        );
+                    }
+                    other => {
+                        self.status_message = Some(format!(
+                            "Unknown /system argument: {other} (try /system edit)"
+                        ));
+                    }
                 }
+                None
+            }
+            SlashCommand::Tools => {
+                let Ok(mut agent_guard) = self.agent.try_lock() else {
+                    self.status_message = Some("Agent busy; try again".to_string());
+                    return None;
+                };
+
+                let mut parts = args.split_whitespace();
+                let action = parts.next().map(str::to_ascii_lowercase);
+                let name = parts.next();
+                let Some(action) = action else {
+                    let states = agent_guard.tool_states();
+                    drop(agent_guard);
+                    self.messages.push(ConversationMessage {
+                        role: MessageRole::System,
+                        content: format_tool_states(&states),
+                        thinking: None,
+                    });
+                    self.scroll_to_last_match("Tools:");
+                    return None;
+                };
+
+                let (Some(name), None) = (name, parts.next()) else {
+                    self.status_message =
+                        Some("Usage: /tools [enable|disable|toggle <name>]".to_string());
+                    return None;
+                };
+                let currently_enabled = agent_guard
+                    .tool_states()
+                    .into_iter()
+                    .find_map(|(tool, enabled)| (tool == name).then_some(enabled));
+                let Some(currently_enabled) = currently_enabled else {
+                    self.status_message = Some(format!("Unknown tool: {name}"));
+                    return None;
+                };
+                let enable = match action.as_str() {
+                    "enable" | "on" => true,
+                    "disable" | "off" => false,
+                    "toggle" => !currently_enabled,
+                    other => {
+                        self.status_message = Some(format!(
+                            "Unknown /tools action: {other} (try enable, disable, or toggle)"
+                        ));
+                        return None;
+                    }
+                };
+                agent_guard.set_tool_enabled(name, enable);
+                drop(agent_guard);
 
-                self.status_message = Some(format!("Thinking level: {level}"));
+                self.status_message = Some(format!(
+                    "Tool {name} {}",
+                    if enable { "enabled" } else { "disabled" }
+                ));
                 None
             }
             SlashCommand::ScopedModels => {
@@ -7424,12 +11990,33 @@ impl PiApp {
                 };
                 let info = self.format_session_info(&session_guard);
                 drop(session_guard);
-                self.messages.push(ConversationMessage {
-                    role: MessageRole::System,
-                    content: info,
-                    thinking: None,
-                });
-                self.scroll_to_bottom();
+                self.messages.push(ConversationMessage {
+                    role: MessageRole::System,
+                    content: info,
+                    thinking: None,
+                });
+                self.scroll_to_bottom();
+                None
+            }
+            SlashCommand::Stats => {
+                let Ok(session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                let leaf = Some(args.trim()).filter(|leaf| !leaf.is_empty());
+                let stats = session_guard.stats(leaf);
+                drop(session_guard);
+                match stats {
+                    Ok(stats) => {
+                        self.messages.push(ConversationMessage {
+                            role: MessageRole::System,
+                            content: stats.to_text(),
+                            thinking: None,
+                        });
+                        self.scroll_to_bottom();
+                    }
+                    Err(err) => self.status_message = Some(err.to_string()),
+                }
                 None
             }
             SlashCommand::Settings => {
@@ -7455,21 +12042,34 @@ impl PiApp {
                     return None;
                 }
 
-                let theme = if name.eq_ignore_ascii_case("dark") {
-                    Theme::dark()
-                } else if name.eq_ignore_ascii_case("light") {
-                    Theme::light()
-                } else {
-                    match Theme::load_by_name(name, &self.cwd) {
-                        Ok(theme) => theme,
-                        Err(err) => {
-                            self.status_message = Some(err.to_string());
-                            return None;
-                        }
+                if name.eq_ignore_ascii_case("revert") {
+                    // Drop any previewed theme and return to the persisted preference.
+                    self.apply_theme(self.resolve_configured_theme());
+                    self.status_message = Some(format!("Theme: {}", self.theme.name));
+                    return None;
+                }
+
+                let (preview, name) = match name.split_once(char::is_whitespace) {
+                    Some((sub, rest)) if sub.eq_ignore_ascii_case("preview") => (true, rest.trim()),
+                    _ => (false, name),
+                };
+
+                let theme = match self.resolve_theme_spec(name) {
+                    Ok(theme) => theme,
+                    Err(err) => {
+                        self.status_message = Some(err.to_string());
+                        return None;
                     }
                 };
 
                 let theme_name = theme.name.clone();
+                if preview {
+                    self.apply_theme(theme);
+                    self.status_message = Some(format!(
+                        "Previewing theme: {theme_name} (/theme {theme_name} to keep, /theme revert to undo)"
+                    ));
+                    return None;
+                }
                 self.apply_theme(theme);
                 self.config.theme = Some(theme_name.clone());
 
@@ -7490,11 +12090,24 @@ impl PiApp {
                     return None;
                 }
 
-                let override_dir = self
+                if !args.is_empty() {
+                    let Some(index) = args.parse::<usize>().ok().filter(|n| *n > 0) else {
+                        self.status_message = Some("Usage: /resume [n]".to_string());
+                        return None;
+                    };
+                    let Some(meta) = self.recent_project_sessions().into_iter().nth(index - 1)
+                    else {
+                        self.status_message = Some(format!("No recent session #{index}"));
+                        return None;
+                    };
+                    return self.load_session_from_path(&meta.path);
+                }
+
+                let (override_dir, current_path) = self
                     .session
                     .try_lock()
-                    .ok()
-                    .and_then(|guard| guard.session_dir.clone());
+                    .map(|guard| (guard.session_dir.clone(), guard.path.clone()))
+                    .unwrap_or_default();
                 let base_dir = override_dir.clone().unwrap_or_else(Config::sessions_dir);
                 let sessions = crate::session_picker::list_sessions_for_project(
                     &self.cwd,
@@ -7505,10 +12118,9 @@ impl PiApp {
                     return None;
                 }
 
-                self.session_picker = Some(SessionPickerOverlay::new_with_root(
-                    sessions,
-                    Some(base_dir),
-                ));
+                let mut picker = SessionPickerOverlay::new_with_root(sessions, Some(base_dir));
+                picker.current_path = current_path;
+                self.session_picker = Some(picker);
                 self.autocomplete.close();
                 None
             }
@@ -7529,11 +12141,13 @@ impl PiApp {
                     session_guard.header.provider = Some(self.model_entry.model.provider.clone());
                     session_guard.header.model_id = Some(self.model_entry.model.id.clone());
                     session_guard.header.thinking_level = Some(ThinkingLevel::Off.to_string());
+                    let new_session_id = session_guard.header.id.clone();
                     drop(session_guard);
 
                     if let Ok(mut agent_guard) = self.agent.try_lock() {
                         agent_guard.replace_messages(Vec::new());
                         agent_guard.stream_options_mut().thinking_level = Some(ThinkingLevel::Off);
+                        agent_guard.stream_options_mut().session_id = Some(new_session_id);
                     }
 
                     self.messages.clear();
@@ -7623,6 +12237,7 @@ impl PiApp {
                             }
                         };
                         agent_guard.replace_messages(Vec::new());
+                        agent_guard.stream_options_mut().session_id = Some(new_session_id.clone());
                         agent_guard.stream_options_mut().thinking_level = Some(ThinkingLevel::Off);
                     }
 
@@ -7661,14 +12276,41 @@ impl PiApp {
                     .find(|m| m.role == MessageRole::Assistant && !m.content.trim().is_empty())
                     .map(|m| m.content.clone());
 
-                let Some(text) = text else {
+                let Some(mut text) = text else {
                     self.status_message = Some("No agent messages to copy yet.".to_string());
                     return None;
                 };
 
+                // `/copy code` copies the last code block, named by its (detected) language.
+                let mut extension = "txt";
+                let mut label = String::new();
+                if args.trim() == "code" {
+                    let Some(block) = crate::code_lang::code_blocks(&text).pop() else {
+                        self.status_message =
+                            Some("The last agent message has no code block.".to_string());
+                        return None;
+                    };
+                    let language = if block.language.is_empty() {
+                        crate::code_lang::detect_language(&block.code, &self.code_languages)
+                    } else {
+                        Some(block.language.as_str())
+                    };
+                    label = language.map_or_else(
+                        || " code block".to_string(),
+                        |language| format!(" {language} code block"),
+                    );
+                    extension = language
+                        .and_then(crate::code_lang::extension_for)
+                        .unwrap_or("txt");
+                    text = block.code;
+                } else if !args.trim().is_empty() {
+                    self.status_message = Some("Usage: /copy [code]".to_string());
+                    return None;
+                }
+
                 let write_fallback = |text: &str| -> std::io::Result<std::path::PathBuf> {
                     let dir = std::env::temp_dir();
-                    let filename = format!("pi_copy_{}.txt", Utc::now().timestamp_millis());
+                    let filename = format!("pi_copy_{}.{extension}", Utc::now().timestamp_millis());
                     let path = dir.join(filename);
                     std::fs::write(&path, text)?;
                     Ok(path)
@@ -7679,11 +12321,13 @@ impl PiApp {
                     match ClipboardProvider::new()
                         .and_then(|mut ctx: ClipboardContext| ctx.set_contents(text.clone()))
                     {
-                        Ok(()) => self.status_message = Some("Copied to clipboard".to_string()),
+                        Ok(()) => {
+                            self.status_message = Some(format!("Copied{label} to clipboard"));
+                        }
                         Err(err) => match write_fallback(&text) {
                             Ok(path) => {
                                 self.status_message = Some(format!(
-                                    "Clipboard support is disabled or unavailable ({err}). Wrote to {}",
+                                    "Clipboard support is disabled or unavailable ({err}). Wrote{label} to {}",
                                     path.display()
                                 ));
                             }
@@ -7701,7 +12345,7 @@ impl PiApp {
                     match write_fallback(&text) {
                         Ok(path) => {
                             self.status_message = Some(format!(
-                                "Clipboard support is disabled. Wrote to {}",
+                                "Clipboard support is disabled. Wrote{label} to {}",
                                 path.display()
                             ));
                         }
@@ -7715,73 +12359,452 @@ impl PiApp {
 
                 None
             }
-            SlashCommand::Name => {
+            SlashCommand::Name => {
+                let name = args.trim();
+                if name.is_empty() {
+                    self.status_message = Some("Usage: /name <name>".to_string());
+                    return None;
+                }
+
+                let Ok(mut session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                session_guard.append_session_info(Some(name.to_string()));
+                drop(session_guard);
+                self.spawn_save_session();
+
+                self.status_message = Some(format!("Session name: {name}"));
+                None
+            }
+            SlashCommand::Hotkeys => {
+                self.messages.push(ConversationMessage {
+                    role: MessageRole::System,
+                    content: self.format_hotkeys(),
+                    thinking: None,
+                });
+                self.scroll_to_bottom();
+                None
+            }
+            SlashCommand::Changelog => {
+                let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("CHANGELOG.md");
+                match std::fs::read_to_string(&path) {
+                    Ok(content) => {
+                        self.messages.push(ConversationMessage {
+                            role: MessageRole::System,
+                            content,
+                            thinking: None,
+                        });
+                        self.scroll_to_last_match("# ");
+                    }
+                    Err(err) => {
+                        self.status_message = Some(format!(
+                            "Failed to read changelog {}: {err}",
+                            path.display()
+                        ));
+                    }
+                }
+                None
+            }
+            SlashCommand::Tree => {
+                if self.agent_state != AgentState::Idle {
+                    self.status_message = Some("Cannot open tree while processing".to_string());
+                    return None;
+                }
+
+                let Ok(session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                let initial_selected_id = resolve_tree_selector_initial_id(&session_guard, args);
+                let selector = TreeSelectorState::new(
+                    &session_guard,
+                    self.term_height,
+                    initial_selected_id.as_deref(),
+                );
+                drop(session_guard);
+                self.tree_ui = Some(TreeUiState::Selector(selector));
+                None
+            }
+            SlashCommand::Timeline => {
+                if self.agent_state != AgentState::Idle {
+                    self.status_message = Some("Cannot open timeline while processing".to_string());
+                    return None;
+                }
+
+                let Ok(session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                let initial_selected_id = resolve_tree_selector_initial_id(&session_guard, args);
+                let mut selector = TreeSelectorState::new(
+                    &session_guard,
+                    self.term_height,
+                    initial_selected_id.as_deref(),
+                );
+                selector.timeline = true;
+                selector.show_all = true;
+                selector.rebuild(&session_guard);
+                drop(session_guard);
+                self.tree_ui = Some(TreeUiState::Selector(selector));
+                None
+            }
+            SlashCommand::Branch => {
+                if self.agent_state != AgentState::Idle {
+                    self.status_message =
+                        Some("Cannot branch while processing a request".to_string());
+                    return None;
+                }
+
+                let Ok(session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                if session_guard.entries.is_empty() {
+                    self.status_message = Some("Nothing to branch from yet".to_string());
+                    return None;
+                }
+                let initial_selected_id = resolve_tree_selector_initial_id(&session_guard, args);
+                let mut selector = TreeSelectorState::new(
+                    &session_guard,
+                    self.term_height,
+                    initial_selected_id.as_deref(),
+                );
+                drop(session_guard);
+                selector.branch_mode = true;
+                self.tree_ui = Some(TreeUiState::Selector(selector));
+                None
+            }
+            SlashCommand::Label => {
+                let name = args.trim();
+                if name.is_empty() {
+                    self.status_message = Some("Usage: /label <name>".to_string());
+                    return None;
+                }
+
+                let Ok(mut session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                session_guard.ensure_entry_ids();
+                let Some(target_id) = session_guard.labelable_leaf_id() else {
+                    self.status_message = Some("Nothing to label yet".to_string());
+                    return None;
+                };
+                session_guard.add_label(&target_id, Some(name.to_string()));
+                drop(session_guard);
+                self.spawn_save_session();
+
+                self.status_message = Some(format!("Labeled {target_id} as {name}"));
+                None
+            }
+            SlashCommand::Labels => {
+                let Ok(session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                let labels = session_guard.labels();
+                if labels.is_empty() {
+                    self.status_message =
+                        Some("No labels yet (use /label <name> to add one)".to_string());
+                    return None;
+                }
+                let list = labels
+                    .iter()
+                    .map(|(target_id, label)| {
+                        let summary = session_guard.get_entry(target_id).map_or_else(
+                            || "(missing entry)".to_string(),
+                            |entry| TreeSelectorState::describe_entry(entry).0,
+                        );
+                        format!("  {label} - {target_id} {summary}")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                drop(session_guard);
+
+                self.messages.push(ConversationMessage {
+                    role: MessageRole::System,
+                    content: format!("Labels (use /goto <label>):\n{list}"),
+                    thinking: None,
+                });
+                self.scroll_to_bottom();
+                None
+            }
+            SlashCommand::Goto => {
+                let label = args.trim();
+                if label.is_empty() {
+                    self.status_message = Some("Usage: /goto <label>".to_string());
+                    return None;
+                }
+                if self.agent_state != AgentState::Idle {
+                    self.status_message =
+                        Some("Cannot switch checkpoints while processing".to_string());
+                    return None;
+                }
+
+                let Ok(session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                let Some(target_id) = session_guard.find_label_target(label) else {
+                    self.status_message = Some(format!("No checkpoint labeled {label}"));
+                    return None;
+                };
+                drop(session_guard);
+
+                if !self.navigate_to_entry(target_id) {
+                    self.status_message = Some(format!("Already at {label}"));
+                }
+                None
+            }
+            SlashCommand::Note => {
+                let text = args.trim();
+                if text.is_empty() {
+                    self.status_message = Some("Usage: /note <text>".to_string());
+                    return None;
+                }
+                let Ok(mut session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                session_guard.add_note(text);
+                drop(session_guard);
+                self.spawn_save_session();
+
+                self.messages.push(ConversationMessage {
+                    role: MessageRole::System,
+                    content: format!("Note: {text}"),
+                    thinking: None,
+                });
+                self.scroll_to_bottom();
+                self.status_message = Some("Note saved (not sent to the model)".to_string());
+                None
+            }
+            SlashCommand::Notes => {
+                self.show_notes();
+                None
+            }
+            SlashCommand::Tour => {
+                self.start_onboarding_tour();
+                None
+            }
+            SlashCommand::Save => {
+                let name = args.trim();
+                if name.is_empty() {
+                    self.show_checkpoints();
+                    return None;
+                }
+                if self.agent_state != AgentState::Idle {
+                    self.status_message =
+                        Some("Cannot save a checkpoint while processing".to_string());
+                    return None;
+                }
+                let has_leaf = self
+                    .session
+                    .try_lock()
+                    .is_ok_and(|guard| guard.labelable_leaf_id().is_some());
+                if !has_leaf {
+                    self.status_message = Some("Nothing to checkpoint yet".to_string());
+                    return None;
+                }
+
+                let worktree =
+                    match WorktreeSnapshot::capture(&self.cwd, &format!("pi checkpoint: {name}")) {
+                        Ok(snapshot) => snapshot,
+                        Err(err) => {
+                            self.status_message =
+                                Some(format!("Could not snapshot the worktree: {err}"));
+                            return None;
+                        }
+                    };
+                let files_note = match &worktree {
+                    None => " (not a git repo; conversation only)",
+                    Some(snapshot) if snapshot.stash.is_some() => " with uncommitted changes",
+                    Some(_) => "",
+                };
+
+                let Ok(mut session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                let saved = session_guard
+                    .add_checkpoint(name, worktree.as_ref().map(WorktreeSnapshot::to_value));
+                drop(session_guard);
+                if saved.is_none() {
+                    self.status_message = Some("Nothing to checkpoint yet".to_string());
+                    return None;
+                }
+                self.spawn_save_session();
+
+                self.status_message = Some(format!("Saved checkpoint {name}{files_note}"));
+                None
+            }
+            SlashCommand::Attach => {
+                self.attach_files(args.trim());
+                None
+            }
+            SlashCommand::Restore => {
                 let name = args.trim();
                 if name.is_empty() {
-                    self.status_message = Some("Usage: /name <name>".to_string());
+                    self.show_checkpoints();
+                    return None;
+                }
+                if self.agent_state != AgentState::Idle {
+                    self.status_message =
+                        Some("Cannot restore a checkpoint while processing".to_string());
+                    return None;
+                }
+
+                let Ok(session_guard) = self.session.try_lock() else {
+                    self.status_message = Some("Session busy; try again".to_string());
+                    return None;
+                };
+                let Some(checkpoint) = session_guard.find_checkpoint(name) else {
+                    self.status_message = Some(format!("No checkpoint named {name}"));
                     return None;
+                };
+                drop(session_guard);
+
+                let mut files_note = "";
+                if let Some(snapshot) = checkpoint
+                    .worktree
+                    .as_ref()
+                    .map(WorktreeSnapshot::from_value)
+                {
+                    if let Err(err) = snapshot.restore(&self.cwd, name) {
+                        self.status_message = Some(format!("Could not restore files: {err}"));
+                        return None;
+                    }
+                    self.refresh_git_status();
+                    files_note = " and files";
                 }
 
+                self.navigate_to_entry(checkpoint.target_id);
+                self.status_message = Some(format!("Restored conversation{files_note} to {name}"));
+                None
+            }
+            SlashCommand::Incognito => {
                 let Ok(mut session_guard) = self.session.try_lock() else {
                     self.status_message = Some("Session busy; try again".to_string());
                     return None;
                 };
-                session_guard.append_session_info(Some(name.to_string()));
+                let enabled = match args.trim().to_ascii_lowercase().as_str() {
+                    "" => !session_guard.is_incognito(),
+                    "on" => true,
+                    "off" => false,
+                    _ => {
+                        self.status_message = Some("Usage: /incognito [on|off]".to_string());
+                        return None;
+                    }
+                };
+                session_guard.set_incognito(enabled);
+                let ephemeral = session_guard.ephemeral_entry_count();
                 drop(session_guard);
-                self.spawn_save_session();
 
-                self.status_message = Some(format!("Session name: {name}"));
+                self.status_message = Some(if enabled {
+                    "Incognito on: new messages stay in memory and are not saved".to_string()
+                } else if ephemeral > 0 {
+                    format!("Incognito off: {ephemeral} earlier entries remain unsaved")
+                } else {
+                    "Incognito off".to_string()
+                });
                 None
             }
-            SlashCommand::Hotkeys => {
-                self.messages.push(ConversationMessage {
-                    role: MessageRole::System,
-                    content: self.format_hotkeys(),
-                    thinking: None,
+            SlashCommand::Pin => {
+                let raw = args.trim();
+                if raw.is_empty() {
+                    self.status_message = Some("Usage: /pin <path>".to_string());
+                    return None;
+                }
+                let path = self.resolve_pin_path(raw);
+                if !path.is_file() {
+                    self.status_message = Some(format!("Not a file: {raw}"));
+                    return None;
+                }
+                let Ok(mut agent_guard) = self.agent.try_lock() else {
+                    self.status_message = Some("Agent busy; try again".to_string());
+                    return None;
+                };
+                agent_guard.set_pinned_budget_tokens(self.config.pin_budget_tokens());
+                let added = agent_guard.pin_file(path.clone());
+                let count = agent_guard.pinned_files().len();
+                drop(agent_guard);
+
+                let display = self.display_pin_path(&path);
+                self.status_message = Some(if added {
+                    format!("Pinned {display} ({count} pinned)")
+                } else {
+                    format!("{display} is already pinned")
                 });
-                self.scroll_to_bottom();
                 None
             }
-            SlashCommand::Changelog => {
-                let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("CHANGELOG.md");
-                match std::fs::read_to_string(&path) {
-                    Ok(content) => {
+            SlashCommand::Pins => {
+                let Ok(mut agent_guard) = self.agent.try_lock() else {
+                    self.status_message = Some("Agent busy; try again".to_string());
+                    return None;
+                };
+                let args = args.trim();
+                let (action, target) = args
+                    .split_once(char::is_whitespace)
+                    .map_or((args, ""), |(action, rest)| (action, rest.trim()));
+                match action {
+                    "" => {
+                        let pinned = agent_guard.pinned_files().to_vec();
+                        drop(agent_guard);
+                        if pinned.is_empty() {
+                            self.status_message =
+                                Some("No pinned files (use /pin <path> to add one)".to_string());
+                            return None;
+                        }
+                        let list = pinned
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, path)| {
+                                let size = std::fs::metadata(path).map_or_else(
+                                    |_| "missing".to_string(),
+                                    |meta| format!("{} bytes", meta.len()),
+                                );
+                                format!("  {}. {} ({size})", idx + 1, self.display_pin_path(path))
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
                         self.messages.push(ConversationMessage {
                             role: MessageRole::System,
-                            content,
+                            content: format!(
+                                "Pinned files (use /pins remove <n> or /pins clear):\n{list}"
+                            ),
                             thinking: None,
                         });
-                        self.scroll_to_last_match("# ");
+                        self.scroll_to_bottom();
                     }
-                    Err(err) => {
-                        self.status_message = Some(format!(
-                            "Failed to read changelog {}: {err}",
-                            path.display()
-                        ));
+                    "clear" => {
+                        let count = agent_guard.pinned_files().len();
+                        agent_guard.clear_pinned_files();
+                        drop(agent_guard);
+                        self.status_message = Some(format!("Unpinned {count} files"));
+                    }
+                    "remove" | "rm" if !target.is_empty() => {
+                        let path = target
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|idx| idx.checked_sub(1))
+                            .and_then(|idx| agent_guard.pinned_files().get(idx).cloned())
+                            .unwrap_or_else(|| self.resolve_pin_path(target));
+                        let removed = agent_guard.unpin_file(&path);
+                        drop(agent_guard);
+                        self.status_message = Some(if removed {
+                            format!("Unpinned {}", self.display_pin_path(&path))
+                        } else {
+                            format!("Not pinned: {target}")
+                        });
+                    }
+                    _ => {
+                        self.status_message =
+                            Some("Usage: /pins [remove <n|path> | clear]".to_string());
                     }
                 }
                 None
             }
-            SlashCommand::Tree => {
-                if self.agent_state != AgentState::Idle {
-                    self.status_message = Some("Cannot open tree while processing".to_string());
-                    return None;
-                }
-
-                let Ok(session_guard) = self.session.try_lock() else {
-                    self.status_message = Some("Session busy; try again".to_string());
-                    return None;
-                };
-                let initial_selected_id = resolve_tree_selector_initial_id(&session_guard, args);
-                let selector = TreeSelectorState::new(
-                    &session_guard,
-                    self.term_height,
-                    initial_selected_id.as_deref(),
-                );
-                drop(session_guard);
-                self.tree_ui = Some(TreeUiState::Selector(selector));
-                None
-            }
             SlashCommand::Fork => {
                 if self.agent_state != AgentState::Idle {
                     self.status_message =
@@ -7915,6 +12938,7 @@ impl PiApp {
                         entries,
                         leaf_id,
                         selected_text,
+                        ephemeral_ids,
                     } = fork_plan;
 
                     let mut new_session = Session::create_with_dir(session_dir);
@@ -7926,6 +12950,7 @@ impl PiApp {
                     }
                     new_session.entries = entries;
                     new_session.leaf_id = leaf_id;
+                    new_session.mark_ephemeral(ephemeral_ids);
                     new_session.ensure_entry_ids();
                     let new_session_id = new_session.header.id.clone();
 
@@ -7947,6 +12972,7 @@ impl PiApp {
                             }
                         };
                         agent_guard.replace_messages(messages_for_agent);
+                        agent_guard.stream_options_mut().session_id = Some(new_session_id.clone());
                     }
 
                     {
@@ -8005,189 +13031,10 @@ impl PiApp {
                     return None;
                 }
 
-                let Ok(agent_guard) = self.agent.try_lock() else {
-                    self.status_message = Some("Agent busy; try again".to_string());
-                    return None;
-                };
-                let provider = agent_guard.provider();
-                let api_key_opt = agent_guard.stream_options().api_key.clone();
-                drop(agent_guard);
-
-                let Some(api_key) = api_key_opt else {
-                    self.status_message =
-                        Some("No API key configured; cannot run compaction".to_string());
-                    return None;
-                };
-
-                let event_tx = self.event_tx.clone();
-                let session = Arc::clone(&self.session);
-                let agent = Arc::clone(&self.agent);
-                let extensions = self.extensions.clone();
-                let runtime_handle = self.runtime_handle.clone();
-                let reserve_tokens = self.config.compaction_reserve_tokens();
-                let keep_recent_tokens = self.config.compaction_keep_recent_tokens();
-                let custom_instructions = args.trim().to_string();
-                let custom_instructions = if custom_instructions.is_empty() {
-                    None
-                } else {
-                    Some(custom_instructions)
-                };
-                let is_compacting = Arc::clone(&self.extension_compacting);
-
-                self.agent_state = AgentState::Processing;
-                self.status_message = Some("Compacting session...".to_string());
-                self.extension_compacting.store(true, Ordering::SeqCst);
-
-                runtime_handle.spawn(async move {
-                    let cx = Cx::for_request();
-
-                    let (session_id, path_entries) = {
-                        let mut guard = match session.lock(&cx).await {
-                            Ok(guard) => guard,
-                            Err(err) => {
-                                is_compacting.store(false, Ordering::SeqCst);
-                                let _ = event_tx.try_send(PiMsg::AgentError(format!(
-                                    "Failed to lock session: {err}"
-                                )));
-                                return;
-                            }
-                        };
-                        guard.ensure_entry_ids();
-                        let session_id = guard.header.id.clone();
-                        let entries = guard
-                            .entries_for_current_path()
-                            .into_iter()
-                            .cloned()
-                            .collect::<Vec<_>>();
-                        (session_id, entries)
-                    };
-
-                    if let Some(manager) = extensions.clone() {
-                        let cancelled = manager
-                            .dispatch_cancellable_event(
-                                ExtensionEventName::SessionBeforeCompact,
-                                Some(json!({
-                                    "sessionId": session_id,
-                                    "notes": custom_instructions.as_deref(),
-                                })),
-                                EXTENSION_EVENT_TIMEOUT_MS,
-                            )
-                            .await
-                            .unwrap_or(false);
-                        if cancelled {
-                            is_compacting.store(false, Ordering::SeqCst);
-                            let _ = event_tx.try_send(PiMsg::System(
-                                "Compaction cancelled by extension".to_string(),
-                            ));
-                            return;
-                        }
-                    }
-
-                    let settings = crate::compaction::ResolvedCompactionSettings {
-                        enabled: true,
-                        reserve_tokens,
-                        keep_recent_tokens,
-                    };
-                    let Some(prep) = crate::compaction::prepare_compaction(&path_entries, settings)
-                    else {
-                        is_compacting.store(false, Ordering::SeqCst);
-                        let _ = event_tx.try_send(PiMsg::System(
-                            "Nothing to compact (already compacted or too little history)"
-                                .to_string(),
-                        ));
-                        return;
-                    };
-
-                    let result = match crate::compaction::compact(
-                        prep,
-                        Arc::clone(&provider),
-                        &api_key,
-                        custom_instructions.as_deref(),
-                    )
-                    .await
-                    {
-                        Ok(result) => result,
-                        Err(err) => {
-                            is_compacting.store(false, Ordering::SeqCst);
-                            let _ = event_tx
-                                .try_send(PiMsg::AgentError(format!("Compaction failed: {err}")));
-                            return;
-                        }
-                    };
-
-                    let details =
-                        crate::compaction::compaction_details_to_value(&result.details).ok();
-
-                    let messages_for_agent = {
-                        let mut guard = match session.lock(&cx).await {
-                            Ok(guard) => guard,
-                            Err(err) => {
-                                is_compacting.store(false, Ordering::SeqCst);
-                                let _ = event_tx.try_send(PiMsg::AgentError(format!(
-                                    "Failed to lock session: {err}"
-                                )));
-                                return;
-                            }
-                        };
-
-                        guard.append_compaction(
-                            result.summary.clone(),
-                            result.first_kept_entry_id.clone(),
-                            result.tokens_before,
-                            details,
-                            None,
-                        );
-                        let _ = guard.save().await;
-                        guard.to_messages_for_current_path()
-                    };
-
-                    {
-                        let mut agent_guard = match agent.lock(&cx).await {
-                            Ok(guard) => guard,
-                            Err(err) => {
-                                is_compacting.store(false, Ordering::SeqCst);
-                                let _ = event_tx.try_send(PiMsg::AgentError(format!(
-                                    "Failed to lock agent: {err}"
-                                )));
-                                return;
-                            }
-                        };
-                        agent_guard.replace_messages(messages_for_agent);
-                    }
-
-                    let (messages, usage) = {
-                        let guard = match session.lock(&cx).await {
-                            Ok(guard) => guard,
-                            Err(err) => {
-                                is_compacting.store(false, Ordering::SeqCst);
-                                let _ = event_tx.try_send(PiMsg::AgentError(format!(
-                                    "Failed to lock session: {err}"
-                                )));
-                                return;
-                            }
-                        };
-                        load_conversation_from_session(&guard)
-                    };
-
-                    is_compacting.store(false, Ordering::SeqCst);
-                    let _ = event_tx.try_send(PiMsg::ConversationReset {
-                        messages,
-                        usage,
-                        status: Some("Compaction complete".to_string()),
-                    });
-
-                    if let Some(manager) = extensions {
-                        let _ = manager
-                            .dispatch_event(
-                                ExtensionEventName::SessionCompact,
-                                Some(json!({
-                                    "tokensBefore": result.tokens_before,
-                                    "firstKeptEntryId": result.first_kept_entry_id,
-                                })),
-                            )
-                            .await;
-                    }
-                });
+                let custom_instructions = args.trim();
+                let custom_instructions =
+                    (!custom_instructions.is_empty()).then(|| custom_instructions.to_string());
+                self.start_compaction(custom_instructions);
                 None
             }
             SlashCommand::Reload => {
@@ -8447,6 +13294,141 @@ mod tests {
         assert_eq!(normalized, "a\r\nb\r\nc\r\nd\r\n");
     }
 
+    #[test]
+    fn streaming_buffer_matches_joined_string() {
+        let deltas = ["Hel", "lo\nwor", "ld\r\n", "\n", "tail"];
+        let mut buffer = StreamingBuffer::new();
+        let mut joined = String::new();
+        for delta in deltas {
+            buffer.push_str(delta);
+            joined.push_str(delta);
+            assert_eq!(buffer.len(), joined.len());
+            assert_eq!(
+                buffer.lines().collect::<Vec<_>>(),
+                joined.lines().collect::<Vec<_>>()
+            );
+            assert_eq!(buffer.line_count(), joined.lines().count());
+            let mut display = String::new();
+            buffer.write_display(&mut display);
+            let expected: String = joined.lines().map(|line| format!("  {line}\n")).collect();
+            assert_eq!(display, expected);
+        }
+
+        assert_eq!(buffer.take(), joined);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.line_count(), 0);
+    }
+
+    #[test]
+    fn git_status_line_parses_porcelain_branch_and_markers() {
+        let clean = GitStatusLine::parse_porcelain("## main...origin/main [ahead 1]\n");
+        assert_eq!(clean.branch.as_deref(), Some("main"));
+        assert!(!clean.dirty && !clean.staged);
+        assert_eq!(clean.label(), "main");
+
+        let mixed = GitStatusLine::parse_porcelain("## feature/x\nM  staged.rs\n M edited.rs\n");
+        assert!(mixed.dirty && mixed.staged);
+        assert_eq!(mixed.label(), "feature/x*+");
+
+        let untracked = GitStatusLine::parse_porcelain("## No commits yet on main\n?? new.txt\n");
+        assert_eq!(untracked.label(), "main*");
+
+        let detached = GitStatusLine::parse_porcelain("## HEAD (no branch)\n");
+        assert_eq!(detached.label(), "(detached)");
+    }
+
+    #[test]
+    fn stream_rate_reports_recent_tokens_and_elapsed_time() {
+        let start = std::time::Instant::now();
+        let secs = std::time::Duration::from_secs;
+        let mut rate = StreamRate::new(start);
+        rate.record(start, &"x".repeat(40));
+        assert_eq!(rate.label(start), None, "nothing shown in the first second");
+
+        // 10 tokens over the first 2 seconds.
+        assert_eq!(rate.label(start + secs(2)).as_deref(), Some("5 tok/s · 2s"));
+
+        // 60 more tokens in the last window of 3 seconds.
+        rate.record(start + secs(9), &"x".repeat(240));
+        assert_eq!(
+            rate.label(start + secs(10)).as_deref(),
+            Some("20 tok/s · 10s")
+        );
+
+        // A stall drains the window to zero while elapsed keeps counting.
+        assert_eq!(
+            rate.label(start + secs(75)).as_deref(),
+            Some("0 tok/s · 1m 15s")
+        );
+    }
+
+    #[test]
+    fn checkpoint_age_is_compact() {
+        assert_eq!(format_age(5), "just now");
+        assert_eq!(format_age(125), "2m ago");
+        assert_eq!(format_age(7200), "2h ago");
+        assert_eq!(format_age(3 * 86_400 + 10), "3d ago");
+    }
+
+    #[test]
+    fn format_tool_output_appends_edit_diff_from_details() {
+        let content = vec![ContentBlock::Text(TextContent::new(
+            "Successfully replaced text in src/lib.rs.",
+        ))];
+        let details = json!({ "diff": "  1 fn main() {\n-2     old();\n+2     new();\n  3 }" });
+        let output = format_tool_output(&content, Some(&details), true).expect("output");
+        assert!(output.starts_with("Successfully replaced text"));
+        assert!(output.contains("+2     new();"));
+        assert!(looks_like_diff(&output));
+
+        // Details without a diff keep the old behaviour (JSON only when there is no text).
+        let output = format_tool_output(&content, Some(&json!({ "lines": 3 })), true).unwrap();
+        assert_eq!(output, "Successfully replaced text in src/lib.rs.");
+    }
+
+    #[test]
+    fn diff_line_classification_covers_unified_and_numbered_formats() {
+        let unified = "--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n ctx\n-old\n+new";
+        assert!(looks_like_diff(unified));
+        let kinds = unified.lines().map(classify_diff_line).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                DiffLineKind::Hunk,
+                DiffLineKind::Hunk,
+                DiffLineKind::Hunk,
+                DiffLineKind::Context,
+                DiffLineKind::Removed,
+                DiffLineKind::Added,
+            ]
+        );
+
+        assert!(looks_like_diff("+12 let x = 1;"));
+        assert!(!looks_like_diff("total 3\n-rw-r--r-- 1 user file"));
+        assert!(!looks_like_diff("plain output\nno changes"));
+    }
+
+    #[test]
+    fn hunk_review_summary_names_rejected_hunks() {
+        let hunk = |header: &str| DiffHunk {
+            header: header.to_string(),
+            text: String::new(),
+        };
+        let hunks = [
+            hunk("@@ -1,5 +1,5 @@"),
+            hunk("@@ -15,6 +15,6 @@"),
+            hunk("@@ -40,2 +40,3 @@"),
+        ];
+        assert_eq!(
+            hunk_review_summary("a.rs", &hunks, &[true, true, true]),
+            None
+        );
+        let summary = hunk_review_summary("a.rs", &hunks, &[true, false, true]).expect("summary");
+        assert!(summary.contains("kept hunks 1, 3 and rejected hunk 2 (@@ -15,6 +15,6 @@)"));
+        let summary = hunk_review_summary("a.rs", &hunks, &[false, false, false]).expect("summary");
+        assert!(summary.contains("kept no hunks and rejected hunks 1, 2, 3"));
+    }
+
     #[test]
     fn parse_bash_command_distinguishes_exclusion() {
         let (command, exclude) = parse_bash_command("! ls -la").expect("bang command");
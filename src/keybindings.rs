@@ -238,6 +238,7 @@ pub enum AppAction {
     Exit,
     Suspend,
     ExternalEditor,
+    PauseAgent,
 
     // Session
     NewSession,
@@ -253,10 +254,12 @@ pub enum AppAction {
     // Display
     ExpandTools,
     ToggleThinking,
+    ThinkingPanel,
 
     // Message Queue
     FollowUp,
     Dequeue,
+    Steer,
 
     // Selection (Lists, Pickers)
     SelectUp,
@@ -322,6 +325,7 @@ impl AppAction {
             Self::Exit => "Exit (when editor empty)",
             Self::Suspend => "Suspend to background",
             Self::ExternalEditor => "Open in external editor",
+            Self::PauseAgent => "Pause the agent before its next tool calls",
 
             // Session
             Self::NewSession => "Start a new session",
@@ -337,10 +341,12 @@ impl AppAction {
             // Display
             Self::ExpandTools => "Collapse/expand tool output",
             Self::ToggleThinking => "Collapse/expand thinking blocks",
+            Self::ThinkingPanel => "Show/hide the thinking side panel",
 
             // Message Queue
             Self::FollowUp => "Queue follow-up message",
             Self::Dequeue => "Restore queued messages to editor",
+            Self::Steer => "Steer the running agent",
 
             // Selection
             Self::SelectUp => "Move selection up",
@@ -390,9 +396,12 @@ impl AppAction {
 
             Self::Copy | Self::PasteImage => ActionCategory::Clipboard,
 
-            Self::Interrupt | Self::Clear | Self::Exit | Self::Suspend | Self::ExternalEditor => {
-                ActionCategory::Application
-            }
+            Self::Interrupt
+            | Self::Clear
+            | Self::Exit
+            | Self::Suspend
+            | Self::ExternalEditor
+            | Self::PauseAgent => ActionCategory::Application,
 
             Self::NewSession | Self::Tree | Self::Fork => ActionCategory::Session,
 
@@ -401,9 +410,11 @@ impl AppAction {
             | Self::CycleModelBackward
             | Self::CycleThinkingLevel => ActionCategory::ModelsThinking,
 
-            Self::ExpandTools | Self::ToggleThinking => ActionCategory::Display,
+            Self::ExpandTools | Self::ToggleThinking | Self::ThinkingPanel => {
+                ActionCategory::Display
+            }
 
-            Self::FollowUp | Self::Dequeue => ActionCategory::MessageQueue,
+            Self::FollowUp | Self::Dequeue | Self::Steer => ActionCategory::MessageQueue,
 
             Self::SelectUp
             | Self::SelectDown
@@ -472,6 +483,7 @@ impl AppAction {
             Self::Exit,
             Self::Suspend,
             Self::ExternalEditor,
+            Self::PauseAgent,
             // Session
             Self::NewSession,
             Self::Tree,
@@ -484,9 +496,11 @@ impl AppAction {
             // Display
             Self::ExpandTools,
             Self::ToggleThinking,
+            Self::ThinkingPanel,
             // Message Queue
             Self::FollowUp,
             Self::Dequeue,
+            Self::Steer,
             // Selection
             Self::SelectUp,
             Self::SelectDown,
@@ -1148,8 +1162,26 @@ impl KeyBindings {
             }
         };
 
-        // Start with defaults
-        let mut bindings = Self::default_bindings();
+        let mut bindings = Self::new();
+        warnings.extend(bindings.apply_overrides(raw, path));
+        KeyBindingsLoadResult {
+            bindings,
+            path: path.to_path_buf(),
+            warnings,
+        }
+    }
+
+    /// Apply user overrides (action ID to key string or array of key strings) on top of the
+    /// current bindings, as found in `keybindings.json` or the `keybindings` section of
+    /// `settings.json`.
+    ///
+    /// Invalid entries are skipped and reported as warnings attributed to `path`.
+    pub fn apply_overrides(
+        &mut self,
+        raw: HashMap<String, serde_json::Value>,
+        path: &Path,
+    ) -> Vec<KeyBindingsWarning> {
+        let mut warnings = Vec::new();
 
         // Process each entry
         for (action_str, value) in raw {
@@ -1212,16 +1244,12 @@ impl KeyBindings {
 
             // Only override if we got at least one valid key
             if !parsed_keys.is_empty() {
-                bindings.insert(action, parsed_keys);
+                self.bindings.insert(action, parsed_keys);
             }
         }
 
-        let reverse = Self::build_reverse_map(&bindings);
-        KeyBindingsLoadResult {
-            bindings: Self { bindings, reverse },
-            path: path.to_path_buf(),
-            warnings,
-        }
+        self.reverse = Self::build_reverse_map(&self.bindings);
+        warnings
     }
 
     /// Look up the action for a key binding.
@@ -1372,6 +1400,7 @@ impl KeyBindings {
         m.insert(AppAction::Exit, vec![KeyBinding::ctrl("d")]);
         m.insert(AppAction::Suspend, vec![KeyBinding::ctrl("z")]);
         m.insert(AppAction::ExternalEditor, vec![KeyBinding::ctrl("g")]);
+        m.insert(AppAction::PauseAgent, vec![KeyBinding::alt("p")]);
 
         // Session (no default bindings)
         m.insert(AppAction::NewSession, vec![]);
@@ -1393,10 +1422,12 @@ impl KeyBindings {
         // Display
         m.insert(AppAction::ExpandTools, vec![KeyBinding::ctrl("o")]);
         m.insert(AppAction::ToggleThinking, vec![KeyBinding::ctrl("t")]);
+        m.insert(AppAction::ThinkingPanel, vec![KeyBinding::alt("t")]);
 
         // Message Queue
         m.insert(AppAction::FollowUp, vec![KeyBinding::alt("enter")]);
         m.insert(AppAction::Dequeue, vec![KeyBinding::alt("up")]);
+        m.insert(AppAction::Steer, vec![KeyBinding::ctrl("s")]);
 
         // Selection (Lists, Pickers)
         m.insert(AppAction::SelectUp, vec![KeyBinding::plain("up")]);
@@ -2089,6 +2120,38 @@ mod tests {
         assert_eq!(up_bindings.len(), 2); // not 3
     }
 
+    #[test]
+    fn test_apply_overrides_layers_settings_on_loaded_bindings() {
+        let mut bindings = KeyBindings::new();
+        bindings.apply_overrides(
+            HashMap::from([("pageUp".to_string(), serde_json::json!("ctrl+b"))]),
+            Path::new("keybindings.json"),
+        );
+        let raw: HashMap<String, serde_json::Value> =
+            serde_json::from_str(r#"{ "submit": ["ctrl+j"], "pageDown": 42, "bogus": "ctrl+x" }"#)
+                .unwrap();
+        let warnings = bindings.apply_overrides(raw, Path::new("settings.json"));
+
+        assert_eq!(warnings.len(), 2);
+        assert!(
+            warnings
+                .iter()
+                .all(|warning| warning.to_string().contains("settings.json"))
+        );
+        assert_eq!(
+            bindings.get_bindings(AppAction::Submit),
+            &[KeyBinding::ctrl("j")]
+        );
+        assert_eq!(
+            bindings.get_bindings(AppAction::PageUp),
+            &[KeyBinding::ctrl("b")]
+        );
+        assert_eq!(
+            bindings.get_bindings(AppAction::PageDown),
+            KeyBindings::new().get_bindings(AppAction::PageDown)
+        );
+    }
+
     #[test]
     fn test_load_warns_on_invalid_json() {
         let temp = tempfile::tempdir().unwrap();
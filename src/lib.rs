@@ -35,38 +35,58 @@ pub mod app;
 pub mod auth;
 pub mod autocomplete;
 pub mod cli;
+pub mod code_lang;
 pub mod compaction;
 pub mod config;
 pub mod connectors;
 pub mod error;
 pub mod error_hints;
+pub mod eval;
 pub mod extension_dispatcher;
 pub mod extension_events;
 pub mod extension_tools;
 pub mod extensions;
 pub mod extensions_js;
+pub mod file_content;
+pub mod hooks;
 pub mod http;
 pub mod interactive;
 pub mod keybindings;
 pub mod model;
 pub mod model_selector;
 pub mod models;
+pub mod notifications;
 pub mod package_manager;
+pub mod permissions;
 pub mod provider;
+pub mod provider_health;
 pub mod providers;
+pub mod redaction;
+pub mod replay;
+pub mod request_scheduler;
 pub mod resources;
 pub mod rpc;
 pub mod scheduler;
 pub mod session;
+pub mod session_bundle;
+pub mod session_diff;
+pub mod session_import;
 pub mod session_index;
 pub mod session_picker;
+pub mod session_playback;
 #[cfg(feature = "sqlite-sessions")]
 pub mod session_sqlite;
+pub mod session_stats;
+pub mod session_store;
+pub mod session_summary;
+pub mod session_template;
 pub mod sse;
+pub mod telemetry;
 pub mod theme;
 pub mod tools;
 pub mod tui;
 pub mod vcr;
+pub mod worktree_snapshot;
 
 pub use error::{Error, Result as PiResult};
 pub use extension_dispatcher::ExtensionDispatcher;
@@ -9,6 +9,7 @@
 // Allow dead code and unused async during scaffolding phase - remove once implementation is complete
 #![allow(dead_code, clippy::unused_async)]
 
+use std::fmt::Write as _;
 use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -19,43 +20,70 @@ use asupersync::runtime::reactor::create_reactor;
 use asupersync::runtime::{RuntimeBuilder, RuntimeHandle};
 use asupersync::sync::Mutex;
 use clap::Parser;
-use pi::agent::{AbortHandle, Agent, AgentConfig, AgentEvent, AgentSession};
+use pi::agent::{
+    AbortHandle, Agent, AgentConfig, AgentEvent, AgentSession, DryRunCall, Plan, PlanApprover,
+};
 use pi::app::StartupError;
 use pi::auth::{AuthCredential, AuthStorage};
 use pi::cli;
 use pi::config::Config;
+use pi::error::ExitStatus;
 use pi::extensions::{ExtensionEventName, extension_event_from_agent};
-use pi::model::{AssistantMessage, ContentBlock, StopReason};
+use pi::model::{AbortReason, AssistantMessage, ContentBlock, Message, StopReason};
 use pi::models::{ModelEntry, ModelRegistry, default_models_path};
+use pi::notifications::{Notification, Notifier};
 use pi::package_manager::{PackageEntry, PackageManager, PackageScope};
-use pi::provider::InputType;
+use pi::provider::{InputType, RequestInjection};
+use pi::provider_health;
 use pi::providers;
+use pi::redaction::Redactor;
+use pi::request_scheduler::RequestScheduler;
 use pi::resources::{ResourceCliOptions, ResourceLoader};
-use pi::session::Session;
-use pi::session_index::SessionIndex;
+use pi::session::{Session, encode_cwd, is_compressed_session_path};
+use pi::session_bundle::{ConfigFingerprint, SessionBundle};
+use pi::session_index::{PruneOptions, SessionIndex, VacuumOptions};
+use pi::session_playback::PlaybackOptions;
+use pi::session_store::{self, WebDavSessionStore};
+use pi::session_summary::PrSummary;
+use pi::session_template::{self, SessionTemplate};
 use pi::tools::ToolRegistry;
 use pi::tui::PiConsole;
+use pi::vcr::{VcrMode, VcrRecorder};
 use serde_json::json;
-use tracing_subscriber::EnvFilter;
+
+/// How long pi waits at exit for session uploads that are still in flight.
+const SESSION_UPLOAD_GRACE: Duration = Duration::from_secs(30);
 
 fn main() {
     if let Err(err) = main_impl() {
         print_error_with_hints(&err);
-        std::process::exit(1);
+        std::process::exit(exit_status_for(&err).code());
     }
 }
 
-fn main_impl() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_target(false)
-        .with_writer(io::stderr)
-        .init();
+/// Map a top-level failure onto the documented exit status contract.
+fn exit_status_for(err: &anyhow::Error) -> ExitStatus {
+    for cause in err.chain() {
+        if let Some(pi_error) = cause.downcast_ref::<pi::error::Error>() {
+            return ExitStatus::from_error(pi_error);
+        }
+        if let Some(startup) = cause.downcast_ref::<StartupError>() {
+            return match startup {
+                StartupError::MissingApiKey { .. } => ExitStatus::ProviderAuth,
+                StartupError::NoModelsAvailable { .. } => ExitStatus::Validation,
+            };
+        }
+    }
+    ExitStatus::Failure
+}
 
+fn main_impl() -> Result<()> {
     // Parse CLI arguments
     let cli = cli::Cli::parse();
 
+    // Initialize logging and trace export
+    pi::telemetry::init(cli.otlp_endpoint.as_deref());
+
     // Run the application
     let reactor = create_reactor()?;
     let runtime = RuntimeBuilder::multi_thread()
@@ -66,7 +94,15 @@ fn main_impl() -> Result<()> {
     let handle = runtime.handle();
     let runtime_handle = handle.clone();
     let join = handle.spawn(Box::pin(run(cli, runtime_handle)));
-    runtime.block_on(join)
+    let result = runtime.block_on(join);
+    // Background bash jobs (`yieldAfter`) must not outlive pi.
+    pi::tools::kill_bash_jobs();
+    if let Some(sync) = session_store::remote() {
+        if !sync.wait_idle(SESSION_UPLOAD_GRACE) {
+            eprintln!("Warning: Session upload still running; the shared copy may be out of date");
+        }
+    }
+    result
 }
 
 fn print_error_with_hints(err: &anyhow::Error) {
@@ -78,6 +114,7 @@ fn print_error_with_hints(err: &anyhow::Error) {
     }
 
     eprintln!("{err}");
+    eprintln!("\nError code: {}", pi::error::ErrorCode::INTERNAL.code);
 }
 
 #[allow(clippy::too_many_lines)]
@@ -97,6 +134,8 @@ async fn run(mut cli: cli::Cli, runtime_handle: RuntimeHandle) -> Result<()> {
         return Ok(());
     }
 
+    // `pi ask` is print mode with a read-only tool set, so it continues below.
+    cli.apply_ask_command();
     if let Some(command) = cli.command.take() {
         handle_subcommand(command, &cwd).await?;
         return Ok(());
@@ -107,6 +146,24 @@ async fn run(mut cli: cli::Cli, runtime_handle: RuntimeHandle) -> Result<()> {
         // Theme already validated above
         config.theme = Some(theme_spec.to_string());
     }
+    if let Some(settings) = &config.redaction {
+        if let Some(redactor) = Redactor::from_settings(settings)? {
+            Redactor::install_global(redactor);
+        }
+    }
+    if let Some(settings) = &config.remote_sessions {
+        if let Some(store) = WebDavSessionStore::from_settings(settings)? {
+            session_store::install_remote(Arc::new(store));
+        }
+    }
+    let template = match cli.template.as_deref() {
+        Some(name) => {
+            let template = SessionTemplate::load(&session_template::templates_dir(), name)?;
+            template.apply_to_cli(&mut cli);
+            Some(template)
+        }
+        None => None,
+    };
     spawn_session_index_maintenance();
     let package_manager = PackageManager::new(cwd.clone());
     let resource_cli = ResourceCliOptions {
@@ -193,6 +250,9 @@ async fn run(mut cli: cli::Cli, runtime_handle: RuntimeHandle) -> Result<()> {
     }
 
     let mut session = Box::pin(Session::new(&cli, &config)).await?;
+    if let Some(template) = &template {
+        template.seed(&mut session);
+    }
 
     let (selection, resolved_key) = loop {
         scoped_models = if scoped_patterns.is_empty() {
@@ -253,7 +313,8 @@ async fn run(mut cli: cli::Cli, runtime_handle: RuntimeHandle) -> Result<()> {
         eprintln!("Warning: {message}");
     }
 
-    let enabled_tools = cli.enabled_tools();
+    let profile_tools = config.tool_profile_tools();
+    let enabled_tools = cli.enabled_tools_with_profile(profile_tools.as_deref());
     let skills_prompt = if enabled_tools.contains(&"read") {
         resources.format_skills_for_prompt()
     } else {
@@ -278,8 +339,24 @@ async fn run(mut cli: cli::Cli, runtime_handle: RuntimeHandle) -> Result<()> {
         system_prompt: Some(system_prompt),
         max_tool_iterations: 50,
         stream_options,
+        tool_retry_diagnostics: config
+            .tool_retry_enabled()
+            .then(|| config.tool_retry_max_retries()),
+        request_injection: config
+            .request_injection
+            .clone()
+            .map(|settings| RequestInjection::new(settings, &cwd)),
+        tool_output_limits: config.tool_output_limits(),
+        tool_timeouts: config.tool_timeouts(),
+        pricing: Some(selection.model_entry.model.cost.clone()),
+        dry_run: cli.dry_run,
+        loop_detection: config.loop_detection(),
     };
 
+    if let Some(settings) = &config.request_scheduler {
+        RequestScheduler::install_global(RequestScheduler::from_settings(settings));
+    }
+
     let tools = ToolRegistry::new(&enabled_tools, &cwd, Some(&config));
     let session_arc = Arc::new(Mutex::new(session));
     let mut agent_session = AgentSession::new(
@@ -287,6 +364,36 @@ async fn run(mut cli: cli::Cli, runtime_handle: RuntimeHandle) -> Result<()> {
         session_arc,
         !cli.no_session,
     );
+    agent_session
+        .agent
+        .set_permission_policy(config.permission_policy());
+    agent_session
+        .agent
+        .set_auto_compaction(config.auto_compaction(selection.model_entry.model.context_window));
+    agent_session
+        .agent
+        .set_context_strategy(config.context_strategy());
+    agent_session
+        .agent
+        .set_retry_policy(config.provider_retry_policy());
+    agent_session.agent.set_hosted_tools(config.hosted_tools());
+    agent_session
+        .agent
+        .set_tool_exposure(config.tool_exposure());
+    agent_session.agent.set_hooks(config.hooks(&cwd));
+    if let Some(title_model) = config.auto_title_model() {
+        let model = title_model.and_then(|spec| {
+            let model = auto_title_model(&spec, &model_registry, &auth);
+            if model.is_none() {
+                eprintln!(
+                    "Warning: auto_title model {spec} is not available; using {}",
+                    selection.model_entry.model.id
+                );
+            }
+            model
+        });
+        agent_session.agent.enable_auto_title(model);
+    }
 
     let history = {
         let cx = pi::agent_cx::AgentCx::for_request();
@@ -358,9 +465,12 @@ async fn run(mut cli: cli::Cli, runtime_handle: RuntimeHandle) -> Result<()> {
     run_print_mode(
         &mut agent_session,
         &mode,
+        cli.plan,
+        cli.dry_run,
         initial,
         messages,
         &resources,
+        &config.notifier(),
         runtime_handle.clone(),
     )
     .await
@@ -381,16 +491,623 @@ async fn handle_subcommand(command: cli::Commands, cwd: &Path) -> Result<()> {
         cli::Commands::List => {
             handle_package_list(&manager).await?;
         }
-        cli::Commands::Config => {
-            handle_config(cwd)?;
+        cli::Commands::Config { explain } => {
+            if explain {
+                handle_config_explain(cwd)?;
+            } else {
+                handle_config(cwd)?;
+            }
+        }
+        cli::Commands::Eval {
+            suite,
+            models,
+            cassettes,
+            json,
+        } => {
+            handle_eval(&suite, models, cassettes.as_deref(), json, cwd).await?;
+        }
+        cli::Commands::Ask { .. } => {
+            unreachable!("`pi ask` is rewritten to print mode before subcommand dispatch")
+        }
+        cli::Commands::Replay {
+            session,
+            realtime,
+            speed,
+            max_pause,
+        } => {
+            handle_replay(&session, realtime, speed, max_pause).await?;
+        }
+        cli::Commands::Doctor { json } => {
+            handle_doctor(json).await?;
+        }
+        cli::Commands::Errors {
+            command: cli::ErrorsCommand::List { json },
+        } => {
+            handle_errors_list(json)?;
+        }
+        cli::Commands::Index {
+            command:
+                cli::IndexCommand::Vacuum {
+                    batch_size,
+                    pause_ms,
+                },
+        } => {
+            handle_index_vacuum(batch_size, pause_ms)?;
+        }
+        cli::Commands::Sessions {
+            command: cli::SessionsCommand::Summarize { session, format },
+        } => {
+            handle_sessions_summarize(&session, format).await?;
+        }
+        cli::Commands::Sessions {
+            command:
+                cli::SessionsCommand::Import {
+                    from_markdown,
+                    from_claude_code,
+                    name,
+                },
+        } => {
+            handle_sessions_import(from_markdown.as_deref(), from_claude_code.as_deref(), name)
+                .await?;
+        }
+        cli::Commands::Sessions {
+            command:
+                cli::SessionsCommand::Diff {
+                    session,
+                    left,
+                    right,
+                    json,
+                },
+        } => {
+            handle_sessions_diff(&session, &left, &right, json).await?;
+        }
+        cli::Commands::Sessions {
+            command:
+                cli::SessionsCommand::Export {
+                    session,
+                    to_claude_code,
+                },
+        } => {
+            handle_sessions_export(&session, &to_claude_code).await?;
+        }
+        cli::Commands::Sessions {
+            command:
+                cli::SessionsCommand::Bundle {
+                    session,
+                    output,
+                    pins,
+                },
+        } => {
+            handle_sessions_bundle(&session, output.as_deref(), &pins).await?;
+        }
+        cli::Commands::Sessions {
+            command: cli::SessionsCommand::Unbundle { bundle, overwrite },
+        } => {
+            handle_sessions_unbundle(&bundle, overwrite).await?;
+        }
+        cli::Commands::Sessions {
+            command: cli::SessionsCommand::Repair { session },
+        } => {
+            handle_sessions_repair(&session)?;
+        }
+        cli::Commands::Sessions {
+            command:
+                cli::SessionsCommand::Prune {
+                    older_than_days,
+                    min_messages,
+                    max_total_mb,
+                    archive,
+                    dry_run,
+                },
+        } => {
+            handle_sessions_prune(&PruneOptions {
+                older_than: older_than_days.map(|days| Duration::from_secs(days * 86_400)),
+                min_messages,
+                max_total_bytes: max_total_mb.map(|mb| mb.saturating_mul(1024 * 1024)),
+                archive_dir: archive.map(PathBuf::from),
+                dry_run,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_eval(
+    suite_path: &str,
+    models: Vec<String>,
+    cassettes: Option<&str>,
+    json: bool,
+    cwd: &Path,
+) -> Result<()> {
+    let suite = pi::eval::EvalSuite::load(Path::new(suite_path))?;
+    let model_specs = if models.is_empty() {
+        suite.models.clone()
+    } else {
+        models
+    };
+    if model_specs.is_empty() {
+        bail!("No models to evaluate: list them under `models` in the suite or pass --model");
+    }
+
+    let config = Config::load()?;
+    let auth = AuthStorage::load_async(Config::auth_path()).await?;
+    let global_dir = Config::global_dir();
+    let model_registry = ModelRegistry::load(&auth, Some(default_models_path(&global_dir)));
+    let mut targets = Vec::new();
+    for spec in &model_specs {
+        let Some((provider, id)) = spec.split_once('/') else {
+            bail!("Invalid model {spec}: expected provider/model");
+        };
+        let Some(entry) = model_registry.find(provider, id) else {
+            bail!("Unknown model {spec}");
+        };
+        let api_key = auth
+            .resolve_api_key(provider, None)
+            .or_else(|| entry.api_key.clone());
+        // Replayed runs never reach the provider, so they need no key.
+        if api_key.is_none() && cassettes.is_none() {
+            return Err(StartupError::MissingApiKey {
+                provider: provider.to_string(),
+            }
+            .into());
+        }
+        targets.push((entry, api_key));
+    }
+
+    let package_manager = PackageManager::new(cwd.to_path_buf());
+    let resource_cli = ResourceCliOptions {
+        no_skills: false,
+        no_prompt_templates: true,
+        no_extensions: true,
+        no_themes: true,
+        skill_paths: Vec::new(),
+        prompt_paths: Vec::new(),
+        extension_paths: Vec::new(),
+        theme_paths: Vec::new(),
+    };
+    let resources = ResourceLoader::load(&package_manager, cwd, &config, &resource_cli)
+        .await
+        .unwrap_or_else(|_| ResourceLoader::empty(config.enable_skill_commands()));
+    let tools = suite.tools.as_ref().map_or_else(
+        || pi::eval::DEFAULT_EVAL_TOOLS.to_vec(),
+        |tools| tools.iter().map(String::as_str).collect(),
+    );
+    let skills_prompt = resources.format_skills_for_prompt();
+    let system_prompt = pi::app::build_system_prompt(
+        &cli::Cli::parse_from(["pi"]),
+        cwd,
+        &tools,
+        (tools.contains(&"read") && !skills_prompt.is_empty()).then_some(skills_prompt.as_str()),
+        &global_dir,
+        &Config::package_dir(),
+    );
+
+    let mut report = pi::eval::EvalReport {
+        suite: suite.display_name().to_string(),
+        results: Vec::new(),
+    };
+    for case in &suite.cases {
+        for (entry, api_key) in &targets {
+            let client = cassettes.map_or_else(pi::http::client::Client::new, |dir| {
+                let name = format!(
+                    "{}-{}-{}-{}",
+                    suite.display_name(),
+                    case.name,
+                    entry.model.provider,
+                    entry.model.id
+                );
+                let probe = VcrRecorder::new_with(&name, VcrMode::Record, dir);
+                let mode = if probe.cassette_path().exists() {
+                    VcrMode::Playback
+                } else {
+                    VcrMode::Record
+                };
+                pi::http::client::Client::new().with_vcr(VcrRecorder::new_with(&name, mode, dir))
+            });
+            let target = pi::eval::EvalTarget {
+                entry: entry.clone(),
+                provider: providers::create_provider_with_client(entry, client)
+                    .map_err(anyhow::Error::new)?,
+                api_key: api_key.clone(),
+            };
+            let result = pi::eval::run_case(&suite, case, &target, &system_prompt, &config).await;
+            if !json {
+                eprintln!(
+                    "{} {} on {}",
+                    if result.passed { "PASS" } else { "FAIL" },
+                    result.case,
+                    result.model
+                );
+            }
+            report.results.push(result);
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("\n{}", report.render());
+    }
+    let failed = report.failed();
+    if failed > 0 {
+        return Err(pi::error::Error::validation(format!(
+            "{failed} of {} eval runs failed",
+            report.results.len()
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+async fn handle_doctor(json: bool) -> Result<()> {
+    let auth = AuthStorage::load_async(Config::auth_path()).await?;
+    let global_dir = Config::global_dir();
+    let model_registry = ModelRegistry::load(&auth, Some(default_models_path(&global_dir)));
+    let results = provider_health::check_models(&model_registry.get_available()).await;
+    let sessions_dir = Config::sessions_dir().join(encode_cwd(&std::env::current_dir()?));
+    let corrupted = pi::session::corrupted_sessions_in(&sessions_dir);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        for (path, skipped) in &corrupted {
+            eprintln!(
+                "Warning: {} has {} corrupted entries",
+                path.display(),
+                skipped.len()
+            );
+        }
+        return Ok(());
+    }
+    if results.is_empty() {
+        println!("No providers configured. Set API keys in environment variables.");
+    }
+
+    let width = results
+        .iter()
+        .map(|health| health.provider.len())
+        .max()
+        .unwrap_or(0);
+    for health in &results {
+        let mut line = format!(
+            "{:<width$}  {:<11}  {:>5} ms  {}",
+            health.provider,
+            health.status.as_str(),
+            health.latency_ms,
+            health.url
+        );
+        if let Some(detail) = &health.detail {
+            let _ = write!(line, "  ({detail})");
+        }
+        println!("{line}");
+    }
+    for (path, skipped) in &corrupted {
+        let lines = skipped
+            .iter()
+            .map(|entry| entry.line_number.to_string())
+            .collect::<Vec<_>>();
+        println!(
+            "Session {} has corrupted entries (lines {}); run `pi sessions repair {}`",
+            path.display(),
+            lines.join(", "),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn handle_errors_list(json: bool) -> Result<()> {
+    let codes = pi::error::ErrorCode::ALL;
+    if json {
+        let entries = codes
+            .iter()
+            .map(|entry| json!({ "code": entry.code, "summary": entry.summary }))
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    let width = codes
+        .iter()
+        .map(|entry| entry.code.len())
+        .max()
+        .unwrap_or(0);
+    for entry in codes {
+        println!("{:<width$}  {}", entry.code, entry.summary);
+    }
+    Ok(())
+}
+
+fn handle_index_vacuum(batch_size: usize, pause_ms: u64) -> Result<()> {
+    let index = SessionIndex::new();
+    let report = index.vacuum(&VacuumOptions {
+        batch_size,
+        pause: Duration::from_millis(pause_ms),
+    })?;
+    if report.resumed {
+        println!("Resumed interrupted vacuum");
+    }
+    println!(
+        "Checked {} sessions, pruned {} stale entries",
+        report.scanned, report.pruned
+    );
+    if report.compacted {
+        println!(
+            "Compacted index: {} -> {} bytes",
+            report.bytes_before, report.bytes_after
+        );
+    } else {
+        println!("Index analyzed; no compaction needed");
+    }
+    Ok(())
+}
+
+fn handle_sessions_prune(options: &PruneOptions) -> Result<()> {
+    let report = SessionIndex::new().prune(options)?;
+    for session in &report.sessions {
+        println!("{}", session.path);
+    }
+    for path in &report.locked {
+        println!("{path} (in use, skipped)");
+    }
+    println!("{}", report.summary());
+    Ok(())
+}
+
+async fn handle_sessions_summarize(spec: &str, format: cli::SummaryFormat) -> Result<()> {
+    let path = resolve_session_spec(spec)?;
+    let session = Session::open(&path.to_string_lossy()).await?;
+    let summary = PrSummary::from_session(&session);
+    match format {
+        cli::SummaryFormat::Pr => print!("{}", summary.to_markdown()),
+        cli::SummaryFormat::Json => println!("{}", serde_json::to_string_pretty(&summary)?),
+    }
+    Ok(())
+}
+
+/// Provider and API key for the `auto_title` model, given as `provider/id` or a bare model id.
+fn auto_title_model(
+    spec: &str,
+    registry: &ModelRegistry,
+    auth: &AuthStorage,
+) -> Option<(Arc<dyn pi::provider::Provider>, Option<String>)> {
+    let entry = match spec.split_once('/') {
+        Some((provider, id)) => registry.find(provider, id),
+        None => registry
+            .get_available()
+            .into_iter()
+            .find(|entry| entry.model.id.eq_ignore_ascii_case(spec)),
+    }?;
+    let provider = providers::create_provider(&entry).ok()?;
+    let api_key = auth
+        .resolve_api_key(&entry.model.provider, None)
+        .or_else(|| entry.api_key.clone());
+    Some((provider, api_key))
+}
+
+async fn handle_replay(
+    spec: &str,
+    realtime: bool,
+    speed: Option<f64>,
+    max_pause: f64,
+) -> Result<()> {
+    if speed.is_some_and(|speed| !speed.is_finite() || speed <= 0.0) {
+        bail!("--speed must be a positive number");
+    }
+    if !max_pause.is_finite() || max_pause < 0.0 {
+        bail!("--max-pause must be zero or more seconds");
+    }
+    let path = resolve_session_spec(spec)?;
+    let session = Session::open(&path.to_string_lossy()).await?;
+    let options = PlaybackOptions {
+        speed: speed.or_else(|| realtime.then_some(1.0)),
+        max_pause: Duration::from_secs_f64(max_pause),
+    };
+    pi::session_playback::play(&session, &PiConsole::new(), &options).await;
+    Ok(())
+}
+
+async fn handle_sessions_diff(spec: &str, left: &str, right: &str, json: bool) -> Result<()> {
+    let path = resolve_session_spec(spec)?;
+    let session = Session::open(&path.to_string_lossy()).await?;
+    let diff = session.diff_branches(left, right)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{}", diff.to_text());
+    }
+    Ok(())
+}
+
+async fn handle_sessions_import(
+    markdown_path: Option<&str>,
+    claude_code_path: Option<&str>,
+    name: Option<String>,
+) -> Result<()> {
+    let source = markdown_path.or(claude_code_path).unwrap_or_default();
+    let path = Path::new(source);
+    let text = std::fs::read_to_string(path).map_err(|err| {
+        pi::error::Error::validation(format!("Failed to read {}: {err}", path.display()))
+    })?;
+    let file_stem = || {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+    };
+    let mut session = if markdown_path.is_some() {
+        pi::session_import::session_from_markdown(&text, name.or_else(file_stem), None)?
+    } else {
+        let mut session = Session::import_claude_code(&text, None)?;
+        // The transcript's own summary beats the file name, which is just its session id.
+        let name = name.or_else(|| {
+            if session.get_name().is_some() {
+                None
+            } else {
+                file_stem()
+            }
+        });
+        if let Some(name) = name {
+            session.set_name(&name);
         }
+        session
+    };
+    session.save().await?;
+    let saved = session
+        .path
+        .as_ref()
+        .map_or_else(String::new, |path| path.display().to_string());
+    println!(
+        "Imported {} messages into {saved}",
+        session.to_messages_for_current_path().len()
+    );
+    println!("Continue it with: pi --session {saved}");
+    Ok(())
+}
+
+async fn handle_sessions_export(spec: &str, output: &str) -> Result<()> {
+    let path = resolve_session_spec(spec)?;
+    let session = Session::open(&path.to_string_lossy()).await?;
+    std::fs::write(output, session.export_claude_code())
+        .map_err(|err| pi::error::Error::validation(format!("Failed to write {output}: {err}")))?;
+    println!(
+        "Exported {} messages to {output}",
+        session.to_messages_for_current_path().len()
+    );
+    Ok(())
+}
+
+async fn handle_sessions_bundle(spec: &str, output: Option<&str>, pins: &[String]) -> Result<()> {
+    let path = resolve_session_spec(spec)?;
+    let session = Session::open(&path.to_string_lossy()).await?;
+    let config = Config::load()?;
+    let pins = pins.iter().map(PathBuf::from).collect::<Vec<_>>();
+    let created = SessionBundle::create(&session, &pins, &config)?;
+    for skipped in &created.skipped {
+        eprintln!("Warning: not bundled: {skipped}");
+    }
+
+    let output = output.map_or_else(
+        || {
+            PathBuf::from(format!(
+                "{}.{}",
+                &session.header.id[..8.min(session.header.id.len())],
+                pi::session_bundle::BUNDLE_EXTENSION
+            ))
+        },
+        PathBuf::from,
+    );
+    std::fs::write(&output, created.bundle.encode()?)?;
+    println!(
+        "Bundled {} ({} files, {}) into {}",
+        path.display(),
+        created.bundle.files.len(),
+        created.bundle.fingerprint.describe_model(),
+        output.display()
+    );
+    println!("Restore it with: pi sessions unbundle {}", output.display());
+    Ok(())
+}
+
+fn handle_sessions_repair(spec: &str) -> Result<()> {
+    let path = resolve_session_spec(spec)?;
+    if path.extension().is_none_or(|ext| ext != "jsonl") && !is_compressed_session_path(&path) {
+        bail!(
+            "Only JSONL session files can be repaired: {}",
+            path.display()
+        );
+    }
+    let repair = Session::repair_jsonl(&path)?;
+    let Some(backup) = &repair.backup else {
+        println!("{} has no corrupted entries", path.display());
+        return Ok(());
+    };
+    for skipped in &repair.skipped_entries {
+        println!(
+            "Line {} (byte {}): {}",
+            skipped.line_number, skipped.byte_offset, skipped.error
+        );
+    }
+    println!(
+        "Recovered {} entries; dropped {} lines",
+        repair.salvaged, repair.dropped
+    );
+    println!(
+        "Repaired {}; the original is saved as {}",
+        path.display(),
+        backup.display()
+    );
+    Ok(())
+}
+
+async fn handle_sessions_unbundle(bundle_path: &str, overwrite: bool) -> Result<()> {
+    let bytes = std::fs::read(bundle_path).map_err(|err| {
+        pi::error::Error::validation(format!("Failed to read {bundle_path}: {err}"))
+    })?;
+    let bundle = SessionBundle::decode(&bytes)?;
+    let cwd = std::env::current_dir()?;
+
+    let restore = bundle.restore_files(&cwd, overwrite)?;
+    let mut session = bundle.session(&cwd, None)?;
+    session.save().await?;
+
+    let config = Config::load()?;
+    let local = ConfigFingerprint::new(&session, &config)?;
+    for difference in bundle.fingerprint.differences(&local) {
+        eprintln!("Note: {difference}");
+    }
+    for conflict in &restore.conflicts {
+        eprintln!(
+            "Warning: kept local {conflict} (differs from the bundle; --overwrite replaces it)"
+        );
     }
 
+    let saved = session
+        .path
+        .as_ref()
+        .map_or_else(String::new, |path| path.display().to_string());
+    println!(
+        "Restored session ({}) into {saved}",
+        bundle.fingerprint.describe_model()
+    );
+    println!(
+        "Files: {} written, {} already up to date, {} conflicting",
+        restore.written.len(),
+        restore.unchanged.len(),
+        restore.conflicts.len()
+    );
+    let pinned = bundle.pinned_paths().collect::<Vec<_>>();
+    if !pinned.is_empty() {
+        println!("The sender had pinned: {}", pinned.join(", "));
+        println!("Pin them again with /pin <path>.");
+    }
+    println!("Continue it with: pi --session {saved}");
     Ok(())
 }
 
+/// Resolve a session file path, or a (prefix of a) session ID via the session index.
+fn resolve_session_spec(spec: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(spec);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let matches = SessionIndex::new()
+        .list_sessions(None)?
+        .into_iter()
+        .filter(|meta| meta.id.starts_with(spec))
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [] => bail!("No session file or ID matches {spec}"),
+        [meta] => Ok(PathBuf::from(&meta.path)),
+        _ => bail!(
+            "Session ID prefix {spec} is ambiguous ({} matches); use more characters",
+            matches.len()
+        ),
+    }
+}
+
 fn spawn_session_index_maintenance() {
     const MAX_INDEX_AGE: Duration = Duration::from_secs(60 * 30);
+    const MAX_VACUUM_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 7);
     let index = SessionIndex::new();
     if !index.should_reindex(MAX_INDEX_AGE) {
         return;
@@ -398,6 +1115,10 @@ fn spawn_session_index_maintenance() {
     std::thread::spawn(move || {
         if let Err(err) = index.reindex_all() {
             eprintln!("Warning: failed to reindex session index: {err}");
+            return;
+        }
+        if let Err(err) = index.vacuum_if_due(MAX_VACUUM_AGE, &VacuumOptions::default()) {
+            eprintln!("Warning: failed to vacuum session index: {err}");
         }
     });
 }
@@ -523,6 +1244,25 @@ fn handle_config(cwd: &Path) -> Result<()> {
     Ok(())
 }
 
+fn handle_config_explain(cwd: &Path) -> Result<()> {
+    let config_path = std::env::var_os("PI_CONFIG_PATH").map(PathBuf::from);
+    let explained = Config::explain_with_roots(config_path.as_deref(), &Config::global_dir(), cwd)?;
+
+    println!("Startup defaults:");
+    for setting in &explained {
+        let value = setting.value.as_deref().unwrap_or("(unset)");
+        println!(
+            "  {:<24} {value:<28} [{}]",
+            setting.key,
+            setting.source.label()
+        );
+    }
+    println!();
+    println!("--provider, --model, --thinking, and --tools override these for a single run.");
+
+    Ok(())
+}
+
 fn print_version() {
     println!(
         "pi {} ({} {})",
@@ -845,19 +1585,33 @@ async fn run_rpc_mode(
 }
 
 #[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments)]
 async fn run_print_mode(
     session: &mut AgentSession,
     mode: &str,
+    plan: bool,
+    dry_run: bool,
     initial: Option<InitialMessage>,
     messages: Vec<String>,
     resources: &ResourceLoader,
+    notifier: &Notifier,
     runtime_handle: RuntimeHandle,
 ) -> Result<()> {
     if mode != "text" && mode != "json" {
-        bail!("Unknown mode: {mode}");
+        return Err(pi::error::Error::validation(format!("Unknown mode: {mode}")).into());
     }
     if initial.is_none() && messages.is_empty() {
-        bail!("No input provided. Use: pi -p \"your message\" or pipe input via stdin");
+        return Err(pi::error::Error::validation(
+            "No input provided. Use: pi -p \"your message\" or pipe input via stdin",
+        )
+        .into());
+    }
+    if plan
+        && initial
+            .as_ref()
+            .is_some_and(|initial| !initial.images.is_empty())
+    {
+        return Err(pi::error::Error::validation("--plan does not take attached images").into());
     }
 
     if mode == "json" {
@@ -882,6 +1636,14 @@ async fn run_print_mode(
                 if let Ok(serialized) = serde_json::to_string(&event) {
                     println!("{serialized}");
                 }
+            } else if let (
+                true,
+                AgentEvent::ToolExecutionStart {
+                    tool_name, args, ..
+                },
+            ) = (dry_run, &event)
+            {
+                eprintln!("[dry-run] {}", DryRunCall::new(tool_name, args).describe());
             }
             if let Some(manager) = &extensions {
                 if let Some((event_name, data)) = extension_event_from_agent(&event) {
@@ -925,16 +1687,25 @@ async fn run_print_mode(
                     .await;
             });
         }
-        let content = pi::app::build_initial_content(&initial);
-        last_message = Some(
+        last_message = Some(if plan {
+            session
+                .run_planned(
+                    initial.text,
+                    terminal_plan_approver(),
+                    Some(abort_signal.clone()),
+                    make_event_handler(),
+                )
+                .await?
+        } else {
+            let content = pi::app::build_initial_content(&initial);
             session
                 .run_with_content_with_abort(
                     content,
                     Some(abort_signal.clone()),
                     make_event_handler(),
                 )
-                .await?,
-        );
+                .await?
+        });
     }
 
     for message in messages {
@@ -953,16 +1724,28 @@ async fn run_print_mode(
                     .await;
             });
         }
-        last_message = Some(
+        last_message = Some(if plan {
+            session
+                .run_planned(
+                    message,
+                    terminal_plan_approver(),
+                    Some(abort_signal.clone()),
+                    make_event_handler(),
+                )
+                .await?
+        } else {
             session
                 .run_text_with_abort(message, Some(abort_signal.clone()), make_event_handler())
-                .await?,
-        );
+                .await?
+        });
     }
 
     let Some(last_message) = last_message else {
         bail!("No messages were sent");
     };
+    notifier
+        .send(&Notification::run_complete(&last_message))
+        .await;
 
     if matches!(
         last_message.stop_reason,
@@ -971,7 +1754,33 @@ async fn run_print_mode(
         let message = last_message
             .error_message
             .unwrap_or_else(|| "Request error".to_string());
-        bail!(message);
+        // Each abort reason maps onto the exit status documented in `pi --help`.
+        let reason = last_message.abort_reason.or_else(|| {
+            (last_message.stop_reason == StopReason::Aborted).then_some(AbortReason::User)
+        });
+        let error = match reason {
+            Some(AbortReason::User | AbortReason::Timeout) => pi::error::Error::Aborted,
+            Some(AbortReason::BudgetExceeded) => pi::error::Error::budget_exceeded(message),
+            Some(AbortReason::ToolFailure) => {
+                let tool = session
+                    .agent
+                    .messages()
+                    .iter()
+                    .rev()
+                    .find_map(|message| match message {
+                        Message::ToolResult(result) if result.is_error => {
+                            Some(result.tool_name.clone())
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                pi::error::Error::tool(tool, message)
+            }
+            Some(AbortReason::ProviderFailure) | None => {
+                pi::error::Error::provider(last_message.provider.clone(), message)
+            }
+        };
+        return Err(error.into());
     }
 
     if mode == "text" {
@@ -995,6 +1804,27 @@ async fn run_print_mode(
     Ok(())
 }
 
+/// Ask on the terminal whether to carry out a plan; with no terminal nobody can approve it.
+fn terminal_plan_approver() -> PlanApprover {
+    Arc::new(|plan: Plan| {
+        Box::pin(async move {
+            if !io::stdin().is_terminal() {
+                eprintln!("Plan mode needs a terminal to approve the plan; nothing was run.");
+                return None;
+            }
+            eprintln!("Plan:");
+            for (index, step) in plan.steps.iter().enumerate() {
+                eprintln!("  {}. {step}", index + 1);
+            }
+            eprint!("Run this plan? [y/N] ");
+            let _ = io::stderr().flush();
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer).ok()?;
+            matches!(answer.trim(), "y" | "Y" | "yes").then_some(plan)
+        })
+    })
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn run_interactive_mode(
     session: AgentSession,
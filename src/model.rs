@@ -53,6 +53,9 @@ pub struct AssistantMessage {
     pub model: String,
     pub usage: Usage,
     pub stop_reason: StopReason,
+    /// Why the run stopped early, when it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_reason: Option<AbortReason>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
     pub timestamp: i64,
@@ -105,6 +108,46 @@ pub enum StopReason {
     Aborted,
 }
 
+/// Why a run stopped before the model finished, recorded next to the [`StopReason`]
+/// (`Aborted` or `Error`) so callers can tell a user's Esc from a limit or an outage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbortReason {
+    /// The user interrupted the run.
+    User,
+    /// A time limit ran out.
+    Timeout,
+    /// A run limit was reached (e.g. `max_tool_iterations`).
+    BudgetExceeded,
+    /// The provider failed and retries did not help.
+    ProviderFailure,
+    /// A tool kept failing after its `tool_retry` retries were used up.
+    ToolFailure,
+}
+
+impl AbortReason {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Timeout => "timeout",
+            Self::BudgetExceeded => "budget_exceeded",
+            Self::ProviderFailure => "provider_failure",
+            Self::ToolFailure => "tool_failure",
+        }
+    }
+
+    /// The `error_message` of a run aborted for this reason.
+    pub const fn abort_message(self) -> &'static str {
+        match self {
+            Self::User => "Aborted",
+            Self::Timeout => "Aborted: timed out",
+            Self::BudgetExceeded => "Aborted: budget exceeded",
+            Self::ProviderFailure => "Aborted: provider failure",
+            Self::ToolFailure => "Aborted: tool failure",
+        }
+    }
+}
+
 // ============================================================================
 // Content Blocks
 // ============================================================================
@@ -121,6 +164,8 @@ pub enum ContentBlock {
     Image(ImageContent),
     /// A request to call a tool with JSON arguments.
     ToolCall(ToolCall),
+    /// A call to, or result from, a tool the provider runs itself (e.g. code execution).
+    HostedTool(HostedToolContent),
 }
 
 /// Text content block.
@@ -142,12 +187,18 @@ impl TextContent {
 }
 
 /// Thinking/reasoning content block.
+///
+/// `thinking_signature` is opaque provider data that must be sent back unchanged on the next
+/// request (Anthropic `signature`). A `redacted` block has no readable text; its encrypted
+/// payload lives in `thinking_signature`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ThinkingContent {
     pub thinking: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking_signature: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub redacted: bool,
 }
 
 /// Image content block.
@@ -158,6 +209,71 @@ pub struct ImageContent {
     pub mime_type: String,
 }
 
+/// Activity of a provider-hosted tool, recorded for display and resent on later turns.
+///
+/// `block` is the provider's content block verbatim (for Anthropic, a `server_tool_use` call
+/// or a `*_tool_result`); pi never executes it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostedToolContent {
+    /// Provider block type, e.g. `server_tool_use` or `code_execution_tool_result`.
+    pub kind: String,
+    pub block: serde_json::Value,
+}
+
+impl HostedToolContent {
+    /// Whether this is the call rather than its result.
+    pub fn is_call(&self) -> bool {
+        self.kind == "server_tool_use"
+    }
+
+    /// One-line description: the tool and its code or query for calls, the output for results.
+    pub fn summary(&self) -> String {
+        let field = |value: &serde_json::Value, key: &str| {
+            value
+                .get(key)
+                .and_then(serde_json::Value::as_str)
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        if self.is_call() {
+            let name = field(&self.block, "name").unwrap_or_else(|| "hosted tool".to_string());
+            let input = self.block.get("input").unwrap_or(&serde_json::Value::Null);
+            let detail = ["code", "command", "query"]
+                .iter()
+                .find_map(|key| field(input, key))
+                .unwrap_or_else(|| input.to_string());
+            return format!("[{name}] {detail}");
+        }
+        let content = self
+            .block
+            .get("content")
+            .unwrap_or(&serde_json::Value::Null);
+        let detail = content.as_array().map_or_else(
+            || {
+                ["stdout", "stderr", "error_code"]
+                    .iter()
+                    .filter_map(|key| field(content, key))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            },
+            |results| {
+                results
+                    .iter()
+                    .filter_map(|result| field(result, "title").or_else(|| field(result, "url")))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            },
+        );
+        if detail.is_empty() {
+            format!("[{}]", self.kind)
+        } else {
+            format!("[{}] {detail}", self.kind)
+        }
+    }
+}
+
 /// Tool call content block.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -194,6 +310,15 @@ pub struct Cost {
     pub cache_read: f64,
     pub cache_write: f64,
     pub total: f64,
+    /// What prompt caching saved against sending the cached tokens as plain input.
+    #[serde(default)]
+    pub cache_savings: f64,
+    /// Spend reported by an API gateway (e.g. LiteLLM) for this request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<f64>,
+    /// Remaining gateway key budget after this request, when the gateway reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway_budget_remaining: Option<f64>,
 }
 
 // ============================================================================
@@ -394,6 +519,19 @@ impl ThinkingLevel {
             Self::XHigh => u32::MAX, // Model max
         }
     }
+
+    /// The next level in the off → xhigh cycle, wrapping back to off.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Minimal,
+            Self::Minimal => Self::Low,
+            Self::Low => Self::Medium,
+            Self::Medium => Self::High,
+            Self::High => Self::XHigh,
+            Self::XHigh => Self::Off,
+        }
+    }
 }
 
 impl std::fmt::Display for ThinkingLevel {
@@ -4,17 +4,59 @@
 
 use crate::models::ModelEntry;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct ModelKey {
     pub provider: String,
     pub id: String,
+    /// Context window in tokens.
+    pub context_window: u32,
+    /// Dollars per million input tokens.
+    pub input_cost: f64,
+    /// Dollars per million output tokens.
+    pub output_cost: f64,
+    /// Part of the `--models` scope; scoped models are listed first.
+    pub scoped: bool,
 }
 
 impl ModelKey {
+    #[must_use]
+    pub fn from_entry(entry: &ModelEntry, scoped: bool) -> Self {
+        Self {
+            provider: entry.model.provider.clone(),
+            id: entry.model.id.clone(),
+            context_window: entry.model.context_window,
+            input_cost: entry.model.cost.input,
+            output_cost: entry.model.cost.output,
+            scoped,
+        }
+    }
+
     #[must_use]
     pub fn full_id(&self) -> String {
         format!("{}/{}", self.provider, self.id)
     }
+
+    /// Context window in compact form, e.g. `200k` or `1M`.
+    #[must_use]
+    pub fn context_label(&self) -> String {
+        match self.context_window {
+            0 => "-".to_string(),
+            tokens if tokens >= 1_000_000 && tokens % 1_000_000 == 0 => {
+                format!("{}M", tokens / 1_000_000)
+            }
+            tokens if tokens >= 1_000 => format!("{}k", tokens / 1_000),
+            tokens => tokens.to_string(),
+        }
+    }
+
+    /// Input/output price per million tokens, e.g. `$3.00/$15.00`.
+    #[must_use]
+    pub fn pricing_label(&self) -> String {
+        if self.input_cost <= 0.0 && self.output_cost <= 0.0 {
+            return "-".to_string();
+        }
+        format!("${:.2}/${:.2}", self.input_cost, self.output_cost)
+    }
 }
 
 #[derive(Debug)]
@@ -27,21 +69,32 @@ pub struct ModelSelectorOverlay {
 }
 
 impl ModelSelectorOverlay {
+    /// List the scoped models first, then every other available model.
     #[must_use]
-    pub fn new(models: &[ModelEntry]) -> Self {
-        let keys = models
+    pub fn new(scoped: &[ModelEntry], available: &[ModelEntry]) -> Self {
+        let mut keys = scoped
             .iter()
-            .map(|entry| ModelKey {
-                provider: entry.model.provider.clone(),
-                id: entry.model.id.clone(),
-            })
+            .map(|entry| ModelKey::from_entry(entry, true))
             .collect::<Vec<_>>();
+        for entry in available {
+            let listed = keys
+                .iter()
+                .any(|key| key.provider == entry.model.provider && key.id == entry.model.id);
+            if !listed {
+                keys.push(ModelKey::from_entry(entry, false));
+            }
+        }
         Self::new_from_keys(keys)
     }
 
     #[must_use]
     pub fn new_from_keys(mut keys: Vec<ModelKey>) -> Self {
-        keys.sort_by(|a, b| a.provider.cmp(&b.provider).then_with(|| a.id.cmp(&b.id)));
+        keys.sort_by(|a, b| {
+            b.scoped
+                .cmp(&a.scoped)
+                .then_with(|| a.provider.cmp(&b.provider))
+                .then_with(|| a.id.cmp(&b.id))
+        });
         let mut selector = Self {
             all: keys,
             filtered: Vec::new(),
@@ -202,6 +255,7 @@ mod tests {
                 .map(|(provider, id)| ModelKey {
                     provider: (*provider).to_string(),
                     id: (*id).to_string(),
+                    ..ModelKey::default()
                 })
                 .collect(),
         )
@@ -233,4 +287,46 @@ mod tests {
         selector.select_next();
         assert_eq!(selector.selected_item().unwrap().full_id(), "openai/gpt-4o");
     }
+
+    #[test]
+    fn scoped_models_come_first_without_duplicates() {
+        let entry = |provider: &str, id: &str| crate::models::ModelEntry {
+            model: crate::provider::Model {
+                id: id.to_string(),
+                name: id.to_string(),
+                api: "test".to_string(),
+                provider: provider.to_string(),
+                base_url: String::new(),
+                reasoning: false,
+                input: Vec::new(),
+                cost: crate::provider::ModelCost {
+                    input: 3.0,
+                    output: 15.0,
+                    cache_read: 0.0,
+                    cache_write: 0.0,
+                },
+                context_window: 200_000,
+                max_tokens: 8192,
+                headers: std::collections::HashMap::new(),
+            },
+            api_key: None,
+            headers: std::collections::HashMap::new(),
+            auth_header: false,
+            compat: None,
+        };
+        let scoped = [entry("openai", "gpt-4o")];
+        let available = [
+            entry("anthropic", "claude-sonnet-4"),
+            entry("openai", "gpt-4o"),
+        ];
+        let selector = ModelSelectorOverlay::new(&scoped, &available);
+
+        assert_eq!(selector.filtered_len(), 2);
+        let first = selector.item_at(0).unwrap();
+        assert_eq!(first.full_id(), "openai/gpt-4o");
+        assert!(first.scoped);
+        assert_eq!(first.context_label(), "200k");
+        assert_eq!(first.pricing_label(), "$3.00/$15.00");
+        assert!(!selector.item_at(1).unwrap().scoped);
+    }
 }
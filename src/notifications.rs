@@ -0,0 +1,312 @@
+//! Notifications for long unattended runs, sent to the sinks in the `notifications` setting.
+//!
+//! Events:
+//! - `run_complete`: a run finished, failed, or was aborted
+//! - `permission_request`: a tool call is waiting for approval and nobody has typed for a while
+//! - `budget`: session spend crossed one of the configured thresholds
+//!
+//! Sinks:
+//! - `command`: run through `sh -c` with `PI_NOTIFICATION_EVENT`, `PI_NOTIFICATION_TITLE` and
+//!   `PI_NOTIFICATION_TEXT` set
+//! - `webhook`: the notification POSTed as JSON
+//! - `slack`: `chat.postMessage` with a bot token; a token starting with `$` names an env var
+//!
+//! Delivery is best effort: a failing sink is logged and never interrupts the run.
+
+use crate::error::{Error, Result};
+use crate::http::client::Client;
+use crate::model::{AssistantMessage, ContentBlock, StopReason};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const SLACK_POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+
+/// Longest a webhook or Slack request may take.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest run summary included in a `run_complete` notification.
+const MAX_SUMMARY_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    RunComplete,
+    PermissionRequest,
+    Budget,
+}
+
+impl NotificationKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::RunComplete => "run_complete",
+            Self::PermissionRequest => "permission_request",
+            Self::Budget => "budget",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotificationSink {
+    Command { command: String },
+    Webhook { url: String },
+    Slack { token: String, channel: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    #[serde(rename = "event")]
+    pub kind: NotificationKind,
+    pub title: String,
+    pub text: String,
+}
+
+impl Notification {
+    /// How a run ended, with the start of the final answer or the error.
+    pub fn run_complete(message: &AssistantMessage) -> Self {
+        let answer = message
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<String>();
+        Self::run_ended(
+            message.stop_reason,
+            message.error_message.as_deref(),
+            &answer,
+        )
+    }
+
+    /// Like [`Self::run_complete`], from the parts of a finished run.
+    pub fn run_ended(stop_reason: StopReason, error_message: Option<&str>, answer: &str) -> Self {
+        let (title, text) = match stop_reason {
+            StopReason::Error => (
+                "pi: run failed",
+                error_message.unwrap_or("Request error").to_string(),
+            ),
+            StopReason::Aborted => ("pi: run aborted", "The run was aborted".to_string()),
+            _ => ("pi: run finished", summarize(answer)),
+        };
+        Self {
+            kind: NotificationKind::RunComplete,
+            title: title.to_string(),
+            text,
+        }
+    }
+
+    /// A tool call waiting for the user's answer; `target` is its path or command.
+    pub fn permission_request(tool_name: &str, target: &str) -> Self {
+        Self {
+            kind: NotificationKind::PermissionRequest,
+            title: "pi: approval needed".to_string(),
+            text: format!("Waiting for approval to run {tool_name} `{target}`"),
+        }
+    }
+
+    pub fn budget(spent_usd: f64, threshold_usd: f64) -> Self {
+        Self {
+            kind: NotificationKind::Budget,
+            title: "pi: budget threshold".to_string(),
+            text: format!("Session spend ${spent_usd:.2} passed ${threshold_usd:.2}"),
+        }
+    }
+
+    /// Body for a Slack `chat.postMessage` call.
+    pub fn slack_payload(&self, channel: &str) -> Value {
+        json!({
+            "channel": channel,
+            "text": format!("*{}*\n{}", self.title, self.text),
+        })
+    }
+}
+
+fn summarize(answer: &str) -> String {
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return "The run finished".to_string();
+    }
+    if answer.chars().count() <= MAX_SUMMARY_CHARS {
+        return answer.to_string();
+    }
+    let mut summary = answer
+        .chars()
+        .take(MAX_SUMMARY_CHARS - 1)
+        .collect::<String>();
+    summary.push('…');
+    summary
+}
+
+/// The configured sinks and the events they receive.
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    sinks: Vec<NotificationSink>,
+    /// Events to send; every event when `None`.
+    events: Option<Vec<NotificationKind>>,
+}
+
+impl Notifier {
+    pub const fn new(sinks: Vec<NotificationSink>, events: Option<Vec<NotificationKind>>) -> Self {
+        Self { sinks, events }
+    }
+
+    /// Whether a notification of `kind` would go anywhere.
+    pub fn wants(&self, kind: NotificationKind) -> bool {
+        !self.sinks.is_empty()
+            && self
+                .events
+                .as_ref()
+                .is_none_or(|events| events.contains(&kind))
+    }
+
+    /// Deliver `notification` to every sink, logging failures.
+    pub async fn send(&self, notification: &Notification) {
+        if !self.wants(notification.kind) {
+            return;
+        }
+        for sink in &self.sinks {
+            if let Err(err) = send_to_sink(sink, notification).await {
+                tracing::warn!(
+                    "notification {} not delivered: {err}",
+                    notification.kind.as_str()
+                );
+            }
+        }
+    }
+}
+
+async fn send_to_sink(sink: &NotificationSink, notification: &Notification) -> Result<()> {
+    match sink {
+        NotificationSink::Command { command } => {
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .env("PI_NOTIFICATION_EVENT", notification.kind.as_str())
+                .env("PI_NOTIFICATION_TITLE", &notification.title)
+                .env("PI_NOTIFICATION_TEXT", &notification.text)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+            // Reap the child without holding up the run.
+            std::thread::spawn(move || child.wait());
+            Ok(())
+        }
+        NotificationSink::Webhook { url } => {
+            let client = Client::new();
+            let response = client
+                .post(url)
+                .timeout(SEND_TIMEOUT)
+                .json(notification)?
+                .send()
+                .await?;
+            let status = response.status();
+            if !(200..300).contains(&status) {
+                return Err(Error::api(format!("webhook {url} answered HTTP {status}")));
+            }
+            Ok(())
+        }
+        NotificationSink::Slack { token, channel } => {
+            let token = resolve_token(token)?;
+            let client = Client::new();
+            let response = client
+                .post(SLACK_POST_MESSAGE_URL)
+                .timeout(SEND_TIMEOUT)
+                .header("Authorization", format!("Bearer {token}"))
+                .json(&notification.slack_payload(channel))?
+                .send()
+                .await?;
+            let body: Value = serde_json::from_str(&response.text().await?)?;
+            if body.get("ok").and_then(Value::as_bool) != Some(true) {
+                let reason = body
+                    .get("error")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error");
+                return Err(Error::api(format!("Slack rejected the message: {reason}")));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// A literal token, or `$NAME` for the value of environment variable `NAME`.
+fn resolve_token(token: &str) -> Result<String> {
+    let Some(name) = token.strip_prefix('$') else {
+        return Ok(token.to_string());
+    };
+    std::env::var(name)
+        .map_err(|_| Error::config(format!("Slack token variable ${name} is not set")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{TextContent, Usage};
+
+    fn assistant(text: &str, stop_reason: StopReason) -> AssistantMessage {
+        AssistantMessage {
+            content: vec![ContentBlock::Text(TextContent::new(text))],
+            api: "test-api".to_string(),
+            provider: "test-provider".to_string(),
+            model: "test-model".to_string(),
+            usage: Usage::default(),
+            stop_reason,
+            abort_reason: None,
+            error_message: None,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn run_complete_summarizes_the_final_answer() {
+        let done = Notification::run_complete(&assistant("All tests pass.", StopReason::Stop));
+        assert_eq!(done.title, "pi: run finished");
+        assert_eq!(done.text, "All tests pass.");
+
+        let long = Notification::run_complete(&assistant(&"x".repeat(500), StopReason::Stop));
+        assert_eq!(long.text.chars().count(), MAX_SUMMARY_CHARS);
+
+        let mut failed = assistant("", StopReason::Error);
+        failed.error_message = Some("rate limited".to_string());
+        assert_eq!(Notification::run_complete(&failed).text, "rate limited");
+    }
+
+    #[test]
+    fn notifier_filters_events_and_needs_a_sink() {
+        let sink = NotificationSink::Webhook {
+            url: "https://example.com/hook".to_string(),
+        };
+        let all = Notifier::new(vec![sink.clone()], None);
+        assert!(all.wants(NotificationKind::Budget));
+
+        let only_runs = Notifier::new(vec![sink], Some(vec![NotificationKind::RunComplete]));
+        assert!(only_runs.wants(NotificationKind::RunComplete));
+        assert!(!only_runs.wants(NotificationKind::PermissionRequest));
+
+        assert!(!Notifier::default().wants(NotificationKind::RunComplete));
+    }
+
+    #[test]
+    fn sinks_deserialize_from_settings() {
+        let sinks: Vec<NotificationSink> = serde_json::from_value(json!([
+            { "type": "command", "command": "notify-send pi \"$PI_NOTIFICATION_TEXT\"" },
+            { "type": "slack", "token": "$SLACK_BOT_TOKEN", "channel": "#builds" },
+        ]))
+        .expect("sinks");
+        assert_eq!(
+            sinks[1],
+            NotificationSink::Slack {
+                token: "$SLACK_BOT_TOKEN".to_string(),
+                channel: "#builds".to_string(),
+            }
+        );
+        let payload = Notification::budget(5.5, 5.0).slack_payload("#builds");
+        assert_eq!(
+            payload["text"],
+            "*pi: budget threshold*\nSession spend $5.50 passed $5.00"
+        );
+    }
+}
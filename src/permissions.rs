@@ -0,0 +1,210 @@
+//! Tool permission policy: which tool calls run, which are refused, and which need approval.
+//!
+//! Rules are checked in order and the first match wins. A rule names a tool (`*` for any)
+//! and optionally narrows it by path prefix (file tools) or command pattern (`bash`):
+//!
+//! ```json
+//! { "tool": "bash", "command": "git status*", "action": "allow" }
+//! { "tool": "write", "pathPrefix": "secrets/", "action": "deny" }
+//! ```
+//!
+//! Calls no rule matches fall back to the default: `ask` for tools that change the workspace
+//! ([`APPROVAL_REQUIRED_TOOLS`]), `allow` for everything else.
+
+use crate::agent::APPROVAL_REQUIRED_TOOLS;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionAction {
+    Allow,
+    Deny,
+    /// Ask the approver (the user, in interactive mode); runs unprompted when there is none.
+    Ask,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionRule {
+    /// Tool name, or `*` for every tool.
+    pub tool: String,
+    /// Only calls whose `path` argument starts with this prefix (a leading `./` is ignored).
+    #[serde(
+        default,
+        alias = "path_prefix",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub path_prefix: Option<String>,
+    /// Only `bash` commands matching this glob pattern (`*` matches anything, including `/`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    pub action: PermissionAction,
+}
+
+impl PermissionRule {
+    pub fn new(tool: impl Into<String>, action: PermissionAction) -> Self {
+        Self {
+            tool: tool.into(),
+            path_prefix: None,
+            command: None,
+            action,
+        }
+    }
+
+    #[must_use]
+    pub fn with_path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.path_prefix = Some(prefix.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_command(mut self, pattern: impl Into<String>) -> Self {
+        self.command = Some(pattern.into());
+        self
+    }
+
+    /// Whether this rule covers a call to `tool_name` with `args`.
+    pub fn matches(&self, tool_name: &str, args: &Value) -> bool {
+        if self.tool != "*" && self.tool != tool_name {
+            return false;
+        }
+        if let Some(prefix) = &self.path_prefix {
+            let Some(path) = args.get("path").and_then(Value::as_str) else {
+                return false;
+            };
+            if !strip_dot_slash(path).starts_with(strip_dot_slash(prefix)) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.command {
+            let Some(command) = args.get("command").and_then(Value::as_str) else {
+                return false;
+            };
+            let Ok(pattern) = glob::Pattern::new(pattern.trim()) else {
+                return false;
+            };
+            if !pattern.matches(command.trim()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Short form for messages, e.g. `bash "rm -rf *"` or `write secrets/`.
+    pub fn describe(&self) -> String {
+        let mut label = self.tool.clone();
+        if let Some(prefix) = &self.path_prefix {
+            label.push(' ');
+            label.push_str(prefix);
+        }
+        if let Some(command) = &self.command {
+            let _ = write!(label, " \"{command}\"");
+        }
+        label
+    }
+}
+
+fn strip_dot_slash(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+/// The policy's answer for one tool call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionDecision {
+    pub action: PermissionAction,
+    /// The rule that decided, or `None` for the default.
+    pub rule: Option<String>,
+}
+
+/// Ordered permission rules consulted before each tool call.
+#[derive(Debug, Clone, Default)]
+pub struct PermissionPolicy {
+    rules: Vec<PermissionRule>,
+}
+
+impl PermissionPolicy {
+    pub const fn new(rules: Vec<PermissionRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn rules(&self) -> &[PermissionRule] {
+        &self.rules
+    }
+
+    /// Decide a call to `tool_name` with `args`: the first matching rule, else the default.
+    pub fn decide(&self, tool_name: &str, args: &Value) -> PermissionDecision {
+        if let Some(rule) = self.rules.iter().find(|rule| rule.matches(tool_name, args)) {
+            return PermissionDecision {
+                action: rule.action,
+                rule: Some(rule.describe()),
+            };
+        }
+        let action = if APPROVAL_REQUIRED_TOOLS.contains(&tool_name) {
+            PermissionAction::Ask
+        } else {
+            PermissionAction::Allow
+        };
+        PermissionDecision { action, rule: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let policy = PermissionPolicy::new(vec![
+            PermissionRule::new("bash", PermissionAction::Allow).with_command("git status*"),
+            PermissionRule::new("bash", PermissionAction::Deny).with_command("rm -rf *"),
+            PermissionRule::new("*", PermissionAction::Deny).with_path_prefix("secrets/"),
+            PermissionRule::new("edit", PermissionAction::Allow).with_path_prefix("./src/"),
+        ]);
+
+        let bash = |command: &str| json!({ "command": command });
+        let path = |path: &str| json!({ "path": path });
+
+        assert_eq!(
+            policy.decide("bash", &bash("git status --short")).action,
+            PermissionAction::Allow
+        );
+        let denied = policy.decide("bash", &bash("rm -rf /tmp/build"));
+        assert_eq!(denied.action, PermissionAction::Deny);
+        assert_eq!(denied.rule.as_deref(), Some("bash \"rm -rf *\""));
+        assert_eq!(
+            policy.decide("read", &path("./secrets/key.pem")).action,
+            PermissionAction::Deny
+        );
+        assert_eq!(
+            policy.decide("edit", &path("src/lib.rs")).action,
+            PermissionAction::Allow
+        );
+    }
+
+    #[test]
+    fn unmatched_calls_use_the_default() {
+        let policy = PermissionPolicy::default();
+        let decision = policy.decide("write", &json!({ "path": "a.txt" }));
+        assert_eq!(decision.action, PermissionAction::Ask);
+        assert_eq!(decision.rule, None);
+        assert_eq!(
+            policy.decide("grep", &json!({ "pattern": "x" })).action,
+            PermissionAction::Allow
+        );
+    }
+
+    #[test]
+    fn rules_deserialize_from_settings() {
+        let rule: PermissionRule = serde_json::from_value(
+            json!({ "tool": "write", "pathPrefix": "docs/", "action": "allow" }),
+        )
+        .expect("rule");
+        assert_eq!(
+            rule,
+            PermissionRule::new("write", PermissionAction::Allow).with_path_prefix("docs/")
+        );
+    }
+}
@@ -8,13 +8,19 @@
 //! - Emitting [`StreamEvent`] values as SSE/HTTP chunks arrive.
 //! - Advertising tool schemas to the model (so it can call [`crate::tools`] by name).
 
+use crate::config::RequestInjectionSettings;
+use crate::error::Error;
 pub use crate::model::StreamEvent;
-use crate::model::{Message, ThinkingLevel};
+use crate::model::{Cost, Message, ThinkingLevel, Usage};
 use async_trait::async_trait;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
 use std::pin::Pin;
+use std::time::Duration;
 
 // ============================================================================
 // Provider Trait
@@ -72,11 +78,59 @@ pub struct Context {
 ///
 /// Providers translate this struct into the backend's tool/schema representation (typically JSON
 /// Schema) so the model can emit tool calls that the host executes locally.
+///
+/// A `hosted` tool is executed by the provider instead: it is declared by kind only, and its
+/// calls and results come back as [`crate::model::ContentBlock::HostedTool`] blocks.
 #[derive(Debug, Clone)]
 pub struct ToolDef {
     pub name: String,
     pub description: String,
     pub parameters: serde_json::Value, // JSON Schema
+    pub hosted: Option<HostedTool>,
+}
+
+impl ToolDef {
+    /// Declaration of a provider-hosted tool.
+    pub fn hosted(kind: HostedTool) -> Self {
+        Self {
+            name: kind.name().to_string(),
+            description: String::new(),
+            parameters: serde_json::Value::Null,
+            hosted: Some(kind),
+        }
+    }
+}
+
+/// Tools a provider can run on its own side (the `hostedTools` setting).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostedTool {
+    /// Sandboxed code execution (Anthropic `code_execution`).
+    CodeExecution,
+    /// Web search (Anthropic `web_search`).
+    WebSearch,
+}
+
+impl HostedTool {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::CodeExecution => "code_execution",
+            Self::WebSearch => "web_search",
+        }
+    }
+}
+
+/// The tools in `tools` that pi executes, dropping hosted tools a provider cannot declare.
+pub fn local_tools<'a>(tools: &'a [ToolDef], provider: &str) -> Vec<&'a ToolDef> {
+    tools
+        .iter()
+        .filter(|tool| {
+            if tool.hosted.is_some() {
+                tracing::debug!("{provider} does not support hosted tool {}", tool.name);
+            }
+            tool.hosted.is_none()
+        })
+        .collect()
 }
 
 // ============================================================================
@@ -95,12 +149,194 @@ pub struct StreamOptions {
     pub cache_retention: CacheRetention,
     pub session_id: Option<String>,
     pub headers: HashMap<String, String>,
+    /// Extra query parameters appended to the request URL (see [`append_query_params`]).
+    pub query_params: HashMap<String, String>,
     pub thinking_level: Option<ThinkingLevel>,
     pub thinking_budgets: Option<ThinkingBudgets>,
 }
 
+/// Append `params` to `url` (sorted by key so requests are deterministic).
+pub fn append_query_params(url: &str, params: &HashMap<String, String>) -> String {
+    if params.is_empty() {
+        return url.to_string();
+    }
+    let mut pairs = params.iter().collect::<Vec<_>>();
+    pairs.sort();
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in pairs {
+        serializer.append_pair(key, value);
+    }
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}{}", serializer.finish())
+}
+
+/// Per-request headers and query parameters from the `request_injection` setting.
+///
+/// Values are templates rendered for every request, so they follow model and session switches.
+/// Supported variables: `{session_id}`, `{cwd_hash}`, `{provider}`, `{model}`, `{env:NAME}`.
+/// Unknown variables are left as-is.
+#[derive(Debug, Clone)]
+pub struct RequestInjection {
+    settings: RequestInjectionSettings,
+    cwd_hash: String,
+}
+
+impl RequestInjection {
+    pub fn new(settings: RequestInjectionSettings, cwd: &Path) -> Self {
+        let digest = Sha256::digest(cwd.to_string_lossy().as_bytes());
+        let cwd_hash = digest.iter().take(6).fold(String::new(), |mut out, byte| {
+            let _ = write!(out, "{byte:02x}");
+            out
+        });
+        Self { settings, cwd_hash }
+    }
+
+    /// Stream options for one request to `provider`/`model`, with the global entries applied
+    /// first and the matching `providers.<name>` entries on top.
+    pub fn apply(&self, options: &StreamOptions, provider: &str, model: &str) -> StreamOptions {
+        let mut options = options.clone();
+        let session_id = options.session_id.clone().unwrap_or_default();
+        let render = |template: &str| {
+            render_request_template(template, |name| match name {
+                "session_id" => Some(session_id.clone()),
+                "cwd_hash" => Some(self.cwd_hash.clone()),
+                "provider" => Some(provider.to_string()),
+                "model" => Some(model.to_string()),
+                _ => name
+                    .strip_prefix("env:")
+                    .map(|var| std::env::var(var).unwrap_or_default()),
+            })
+        };
+        let provider_settings = self
+            .settings
+            .providers
+            .as_ref()
+            .and_then(|providers| providers.get(provider));
+        for settings in std::iter::once(&self.settings).chain(provider_settings) {
+            for (key, value) in settings.headers.iter().flatten() {
+                options.headers.insert(key.clone(), render(value));
+            }
+            for (key, value) in settings.query.iter().flatten() {
+                options.query_params.insert(key.clone(), render(value));
+            }
+        }
+        options
+    }
+}
+
+/// Replace `{name}` placeholders using `lookup`; unresolved placeholders are kept verbatim.
+fn render_request_template(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let name = &after[..end];
+        match lookup(name) {
+            Some(value) => out.push_str(&value),
+            None => {
+                out.push('{');
+                out.push_str(name);
+                out.push('}');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Response header carrying the cost of a request, in dollars (LiteLLM, or any gateway that
+/// uses the generic name).
+const GATEWAY_COST_HEADERS: &[&str] = &["x-litellm-response-cost", "x-gateway-cost"];
+/// Response headers carrying the API key's cumulative spend and budget (LiteLLM).
+const GATEWAY_KEY_SPEND_HEADER: &str = "x-litellm-key-spend";
+const GATEWAY_KEY_MAX_BUDGET_HEADER: &str = "x-litellm-key-max-budget";
+
+/// Relative difference between gateway-reported and locally estimated cost that counts as drift.
+pub const GATEWAY_COST_DRIFT_THRESHOLD: f64 = 0.2;
+
+/// Record gateway-reported spend and remaining key budget from response `headers` into `cost`.
+pub fn record_gateway_cost(headers: &[(String, String)], cost: &mut Cost) {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.trim().parse::<f64>().ok())
+            .filter(|value| value.is_finite() && *value >= 0.0)
+    };
+    if let Some(spend) = GATEWAY_COST_HEADERS.iter().find_map(|name| header(name)) {
+        cost.gateway = Some(spend);
+    }
+    if let (Some(spend), Some(max_budget)) = (
+        header(GATEWAY_KEY_SPEND_HEADER),
+        header(GATEWAY_KEY_MAX_BUDGET_HEADER),
+    ) {
+        cost.gateway_budget_remaining = Some((max_budget - spend).max(0.0));
+    }
+}
+
+/// Relative drift of a gateway-reported cost from the local estimate, if it exceeds
+/// [`GATEWAY_COST_DRIFT_THRESHOLD`]. Models without local pricing are never reported.
+pub fn gateway_cost_drift(gateway: f64, local: f64) -> Option<f64> {
+    if local <= 0.0 {
+        return None;
+    }
+    let drift = (gateway - local) / local;
+    (drift.abs() > GATEWAY_COST_DRIFT_THRESHOLD).then_some(drift)
+}
+
+/// Error for a non-success HTTP response, e.g. `Provider error: Anthropic: HTTP 429: ...`.
+/// The status and any server-requested delay are kept on the error for [`retryable_failure`].
+pub fn http_status_error(
+    label: &str,
+    status: u16,
+    headers: &[(String, String)],
+    body: &str,
+) -> Error {
+    Error::Provider {
+        provider: label.to_string(),
+        message: format!("HTTP {status}: {body}"),
+        status: Some(status),
+        retry_after: retry_after(headers),
+    }
+}
+
+/// Delay requested by `retry-after-ms` or `Retry-After` (seconds; the date form is ignored).
+pub fn retry_after(headers: &[(String, String)]) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.trim().parse::<f64>().ok())
+            .filter(|value| value.is_finite() && *value >= 0.0)
+    };
+    header("retry-after-ms")
+        .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+        .or_else(|| header("retry-after").map(Duration::from_secs_f64))
+}
+
+/// Status and requested delay of a provider failure worth retrying: 429 (rate limited),
+/// 529 (overloaded), or any other 5xx. Other errors are `None`.
+pub fn retryable_failure(err: &Error) -> Option<(u16, Option<Duration>)> {
+    let Error::Provider {
+        status: Some(status),
+        retry_after,
+        ..
+    } = err
+    else {
+        return None;
+    };
+    (*status == 429 || (500..600).contains(status)).then_some((*status, *retry_after))
+}
+
 /// Cache retention policy.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CacheRetention {
     #[default]
     None,
@@ -191,6 +427,36 @@ impl Model {
     }
 }
 
+impl ModelCost {
+    /// The cost of `usage` at these prices, itemized.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn usage_cost(&self, usage: &Usage) -> Cost {
+        let price = |per_million: f64, tokens: u64| per_million / 1_000_000.0 * tokens as f64;
+        let input = price(self.input, usage.input);
+        let output = price(self.output, usage.output);
+        let cache_read = price(self.cache_read, usage.cache_read);
+        let cache_write = price(self.cache_write, usage.cache_write);
+        Cost {
+            input,
+            output,
+            cache_read,
+            cache_write,
+            total: input + output + cache_read + cache_write,
+            cache_savings: self.cache_savings(usage.cache_read, usage.cache_write),
+            ..Cost::default()
+        }
+    }
+
+    /// Dollars saved by prompt caching against sending the cached tokens as plain input:
+    /// reads cost less than input, writes cost more. Negative until reads repay the writes.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn cache_savings(&self, cache_read: u64, cache_write: u64) -> f64 {
+        let read_savings = (self.input - self.cache_read) / 1_000_000.0 * cache_read as f64;
+        let write_premium = (self.cache_write - self.input) / 1_000_000.0 * cache_write as f64;
+        read_savings - write_premium
+    }
+}
+
 // ============================================================================
 // Known APIs and Providers
 // ============================================================================
@@ -305,3 +571,109 @@ impl std::str::FromStr for KnownProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retryable_failure_uses_status_and_retry_after_fields() {
+        let headers = vec![("Retry-After".to_string(), "8".to_string())];
+        let err = http_status_error("Anthropic", 429, &headers, "rate limited");
+        assert_eq!(
+            err.to_string(),
+            "Provider error: Anthropic: HTTP 429: rate limited"
+        );
+        assert_eq!(
+            retryable_failure(&err),
+            Some((429, Some(Duration::from_secs(8))))
+        );
+
+        let overloaded = http_status_error("Anthropic", 529, &[], "overloaded");
+        assert_eq!(retryable_failure(&overloaded), Some((529, None)));
+
+        let bad_request = http_status_error("OpenAI", 400, &headers, "bad input");
+        assert_eq!(retryable_failure(&bad_request), None);
+        assert_eq!(retryable_failure(&Error::api("connection reset")), None);
+        // Only the typed status counts, not a status-like message.
+        assert_eq!(
+            retryable_failure(&Error::provider("Anthropic", "HTTP 529: overloaded")),
+            None
+        );
+    }
+
+    #[test]
+    fn request_injection_renders_templates_with_provider_overrides() {
+        let settings: RequestInjectionSettings = serde_json::from_value(serde_json::json!({
+            "headers": { "Helicone-Session-Id": "{session_id}", "X-Tenant": "default" },
+            "query": { "tag": "{provider}/{model}" },
+            "providers": {
+                "openai": {
+                    "headers": { "X-Tenant": "acme-{cwd_hash}", "X-Unknown": "{nope}" }
+                }
+            }
+        }))
+        .expect("settings");
+        let injection = RequestInjection::new(settings, Path::new("/work/project"));
+        let base = StreamOptions {
+            session_id: Some("sess-1".to_string()),
+            ..StreamOptions::default()
+        };
+
+        let anthropic = injection.apply(&base, "anthropic", "claude");
+        assert_eq!(anthropic.headers["Helicone-Session-Id"], "sess-1");
+        assert_eq!(anthropic.headers["X-Tenant"], "default");
+        assert_eq!(anthropic.query_params["tag"], "anthropic/claude");
+
+        let openai = injection.apply(&base, "openai", "gpt");
+        let tenant = &openai.headers["X-Tenant"];
+        assert!(tenant.starts_with("acme-") && tenant.len() == "acme-".len() + 12);
+        assert_eq!(openai.headers["X-Unknown"], "{nope}");
+        assert!(base.headers.is_empty());
+    }
+
+    #[test]
+    fn gateway_headers_record_spend_budget_and_drift() {
+        let headers = vec![
+            ("X-LiteLLM-Response-Cost".to_string(), "0.0125".to_string()),
+            ("x-litellm-key-spend".to_string(), "4.5".to_string()),
+            ("x-litellm-key-max-budget".to_string(), "10".to_string()),
+        ];
+        let mut cost = Cost::default();
+        record_gateway_cost(&headers, &mut cost);
+        assert_eq!(cost.gateway, Some(0.0125));
+        assert_eq!(cost.gateway_budget_remaining, Some(5.5));
+
+        let mut untouched = Cost::default();
+        record_gateway_cost(
+            &[("x-gateway-cost".to_string(), "n/a".to_string())],
+            &mut untouched,
+        );
+        assert_eq!(untouched.gateway, None);
+
+        assert_eq!(gateway_cost_drift(0.0125, 0.0124), None);
+        let drift = gateway_cost_drift(0.015, 0.01).expect("drift");
+        assert!((drift - 0.5).abs() < 1e-9);
+        assert_eq!(gateway_cost_drift(0.015, 0.0), None);
+    }
+
+    #[test]
+    fn append_query_params_respects_existing_query() {
+        let params = HashMap::from([
+            ("b".to_string(), "two words".to_string()),
+            ("a".to_string(), "1".to_string()),
+        ]);
+        assert_eq!(
+            append_query_params("https://api.test/v1", &params),
+            "https://api.test/v1?a=1&b=two+words"
+        );
+        assert_eq!(
+            append_query_params("https://api.test/v1?alt=sse", &params),
+            "https://api.test/v1?alt=sse&a=1&b=two+words"
+        );
+        assert_eq!(
+            append_query_params("https://api.test/v1", &HashMap::new()),
+            "https://api.test/v1"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Reachability checks for configured providers (model picker badges and `pi doctor`).
+//!
+//! A check is one unauthenticated GET against the provider's models endpoint. Any HTTP
+//! response proves the endpoint is reachable; a 401/404 is expected without credentials.
+//! - healthy: answered below [`DEGRADED_LATENCY`] with a non-5xx status
+//! - degraded: answered slowly or with a 5xx status
+//! - unreachable: connection failure or no answer within [`CHECK_TIMEOUT`]
+//!
+//! Results are cached per base URL for [`CACHE_TTL`] so the picker and repeated runs stay cheap.
+
+use crate::http::client::Client;
+use crate::models::ModelEntry;
+use futures::future::join_all;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Longest a check waits before calling the provider unreachable.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Answers slower than this mark the provider as degraded.
+pub const DEGRADED_LATENCY: Duration = Duration::from_secs(2);
+
+/// How long a result is reused before the endpoint is probed again.
+pub const CACHE_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unreachable,
+}
+
+impl HealthStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Degraded => "degraded",
+            Self::Unreachable => "unreachable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub url: String,
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    /// HTTP status or transport error behind a non-healthy result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+struct CachedHealth {
+    checked_at: Instant,
+    health: ProviderHealth,
+}
+
+fn cache() -> &'static Mutex<HashMap<String, CachedHealth>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, CachedHealth>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The URL probed for a provider base URL: its `/models` listing where the API has one.
+pub fn probe_url(base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    if base.ends_with("/models") {
+        return base.to_string();
+    }
+    if base.ends_with("/v1") || base.ends_with("/v1beta") {
+        return format!("{base}/models");
+    }
+    base.to_string()
+}
+
+/// Classify a probe outcome.
+pub fn classify(status: Option<u16>, latency: Duration) -> (HealthStatus, Option<String>) {
+    match status {
+        None => (HealthStatus::Unreachable, None),
+        Some(code) if code >= 500 => (HealthStatus::Degraded, Some(format!("HTTP {code}"))),
+        Some(_) if latency > DEGRADED_LATENCY => (
+            HealthStatus::Degraded,
+            Some(format!("slow response ({} ms)", latency.as_millis())),
+        ),
+        Some(_) => (HealthStatus::Healthy, None),
+    }
+}
+
+/// Check one provider endpoint, reusing a cached result younger than [`CACHE_TTL`].
+pub async fn check_provider(client: &Client, provider: &str, base_url: &str) -> ProviderHealth {
+    let url = probe_url(base_url);
+    if let Ok(cache) = cache().lock() {
+        if let Some(cached) = cache.get(&url) {
+            if cached.checked_at.elapsed() < CACHE_TTL {
+                return ProviderHealth {
+                    provider: provider.to_string(),
+                    ..cached.health.clone()
+                };
+            }
+        }
+    }
+
+    let started = Instant::now();
+    let result = client.get(&url).timeout(CHECK_TIMEOUT).send().await;
+    let latency = started.elapsed();
+    let (status, detail) = match result {
+        Ok(response) => classify(Some(response.status()), latency),
+        Err(err) => (HealthStatus::Unreachable, Some(err.to_string())),
+    };
+    let health = ProviderHealth {
+        provider: provider.to_string(),
+        url: url.clone(),
+        status,
+        latency_ms: u64::try_from(latency.as_millis()).unwrap_or(u64::MAX),
+        detail,
+    };
+
+    if let Ok(mut cache) = cache().lock() {
+        cache.insert(
+            url,
+            CachedHealth {
+                checked_at: Instant::now(),
+                health: health.clone(),
+            },
+        );
+    }
+    health
+}
+
+/// Check every distinct provider endpoint among `models` concurrently, sorted by provider.
+pub async fn check_models(models: &[ModelEntry]) -> Vec<ProviderHealth> {
+    let mut endpoints: Vec<(String, String)> = models
+        .iter()
+        .map(|entry| (entry.model.provider.clone(), entry.model.base_url.clone()))
+        .collect();
+    endpoints.sort();
+    endpoints.dedup();
+
+    let client = Client::new();
+    join_all(
+        endpoints
+            .iter()
+            .map(|(provider, base_url)| check_provider(&client, provider, base_url)),
+    )
+    .await
+}
+
+/// Worst status per provider, for labelling models in the picker.
+pub fn status_by_provider(results: &[ProviderHealth]) -> HashMap<String, HealthStatus> {
+    let rank = |status: HealthStatus| match status {
+        HealthStatus::Healthy => 0,
+        HealthStatus::Degraded => 1,
+        HealthStatus::Unreachable => 2,
+    };
+    let mut statuses: HashMap<String, HealthStatus> = HashMap::new();
+    for health in results {
+        statuses
+            .entry(health.provider.clone())
+            .and_modify(|status| {
+                if rank(health.status) > rank(*status) {
+                    *status = health.status;
+                }
+            })
+            .or_insert(health.status);
+    }
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_url_targets_models_listing() {
+        assert_eq!(
+            probe_url("https://api.openai.com/v1/"),
+            "https://api.openai.com/v1/models"
+        );
+        assert_eq!(
+            probe_url("https://generativelanguage.googleapis.com/v1beta"),
+            "https://generativelanguage.googleapis.com/v1beta/models"
+        );
+        assert_eq!(
+            probe_url("https://api.anthropic.com/v1/messages"),
+            "https://api.anthropic.com/v1/messages"
+        );
+    }
+
+    #[test]
+    fn classify_treats_auth_errors_as_reachable() {
+        let fast = Duration::from_millis(120);
+        assert_eq!(classify(Some(401), fast), (HealthStatus::Healthy, None));
+        assert_eq!(
+            classify(Some(503), fast),
+            (HealthStatus::Degraded, Some("HTTP 503".to_string()))
+        );
+        assert_eq!(
+            classify(Some(200), Duration::from_secs(3)).0,
+            HealthStatus::Degraded
+        );
+        assert_eq!(classify(None, fast).0, HealthStatus::Unreachable);
+    }
+
+    #[test]
+    fn status_by_provider_keeps_the_worst_endpoint() {
+        let health = |provider: &str, url: &str, status| ProviderHealth {
+            provider: provider.to_string(),
+            url: url.to_string(),
+            status,
+            latency_ms: 10,
+            detail: None,
+        };
+        let statuses = status_by_provider(&[
+            health("openai", "https://a", HealthStatus::Healthy),
+            health("openai", "https://b", HealthStatus::Unreachable),
+            health("anthropic", "https://c", HealthStatus::Degraded),
+        ]);
+        assert_eq!(statuses["openai"], HealthStatus::Unreachable);
+        assert_eq!(statuses["anthropic"], HealthStatus::Degraded);
+    }
+}
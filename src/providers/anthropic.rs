@@ -6,10 +6,13 @@
 use crate::error::{Error, Result};
 use crate::http::client::Client;
 use crate::model::{
-    AssistantMessage, ContentBlock, Message, StopReason, StreamEvent, TextContent, ThinkingContent,
-    ThinkingLevel, ToolCall, Usage, UserContent,
+    AssistantMessage, ContentBlock, Cost, HostedToolContent, Message, StopReason, StreamEvent,
+    TextContent, ThinkingContent, ThinkingLevel, ToolCall, Usage, UserContent,
+};
+use crate::provider::{
+    CacheRetention, Context, HostedTool, Provider, StreamOptions, ToolDef, append_query_params,
+    http_status_error, record_gateway_cost,
 };
-use crate::provider::{CacheRetention, Context, Provider, StreamOptions, ToolDef};
 use crate::sse::SseStream;
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -24,6 +27,10 @@ use std::pin::Pin;
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const ANTHROPIC_API_VERSION: &str = "2023-06-01";
 const DEFAULT_MAX_TOKENS: u32 = 8192;
+/// Beta flag required to declare the `code_execution` server tool.
+const CODE_EXECUTION_BETA: &str = "code-execution-2025-05-22";
+/// Beta enabling the one-hour cache TTL used by `CacheRetention::Long`.
+const EXTENDED_CACHE_TTL_BETA: &str = "extended-cache-ttl-2025-04-11";
 
 // ============================================================================
 // Anthropic Provider
@@ -103,16 +110,20 @@ impl AnthropicProvider {
             }
         });
 
-        AnthropicRequest {
+        let mut request = AnthropicRequest {
             model: self.model.clone(),
             messages,
-            system: context.system_prompt.clone(),
+            system: context.system_prompt.clone().map(AnthropicSystem::Text),
             max_tokens: options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
             temperature: options.temperature,
             tools,
             stream: true,
             thinking,
+        };
+        if let Some(cache_control) = AnthropicCacheControl::for_retention(options.cache_retention) {
+            place_cache_breakpoints(&mut request, &cache_control);
         }
+        request
     }
 }
 
@@ -146,15 +157,28 @@ impl Provider for AnthropicProvider {
         // Build request with headers
         let mut request = self
             .client
-            .post(&self.base_url)
+            .post(&append_query_params(&self.base_url, &options.query_params))
             .header("Content-Type", "application/json")
             .header("Accept", "text/event-stream")
             .header("X-API-Key", &auth_value)
             .header("anthropic-version", ANTHROPIC_API_VERSION);
 
-        // Add cache control header if needed
+        let mut betas = Vec::new();
         if options.cache_retention != CacheRetention::None {
-            request = request.header("anthropic-beta", "prompt-caching-2024-07-31");
+            betas.push("prompt-caching-2024-07-31");
+        }
+        if options.cache_retention == CacheRetention::Long {
+            betas.push(EXTENDED_CACHE_TTL_BETA);
+        }
+        if context
+            .tools
+            .iter()
+            .any(|tool| tool.hosted == Some(HostedTool::CodeExecution))
+        {
+            betas.push(CODE_EXECUTION_BETA);
+        }
+        if !betas.is_empty() {
+            request = request.header("anthropic-beta", betas.join(","));
         }
 
         // Add custom headers
@@ -167,13 +191,14 @@ impl Provider for AnthropicProvider {
         let response = Box::pin(request.send()).await?;
         let status = response.status();
         if !(200..300).contains(&status) {
+            let headers = response.headers().to_vec();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::api(format!(
-                "Anthropic API error (HTTP {status}): {body}"
-            )));
+            return Err(http_status_error("Anthropic", status, &headers, &body));
         }
 
         // Create SSE stream for streaming responses.
+        let mut gateway_cost = Cost::default();
+        record_gateway_cost(response.headers(), &mut gateway_cost);
         let event_source = SseStream::new(response.bytes_stream());
 
         // Create stream state
@@ -182,7 +207,11 @@ impl Provider for AnthropicProvider {
         let provider = self.name().to_string();
 
         let stream = stream::unfold(
-            StreamState::new(event_source, model, api, provider),
+            {
+                let mut state = StreamState::new(event_source, model, api, provider);
+                state.partial.usage.cost = gateway_cost;
+                state
+            },
             |mut state| async move {
                 loop {
                     match state.event_source.next().await {
@@ -242,6 +271,7 @@ where
                 model,
                 usage: Usage::default(),
                 stop_reason: StopReason::Stop,
+                abort_reason: None,
                 error_message: None,
                 timestamp: chrono::Utc::now().timestamp_millis(),
             },
@@ -331,12 +361,48 @@ where
                     .push(ContentBlock::Thinking(ThinkingContent {
                         thinking: String::new(),
                         thinking_signature: None,
+                        redacted: false,
                     }));
                 Some(StreamEvent::ThinkingStart {
                     content_index,
                     partial: self.partial.clone(),
                 })
             }
+            "redacted_thinking" => {
+                // Encrypted reasoning: nothing to show, but it must be resent verbatim.
+                self.current_thinking.clear();
+                self.partial
+                    .content
+                    .push(ContentBlock::Thinking(ThinkingContent {
+                        thinking: String::new(),
+                        thinking_signature: content_block.data,
+                        redacted: true,
+                    }));
+                Some(StreamEvent::ThinkingStart {
+                    content_index,
+                    partial: self.partial.clone(),
+                })
+            }
+            "server_tool_use" => {
+                // Run by the provider: recorded, never executed; input arrives as JSON deltas.
+                self.current_tool_json.clear();
+                self.partial
+                    .content
+                    .push(ContentBlock::HostedTool(HostedToolContent {
+                        kind: content_block.r#type.clone(),
+                        block: content_block.into_raw(),
+                    }));
+                None
+            }
+            kind if kind.ends_with("_tool_result") => {
+                self.partial
+                    .content
+                    .push(ContentBlock::HostedTool(HostedToolContent {
+                        kind: kind.to_string(),
+                        block: content_block.into_raw(),
+                    }));
+                None
+            }
             "tool_use" => {
                 self.current_tool_json.clear();
                 self.current_tool_id = content_block.id;
@@ -394,9 +460,25 @@ where
                     None
                 }
             }
+            "signature_delta" => {
+                if let (Some(signature), Some(ContentBlock::Thinking(t))) =
+                    (delta.signature, self.partial.content.get_mut(idx))
+                {
+                    t.thinking_signature
+                        .get_or_insert_with(String::new)
+                        .push_str(&signature);
+                }
+                None
+            }
             "input_json_delta" => {
                 if let Some(partial_json) = delta.partial_json {
                     self.current_tool_json.push_str(&partial_json);
+                    if matches!(
+                        self.partial.content.get(idx),
+                        Some(ContentBlock::HostedTool(_))
+                    ) {
+                        return None;
+                    }
                     Some(StreamEvent::ToolCallDelta {
                         content_index: idx,
                         delta: partial_json,
@@ -432,6 +514,18 @@ where
                     partial: self.partial.clone(),
                 })
             }
+            Some(ContentBlock::HostedTool(hosted)) => {
+                if hosted.is_call() {
+                    let input = std::mem::take(&mut self.current_tool_json);
+                    if let (Ok(input), Some(block)) = (
+                        serde_json::from_str::<serde_json::Value>(&input),
+                        hosted.block.as_object_mut(),
+                    ) {
+                        block.insert("input".to_string(), input);
+                    }
+                }
+                None
+            }
             Some(ContentBlock::ToolCall(tc)) => {
                 let arguments: serde_json::Value =
                     match serde_json::from_str(&self.current_tool_json) {
@@ -495,7 +589,7 @@ struct AnthropicRequest {
     model: String,
     messages: Vec<AnthropicMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    system: Option<AnthropicSystem>,
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
@@ -506,6 +600,37 @@ struct AnthropicRequest {
     thinking: Option<AnthropicThinking>,
 }
 
+/// The system prompt: plain text, or a text block when it carries a cache breakpoint.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum AnthropicSystem {
+    Text(String),
+    Blocks(Vec<AnthropicContent>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AnthropicCacheControl {
+    r#type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<&'static str>,
+}
+
+impl AnthropicCacheControl {
+    const fn for_retention(retention: CacheRetention) -> Option<Self> {
+        match retention {
+            CacheRetention::None => None,
+            CacheRetention::Short => Some(Self {
+                r#type: "ephemeral",
+                ttl: None,
+            }),
+            CacheRetention::Long => Some(Self {
+                r#type: "ephemeral",
+                ttl: Some("1h"),
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct AnthropicThinking {
     r#type: String,
@@ -523,9 +648,20 @@ struct AnthropicMessage {
 enum AnthropicContent {
     Text {
         text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
+    },
+    Thinking {
+        thinking: String,
+        signature: String,
+    },
+    RedactedThinking {
+        data: String,
     },
     Image {
         source: AnthropicImageSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
     },
     ToolUse {
         id: String,
@@ -537,7 +673,12 @@ enum AnthropicContent {
         content: Vec<AnthropicToolResultContent>,
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
     },
+    /// A server tool call or result, resent exactly as received.
+    #[serde(untagged)]
+    Raw(serde_json::Value),
 }
 
 #[derive(Debug, Serialize)]
@@ -555,10 +696,20 @@ enum AnthropicToolResultContent {
 }
 
 #[derive(Debug, Serialize)]
-struct AnthropicTool {
-    name: String,
-    description: String,
-    input_schema: serde_json::Value,
+#[serde(untagged)]
+enum AnthropicTool {
+    Custom {
+        name: String,
+        description: String,
+        input_schema: serde_json::Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<AnthropicCacheControl>,
+    },
+    /// A server tool, declared by its versioned type.
+    Server {
+        r#type: &'static str,
+        name: &'static str,
+    },
 }
 
 // ============================================================================
@@ -625,6 +776,27 @@ struct AnthropicContentBlock {
     id: Option<String>,
     #[serde(default)]
     name: Option<String>,
+    /// Encrypted payload of a `redacted_thinking` block.
+    #[serde(default)]
+    data: Option<String>,
+    /// Every other field, kept for server tool blocks.
+    #[serde(flatten)]
+    rest: serde_json::Map<String, serde_json::Value>,
+}
+
+impl AnthropicContentBlock {
+    /// The block as the API sent it (without `data`, which only thinking blocks carry).
+    fn into_raw(self) -> serde_json::Value {
+        let mut block = self.rest;
+        block.insert("type".to_string(), self.r#type.into());
+        if let Some(id) = self.id {
+            block.insert("id".to_string(), id.into());
+        }
+        if let Some(name) = self.name {
+            block.insert("name".to_string(), name.into());
+        }
+        serde_json::Value::Object(block)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -635,6 +807,8 @@ struct AnthropicDelta {
     #[serde(default)]
     thinking: Option<String>,
     #[serde(default)]
+    signature: Option<String>,
+    #[serde(default)]
     partial_json: Option<String>,
 }
 
@@ -663,16 +837,21 @@ fn convert_message_to_anthropic(message: &Message) -> AnthropicMessage {
             role: "user".to_string(),
             content: vec![AnthropicContent::Text {
                 text: custom.content.clone(),
+                cache_control: None,
             }],
         },
-        Message::Assistant(assistant) => AnthropicMessage {
-            role: "assistant".to_string(),
-            content: assistant
-                .content
-                .iter()
-                .filter_map(convert_content_block_to_anthropic)
-                .collect(),
-        },
+        Message::Assistant(assistant) => {
+            // Signatures are only valid for the provider that issued them.
+            let keep_signatures = assistant.provider == "anthropic";
+            AnthropicMessage {
+                role: "assistant".to_string(),
+                content: assistant
+                    .content
+                    .iter()
+                    .filter_map(|block| convert_content_block_to_anthropic(block, keep_signatures))
+                    .collect(),
+            }
+        }
         Message::ToolResult(result) => AnthropicMessage {
             role: "user".to_string(),
             content: vec![AnthropicContent::ToolResult {
@@ -695,6 +874,7 @@ fn convert_message_to_anthropic(message: &Message) -> AnthropicMessage {
                     })
                     .collect(),
                 is_error: if result.is_error { Some(true) } else { None },
+                cache_control: None,
             }],
         },
     }
@@ -702,12 +882,16 @@ fn convert_message_to_anthropic(message: &Message) -> AnthropicMessage {
 
 fn convert_user_content(content: &UserContent) -> Vec<AnthropicContent> {
     match content {
-        UserContent::Text(text) => vec![AnthropicContent::Text { text: text.clone() }],
+        UserContent::Text(text) => vec![AnthropicContent::Text {
+            text: text.clone(),
+            cache_control: None,
+        }],
         UserContent::Blocks(blocks) => blocks
             .iter()
             .filter_map(|block| match block {
                 ContentBlock::Text(t) => Some(AnthropicContent::Text {
                     text: t.text.clone(),
+                    cache_control: None,
                 }),
                 ContentBlock::Image(img) => Some(AnthropicContent::Image {
                     source: AnthropicImageSource {
@@ -715,6 +899,7 @@ fn convert_user_content(content: &UserContent) -> Vec<AnthropicContent> {
                         media_type: img.mime_type.clone(),
                         data: img.data.clone(),
                     },
+                    cache_control: None,
                 }),
                 _ => None,
             })
@@ -722,26 +907,137 @@ fn convert_user_content(content: &UserContent) -> Vec<AnthropicContent> {
     }
 }
 
-fn convert_content_block_to_anthropic(block: &ContentBlock) -> Option<AnthropicContent> {
+fn convert_content_block_to_anthropic(
+    block: &ContentBlock,
+    keep_signatures: bool,
+) -> Option<AnthropicContent> {
     match block {
         ContentBlock::Text(t) => Some(AnthropicContent::Text {
             text: t.text.clone(),
+            cache_control: None,
         }),
         ContentBlock::ToolCall(tc) => Some(AnthropicContent::ToolUse {
             id: tc.id.clone(),
             name: tc.name.clone(),
             input: tc.arguments.clone(),
         }),
-        // Thinking blocks are not sent back to the API
-        ContentBlock::Thinking(_) | ContentBlock::Image(_) => None,
+        // Signed thinking must be echoed back for multi-turn extended thinking (tool use in
+        // particular); unsigned thinking would be rejected, so it is dropped.
+        ContentBlock::Thinking(t) if keep_signatures => {
+            let signature = t.thinking_signature.clone()?;
+            Some(if t.redacted {
+                AnthropicContent::RedactedThinking { data: signature }
+            } else {
+                AnthropicContent::Thinking {
+                    thinking: t.thinking.clone(),
+                    signature,
+                }
+            })
+        }
+        ContentBlock::HostedTool(hosted) if keep_signatures => {
+            Some(AnthropicContent::Raw(hosted.block.clone()))
+        }
+        ContentBlock::Thinking(_) | ContentBlock::Image(_) | ContentBlock::HostedTool(_) => None,
     }
 }
 
 fn convert_tool_to_anthropic(tool: &ToolDef) -> AnthropicTool {
-    AnthropicTool {
-        name: tool.name.clone(),
-        description: tool.description.clone(),
-        input_schema: tool.parameters.clone(),
+    match tool.hosted {
+        Some(HostedTool::CodeExecution) => AnthropicTool::Server {
+            r#type: "code_execution_20250522",
+            name: "code_execution",
+        },
+        Some(HostedTool::WebSearch) => AnthropicTool::Server {
+            r#type: "web_search_20250305",
+            name: "web_search",
+        },
+        None => AnthropicTool::Custom {
+            name: tool.name.clone(),
+            description: tool.description.clone(),
+            input_schema: tool.parameters.clone(),
+            cache_control: None,
+        },
+    }
+}
+
+/// Most cache breakpoints Anthropic accepts in one request.
+const MAX_CACHE_BREAKPOINTS: usize = 4;
+
+/// Mark the stable prefix of the request for prompt caching: the tool definitions, the system
+/// prompt, and the history up to the latest two user turns. The breakpoint on the previous
+/// turn reads what the last request cached; the one on the latest turn writes for the next.
+fn place_cache_breakpoints(request: &mut AnthropicRequest, cache_control: &AnthropicCacheControl) {
+    let mut placed = 0;
+    if let Some(AnthropicTool::Custom {
+        cache_control: slot,
+        ..
+    }) = request.tools.as_mut().and_then(|tools| tools.last_mut())
+    {
+        *slot = Some(cache_control.clone());
+        placed += 1;
+    }
+    if let Some(AnthropicSystem::Text(text)) = request.system.take() {
+        request.system = Some(AnthropicSystem::Blocks(vec![AnthropicContent::Text {
+            text,
+            cache_control: Some(cache_control.clone()),
+        }]));
+        placed += 1;
+    }
+    for message in request
+        .messages
+        .iter_mut()
+        .rev()
+        .filter(|message| message.role == "user")
+    {
+        if placed >= MAX_CACHE_BREAKPOINTS {
+            break;
+        }
+        let slot = message
+            .content
+            .iter_mut()
+            .rev()
+            .find_map(|block| match block {
+                AnthropicContent::Text { cache_control, .. }
+                | AnthropicContent::Image { cache_control, .. }
+                | AnthropicContent::ToolResult { cache_control, .. } => Some(cache_control),
+                _ => None,
+            });
+        if let Some(slot) = slot {
+            *slot = Some(cache_control.clone());
+            placed += 1;
+        }
+    }
+}
+
+// ============================================================================
+// Fuzzing
+// ============================================================================
+
+/// Entry points for `cargo fuzz` (compiled only with `--cfg fuzzing`).
+#[cfg(fuzzing)]
+pub mod fuzz {
+    use super::*;
+
+    /// Drive the stream decoder over raw SSE bytes split into `chunk_len` pieces.
+    pub fn process_sse_bytes(data: &[u8], chunk_len: usize) {
+        let chunks: Vec<std::io::Result<Vec<u8>>> = data
+            .chunks(chunk_len.max(1))
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+        let event_source = SseStream::new(stream::iter(chunks));
+        let mut state = StreamState::new(
+            event_source,
+            "fuzz".to_string(),
+            "anthropic-messages".to_string(),
+            "anthropic".to_string(),
+        );
+        futures::executor::block_on(async {
+            while let Some(Ok(msg)) = state.event_source.next().await {
+                if msg.event != "ping" {
+                    let _ = state.process_event(&msg.data);
+                }
+            }
+        });
     }
 }
 
@@ -771,6 +1067,62 @@ mod tests {
         assert_eq!(converted.content.len(), 1);
     }
 
+    #[test]
+    fn test_cache_breakpoints_mark_tools_system_and_latest_user_turns() {
+        let user = |text: &str| {
+            Message::User(crate::model::UserMessage {
+                content: UserContent::Text(text.to_string()),
+                timestamp: 0,
+            })
+        };
+        let context = Context {
+            system_prompt: Some("You are pi.".to_string()),
+            messages: vec![
+                user("first"),
+                assistant_with(
+                    "anthropic",
+                    vec![ContentBlock::Text(TextContent::new("ok"))],
+                ),
+                user("second"),
+                assistant_with(
+                    "anthropic",
+                    vec![ContentBlock::Text(TextContent::new("ok"))],
+                ),
+                user("third"),
+            ],
+            tools: vec![ToolDef {
+                name: "read".to_string(),
+                description: "Read a file".to_string(),
+                parameters: json!({ "type": "object" }),
+                hosted: None,
+            }],
+        };
+        let provider = AnthropicProvider::new("claude-sonnet-4-5");
+        let options = StreamOptions {
+            cache_retention: CacheRetention::Short,
+            ..StreamOptions::default()
+        };
+
+        let body = serde_json::to_value(provider.build_request(&context, &options)).unwrap();
+        let ephemeral = json!({ "type": "ephemeral" });
+        assert_eq!(body["tools"][0]["cache_control"], ephemeral);
+        assert_eq!(body["system"][0]["text"], "You are pi.");
+        assert_eq!(body["system"][0]["cache_control"], ephemeral);
+        let marked = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["content"][0].get("cache_control").is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(marked, [false, false, true, false, true]);
+
+        let plain =
+            serde_json::to_value(provider.build_request(&context, &StreamOptions::default()))
+                .unwrap();
+        assert_eq!(plain["system"], "You are pi.");
+        assert!(plain["tools"][0].get("cache_control").is_none());
+    }
+
     #[test]
     fn test_thinking_budget() {
         assert_eq!(ThinkingLevel::Minimal.default_budget(), 1024);
@@ -835,6 +1187,172 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stream_captures_thinking_signatures_and_redacted_blocks() {
+        let events = vec![
+            json!({ "type": "message_start", "message": {} }),
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "thinking" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "thinking_delta", "thinking": "hmm" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "signature_delta", "signature": "sig-" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "signature_delta", "signature": "abc" } }),
+            json!({ "type": "content_block_stop", "index": 0 }),
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "redacted_thinking", "data": "enc" } }),
+            json!({ "type": "content_block_stop", "index": 1 }),
+        ];
+
+        let out = collect_events(&events);
+        let Some(StreamEvent::ThinkingEnd { partial, .. }) = out.last() else {
+            panic!("expected ThinkingEnd, got {out:?}");
+        };
+        let [
+            ContentBlock::Thinking(signed),
+            ContentBlock::Thinking(redacted),
+        ] = partial.content.as_slice()
+        else {
+            panic!("expected two thinking blocks, got {:?}", partial.content);
+        };
+        assert_eq!(signed.thinking, "hmm");
+        assert_eq!(signed.thinking_signature.as_deref(), Some("sig-abc"));
+        assert!(!signed.redacted);
+        assert!(redacted.redacted);
+        assert_eq!(redacted.thinking_signature.as_deref(), Some("enc"));
+    }
+
+    fn assistant_with(provider: &str, content: Vec<ContentBlock>) -> Message {
+        Message::Assistant(AssistantMessage {
+            content,
+            api: "test".to_string(),
+            provider: provider.to_string(),
+            model: "test".to_string(),
+            usage: Usage::default(),
+            stop_reason: StopReason::ToolUse,
+            abort_reason: None,
+            error_message: None,
+            timestamp: 0,
+        })
+    }
+
+    fn thinking(text: &str, signature: Option<&str>, redacted: bool) -> ContentBlock {
+        ContentBlock::Thinking(ThinkingContent {
+            thinking: text.to_string(),
+            thinking_signature: signature.map(str::to_string),
+            redacted,
+        })
+    }
+
+    #[test]
+    fn test_signed_thinking_is_sent_back() {
+        let message = assistant_with(
+            "anthropic",
+            vec![
+                thinking("plan", Some("sig"), false),
+                thinking("", Some("enc"), true),
+                thinking("unsigned", None, false),
+                ContentBlock::Text(TextContent::new("done")),
+            ],
+        );
+
+        let converted = serde_json::to_value(convert_message_to_anthropic(&message)).unwrap();
+        assert_eq!(
+            converted["content"],
+            json!([
+                { "type": "thinking", "thinking": "plan", "signature": "sig" },
+                { "type": "redacted_thinking", "data": "enc" },
+                { "type": "text", "text": "done" },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_thinking_from_other_provider_is_dropped() {
+        let message = assistant_with(
+            "google",
+            vec![
+                thinking("plan", Some("sig"), false),
+                ContentBlock::Text(TextContent::new("done")),
+            ],
+        );
+
+        let converted = serde_json::to_value(convert_message_to_anthropic(&message)).unwrap();
+        assert_eq!(
+            converted["content"],
+            json!([{ "type": "text", "text": "done" }])
+        );
+    }
+
+    #[test]
+    fn test_server_tool_blocks_are_recorded_and_resent() {
+        let events = vec![
+            json!({ "type": "message_start", "message": {} }),
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "server_tool_use", "id": "srvtoolu_1", "name": "code_execution", "input": {} } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "input_json_delta", "partial_json": "{\"code\": \"print(6 * 7)\"}" } }),
+            json!({ "type": "content_block_stop", "index": 0 }),
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "code_execution_tool_result", "tool_use_id": "srvtoolu_1", "content": { "type": "code_execution_result", "stdout": "42\n", "stderr": "", "return_code": 0 } } }),
+            json!({ "type": "content_block_stop", "index": 1 }),
+            json!({ "type": "content_block_start", "index": 2, "content_block": { "type": "text", "text": "" } }),
+            json!({ "type": "content_block_delta", "index": 2, "delta": { "type": "text_delta", "text": "It is 42." } }),
+            json!({ "type": "content_block_stop", "index": 2 }),
+            json!({ "type": "message_delta", "delta": { "stop_reason": "end_turn" }, "usage": { "output_tokens": 12 } }),
+            json!({ "type": "message_stop" }),
+        ];
+
+        let out = collect_events(&events);
+        assert!(
+            !out.iter()
+                .any(|event| matches!(event, StreamEvent::ToolCallStart { .. })),
+            "server tools must not surface as local tool calls"
+        );
+        let Some(StreamEvent::Done { message, .. }) = out.last() else {
+            panic!("expected Done, got {out:?}");
+        };
+        let [
+            ContentBlock::HostedTool(call),
+            ContentBlock::HostedTool(result),
+            ContentBlock::Text(text),
+        ] = message.content.as_slice()
+        else {
+            panic!("unexpected content: {:?}", message.content);
+        };
+        assert_eq!(call.summary(), "[code_execution] print(6 * 7)");
+        assert_eq!(result.summary(), "[code_execution_tool_result] 42");
+        assert_eq!(text.text, "It is 42.");
+
+        let resent = serde_json::to_value(convert_message_to_anthropic(&Message::Assistant(
+            message.clone(),
+        )))
+        .unwrap();
+        assert_eq!(
+            resent["content"][0],
+            json!({ "type": "server_tool_use", "id": "srvtoolu_1", "name": "code_execution", "input": { "code": "print(6 * 7)" } })
+        );
+        assert_eq!(resent["content"][1]["tool_use_id"], "srvtoolu_1");
+    }
+
+    #[test]
+    fn test_hosted_tools_are_declared_by_type() {
+        let tools = [
+            ToolDef::hosted(HostedTool::CodeExecution),
+            ToolDef {
+                name: "read".to_string(),
+                description: "Read a file".to_string(),
+                parameters: json!({ "type": "object" }),
+                hosted: None,
+            },
+        ];
+        let declared = serde_json::to_value(
+            tools
+                .iter()
+                .map(convert_tool_to_anthropic)
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        assert_eq!(
+            declared[0],
+            json!({ "type": "code_execution_20250522", "name": "code_execution" })
+        );
+        assert_eq!(declared[1]["input_schema"], json!({ "type": "object" }));
+    }
+
     fn load_fixture(file_name: &str) -> ProviderFixture {
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("tests/fixtures/provider_responses")
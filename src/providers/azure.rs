@@ -9,9 +9,12 @@
 use crate::error::{Error, Result};
 use crate::http::client::Client;
 use crate::model::{
-    AssistantMessage, ContentBlock, Message, StopReason, StreamEvent, Usage, UserContent,
+    AssistantMessage, ContentBlock, Cost, Message, StopReason, StreamEvent, Usage, UserContent,
+};
+use crate::provider::{
+    Context, Provider, StreamOptions, ToolDef, append_query_params, http_status_error, local_tools,
+    record_gateway_cost,
 };
-use crate::provider::{Context, Provider, StreamOptions, ToolDef};
 use crate::sse::SseStream;
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -100,10 +103,16 @@ impl AzureOpenAIProvider {
     fn build_request(&self, context: &Context, options: &StreamOptions) -> AzureRequest {
         let messages = Self::build_messages(context);
 
-        let tools: Option<Vec<AzureTool>> = if context.tools.is_empty() {
+        let function_tools = local_tools(&context.tools, "azure");
+        let tools: Option<Vec<AzureTool>> = if function_tools.is_empty() {
             None
         } else {
-            Some(context.tools.iter().map(convert_tool_to_azure).collect())
+            Some(
+                function_tools
+                    .into_iter()
+                    .map(convert_tool_to_azure)
+                    .collect(),
+            )
         };
 
         AzureRequest {
@@ -173,7 +182,7 @@ impl Provider for AzureOpenAIProvider {
         // Build request with Azure-specific headers
         let mut request = self
             .client
-            .post(&endpoint_url)
+            .post(&append_query_params(&endpoint_url, &options.query_params))
             .header("Content-Type", "application/json")
             .header("Accept", "text/event-stream")
             .header("api-key", &auth_value); // Azure uses api-key header, not Authorization
@@ -187,13 +196,14 @@ impl Provider for AzureOpenAIProvider {
         let response = Box::pin(request.send()).await?;
         let status = response.status();
         if !(200..300).contains(&status) {
+            let headers = response.headers().to_vec();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::api(format!(
-                "Azure OpenAI API error (HTTP {status}): {body}"
-            )));
+            return Err(http_status_error("Azure OpenAI", status, &headers, &body));
         }
 
         // Create SSE stream for streaming responses.
+        let mut gateway_cost = Cost::default();
+        record_gateway_cost(response.headers(), &mut gateway_cost);
         let event_source = SseStream::new(response.bytes_stream());
 
         // Create stream state
@@ -202,7 +212,11 @@ impl Provider for AzureOpenAIProvider {
         let provider = self.name().to_string();
 
         let stream = stream::unfold(
-            StreamState::new(event_source, model, api, provider),
+            {
+                let mut state = StreamState::new(event_source, model, api, provider);
+                state.partial.usage.cost = gateway_cost;
+                state
+            },
             |mut state| async move {
                 loop {
                     if let Some(event) = state.pending_events.pop_front() {
@@ -278,6 +292,7 @@ where
                 model,
                 usage: Usage::default(),
                 stop_reason: StopReason::Stop,
+                abort_reason: None,
                 error_message: None,
                 timestamp: chrono::Utc::now().timestamp_millis(),
             },
@@ -6,10 +6,13 @@
 use crate::error::{Error, Result};
 use crate::http::client::Client;
 use crate::model::{
-    AssistantMessage, ContentBlock, Message, StopReason, StreamEvent, TextContent, ToolCall, Usage,
-    UserContent,
+    AssistantMessage, ContentBlock, Cost, Message, StopReason, StreamEvent, TextContent, ToolCall,
+    Usage, UserContent,
+};
+use crate::provider::{
+    Context, Provider, StreamOptions, ToolDef, append_query_params, http_status_error, local_tools,
+    record_gateway_cost,
 };
-use crate::provider::{Context, Provider, StreamOptions, ToolDef};
 use crate::sse::SseStream;
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -73,14 +76,21 @@ impl GeminiProvider {
         let contents = Self::build_contents(context);
         let system_instruction = context.system_prompt.as_ref().map(|s| GeminiContent {
             role: None,
-            parts: vec![GeminiPart::Text { text: s.clone() }],
+            parts: vec![GeminiPart::Text {
+                text: s.clone(),
+                thought_signature: None,
+            }],
         });
 
-        let tools: Option<Vec<GeminiTool>> = if context.tools.is_empty() {
+        let function_tools = local_tools(&context.tools, "google");
+        let tools: Option<Vec<GeminiTool>> = if function_tools.is_empty() {
             None
         } else {
             Some(vec![GeminiTool {
-                function_declarations: context.tools.iter().map(convert_tool_to_gemini).collect(),
+                function_declarations: function_tools
+                    .into_iter()
+                    .map(convert_tool_to_gemini)
+                    .collect(),
             }])
         };
 
@@ -151,7 +161,7 @@ impl Provider for GeminiProvider {
         // Build request
         let mut request = self
             .client
-            .post(&url)
+            .post(&append_query_params(&url, &options.query_params))
             .header("Content-Type", "application/json")
             .header("Accept", "text/event-stream");
 
@@ -164,13 +174,14 @@ impl Provider for GeminiProvider {
         let response = Box::pin(request.send()).await?;
         let status = response.status();
         if !(200..300).contains(&status) {
+            let headers = response.headers().to_vec();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::api(format!(
-                "Gemini API error (HTTP {status}): {body}"
-            )));
+            return Err(http_status_error("Gemini", status, &headers, &body));
         }
 
         // Create SSE stream for streaming responses.
+        let mut gateway_cost = Cost::default();
+        record_gateway_cost(response.headers(), &mut gateway_cost);
         let event_source = SseStream::new(response.bytes_stream());
 
         // Create stream state
@@ -179,7 +190,11 @@ impl Provider for GeminiProvider {
         let provider = self.name().to_string();
 
         let stream = stream::unfold(
-            StreamState::new(event_source, model, api, provider),
+            {
+                let mut state = StreamState::new(event_source, model, api, provider);
+                state.partial.usage.cost = gateway_cost;
+                state
+            },
             |mut state| async move {
                 loop {
                     match state.event_source.next().await {
@@ -257,6 +272,7 @@ where
                 model,
                 usage: Usage::default(),
                 stop_reason: StopReason::Stop,
+                abort_reason: None,
                 error_message: None,
                 timestamp: chrono::Utc::now().timestamp_millis(),
             },
@@ -304,7 +320,10 @@ where
         if let Some(content) = candidate.content {
             for part in content.parts {
                 match part {
-                    GeminiPart::Text { text } => {
+                    GeminiPart::Text {
+                        text,
+                        thought_signature,
+                    } => {
                         if !self.started {
                             self.started = true;
                             return Ok(Some(StreamEvent::Start {
@@ -326,6 +345,9 @@ where
                             self.partial.content.get_mut(content_index)
                         {
                             t.text.push_str(&text);
+                            if thought_signature.is_some() {
+                                t.text_signature = thought_signature;
+                            }
                         }
 
                         return Ok(Some(StreamEvent::TextDelta {
@@ -334,7 +356,10 @@ where
                             partial: self.partial.clone(),
                         }));
                     }
-                    GeminiPart::FunctionCall { function_call } => {
+                    GeminiPart::FunctionCall {
+                        function_call,
+                        thought_signature,
+                    } => {
                         // Generate a unique ID for this tool call
                         let id = format!("call_{}", uuid::Uuid::new_v4().simple());
 
@@ -347,7 +372,7 @@ where
                             id,
                             name,
                             arguments: args,
-                            thought_signature: None,
+                            thought_signature,
                         };
 
                         self.partial.content.push(ContentBlock::ToolCall(tool_call));
@@ -413,6 +438,12 @@ struct GeminiContent {
 enum GeminiPart {
     Text {
         text: String,
+        #[serde(
+            rename = "thoughtSignature",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        thought_signature: Option<String>,
     },
     InlineData {
         inline_data: GeminiBlob,
@@ -420,6 +451,12 @@ enum GeminiPart {
     FunctionCall {
         #[serde(rename = "functionCall")]
         function_call: GeminiFunctionCall,
+        #[serde(
+            rename = "thoughtSignature",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        thought_signature: Option<String>,
     },
     FunctionResponse {
         #[serde(rename = "functionResponse")]
@@ -529,16 +566,22 @@ fn convert_message_to_gemini(message: &Message) -> Vec<GeminiContent> {
             role: Some("user".to_string()),
             parts: vec![GeminiPart::Text {
                 text: custom.content.clone(),
+                thought_signature: None,
             }],
         }],
         Message::Assistant(assistant) => {
             let mut parts = Vec::new();
+            // Thought signatures are only valid for the provider that issued them; Gemini
+            // rejects function calls in the current turn that come back without theirs.
+            let keep_signatures = assistant.provider == "google";
+            let signature = |sig: Option<&String>| sig.filter(|_| keep_signatures).cloned();
 
             for block in &assistant.content {
                 match block {
                     ContentBlock::Text(t) => {
                         parts.push(GeminiPart::Text {
                             text: t.text.clone(),
+                            thought_signature: signature(t.text_signature.as_ref()),
                         });
                     }
                     ContentBlock::ToolCall(tc) => {
@@ -547,10 +590,13 @@ fn convert_message_to_gemini(message: &Message) -> Vec<GeminiContent> {
                                 name: tc.name.clone(),
                                 args: tc.arguments.clone(),
                             },
+                            thought_signature: signature(tc.thought_signature.as_ref()),
                         });
                     }
-                    ContentBlock::Thinking(_) | ContentBlock::Image(_) => {
-                        // Skip thinking blocks and images in assistant output
+                    ContentBlock::Thinking(_)
+                    | ContentBlock::Image(_)
+                    | ContentBlock::HostedTool(_) => {
+                        // Skip thinking blocks, images, and other providers' hosted tools
                     }
                 }
             }
@@ -597,12 +643,16 @@ fn convert_message_to_gemini(message: &Message) -> Vec<GeminiContent> {
 
 fn convert_user_content_to_parts(content: &UserContent) -> Vec<GeminiPart> {
     match content {
-        UserContent::Text(text) => vec![GeminiPart::Text { text: text.clone() }],
+        UserContent::Text(text) => vec![GeminiPart::Text {
+            text: text.clone(),
+            thought_signature: None,
+        }],
         UserContent::Blocks(blocks) => blocks
             .iter()
             .filter_map(|block| match block {
                 ContentBlock::Text(t) => Some(GeminiPart::Text {
                     text: t.text.clone(),
+                    thought_signature: None,
                 }),
                 ContentBlock::Image(img) => Some(GeminiPart::InlineData {
                     inline_data: GeminiBlob {
@@ -624,6 +674,36 @@ fn convert_tool_to_gemini(tool: &ToolDef) -> GeminiFunctionDeclaration {
     }
 }
 
+// ============================================================================
+// Fuzzing
+// ============================================================================
+
+/// Entry points for `cargo fuzz` (compiled only with `--cfg fuzzing`).
+#[cfg(fuzzing)]
+pub mod fuzz {
+    use super::*;
+
+    /// Drive the stream decoder over raw SSE bytes split into `chunk_len` pieces.
+    pub fn process_sse_bytes(data: &[u8], chunk_len: usize) {
+        let chunks: Vec<std::io::Result<Vec<u8>>> = data
+            .chunks(chunk_len.max(1))
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+        let event_source = SseStream::new(stream::iter(chunks));
+        let mut state = StreamState::new(
+            event_source,
+            "fuzz".to_string(),
+            "google-generative-ai".to_string(),
+            "google".to_string(),
+        );
+        futures::executor::block_on(async {
+            while let Some(Ok(msg)) = state.event_source.next().await {
+                let _ = state.process_event(&msg.data);
+            }
+        });
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -660,6 +740,7 @@ mod tests {
                     "arg": {"type": "string"}
                 }
             }),
+            hosted: None,
         };
 
         let converted = convert_tool_to_gemini(&tool);
@@ -718,6 +799,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stream_captures_function_call_thought_signature() {
+        let events = vec![serde_json::json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [{
+                        "functionCall": { "name": "read", "args": { "path": "a.rs" } },
+                        "thoughtSignature": "sig-1"
+                    }]
+                }
+            }]
+        })];
+
+        let out = collect_events(&events);
+        let Some(StreamEvent::Done { message, .. }) = out.last() else {
+            panic!("expected Done, got {out:?}");
+        };
+        let Some(ContentBlock::ToolCall(call)) = message.content.first() else {
+            panic!("expected a tool call, got {:?}", message.content);
+        };
+        assert_eq!(call.thought_signature.as_deref(), Some("sig-1"));
+    }
+
+    fn assistant_with_signatures(provider: &str) -> Message {
+        Message::Assistant(AssistantMessage {
+            content: vec![
+                ContentBlock::Text(TextContent {
+                    text: "reading".to_string(),
+                    text_signature: Some("sig-text".to_string()),
+                }),
+                ContentBlock::ToolCall(ToolCall {
+                    id: "call_1".to_string(),
+                    name: "read".to_string(),
+                    arguments: serde_json::json!({ "path": "a.rs" }),
+                    thought_signature: Some("sig-call".to_string()),
+                }),
+            ],
+            api: "google-generative".to_string(),
+            provider: provider.to_string(),
+            model: "gemini-test".to_string(),
+            usage: Usage::default(),
+            stop_reason: StopReason::ToolUse,
+            abort_reason: None,
+            error_message: None,
+            timestamp: 0,
+        })
+    }
+
+    #[test]
+    fn test_thought_signatures_are_sent_back() {
+        let converted = convert_message_to_gemini(&assistant_with_signatures("google"));
+        let parts = serde_json::to_value(&converted[0].parts).unwrap();
+        assert_eq!(
+            parts,
+            serde_json::json!([
+                { "text": "reading", "thoughtSignature": "sig-text" },
+                {
+                    "functionCall": { "name": "read", "args": { "path": "a.rs" } },
+                    "thoughtSignature": "sig-call"
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_thought_signatures_from_other_provider_are_dropped() {
+        let converted = convert_message_to_gemini(&assistant_with_signatures("anthropic"));
+        let parts = serde_json::to_value(&converted[0].parts).unwrap();
+        assert!(
+            !parts.to_string().contains("thoughtSignature"),
+            "unexpected signature in {parts}"
+        );
+    }
+
     fn load_fixture(file_name: &str) -> ProviderFixture {
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("tests/fixtures/provider_responses")
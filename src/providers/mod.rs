@@ -4,6 +4,7 @@
 //! for various LLM APIs.
 
 use crate::error::{Error, Result};
+use crate::http::client::Client;
 use crate::models::ModelEntry;
 use crate::provider::Provider;
 use std::sync::Arc;
@@ -14,18 +15,29 @@ pub mod gemini;
 pub mod openai;
 
 pub fn create_provider(entry: &ModelEntry) -> Result<Arc<dyn Provider>> {
+    create_provider_with_client(entry, Client::new())
+}
+
+/// Create a provider that sends requests through `client` (e.g. one with a VCR recorder).
+pub fn create_provider_with_client(
+    entry: &ModelEntry,
+    client: Client,
+) -> Result<Arc<dyn Provider>> {
     match entry.model.provider.as_str() {
         "anthropic" => Ok(Arc::new(
             anthropic::AnthropicProvider::new(entry.model.id.clone())
-                .with_base_url(entry.model.base_url.clone()),
+                .with_base_url(entry.model.base_url.clone())
+                .with_client(client),
         )),
         "openai" => Ok(Arc::new(
             openai::OpenAIProvider::new(entry.model.id.clone())
-                .with_base_url(normalize_openai_base(&entry.model.base_url)),
+                .with_base_url(normalize_openai_base(&entry.model.base_url))
+                .with_client(client),
         )),
         "google" => Ok(Arc::new(
             gemini::GeminiProvider::new(entry.model.id.clone())
-                .with_base_url(entry.model.base_url.clone()),
+                .with_base_url(entry.model.base_url.clone())
+                .with_client(client),
         )),
         "azure-openai" => Err(Error::provider(
             "azure-openai",
@@ -9,10 +9,13 @@
 use crate::error::{Error, Result};
 use crate::http::client::Client;
 use crate::model::{
-    AssistantMessage, ContentBlock, Message, StopReason, StreamEvent, TextContent, ToolCall, Usage,
-    UserContent,
+    AssistantMessage, ContentBlock, Cost, Message, StopReason, StreamEvent, TextContent, ToolCall,
+    Usage, UserContent,
+};
+use crate::provider::{
+    Context, Provider, StreamOptions, ToolDef, append_query_params, http_status_error, local_tools,
+    record_gateway_cost,
 };
-use crate::provider::{Context, Provider, StreamOptions, ToolDef};
 use crate::sse::SseStream;
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -67,10 +70,16 @@ impl OpenAIProvider {
     fn build_request(&self, context: &Context, options: &StreamOptions) -> OpenAIRequest {
         let messages = Self::build_messages(context);
 
-        let tools: Option<Vec<OpenAITool>> = if context.tools.is_empty() {
+        let function_tools = local_tools(&context.tools, "openai");
+        let tools: Option<Vec<OpenAITool>> = if function_tools.is_empty() {
             None
         } else {
-            Some(context.tools.iter().map(convert_tool_to_openai).collect())
+            Some(
+                function_tools
+                    .into_iter()
+                    .map(convert_tool_to_openai)
+                    .collect(),
+            )
         };
 
         OpenAIRequest {
@@ -150,7 +159,7 @@ impl Provider for OpenAIProvider {
         // Build request with headers
         let mut request = self
             .client
-            .post(&self.base_url)
+            .post(&append_query_params(&self.base_url, &options.query_params))
             .header("Content-Type", "application/json")
             .header("Accept", "text/event-stream");
 
@@ -167,13 +176,14 @@ impl Provider for OpenAIProvider {
         let response = Box::pin(request.send()).await?;
         let status = response.status();
         if !(200..300).contains(&status) {
+            let headers = response.headers().to_vec();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::api(format!(
-                "OpenAI API error (HTTP {status}): {body}"
-            )));
+            return Err(http_status_error("OpenAI", status, &headers, &body));
         }
 
         // Create SSE stream for streaming responses.
+        let mut gateway_cost = Cost::default();
+        record_gateway_cost(response.headers(), &mut gateway_cost);
         let event_source = SseStream::new(response.bytes_stream());
 
         // Create stream state
@@ -182,7 +192,11 @@ impl Provider for OpenAIProvider {
         let provider = self.name().to_string();
 
         let stream = stream::unfold(
-            StreamState::new(event_source, model, api, provider),
+            {
+                let mut state = StreamState::new(event_source, model, api, provider);
+                state.partial.usage.cost = gateway_cost;
+                state
+            },
             |mut state| async move {
                 loop {
                     if let Some(event) = state.pending_events.pop_front() {
@@ -259,6 +273,7 @@ where
                 model,
                 usage: Usage::default(),
                 stop_reason: StopReason::Stop,
+                abort_reason: None,
                 error_message: None,
                 timestamp: chrono::Utc::now().timestamp_millis(),
             },
@@ -703,6 +718,40 @@ fn convert_tool_to_openai(tool: &ToolDef) -> OpenAITool {
     }
 }
 
+// ============================================================================
+// Fuzzing
+// ============================================================================
+
+/// Entry points for `cargo fuzz` (compiled only with `--cfg fuzzing`).
+#[cfg(fuzzing)]
+pub mod fuzz {
+    use super::*;
+
+    /// Drive the stream decoder over raw SSE bytes split into `chunk_len` pieces.
+    pub fn process_sse_bytes(data: &[u8], chunk_len: usize) {
+        let chunks: Vec<std::io::Result<Vec<u8>>> = data
+            .chunks(chunk_len.max(1))
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+        let event_source = SseStream::new(stream::iter(chunks));
+        let mut state = StreamState::new(
+            event_source,
+            "fuzz".to_string(),
+            "openai".to_string(),
+            "openai".to_string(),
+        );
+        futures::executor::block_on(async {
+            while let Some(Ok(msg)) = state.event_source.next().await {
+                if msg.data == "[DONE]" {
+                    break;
+                }
+                let _ = state.process_event(&msg.data);
+                state.pending_events.clear();
+            }
+        });
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -739,6 +788,7 @@ mod tests {
                     "arg": {"type": "string"}
                 }
             }),
+            hosted: None,
         };
 
         let converted = convert_tool_to_openai(&tool);
@@ -0,0 +1,459 @@
+//! Deterministic replay of a saved session through the agent loop.
+//!
+//! [`ReplayProvider`] answers each request with the next recorded assistant message, so the
+//! agent makes the same tool calls in the same order without a network or a model. Tools
+//! either run for real ([`ReplayTools::Execute`]), which shows where today's tools disagree
+//! with what was recorded, or return their recorded results ([`ReplayTools::Stub`]), which
+//! re-drives the loop exactly as it happened.
+
+use crate::agent::{Agent, AgentConfig, blocks_text};
+use crate::error::{Error, Result};
+use crate::model::{
+    AssistantMessage, ContentBlock, Message, StopReason, StreamEvent, TextContent,
+    ToolResultMessage, UserContent, UserMessage,
+};
+use crate::provider::{Context, Provider, StreamOptions};
+use crate::session::{Session, SessionEntry, session_message_to_model};
+use crate::tools::{Tool, ToolOutput, ToolRegistry, ToolUpdate};
+use async_trait::async_trait;
+use futures::Stream;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// A provider that streams recorded assistant messages back in order.
+pub struct ReplayProvider {
+    name: String,
+    api: String,
+    model: String,
+    responses: Mutex<VecDeque<AssistantMessage>>,
+}
+
+impl ReplayProvider {
+    pub fn new(responses: impl IntoIterator<Item = AssistantMessage>) -> Self {
+        let responses = responses.into_iter().collect::<VecDeque<_>>();
+        let (name, api, model) = responses.front().map_or_else(
+            || {
+                (
+                    "replay".to_string(),
+                    "replay".to_string(),
+                    "replay".to_string(),
+                )
+            },
+            |first| {
+                (
+                    first.provider.clone(),
+                    first.api.clone(),
+                    first.model.clone(),
+                )
+            },
+        );
+        Self {
+            name,
+            api,
+            model,
+            responses: Mutex::new(responses),
+        }
+    }
+
+    /// Recorded messages not yet replayed.
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().map_or(0, |responses| responses.len())
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn api(&self) -> &str {
+        &self.api
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+
+    async fn stream(
+        &self,
+        _context: &Context,
+        _options: &StreamOptions,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>> {
+        let message = self
+            .responses
+            .lock()
+            .ok()
+            .and_then(|mut responses| responses.pop_front())
+            .ok_or_else(|| Error::session("Replay ran out of recorded assistant messages"))?;
+        let partial = AssistantMessage {
+            content: Vec::new(),
+            ..message.clone()
+        };
+        let last = if matches!(message.stop_reason, StopReason::Error | StopReason::Aborted) {
+            StreamEvent::Error {
+                reason: message.stop_reason,
+                error: message,
+            }
+        } else {
+            StreamEvent::Done {
+                reason: message.stop_reason,
+                message,
+            }
+        };
+        let events = vec![Ok(StreamEvent::Start { partial }), Ok(last)];
+        Ok(Box::pin(futures::stream::iter(events)))
+    }
+}
+
+/// How tool calls are answered during a replay.
+pub enum ReplayTools {
+    /// Run the calls with these tools.
+    Execute(ToolRegistry),
+    /// Answer each call with its recorded result.
+    Stub,
+}
+
+/// A tool whose calls return the results recorded for their call ids.
+struct RecordedTool {
+    name: String,
+    results: Arc<HashMap<String, ToolResultMessage>>,
+}
+
+#[async_trait]
+impl Tool for RecordedTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn label(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "Returns the result recorded in the session being replayed."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        serde_json::json!({ "type": "object" })
+    }
+
+    async fn execute(
+        &self,
+        tool_call_id: &str,
+        _input: serde_json::Value,
+        _on_update: Option<Box<dyn Fn(ToolUpdate) + Send + Sync>>,
+    ) -> Result<ToolOutput> {
+        let recorded = self.results.get(tool_call_id).ok_or_else(|| {
+            Error::tool(&self.name, format!("No recorded result for {tool_call_id}"))
+        })?;
+        Ok(ToolOutput {
+            content: recorded.content.clone(),
+            details: recorded.details.clone(),
+            is_error: recorded.is_error,
+        })
+    }
+}
+
+/// A tool call whose replayed result differs from the recorded one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolDivergence {
+    pub tool_call_id: String,
+    pub tool_name: String,
+    /// Recorded output text; `None` when the session has no result for the call.
+    pub recorded: Option<String>,
+    /// Replayed output text; `None` when the call was not made again.
+    pub replayed: Option<String>,
+    pub recorded_is_error: bool,
+    pub replayed_is_error: bool,
+}
+
+/// What a replay did and where it departed from the recording.
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    /// User prompts re-sent to the agent.
+    pub runs: usize,
+    pub assistant_messages: usize,
+    pub tool_calls: usize,
+    /// Recorded assistant messages the agent never asked for.
+    pub unused_responses: usize,
+    pub divergences: Vec<ToolDivergence>,
+    /// The replayed transcript.
+    pub messages: Vec<Message>,
+}
+
+impl ReplayReport {
+    /// Whether the replay reproduced every recorded tool result and response.
+    pub fn matches_recording(&self) -> bool {
+        self.divergences.is_empty() && self.unused_responses == 0
+    }
+}
+
+/// The messages on `session`'s current branch, as recorded (compactions are not applied).
+pub fn recorded_messages(session: &Session) -> Vec<Message> {
+    session
+        .entries_for_current_path()
+        .into_iter()
+        .filter_map(|entry| match entry {
+            SessionEntry::Message(entry) => session_message_to_model(&entry.message),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replay `session`'s current branch; see [`replay_messages`].
+pub async fn replay_session(session: &Session, tools: ReplayTools) -> Result<ReplayReport> {
+    replay_messages(&recorded_messages(session), tools).await
+}
+
+/// Re-send each user message in `recorded` to an agent whose provider answers with the
+/// recorded assistant messages, then compare the tool results.
+pub async fn replay_messages(recorded: &[Message], tools: ReplayTools) -> Result<ReplayReport> {
+    let responses = recorded
+        .iter()
+        .filter_map(|message| match message {
+            Message::Assistant(assistant) => Some(assistant.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let recorded_results = recorded
+        .iter()
+        .filter_map(|message| match message {
+            Message::ToolResult(result) => Some((result.tool_call_id.clone(), result.clone())),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    let tools = match tools {
+        ReplayTools::Execute(registry) => registry,
+        ReplayTools::Stub => {
+            let results = Arc::new(recorded_results.clone());
+            let mut names = responses
+                .iter()
+                .flat_map(|message| &message.content)
+                .filter_map(|block| match block {
+                    ContentBlock::ToolCall(call) => Some(call.name.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            names.sort();
+            names.dedup();
+            ToolRegistry::from_tools(
+                names
+                    .into_iter()
+                    .map(|name| {
+                        Box::new(RecordedTool {
+                            name,
+                            results: Arc::clone(&results),
+                        }) as Box<dyn Tool>
+                    })
+                    .collect(),
+            )
+        }
+    };
+
+    let provider = Arc::new(ReplayProvider::new(responses));
+    let mut agent = Agent::new(provider.clone(), tools, AgentConfig::default());
+    let mut runs = 0;
+    for message in recorded {
+        let Message::User(user) = message else {
+            continue;
+        };
+        if provider.remaining() == 0 {
+            break;
+        }
+        runs += 1;
+        agent.run_with_content(user_blocks(user), |_| {}).await?;
+    }
+
+    let messages = agent.messages().to_vec();
+    let replayed_results = messages
+        .iter()
+        .filter_map(|message| match message {
+            Message::ToolResult(result) => Some(result),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut divergences = Vec::new();
+    for replayed in &replayed_results {
+        let recorded = recorded_results.get(&replayed.tool_call_id);
+        let replayed_text = blocks_text(&replayed.content);
+        let recorded_text = recorded.map(|result| blocks_text(&result.content));
+        let recorded_is_error = recorded.is_some_and(|result| result.is_error);
+        if recorded_text.as_deref() != Some(replayed_text.as_str())
+            || recorded_is_error != replayed.is_error
+        {
+            divergences.push(ToolDivergence {
+                tool_call_id: replayed.tool_call_id.clone(),
+                tool_name: replayed.tool_name.clone(),
+                recorded: recorded_text,
+                replayed: Some(replayed_text),
+                recorded_is_error,
+                replayed_is_error: replayed.is_error,
+            });
+        }
+    }
+    for (id, recorded) in &recorded_results {
+        if !replayed_results
+            .iter()
+            .any(|replayed| &replayed.tool_call_id == id)
+        {
+            divergences.push(ToolDivergence {
+                tool_call_id: id.clone(),
+                tool_name: recorded.tool_name.clone(),
+                recorded: Some(blocks_text(&recorded.content)),
+                replayed: None,
+                recorded_is_error: recorded.is_error,
+                replayed_is_error: false,
+            });
+        }
+    }
+    divergences.sort_by(|a, b| a.tool_call_id.cmp(&b.tool_call_id));
+
+    Ok(ReplayReport {
+        runs,
+        assistant_messages: messages
+            .iter()
+            .filter(|message| matches!(message, Message::Assistant(_)))
+            .count(),
+        tool_calls: replayed_results.len(),
+        unused_responses: provider.remaining(),
+        divergences,
+        messages,
+    })
+}
+
+fn user_blocks(user: &UserMessage) -> Vec<ContentBlock> {
+    match &user.content {
+        UserContent::Text(text) => vec![ContentBlock::Text(TextContent::new(text.clone()))],
+        UserContent::Blocks(blocks) => blocks.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ToolCall, Usage};
+    use asupersync::runtime::RuntimeBuilder;
+    use serde_json::json;
+
+    fn assistant(content: Vec<ContentBlock>, stop_reason: StopReason) -> Message {
+        Message::Assistant(AssistantMessage {
+            content,
+            api: "test-api".to_string(),
+            provider: "test-provider".to_string(),
+            model: "test-model".to_string(),
+            usage: Usage::default(),
+            stop_reason,
+            abort_reason: None,
+            error_message: None,
+            timestamp: 0,
+        })
+    }
+
+    fn recording() -> Vec<Message> {
+        vec![
+            Message::User(UserMessage {
+                content: UserContent::Text("what is here?".to_string()),
+                timestamp: 0,
+            }),
+            assistant(
+                vec![ContentBlock::ToolCall(ToolCall {
+                    id: "call-1".to_string(),
+                    name: "listing".to_string(),
+                    arguments: json!({}),
+                    thought_signature: None,
+                })],
+                StopReason::ToolUse,
+            ),
+            Message::ToolResult(ToolResultMessage {
+                tool_call_id: "call-1".to_string(),
+                tool_name: "listing".to_string(),
+                content: vec![ContentBlock::Text(TextContent::new("a.txt"))],
+                details: None,
+                is_error: false,
+                timestamp: 0,
+            }),
+            assistant(
+                vec![ContentBlock::Text(TextContent::new("One file: a.txt"))],
+                StopReason::Stop,
+            ),
+        ]
+    }
+
+    struct ListingTool;
+
+    #[async_trait]
+    #[allow(clippy::unnecessary_literal_bound)]
+    impl Tool for ListingTool {
+        fn name(&self) -> &str {
+            "listing"
+        }
+
+        fn label(&self) -> &str {
+            "listing"
+        }
+
+        fn description(&self) -> &str {
+            "List files"
+        }
+
+        fn parameters(&self) -> serde_json::Value {
+            json!({ "type": "object" })
+        }
+
+        async fn execute(
+            &self,
+            _tool_call_id: &str,
+            _input: serde_json::Value,
+            _on_update: Option<Box<dyn Fn(ToolUpdate) + Send + Sync>>,
+        ) -> Result<ToolOutput> {
+            Ok(ToolOutput {
+                content: vec![ContentBlock::Text(TextContent::new("a.txt\nb.txt"))],
+                details: None,
+                is_error: false,
+            })
+        }
+    }
+
+    #[test]
+    fn stubbed_replay_reproduces_the_recording() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let report = runtime
+            .block_on(replay_messages(&recording(), ReplayTools::Stub))
+            .expect("replay");
+        assert_eq!(report.runs, 1);
+        assert_eq!(report.assistant_messages, 2);
+        assert_eq!(report.tool_calls, 1);
+        assert!(report.matches_recording(), "{:?}", report.divergences);
+        assert_eq!(report.messages.len(), 4);
+    }
+
+    #[test]
+    fn executed_replay_reports_changed_tool_output() {
+        let runtime = RuntimeBuilder::current_thread()
+            .build()
+            .expect("runtime build");
+        let tools = ToolRegistry::from_tools(vec![Box::new(ListingTool)]);
+        let report = runtime
+            .block_on(replay_messages(&recording(), ReplayTools::Execute(tools)))
+            .expect("replay");
+        assert_eq!(
+            report.divergences,
+            vec![ToolDivergence {
+                tool_call_id: "call-1".to_string(),
+                tool_name: "listing".to_string(),
+                recorded: Some("a.txt".to_string()),
+                replayed: Some("a.txt\nb.txt".to_string()),
+                recorded_is_error: false,
+                replayed_is_error: false,
+            }]
+        );
+        assert!(!report.matches_recording());
+    }
+}
@@ -0,0 +1,303 @@
+//! Process-wide scheduling of provider requests.
+//!
+//! When the `request_scheduler` setting is present, every provider request (agent turns and
+//! compaction/branch summaries) takes a permit before streaming and holds it until the response
+//! is consumed:
+//! - Concurrency is limited per provider (`max_concurrent`, overridable per provider)
+//! - Waiting requests are served by priority (interactive before background), then in arrival
+//!   order, so batch work cannot starve an interactive session
+
+use crate::config::RequestSchedulerSettings;
+use futures::channel::oneshot;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+
+static GLOBAL: OnceLock<RequestScheduler> = OnceLock::new();
+
+/// Priority of a provider request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+    /// Batch work that can wait: compaction and branch summaries.
+    Background,
+    /// Requests a user is actively waiting on.
+    #[default]
+    Interactive,
+}
+
+/// Shared request queue with per-provider concurrency limits.
+#[derive(Debug, Clone)]
+pub struct RequestScheduler {
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+#[derive(Debug)]
+struct SchedulerState {
+    max_concurrent: usize,
+    provider_limits: HashMap<String, usize>,
+    queues: HashMap<String, ProviderQueue>,
+    next_seq: u64,
+}
+
+#[derive(Debug, Default)]
+struct ProviderQueue {
+    in_flight: usize,
+    waiters: BinaryHeap<Waiter>,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    priority: RequestPriority,
+    seq: u64,
+    sender: oneshot::Sender<RequestPermit>,
+}
+
+// Max-heap order: higher priority first, then lower sequence number (FIFO).
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.seq == other.seq
+    }
+}
+
+impl Eq for Waiter {}
+
+impl RequestScheduler {
+    /// Create a scheduler allowing `max_concurrent` in-flight requests per provider
+    /// (`0` means unlimited), with optional per-provider overrides.
+    pub fn new(max_concurrent: usize, provider_limits: HashMap<String, usize>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SchedulerState {
+                max_concurrent,
+                provider_limits,
+                queues: HashMap::new(),
+                next_seq: 0,
+            })),
+        }
+    }
+
+    pub fn from_settings(settings: &RequestSchedulerSettings) -> Self {
+        let provider_limits = settings
+            .providers
+            .iter()
+            .flatten()
+            .map(|(provider, limit)| (provider.clone(), *limit as usize))
+            .collect();
+        Self::new(
+            settings.max_concurrent.unwrap_or(0) as usize,
+            provider_limits,
+        )
+    }
+
+    /// Install the process-wide scheduler. Returns `false` if one is already installed.
+    pub fn install_global(scheduler: Self) -> bool {
+        GLOBAL.set(scheduler).is_ok()
+    }
+
+    /// The process-wide scheduler, if one was installed.
+    pub fn global() -> Option<&'static Self> {
+        GLOBAL.get()
+    }
+
+    /// Wait for a request slot on `provider`.
+    ///
+    /// The slot is released when the returned permit is dropped. Dropping the future while it
+    /// waits gives up its place in the queue.
+    pub async fn acquire(&self, provider: &str, priority: RequestPriority) -> RequestPermit {
+        let receiver = {
+            let mut state = self.lock();
+            let limit = state.limit_for(provider);
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            let queue = state.queues.entry(provider.to_string()).or_default();
+            if limit == 0 || queue.in_flight < limit {
+                queue.in_flight += 1;
+                return self.permit(provider);
+            }
+            let (sender, receiver) = oneshot::channel();
+            queue.waiters.push(Waiter {
+                priority,
+                seq,
+                sender,
+            });
+            receiver
+        };
+
+        // Waiters are only removed from the queue to be handed a permit, so the sender is
+        // never dropped without sending.
+        match receiver.await {
+            Ok(permit) => permit,
+            Err(oneshot::Canceled) => RequestPermit {
+                scheduler: None,
+                provider: provider.to_string(),
+            },
+        }
+    }
+
+    /// Number of requests waiting for a slot on `provider`.
+    pub fn queued(&self, provider: &str) -> usize {
+        self.lock()
+            .queues
+            .get(provider)
+            .map_or(0, |queue| queue.waiters.len())
+    }
+
+    /// Number of requests holding a slot on `provider`.
+    pub fn in_flight(&self, provider: &str) -> usize {
+        self.lock()
+            .queues
+            .get(provider)
+            .map_or(0, |queue| queue.in_flight)
+    }
+
+    fn permit(&self, provider: &str) -> RequestPermit {
+        RequestPermit {
+            scheduler: Some(self.clone()),
+            provider: provider.to_string(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SchedulerState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Hand the slot to the best waiter, or free it if nobody is waiting.
+    fn release(&self, provider: &str) {
+        loop {
+            let waiter = {
+                let mut state = self.lock();
+                let Some(queue) = state.queues.get_mut(provider) else {
+                    return;
+                };
+                match queue.waiters.pop() {
+                    Some(waiter) => waiter,
+                    None => {
+                        queue.in_flight = queue.in_flight.saturating_sub(1);
+                        return;
+                    }
+                }
+            };
+            // Sent outside the lock. If the waiter gave up, its permit is disarmed and the slot
+            // goes to the next waiter.
+            match waiter.sender.send(self.permit(provider)) {
+                Ok(()) => return,
+                Err(mut permit) => {
+                    permit.scheduler = None;
+                }
+            }
+        }
+    }
+}
+
+impl SchedulerState {
+    fn limit_for(&self, provider: &str) -> usize {
+        self.provider_limits
+            .get(provider)
+            .copied()
+            .unwrap_or(self.max_concurrent)
+    }
+}
+
+/// A request slot; released (or handed to the next waiter) on drop.
+#[derive(Debug)]
+pub struct RequestPermit {
+    scheduler: Option<RequestScheduler>,
+    provider: String,
+}
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release(&self.provider);
+        }
+    }
+}
+
+/// Take a slot from the global scheduler, if one is installed.
+pub async fn acquire_global(provider: &str, priority: RequestPriority) -> Option<RequestPermit> {
+    match RequestScheduler::global() {
+        Some(scheduler) => Some(scheduler.acquire(provider, priority).await),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[test]
+    fn waiters_are_served_by_priority_then_arrival() {
+        let scheduler = RequestScheduler::new(1, HashMap::new());
+        let held = scheduler
+            .acquire("openai", RequestPriority::Background)
+            .now_or_never()
+            .expect("free slot");
+
+        let mut first_background =
+            Box::pin(scheduler.acquire("openai", RequestPriority::Background));
+        assert!((&mut first_background).now_or_never().is_none());
+        let mut second_background =
+            Box::pin(scheduler.acquire("openai", RequestPriority::Background));
+        assert!((&mut second_background).now_or_never().is_none());
+        let mut interactive = Box::pin(scheduler.acquire("openai", RequestPriority::Interactive));
+        assert!((&mut interactive).now_or_never().is_none());
+        assert_eq!(scheduler.queued("openai"), 3);
+
+        // Other providers are not limited by this queue.
+        assert!(
+            scheduler
+                .acquire("anthropic", RequestPriority::Background)
+                .now_or_never()
+                .is_some()
+        );
+
+        drop(held);
+        let interactive = interactive.now_or_never().expect("interactive runs first");
+        assert!((&mut first_background).now_or_never().is_none());
+        drop(interactive);
+        let first = first_background
+            .now_or_never()
+            .expect("then the oldest waiter");
+        assert!((&mut second_background).now_or_never().is_none());
+        drop(first);
+        drop(second_background.now_or_never().expect("last waiter"));
+        assert_eq!(scheduler.in_flight("openai"), 0);
+    }
+
+    #[test]
+    fn cancelled_waiters_do_not_leak_slots() {
+        let scheduler = RequestScheduler::new(0, HashMap::from([("openai".to_string(), 1)]));
+        let held = scheduler
+            .acquire("openai", RequestPriority::Interactive)
+            .now_or_never()
+            .expect("free slot");
+        let mut abandoned = Box::pin(scheduler.acquire("openai", RequestPriority::Interactive));
+        assert!((&mut abandoned).now_or_never().is_none());
+        drop(abandoned);
+
+        drop(held);
+        assert_eq!(scheduler.in_flight("openai"), 0);
+        assert!(
+            scheduler
+                .acquire("openai", RequestPriority::Background)
+                .now_or_never()
+                .is_some()
+        );
+    }
+}
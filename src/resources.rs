@@ -1374,7 +1374,7 @@ pub fn expand_prompt_template(text: &str, templates: &[PromptTemplate]) -> Strin
     text.to_string()
 }
 
-fn expand_skill_command(text: &str, skills: &[Skill]) -> String {
+pub fn expand_skill_command(text: &str, skills: &[Skill]) -> String {
     if !text.starts_with("/skill:") {
         return text.to_string();
     }
@@ -1656,6 +1656,46 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_ask_command_loads_no_extensions() {
+        use clap::Parser as _;
+
+        run_async(async {
+            let temp_dir = tempfile::tempdir().expect("tempdir");
+            let extension_path = temp_dir.path().join("ext.js");
+            fs::write(&extension_path, "export default function() {}").expect("write extension");
+            let settings_dir = temp_dir.path().join(".pi");
+            fs::create_dir_all(&settings_dir).expect("create settings dir");
+            let settings = json!({
+                "extensions": [extension_path.to_string_lossy().to_string()]
+            });
+            fs::write(settings_dir.join("settings.json"), settings.to_string())
+                .expect("write settings");
+
+            let extension_arg = extension_path.to_string_lossy().to_string();
+            let mut cli =
+                crate::cli::Cli::parse_from(["pi", "-e", extension_arg.as_str(), "ask", "why?"]);
+            assert!(cli.apply_ask_command());
+            let options = ResourceCliOptions {
+                no_skills: true,
+                no_prompt_templates: true,
+                no_extensions: cli.no_extensions,
+                no_themes: true,
+                skill_paths: Vec::new(),
+                prompt_paths: Vec::new(),
+                extension_paths: cli.extension.clone(),
+                theme_paths: Vec::new(),
+            };
+
+            let manager = PackageManager::new(temp_dir.path().to_path_buf());
+            let loader =
+                ResourceLoader::load(&manager, temp_dir.path(), &Config::default(), &options)
+                    .await
+                    .expect("load resources");
+            assert!(loader.extensions().is_empty());
+        });
+    }
+
     #[test]
     fn test_extension_paths_deduped_between_settings_and_cli() {
         run_async(async {
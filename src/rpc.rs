@@ -11,17 +11,18 @@
 #![allow(clippy::ignored_unit_patterns)]
 #![allow(clippy::needless_pass_by_value)]
 
-use crate::agent::{AbortHandle, AgentEvent, AgentSession, QueueMode};
+use crate::agent::{AbortHandle, AgentEvent, AgentSession, PauseHandle, QueueMode};
 use crate::auth::AuthStorage;
 use crate::compaction::{
     ResolvedCompactionSettings, compact, compaction_details_to_value, prepare_compaction,
 };
 use crate::config::Config;
-use crate::error::{Error, Result};
+use crate::error::{Error, ErrorCode, Result};
 use crate::error_hints;
 use crate::extensions::{ExtensionEventName, extension_event_from_agent};
 use crate::model::{
-    ContentBlock, ImageContent, Message, StopReason, TextContent, UserContent, UserMessage,
+    AbortReason, ContentBlock, ImageContent, Message, StopReason, TextContent, UserContent,
+    UserMessage,
 };
 use crate::models::ModelEntry;
 use crate::providers;
@@ -252,6 +253,7 @@ pub async fn run(
     let abort_handle: Arc<Mutex<Option<AbortHandle>>> = Arc::new(Mutex::new(None));
     let bash_state: Arc<Mutex<Option<RunningBash>>> = Arc::new(Mutex::new(None));
     let retry_abort = Arc::new(AtomicBool::new(false));
+    let pause_handle: PauseHandle;
 
     {
         use futures::future::BoxFuture;
@@ -287,6 +289,7 @@ pub async fn run(
             Some(Arc::new(steering_fetcher)),
             Some(Arc::new(follow_fetcher)),
         );
+        pause_handle = guard.agent.pause_handle();
     }
 
     while let Ok(line) = in_rx.recv(&cx).await {
@@ -547,6 +550,29 @@ pub async fn run(
                 let _ = out_tx.send(response_ok(id, "abort", None));
             }
 
+            "pause" => {
+                pause_handle.pause();
+                let _ = out_tx.send(response_ok(id, "pause", None));
+            }
+
+            "resume" => {
+                pause_handle.resume();
+                let _ = out_tx.send(response_ok(id, "resume", None));
+            }
+
+            "cancel_tool_call" => {
+                let Some(tool_call_id) = parsed.get("toolCallId").and_then(Value::as_str) else {
+                    let _ = out_tx.send(response_error(
+                        id,
+                        "cancel_tool_call",
+                        "Missing toolCallId".to_string(),
+                    ));
+                    continue;
+                };
+                pause_handle.cancel_tool_call(tool_call_id);
+                let _ = out_tx.send(response_ok(id, "cancel_tool_call", None));
+            }
+
             "get_state" => {
                 let snapshot = {
                     let state = shared_state
@@ -690,6 +716,8 @@ pub async fn run(
                     guard
                         .agent
                         .set_provider(providers::create_provider(&entry)?);
+                    guard.agent.set_context_window(entry.model.context_window);
+                    guard.agent.set_pricing(Some(entry.model.cost.clone()));
                     guard.agent.stream_options_mut().api_key = Some(api_key);
                     guard
                         .agent
@@ -1027,7 +1055,7 @@ pub async fn run(
                                     inner_session.append_message(SessionMessage::BashExecution {
                                         command: command.clone(),
                                         output: result.output.clone(),
-                                        exit_code: result.exit_code,
+                                        exit_code: result.exit_code.unwrap_or(-1),
                                         cancelled: Some(result.cancelled),
                                         truncated: Some(result.truncated),
                                         full_output_path: result.full_output_path.clone(),
@@ -1361,6 +1389,8 @@ async fn run_prompt_with_retry(
     let mut success = false;
     let mut final_error: Option<String> = None;
     let mut final_error_hints: Option<Value> = None;
+    let mut final_error_code = ErrorCode::API_FAILED.code;
+    let mut final_abort_reason: Option<AbortReason> = None;
 
     loop {
         let (abort_handle, abort_signal) = AbortHandle::new();
@@ -1379,6 +1409,7 @@ async fn run_prompt_with_retry(
                 Err(err) => {
                     final_error = Some(format!("session lock failed: {err}"));
                     final_error_hints = None;
+                    final_error_code = ErrorCode::SESSION_FAILED.code;
                     break;
                 }
             };
@@ -1387,12 +1418,20 @@ async fn run_prompt_with_retry(
             let runtime_for_events_handler = runtime_for_events.clone();
             let event_tx = out_tx.clone();
             let event_handler = move |event: AgentEvent| {
-                let serialized = if let AgentEvent::AgentEnd { error, .. } = &event {
-                    json!({
+                let serialized = if let AgentEvent::AgentEnd {
+                    error,
+                    abort_reason,
+                    ..
+                } = &event
+                {
+                    let mut end = json!({
                         "type": "agent_end",
                         "error": error,
-                    })
-                    .to_string()
+                    });
+                    if let Some(reason) = abort_reason {
+                        end["abortReason"] = json!(reason.as_str());
+                    }
+                    end.to_string()
                 } else {
                     serde_json::to_string(&event).unwrap_or_else(|err| {
                         json!({
@@ -1456,9 +1495,12 @@ async fn run_prompt_with_retry(
                         .clone()
                         .or_else(|| Some("Request error".to_string()));
                     final_error_hints = None;
+                    final_abort_reason = message.abort_reason;
                     if message.stop_reason == StopReason::Aborted {
+                        final_error_code = ErrorCode::ABORTED.code;
                         break;
                     }
+                    final_error_code = ErrorCode::API_FAILED.code;
                 } else {
                     success = true;
                     break;
@@ -1467,6 +1509,8 @@ async fn run_prompt_with_retry(
             Err(err) => {
                 final_error = Some(err.to_string());
                 final_error_hints = Some(error_hints_value(&err));
+                final_error_code = err.code();
+                final_abort_reason = err.abort_reason();
             }
         }
 
@@ -1502,6 +1546,7 @@ async fn run_prompt_with_retry(
 
         if retry_abort.load(Ordering::SeqCst) {
             final_error = Some("Retry aborted".to_string());
+            final_abort_reason = Some(AbortReason::User);
             break;
         }
     }
@@ -1522,11 +1567,15 @@ async fn run_prompt_with_retry(
             let mut payload = json!({
                 "type": "agent_end",
                 "messages": [],
-                "error": err
+                "error": err,
+                "errorCode": final_error_code,
             });
             if let Some(hints) = final_error_hints {
                 payload["errorHints"] = hints;
             }
+            if let Some(reason) = final_abort_reason {
+                payload["abortReason"] = json!(reason.as_str());
+            }
             let _ = out_tx.send(event(&payload));
         }
         return;
@@ -1566,6 +1615,7 @@ fn response_error(id: Option<String>, command: &str, error: impl Into<String>) -
         "command": command,
         "success": false,
         "error": error.into(),
+        "errorCode": ErrorCode::RPC_COMMAND_FAILED.code,
     });
     if let Some(id) = id {
         resp["id"] = Value::String(id);
@@ -1579,6 +1629,7 @@ fn response_error_with_hints(id: Option<String>, command: &str, error: &Error) -
         "command": command,
         "success": false,
         "error": error.to_string(),
+        "errorCode": error.code(),
         "errorHints": error_hints_value(error),
     });
     if let Some(id) = id {
@@ -1594,6 +1645,7 @@ fn event(value: &Value) -> String {
 fn error_hints_value(error: &Error) -> Value {
     let hint = error_hints::hints_for_error(error);
     json!({
+        "code": error.code(),
         "summary": hint.summary,
         "hints": hint.hints,
         "contextFields": hint.context_fields,
@@ -1644,6 +1696,27 @@ fn retry_delay_ms(config: &Config, attempt: u32) -> u32 {
     u32::try_from(delay).unwrap_or(u32::MAX)
 }
 
+/// Entry points for `cargo fuzz` (compiled only with `--cfg fuzzing`).
+#[cfg(fuzzing)]
+pub mod fuzz {
+    use super::*;
+
+    /// Run the request-decoding steps of the RPC loop over a single input line.
+    pub fn decode_command_line(line: &str) {
+        let Ok(parsed) = serde_json::from_str::<Value>(line) else {
+            return;
+        };
+        let _ = parsed.get("type").and_then(Value::as_str);
+        let _ = parsed.get("id").and_then(Value::as_str);
+        let _ = parse_prompt_images(parsed.get("images"));
+        let _ = parse_streaming_behavior(parsed.get("streamingBehavior"));
+        let _ = parse_queue_mode(parsed.get("mode").and_then(Value::as_str));
+        if let Some(level) = parsed.get("level").and_then(Value::as_str) {
+            let _ = parse_thinking_level(level);
+        }
+    }
+}
+
 #[cfg(test)]
 mod retry_tests {
     use super::*;
@@ -1708,6 +1781,7 @@ mod retry_tests {
                 model: self.model_id().to_string(),
                 usage: Usage::default(),
                 stop_reason: StopReason::Stop,
+                abort_reason: None,
                 error_message: None,
                 timestamp: 0,
             };
@@ -2098,43 +2172,8 @@ fn session_state(
 }
 
 fn session_stats(session: &crate::session::Session) -> Value {
-    let mut user_messages: u64 = 0;
-    let mut assistant_messages: u64 = 0;
-    let mut tool_results: u64 = 0;
-    let mut tool_calls: u64 = 0;
-
-    let mut total_input: u64 = 0;
-    let mut total_output: u64 = 0;
-    let mut total_cache_read: u64 = 0;
-    let mut total_cache_write: u64 = 0;
-    let mut total_cost: f64 = 0.0;
-
-    let messages = session.to_messages_for_current_path();
-
-    for message in &messages {
-        match message {
-            Message::User(_) | Message::Custom(_) => user_messages += 1,
-            Message::Assistant(message) => {
-                assistant_messages += 1;
-                tool_calls += message
-                    .content
-                    .iter()
-                    .filter(|block| matches!(block, ContentBlock::ToolCall(_)))
-                    .count() as u64;
-                total_input += message.usage.input;
-                total_output += message.usage.output;
-                total_cache_read += message.usage.cache_read;
-                total_cache_write += message.usage.cache_write;
-                total_cost += message.usage.cost.total;
-            }
-            Message::ToolResult(_) => tool_results += 1,
-        }
-    }
-
-    let total_messages = messages.len() as u64;
-
-    let total_tokens = total_input + total_output + total_cache_read + total_cache_write;
-
+    // The current branch always resolves, so this cannot fail.
+    let stats = session.stats(None).unwrap_or_default();
     let mut data = serde_json::Map::new();
     data.insert(
         "sessionFile".to_string(),
@@ -2147,34 +2186,10 @@ fn session_stats(session: &crate::session::Session) -> Value {
         "sessionId".to_string(),
         Value::String(session.header.id.clone()),
     );
-    data.insert(
-        "userMessages".to_string(),
-        Value::Number(user_messages.into()),
-    );
-    data.insert(
-        "assistantMessages".to_string(),
-        Value::Number(assistant_messages.into()),
-    );
-    data.insert("toolCalls".to_string(), Value::Number(tool_calls.into()));
-    data.insert(
-        "toolResults".to_string(),
-        Value::Number(tool_results.into()),
-    );
-    data.insert(
-        "totalMessages".to_string(),
-        Value::Number(total_messages.into()),
-    );
-    data.insert(
-        "tokens".to_string(),
-        json!({
-            "input": total_input,
-            "output": total_output,
-            "cacheRead": total_cache_read,
-            "cacheWrite": total_cache_write,
-            "total": total_tokens,
-        }),
-    );
-    data.insert("cost".to_string(), Value::from(total_cost));
+    if let Ok(Value::Object(mut fields)) = serde_json::to_value(&stats) {
+        fields.remove("leafId");
+        data.extend(fields);
+    }
     Value::Object(data)
 }
 
@@ -2679,6 +2694,10 @@ async fn cycle_model_for_rpc(
     let next_entry = candidates[next_index].clone();
     let provider_impl = crate::providers::create_provider(&next_entry)?;
     guard.agent.set_provider(provider_impl);
+    guard
+        .agent
+        .set_context_window(next_entry.model.context_window);
+    guard.agent.set_pricing(Some(next_entry.model.cost.clone()));
 
     let api_key = resolve_model_api_key(&options.auth, &next_entry).ok_or_else(|| {
         Error::auth(format!(
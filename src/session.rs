@@ -12,7 +12,9 @@ use crate::model::{
     AssistantMessage, ContentBlock, Message, TextContent, ToolResultMessage, UserContent,
     UserMessage,
 };
+use crate::redaction::{RedactionDetails, Redactor};
 use crate::session_index::SessionIndex;
+use crate::session_store::REMOTE_SESSION_PREFIX;
 use crate::tui::PiConsole;
 use asupersync::Cx;
 use asupersync::channel::oneshot;
@@ -28,11 +30,89 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Current session file format version.
 pub const SESSION_VERSION: u8 = 3;
 
+/// One step of the session format upgrade path, from version `from` to `from + 1`.
+///
+/// Steps rewrite the raw JSON of the header and of each entry before they are deserialized,
+/// so a step can read fields the current types no longer know about.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionMigration {
+    pub from: u8,
+    pub header: fn(&mut Value),
+    pub entry: fn(&mut Value),
+}
+
+/// Upgrades applied when a session file is opened, oldest first.
+///
+/// A format change adds its step here and bumps [`SESSION_VERSION`]. Versions without a step
+/// are read as they are: versions 1 through 3 only added fields and aliases.
+pub const SESSION_MIGRATIONS: &[SessionMigration] = &[];
+
+/// Version recorded in a raw session header; files from before the field existed are v1.
+fn session_file_version(header: &Value) -> Result<u8> {
+    let Some(version) = header.get("version").and_then(Value::as_u64) else {
+        return Ok(1);
+    };
+    u8::try_from(version).map_err(|_| newer_session_error(version))
+}
+
+fn newer_session_error(version: impl std::fmt::Display) -> Error {
+    Error::session(format!(
+        "Session file version {version} is newer than this version of pi supports \
+         ({SESSION_VERSION}); upgrade pi to open it"
+    ))
+}
+
+/// The steps of `migrations` that take a file at `version` up to `target`, in order.
+///
+/// Errors when the file is newer than `target`.
+pub fn pending_migrations(
+    version: u8,
+    migrations: &[SessionMigration],
+    target: u8,
+) -> Result<Vec<SessionMigration>> {
+    if version > target {
+        return Err(newer_session_error(version));
+    }
+    let mut steps = migrations
+        .iter()
+        .filter(|step| (version..target).contains(&step.from))
+        .copied()
+        .collect::<Vec<_>>();
+    steps.sort_by_key(|step| step.from);
+    Ok(steps)
+}
+
+/// Run `steps` over a raw header and entries, then stamp the header with `target`.
+///
+/// Returns the version the file was migrated from, or `None` when there was nothing to do.
+pub fn migrate_session_values(
+    header: &mut Value,
+    entries: &mut [Value],
+    migrations: &[SessionMigration],
+    target: u8,
+) -> Result<Option<u8>> {
+    let version = session_file_version(header)?;
+    let steps = pending_migrations(version, migrations, target)?;
+    if steps.is_empty() {
+        return Ok(None);
+    }
+    for step in &steps {
+        (step.header)(header);
+        for entry in entries.iter_mut() {
+            (step.entry)(entry);
+        }
+    }
+    if let Value::Object(fields) = header {
+        fields.insert("version".to_string(), Value::from(target));
+    }
+    Ok(Some(version))
+}
+
 /// Handle to a thread-safe shared session.
 #[derive(Clone, Debug)]
 pub struct SessionHandle(pub Arc<Mutex<Session>>);
@@ -141,6 +221,41 @@ impl ExtensionSession for SessionHandle {
     }
 }
 
+/// Custom entry type recording a named checkpoint saved with `/save`.
+pub const CHECKPOINT_CUSTOM_TYPE: &str = "checkpoint";
+
+/// Custom entry type for a user note added with `/note`: shown in exports and the timeline,
+/// never sent to the model.
+pub const NOTE_CUSTOM_TYPE: &str = "note";
+
+/// A user note from a [`NOTE_CUSTOM_TYPE`] entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionNote {
+    pub id: String,
+    pub text: String,
+    pub timestamp: String,
+}
+
+impl CustomEntry {
+    /// The note text, when this is a [`NOTE_CUSTOM_TYPE`] entry.
+    pub fn note_text(&self) -> Option<&str> {
+        if self.custom_type != NOTE_CUSTOM_TYPE {
+            return None;
+        }
+        self.data.as_ref()?.get("text")?.as_str()
+    }
+}
+
+/// A named checkpoint: a labeled entry plus the worktree snapshot taken with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub name: String,
+    pub target_id: String,
+    pub timestamp: String,
+    /// Opaque worktree snapshot (for the TUI: `{"head": ..., "stash": ...}`).
+    pub worktree: Option<Value>,
+}
+
 /// Default base URL for the Pi session share viewer.
 pub const DEFAULT_SHARE_VIEWER_URL: &str = "https://buildwithpi.ai/session/";
 
@@ -167,18 +282,25 @@ pub fn get_share_viewer_url(gist_id: &str) -> String {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SessionStoreKind {
     Jsonl,
+    /// JSONL written as a single zstd frame to a `.jsonl.zst` file.
+    CompressedJsonl,
     #[cfg(feature = "sqlite-sessions")]
     Sqlite,
 }
 
 impl SessionStoreKind {
     fn from_config(config: &Config) -> Self {
+        let jsonl = if config.compress_sessions.unwrap_or(false) {
+            Self::CompressedJsonl
+        } else {
+            Self::Jsonl
+        };
         let Some(value) = config.session_store.as_deref() else {
-            return Self::Jsonl;
+            return jsonl;
         };
 
         if value.eq_ignore_ascii_case("jsonl") {
-            return Self::Jsonl;
+            return jsonl;
         }
 
         if value.eq_ignore_ascii_case("sqlite") {
@@ -203,6 +325,7 @@ impl SessionStoreKind {
     const fn extension(self) -> &'static str {
         match self {
             Self::Jsonl => "jsonl",
+            Self::CompressedJsonl => "jsonl.zst",
             #[cfg(feature = "sqlite-sessions")]
             Self::Sqlite => "sqlite",
         }
@@ -227,6 +350,12 @@ pub struct Session {
     /// Base directory for session storage (optional override)
     pub session_dir: Option<PathBuf>,
     store_kind: SessionStoreKind,
+    /// Whether new entries are kept in memory only (`/incognito`).
+    incognito: bool,
+    /// IDs of entries appended while incognito; never written to disk or the index.
+    ephemeral_ids: HashSet<String>,
+    /// Corrupted lines skipped when the file was opened.
+    skipped_entries: Vec<SessionOpenSkippedEntry>,
 }
 
 /// Result of planning a `/fork` operation from a specific user message.
@@ -244,21 +373,40 @@ pub struct ForkPlan {
     pub leaf_id: Option<String>,
     /// Text of the selected user message (for editor pre-fill).
     pub selected_text: String,
+    /// IDs among `entries` that were recorded while incognito and must stay unpersisted.
+    pub ephemeral_ids: HashSet<String>,
 }
 
 /// Diagnostics captured while opening a session file.
 #[derive(Debug, Clone, Default)]
 pub struct SessionOpenDiagnostics {
     pub skipped_entries: Vec<SessionOpenSkippedEntry>,
+    /// Format version the file was upgraded from while opening, if it was.
+    pub migrated_from: Option<u8>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SessionOpenSkippedEntry {
     /// 1-based line number in the session file.
     pub line_number: usize,
+    /// Offset of the line's first byte in the (decompressed) file.
+    pub byte_offset: usize,
     pub error: String,
 }
 
+/// What [`Session::repair_jsonl`] did to a session file.
+#[derive(Debug, Clone, Default)]
+pub struct SessionRepair {
+    /// The corrupted lines found.
+    pub skipped_entries: Vec<SessionOpenSkippedEntry>,
+    /// Entries recovered from corrupted lines, e.g. two entries run together on one line.
+    pub salvaged: usize,
+    /// Corrupted lines nothing could be recovered from.
+    pub dropped: usize,
+    /// Copy of the original file; `None` when the file needed no repair.
+    pub backup: Option<PathBuf>,
+}
+
 impl SessionOpenDiagnostics {
     fn warning_lines(&self) -> Vec<String> {
         let mut lines = Vec::new();
@@ -466,6 +614,9 @@ impl Session {
             leaf_id: None,
             session_dir: None,
             store_kind: SessionStoreKind::Jsonl,
+            incognito: false,
+            ephemeral_ids: HashSet::new(),
+            skipped_entries: Vec::new(),
         }
     }
 
@@ -491,6 +642,9 @@ impl Session {
             leaf_id: None,
             session_dir,
             store_kind,
+            incognito: false,
+            ephemeral_ids: HashSet::new(),
+            skipped_entries: Vec::new(),
         }
     }
 
@@ -500,11 +654,17 @@ impl Session {
         for warning in diagnostics.warning_lines() {
             eprintln!("{warning}");
         }
+        if !diagnostics.skipped_entries.is_empty() {
+            eprintln!("Run `pi sessions repair {path}` to recover what can be recovered");
+        }
         Ok(session)
     }
 
     /// Open an existing session and return diagnostics about any recovered corruption.
     pub async fn open_with_diagnostics(path: &str) -> Result<(Self, SessionOpenDiagnostics)> {
+        if let Some(key) = path.strip_prefix(REMOTE_SESSION_PREFIX) {
+            return Self::open_remote(key).await;
+        }
         let path = PathBuf::from(path);
         if !path.exists() {
             return Err(crate::Error::SessionNotFound {
@@ -530,31 +690,89 @@ impl Session {
         Self::open_jsonl_with_diagnostics(&path).await
     }
 
+    /// Download `key` from the configured remote store into the sessions directory for the
+    /// current working directory. Later saves write that local copy and upload it back.
+    async fn open_remote(key: &str) -> Result<(Self, SessionOpenDiagnostics)> {
+        let sync = crate::session_store::remote()
+            .ok_or_else(|| Error::config("`remote:` sessions need the remote_sessions setting"))?;
+        let key_path = Path::new(key);
+        if !(key_path.extension().is_some_and(|ext| ext == "jsonl")
+            || is_compressed_session_path(key_path))
+        {
+            return Err(Error::validation(format!(
+                "Not a JSONL session file name: {key}"
+            )));
+        }
+        let bytes = sync.download(key).await?;
+        let content = decode_jsonl_bytes(bytes)?;
+        let (mut session, diagnostics) = Self::from_jsonl_str(&content)?;
+
+        let cwd = std::env::current_dir()?;
+        let local_dir = Config::sessions_dir().join(encode_cwd(&cwd));
+        asupersync::fs::create_dir_all(&local_dir).await?;
+        session.path = Some(local_dir.join(key));
+        if is_compressed_session_path(Path::new(key)) {
+            session.store_kind = SessionStoreKind::CompressedJsonl;
+        }
+        Ok((session, diagnostics))
+    }
+
     async fn open_jsonl_with_diagnostics(path: &Path) -> Result<(Self, SessionOpenDiagnostics)> {
-        let content = asupersync::fs::read_to_string(path).await?;
-        let mut lines = content.lines();
+        let (store, key) = crate::session_store::local_store(path)?;
+        let bytes = store.read(&key).await?;
+        let content = decode_jsonl_bytes(bytes)?;
+        let (mut session, diagnostics) = Self::from_jsonl_str(&content)?;
+        session.path = Some(path.to_path_buf());
+        Ok((session, diagnostics))
+    }
+
+    /// Parse a JSONL session from memory (header line followed by entries).
+    ///
+    /// Corrupted entry lines are skipped and reported in the diagnostics.
+    pub fn from_jsonl_str(content: &str) -> Result<(Self, SessionOpenDiagnostics)> {
+        let mut lines = jsonl_lines(content);
 
         // Parse header (first line)
-        let header: SessionHeader = lines
+        let mut header_value: Value = lines
             .next()
-            .map(serde_json::from_str)
+            .map(|(_, line)| serde_json::from_str(line))
             .transpose()?
             .ok_or_else(|| crate::Error::session("Empty session file"))?;
+        let version = session_file_version(&header_value)?;
+        let steps = pending_migrations(version, SESSION_MIGRATIONS, SESSION_VERSION)?;
 
-        // Parse entries
+        // Parse entries, upgrading their raw JSON first when the file is from an older format
         let mut entries = Vec::new();
         let mut diagnostics = SessionOpenDiagnostics::default();
-        for (line_num, line) in lines.enumerate() {
-            match serde_json::from_str::<SessionEntry>(line) {
+        for (line_num, (byte_offset, line)) in lines.enumerate() {
+            let parsed = if steps.is_empty() {
+                serde_json::from_str::<SessionEntry>(line)
+            } else {
+                serde_json::from_str::<Value>(line).and_then(|mut value| {
+                    for step in &steps {
+                        (step.entry)(&mut value);
+                    }
+                    serde_json::from_value::<SessionEntry>(value)
+                })
+            };
+            match parsed {
                 Ok(entry) => entries.push(entry),
                 Err(e) => {
                     diagnostics.skipped_entries.push(SessionOpenSkippedEntry {
                         line_number: line_num + 2, // +2 for 1-based indexing and header line
+                        byte_offset,
                         error: e.to_string(),
                     });
                 }
             }
         }
+        diagnostics.migrated_from = migrate_session_values(
+            &mut header_value,
+            &mut [],
+            SESSION_MIGRATIONS,
+            SESSION_VERSION,
+        )?;
+        let header: SessionHeader = serde_json::from_value(header_value)?;
 
         ensure_entry_ids(&mut entries);
 
@@ -564,15 +782,72 @@ impl Session {
             Self {
                 header,
                 entries,
-                path: Some(path.to_path_buf()),
+                path: None,
                 leaf_id,
                 session_dir: None,
                 store_kind: SessionStoreKind::Jsonl,
+                incognito: false,
+                ephemeral_ids: HashSet::new(),
+                skipped_entries: diagnostics.skipped_entries.clone(),
             },
             diagnostics,
         ))
     }
 
+    /// Corrupted lines skipped when this session was opened.
+    pub fn skipped_entries(&self) -> &[SessionOpenSkippedEntry] {
+        &self.skipped_entries
+    }
+
+    /// Rewrite a JSONL session file without its corrupted lines, keeping every entry that can
+    /// be recovered from them. The original is copied to `<file>.bak` first.
+    pub fn repair_jsonl(path: &Path) -> Result<SessionRepair> {
+        let content = decode_jsonl_bytes(std::fs::read(path)?)?;
+        let (_, diagnostics) = Self::from_jsonl_str(&content)?;
+        if diagnostics.skipped_entries.is_empty() {
+            return Ok(SessionRepair::default());
+        }
+
+        let mut repair = SessionRepair::default();
+        let mut repaired = String::with_capacity(content.len());
+        let mut skipped = diagnostics.skipped_entries.iter().peekable();
+        for (index, (_, line)) in jsonl_lines(&content).enumerate() {
+            if skipped
+                .next_if(|entry| entry.line_number == index + 1)
+                .is_none()
+            {
+                repaired.push_str(line);
+                repaired.push('\n');
+                continue;
+            }
+            let salvaged = salvage_entries(line);
+            if salvaged.is_empty() {
+                repair.dropped += 1;
+            }
+            repair.salvaged += salvaged.len();
+            for entry in salvaged {
+                repaired.push_str(&serde_json::to_string(&entry)?);
+                repaired.push('\n');
+            }
+        }
+
+        let backup = backup_path(path);
+        std::fs::copy(path, &backup)?;
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        let temp_file = tempfile::NamedTempFile::new_in(parent)?;
+        std::fs::write(
+            temp_file.path(),
+            encode_jsonl_bytes(repaired, is_compressed_session_path(path))?,
+        )?;
+        temp_file
+            .persist(path)
+            .map_err(|e| crate::Error::Io(Box::new(e.error)))?;
+
+        repair.skipped_entries = diagnostics.skipped_entries;
+        repair.backup = Some(backup);
+        Ok(repair)
+    }
+
     #[cfg(feature = "sqlite-sessions")]
     async fn open_sqlite(path: &Path) -> Result<Self> {
         let path = path.to_path_buf();
@@ -593,6 +868,9 @@ impl Session {
                     leaf_id,
                     session_dir: None,
                     store_kind: SessionStoreKind::Sqlite,
+                    incognito: false,
+                    ephemeral_ids: HashSet::new(),
+                    skipped_entries: Vec::new(),
                 })
             })();
 
@@ -681,6 +959,9 @@ impl Session {
             .and_then(|path| path.extension().and_then(|ext| ext.to_str()))
         {
             Some("jsonl") => SessionStoreKind::Jsonl,
+            Some("zst") if self.path.as_deref().is_some_and(is_compressed_session_path) => {
+                SessionStoreKind::CompressedJsonl
+            }
             Some("sqlite") => {
                 #[cfg(feature = "sqlite-sessions")]
                 {
@@ -719,7 +1000,8 @@ impl Session {
             self.path = Some(project_session_dir.join(filename));
         }
 
-        let session_clone = self.clone();
+        let mut session_clone = self.clone();
+        session_clone.entries = self.persistable_entries();
         let session_dir_clone = self.session_dir.clone();
         let path = self.path.clone().unwrap();
         let path_clone = path.clone();
@@ -727,27 +1009,20 @@ impl Session {
         let (tx, rx) = oneshot::channel();
 
         match store_kind {
-            SessionStoreKind::Jsonl => {
-                let mut content = String::new();
-
-                // Write header
-                content.push_str(&serde_json::to_string(&session_clone.header)?);
-                content.push('\n');
+            SessionStoreKind::Jsonl | SessionStoreKind::CompressedJsonl => {
+                let content = jsonl_content(&session_clone.header, &session_clone.entries)?;
+                let always_compress = store_kind == SessionStoreKind::CompressedJsonl;
 
-                // Write entries
-                for entry in &session_clone.entries {
-                    content.push_str(&serde_json::to_string(entry)?);
-                    content.push('\n');
-                }
+                let upload = crate::session_store::remote().is_some();
+                let (store, key) = crate::session_store::local_store(&path)?;
 
                 thread::spawn(move || {
-                    let res = || -> Result<()> {
-                        let parent = path_clone.parent().unwrap_or_else(|| Path::new("."));
-                        let temp_file = tempfile::NamedTempFile::new_in(parent)?;
-                        std::fs::write(temp_file.path(), content)?;
-                        temp_file
-                            .persist(&path_clone)
-                            .map_err(|e| crate::Error::Io(Box::new(e.error)))?;
+                    let res = || -> Result<Option<Vec<u8>>> {
+                        let lock_file = open_session_lock_file(&path_clone)?;
+                        let _lock = lock_session_file(&lock_file, &path_clone, SAVE_LOCK_TIMEOUT)?;
+                        let bytes = encode_jsonl_bytes(content, always_compress)?;
+                        let upload = upload.then(|| bytes.clone());
+                        futures::executor::block_on(store.write(&key, bytes))?;
 
                         let sessions_root = session_dir_clone.unwrap_or_else(Config::sessions_dir);
                         if let Err(err) = SessionIndex::for_sessions_root(&sessions_root)
@@ -755,7 +1030,7 @@ impl Session {
                         {
                             tracing::warn!("Failed to update session index: {err}");
                         }
-                        Ok(())
+                        Ok(upload)
                     }();
                     let cx = AgentCx::for_request();
                     let _ = tx.send(cx.cx(), res);
@@ -764,7 +1039,9 @@ impl Session {
             #[cfg(feature = "sqlite-sessions")]
             SessionStoreKind::Sqlite => {
                 thread::spawn(move || {
-                    let res = || -> Result<()> {
+                    let res = || -> Result<Option<Vec<u8>>> {
+                        let lock_file = open_session_lock_file(&path_clone)?;
+                        let _lock = lock_session_file(&lock_file, &path_clone, SAVE_LOCK_TIMEOUT)?;
                         futures::executor::block_on(async {
                             crate::session_sqlite::save_session(
                                 &path_clone,
@@ -780,7 +1057,8 @@ impl Session {
                         {
                             tracing::warn!("Failed to update session index: {err}");
                         }
-                        Ok(())
+                        // SQLite sessions stay local.
+                        Ok(None)
                     }();
 
                     let cx = AgentCx::for_request();
@@ -790,19 +1068,173 @@ impl Session {
         }
 
         let cx = AgentCx::for_request();
-        rx.recv(cx.cx())
+        let upload = rx
+            .recv(cx.cx())
             .await
             .map_err(|_| crate::Error::session("Save task cancelled"))??;
+
+        // Uploaded in the background so a slow server never holds up the save.
+        if let (Some(bytes), Some(sync), Some(key)) = (
+            upload,
+            crate::session_store::remote(),
+            crate::session_store::remote_key(&path),
+        ) {
+            sync.upload(key, bytes);
+        }
         Ok(())
     }
 
+    /// The JSONL text `save` would write, without touching disk.
+    pub fn to_jsonl(&self) -> Result<String> {
+        jsonl_content(&self.header, &self.persistable_entries())
+    }
+
+    fn push_entry(&mut self, entry: SessionEntry) {
+        if self.incognito {
+            if let Some(id) = entry.base_id() {
+                self.ephemeral_ids.insert(id.clone());
+            }
+        }
+        self.entries.push(entry);
+    }
+
+    /// Whether entries appended from now on stay in memory only.
+    pub const fn is_incognito(&self) -> bool {
+        self.incognito
+    }
+
+    /// Toggle incognito mode.
+    ///
+    /// Entries appended while incognito remain part of the conversation (the agent still sees
+    /// them) but are dropped from every save and from the session index. Turning incognito off
+    /// does not retroactively persist them.
+    pub fn set_incognito(&mut self, enabled: bool) {
+        self.incognito = enabled;
+    }
+
+    /// Treat existing entries as in-memory only (used when copying them into a fork).
+    pub fn mark_ephemeral(&mut self, ids: impl IntoIterator<Item = String>) {
+        self.ephemeral_ids.extend(ids);
+    }
+
+    /// Number of in-memory-only entries in this session.
+    pub fn ephemeral_entry_count(&self) -> usize {
+        self.ephemeral_ids.len()
+    }
+
+    /// Whether the entry `id` is in memory only.
+    pub fn is_ephemeral(&self, id: &str) -> bool {
+        self.ephemeral_ids.contains(id)
+    }
+
+    /// Entries as they are written to disk: ephemeral entries are removed, their persisted
+    /// descendants are re-parented onto the nearest persisted ancestor, and messages are
+    /// scrubbed by the installed [`Redactor`], if any.
+    pub fn persistable_entries(&self) -> Vec<SessionEntry> {
+        let mut entries = self.non_ephemeral_entries();
+        if let Some(redactor) = Redactor::global() {
+            for entry in &mut entries {
+                redactor.redact_entry(entry);
+            }
+        }
+        entries
+    }
+
+    fn non_ephemeral_entries(&self) -> Vec<SessionEntry> {
+        if self.ephemeral_ids.is_empty() {
+            return self.entries.clone();
+        }
+
+        // Labels and branch summaries that point into an incognito stretch are dropped with it;
+        // a summary of an incognito branch would leak what was never meant to be saved.
+        let mut skipped: HashSet<&str> = self.ephemeral_ids.iter().map(String::as_str).collect();
+        for entry in &self.entries {
+            let points_at_skipped = match entry {
+                SessionEntry::Label(label) => self.ephemeral_ids.contains(&label.target_id),
+                SessionEntry::BranchSummary(summary) => {
+                    self.ephemeral_ids.contains(&summary.from_id)
+                }
+                _ => false,
+            };
+            if points_at_skipped && let Some(id) = entry.base_id() {
+                skipped.insert(id.as_str());
+            }
+        }
+
+        let parent_by_id: HashMap<&str, Option<&str>> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .base_id()
+                    .map(|id| (id.as_str(), entry.base().parent_id.as_deref()))
+            })
+            .collect();
+        let persisted_ancestor = |mut parent: Option<&str>| -> Option<String> {
+            // Bounded by the entry count so a corrupted parent cycle cannot spin forever.
+            for _ in 0..=self.entries.len() {
+                match parent {
+                    Some(id) if skipped.contains(id) => {
+                        parent = parent_by_id.get(id).copied().flatten();
+                    }
+                    _ => break,
+                }
+            }
+            parent.map(str::to_string)
+        };
+        // The saved entry closest after `first_kept` on the way to the compaction, or the
+        // compaction itself, so reloading still keeps everything after the cut.
+        let persisted_first_kept = |compaction_id: &str, first_kept: &str| -> String {
+            let mut kept = None;
+            let mut current = parent_by_id.get(compaction_id).copied().flatten();
+            for _ in 0..=self.entries.len() {
+                match current {
+                    Some(id) if id != first_kept => {
+                        if !skipped.contains(id) {
+                            kept = Some(id);
+                        }
+                        current = parent_by_id.get(id).copied().flatten();
+                    }
+                    _ => break,
+                }
+            }
+            kept.map_or_else(|| compaction_id.to_string(), str::to_string)
+        };
+
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry
+                    .base_id()
+                    .is_none_or(|id| !skipped.contains(id.as_str()))
+            })
+            .map(|entry| {
+                let mut entry = entry.clone();
+                let parent = persisted_ancestor(entry.base().parent_id.as_deref());
+                entry.base_mut().parent_id = parent;
+                if let SessionEntry::Compaction(compaction) = &mut entry
+                    && skipped.contains(compaction.first_kept_entry_id.as_str())
+                    && let Some(id) = compaction.base.id.clone()
+                {
+                    compaction.first_kept_entry_id =
+                        persisted_first_kept(&id, &compaction.first_kept_entry_id);
+                }
+                entry
+            })
+            .collect()
+    }
+
     /// Append a session message entry.
     pub fn append_message(&mut self, message: SessionMessage) -> String {
         let id = self.next_entry_id();
         let base = EntryBase::new(self.leaf_id.clone(), id.clone());
-        let entry = SessionEntry::Message(MessageEntry { base, message });
+        let entry = SessionEntry::Message(MessageEntry {
+            base,
+            message,
+            redaction: None,
+        });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
         id
     }
 
@@ -820,7 +1252,7 @@ impl Session {
             model_id,
         });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
         id
     }
 
@@ -832,7 +1264,7 @@ impl Session {
             thinking_level,
         });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
         id
     }
 
@@ -841,7 +1273,7 @@ impl Session {
         let base = EntryBase::new(self.leaf_id.clone(), id.clone());
         let entry = SessionEntry::SessionInfo(SessionInfoEntry { base, name });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
         id
     }
 
@@ -859,10 +1291,20 @@ impl Session {
             data,
         });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
         id
     }
 
+    /// Data of the most recent custom entry of `custom_type`, on any branch.
+    pub fn latest_custom_data(&self, custom_type: &str) -> Option<&Value> {
+        self.entries.iter().rev().find_map(|entry| match entry {
+            SessionEntry::Custom(custom) if custom.custom_type == custom_type => {
+                custom.data.as_ref()
+            }
+            _ => None,
+        })
+    }
+
     pub fn append_bash_execution(
         &mut self,
         command: String,
@@ -886,9 +1328,10 @@ impl Session {
                 timestamp: Some(chrono::Utc::now().timestamp_millis()),
                 extra: HashMap::new(),
             },
+            redaction: None,
         });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
         id
     }
 
@@ -916,6 +1359,14 @@ impl Session {
         details: Option<Value>,
         from_hook: Option<bool>,
     ) -> String {
+        // A summary of incognito entries must not reach disk even after incognito is off.
+        let summarizes_ephemeral = !self.ephemeral_ids.is_empty()
+            && self
+                .entries_for_current_path()
+                .into_iter()
+                .filter_map(SessionEntry::base_id)
+                .take_while(|id| **id != first_kept_entry_id)
+                .any(|id| self.ephemeral_ids.contains(id));
         let id = self.next_entry_id();
         let base = EntryBase::new(self.leaf_id.clone(), id.clone());
         let entry = SessionEntry::Compaction(CompactionEntry {
@@ -927,7 +1378,10 @@ impl Session {
             from_hook,
         });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
+        if summarizes_ephemeral {
+            self.ephemeral_ids.insert(id.clone());
+        }
         id
     }
 
@@ -948,7 +1402,7 @@ impl Session {
             from_hook,
         });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
         id
     }
 
@@ -1045,6 +1499,14 @@ impl Session {
                     }
                 }
                 SessionEntry::Custom(custom) => {
+                    if let Some(text) = custom.note_text() {
+                        let _ = write!(
+                            html,
+                            "<div class=\"msg system\"><div class=\"role\">Note</div><div class=\"note\">{}</div></div>",
+                            escape_html(text)
+                        );
+                        continue;
+                    }
                     let _ = write!(
                         html,
                         "<div class=\"msg system\"><div class=\"role\">{}</div></div>",
@@ -1119,10 +1581,18 @@ impl Session {
             Vec::new()
         };
 
+        let ephemeral_ids = entries
+            .iter()
+            .filter_map(SessionEntry::base_id)
+            .filter(|id| self.ephemeral_ids.contains(*id))
+            .cloned()
+            .collect();
+
         Ok(ForkPlan {
             entries,
             leaf_id,
             selected_text,
+            ephemeral_ids,
         })
     }
 
@@ -1165,9 +1635,14 @@ impl Session {
     pub fn get_path_to_entry(&self, entry_id: &str) -> Vec<String> {
         let parent_map = self.build_parent_map();
         let mut path = Vec::new();
+        let mut seen = HashSet::new();
         let mut current = Some(entry_id.to_string());
 
+        // Stop at the first repeated id so a corrupted (cyclic) parent chain can't hang.
         while let Some(id) = current {
+            if !seen.insert(id.clone()) {
+                break;
+            }
             path.push(id.clone());
             current = parent_map.get(&id).and_then(Clone::clone);
         }
@@ -1267,82 +1742,93 @@ impl Session {
             .collect()
     }
 
+    /// Most recent model change on the current path, as `(provider, model_id)`.
+    pub fn model_for_current_path(&self) -> Option<(String, String)> {
+        self.entries_for_current_path()
+            .into_iter()
+            .rev()
+            .find_map(|entry| match entry {
+                SessionEntry::ModelChange(change) => {
+                    Some((change.provider.clone(), change.model_id.clone()))
+                }
+                _ => None,
+            })
+    }
+
+    /// Most recent thinking level change on the current path.
+    pub fn thinking_level_for_current_path(&self) -> Option<String> {
+        self.entries_for_current_path()
+            .into_iter()
+            .rev()
+            .find_map(|entry| match entry {
+                SessionEntry::ThinkingLevelChange(change) => Some(change.thinking_level.clone()),
+                _ => None,
+            })
+    }
+
     /// Convert session entries along the current path to model messages.
     /// This follows parent_id links from leaf_id back to root.
     pub fn to_messages_for_current_path(&self) -> Vec<Message> {
-        let path_entries = self.entries_for_current_path();
+        self.messages_with_entry_ids_for_current_path()
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect()
+    }
 
-        // If the current path contains a compaction entry, omit older messages
+    /// [`Self::to_messages_for_current_path`], each message paired with the id of the entry
+    /// it came from (`None` for the compaction summary).
+    pub fn messages_with_entry_ids_for_current_path(&self) -> Vec<(Option<String>, Message)> {
+        Self::messages_with_entry_ids_for_path(&self.entries_for_current_path())
+    }
+
+    /// Context messages for the root-to-leaf `path_entries` of any branch, applying its last
+    /// compaction.
+    pub(crate) fn messages_with_entry_ids_for_path(
+        path_entries: &[&SessionEntry],
+    ) -> Vec<(Option<String>, Message)> {
+        // If the path contains a compaction entry, omit older messages
         // and insert the compaction summary before the kept region.
         let last_compaction = path_entries.iter().rev().find_map(|entry| match entry {
             SessionEntry::Compaction(compaction) => Some(compaction),
             _ => None,
         });
 
+        let mut messages = Vec::new();
+        let mut keep = true;
         if let Some(compaction) = last_compaction {
-            let mut messages = Vec::new();
-
             let summary_message = SessionMessage::CompactionSummary {
                 summary: compaction.summary.clone(),
                 tokens_before: compaction.tokens_before,
             };
             if let Some(message) = session_message_to_model(&summary_message) {
-                messages.push(message);
-            }
-
-            let mut keep = false;
-            for entry in path_entries {
-                if !keep {
-                    if entry
-                        .base_id()
-                        .is_some_and(|id| id == &compaction.first_kept_entry_id)
-                    {
-                        keep = true;
-                    } else {
-                        continue;
-                    }
-                }
-
-                match entry {
-                    SessionEntry::Message(msg_entry) => {
-                        if let Some(message) = session_message_to_model(&msg_entry.message) {
-                            messages.push(message);
-                        }
-                    }
-                    SessionEntry::BranchSummary(summary) => {
-                        let summary_message = SessionMessage::BranchSummary {
-                            summary: summary.summary.clone(),
-                            from_id: summary.from_id.clone(),
-                        };
-                        if let Some(message) = session_message_to_model(&summary_message) {
-                            messages.push(message);
-                        }
-                    }
-                    _ => {}
-                }
+                messages.push((None, message));
             }
-
-            return messages;
+            keep = false;
         }
 
-        let mut messages = Vec::new();
         for entry in path_entries {
-            match entry {
-                SessionEntry::Message(msg_entry) => {
-                    if let Some(message) = session_message_to_model(&msg_entry.message) {
-                        messages.push(message);
-                    }
+            if !keep {
+                if last_compaction.is_some_and(|compaction| {
+                    entry.base_id() == Some(&compaction.first_kept_entry_id)
+                }) {
+                    keep = true;
+                } else {
+                    continue;
                 }
+            }
+
+            let message = match entry {
+                SessionEntry::Message(msg_entry) => session_message_to_model(&msg_entry.message),
                 SessionEntry::BranchSummary(summary) => {
-                    let summary_message = SessionMessage::BranchSummary {
+                    session_message_to_model(&SessionMessage::BranchSummary {
                         summary: summary.summary.clone(),
                         from_id: summary.from_id.clone(),
-                    };
-                    if let Some(message) = session_message_to_model(&summary_message) {
-                        messages.push(message);
-                    }
+                    })
                 }
-                _ => {}
+                _ => None,
+            };
+            if let Some(message) = message {
+                messages.push((entry.base_id().cloned(), message));
             }
         }
         messages
@@ -1391,9 +1877,137 @@ impl Session {
             label,
         });
         self.leaf_id = Some(id.clone());
-        self.entries.push(entry);
+        self.push_entry(entry);
         Some(id)
     }
+
+    /// Active labels as `(target_id, label)` pairs, in the order they were set.
+    ///
+    /// A later label entry for the same target replaces the earlier one; a `None` label clears it.
+    pub fn labels(&self) -> Vec<(String, String)> {
+        let mut labels: Vec<(String, String)> = Vec::new();
+        for entry in &self.entries {
+            if let SessionEntry::Label(label_entry) = entry {
+                labels.retain(|(target, _)| target != &label_entry.target_id);
+                if let Some(label) = &label_entry.label {
+                    labels.push((label_entry.target_id.clone(), label.clone()));
+                }
+            }
+        }
+        labels
+    }
+
+    /// Entry ID carrying `label`, if any (most recently set wins).
+    pub fn find_label_target(&self, label: &str) -> Option<String> {
+        self.labels()
+            .into_iter()
+            .rev()
+            .find(|(_, name)| name == label)
+            .map(|(target, _)| target)
+    }
+
+    /// The current leaf, skipping over trailing label and checkpoint entries.
+    ///
+    /// Labeling moves the leaf onto the label entry itself; this returns the entry it tagged.
+    pub fn labelable_leaf_id(&self) -> Option<String> {
+        let mut current = self.leaf_id.clone();
+        // Bounded by the entry count so a corrupted parent cycle cannot spin forever.
+        for _ in 0..self.entries.len() {
+            current = match current.as_deref().and_then(|id| self.get_entry(id)) {
+                Some(SessionEntry::Label(label_entry)) => label_entry.base.parent_id.clone(),
+                Some(SessionEntry::Custom(custom))
+                    if custom.custom_type == CHECKPOINT_CUSTOM_TYPE
+                        || custom.custom_type == NOTE_CUSTOM_TYPE =>
+                {
+                    custom.base.parent_id.clone()
+                }
+                _ => break,
+            };
+        }
+        current
+    }
+
+    /// Save a named checkpoint at the current leaf and label it with `name`.
+    ///
+    /// Returns the checkpointed entry ID, or `None` if there is nothing to checkpoint yet.
+    pub fn add_checkpoint(&mut self, name: &str, worktree: Option<Value>) -> Option<String> {
+        self.ensure_entry_ids();
+        let target_id = self.labelable_leaf_id()?;
+        self.append_custom_entry(
+            CHECKPOINT_CUSTOM_TYPE.to_string(),
+            Some(serde_json::json!({
+                "name": name,
+                "targetId": target_id,
+                "worktree": worktree,
+            })),
+        );
+        self.add_label(&target_id, Some(name.to_string()))?;
+        Some(target_id)
+    }
+
+    /// Append a note at the current leaf; returns its entry ID.
+    pub fn add_note(&mut self, text: &str) -> String {
+        self.ensure_entry_ids();
+        self.append_custom_entry(
+            NOTE_CUSTOM_TYPE.to_string(),
+            Some(serde_json::json!({ "text": text, "display": true })),
+        )
+    }
+
+    /// Notes on the current path, oldest first.
+    pub fn notes(&self) -> Vec<SessionNote> {
+        self.entries_for_current_path()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                SessionEntry::Custom(custom) => Some(SessionNote {
+                    id: custom.base.id.clone()?,
+                    text: custom.note_text()?.to_string(),
+                    timestamp: custom.base.timestamp.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Saved checkpoints in the order they were (last) saved; re-saving a name replaces it.
+    pub fn checkpoints(&self) -> Vec<Checkpoint> {
+        let mut checkpoints: Vec<Checkpoint> = Vec::new();
+        for entry in &self.entries {
+            let SessionEntry::Custom(custom) = entry else {
+                continue;
+            };
+            if custom.custom_type != CHECKPOINT_CUSTOM_TYPE {
+                continue;
+            }
+            let Some(data) = custom.data.as_ref() else {
+                continue;
+            };
+            let (Some(name), Some(target_id)) = (
+                data.get("name").and_then(Value::as_str),
+                data.get("targetId").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            checkpoints.retain(|checkpoint| checkpoint.name != name);
+            checkpoints.push(Checkpoint {
+                name: name.to_string(),
+                target_id: target_id.to_string(),
+                timestamp: custom.base.timestamp.clone(),
+                worktree: data
+                    .get("worktree")
+                    .filter(|value| !value.is_null())
+                    .cloned(),
+            });
+        }
+        checkpoints
+    }
+
+    /// The checkpoint saved as `name`, if any.
+    pub fn find_checkpoint(&self, name: &str) -> Option<Checkpoint> {
+        self.checkpoints()
+            .into_iter()
+            .find(|checkpoint| checkpoint.name == name)
+    }
 }
 
 /// Summary of branches in a session.
@@ -1453,15 +2067,178 @@ fn scan_sessions_on_disk(project_session_dir: &Path) -> Result<Vec<SessionPickEn
 fn is_session_file_path(path: &Path) -> bool {
     match path.extension().and_then(|ext| ext.to_str()) {
         Some("jsonl") => true,
+        Some("zst") => is_compressed_session_path(path),
         #[cfg(feature = "sqlite-sessions")]
         Some("sqlite") => true,
         _ => false,
     }
 }
 
+/// Frame magic for zstd-compressed session files (RFC 8878).
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// JSONL session files larger than this are written as a single zstd frame.
+///
+/// The file name and extension are unchanged; readers detect compression from the magic bytes,
+/// so plain and compressed files can coexist in the same sessions directory.
+pub const SESSION_COMPRESSION_THRESHOLD_BYTES: usize = 256 * 1024;
+
+const SESSION_COMPRESSION_LEVEL: i32 = 3;
+
+/// File name suffix of sessions that are always compressed (`compress_sessions`).
+pub const COMPRESSED_SESSION_SUFFIX: &str = ".jsonl.zst";
+
+/// Returns true for a `.jsonl.zst` session file.
+#[must_use]
+pub fn is_compressed_session_path(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(COMPRESSED_SESSION_SUFFIX))
+}
+
+/// Returns true if `bytes` start with a zstd frame header.
+#[must_use]
+pub fn is_zstd_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ZSTD_MAGIC)
+}
+
+/// Longest a save waits for another process to finish writing the same session.
+const SAVE_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Advisory lock file kept beside a session file, e.g. `<session>.jsonl.lock`.
+pub(crate) fn session_lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+pub(crate) fn open_session_lock_file(path: &Path) -> Result<std::fs::File> {
+    Ok(std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(session_lock_path(path))?)
+}
+
+/// Take the cross-process lock for the session at `path`, naming the holder on timeout.
+pub(crate) fn lock_session_file<'a>(
+    lock_file: &'a std::fs::File,
+    path: &Path,
+    timeout: Duration,
+) -> Result<crate::session_index::LockGuard<'a>> {
+    crate::session_index::acquire_pid_lock(lock_file, timeout).map_err(|holder| {
+        holder.map_or_else(
+            || {
+                Error::session(format!(
+                    "Session {} is locked by another process",
+                    path.display()
+                ))
+            },
+            |pid| Error::session(format!("Session {} is locked by pid {pid}", path.display())),
+        )
+    })
+}
+
+/// Read a JSONL session file, transparently decompressing zstd-framed files.
+pub fn read_jsonl_session_file(path: &Path) -> Result<String> {
+    decode_jsonl_bytes(std::fs::read(path)?)
+}
+
+/// Header line followed by one line per entry.
+fn jsonl_content(header: &SessionHeader, entries: &[SessionEntry]) -> Result<String> {
+    let mut content = serde_json::to_string(header)?;
+    content.push('\n');
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry)?);
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// JSONL sessions directly in `dir` that have corrupted lines, with those lines.
+pub fn corrupted_sessions_in(dir: &Path) -> Vec<(PathBuf, Vec<SessionOpenSkippedEntry>)> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut corrupted = read_dir
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .filter_map(|path| {
+            let content = decode_jsonl_bytes(std::fs::read(&path).ok()?).ok()?;
+            let (_, diagnostics) = Session::from_jsonl_str(&content).ok()?;
+            (!diagnostics.skipped_entries.is_empty()).then_some((path, diagnostics.skipped_entries))
+        })
+        .collect::<Vec<_>>();
+    corrupted.sort_by(|a, b| a.0.cmp(&b.0));
+    corrupted
+}
+
+/// The lines of a JSONL file with the byte offset each starts at (line endings removed).
+fn jsonl_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    content.split_inclusive('\n').map(move |raw| {
+        let start = offset;
+        offset += raw.len();
+        let line = raw
+            .strip_suffix('\n')
+            .map_or(raw, |line| line.strip_suffix('\r').unwrap_or(line));
+        (start, line)
+    })
+}
+
+/// Complete entries inside a corrupted line. An interrupted write followed by an append runs
+/// a partial entry into a whole one, so parsing restarts at each `{` after a failure.
+fn salvage_entries(line: &str) -> Vec<SessionEntry> {
+    let mut entries = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('{') {
+        let mut stream =
+            serde_json::Deserializer::from_str(&rest[start..]).into_iter::<SessionEntry>();
+        if let Some(Ok(entry)) = stream.next() {
+            entries.push(entry);
+            rest = &rest[start + stream.byte_offset()..];
+        } else {
+            rest = &rest[start + 1..];
+        }
+    }
+    entries
+}
+
+/// `<file>.bak`, or `<file>.bak.N` for the first `N` not taken.
+fn backup_path(path: &Path) -> PathBuf {
+    let backup = PathBuf::from(format!("{}.bak", path.display()));
+    if !backup.exists() {
+        return backup;
+    }
+    (1..)
+        .map(|n| PathBuf::from(format!("{}.bak.{n}", path.display())))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(backup)
+}
+
+fn decode_jsonl_bytes(bytes: Vec<u8>) -> Result<String> {
+    let bytes = if is_zstd_compressed(&bytes) {
+        zstd::stream::decode_all(bytes.as_slice())
+            .map_err(|e| Error::session(format!("Failed to decompress session file: {e}")))?
+    } else {
+        bytes
+    };
+    String::from_utf8(bytes)
+        .map_err(|e| Error::session(format!("Session file is not valid UTF-8: {e}")))
+}
+
+fn encode_jsonl_bytes(content: String, always_compress: bool) -> Result<Vec<u8>> {
+    if !always_compress && content.len() <= SESSION_COMPRESSION_THRESHOLD_BYTES {
+        return Ok(content.into_bytes());
+    }
+    zstd::stream::encode_all(content.as_bytes(), SESSION_COMPRESSION_LEVEL)
+        .map_err(|e| Error::session(format!("Failed to compress session file: {e}")))
+}
+
 fn load_session_meta(path: &Path) -> Result<SessionPickEntry> {
     match path.extension().and_then(|ext| ext.to_str()) {
-        Some("jsonl") => load_session_meta_jsonl(path),
+        Some("jsonl" | "zst") => load_session_meta_jsonl(path),
         #[cfg(feature = "sqlite-sessions")]
         Some("sqlite") => load_session_meta_sqlite(path),
         _ => Err(Error::session(format!(
@@ -1472,7 +2249,7 @@ fn load_session_meta(path: &Path) -> Result<SessionPickEntry> {
 }
 
 fn load_session_meta_jsonl(path: &Path) -> Result<SessionPickEntry> {
-    let content = std::fs::read_to_string(path)
+    let content = read_jsonl_session_file(path)
         .map_err(|e| Error::session(format!("Failed to read session: {e}")))?;
     let mut lines = content.lines();
     let header_line = lines
@@ -1568,6 +2345,9 @@ pub struct SessionHeader {
         alias = "parentSession"
     )]
     pub parent_session: Option<String>,
+    /// Name of the session template the session was created from (`pi --template`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
 }
 
 impl SessionHeader {
@@ -1585,6 +2365,7 @@ impl SessionHeader {
             model_id: None,
             thinking_level: None,
             parent_session: None,
+            template: None,
         }
     }
 }
@@ -1673,6 +2454,9 @@ pub struct MessageEntry {
     #[serde(flatten)]
     pub base: EntryBase,
     pub message: SessionMessage,
+    /// What the `redaction` setting scrubbed from this message before it was saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<RedactionDetails>,
 }
 
 /// Session message payload.
@@ -2098,6 +2882,13 @@ fn render_blocks(blocks: &[ContentBlock]) -> String {
                     escape_html(&args)
                 );
             }
+            ContentBlock::HostedTool(hosted) => {
+                let _ = write!(
+                    html,
+                    "<div class=\"note\">Hosted tool</div><pre>{}</pre>",
+                    escape_html(&hosted.summary())
+                );
+            }
         }
     }
     html
@@ -2139,6 +2930,7 @@ fn content_blocks_to_text(blocks: &[ContentBlock]) -> String {
             ContentBlock::ToolCall(call) => {
                 push_line(&mut output, &format!("[tool call: {}]", call.name));
             }
+            ContentBlock::HostedTool(hosted) => push_line(&mut output, &hosted.summary()),
         }
     }
     output
@@ -2184,7 +2976,7 @@ fn generate_entry_id(existing: &HashSet<String>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::{StopReason, Usage};
+    use crate::model::{StopReason, ThinkingContent, ToolCall, Usage};
     use asupersync::runtime::RuntimeBuilder;
     use std::future::Future;
 
@@ -2294,6 +3086,37 @@ mod tests {
         assert_eq!(session.leaf_id.as_deref(), Some(id2.as_str()));
     }
 
+    #[test]
+    fn test_model_and_thinking_level_follow_current_branch() {
+        let mut session = Session::in_memory();
+
+        // A -> [model b, thinking high] -> C
+        //   -> D
+        let id_a = session.append_message(make_test_message("A"));
+        session.append_model_change("openai".to_string(), "gpt-4o".to_string());
+        session.append_thinking_level_change("high".to_string());
+        let id_c = session.append_message(make_test_message("C"));
+        assert_eq!(
+            session.model_for_current_path(),
+            Some(("openai".to_string(), "gpt-4o".to_string()))
+        );
+        assert_eq!(
+            session.thinking_level_for_current_path().as_deref(),
+            Some("high")
+        );
+
+        session.create_branch_from(&id_a);
+        session.append_message(make_test_message("D"));
+        assert_eq!(session.model_for_current_path(), None);
+        assert_eq!(session.thinking_level_for_current_path(), None);
+
+        assert!(session.navigate_to(&id_c));
+        assert_eq!(
+            session.thinking_level_for_current_path().as_deref(),
+            Some("high")
+        );
+    }
+
     #[test]
     fn test_session_get_children() {
         let mut session = Session::in_memory();
@@ -2361,6 +3184,7 @@ mod tests {
             model: "claude-test".to_string(),
             usage: Usage::default(),
             stop_reason: StopReason::Stop,
+            abort_reason: None,
             error_message: None,
             timestamp: 0,
         };
@@ -2396,6 +3220,171 @@ mod tests {
         assert!(types.contains(&"session_info".to_string()));
     }
 
+    #[test]
+    fn test_labels_track_latest_value_and_skip_label_leaves() {
+        let mut session = Session::in_memory();
+        let a = session.append_message(make_test_message("A"));
+        session.add_label(&a, Some("first".to_string()));
+        assert_eq!(session.labelable_leaf_id().as_deref(), Some(a.as_str()));
+
+        let b = session.append_message(make_test_message("B"));
+        session.add_label(&b, Some("second".to_string()));
+        session.add_label(&a, Some("renamed".to_string()));
+        session.add_label(&b, None);
+
+        assert_eq!(session.labels(), vec![(a.clone(), "renamed".to_string())]);
+        assert_eq!(
+            session.find_label_target("renamed").as_deref(),
+            Some(a.as_str())
+        );
+        assert!(session.find_label_target("first").is_none());
+        assert!(session.find_label_target("second").is_none());
+        assert_eq!(session.labelable_leaf_id().as_deref(), Some(b.as_str()));
+    }
+
+    #[test]
+    fn test_checkpoints_label_the_leaf_and_latest_save_wins() {
+        let mut session = Session::in_memory();
+        assert!(session.add_checkpoint("empty", None).is_none());
+
+        let a = session.append_message(make_test_message("A"));
+        let saved = session.add_checkpoint("before", Some(serde_json::json!({ "head": "abc" })));
+        assert_eq!(saved.as_deref(), Some(a.as_str()));
+        assert_eq!(session.labelable_leaf_id().as_deref(), Some(a.as_str()));
+        assert_eq!(
+            session.find_label_target("before").as_deref(),
+            Some(a.as_str())
+        );
+
+        let b = session.append_message(make_test_message("B"));
+        session.add_checkpoint("after", None);
+        session.add_checkpoint("before", None);
+
+        let checkpoints = session.checkpoints();
+        let names = checkpoints
+            .iter()
+            .map(|checkpoint| checkpoint.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["after", "before"]);
+        let before = session.find_checkpoint("before").unwrap();
+        assert_eq!(before.target_id, b);
+        assert!(before.worktree.is_none());
+        assert!(session.find_checkpoint("missing").is_none());
+    }
+
+    #[test]
+    fn test_notes_are_exported_but_never_sent_to_the_model() {
+        let mut session = Session::in_memory();
+        let a = session.append_message(make_test_message("A"));
+        session.add_note("flaky test again; check the seed");
+        assert_eq!(session.labelable_leaf_id().as_deref(), Some(a.as_str()));
+        session.append_message(make_test_message("B"));
+
+        let notes = session.notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].text, "flaky test again; check the seed");
+        assert_eq!(session.to_messages_for_current_path().len(), 2);
+        assert!(
+            session
+                .to_html()
+                .contains("<div class=\"role\">Note</div><div class=\"note\">flaky test again; check the seed</div>")
+        );
+    }
+
+    #[test]
+    fn test_compressed_store_always_writes_jsonl_zst() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut session = Session::create_with_dir_and_store(
+            Some(temp.path().to_path_buf()),
+            SessionStoreKind::CompressedJsonl,
+        );
+        session.append_message(make_test_message("small"));
+        run_async(async { session.save().await }).unwrap();
+        let path = session.path.clone().unwrap();
+        assert!(is_compressed_session_path(&path));
+        assert!(is_session_file_path(&path));
+        assert!(is_zstd_compressed(&std::fs::read(&path).unwrap()));
+
+        let path_str = path.display().to_string();
+        let mut reopened = run_async(async move { Session::open(&path_str).await }).unwrap();
+        assert_eq!(reopened.entries.len(), 1);
+        reopened.append_message(make_test_message("again"));
+        run_async(async { reopened.save().await }).unwrap();
+        assert_eq!(reopened.path.as_deref(), Some(path.as_path()));
+        assert!(is_zstd_compressed(&std::fs::read(&path).unwrap()));
+    }
+
+    #[test]
+    fn test_pending_migrations_cover_the_gap_in_order() {
+        fn noop(_: &mut Value) {}
+        let step = |from| SessionMigration {
+            from,
+            header: noop,
+            entry: noop,
+        };
+        let migrations = [step(4), step(2), step(3)];
+
+        let steps = pending_migrations(2, &migrations, 4).unwrap();
+        assert_eq!(steps.iter().map(|s| s.from).collect::<Vec<_>>(), [2, 3]);
+        assert!(pending_migrations(4, &migrations, 4).unwrap().is_empty());
+
+        let err = pending_migrations(5, &migrations, 4).unwrap_err();
+        assert!(err.to_string().contains("version 5 is newer"));
+        let err = session_file_version(&serde_json::json!({ "version": 300 })).unwrap_err();
+        assert!(err.to_string().contains("version 300 is newer"));
+        assert_eq!(session_file_version(&serde_json::json!({})).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_save_lock_names_the_holding_pid() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("session.jsonl");
+        let holder = open_session_lock_file(&path).unwrap();
+        let _held =
+            crate::session_index::acquire_pid_lock(&holder, Duration::from_millis(50)).unwrap();
+        assert!(temp.path().join("session.jsonl.lock").exists());
+
+        let waiter = open_session_lock_file(&path).unwrap();
+        let err = lock_session_file(&waiter, &path, Duration::from_millis(50))
+            .err()
+            .expect("lock should be held");
+        assert!(
+            err.to_string()
+                .contains(&format!("locked by pid {}", std::process::id())),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_save_compresses_large_sessions_and_open_reads_them_back() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut session = Session::create_with_dir(Some(temp.path().to_path_buf()));
+        session.append_message(make_test_message("small"));
+        run_async(async { session.save().await }).unwrap();
+        let path = session.path.clone().unwrap();
+        assert!(!is_zstd_compressed(&std::fs::read(&path).unwrap()));
+
+        let tool_output = "line of tool output\n".repeat(1024);
+        for _ in 0..(SESSION_COMPRESSION_THRESHOLD_BYTES / tool_output.len() + 1) {
+            session.append_message(make_test_message(&tool_output));
+        }
+        let leaf = session.leaf_id.clone();
+        run_async(async { session.save().await }).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(is_zstd_compressed(&bytes));
+        assert!(bytes.len() < SESSION_COMPRESSION_THRESHOLD_BYTES / 10);
+
+        let path_str = path.display().to_string();
+        let reopened = run_async(async move { Session::open(&path_str).await }).unwrap();
+        assert_eq!(reopened.entries.len(), session.entries.len());
+        assert_eq!(reopened.leaf_id, leaf);
+
+        let content = read_jsonl_session_file(&path).unwrap();
+        assert!(content.starts_with('{'));
+        assert_eq!(content.lines().count(), session.entries.len() + 1);
+    }
+
     #[test]
     fn test_open_with_diagnostics_skips_corrupted_last_entry_and_recovers_leaf() {
         let temp = tempfile::tempdir().unwrap();
@@ -2456,6 +3445,45 @@ mod tests {
         assert_eq!(loaded.leaf_id.as_deref(), Some(first_id.as_str()));
     }
 
+    #[test]
+    fn test_repair_jsonl_salvages_run_together_entries_and_keeps_a_backup() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut session = Session::create_with_dir(Some(temp.path().to_path_buf()));
+        session.append_message(make_test_message("Hello"));
+        let second_id = session.append_message(make_test_message("World"));
+        run_async(async { session.save().await }).unwrap();
+        let path = session.path.clone().expect("session path set");
+
+        let original = std::fs::read_to_string(&path).unwrap();
+        let lines = original.lines().collect::<Vec<_>>();
+        // An interrupted write of the first entry, then the second entry on the same line.
+        let corrupted = format!(
+            "{}\n{}{}\nnot json\n",
+            lines[0],
+            &lines[1][..lines[1].len() / 2],
+            lines[2]
+        );
+        std::fs::write(&path, &corrupted).unwrap();
+
+        let (loaded, _) = Session::from_jsonl_str(&corrupted).unwrap();
+        let skipped = loaded.skipped_entries();
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].line_number, 2);
+        assert_eq!(skipped[0].byte_offset, lines[0].len() + 1);
+
+        let repair = Session::repair_jsonl(&path).unwrap();
+        assert_eq!(repair.salvaged, 1);
+        assert_eq!(repair.dropped, 1);
+        let backup = repair.backup.expect("backup written");
+        assert_eq!(std::fs::read_to_string(backup).unwrap(), corrupted);
+
+        let (repaired, diagnostics) =
+            Session::from_jsonl_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(diagnostics.skipped_entries.is_empty());
+        assert_eq!(repaired.leaf_id.as_deref(), Some(second_id.as_str()));
+        assert!(Session::repair_jsonl(&path).unwrap().backup.is_none());
+    }
+
     #[test]
     fn test_save_and_open_round_trip_preserves_compaction_and_branch_summary() {
         let temp = tempfile::tempdir().unwrap();
@@ -2483,6 +3511,71 @@ mod tests {
         assert!(html.contains("branch summary"));
     }
 
+    #[test]
+    fn test_save_and_open_round_trip_preserves_provider_signatures() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut session = Session::create_with_dir(Some(temp.path().to_path_buf()));
+        session.append_message(make_test_message("Hello"));
+        session.append_message(SessionMessage::Assistant {
+            message: AssistantMessage {
+                content: vec![
+                    ContentBlock::Thinking(ThinkingContent {
+                        thinking: "plan".to_string(),
+                        thinking_signature: Some("sig-think".to_string()),
+                        redacted: false,
+                    }),
+                    ContentBlock::Thinking(ThinkingContent {
+                        thinking: String::new(),
+                        thinking_signature: Some("enc".to_string()),
+                        redacted: true,
+                    }),
+                    ContentBlock::Text(TextContent {
+                        text: "reading".to_string(),
+                        text_signature: Some("sig-text".to_string()),
+                    }),
+                    ContentBlock::ToolCall(ToolCall {
+                        id: "call_1".to_string(),
+                        name: "read".to_string(),
+                        arguments: serde_json::json!({ "path": "a.rs" }),
+                        thought_signature: Some("sig-call".to_string()),
+                    }),
+                ],
+                api: "test".to_string(),
+                provider: "test".to_string(),
+                model: "test".to_string(),
+                usage: Usage::default(),
+                stop_reason: StopReason::ToolUse,
+                abort_reason: None,
+                error_message: None,
+                timestamp: 0,
+            },
+        });
+
+        run_async(async { session.save().await }).unwrap();
+        let path = session.path.clone().expect("session path set");
+        let loaded = run_async(async { Session::open(path.to_string_lossy().as_ref()).await })
+            .expect("reopen session");
+
+        let messages = loaded.to_messages_for_current_path();
+        let Some(Message::Assistant(assistant)) = messages.last() else {
+            panic!("expected assistant message, got {messages:?}");
+        };
+        let [
+            ContentBlock::Thinking(thinking),
+            ContentBlock::Thinking(redacted),
+            ContentBlock::Text(text),
+            ContentBlock::ToolCall(call),
+        ] = assistant.content.as_slice()
+        else {
+            panic!("unexpected content {:?}", assistant.content);
+        };
+        assert_eq!(thinking.thinking_signature.as_deref(), Some("sig-think"));
+        assert!(redacted.redacted);
+        assert_eq!(redacted.thinking_signature.as_deref(), Some("enc"));
+        assert_eq!(text.text_signature.as_deref(), Some("sig-text"));
+        assert_eq!(call.thought_signature.as_deref(), Some("sig-call"));
+    }
+
     #[test]
     fn test_concurrent_saves_do_not_corrupt_session_file_unit() {
         let temp = tempfile::tempdir().unwrap();
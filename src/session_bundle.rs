@@ -0,0 +1,651 @@
+//! Portable session bundles for handing an in-progress task to someone else
+//! (`pi sessions bundle` / `pi sessions unbundle`).
+//!
+//! A bundle is one zstd-compressed JSON document holding:
+//! - the session JSONL, as it would be saved
+//! - the current contents of the files the session wrote or edited, plus any pinned files,
+//!   stored relative to the session's working directory
+//! - a config fingerprint (pi version, model, thinking level, settings hash) so the receiver
+//!   can see where their setup differs from the sender's
+//!
+//! Unbundling saves the session as a new session for the receiver's working directory and
+//! restores the files there. A file that already exists with different contents is reported
+//! as a conflict and left alone unless overwriting is requested.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::session::Session;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Read as _;
+use std::path::{Component, Path, PathBuf};
+
+/// Bundle format version; bumped on incompatible changes.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// File extension used for bundles.
+pub const BUNDLE_EXTENSION: &str = "pibundle";
+
+/// Files larger than this are left out of the bundle.
+pub const MAX_BUNDLED_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Decompressed bundles larger than this are rejected rather than read into memory.
+pub const MAX_BUNDLE_DECODED_BYTES: u64 = 256 * 1024 * 1024;
+
+const BUNDLE_COMPRESSION_LEVEL: i32 = 3;
+
+/// Directories whose contents can run code or change pi's behavior on the receiver's machine
+/// (git hooks, project settings), so bundles never carry or restore files inside them.
+const PROTECTED_DIRS: &[&str] = &[".git", ".pi"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BundledFileRole {
+    /// Written or edited during the session.
+    Changed,
+    /// Pinned into the agent's context by the sender.
+    Pinned,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledFile {
+    /// Path relative to the session's working directory, with `/` separators.
+    pub path: String,
+    pub role: BundledFileRole,
+    /// Base64 file contents.
+    pub content: String,
+    pub sha256: String,
+}
+
+impl BundledFile {
+    fn bytes(&self) -> Result<Vec<u8>> {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.content)
+            .map_err(|err| {
+                Error::validation(format!("Bundled file {} is corrupt: {err}", self.path))
+            })
+    }
+}
+
+/// What the receiver should compare against their own setup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigFingerprint {
+    pub pi_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_level: Option<String>,
+    /// SHA-256 of the effective settings, so differing settings show up without sharing them.
+    pub settings_sha256: String,
+}
+
+impl ConfigFingerprint {
+    /// The fingerprint of `session` run with `config` on this build of pi.
+    pub fn new(session: &Session, config: &Config) -> Result<Self> {
+        let (provider, model_id) = session.model_for_current_path().map_or_else(
+            || {
+                (
+                    session.header.provider.clone(),
+                    session.header.model_id.clone(),
+                )
+            },
+            |(provider, model_id)| (Some(provider), Some(model_id)),
+        );
+        Ok(Self {
+            pi_version: env!("CARGO_PKG_VERSION").to_string(),
+            provider,
+            model_id,
+            thinking_level: session
+                .thinking_level_for_current_path()
+                .or_else(|| session.header.thinking_level.clone()),
+            settings_sha256: sha256_hex(
+                canonical_json(serde_json::to_value(config)?)
+                    .to_string()
+                    .as_bytes(),
+            ),
+        })
+    }
+
+    /// Human-readable differences between the sender's fingerprint and the receiver's.
+    pub fn differences(&self, local: &Self) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.pi_version != local.pi_version {
+            differences.push(format!(
+                "pi version: bundle {}, local {}",
+                self.pi_version, local.pi_version
+            ));
+        }
+        if self.settings_sha256 != local.settings_sha256 {
+            differences.push("settings differ from the sender's".to_string());
+        }
+        differences
+    }
+
+    /// One line describing the model the session was using.
+    pub fn describe_model(&self) -> String {
+        let mut model = match (&self.provider, &self.model_id) {
+            (Some(provider), Some(model_id)) => format!("{provider}/{model_id}"),
+            (None, Some(model_id)) => model_id.clone(),
+            _ => "unknown model".to_string(),
+        };
+        if let Some(level) = &self.thinking_level {
+            let _ = write!(model, " (thinking: {level})");
+        }
+        model
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionBundle {
+    pub version: u32,
+    pub created_at: String,
+    pub session_jsonl: String,
+    pub files: Vec<BundledFile>,
+    pub fingerprint: ConfigFingerprint,
+}
+
+/// Outcome of [`SessionBundle::create`].
+#[derive(Debug)]
+pub struct BundleCreation {
+    pub bundle: SessionBundle,
+    /// Files that could not be included, with the reason.
+    pub skipped: Vec<String>,
+}
+
+/// Outcome of [`SessionBundle::restore_files`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FileRestore {
+    pub written: Vec<String>,
+    /// Already present with the bundled contents.
+    pub unchanged: Vec<String>,
+    /// Present with different contents and left alone.
+    pub conflicts: Vec<String>,
+}
+
+impl SessionBundle {
+    /// Bundle `session` with the files it changed and the `pinned` files.
+    ///
+    /// Relative paths are resolved against the session's working directory; files outside it,
+    /// missing files, and files over [`MAX_BUNDLED_FILE_BYTES`] are skipped and reported.
+    pub fn create(
+        session: &Session,
+        pinned: &[PathBuf],
+        config: &Config,
+    ) -> Result<BundleCreation> {
+        let cwd = PathBuf::from(&session.header.cwd);
+        let changed =
+            crate::compaction::FileOperations::from_entries(session.entries_for_current_path())
+                .modified_files();
+
+        let mut files: Vec<BundledFile> = Vec::new();
+        let mut skipped = Vec::new();
+        let candidates = changed
+            .iter()
+            .map(|path| (PathBuf::from(path), BundledFileRole::Changed))
+            .chain(
+                pinned
+                    .iter()
+                    .map(|path| (path.clone(), BundledFileRole::Pinned)),
+            );
+        for (path, role) in candidates {
+            let absolute = if path.is_absolute() {
+                path.clone()
+            } else {
+                cwd.join(&path)
+            };
+            let Some(relative) = relative_bundle_path(&absolute, &cwd) else {
+                skipped.push(format!("{}: outside {}", path.display(), cwd.display()));
+                continue;
+            };
+            if files.iter().any(|file| file.path == relative) {
+                continue;
+            }
+            if let Some(dir) = protected_dir(&relative) {
+                skipped.push(format!("{relative}: inside {dir}/"));
+                continue;
+            }
+            match std::fs::metadata(&absolute) {
+                Ok(meta) if meta.len() > MAX_BUNDLED_FILE_BYTES => {
+                    skipped.push(format!(
+                        "{relative}: larger than {MAX_BUNDLED_FILE_BYTES} bytes"
+                    ));
+                    continue;
+                }
+                Ok(meta) if meta.is_file() => {}
+                Ok(_) => {
+                    skipped.push(format!("{relative}: not a file"));
+                    continue;
+                }
+                Err(err) => {
+                    skipped.push(format!("{relative}: {err}"));
+                    continue;
+                }
+            }
+            let bytes = std::fs::read(&absolute)?;
+            files.push(BundledFile {
+                path: relative,
+                role,
+                content: base64::engine::general_purpose::STANDARD.encode(&bytes),
+                sha256: sha256_hex(&bytes),
+            });
+        }
+
+        Ok(BundleCreation {
+            bundle: Self {
+                version: BUNDLE_VERSION,
+                created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                session_jsonl: session.to_jsonl()?,
+                files,
+                fingerprint: ConfigFingerprint::new(session, config)?,
+            },
+            skipped,
+        })
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        zstd::stream::encode_all(
+            serde_json::to_vec(self)?.as_slice(),
+            BUNDLE_COMPRESSION_LEVEL,
+        )
+        .map_err(|err| Error::session(format!("Failed to compress bundle: {err}")))
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        Self::decode_limited(bytes, MAX_BUNDLE_DECODED_BYTES)
+    }
+
+    fn decode_limited(bytes: &[u8], limit: u64) -> Result<Self> {
+        let decoder = zstd::stream::read::Decoder::new(bytes)
+            .map_err(|err| Error::validation(format!("Not a pi session bundle: {err}")))?;
+        let mut json = Vec::new();
+        decoder
+            .take(limit + 1)
+            .read_to_end(&mut json)
+            .map_err(|err| Error::validation(format!("Not a pi session bundle: {err}")))?;
+        if json.len() as u64 > limit {
+            return Err(Error::validation(format!(
+                "Bundle is larger than {limit} bytes uncompressed"
+            )));
+        }
+        let bundle: Self = serde_json::from_slice(&json)
+            .map_err(|err| Error::validation(format!("Not a pi session bundle: {err}")))?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(Error::validation(format!(
+                "Bundle version {} is newer than this pi supports ({BUNDLE_VERSION}); upgrade pi",
+                bundle.version
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// The bundled session, re-homed to `cwd` under a fresh ID so it never collides with
+    /// the sender's copy. It is not saved yet.
+    pub fn session(&self, cwd: &Path, session_dir: Option<PathBuf>) -> Result<Session> {
+        let (bundled, _diagnostics) = Session::from_jsonl_str(&self.session_jsonl)?;
+        let mut session = Session::create_with_dir(session_dir);
+        session.header = bundled.header;
+        session.header.id = uuid::Uuid::new_v4().to_string();
+        session.header.cwd = cwd.display().to_string();
+        session.entries = bundled.entries;
+        session.leaf_id = bundled.leaf_id;
+        Ok(session)
+    }
+
+    /// Write the bundled files under `root`.
+    ///
+    /// Every path and checksum is checked before anything is written, so a bad entry leaves
+    /// the tree untouched. Existing files with other contents are conflicts: left alone, or
+    /// replaced when `overwrite` is set.
+    pub fn restore_files(&self, root: &Path, overwrite: bool) -> Result<FileRestore> {
+        let mut checked = Vec::with_capacity(self.files.len());
+        for file in &self.files {
+            let Some(target) = safe_join(root, &file.path) else {
+                return Err(Error::validation(format!(
+                    "Bundle contains an unsafe path: {}",
+                    file.path
+                )));
+            };
+            if let Some(dir) = protected_dir(&file.path) {
+                return Err(Error::validation(format!(
+                    "Bundle contains {}, inside {dir}/, which is never restored",
+                    file.path
+                )));
+            }
+            let bytes = file.bytes()?;
+            if sha256_hex(&bytes) != file.sha256 {
+                return Err(Error::validation(format!(
+                    "Bundled file {} does not match its checksum",
+                    file.path
+                )));
+            }
+            checked.push((file, target, bytes));
+        }
+
+        let mut restore = FileRestore::default();
+        for (file, target, bytes) in checked {
+            if let Ok(existing) = std::fs::read(&target) {
+                if existing == bytes {
+                    restore.unchanged.push(file.path.clone());
+                    continue;
+                }
+                if !overwrite {
+                    restore.conflicts.push(file.path.clone());
+                    continue;
+                }
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&target, &bytes)?;
+            restore.written.push(file.path.clone());
+        }
+        Ok(restore)
+    }
+
+    /// Paths of the bundled pinned files.
+    pub fn pinned_paths(&self) -> impl Iterator<Item = &str> {
+        self.files
+            .iter()
+            .filter(|file| file.role == BundledFileRole::Pinned)
+            .map(|file| file.path.as_str())
+    }
+}
+
+/// `path` relative to `cwd` with `/` separators, if it lies inside `cwd`.
+fn relative_bundle_path(path: &Path, cwd: &Path) -> Option<String> {
+    let relative = path.strip_prefix(cwd).ok()?;
+    let parts = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            Component::CurDir => Some(String::new()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let parts = parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// `root` joined with a bundle path, refusing absolute paths and `..`.
+fn safe_join(root: &Path, bundle_path: &str) -> Option<PathBuf> {
+    let mut target = root.to_path_buf();
+    let mut any = false;
+    for part in bundle_path.split('/') {
+        match Path::new(part).components().next() {
+            Some(Component::Normal(_)) if Path::new(part).components().count() == 1 => {
+                target.push(part);
+                any = true;
+            }
+            _ => return None,
+        }
+    }
+    any.then_some(target)
+}
+
+/// The protected directory `bundle_path` lies inside, if any. Compared case-insensitively
+/// for case-insensitive filesystems.
+fn protected_dir(bundle_path: &str) -> Option<&'static str> {
+    bundle_path.split('/').find_map(|part| {
+        PROTECTED_DIRS
+            .iter()
+            .copied()
+            .find(|dir| part.eq_ignore_ascii_case(dir))
+    })
+}
+
+/// `value` with object keys sorted at every level, so map-backed settings hash the same
+/// regardless of iteration order.
+fn canonical_json(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, canonical_json(value)))
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(canonical_json).collect()),
+        other => other,
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AssistantMessage, ContentBlock, StopReason, ToolCall, Usage};
+    use crate::session::SessionMessage;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn session_writing(cwd: &Path, path: &str) -> Session {
+        let mut session = Session::in_memory();
+        session.header.cwd = cwd.display().to_string();
+        session.append_message(SessionMessage::Assistant {
+            message: AssistantMessage {
+                content: vec![ContentBlock::ToolCall(ToolCall {
+                    id: "call-1".to_string(),
+                    name: "write".to_string(),
+                    arguments: json!({ "path": path, "content": "x" }),
+                    thought_signature: None,
+                })],
+                api: "test-api".to_string(),
+                provider: "test-provider".to_string(),
+                model: "test-model".to_string(),
+                usage: Usage::default(),
+                stop_reason: StopReason::ToolUse,
+                abort_reason: None,
+                error_message: None,
+                timestamp: 0,
+            },
+        });
+        session
+    }
+
+    #[test]
+    fn bundle_round_trips_session_and_files() {
+        let sender = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir_all(sender.path().join("src")).expect("mkdir");
+        std::fs::write(sender.path().join("src/lib.rs"), "fn main() {}\n").expect("write");
+        std::fs::write(sender.path().join("NOTES.md"), "todo\n").expect("write");
+        let session = session_writing(sender.path(), "src/lib.rs");
+
+        let created = SessionBundle::create(
+            &session,
+            &[PathBuf::from("NOTES.md"), PathBuf::from("/etc/hostname")],
+            &Config::default(),
+        )
+        .expect("create");
+        assert_eq!(created.skipped.len(), 1);
+        let bytes = created.bundle.encode().expect("encode");
+        let bundle = SessionBundle::decode(&bytes).expect("decode");
+        assert_eq!(bundle, created.bundle);
+        assert_eq!(bundle.pinned_paths().collect::<Vec<_>>(), vec!["NOTES.md"]);
+
+        let receiver = tempfile::tempdir().expect("tempdir");
+        std::fs::write(receiver.path().join("NOTES.md"), "mine\n").expect("write");
+        let restore = bundle
+            .restore_files(receiver.path(), false)
+            .expect("restore");
+        assert_eq!(restore.written, vec!["src/lib.rs"]);
+        assert_eq!(restore.conflicts, vec!["NOTES.md"]);
+        assert_eq!(
+            std::fs::read_to_string(receiver.path().join("NOTES.md")).expect("read"),
+            "mine\n"
+        );
+
+        let restored = bundle.session(receiver.path(), None).expect("session");
+        assert_ne!(restored.header.id, session.header.id);
+        assert_eq!(restored.header.cwd, receiver.path().display().to_string());
+        assert_eq!(restored.entries.len(), session.entries.len());
+    }
+
+    #[test]
+    fn restore_refuses_paths_outside_the_root() {
+        let root = Path::new("/work");
+        assert_eq!(
+            safe_join(root, "src/a.rs"),
+            Some(PathBuf::from("/work/src/a.rs"))
+        );
+        assert_eq!(safe_join(root, "../a.rs"), None);
+        assert_eq!(safe_join(root, "/etc/passwd"), None);
+        assert_eq!(safe_join(root, ""), None);
+
+        let mut bundle = SessionBundle::create(&Session::in_memory(), &[], &Config::default())
+            .expect("create")
+            .bundle;
+        bundle.files.push(BundledFile {
+            path: "../escape.txt".to_string(),
+            role: BundledFileRole::Changed,
+            content: String::new(),
+            sha256: sha256_hex(b""),
+        });
+        let dir = tempfile::tempdir().expect("tempdir");
+        assert!(bundle.restore_files(dir.path(), true).is_err());
+
+        // A bad path late in the bundle must not leave earlier files written.
+        bundle.files.insert(
+            0,
+            BundledFile {
+                path: "first.txt".to_string(),
+                role: BundledFileRole::Changed,
+                content: String::new(),
+                sha256: sha256_hex(b""),
+            },
+        );
+        assert!(bundle.restore_files(dir.path(), true).is_err());
+        assert!(!dir.path().join("first.txt").exists());
+    }
+
+    fn bundle_with_file(path: &str) -> SessionBundle {
+        let mut bundle = SessionBundle::create(&Session::in_memory(), &[], &Config::default())
+            .expect("create")
+            .bundle;
+        bundle.files.push(BundledFile {
+            path: "ok.txt".to_string(),
+            role: BundledFileRole::Changed,
+            content: String::new(),
+            sha256: sha256_hex(b""),
+        });
+        bundle.files.push(BundledFile {
+            path: path.to_string(),
+            role: BundledFileRole::Changed,
+            content: base64::engine::general_purpose::STANDARD.encode(b"#!/bin/sh\n"),
+            sha256: sha256_hex(b"#!/bin/sh\n"),
+        });
+        bundle
+    }
+
+    #[test]
+    fn restore_refuses_git_hooks() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        for path in [".git/hooks/pre-commit", "vendor/.GIT/config"] {
+            let err = bundle_with_file(path)
+                .restore_files(dir.path(), true)
+                .unwrap_err();
+            assert!(err.to_string().contains("never restored"), "{err}");
+        }
+        assert!(!dir.path().join("ok.txt").exists());
+        assert!(!dir.path().join(".git").exists());
+    }
+
+    #[test]
+    fn restore_refuses_project_settings() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let err = bundle_with_file(".pi/settings.json")
+            .restore_files(dir.path(), true)
+            .unwrap_err();
+        assert!(err.to_string().contains(".pi/"), "{err}");
+        assert!(!dir.path().join(".pi").exists());
+
+        // The sender's side leaves such files out in the first place.
+        std::fs::create_dir_all(dir.path().join(".pi")).expect("mkdir");
+        std::fs::write(dir.path().join(".pi/settings.json"), "{}").expect("write");
+        let session = session_writing(dir.path(), ".pi/settings.json");
+        let creation = SessionBundle::create(&session, &[], &Config::default()).expect("create");
+        assert!(creation.bundle.files.is_empty());
+        assert_eq!(creation.skipped, vec![".pi/settings.json: inside .pi/"]);
+    }
+
+    #[test]
+    fn decode_rejects_oversized_bundles() {
+        let bundle = SessionBundle::create(&Session::in_memory(), &[], &Config::default())
+            .expect("create")
+            .bundle;
+        let bytes = bundle.encode().expect("encode");
+        assert!(SessionBundle::decode(&bytes).is_ok());
+        let err = SessionBundle::decode_limited(&bytes, 16).unwrap_err();
+        assert!(err.to_string().contains("uncompressed"), "{err}");
+    }
+
+    #[test]
+    fn fingerprint_settings_hash_ignores_map_order() {
+        let keys = (0..32).map(|n| format!("tool-{n}")).collect::<Vec<_>>();
+        let config_with = |keys: Vec<&String>| {
+            // Each HashMap gets its own random seed, so iteration order differs too.
+            let timeouts = keys
+                .into_iter()
+                .map(|key| (key.clone(), 30))
+                .collect::<HashMap<_, _>>();
+            Config {
+                tool_timeouts: Some(timeouts),
+                ..Config::default()
+            }
+        };
+        let forward = config_with(keys.iter().collect());
+        let backward = config_with(keys.iter().rev().collect());
+        let session = Session::in_memory();
+        let hash = |config: &Config| {
+            ConfigFingerprint::new(&session, config)
+                .expect("fingerprint")
+                .settings_sha256
+        };
+        assert_eq!(hash(&forward), hash(&backward));
+
+        let a = serde_json::from_str::<Value>(r#"{"b":1,"a":{"d":2,"c":3}}"#).unwrap();
+        let b = serde_json::from_str::<Value>(r#"{"a":{"c":3,"d":2},"b":1}"#).unwrap();
+        assert_eq!(canonical_json(a).to_string(), canonical_json(b).to_string());
+    }
+
+    #[test]
+    fn fingerprint_differences_name_version_and_settings() {
+        let base = ConfigFingerprint {
+            pi_version: "1.0.0".to_string(),
+            provider: Some("anthropic".to_string()),
+            model_id: Some("claude-sonnet-4".to_string()),
+            thinking_level: Some("high".to_string()),
+            settings_sha256: "a".to_string(),
+        };
+        assert!(base.differences(&base).is_empty());
+        let local = ConfigFingerprint {
+            pi_version: "1.1.0".to_string(),
+            settings_sha256: "b".to_string(),
+            ..base.clone()
+        };
+        assert_eq!(base.differences(&local).len(), 2);
+        assert_eq!(
+            base.describe_model(),
+            "anthropic/claude-sonnet-4 (thinking: high)"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Differences between two branches of a session (`pi sessions diff`).
+//!
+//! Both leaves are walked back to their last shared entry; everything after it is listed per
+//! side. Messages are reported with their role and text, along with model and thinking level
+//! changes. Labels, checkpoints and other bookkeeping entries are left out.
+
+use crate::error::{Error, Result};
+use crate::model::{ContentBlock, UserContent};
+use crate::session::{Session, SessionEntry, SessionMessage};
+use serde::Serialize;
+use std::fmt::Write as _;
+
+/// Longest line shown for a message in [`BranchDiff::to_text`].
+const PREVIEW_MAX_CHARS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BranchChange {
+    Message {
+        entry_id: String,
+        role: String,
+        text: String,
+    },
+    ModelChange {
+        entry_id: String,
+        provider: String,
+        model_id: String,
+    },
+    ThinkingLevelChange {
+        entry_id: String,
+        thinking_level: String,
+    },
+}
+
+/// The entries one branch has that the other does not, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchSide {
+    pub leaf_id: String,
+    pub changes: Vec<BranchChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchDiff {
+    /// Last entry on both branches; `None` when they share no entry.
+    pub common_ancestor: Option<String>,
+    pub left: BranchSide,
+    pub right: BranchSide,
+}
+
+impl Session {
+    /// Compare the branches ending at `left` and `right`, each an entry ID or a label.
+    pub fn diff_branches(&self, left: &str, right: &str) -> Result<BranchDiff> {
+        let left_id = self.resolve_branch_leaf(left)?;
+        let right_id = self.resolve_branch_leaf(right)?;
+        let left_path = self.get_path_to_entry(&left_id);
+        let right_path = self.get_path_to_entry(&right_id);
+
+        let shared = left_path
+            .iter()
+            .zip(&right_path)
+            .take_while(|(left, right)| left == right)
+            .count();
+        let common_ancestor = shared.checked_sub(1).map(|index| left_path[index].clone());
+
+        Ok(BranchDiff {
+            common_ancestor,
+            left: BranchSide {
+                changes: self.branch_changes(&left_path[shared..]),
+                leaf_id: left_id,
+            },
+            right: BranchSide {
+                changes: self.branch_changes(&right_path[shared..]),
+                leaf_id: right_id,
+            },
+        })
+    }
+
+    fn resolve_branch_leaf(&self, spec: &str) -> Result<String> {
+        if self.get_entry(spec).is_some() {
+            return Ok(spec.to_string());
+        }
+        self.find_label_target(spec)
+            .ok_or_else(|| Error::session(format!("Entry not found: {spec}")))
+    }
+
+    fn branch_changes(&self, ids: &[String]) -> Vec<BranchChange> {
+        ids.iter()
+            .filter_map(|id| {
+                let entry_id = id.clone();
+                match self.get_entry(id)? {
+                    SessionEntry::Message(entry) => {
+                        let (role, text) = message_role_and_text(&entry.message);
+                        Some(BranchChange::Message {
+                            entry_id,
+                            role: role.to_string(),
+                            text,
+                        })
+                    }
+                    SessionEntry::ModelChange(change) => Some(BranchChange::ModelChange {
+                        entry_id,
+                        provider: change.provider.clone(),
+                        model_id: change.model_id.clone(),
+                    }),
+                    SessionEntry::ThinkingLevelChange(change) => {
+                        Some(BranchChange::ThinkingLevelChange {
+                            entry_id,
+                            thinking_level: change.thinking_level.clone(),
+                        })
+                    }
+                    SessionEntry::BranchSummary(summary) => Some(BranchChange::Message {
+                        entry_id,
+                        role: "branchSummary".to_string(),
+                        text: summary.summary.clone(),
+                    }),
+                    SessionEntry::Compaction(compaction) => Some(BranchChange::Message {
+                        entry_id,
+                        role: "compactionSummary".to_string(),
+                        text: compaction.summary.clone(),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl BranchDiff {
+    /// Plain-text rendering: the shared entry, then each side's changes.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        match &self.common_ancestor {
+            Some(id) => {
+                let _ = writeln!(out, "Branches diverge after {id}");
+            }
+            None => {
+                let _ = writeln!(out, "Branches share no entries");
+            }
+        }
+        for (marker, side) in [("-", &self.left), ("+", &self.right)] {
+            let _ = writeln!(
+                out,
+                "\n{marker}{marker}{marker} {} ({} entries)",
+                side.leaf_id,
+                side.changes.len()
+            );
+            for change in &side.changes {
+                let _ = writeln!(out, "{marker} {}", describe_change(change));
+            }
+        }
+        out
+    }
+}
+
+fn describe_change(change: &BranchChange) -> String {
+    match change {
+        BranchChange::Message {
+            entry_id,
+            role,
+            text,
+        } => format!("[{entry_id}] {role}: {}", preview(text)),
+        BranchChange::ModelChange {
+            entry_id,
+            provider,
+            model_id,
+        } => format!("[{entry_id}] model -> {provider}/{model_id}"),
+        BranchChange::ThinkingLevelChange {
+            entry_id,
+            thinking_level,
+        } => format!("[{entry_id}] thinking -> {thinking_level}"),
+    }
+}
+
+fn message_role_and_text(message: &SessionMessage) -> (&'static str, String) {
+    match message {
+        SessionMessage::User { content, .. } => (
+            "user",
+            match content {
+                UserContent::Text(text) => text.clone(),
+                UserContent::Blocks(blocks) => blocks_text(blocks),
+            },
+        ),
+        SessionMessage::Assistant { message } => ("assistant", blocks_text(&message.content)),
+        SessionMessage::ToolResult {
+            tool_name, content, ..
+        } => (
+            "toolResult",
+            format!("{tool_name}: {}", blocks_text(content)),
+        ),
+        SessionMessage::Custom { content, .. } => ("custom", content.clone()),
+        SessionMessage::BashExecution { command, .. } => ("bashExecution", format!("$ {command}")),
+        SessionMessage::BranchSummary { summary, .. } => ("branchSummary", summary.clone()),
+        SessionMessage::CompactionSummary { summary, .. } => ("compactionSummary", summary.clone()),
+    }
+}
+
+/// Text blocks joined by newlines, with each tool call shown as `[tool: name]`.
+fn blocks_text(blocks: &[ContentBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.text.clone()),
+            ContentBlock::ToolCall(call) => Some(format!("[tool: {}]", call.name)),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn preview(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    if line.chars().count() <= PREVIEW_MAX_CHARS {
+        return line.to_string();
+    }
+    let mut out = line.chars().take(PREVIEW_MAX_CHARS - 1).collect::<String>();
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(text: &str) -> SessionMessage {
+        SessionMessage::User {
+            content: UserContent::Text(text.to_string()),
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn diff_lists_what_each_branch_added_after_the_fork() {
+        let mut session = Session::in_memory();
+        session.append_message(user("start"));
+        let fork = session.leaf_id.clone().unwrap();
+        session.append_message(user("try the parser fix"));
+        session.append_model_change("anthropic".to_string(), "claude-sonnet-4".to_string());
+        let left = session.leaf_id.clone().unwrap();
+
+        session.create_branch_from(&fork);
+        session.append_thinking_level_change("high".to_string());
+        session.append_message(user("try the lexer fix"));
+        let right = session.leaf_id.clone().unwrap();
+        session.add_label(&right, Some("lexer".to_string()));
+
+        let diff = session.diff_branches(&left, "lexer").unwrap();
+        assert_eq!(diff.common_ancestor.as_deref(), Some(fork.as_str()));
+        assert_eq!(diff.right.leaf_id, right);
+        assert_eq!(diff.left.changes.len(), 2);
+        assert!(matches!(
+            &diff.left.changes[0],
+            BranchChange::Message { role, text, .. } if role == "user" && text == "try the parser fix"
+        ));
+        assert!(matches!(
+            &diff.left.changes[1],
+            BranchChange::ModelChange { model_id, .. } if model_id == "claude-sonnet-4"
+        ));
+        assert!(matches!(
+            &diff.right.changes[0],
+            BranchChange::ThinkingLevelChange { thinking_level, .. } if thinking_level == "high"
+        ));
+
+        let text = diff.to_text();
+        assert!(text.contains(&format!("Branches diverge after {fork}")));
+        assert!(text.contains("+ ["));
+        assert!(text.contains("user: try the lexer fix"));
+
+        let err = session.diff_branches(&left, "missing").unwrap_err();
+        assert!(err.to_string().contains("Entry not found: missing"));
+    }
+}
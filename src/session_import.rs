@@ -0,0 +1,706 @@
+//! Import conversations written elsewhere as pi sessions (`pi sessions import`).
+//!
+//! The markdown format is a sequence of role headings, each followed by that turn's text:
+//!
+//! ```text
+//! ### User
+//! How do I parse a date?
+//!
+//! ### Assistant
+//! Use `chrono::NaiveDate::parse_from_str`.
+//! ```
+//!
+//! - headings may use any level (`#` to `######`), any case, and a trailing colon
+//! - headings inside fenced code blocks are part of the turn, not new turns
+//! - text before the first heading (a title, notes) is ignored
+//! - consecutive turns with the same role are merged
+//!
+//! Claude Code transcripts (`~/.claude/projects/*/<session>.jsonl`) are imported with their tool
+//! calls and results, and any pi session can be written back out in that format:
+//! - assistant lines sharing a message id are merged into one message
+//! - `tool_result` blocks become tool result messages; other user blocks stay user content
+//! - sidechain (subagent) and meta lines are skipped
+//! - the built-in tools are renamed both ways (`Bash`/`bash`, `Glob`/`find`, ...), along with
+//!   their differently named arguments (`file_path`/`path`, `old_string`/`oldText`, ...)
+
+use crate::error::{Error, Result};
+use crate::model::{
+    AssistantMessage, ContentBlock, ImageContent, Message, StopReason, TextContent,
+    ThinkingContent, ToolCall, Usage, UserContent,
+};
+use crate::session::{Session, SessionMessage};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Provider/model recorded on imported assistant turns.
+const IMPORT_PROVIDER: &str = "import";
+const IMPORT_MODEL: &str = "markdown";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedTurn {
+    pub role: ImportRole,
+    pub text: String,
+}
+
+/// The role named by a `### User` / `### Assistant` heading line.
+fn heading_role(line: &str) -> Option<ImportRole> {
+    let rest = line.trim_start();
+    let hashes = rest.chars().take_while(|ch| *ch == '#').count();
+    if !(1..=6).contains(&hashes) {
+        return None;
+    }
+    let title = rest[hashes..].trim().trim_end_matches(':').trim();
+    if title.eq_ignore_ascii_case("user") {
+        Some(ImportRole::User)
+    } else if title.eq_ignore_ascii_case("assistant") {
+        Some(ImportRole::Assistant)
+    } else {
+        None
+    }
+}
+
+/// Close the open turn, dropping it when empty and merging it into a same-role predecessor.
+fn finish_turn(current: Option<(ImportRole, Vec<&str>)>, turns: &mut Vec<ImportedTurn>) {
+    let Some((role, lines)) = current else {
+        return;
+    };
+    let text = lines.join("\n").trim().to_string();
+    if text.is_empty() {
+        return;
+    }
+    match turns.last_mut() {
+        Some(last) if last.role == role => {
+            last.text.push_str("\n\n");
+            last.text.push_str(&text);
+        }
+        _ => turns.push(ImportedTurn { role, text }),
+    }
+}
+
+/// Split a markdown conversation into turns.
+pub fn parse_markdown_conversation(markdown: &str) -> Result<Vec<ImportedTurn>> {
+    let mut turns: Vec<ImportedTurn> = Vec::new();
+    let mut current: Option<(ImportRole, Vec<&str>)> = None;
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some(role) = heading_role(line) {
+            finish_turn(current.take(), &mut turns);
+            current = Some((role, Vec::new()));
+            continue;
+        }
+        if let Some((_, lines)) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    finish_turn(current.take(), &mut turns);
+
+    if turns.is_empty() {
+        return Err(Error::validation(
+            "No conversation found: expected `### User` and `### Assistant` sections",
+        ));
+    }
+    Ok(turns)
+}
+
+/// Build an unsaved session from a markdown conversation, named `name` when given.
+pub fn session_from_markdown(
+    markdown: &str,
+    name: Option<String>,
+    session_dir: Option<PathBuf>,
+) -> Result<Session> {
+    let turns = parse_markdown_conversation(markdown)?;
+    let mut session = Session::create_with_dir(session_dir);
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    for turn in turns {
+        let message = match turn.role {
+            ImportRole::User => SessionMessage::User {
+                content: UserContent::Text(turn.text),
+                timestamp: Some(timestamp),
+            },
+            ImportRole::Assistant => SessionMessage::Assistant {
+                message: AssistantMessage {
+                    content: vec![ContentBlock::Text(TextContent::new(turn.text))],
+                    api: IMPORT_PROVIDER.to_string(),
+                    provider: IMPORT_PROVIDER.to_string(),
+                    model: IMPORT_MODEL.to_string(),
+                    usage: Usage::default(),
+                    stop_reason: StopReason::Stop,
+                    abort_reason: None,
+                    error_message: None,
+                    timestamp,
+                },
+            },
+        };
+        session.append_message(message);
+    }
+    if name.is_some() {
+        session.append_session_info(name);
+    }
+    Ok(session)
+}
+
+/// Claude Code's built-in tools, the pi tools they correspond to, and their argument renames
+/// as `(claude_code, pi)` pairs.
+const CLAUDE_CODE_TOOLS: &[(&str, &str, &[(&str, &str)])] = &[
+    ("Bash", "bash", &[]),
+    ("Read", "read", &[("file_path", "path")]),
+    ("Write", "write", &[("file_path", "path")]),
+    (
+        "Edit",
+        "edit",
+        &[
+            ("file_path", "path"),
+            ("old_string", "oldText"),
+            ("new_string", "newText"),
+        ],
+    ),
+    ("Grep", "grep", &[]),
+    ("Glob", "find", &[]),
+    ("LS", "ls", &[]),
+];
+
+/// Provider/API recorded on imported Claude Code turns, and the model when a line names none.
+const CLAUDE_CODE_PROVIDER: &str = "anthropic";
+const CLAUDE_CODE_API: &str = "anthropic-messages";
+const CLAUDE_CODE_MODEL: &str = "claude-code";
+
+/// Rename a tool call between Claude Code and pi; `to_pi` picks the direction.
+fn map_tool_call(name: &str, arguments: &Value, to_pi: bool) -> (String, Value) {
+    let Some((claude, pi, renames)) = CLAUDE_CODE_TOOLS
+        .iter()
+        .find(|(claude, pi, _)| if to_pi { *claude == name } else { *pi == name })
+    else {
+        return (name.to_string(), arguments.clone());
+    };
+    let mapped_name = if to_pi { pi } else { claude };
+    let Value::Object(fields) = arguments else {
+        return ((*mapped_name).to_string(), arguments.clone());
+    };
+    let fields = fields
+        .iter()
+        .map(|(key, value)| {
+            let renamed = renames
+                .iter()
+                .find_map(|&(claude_key, pi_key)| {
+                    if to_pi {
+                        (key == claude_key).then_some(pi_key)
+                    } else {
+                        (key == pi_key).then_some(claude_key)
+                    }
+                })
+                .unwrap_or(key.as_str());
+            (renamed.to_string(), value.clone())
+        })
+        .collect::<Map<_, _>>();
+    ((*mapped_name).to_string(), Value::Object(fields))
+}
+
+fn timestamp_millis(line: &Value) -> Option<i64> {
+    let timestamp = line.get("timestamp")?.as_str()?;
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|time| time.timestamp_millis())
+}
+
+fn rfc3339(millis: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Text and image blocks of Claude Code message content (a string or a block array).
+fn claude_code_blocks(content: &Value) -> Vec<ContentBlock> {
+    match content {
+        Value::String(text) => vec![ContentBlock::Text(TextContent::new(text.clone()))],
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| match block.get("type")?.as_str()? {
+                "text" => Some(ContentBlock::Text(TextContent::new(
+                    block.get("text")?.as_str()?,
+                ))),
+                "image" => {
+                    let source = block.get("source")?;
+                    Some(ContentBlock::Image(ImageContent {
+                        data: source.get("data")?.as_str()?.to_string(),
+                        mime_type: source.get("media_type")?.as_str()?.to_string(),
+                    }))
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn claude_code_usage(usage: Option<&Value>) -> Usage {
+    let tokens = |key: &str| {
+        usage
+            .and_then(|usage| usage.get(key))
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+    };
+    let usage = Usage {
+        input: tokens("input_tokens"),
+        output: tokens("output_tokens"),
+        cache_read: tokens("cache_read_input_tokens"),
+        cache_write: tokens("cache_creation_input_tokens"),
+        ..Usage::default()
+    };
+    Usage {
+        total_tokens: usage.input + usage.output + usage.cache_read + usage.cache_write,
+        ..usage
+    }
+}
+
+/// An assistant line's content blocks, with tool calls renamed to pi's tools.
+fn claude_code_assistant_blocks(content: &Value) -> Vec<ContentBlock> {
+    let Value::Array(blocks) = content else {
+        return claude_code_blocks(content);
+    };
+    blocks
+        .iter()
+        .filter_map(|block| match block.get("type")?.as_str()? {
+            "thinking" => Some(ContentBlock::Thinking(ThinkingContent {
+                thinking: block.get("thinking")?.as_str()?.to_string(),
+                thinking_signature: block
+                    .get("signature")
+                    .and_then(Value::as_str)
+                    .filter(|signature| !signature.is_empty())
+                    .map(str::to_string),
+                redacted: false,
+            })),
+            "redacted_thinking" => Some(ContentBlock::Thinking(ThinkingContent {
+                thinking: block.get("data")?.as_str()?.to_string(),
+                thinking_signature: None,
+                redacted: true,
+            })),
+            "tool_use" => {
+                let (name, arguments) = map_tool_call(
+                    block.get("name")?.as_str()?,
+                    block.get("input").unwrap_or(&Value::Null),
+                    true,
+                );
+                Some(ContentBlock::ToolCall(ToolCall {
+                    id: block.get("id")?.as_str()?.to_string(),
+                    name,
+                    arguments,
+                    thought_signature: None,
+                }))
+            }
+            _ => claude_code_blocks(&Value::Array(vec![block.clone()]))
+                .into_iter()
+                .next(),
+        })
+        .collect()
+}
+
+impl Session {
+    /// Build an unsaved session from a Claude Code JSONL transcript.
+    #[allow(clippy::too_many_lines)]
+    pub fn import_claude_code(jsonl: &str, session_dir: Option<PathBuf>) -> Result<Self> {
+        let mut session = Self::create_with_dir(session_dir);
+        let mut tool_names: HashMap<String, String> = HashMap::new();
+        // The assistant message being assembled, with its Claude Code message id.
+        let mut pending: Option<(String, AssistantMessage)> = None;
+        let mut name = None;
+        let mut cwd = None;
+
+        for (index, line) in jsonl.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line: Value = serde_json::from_str(line).map_err(|err| {
+                Error::validation(format!("Line {} is not a JSON object: {err}", index + 1))
+            })?;
+            let kind = line.get("type").and_then(Value::as_str).unwrap_or_default();
+            if kind == "summary" {
+                name = name.or_else(|| {
+                    line.get("summary")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                });
+                continue;
+            }
+            let skipped = ["isSidechain", "isMeta"]
+                .iter()
+                .any(|flag| line.get(flag).and_then(Value::as_bool) == Some(true));
+            let Some(message) = line.get("message").filter(|_| !skipped) else {
+                continue;
+            };
+            if cwd.is_none() {
+                cwd = line.get("cwd").and_then(Value::as_str).map(str::to_string);
+            }
+            let timestamp = timestamp_millis(&line).unwrap_or_default();
+            let content = message.get("content").unwrap_or(&Value::Null);
+
+            if kind == "assistant" {
+                let id = message
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let blocks = claude_code_assistant_blocks(content);
+                for block in &blocks {
+                    if let ContentBlock::ToolCall(call) = block {
+                        tool_names.insert(call.id.clone(), call.name.clone());
+                    }
+                }
+                let stop_reason = match message.get("stop_reason").and_then(Value::as_str) {
+                    Some("tool_use") => StopReason::ToolUse,
+                    Some("max_tokens") => StopReason::Length,
+                    _ => StopReason::Stop,
+                };
+                match &mut pending {
+                    Some((pending_id, assistant)) if !id.is_empty() && *pending_id == id => {
+                        assistant.content.extend(blocks);
+                        assistant.stop_reason = stop_reason;
+                        assistant.usage = claude_code_usage(message.get("usage"));
+                    }
+                    _ => {
+                        flush_assistant(&mut session, pending.take());
+                        pending = Some((
+                            id,
+                            AssistantMessage {
+                                content: blocks,
+                                api: CLAUDE_CODE_API.to_string(),
+                                provider: CLAUDE_CODE_PROVIDER.to_string(),
+                                model: message
+                                    .get("model")
+                                    .and_then(Value::as_str)
+                                    .unwrap_or(CLAUDE_CODE_MODEL)
+                                    .to_string(),
+                                usage: claude_code_usage(message.get("usage")),
+                                stop_reason,
+                                abort_reason: None,
+                                error_message: None,
+                                timestamp,
+                            },
+                        ));
+                    }
+                }
+                continue;
+            }
+            if kind != "user" {
+                continue;
+            }
+            flush_assistant(&mut session, pending.take());
+
+            let results =
+                content.as_array().into_iter().flatten().filter(|block| {
+                    block.get("type").and_then(Value::as_str) == Some("tool_result")
+                });
+            for result in results {
+                let tool_call_id = result
+                    .get("tool_use_id")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                session.append_message(SessionMessage::ToolResult {
+                    tool_name: tool_names.get(&tool_call_id).cloned().unwrap_or_default(),
+                    tool_call_id,
+                    content: claude_code_blocks(result.get("content").unwrap_or(&Value::Null)),
+                    details: None,
+                    is_error: result.get("is_error").and_then(Value::as_bool) == Some(true),
+                    timestamp: Some(timestamp),
+                });
+            }
+            match content {
+                Value::String(text) => {
+                    session.append_message(SessionMessage::User {
+                        content: UserContent::Text(text.clone()),
+                        timestamp: Some(timestamp),
+                    });
+                }
+                Value::Array(blocks) => {
+                    let other = blocks
+                        .iter()
+                        .filter(|block| {
+                            block.get("type").and_then(Value::as_str) != Some("tool_result")
+                        })
+                        .cloned()
+                        .collect();
+                    let user_blocks = claude_code_blocks(&Value::Array(other));
+                    if !user_blocks.is_empty() {
+                        session.append_message(SessionMessage::User {
+                            content: UserContent::Blocks(user_blocks),
+                            timestamp: Some(timestamp),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        flush_assistant(&mut session, pending.take());
+
+        if session.to_messages_for_current_path().is_empty() {
+            return Err(Error::validation(
+                "No conversation found: expected Claude Code `user` and `assistant` lines",
+            ));
+        }
+        if let Some(cwd) = cwd {
+            session.header.cwd = cwd;
+        }
+        if name.is_some() {
+            session.append_session_info(name);
+        }
+        Ok(session)
+    }
+
+    /// The current branch as a Claude Code JSONL transcript, one line per message.
+    pub fn export_claude_code(&self) -> String {
+        let mut lines = Vec::new();
+        let mut parent: Option<String> = None;
+        for message in self.to_messages_for_current_path() {
+            let (kind, timestamp, body) = match &message {
+                Message::User(user) => {
+                    let content = match &user.content {
+                        UserContent::Text(text) => json!(text),
+                        UserContent::Blocks(blocks) => json!(export_blocks(blocks)),
+                    };
+                    (
+                        "user",
+                        user.timestamp,
+                        json!({ "role": "user", "content": content }),
+                    )
+                }
+                Message::Assistant(assistant) => (
+                    "assistant",
+                    assistant.timestamp,
+                    export_assistant(assistant),
+                ),
+                Message::ToolResult(result) => (
+                    "user",
+                    result.timestamp,
+                    json!({
+                        "role": "user",
+                        "content": [{
+                            "type": "tool_result",
+                            "tool_use_id": result.tool_call_id,
+                            "content": export_blocks(&result.content),
+                            "is_error": result.is_error,
+                        }],
+                    }),
+                ),
+                _ => continue,
+            };
+            let uuid = uuid::Uuid::new_v4().to_string();
+            let line = json!({
+                "parentUuid": parent,
+                "isSidechain": false,
+                "userType": "external",
+                "cwd": self.header.cwd,
+                "sessionId": self.header.id,
+                "type": kind,
+                "message": body,
+                "uuid": uuid,
+                "timestamp": rfc3339(timestamp),
+            });
+            lines.push(line.to_string());
+            parent = Some(uuid);
+        }
+        if let Some(name) = self.get_name() {
+            let summary = json!({ "type": "summary", "summary": name, "leafUuid": parent });
+            lines.insert(0, summary.to_string());
+        }
+        let mut jsonl = lines.join("\n");
+        jsonl.push('\n');
+        jsonl
+    }
+}
+
+/// Push the assistant message being assembled, if any.
+fn flush_assistant(session: &mut Session, pending: Option<(String, AssistantMessage)>) {
+    if let Some((_, message)) = pending {
+        session.append_message(SessionMessage::Assistant { message });
+    }
+}
+
+fn export_blocks(blocks: &[ContentBlock]) -> Vec<Value> {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(json!({ "type": "text", "text": text.text })),
+            ContentBlock::Image(image) => Some(json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": image.mime_type, "data": image.data },
+            })),
+            _ => None,
+        })
+        .collect()
+}
+
+fn export_assistant(assistant: &AssistantMessage) -> Value {
+    let content = assistant
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Thinking(thinking) if thinking.redacted => {
+                Some(json!({ "type": "redacted_thinking", "data": thinking.thinking }))
+            }
+            ContentBlock::Thinking(thinking) => Some(json!({
+                "type": "thinking",
+                "thinking": thinking.thinking,
+                "signature": thinking.thinking_signature.clone().unwrap_or_default(),
+            })),
+            ContentBlock::ToolCall(call) => {
+                let (name, input) = map_tool_call(&call.name, &call.arguments, false);
+                Some(json!({ "type": "tool_use", "id": call.id, "name": name, "input": input }))
+            }
+            other => export_blocks(std::slice::from_ref(other)).pop(),
+        })
+        .collect::<Vec<_>>();
+    let stop_reason = match assistant.stop_reason {
+        StopReason::ToolUse => "tool_use",
+        StopReason::Length => "max_tokens",
+        _ => "end_turn",
+    };
+    json!({
+        "id": format!("msg_{}", uuid::Uuid::new_v4().simple()),
+        "type": "message",
+        "role": "assistant",
+        "model": assistant.model,
+        "content": content,
+        "stop_reason": stop_reason,
+        "usage": {
+            "input_tokens": assistant.usage.input,
+            "output_tokens": assistant.usage.output,
+            "cache_read_input_tokens": assistant.usage.cache_read,
+            "cache_creation_input_tokens": assistant.usage.cache_write,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_role_sections_and_skips_fenced_headings() {
+        let markdown = "# Notes from last week\n\n### User\nHow do I list files?\n\n## assistant:\nRun:\n\n```sh\n### User\nls -la\n```\n\n### Assistant\nAdd `-R` to recurse.\n\n### User\n\n### User\nThanks!\n";
+        let turns = parse_markdown_conversation(markdown).expect("parse");
+        assert_eq!(
+            turns,
+            vec![
+                ImportedTurn {
+                    role: ImportRole::User,
+                    text: "How do I list files?".to_string(),
+                },
+                ImportedTurn {
+                    role: ImportRole::Assistant,
+                    text: "Run:\n\n```sh\n### User\nls -la\n```\n\nAdd `-R` to recurse."
+                        .to_string(),
+                },
+                ImportedTurn {
+                    role: ImportRole::User,
+                    text: "Thanks!".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_markdown_without_role_sections() {
+        let err = parse_markdown_conversation("# Title\n\nJust notes.\n").unwrap_err();
+        assert!(err.to_string().contains("### User"));
+    }
+
+    #[test]
+    fn builds_a_named_session() {
+        let session = session_from_markdown(
+            "### User\nhi\n### Assistant\nhello\n",
+            Some("notes".to_string()),
+            None,
+        )
+        .expect("import");
+        let messages = session.to_messages_for_current_path();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(session.get_name().as_deref(), Some("notes"));
+    }
+
+    const CLAUDE_CODE_TRANSCRIPT: &str = concat!(
+        r#"{"type":"summary","summary":"List the repo","leafUuid":"u4"}"#,
+        "\n",
+        r#"{"parentUuid":null,"isSidechain":false,"cwd":"/work/repo","sessionId":"s1","type":"user","message":{"role":"user","content":"What is in src?"},"uuid":"u1","timestamp":"2025-06-01T10:00:00.000Z"}"#,
+        "\n",
+        r#"{"parentUuid":"u1","type":"assistant","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-sonnet-4","content":[{"type":"text","text":"Let me look."}],"stop_reason":null,"usage":{"input_tokens":10,"output_tokens":2}},"uuid":"u2","timestamp":"2025-06-01T10:00:01.000Z"}"#,
+        "\n",
+        r#"{"parentUuid":"u2","type":"assistant","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-sonnet-4","content":[{"type":"tool_use","id":"toolu_1","name":"Read","input":{"file_path":"src/lib.rs"}}],"stop_reason":"tool_use","usage":{"input_tokens":10,"output_tokens":8}},"uuid":"u3","timestamp":"2025-06-01T10:00:02.000Z"}"#,
+        "\n",
+        r#"{"parentUuid":"u3","isSidechain":true,"type":"user","message":{"role":"user","content":"subagent prompt"},"uuid":"side","timestamp":"2025-06-01T10:00:02.500Z"}"#,
+        "\n",
+        r#"{"parentUuid":"u3","type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_1","content":[{"type":"text","text":"pub mod agent;"}]}]},"uuid":"u4","timestamp":"2025-06-01T10:00:03.000Z"}"#,
+        "\n",
+        r#"{"parentUuid":"u4","type":"assistant","message":{"id":"msg_2","type":"message","role":"assistant","model":"claude-sonnet-4","content":[{"type":"text","text":"One module: agent."}],"stop_reason":"end_turn","usage":{"input_tokens":20,"output_tokens":5}},"uuid":"u5","timestamp":"2025-06-01T10:00:04.000Z"}"#,
+        "\n",
+    );
+
+    #[test]
+    fn imports_a_claude_code_transcript_with_tool_calls() {
+        let session = Session::import_claude_code(CLAUDE_CODE_TRANSCRIPT, None).expect("import");
+        assert_eq!(session.header.cwd, "/work/repo");
+        assert_eq!(session.get_name().as_deref(), Some("List the repo"));
+
+        let messages = session.to_messages_for_current_path();
+        assert_eq!(messages.len(), 4);
+        let Message::Assistant(first) = &messages[1] else {
+            panic!("expected assistant, got {:?}", messages[1]);
+        };
+        assert_eq!(first.content.len(), 2, "lines of msg_1 are merged");
+        assert_eq!(first.stop_reason, StopReason::ToolUse);
+        let ContentBlock::ToolCall(call) = &first.content[1] else {
+            panic!("expected tool call");
+        };
+        assert_eq!(call.name, "read");
+        assert_eq!(call.arguments, json!({ "path": "src/lib.rs" }));
+        let Message::ToolResult(result) = &messages[2] else {
+            panic!("expected tool result, got {:?}", messages[2]);
+        };
+        assert_eq!(result.tool_name, "read");
+        assert_eq!(crate::agent::blocks_text(&result.content), "pub mod agent;");
+    }
+
+    #[test]
+    fn claude_code_export_round_trips() {
+        let session = Session::import_claude_code(CLAUDE_CODE_TRANSCRIPT, None).expect("import");
+        let exported = session.export_claude_code();
+        let lines = exported
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).expect("json line"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0]["summary"], "List the repo");
+        assert_eq!(lines[2]["message"]["content"][1]["name"], "Read");
+        assert_eq!(
+            lines[2]["message"]["content"][1]["input"]["file_path"],
+            "src/lib.rs"
+        );
+        assert_eq!(lines[3]["parentUuid"], lines[2]["uuid"]);
+        assert_eq!(lines[3]["timestamp"], "2025-06-01T10:00:03.000Z");
+
+        let reimported = Session::import_claude_code(&exported, None).expect("reimport");
+        let texts = |session: &Session| {
+            session
+                .to_messages_for_current_path()
+                .iter()
+                .map(|message| serde_json::to_value(message).expect("message"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(texts(&reimported), texts(&session));
+        assert_eq!(reimported.get_name().as_deref(), Some("List the repo"));
+    }
+}
@@ -2,11 +2,15 @@
 
 use crate::config::Config;
 use crate::error::{Error, Result};
-use crate::session::{Session, SessionEntry, SessionHeader};
+use crate::session::{
+    Session, SessionEntry, SessionHeader, is_compressed_session_path, read_jsonl_session_file,
+};
 use fs4::fs_std::FileExt;
 use sqlmodel_core::Value;
 use sqlmodel_sqlite::{OpenFlags, SqliteConfig, SqliteConnection};
+use std::fmt::Write as _;
 use std::fs::{self, File};
+use std::io::{Read as _, Seek as _, SeekFrom, Write as _};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
@@ -195,6 +199,183 @@ impl SessionIndex {
         Ok(true)
     }
 
+    /// Run incremental index maintenance.
+    ///
+    /// Rows for session files that no longer exist are pruned in batches of
+    /// `options.batch_size`, releasing the index lock and sleeping `options.pause`
+    /// between batches so an active session can keep writing. Progress is saved
+    /// in the `meta` table, so an interrupted run resumes where it stopped.
+    /// Afterwards the index is analyzed and, when it has free pages, compacted
+    /// with `VACUUM INTO` and swapped in only if nothing was written meanwhile.
+    pub fn vacuum(&self, options: &VacuumOptions) -> Result<VacuumReport> {
+        let mut report = VacuumReport {
+            bytes_before: fs::metadata(&self.db_path).map_or(0, |meta| meta.len()),
+            ..VacuumReport::default()
+        };
+        let batch_size = options.batch_size.max(1);
+
+        let mut cursor = self.with_lock(|conn| {
+            init_schema(conn)?;
+            read_meta(conn, META_VACUUM_CURSOR)
+        })?;
+        report.resumed = cursor.is_some();
+
+        loop {
+            let (scanned, next_cursor, pruned) = self.with_lock(|conn| {
+                let rows = conn
+                    .query_sync(
+                        "SELECT path FROM sessions WHERE path > ?1 ORDER BY path LIMIT ?2",
+                        &[
+                            Value::Text(cursor.clone().unwrap_or_default()),
+                            Value::BigInt(i64::try_from(batch_size).unwrap_or(i64::MAX)),
+                        ],
+                    )
+                    .map_err(|e| Error::session(format!("Query failed: {e}")))?;
+
+                let mut last = None;
+                let mut scanned = 0;
+                let mut pruned = 0;
+                for row in rows {
+                    let path: String = row
+                        .get_named("path")
+                        .map_err(|e| Error::session(format!("get path: {e}")))?;
+                    scanned += 1;
+                    if !Path::new(&path).exists() {
+                        conn.execute_sync(
+                            "DELETE FROM sessions WHERE path=?1",
+                            &[Value::Text(path.clone())],
+                        )
+                        .map_err(|e| Error::session(format!("Delete failed: {e}")))?;
+                        pruned += 1;
+                    }
+                    last = Some(path);
+                }
+
+                match &last {
+                    Some(path) => write_meta(conn, META_VACUUM_CURSOR, path)?,
+                    None => delete_meta(conn, META_VACUUM_CURSOR)?,
+                }
+                Ok((scanned, last, pruned))
+            })?;
+
+            report.scanned += scanned;
+            report.pruned += pruned;
+            cursor = next_cursor;
+            if cursor.is_none() || scanned < batch_size {
+                break;
+            }
+            std::thread::sleep(options.pause);
+        }
+
+        let generation = self.with_lock(|conn| {
+            delete_meta(conn, META_VACUUM_CURSOR)?;
+            conn.execute_raw("ANALYZE")
+                .map_err(|e| Error::session(format!("ANALYZE failed: {e}")))?;
+            let free_pages = pragma_i64(conn, "freelist_count")?;
+            (free_pages > 0).then(|| index_generation(conn)).transpose()
+        })?;
+        report.analyzed = true;
+
+        if let Some(generation) = generation {
+            std::thread::sleep(options.pause);
+            report.compacted = self.compact(&generation)?;
+        }
+
+        self.with_lock(|conn| write_meta(conn, META_LAST_VACUUM, &current_epoch_ms()))?;
+        report.bytes_after = fs::metadata(&self.db_path).map_or(0, |meta| meta.len());
+        Ok(report)
+    }
+
+    /// Run [`Self::vacuum`] if the last run is older than `max_age`.
+    pub fn vacuum_if_due(&self, max_age: Duration, options: &VacuumOptions) -> Result<bool> {
+        let last = self.with_lock(|conn| {
+            init_schema(conn)?;
+            read_meta(conn, META_LAST_VACUUM)
+        })?;
+        let last_ms = last.and_then(|value| value.parse::<i64>().ok());
+        let max_age_ms = i64::try_from(max_age.as_millis()).unwrap_or(i64::MAX);
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        if last_ms.is_some_and(|last_ms| now_ms.saturating_sub(last_ms) < max_age_ms) {
+            return Ok(false);
+        }
+        self.vacuum(options)?;
+        Ok(true)
+    }
+
+    /// Delete or archive the sessions matching `options`, after reindexing so every file on
+    /// disk is considered. Pruned sessions are removed from the index.
+    pub fn prune(&self, options: &PruneOptions) -> Result<PruneReport> {
+        self.reindex_all()?;
+        let sessions = self.list_sessions(None)?;
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let mut report = PruneReport {
+            archived: options.archive_dir.is_some(),
+            dry_run: options.dry_run,
+            ..PruneReport::default()
+        };
+        for meta in select_for_prune(sessions, options, now_ms) {
+            if !options.dry_run {
+                let path = Path::new(&meta.path);
+                // A session open in another pi process is left alone rather than removed
+                // from under its writer.
+                let lock_file = crate::session::open_session_lock_file(path)?;
+                let Ok(lock) = crate::session::lock_session_file(&lock_file, path, Duration::ZERO)
+                else {
+                    report.locked.push(meta.path);
+                    continue;
+                };
+                match &options.archive_dir {
+                    Some(dir) => archive_session_file(path, self.sessions_root(), dir)?,
+                    None => crate::session_picker::delete_session_file(path)?,
+                }
+                self.delete_session_path(path)?;
+                drop(lock);
+                drop(lock_file);
+                let _ = fs::remove_file(crate::session::session_lock_path(path));
+            }
+            report.reclaimed_bytes += meta.size_bytes;
+            report.sessions.push(meta);
+        }
+        Ok(report)
+    }
+
+    /// Write a compacted copy with `VACUUM INTO` (readers and writers are not
+    /// blocked), then swap it in under the lock unless the index changed.
+    fn compact(&self, generation: &(i64, Option<String>)) -> Result<bool> {
+        let snapshot = self.db_path.with_extension("sqlite.vacuum");
+        if snapshot.exists() {
+            fs::remove_file(&snapshot)?;
+        }
+
+        let conn = self.open_connection()?;
+        conn.execute_sync(
+            "VACUUM INTO ?1",
+            &[Value::Text(snapshot.to_string_lossy().to_string())],
+        )
+        .map_err(|e| Error::session(format!("VACUUM INTO failed: {e}")))?;
+        drop(conn);
+
+        // Swap with the lock held but no connection open on the old file.
+        let lock_file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.lock_path)?;
+        let _lock = lock_file_guard(&lock_file, Duration::from_secs(5))?;
+        let conn = self.open_connection()?;
+        if index_generation(&conn)? != *generation {
+            drop(conn);
+            fs::remove_file(&snapshot)?;
+            return Ok(false);
+        }
+        conn.execute_raw("PRAGMA wal_checkpoint(TRUNCATE)")
+            .map_err(|e| Error::session(format!("WAL checkpoint failed: {e}")))?;
+        drop(conn);
+        fs::rename(&snapshot, &self.db_path)?;
+        Ok(true)
+    }
+
     fn with_lock<T>(&self, f: impl FnOnce(&SqliteConnection) -> Result<T>) -> Result<T> {
         if let Some(parent) = self.db_path.parent() {
             fs::create_dir_all(parent)?;
@@ -206,7 +387,11 @@ impl SessionIndex {
             .truncate(false)
             .open(&self.lock_path)?;
         let _lock = lock_file_guard(&lock_file, Duration::from_secs(5))?;
+        let conn = self.open_connection()?;
+        f(&conn)
+    }
 
+    fn open_connection(&self) -> Result<SqliteConnection> {
         let config = SqliteConfig::file(self.db_path.to_string_lossy())
             .flags(OpenFlags::create_read_write())
             .busy_timeout(5000);
@@ -224,7 +409,7 @@ impl SessionIndex {
         conn.execute_raw("PRAGMA foreign_keys = ON")
             .map_err(|e| Error::session(format!("PRAGMA foreign_keys: {e}")))?;
 
-        f(&conn)
+        Ok(conn)
     }
 
     fn sessions_root(&self) -> &Path {
@@ -264,6 +449,221 @@ fn init_schema(conn: &SqliteConnection) -> Result<()> {
     Ok(())
 }
 
+const META_VACUUM_CURSOR: &str = "vacuum_cursor";
+const META_LAST_VACUUM: &str = "last_vacuum_epoch_ms";
+
+/// Tuning for [`SessionIndex::vacuum`].
+#[derive(Debug, Clone)]
+pub struct VacuumOptions {
+    /// Rows checked per lock acquisition.
+    pub batch_size: usize,
+    /// Sleep between batches so concurrent writers can take the lock.
+    pub pause: Duration,
+}
+
+impl Default for VacuumOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 200,
+            pause: Duration::from_millis(50),
+        }
+    }
+}
+
+/// Which sessions [`SessionIndex::prune`] removes; a session matching any criterion goes.
+#[derive(Debug, Clone, Default)]
+pub struct PruneOptions {
+    /// Sessions last modified longer ago than this.
+    pub older_than: Option<Duration>,
+    /// Sessions with fewer messages than this.
+    pub min_messages: Option<u64>,
+    /// Prune the least recently modified sessions until the rest fit in this many bytes.
+    pub max_total_bytes: Option<u64>,
+    /// Move pruned sessions under this directory, compressed, instead of deleting them.
+    pub archive_dir: Option<PathBuf>,
+    /// Report what would be pruned without touching any file.
+    pub dry_run: bool,
+}
+
+/// What a [`SessionIndex::prune`] run did.
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    /// Pruned sessions, most recently modified first.
+    pub sessions: Vec<SessionMeta>,
+    /// Bytes freed in the sessions directory.
+    pub reclaimed_bytes: u64,
+    pub archived: bool,
+    pub dry_run: bool,
+    /// Sessions skipped because another process holds their lock.
+    pub locked: Vec<String>,
+}
+
+impl PruneReport {
+    /// One-line summary, e.g. `Deleted 3 sessions, reclaimed 1.2MB`.
+    pub fn summary(&self) -> String {
+        let verb = match (self.dry_run, self.archived) {
+            (true, true) => "Would archive",
+            (true, false) => "Would delete",
+            (false, true) => "Archived",
+            (false, false) => "Deleted",
+        };
+        let count = self.sessions.len();
+        let noun = if count == 1 { "session" } else { "sessions" };
+        let bytes = usize::try_from(self.reclaimed_bytes).unwrap_or(usize::MAX);
+        let reclaimed = if self.dry_run {
+            "reclaiming"
+        } else {
+            "reclaimed"
+        };
+        let mut summary = format!(
+            "{verb} {count} {noun}, {reclaimed} {}",
+            crate::tools::format_size(bytes)
+        );
+        if !self.locked.is_empty() {
+            let _ = write!(summary, "; skipped {} in use", self.locked.len());
+        }
+        summary
+    }
+}
+
+/// Sessions to prune from `sessions` (ordered most recently modified first).
+fn select_for_prune(
+    sessions: Vec<SessionMeta>,
+    options: &PruneOptions,
+    now_ms: i64,
+) -> Vec<SessionMeta> {
+    let cutoff_ms = options
+        .older_than
+        .map(|age| now_ms.saturating_sub(i64::try_from(age.as_millis()).unwrap_or(i64::MAX)));
+    let mut kept_bytes = 0_u64;
+    sessions
+        .into_iter()
+        .filter(|meta| {
+            let stale = cutoff_ms.is_some_and(|cutoff| meta.last_modified_ms < cutoff);
+            let short = options
+                .min_messages
+                .is_some_and(|min| meta.message_count < min);
+            if stale || short {
+                return true;
+            }
+            kept_bytes += meta.size_bytes;
+            options.max_total_bytes.is_some_and(|cap| kept_bytes > cap)
+        })
+        .collect()
+}
+
+/// Move `path` to the same place under `archive_dir` as it had under `sessions_root`. JSONL
+/// sessions are stored as `.jsonl.zst`.
+fn archive_session_file(path: &Path, sessions_root: &Path, archive_dir: &Path) -> Result<()> {
+    let relative = path.strip_prefix(sessions_root).ok().map_or_else(
+        || PathBuf::from(path.file_name().unwrap_or_default()),
+        Path::to_path_buf,
+    );
+    let mut target = archive_dir.join(relative);
+    let mut bytes = fs::read(path)?;
+    if target.extension().is_some_and(|ext| ext == "jsonl") {
+        if !crate::session::is_zstd_compressed(&bytes) {
+            bytes = zstd::stream::encode_all(bytes.as_slice(), 3)
+                .map_err(|e| Error::session(format!("Failed to compress session file: {e}")))?;
+        }
+        let mut name = target.file_name().unwrap_or_default().to_os_string();
+        name.push(".zst");
+        target.set_file_name(name);
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if target.exists() {
+        return Err(Error::session(format!(
+            "Archive already has {}",
+            target.display()
+        )));
+    }
+    // Rename a complete temp file into place before removing the source, so a crash never
+    // leaves a partial archive as the only copy.
+    let temp_file = tempfile::NamedTempFile::new_in(target.parent().unwrap_or(archive_dir))?;
+    fs::write(temp_file.path(), bytes)?;
+    temp_file.persist_noclobber(&target).map_err(|e| {
+        Error::session(format!(
+            "Failed to archive to {}: {}",
+            target.display(),
+            e.error
+        ))
+    })?;
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// What a [`SessionIndex::vacuum`] run did.
+#[derive(Debug, Clone, Default)]
+pub struct VacuumReport {
+    /// The run continued from a cursor left by an interrupted run.
+    pub resumed: bool,
+    pub scanned: usize,
+    pub pruned: usize,
+    pub analyzed: bool,
+    pub compacted: bool,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+fn read_meta(conn: &SqliteConnection, key: &str) -> Result<Option<String>> {
+    let rows = conn
+        .query_sync(
+            "SELECT value FROM meta WHERE key=?1 LIMIT 1",
+            &[Value::Text(key.to_string())],
+        )
+        .map_err(|e| Error::session(format!("Query meta failed: {e}")))?;
+    rows.into_iter()
+        .next()
+        .map(|row| {
+            row.get_named::<String>("value")
+                .map_err(|e| Error::session(format!("get meta value: {e}")))
+        })
+        .transpose()
+}
+
+fn write_meta(conn: &SqliteConnection, key: &str, value: &str) -> Result<()> {
+    conn.execute_sync(
+        "INSERT INTO meta (key,value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value=excluded.value",
+        &[Value::Text(key.to_string()), Value::Text(value.to_string())],
+    )
+    .map_err(|e| Error::session(format!("Meta update failed: {e}")))?;
+    Ok(())
+}
+
+fn delete_meta(conn: &SqliteConnection, key: &str) -> Result<()> {
+    conn.execute_sync(
+        "DELETE FROM meta WHERE key=?1",
+        &[Value::Text(key.to_string())],
+    )
+    .map_err(|e| Error::session(format!("Meta delete failed: {e}")))?;
+    Ok(())
+}
+
+fn pragma_i64(conn: &SqliteConnection, pragma: &str) -> Result<i64> {
+    let rows = conn
+        .query_sync(&format!("PRAGMA {pragma}"), &[])
+        .map_err(|e| Error::session(format!("PRAGMA {pragma}: {e}")))?;
+    rows.into_iter().next().map_or(Ok(0), |row| {
+        row.get_named::<i64>(pragma)
+            .map_err(|e| Error::session(format!("PRAGMA {pragma}: {e}")))
+    })
+}
+
+/// Row count plus last sync time; every index write bumps one of them.
+fn index_generation(conn: &SqliteConnection) -> Result<(i64, Option<String>)> {
+    let rows = conn
+        .query_sync("SELECT COUNT(*) AS count FROM sessions", &[])
+        .map_err(|e| Error::session(format!("Query failed: {e}")))?;
+    let count = rows.into_iter().next().map_or(Ok(0), |row| {
+        row.get_named::<i64>("count")
+            .map_err(|e| Error::session(format!("get count: {e}")))
+    })?;
+    Ok((count, read_meta(conn, "last_sync_epoch_ms")?))
+}
+
 fn row_to_meta(row: &sqlmodel_core::Row) -> Result<SessionMeta> {
     Ok(SessionMeta {
         path: row
@@ -318,7 +718,7 @@ fn build_meta(
 
 fn build_meta_from_file(path: &Path) -> Result<SessionMeta> {
     match path.extension().and_then(|ext| ext.to_str()) {
-        Some("jsonl") => build_meta_from_jsonl(path),
+        Some("jsonl" | "zst") => build_meta_from_jsonl(path),
         #[cfg(feature = "sqlite-sessions")]
         Some("sqlite") => build_meta_from_sqlite(path),
         _ => build_meta_from_jsonl(path),
@@ -326,7 +726,7 @@ fn build_meta_from_file(path: &Path) -> Result<SessionMeta> {
 }
 
 fn build_meta_from_jsonl(path: &Path) -> Result<SessionMeta> {
-    let content = fs::read_to_string(path)
+    let content = read_jsonl_session_file(path)
         .map_err(|err| Error::session(format!("Read session file {}: {err}", path.display())))?;
     let mut lines = content.lines();
     let header: SessionHeader = lines
@@ -400,6 +800,7 @@ fn file_stats(path: &Path) -> Result<(i64, u64)> {
 fn is_session_file_path(path: &Path) -> bool {
     match path.extension().and_then(|ext| ext.to_str()) {
         Some("jsonl") => true,
+        Some("zst") => is_compressed_session_path(path),
         #[cfg(feature = "sqlite-sessions")]
         Some("sqlite") => true,
         _ => false,
@@ -426,24 +827,47 @@ fn current_epoch_ms() -> String {
 }
 
 fn lock_file_guard(file: &File, timeout: Duration) -> Result<LockGuard<'_>> {
+    acquire_pid_lock(file, timeout).map_err(|holder| {
+        Error::session(match holder {
+            Some(pid) => format!("Timed out waiting for session index lock held by pid {pid}"),
+            None => "Timed out waiting for session index lock".to_string(),
+        })
+    })
+}
+
+/// Exclusively lock `file` (flock on Unix, `LockFileEx` on Windows) and record this process's
+/// pid in it. After `timeout`, fails with the pid of the holder when it can be read.
+pub(crate) fn acquire_pid_lock(
+    file: &File,
+    timeout: Duration,
+) -> std::result::Result<LockGuard<'_>, Option<u32>> {
     let start = Instant::now();
     loop {
         if matches!(FileExt::try_lock_exclusive(file), Ok(true)) {
+            let mut writer = file;
+            let _ = file.set_len(0).and_then(|()| {
+                writer.seek(SeekFrom::Start(0))?;
+                write!(writer, "{}", std::process::id())
+            });
             return Ok(LockGuard { file });
         }
 
         if start.elapsed() >= timeout {
-            return Err(Error::session(
-                "Timed out waiting for session index lock".to_string(),
-            ));
+            let mut holder = String::new();
+            let mut reader = file;
+            let _ = reader
+                .seek(SeekFrom::Start(0))
+                .and_then(|_| reader.read_to_string(&mut holder));
+            return Err(holder.trim().parse().ok());
         }
 
         std::thread::sleep(Duration::from_millis(50));
     }
 }
 
+/// An exclusive lock taken by [`acquire_pid_lock`]; released on drop.
 #[derive(Debug)]
-struct LockGuard<'a> {
+pub(crate) struct LockGuard<'a> {
     file: &'a File,
 }
 
@@ -493,6 +917,7 @@ mod tests {
                 content: UserContent::Text(text.to_string()),
                 timestamp: Some(chrono::Utc::now().timestamp_millis()),
             },
+            redaction: None,
         })
     }
 
@@ -758,6 +1183,258 @@ mod tests {
         assert!(meta_value.parse::<i64>().unwrap_or(0) > 0);
     }
 
+    fn write_indexed_sessions(harness: &TestHarness, count: usize) -> (SessionIndex, Vec<PathBuf>) {
+        let root = harness.temp_path("sessions");
+        fs::create_dir_all(root.join("project")).expect("create root dir");
+        let index = SessionIndex::for_sessions_root(&root);
+
+        let mut paths = Vec::new();
+        for idx in 0..count {
+            let path = root.join(format!("project/session-{idx}.jsonl"));
+            let header = make_header(&format!("id-{idx}"), "cwd-vacuum");
+            write_session_jsonl(&path, &header, &[make_user_entry(None, "m1", "hello")]);
+            paths.push(path);
+        }
+        index.reindex_all().expect("reindex_all");
+        (index, paths)
+    }
+
+    #[test]
+    fn vacuum_prunes_missing_sessions_in_batches_and_records_run() {
+        let harness = TestHarness::new("vacuum_prunes_missing_sessions_in_batches_and_records_run");
+        let (index, paths) = write_indexed_sessions(&harness, 5);
+        for path in &paths[..3] {
+            fs::remove_file(path).expect("remove session file");
+        }
+
+        let options = VacuumOptions {
+            batch_size: 2,
+            pause: Duration::ZERO,
+        };
+        let report = index.vacuum(&options).expect("vacuum");
+        assert!(!report.resumed);
+        assert_eq!(report.scanned, 5);
+        assert_eq!(report.pruned, 3);
+        assert!(report.analyzed);
+
+        let sessions = index.list_sessions(Some("cwd-vacuum")).expect("list");
+        assert_eq!(sessions.len(), 2);
+
+        let cursor = index
+            .with_lock(|conn| read_meta(conn, META_VACUUM_CURSOR))
+            .expect("read cursor");
+        assert_eq!(cursor, None);
+        assert!(
+            !index
+                .vacuum_if_due(Duration::from_secs(3600), &options)
+                .expect("vacuum_if_due")
+        );
+    }
+
+    #[test]
+    fn vacuum_resumes_from_saved_cursor() {
+        let harness = TestHarness::new("vacuum_resumes_from_saved_cursor");
+        let (index, paths) = write_indexed_sessions(&harness, 4);
+        let cursor = paths[1].to_string_lossy().to_string();
+        index
+            .with_lock(|conn| write_meta(conn, META_VACUUM_CURSOR, &cursor))
+            .expect("write cursor");
+
+        let report = index
+            .vacuum(&VacuumOptions {
+                batch_size: 10,
+                pause: Duration::ZERO,
+            })
+            .expect("vacuum");
+        assert!(report.resumed);
+        assert_eq!(report.scanned, 2);
+        assert_eq!(report.pruned, 0);
+    }
+
+    #[test]
+    fn reindex_all_reads_compressed_sessions() {
+        let harness = TestHarness::new("reindex_all_reads_compressed_sessions");
+        let root = harness.temp_path("sessions");
+        fs::create_dir_all(root.join("project")).expect("create root dir");
+        let index = SessionIndex::for_sessions_root(&root);
+
+        let plain = root.join("project/plain.jsonl");
+        let header = make_header("id-plain", "cwd-zst");
+        write_session_jsonl(&plain, &header, &[make_user_entry(None, "m1", "hello")]);
+        let compressed = root.join("project/archived.jsonl.zst");
+        let header = make_header("id-zst", "cwd-zst");
+        write_session_jsonl(
+            &compressed,
+            &header,
+            &[make_user_entry(None, "m1", "hello")],
+        );
+        let bytes = fs::read(&compressed).expect("read session");
+        let bytes = zstd::stream::encode_all(bytes.as_slice(), 3).expect("compress session");
+        fs::write(&compressed, bytes).expect("write compressed session");
+        fs::write(root.join("project/notes.txt.zst"), b"ignored").expect("write other file");
+
+        index.reindex_all().expect("reindex_all");
+        let mut ids = index
+            .list_sessions(Some("cwd-zst"))
+            .expect("list sessions")
+            .into_iter()
+            .map(|meta| meta.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, ["id-plain", "id-zst"]);
+    }
+
+    fn prune_meta(id: &str, age_days: i64, message_count: u64, size_bytes: u64) -> SessionMeta {
+        SessionMeta {
+            path: format!("/sessions/{id}.jsonl"),
+            id: id.to_string(),
+            cwd: "cwd".to_string(),
+            timestamp: String::new(),
+            message_count,
+            last_modified_ms: -age_days * 86_400_000,
+            size_bytes,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn select_for_prune_combines_age_length_and_size_cap() {
+        let sessions = vec![
+            prune_meta("new", 0, 10, 600),
+            prune_meta("tiny", 1, 1, 100),
+            prune_meta("mid", 5, 10, 600),
+            prune_meta("old", 40, 10, 100),
+        ];
+        let ids = |options: &PruneOptions| {
+            select_for_prune(sessions.clone(), options, 0)
+                .into_iter()
+                .map(|meta| meta.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ids(&PruneOptions {
+                older_than: Some(Duration::from_secs(30 * 86_400)),
+                ..PruneOptions::default()
+            }),
+            ["old"]
+        );
+        assert_eq!(
+            ids(&PruneOptions {
+                min_messages: Some(2),
+                ..PruneOptions::default()
+            }),
+            ["tiny"]
+        );
+        assert_eq!(
+            ids(&PruneOptions {
+                min_messages: Some(2),
+                max_total_bytes: Some(1_000),
+                ..PruneOptions::default()
+            }),
+            ["tiny", "mid", "old"]
+        );
+        assert!(ids(&PruneOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn prune_archives_sessions_and_drops_them_from_the_index() {
+        let harness = TestHarness::new("prune_archives_sessions_and_drops_them_from_the_index");
+        let root = harness.temp_path("sessions");
+        fs::create_dir_all(root.join("project")).expect("create root dir");
+        let index = SessionIndex::for_sessions_root(&root);
+
+        let short = root.join("project/short.jsonl");
+        write_session_jsonl(
+            &short,
+            &make_header("id-short", "cwd-prune"),
+            &[make_user_entry(None, "m1", "hi")],
+        );
+        let long = root.join("project/long.jsonl");
+        write_session_jsonl(
+            &long,
+            &make_header("id-long", "cwd-prune"),
+            &[
+                make_user_entry(None, "m1", "hello"),
+                make_user_entry(Some("m1".to_string()), "m2", "again"),
+            ],
+        );
+
+        let archive = harness.temp_path("archive");
+        let options = PruneOptions {
+            min_messages: Some(2),
+            archive_dir: Some(archive.clone()),
+            dry_run: true,
+            ..PruneOptions::default()
+        };
+        let report = index.prune(&options).expect("dry run");
+        assert_eq!(report.sessions.len(), 1);
+        assert!(
+            report
+                .summary()
+                .starts_with("Would archive 1 session, reclaiming")
+        );
+        assert!(short.exists());
+
+        let report = index
+            .prune(&PruneOptions {
+                dry_run: false,
+                ..options
+            })
+            .expect("prune");
+        assert_eq!(report.sessions[0].id, "id-short");
+        assert_eq!(report.reclaimed_bytes, report.sessions[0].size_bytes);
+        assert!(!short.exists());
+        let archived = archive.join("project/short.jsonl.zst");
+        assert!(
+            read_jsonl_session_file(&archived)
+                .expect("read archive")
+                .contains("id-short")
+        );
+
+        let ids = index
+            .list_sessions(Some("cwd-prune"))
+            .expect("list sessions")
+            .into_iter()
+            .map(|meta| meta.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["id-long"]);
+    }
+
+    #[test]
+    fn prune_skips_locked_sessions_and_removes_lock_files() {
+        let harness = TestHarness::new("prune_skips_locked_sessions_and_removes_lock_files");
+        let root = harness.temp_path("sessions");
+        let index = SessionIndex::for_sessions_root(&root);
+        let path = root.join("project/short.jsonl");
+        fs::create_dir_all(path.parent().expect("parent")).expect("create dirs");
+        write_session_jsonl(
+            &path,
+            &make_header("id-short", "cwd-prune"),
+            &[make_user_entry(None, "m1", "hi")],
+        );
+        let options = PruneOptions {
+            min_messages: Some(2),
+            ..PruneOptions::default()
+        };
+
+        let holder = crate::session::open_session_lock_file(&path).expect("open lock");
+        let lock =
+            crate::session::lock_session_file(&holder, &path, Duration::ZERO).expect("hold lock");
+        let report = index.prune(&options).expect("prune while locked");
+        assert!(report.sessions.is_empty());
+        assert_eq!(report.locked, [path.display().to_string()]);
+        assert!(report.summary().ends_with("; skipped 1 in use"));
+        assert!(path.exists());
+
+        drop(lock);
+        drop(holder);
+        let report = index.prune(&options).expect("prune");
+        assert_eq!(report.sessions.len(), 1);
+        assert!(!path.exists());
+        assert!(!crate::session::session_lock_path(&path).exists());
+    }
+
     #[test]
     fn reindex_all_skips_invalid_jsonl_files() {
         let harness = TestHarness::new("reindex_all_skips_invalid_jsonl_files");
@@ -864,6 +1541,34 @@ mod tests {
             lock_file_guard(&file2, Duration::from_millis(50)).expect("lock after release");
     }
 
+    #[test]
+    fn lock_timeout_names_the_holding_pid() {
+        let harness = TestHarness::new("lock_timeout_names_the_holding_pid");
+        let path = harness.temp_path("index.lock");
+        let open = || {
+            File::options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .expect("open lock file")
+        };
+        let (holder, waiter) = (open(), open());
+
+        let _guard = acquire_pid_lock(&holder, Duration::from_millis(50)).expect("acquire lock");
+        assert_eq!(
+            fs::read_to_string(&path).expect("read lock file"),
+            std::process::id().to_string()
+        );
+        let err = lock_file_guard(&waiter, Duration::from_millis(50)).expect_err("locked");
+        assert!(
+            err.to_string()
+                .contains(&format!("held by pid {}", std::process::id())),
+            "{err}"
+        );
+    }
+
     #[test]
     fn should_reindex_returns_true_when_db_missing() {
         let harness = TestHarness::new("should_reindex_returns_true_when_db_missing");
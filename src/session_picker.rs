@@ -13,7 +13,10 @@ use bubbletea::{Cmd, KeyMsg, KeyType, Message, Program, quit};
 
 use crate::config::Config;
 use crate::error::{Error, Result};
-use crate::session::{Session, SessionEntry, SessionHeader, encode_cwd};
+use crate::session::{
+    Session, SessionEntry, SessionHeader, encode_cwd, is_compressed_session_path,
+    read_jsonl_session_file,
+};
 use crate::session_index::{SessionIndex, SessionMeta};
 use crate::theme::{Theme, TuiStyles};
 
@@ -397,7 +400,7 @@ fn scan_sessions_on_disk(project_session_dir: &Path) -> Vec<SessionMeta> {
 
 fn build_meta_from_file(path: &Path) -> crate::error::Result<SessionMeta> {
     match path.extension().and_then(|ext| ext.to_str()) {
-        Some("jsonl") => build_meta_from_jsonl(path),
+        Some("jsonl" | "zst") => build_meta_from_jsonl(path),
         #[cfg(feature = "sqlite-sessions")]
         Some("sqlite") => build_meta_from_sqlite(path),
         _ => Err(Error::session(format!(
@@ -408,7 +411,7 @@ fn build_meta_from_file(path: &Path) -> crate::error::Result<SessionMeta> {
 }
 
 fn build_meta_from_jsonl(path: &Path) -> crate::error::Result<SessionMeta> {
-    let content = fs::read_to_string(path)?;
+    let content = read_jsonl_session_file(path)?;
     let mut lines = content.lines();
     let header: SessionHeader = lines
         .next()
@@ -484,6 +487,7 @@ fn build_meta_from_sqlite(path: &Path) -> crate::error::Result<SessionMeta> {
 fn is_session_file_path(path: &Path) -> bool {
     match path.extension().and_then(|ext| ext.to_str()) {
         Some("jsonl") => true,
+        Some("zst") => is_compressed_session_path(path),
         #[cfg(feature = "sqlite-sessions")]
         Some("sqlite") => true,
         _ => false,
@@ -0,0 +1,220 @@
+//! Playback of a saved session to the terminal (`pi replay`), for demos and reviews.
+//!
+//! The current branch is rendered entry by entry with [`PiConsole`]. By default entries follow
+//! each other immediately; with a speed, the gaps between the original entry timestamps are
+//! reproduced (divided by the speed), each capped so idle time does not stall the playback.
+
+use crate::model::{ContentBlock, UserContent};
+use crate::session::{Session, SessionEntry, SessionMessage};
+use crate::tui::PiConsole;
+use std::time::Duration;
+
+/// Gaps longer than this are shortened to it by default.
+pub const DEFAULT_MAX_PAUSE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlaybackOptions {
+    /// Multiplier on the original timing (`1.0` is real time); `None` plays without pauses.
+    pub speed: Option<f64>,
+    /// Longest pause between two entries.
+    pub max_pause: Duration,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            speed: None,
+            max_pause: DEFAULT_MAX_PAUSE,
+        }
+    }
+}
+
+/// Pause before each entry: the gap since the previous entry's timestamp, scaled by the speed
+/// and capped. Entries whose timestamps cannot be parsed get no pause.
+pub fn playback_delays(entries: &[&SessionEntry], options: &PlaybackOptions) -> Vec<Duration> {
+    let Some(speed) = options
+        .speed
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+    else {
+        return vec![Duration::ZERO; entries.len()];
+    };
+    let mut previous: Option<chrono::DateTime<chrono::FixedOffset>> = None;
+    entries
+        .iter()
+        .map(|entry| {
+            let at = chrono::DateTime::parse_from_rfc3339(&entry.base().timestamp).ok();
+            let gap = match (previous, at) {
+                (Some(previous), Some(at)) => (at - previous).to_std().unwrap_or_default(),
+                _ => Duration::ZERO,
+            };
+            if at.is_some() {
+                previous = at;
+            }
+            gap.div_f64(speed).min(options.max_pause)
+        })
+        .collect()
+}
+
+/// Render the current branch of `session`, pausing between entries per `options`.
+pub async fn play(session: &Session, console: &PiConsole, options: &PlaybackOptions) {
+    let entries = session.entries_for_current_path();
+    let delays = playback_delays(&entries, options);
+    for (entry, delay) in entries.into_iter().zip(delays) {
+        if !delay.is_zero() {
+            asupersync::time::sleep(asupersync::time::wall_now(), delay).await;
+        }
+        render_entry(console, entry);
+    }
+}
+
+fn render_entry(console: &PiConsole, entry: &SessionEntry) {
+    match entry {
+        SessionEntry::Message(entry) => render_message(console, &entry.message),
+        SessionEntry::ModelChange(change) => {
+            console.render_info(&format!("Model: {}/{}", change.provider, change.model_id));
+        }
+        SessionEntry::ThinkingLevelChange(change) => {
+            console.render_info(&format!("Thinking level: {}", change.thinking_level));
+        }
+        SessionEntry::Compaction(compaction) => {
+            console.render_panel(&compaction.summary, "Compacted history");
+        }
+        SessionEntry::BranchSummary(summary) => {
+            console.render_panel(&summary.summary, "Branch summary");
+        }
+        _ => {}
+    }
+}
+
+fn render_message(console: &PiConsole, message: &SessionMessage) {
+    match message {
+        SessionMessage::User { content, .. } => {
+            let text = match content {
+                UserContent::Text(text) => text.clone(),
+                UserContent::Blocks(blocks) => blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text(text) => Some(text.text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            };
+            console.render_user_message(&text);
+        }
+        SessionMessage::Assistant { message } => {
+            console.render_assistant_start();
+            for block in &message.content {
+                match block {
+                    ContentBlock::Thinking(thinking) => {
+                        console.render_thinking_start();
+                        console.render_thinking_delta(&thinking.thinking);
+                        console.render_thinking_end();
+                    }
+                    ContentBlock::Text(text) => console.render_markdown(&text.text),
+                    ContentBlock::ToolCall(call) => {
+                        console.render_tool_start(&call.name, &call.arguments.to_string());
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(error) = &message.error_message {
+                console.render_error(error);
+            }
+            let usage = &message.usage;
+            console.render_usage(
+                u32::try_from(usage.input).unwrap_or(u32::MAX),
+                u32::try_from(usage.output).unwrap_or(u32::MAX),
+                (usage.cost.total > 0.0).then_some(usage.cost.total),
+            );
+        }
+        SessionMessage::ToolResult {
+            tool_name,
+            is_error,
+            ..
+        } => console.render_tool_end(tool_name, *is_error),
+        SessionMessage::BashExecution {
+            command, output, ..
+        } => {
+            console.render_info(&format!("$ {command}"));
+            console.print_plain(output);
+            if !output.ends_with('\n') {
+                console.newline();
+            }
+        }
+        SessionMessage::Custom {
+            content, display, ..
+        } => {
+            if *display {
+                console.render_markdown(content);
+            }
+        }
+        SessionMessage::BranchSummary { summary, .. } => {
+            console.render_panel(summary, "Branch summary");
+        }
+        SessionMessage::CompactionSummary { summary, .. } => {
+            console.render_panel(summary, "Compacted history");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_with_timestamps(timestamps: &[&str]) -> Session {
+        let mut session = Session::in_memory();
+        for (index, timestamp) in timestamps.iter().enumerate() {
+            let id = session.append_message(SessionMessage::User {
+                content: UserContent::Text(format!("message {index}")),
+                timestamp: None,
+            });
+            if let Some(SessionEntry::Message(entry)) = session.get_entry_mut(&id) {
+                entry.base.timestamp = (*timestamp).to_string();
+            }
+        }
+        session
+    }
+
+    #[test]
+    fn delays_follow_the_original_gaps_scaled_and_capped() {
+        let session = session_with_timestamps(&[
+            "2026-01-01T10:00:00Z",
+            "2026-01-01T10:00:02Z",
+            "not a timestamp",
+            "2026-01-01T10:00:03Z",
+            "2026-01-01T11:00:00Z",
+        ]);
+        let entries = session.entries_for_current_path();
+
+        let realtime = PlaybackOptions {
+            speed: Some(1.0),
+            ..PlaybackOptions::default()
+        };
+        assert_eq!(
+            playback_delays(&entries, &realtime),
+            [
+                Duration::ZERO,
+                Duration::from_secs(2),
+                Duration::ZERO,
+                Duration::from_secs(1),
+                DEFAULT_MAX_PAUSE,
+            ]
+        );
+
+        let fast = PlaybackOptions {
+            speed: Some(4.0),
+            ..PlaybackOptions::default()
+        };
+        assert_eq!(
+            playback_delays(&entries, &fast)[1],
+            Duration::from_millis(500)
+        );
+
+        assert!(
+            playback_delays(&entries, &PlaybackOptions::default())
+                .iter()
+                .all(Duration::is_zero)
+        );
+    }
+}
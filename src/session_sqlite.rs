@@ -1,6 +1,8 @@
 use crate::agent_cx::AgentCx;
 use crate::error::{Error, Result};
-use crate::session::{SessionEntry, SessionHeader};
+use crate::session::{
+    SESSION_MIGRATIONS, SESSION_VERSION, SessionEntry, SessionHeader, migrate_session_values,
+};
 use asupersync::Outcome;
 use asupersync::database::{SqliteConnection, SqliteError, SqliteRow, SqliteValue};
 use std::path::Path;
@@ -86,7 +88,7 @@ pub async fn load_session(path: &Path) -> Result<(SessionHeader, Vec<SessionEntr
         .first()
         .ok_or_else(|| Error::session("SQLite session missing header row"))?;
     let header_json = row_get_str(header_row, "json")?;
-    let header: SessionHeader = serde_json::from_str(header_json)?;
+    let mut header_value: serde_json::Value = serde_json::from_str(header_json)?;
 
     let entry_rows = map_outcome(
         conn.query(
@@ -97,12 +99,23 @@ pub async fn load_session(path: &Path) -> Result<(SessionHeader, Vec<SessionEntr
         .await,
     )?;
 
-    let mut entries = Vec::with_capacity(entry_rows.len());
+    let mut values = Vec::with_capacity(entry_rows.len());
     for row in entry_rows {
         let json = row_get_str(&row, "json")?;
-        let entry: SessionEntry = serde_json::from_str(json)?;
-        entries.push(entry);
+        values.push(serde_json::from_str::<serde_json::Value>(json)?);
     }
+    migrate_session_values(
+        &mut header_value,
+        &mut values,
+        SESSION_MIGRATIONS,
+        SESSION_VERSION,
+    )?;
+
+    let header: SessionHeader = serde_json::from_value(header_value)?;
+    let entries = values
+        .into_iter()
+        .map(serde_json::from_value::<SessionEntry>)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
 
     Ok((header, entries))
 }
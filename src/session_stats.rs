@@ -0,0 +1,236 @@
+//! Message, tool call, token, and cost totals for one branch of a session (`/stats`, RPC
+//! `get_session_stats`).
+//!
+//! Totals cover the branch's context as it would be sent to the model: messages a compaction
+//! replaced are left out and its summary counts instead. Custom messages, summaries, and `!`
+//! shell output count as user messages.
+
+use crate::error::{Error, Result};
+use crate::model::{ContentBlock, Message};
+use crate::session::Session;
+use serde::Serialize;
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenTotals {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStats {
+    /// Last entry of the branch; `None` for an empty session.
+    pub leaf_id: Option<String>,
+    pub user_messages: u64,
+    pub assistant_messages: u64,
+    pub tool_calls: u64,
+    pub tool_results: u64,
+    pub total_messages: u64,
+    pub tokens: TokenTotals,
+    /// Estimated cost from model pricing, in USD.
+    pub cost: f64,
+    /// Cost reported by a gateway, when any response carried one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway_cost: Option<f64>,
+}
+
+impl Session {
+    /// Totals for the branch ending at `leaf_id` (an entry ID or a label), or for the current
+    /// branch when `None`.
+    pub fn stats(&self, leaf_id: Option<&str>) -> Result<SessionStats> {
+        let (leaf_id, path) = match leaf_id {
+            None => (self.leaf_id.clone(), self.entries_for_current_path()),
+            Some(spec) => {
+                let id = if self.get_entry(spec).is_some() {
+                    spec.to_string()
+                } else {
+                    self.find_label_target(spec)
+                        .ok_or_else(|| Error::session(format!("Entry not found: {spec}")))?
+                };
+                let path = self
+                    .get_path_to_entry(&id)
+                    .iter()
+                    .filter_map(|id| self.get_entry(id))
+                    .collect();
+                (Some(id), path)
+            }
+        };
+
+        let messages = Self::messages_with_entry_ids_for_path(&path);
+        let mut stats = SessionStats {
+            leaf_id,
+            total_messages: messages.len() as u64,
+            ..SessionStats::default()
+        };
+        for (_, message) in messages {
+            match message {
+                Message::User(_) | Message::Custom(_) => stats.user_messages += 1,
+                Message::Assistant(message) => {
+                    stats.assistant_messages += 1;
+                    stats.tool_calls += message
+                        .content
+                        .iter()
+                        .filter(|block| matches!(block, ContentBlock::ToolCall(_)))
+                        .count() as u64;
+                    let usage = &message.usage;
+                    stats.tokens.input += usage.input;
+                    stats.tokens.output += usage.output;
+                    stats.tokens.cache_read += usage.cache_read;
+                    stats.tokens.cache_write += usage.cache_write;
+                    stats.cost += usage.cost.total;
+                    if let Some(cost) = usage.cost.gateway {
+                        *stats.gateway_cost.get_or_insert(0.0) += cost;
+                    }
+                }
+                Message::ToolResult(_) => stats.tool_results += 1,
+            }
+        }
+        let tokens = &mut stats.tokens;
+        tokens.total = tokens.input + tokens.output + tokens.cache_read + tokens.cache_write;
+        Ok(stats)
+    }
+}
+
+impl SessionStats {
+    /// Plain-text rendering for `/stats`.
+    pub fn to_text(&self) -> String {
+        let leaf = self.leaf_id.as_deref().unwrap_or("(empty)");
+        let tokens = &self.tokens;
+        let mut out = format!(
+            "Branch stats:\n  leaf: {leaf}\n  messages: {} ({} user, {} assistant, {} tool results)\n  toolCalls: {}\n  tokens: {} ({} in / {} out / {} cache read / {} cache write)\n  cost: ${:.4}",
+            self.total_messages,
+            self.user_messages,
+            self.assistant_messages,
+            self.tool_results,
+            self.tool_calls,
+            tokens.total,
+            tokens.input,
+            tokens.output,
+            tokens.cache_read,
+            tokens.cache_write,
+            self.cost,
+        );
+        if let Some(gateway) = self.gateway_cost {
+            let _ = write!(out, "\n  gatewayCost: ${gateway:.4}");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AssistantMessage, StopReason, TextContent, ToolCall, Usage, UserContent};
+    use crate::session::SessionMessage;
+
+    fn user(text: &str) -> SessionMessage {
+        SessionMessage::User {
+            content: UserContent::Text(text.to_string()),
+            timestamp: None,
+        }
+    }
+
+    fn assistant(content: Vec<ContentBlock>, input: u64, output: u64, cost: f64) -> SessionMessage {
+        let mut usage = Usage {
+            input,
+            output,
+            ..Usage::default()
+        };
+        usage.cost.total = cost;
+        SessionMessage::Assistant {
+            message: AssistantMessage {
+                content,
+                api: "test".to_string(),
+                provider: "test".to_string(),
+                model: "test".to_string(),
+                usage,
+                stop_reason: StopReason::Stop,
+                abort_reason: None,
+                error_message: None,
+                timestamp: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn stats_follow_the_requested_branch() {
+        let mut session = Session::in_memory();
+        session.append_message(user("fix the test"));
+        session.append_message(assistant(
+            vec![ContentBlock::ToolCall(ToolCall {
+                id: "call-1".to_string(),
+                name: "read".to_string(),
+                arguments: serde_json::json!({ "path": "a.rs" }),
+                thought_signature: None,
+            })],
+            100,
+            20,
+            0.5,
+        ));
+        session.append_message(SessionMessage::ToolResult {
+            tool_call_id: "call-1".to_string(),
+            tool_name: "read".to_string(),
+            content: vec![ContentBlock::Text(TextContent::new("fn main() {}"))],
+            details: None,
+            is_error: false,
+            timestamp: None,
+        });
+        let fork = session.leaf_id.clone().unwrap();
+        session.append_message(assistant(
+            vec![ContentBlock::Text(TextContent::new("Fixed."))],
+            200,
+            10,
+            0.25,
+        ));
+        let main_leaf = session.leaf_id.clone().unwrap();
+
+        session.create_branch_from(&fork);
+        session.append_message(user("try another way"));
+        session.add_label(&main_leaf, Some("done".to_string()));
+
+        let current = session.stats(None).unwrap();
+        assert_eq!(current.total_messages, 4);
+        assert_eq!(current.user_messages, 2);
+        assert_eq!(current.tool_calls, 1);
+        assert_eq!(current.tool_results, 1);
+        assert_eq!(current.tokens.total, 120);
+
+        let done = session.stats(Some("done")).unwrap();
+        assert_eq!(done.leaf_id.as_deref(), Some(main_leaf.as_str()));
+        assert_eq!(done.assistant_messages, 2);
+        assert_eq!(done.tokens.input, 300);
+        assert!((done.cost - 0.75).abs() < 1e-9);
+        assert!(done.to_text().contains("cost: $0.7500"));
+
+        let err = session.stats(Some("missing")).unwrap_err();
+        assert!(err.to_string().contains("Entry not found: missing"));
+    }
+
+    #[test]
+    fn stats_count_the_compacted_context() {
+        let mut session = Session::in_memory();
+        session.append_message(user("first question"));
+        session.append_message(assistant(Vec::new(), 1000, 50, 1.0));
+        let kept = session.append_message(user("second question"));
+        session.append_compaction(
+            "Earlier: first question".to_string(),
+            kept,
+            1050,
+            None,
+            None,
+        );
+        session.append_message(assistant(Vec::new(), 300, 20, 0.5));
+
+        let stats = session.stats(None).unwrap();
+        // The summary, the kept question, and the reply after the compaction.
+        assert_eq!(stats.total_messages, 3);
+        assert_eq!(stats.user_messages, 2);
+        assert_eq!(stats.assistant_messages, 1);
+        assert_eq!(stats.tokens.total, 320);
+    }
+}
@@ -0,0 +1,519 @@
+//! Where session files are kept.
+//!
+//! [`SessionStore`] reads and writes the encoded bytes of a session file by key. Every session
+//! is written to the local sessions directory through [`FileSessionStore`], which keeps
+//! locking, the session index, and the picker working offline. When `remote_sessions` is
+//! configured, each save is also uploaded to a shared [`WebDavSessionStore`] under the file
+//! name. Teammates then open it with `pi --session remote:<file name>`, which downloads a local
+//! copy that keeps syncing to the same key.
+//!
+//! Uploads run in the background through [`RemoteSync`], so a slow server never holds up a
+//! save. Saves made while an upload is in flight are coalesced into one follow-up upload.
+//!
+//! The WebDAV store remembers the `ETag` of each session it downloads or uploads and sends it
+//! back as `If-Match`, so an upload never silently replaces a version another client wrote in
+//! the meantime. After such a conflict the session stops uploading until it is downloaded
+//! again.
+
+use crate::config::RemoteSessionSettings;
+use crate::error::{Error, Result};
+use crate::http::client::Client;
+use asupersync::runtime::RuntimeBuilder;
+use async_trait::async_trait;
+use base64::Engine as _;
+use futures::TryStreamExt as _;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+/// `--session` prefix that names a session in the configured remote store.
+pub const REMOTE_SESSION_PREFIX: &str = "remote:";
+
+/// Environment variable read for the WebDAV password when `password_env` is unset.
+pub const DEFAULT_PASSWORD_ENV: &str = "PI_SESSION_STORE_PASSWORD";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+static REMOTE: OnceLock<Arc<RemoteSync>> = OnceLock::new();
+
+#[async_trait]
+pub trait SessionStore: Send + Sync + std::fmt::Debug {
+    /// Encoded bytes of the session stored under `key`.
+    async fn read(&self, key: &str) -> Result<Vec<u8>>;
+
+    /// Store the encoded bytes of a session under `key`, replacing any previous version.
+    async fn write(&self, key: &str, bytes: Vec<u8>) -> Result<()>;
+
+    /// Human-readable location of `key`, for messages.
+    fn location(&self, key: &str) -> String;
+}
+
+/// Install the shared store every save is mirrored to.
+///
+/// Returns `false` if one was already installed.
+pub fn install_remote(store: Arc<dyn SessionStore>) -> bool {
+    REMOTE.set(Arc::new(RemoteSync::new(store))).is_ok()
+}
+
+/// Background uploads to the shared store, if one was installed.
+pub fn remote() -> Option<&'static Arc<RemoteSync>> {
+    REMOTE.get()
+}
+
+/// The local store holding the session file at `path`, and the file's key in it.
+pub fn local_store(path: &Path) -> Result<(Arc<dyn SessionStore>, String)> {
+    let key = remote_key(path)
+        .ok_or_else(|| Error::validation(format!("Invalid session path: {}", path.display())))?;
+    let root = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    Ok((Arc::new(FileSessionStore::new(root.to_path_buf())), key))
+}
+
+/// Key a session file is stored under remotely: its file name.
+pub fn remote_key(path: &Path) -> Option<String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+}
+
+fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.starts_with('.') || key.contains(['/', '\\']) {
+        return Err(Error::validation(format!("Invalid session key: {key}")));
+    }
+    Ok(())
+}
+
+/// Session files under a local directory.
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    root: PathBuf,
+}
+
+impl FileSessionStore {
+    pub const fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Replace `path` with `bytes` through a temp file in the same directory, so readers never
+    /// see a partial session.
+    pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        let temp_file = tempfile::NamedTempFile::new_in(parent)?;
+        std::fs::write(temp_file.path(), bytes)?;
+        temp_file
+            .persist(path)
+            .map_err(|e| Error::Io(Box::new(e.error)))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn read(&self, key: &str) -> Result<Vec<u8>> {
+        validate_key(key)?;
+        let path = self.root.join(key);
+        if !path.exists() {
+            return Err(Error::SessionNotFound {
+                path: path.display().to_string(),
+            });
+        }
+        Ok(asupersync::fs::read(&path).await?)
+    }
+
+    /// Writes synchronously, so it may also be driven from a plain thread.
+    async fn write(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        validate_key(key)?;
+        std::fs::create_dir_all(&self.root)?;
+        Self::write_atomic(&self.root.join(key), &bytes)
+    }
+
+    fn location(&self, key: &str) -> String {
+        self.root.join(key).display().to_string()
+    }
+}
+
+/// Session files in a WebDAV collection (Nextcloud, Apache `mod_dav`, rclone serve, ...).
+#[derive(Debug)]
+pub struct WebDavSessionStore {
+    base_url: String,
+    authorization: Option<String>,
+    client: Client,
+    /// Last `ETag` seen for each key.
+    etags: Mutex<HashMap<String, String>>,
+}
+
+impl WebDavSessionStore {
+    /// `base_url` is the collection URL; `credentials` are used for basic auth.
+    ///
+    /// Plain `http://` URLs are refused unless `allow_http` is set, since they would send the
+    /// credentials and sessions in cleartext.
+    pub fn new(
+        base_url: &str,
+        credentials: Option<(&str, &str)>,
+        allow_http: bool,
+    ) -> Result<Self> {
+        if base_url.starts_with("http://") {
+            if !allow_http {
+                return Err(Error::config(format!(
+                    "remote_sessions.url {base_url} is not encrypted; use https:// or set \
+                     remote_sessions.allow_http"
+                )));
+            }
+        } else if !base_url.starts_with("https://") {
+            return Err(Error::config(format!(
+                "remote_sessions.url must be an http(s) URL, got {base_url}"
+            )));
+        }
+        let authorization = credentials.map(|(username, password)| {
+            let token =
+                base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+            format!("Basic {token}")
+        });
+        Ok(Self {
+            base_url: format!("{}/", base_url.trim_end_matches('/')),
+            authorization,
+            client: Client::new(),
+            etags: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The store described by the `remote_sessions` setting, or `None` when no URL is set.
+    ///
+    /// The password is read from the `password_env` variable (default
+    /// [`DEFAULT_PASSWORD_ENV`]) and is required when a username is set.
+    pub fn from_settings(settings: &RemoteSessionSettings) -> Result<Option<Self>> {
+        let Some(url) = settings.url.as_deref().filter(|url| !url.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let password = match &settings.username {
+            Some(_) => {
+                let env = settings
+                    .password_env
+                    .as_deref()
+                    .unwrap_or(DEFAULT_PASSWORD_ENV);
+                Some(std::env::var(env).map_err(|_| {
+                    Error::config(format!(
+                        "remote_sessions.username is set but ${env} holds no password"
+                    ))
+                })?)
+            }
+            None => None,
+        };
+        let credentials = settings.username.as_deref().zip(password.as_deref());
+        Self::new(
+            url.trim(),
+            credentials,
+            settings.allow_http.unwrap_or(false),
+        )
+        .map(Some)
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}{key}", self.base_url)
+    }
+
+    fn etag(&self, key: &str) -> Option<String> {
+        self.etags
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(key)
+            .cloned()
+    }
+
+    /// Remember the `ETag` in `headers` for `key`, or forget the old one when there is none.
+    fn record_etag(&self, key: &str, headers: &[(String, String)]) {
+        let etag = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("etag"))
+            .map(|(_, value)| value.clone());
+        let mut etags = self
+            .etags
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        match etag {
+            Some(etag) => etags.insert(key.to_string(), etag),
+            None => etags.remove(key),
+        };
+    }
+}
+
+#[async_trait]
+impl SessionStore for WebDavSessionStore {
+    async fn read(&self, key: &str) -> Result<Vec<u8>> {
+        validate_key(key)?;
+        let url = self.url(key);
+        let mut request = self.client.get(&url).timeout(REQUEST_TIMEOUT);
+        if let Some(authorization) = &self.authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let response = request.send().await?;
+        match response.status() {
+            200..=299 => {}
+            404 => return Err(Error::SessionNotFound { path: url }),
+            status => {
+                return Err(Error::session(format!(
+                    "Downloading {url} failed with HTTP {status}"
+                )));
+            }
+        }
+        self.record_etag(key, response.headers());
+        let bytes = response
+            .bytes_stream()
+            .try_fold(Vec::new(), |mut acc, chunk| async move {
+                acc.extend_from_slice(&chunk);
+                Ok::<_, std::io::Error>(acc)
+            })
+            .await?;
+        Ok(bytes)
+    }
+
+    async fn write(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        validate_key(key)?;
+        let url = self.url(key);
+        let mut request = self
+            .client
+            .put(&url)
+            .timeout(REQUEST_TIMEOUT)
+            .header("Content-Type", "application/octet-stream")
+            .body(bytes);
+        if let Some(authorization) = &self.authorization {
+            request = request.header("Authorization", authorization);
+        }
+        if let Some(etag) = self.etag(key) {
+            request = request.header("If-Match", etag);
+        }
+        let response = request.send().await?;
+        match response.status() {
+            200..=299 => {}
+            412 => return Err(Error::SessionConflict { location: url }),
+            status => {
+                return Err(Error::session(format!(
+                    "Uploading {url} failed with HTTP {status}"
+                )));
+            }
+        }
+        self.record_etag(key, response.headers());
+        Ok(())
+    }
+
+    fn location(&self, key: &str) -> String {
+        self.url(key)
+    }
+}
+
+/// Uploads saved sessions to a shared store on a background thread.
+#[derive(Debug)]
+pub struct RemoteSync {
+    store: Arc<dyn SessionStore>,
+    state: Mutex<SyncState>,
+    idle: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct SyncState {
+    /// Latest bytes waiting to be uploaded, by key.
+    queued: BTreeMap<String, Vec<u8>>,
+    /// Keys another client changed; not uploaded again until downloaded.
+    conflicted: HashSet<String>,
+    /// Whether the upload thread is running.
+    uploading: bool,
+}
+
+impl RemoteSync {
+    pub fn new(store: Arc<dyn SessionStore>) -> Self {
+        Self {
+            store,
+            state: Mutex::new(SyncState::default()),
+            idle: Condvar::new(),
+        }
+    }
+
+    /// Queue `bytes` for upload under `key`, replacing bytes still waiting for the same key.
+    pub fn upload(self: &Arc<Self>, key: String, bytes: Vec<u8>) {
+        let mut state = self.lock_state();
+        if state.conflicted.contains(&key) {
+            return;
+        }
+        state.queued.insert(key, bytes);
+        if state.uploading {
+            return;
+        }
+        state.uploading = true;
+        drop(state);
+        let sync = Arc::clone(self);
+        std::thread::spawn(move || sync.drain());
+    }
+
+    /// Read `key` from the store, and resume uploading it if it was in conflict.
+    pub async fn download(&self, key: &str) -> Result<Vec<u8>> {
+        let bytes = self.store.read(key).await?;
+        self.lock_state().conflicted.remove(key);
+        Ok(bytes)
+    }
+
+    /// Block until every queued upload has finished, or `timeout` passes.
+    ///
+    /// Returns `false` on timeout.
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock_state();
+        while state.uploading {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                return false;
+            };
+            state = self
+                .idle
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .0;
+        }
+        true
+    }
+
+    fn drain(&self) {
+        let runtime = match RuntimeBuilder::current_thread().build() {
+            Ok(runtime) => runtime,
+            Err(err) => {
+                tracing::warn!("Session upload failed to start: {err}");
+                let mut state = self.lock_state();
+                state.queued.clear();
+                state.uploading = false;
+                self.idle.notify_all();
+                return;
+            }
+        };
+        loop {
+            let (key, bytes) = {
+                let mut state = self.lock_state();
+                let Some(next) = state.queued.pop_first() else {
+                    // Under the same lock as the check, so a racing `upload` spawns a new thread.
+                    state.uploading = false;
+                    self.idle.notify_all();
+                    return;
+                };
+                next
+            };
+            match runtime.block_on(self.store.write(&key, bytes)) {
+                Ok(()) => {}
+                Err(err @ Error::SessionConflict { .. }) => {
+                    tracing::warn!("{err}; not uploading it again until it is reopened");
+                    let mut state = self.lock_state();
+                    state.queued.remove(&key);
+                    state.conflicted.insert(key);
+                }
+                Err(err) => tracing::warn!(
+                    "Session saved locally but not uploaded to {}: {err}",
+                    self.store.location(&key)
+                ),
+            }
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, SyncState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_store_round_trips_and_rejects_nested_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileSessionStore::new(dir.path().join("shared"));
+        let runtime = RuntimeBuilder::current_thread().build().unwrap();
+        runtime.block_on(async {
+            store.write("a.jsonl", b"{}\n".to_vec()).await.unwrap();
+            assert_eq!(store.read("a.jsonl").await.unwrap(), b"{}\n");
+            assert!(matches!(
+                store.read("b.jsonl").await,
+                Err(Error::SessionNotFound { .. })
+            ));
+            assert!(store.write("../a.jsonl", Vec::new()).await.is_err());
+        });
+    }
+
+    /// Records writes, taking a moment each, and reports a conflict for `conflict.jsonl`.
+    #[derive(Debug, Default)]
+    struct SlowStore {
+        writes: Mutex<Vec<(String, Vec<u8>)>>,
+    }
+
+    #[async_trait]
+    impl SessionStore for SlowStore {
+        async fn read(&self, _key: &str) -> Result<Vec<u8>> {
+            Ok(Vec::new())
+        }
+
+        async fn write(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+            std::thread::sleep(Duration::from_millis(50));
+            self.writes.lock().unwrap().push((key.to_string(), bytes));
+            if key == "conflict.jsonl" {
+                return Err(Error::SessionConflict {
+                    location: key.to_string(),
+                });
+            }
+            Ok(())
+        }
+
+        fn location(&self, key: &str) -> String {
+            key.to_string()
+        }
+    }
+
+    #[test]
+    fn remote_sync_coalesces_saves_and_stops_after_a_conflict() {
+        let store = Arc::new(SlowStore::default());
+        let sync = Arc::new(RemoteSync::new(store.clone()));
+        for n in 0..5_u8 {
+            sync.upload("s.jsonl".to_string(), vec![n]);
+        }
+        assert!(sync.wait_idle(Duration::from_secs(10)));
+        let writes = store.writes.lock().unwrap().clone();
+        assert!(writes.len() <= 2, "{writes:?}");
+        assert_eq!(writes.last(), Some(&("s.jsonl".to_string(), vec![4])));
+
+        store.writes.lock().unwrap().clear();
+        sync.upload("conflict.jsonl".to_string(), vec![1]);
+        assert!(sync.wait_idle(Duration::from_secs(10)));
+        sync.upload("conflict.jsonl".to_string(), vec![2]);
+        assert!(sync.wait_idle(Duration::from_secs(10)));
+        assert_eq!(store.writes.lock().unwrap().len(), 1);
+
+        // Downloading it again clears the conflict.
+        let runtime = RuntimeBuilder::current_thread().build().unwrap();
+        runtime.block_on(sync.download("conflict.jsonl")).unwrap();
+        sync.upload("conflict.jsonl".to_string(), vec![3]);
+        assert!(sync.wait_idle(Duration::from_secs(10)));
+        assert_eq!(store.writes.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn webdav_store_needs_an_https_url() {
+        let err = WebDavSessionStore::new("ftp://example.com", None, false).unwrap_err();
+        assert!(err.to_string().contains("http(s) URL"));
+        let err = WebDavSessionStore::new("http://example.com", None, false).unwrap_err();
+        assert!(err.to_string().contains("allow_http"), "{err}");
+        assert!(WebDavSessionStore::new("http://example.com", None, true).is_ok());
+
+        let store =
+            WebDavSessionStore::new("https://dav.example.com/pi/", Some(("me", "pw")), false)
+                .unwrap();
+        assert_eq!(
+            store.location("s.jsonl"),
+            "https://dav.example.com/pi/s.jsonl"
+        );
+        assert_eq!(store.authorization.as_deref(), Some("Basic bWU6cHc="));
+        assert!(
+            WebDavSessionStore::from_settings(&RemoteSessionSettings::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
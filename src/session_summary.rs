@@ -0,0 +1,342 @@
+//! Pull-request descriptions generated from a session (`pi sessions summarize --format pr`).
+//!
+//! The summary is built from the transcript alone, without a model call:
+//! - problem: the first user message on the current branch
+//! - approach: the final assistant reply
+//! - changes: files written or edited (including files carried forward by compactions)
+//! - testing: test-like commands run through the `bash` tool or `!`, with their outcome
+//!
+//! The markdown body is meant for `gh pr create --title ... --body-file -`.
+
+use crate::compaction::FileOperations;
+use crate::model::{ContentBlock, UserContent};
+use crate::session::{Session, SessionEntry, SessionMessage};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+/// Longest title (in chars) derived from the first user message.
+const TITLE_MAX_CHARS: usize = 72;
+
+/// Longest approach section (in chars) taken from the final assistant reply.
+const APPROACH_MAX_CHARS: usize = 1200;
+
+/// Substrings that mark a shell command as a test run.
+const TEST_COMMAND_MARKERS: &[&str] = &[
+    "cargo test",
+    "cargo nextest",
+    "npm test",
+    "npm run test",
+    "pnpm test",
+    "yarn test",
+    "bun test",
+    "pytest",
+    "go test",
+    "jest",
+    "vitest",
+    "make test",
+    "make check",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestRun {
+    pub command: String,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrSummary {
+    pub title: String,
+    pub problem: String,
+    pub approach: String,
+    pub changes: Vec<String>,
+    pub tests: Vec<TestRun>,
+}
+
+impl PrSummary {
+    /// Summarize the current branch of `session`.
+    pub fn from_session(session: &Session) -> Self {
+        let entries = session.entries_for_current_path();
+
+        let problem = entries
+            .iter()
+            .find_map(|entry| match entry {
+                SessionEntry::Message(entry) => match &entry.message {
+                    SessionMessage::User { content, .. } => Some(user_text(content)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let approach = entries
+            .iter()
+            .rev()
+            .find_map(|entry| match entry {
+                SessionEntry::Message(entry) => match &entry.message {
+                    SessionMessage::Assistant { message } => {
+                        let text = blocks_text(&message.content);
+                        (!text.trim().is_empty()).then_some(text)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let title = session
+            .get_name()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| first_line(&problem));
+
+        Self {
+            title: truncate_chars(title.trim(), TITLE_MAX_CHARS),
+            problem: problem.trim().to_string(),
+            approach: truncate_chars(approach.trim(), APPROACH_MAX_CHARS),
+            changes: FileOperations::from_entries(entries.iter().copied()).modified_files(),
+            tests: collect_test_runs(&entries),
+        }
+    }
+
+    /// Render the PR body (without the title).
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "## Problem\n");
+        let _ = writeln!(out, "{}\n", or_placeholder(&self.problem));
+        let _ = writeln!(out, "## Approach\n");
+        let _ = writeln!(out, "{}\n", or_placeholder(&self.approach));
+
+        let _ = writeln!(out, "## Changes\n");
+        if self.changes.is_empty() {
+            let _ = writeln!(out, "_No file changes recorded._");
+        }
+        for path in &self.changes {
+            let _ = writeln!(out, "- `{path}`");
+        }
+
+        let _ = writeln!(out, "\n## Testing\n");
+        if self.tests.is_empty() {
+            let _ = writeln!(out, "_No test runs recorded._");
+        }
+        for run in &self.tests {
+            let outcome = if run.passed { "passed" } else { "failed" };
+            let _ = writeln!(out, "- `{}` ({outcome})", run.command);
+        }
+        out
+    }
+}
+
+fn collect_test_runs(entries: &[&SessionEntry]) -> Vec<TestRun> {
+    let mut pending: HashMap<&str, &str> = HashMap::new();
+    let mut runs: Vec<TestRun> = Vec::new();
+    let mut record = |command: &str, passed: bool| {
+        let command = command.trim().to_string();
+        // A re-run supersedes the earlier outcome; keep the first position.
+        if let Some(existing) = runs.iter_mut().find(|run| run.command == command) {
+            existing.passed = passed;
+        } else {
+            runs.push(TestRun { command, passed });
+        }
+    };
+
+    for entry in entries {
+        let SessionEntry::Message(entry) = entry else {
+            continue;
+        };
+        match &entry.message {
+            SessionMessage::Assistant { message } => {
+                for block in &message.content {
+                    let ContentBlock::ToolCall(call) = block else {
+                        continue;
+                    };
+                    if call.name != "bash" {
+                        continue;
+                    }
+                    if let Some(command) = call.arguments.get("command").and_then(Value::as_str) {
+                        if is_test_command(command) {
+                            pending.insert(call.id.as_str(), command);
+                        }
+                    }
+                }
+            }
+            SessionMessage::ToolResult {
+                tool_call_id,
+                is_error,
+                ..
+            } => {
+                if let Some(command) = pending.remove(tool_call_id.as_str()) {
+                    record(command, !is_error);
+                }
+            }
+            SessionMessage::BashExecution {
+                command,
+                exit_code,
+                cancelled,
+                ..
+            } => {
+                if is_test_command(command) {
+                    record(command, *exit_code == 0 && !cancelled.unwrap_or(false));
+                }
+            }
+            _ => {}
+        }
+    }
+    runs
+}
+
+fn is_test_command(command: &str) -> bool {
+    TEST_COMMAND_MARKERS
+        .iter()
+        .any(|marker| command.contains(marker))
+}
+
+fn user_text(content: &UserContent) -> String {
+    match content {
+        UserContent::Text(text) => text.clone(),
+        UserContent::Blocks(blocks) => blocks_text(blocks),
+    }
+}
+
+fn blocks_text(blocks: &[ContentBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn first_line(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out = text.chars().take(max.saturating_sub(1)).collect::<String>();
+    out.push('…');
+    out
+}
+
+const fn or_placeholder(text: &str) -> &str {
+    if text.is_empty() {
+        "_Not recorded._"
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AssistantMessage, StopReason, TextContent, ToolCall, Usage};
+    use serde_json::json;
+
+    fn assistant(content: Vec<ContentBlock>) -> SessionMessage {
+        SessionMessage::Assistant {
+            message: AssistantMessage {
+                content,
+                api: "test".to_string(),
+                provider: "test".to_string(),
+                model: "test".to_string(),
+                usage: Usage::default(),
+                stop_reason: StopReason::Stop,
+                abort_reason: None,
+                error_message: None,
+                timestamp: 0,
+            },
+        }
+    }
+
+    fn tool_call(id: &str, name: &str, arguments: serde_json::Value) -> ContentBlock {
+        ContentBlock::ToolCall(ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments,
+            thought_signature: None,
+        })
+    }
+
+    fn tool_result(id: &str, name: &str, is_error: bool) -> SessionMessage {
+        SessionMessage::ToolResult {
+            tool_call_id: id.to_string(),
+            tool_name: name.to_string(),
+            content: Vec::new(),
+            details: None,
+            is_error,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn summarizes_problem_changes_and_tests() {
+        let mut session = Session::in_memory();
+        session.append_message(SessionMessage::User {
+            content: UserContent::Text(
+                "Fix the off-by-one in pagination\n\nPage 2 repeats the last row.".to_string(),
+            ),
+            timestamp: None,
+        });
+        session.append_message(assistant(vec![
+            tool_call("c1", "edit", json!({ "path": "src/page.rs" })),
+            tool_call("c2", "read", json!({ "path": "src/lib.rs" })),
+            tool_call("c3", "bash", json!({ "command": "cargo test page" })),
+        ]));
+        session.append_message(tool_result("c1", "edit", false));
+        session.append_message(tool_result("c2", "read", false));
+        session.append_message(tool_result("c3", "bash", true));
+        session.append_bash_execution(
+            "cargo test page".to_string(),
+            "ok".to_string(),
+            0,
+            false,
+            false,
+            None,
+        );
+        session.append_bash_execution("ls".to_string(), String::new(), 0, false, false, None);
+        session.append_message(assistant(vec![ContentBlock::Text(TextContent::new(
+            "Use an exclusive upper bound when slicing pages.",
+        ))]));
+
+        let summary = PrSummary::from_session(&session);
+        assert_eq!(summary.title, "Fix the off-by-one in pagination");
+        assert!(summary.problem.contains("Page 2 repeats"));
+        assert_eq!(
+            summary.approach,
+            "Use an exclusive upper bound when slicing pages."
+        );
+        assert_eq!(summary.changes, vec!["src/page.rs".to_string()]);
+        assert_eq!(
+            summary.tests,
+            vec![TestRun {
+                command: "cargo test page".to_string(),
+                passed: true,
+            }]
+        );
+
+        let body = summary.to_markdown();
+        assert!(body.starts_with("## Problem\n"));
+        assert!(body.contains("- `src/page.rs`"));
+        assert!(body.contains("- `cargo test page` (passed)"));
+    }
+
+    #[test]
+    fn empty_session_renders_placeholders() {
+        let session = Session::in_memory();
+        let summary = PrSummary::from_session(&session);
+        assert_eq!(summary, PrSummary::default());
+        let body = summary.to_markdown();
+        assert!(body.contains("_No file changes recorded._"));
+        assert!(body.contains("_No test runs recorded._"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Session templates for recurring workflows (`pi --template <name>`).
+//!
+//! A template is a JSON file at `<global dir>/templates/<name>.json`:
+//!
+//! ```json
+//! {
+//!   "systemPrompt": "You review diffs for correctness.",
+//!   "messages": ["Review the changes on this branch against main."],
+//!   "tools": ["read", "grep", "find", "ls"],
+//!   "provider": "anthropic",
+//!   "model": "claude-sonnet-4",
+//!   "thinking": "high"
+//! }
+//! ```
+//!
+//! Every field is optional. Flags given on the command line win over the template; the
+//! messages are added to the new session as user messages, and the template name is recorded
+//! in the session header.
+
+use crate::cli::{Cli, DEFAULT_TOOLS};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::model::UserContent;
+use crate::session::{Session, SessionMessage};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SessionTemplate {
+    /// File stem the template was loaded from.
+    #[serde(skip)]
+    pub name: String,
+    pub system_prompt: Option<String>,
+    pub append_system_prompt: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<String>,
+    pub tools: Option<Vec<String>>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub thinking: Option<String>,
+}
+
+/// Directory holding the user's session templates.
+pub fn templates_dir() -> PathBuf {
+    Config::global_dir().join("templates")
+}
+
+impl SessionTemplate {
+    /// Load `<dir>/<name>.json`.
+    pub fn load(dir: &Path, name: &str) -> Result<Self> {
+        if name.is_empty()
+            || name.starts_with('.')
+            || name.contains(|c: char| matches!(c, '/' | '\\'))
+        {
+            return Err(Error::validation(format!("Invalid template name: {name}")));
+        }
+        let path = dir.join(format!("{name}.json"));
+        let content = std::fs::read_to_string(&path).map_err(|err| {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                Error::config(format!(
+                    "Template '{name}' not found (expected {})",
+                    path.display()
+                ))
+            } else {
+                Error::config(format!("Failed to read template {}: {err}", path.display()))
+            }
+        })?;
+        let mut template: Self = serde_json::from_str(&content).map_err(|err| {
+            Error::config(format!(
+                "Failed to parse template {}: {err}",
+                path.display()
+            ))
+        })?;
+        template.name = name.to_string();
+        Ok(template)
+    }
+
+    /// Fill in the CLI options the user did not set from the template.
+    ///
+    /// `--tools` counts as set only when it differs from the built-in default list.
+    pub fn apply_to_cli(&self, cli: &mut Cli) {
+        if cli.system_prompt.is_none() {
+            cli.system_prompt.clone_from(&self.system_prompt);
+        }
+        if cli.append_system_prompt.is_none() {
+            cli.append_system_prompt
+                .clone_from(&self.append_system_prompt);
+        }
+        if cli.provider.is_none() && cli.model.is_none() {
+            cli.provider.clone_from(&self.provider);
+            cli.model.clone_from(&self.model);
+        }
+        if cli.thinking.is_none() {
+            cli.thinking.clone_from(&self.thinking);
+        }
+        if let Some(tools) = &self.tools {
+            if !cli.no_tools && cli.tools == DEFAULT_TOOLS {
+                if tools.is_empty() {
+                    cli.no_tools = true;
+                } else {
+                    cli.tools = tools.join(",");
+                }
+            }
+        }
+    }
+
+    /// Record the template in the session header and add its messages.
+    pub fn seed(&self, session: &mut Session) {
+        session.header.template = Some(self.name.clone());
+        for text in &self.messages {
+            session.append_message(SessionMessage::User {
+                content: UserContent::Text(text.clone()),
+                timestamp: Some(chrono::Utc::now().timestamp_millis()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn template_fills_unset_options_and_seeds_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("review.json"),
+            r#"{
+                "systemPrompt": "You review diffs.",
+                "messages": ["Review the branch."],
+                "tools": ["read", "grep"],
+                "provider": "anthropic",
+                "model": "claude-sonnet-4",
+                "thinking": "high"
+            }"#,
+        )
+        .unwrap();
+
+        let template = SessionTemplate::load(dir.path(), "review").unwrap();
+        assert_eq!(template.name, "review");
+
+        let mut cli = Cli::parse_from(["pi", "--thinking", "low"]);
+        template.apply_to_cli(&mut cli);
+        assert_eq!(cli.system_prompt.as_deref(), Some("You review diffs."));
+        assert_eq!(cli.model.as_deref(), Some("claude-sonnet-4"));
+        assert_eq!(cli.thinking.as_deref(), Some("low"));
+        assert_eq!(cli.tools, "read,grep");
+
+        let mut session = Session::in_memory();
+        template.seed(&mut session);
+        assert_eq!(session.header.template.as_deref(), Some("review"));
+        assert_eq!(session.to_messages_for_current_path().len(), 1);
+
+        let err = SessionTemplate::load(dir.path(), "missing").unwrap_err();
+        assert!(err.to_string().contains("Template 'missing' not found"));
+        assert!(SessionTemplate::load(dir.path(), "../review").is_err());
+    }
+}
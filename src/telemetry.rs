@@ -0,0 +1,112 @@
+//! Logging and trace export.
+//!
+//! Log lines go to stderr, filtered by `RUST_LOG`. With an OTLP endpoint (`--otlp-endpoint` or
+//! `OTEL_EXPORTER_OTLP_ENDPOINT`), the agent's spans are also exported over OTLP/HTTP so runs
+//! can be inspected in Jaeger, Grafana Tempo, or any other OTLP collector:
+//! - `agent.run`: one prompt, with the provider, model, turn count and token totals
+//! - `agent.request`: one provider request, with its usage and stop reason
+//! - `agent.tool`: one tool call, with whether it failed
+//!
+//! Every span records `duration_ms`. Export needs a build with the `otlp` feature.
+
+use std::io;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::Layer as _;
+use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::util::SubscriberInitExt as _;
+
+/// Service name reported with exported spans.
+#[cfg(feature = "otlp")]
+const SERVICE_NAME: &str = "pi";
+
+/// Install the global subscriber: stderr logs, plus span export when `otlp_endpoint` is set.
+pub fn init(otlp_endpoint: Option<&str>) {
+    let logs = tracing_subscriber::fmt::layer()
+        .with_target(false)
+        .with_writer(io::stderr)
+        .with_filter(EnvFilter::from_default_env());
+    let registry = tracing_subscriber::registry().with(logs);
+
+    let Some(endpoint) = otlp_endpoint.filter(|endpoint| !endpoint.trim().is_empty()) else {
+        registry.init();
+        return;
+    };
+    #[cfg(feature = "otlp")]
+    let spans = otlp::layer(endpoint);
+    #[cfg(not(feature = "otlp"))]
+    let spans: Result<tracing_subscriber::layer::Identity, String> =
+        Err("pi was built without the `otlp` feature".to_string());
+    match spans {
+        Ok(spans) => registry.with(spans).init(),
+        Err(err) => {
+            registry.init();
+            tracing::warn!("OTLP export to {endpoint} disabled: {err}");
+        }
+    }
+}
+
+/// The traces URL for an OTLP/HTTP collector given as a base URL or a full `/v1/traces` URL.
+pub fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim().trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{endpoint}/v1/traces")
+    }
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use super::{SERVICE_NAME, traces_url};
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig as _;
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use tracing::Subscriber;
+    use tracing_subscriber::Layer;
+    use tracing_subscriber::filter::Targets;
+    use tracing_subscriber::registry::LookupSpan;
+
+    /// Layer exporting the crate's info-level spans to `endpoint`. Spans are sent as they
+    /// close, so nothing is lost when the process exits.
+    pub fn layer<S>(endpoint: &str) -> Result<impl Layer<S>, String>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(traces_url(endpoint))
+            .build()
+            .map_err(|err| err.to_string())?;
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter)
+            .with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                SERVICE_NAME,
+            )]))
+            .build();
+        let tracer = provider.tracer(SERVICE_NAME);
+        opentelemetry::global::set_tracer_provider(provider);
+        Ok(tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(Targets::new().with_target("pi", tracing::Level::INFO)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_url_appends_the_signal_path_once() {
+        assert_eq!(
+            traces_url("http://localhost:4318"),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("http://localhost:4318/v1/traces/"),
+            "http://localhost:4318/v1/traces"
+        );
+    }
+}
@@ -24,6 +24,9 @@ pub struct TuiStyles {
     pub warning: LipglossStyle,
     pub warning_bold: LipglossStyle,
     pub error_bold: LipglossStyle,
+    pub diff_added: LipglossStyle,
+    pub diff_removed: LipglossStyle,
+    pub diff_hunk: LipglossStyle,
     pub border: LipglossStyle,
     pub selection: LipglossStyle,
 }
@@ -187,6 +190,11 @@ impl Theme {
             error_bold: LipglossStyle::new()
                 .foreground(self.colors.error.as_str())
                 .bold(),
+            diff_added: LipglossStyle::new().foreground(self.colors.success.as_str()),
+            diff_removed: LipglossStyle::new().foreground(self.colors.error.as_str()),
+            diff_hunk: LipglossStyle::new()
+                .foreground(self.colors.accent.as_str())
+                .bold(),
             border: LipglossStyle::new().foreground(self.ui.border.as_str()),
             selection: LipglossStyle::new()
                 .foreground(self.colors.foreground.as_str())
@@ -234,7 +242,12 @@ impl Theme {
     /// Load a theme from a JSON file.
     pub fn load(path: &Path) -> Result<Self> {
         let content = fs::read_to_string(path)?;
-        let theme: Self = serde_json::from_str(&content)?;
+        Self::from_json_str(&content)
+    }
+
+    /// Parse and validate a theme from JSON text.
+    pub fn from_json_str(content: &str) -> Result<Self> {
+        let theme: Self = serde_json::from_str(content)?;
         theme.validate()?;
         Ok(theme)
     }
@@ -6,19 +6,20 @@
 //! locally by the agent loop. Each tool returns structured [`ContentBlock`] output suitable for
 //! rendering in the TUI and for inclusion in provider messages as tool results.
 
-use crate::config::Config;
+use crate::config::{Config, FileContentSettings};
 use crate::error::{Error, Result};
+use crate::file_content::{self, FileContentOptions};
 use crate::model::{ContentBlock, ImageContent, TextContent};
 use asupersync::io::AsyncWriteExt;
 use asupersync::time::{sleep, wall_now};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Write as _;
 use std::io::{BufRead, Read};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock, mpsc};
 use std::thread;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
@@ -304,7 +305,7 @@ fn truncate_string_to_bytes_from_end(s: &str, max_bytes: usize) -> String {
 
 /// Format a byte count into a human-readable string with appropriate unit suffix.
 #[allow(clippy::cast_precision_loss)]
-fn format_size(bytes: usize) -> String {
+pub(crate) fn format_size(bytes: usize) -> String {
     const KB: usize = 1024;
     const MB: usize = 1024 * 1024;
 
@@ -591,6 +592,135 @@ fn resolve_path(file_path: &str, cwd: &Path) -> PathBuf {
     resolve_to_cwd(file_path, cwd)
 }
 
+// ============================================================================
+// Write Scope
+// ============================================================================
+
+/// System directories `write` and `edit` never touch, whatever the settings or the user say.
+const SYSTEM_PATHS: &[&str] = &[
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/lib",
+    "/lib64",
+    "/proc",
+    "/sbin",
+    "/sys",
+    "/usr/bin",
+    "/usr/lib",
+    "/usr/sbin",
+    "/System",
+    "/private/etc",
+    "C:\\Windows",
+];
+
+/// How a file change relates to the places `write` and `edit` may change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteAccess {
+    /// Inside the working directory or a configured writable root.
+    Allowed,
+    /// Outside every writable root; the change needs the user's one-off approval.
+    NeedsApproval,
+    /// A system path; refused outright.
+    Denied,
+}
+
+/// Where `write` and `edit` may change files: the working directory, the `writableRoots`
+/// setting, and paths the user approved for a single write. Clones share approvals, so the
+/// agent can approve a path that the tool then writes.
+#[derive(Debug, Clone)]
+pub struct WriteScope {
+    cwd: PathBuf,
+    roots: Vec<PathBuf>,
+    approved: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl WriteScope {
+    pub fn new(cwd: &Path, roots: &[String]) -> Self {
+        Self {
+            cwd: canonical_target(cwd),
+            roots: roots
+                .iter()
+                .map(|root| canonical_target(&resolve_to_cwd(root, cwd)))
+                .collect(),
+            approved: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Classify a tool's `path` argument.
+    pub fn check(&self, file_path: &str) -> WriteAccess {
+        let target = canonical_target(&resolve_to_cwd(file_path, &self.cwd));
+        if SYSTEM_PATHS.iter().any(|system| target.starts_with(system)) {
+            return WriteAccess::Denied;
+        }
+        if target.starts_with(&self.cwd) || self.roots.iter().any(|root| target.starts_with(root)) {
+            return WriteAccess::Allowed;
+        }
+        WriteAccess::NeedsApproval
+    }
+
+    /// Let the next change to `file_path` through once.
+    pub fn approve_once(&self, file_path: &str) {
+        let target = canonical_target(&resolve_to_cwd(file_path, &self.cwd));
+        if let Ok(mut approved) = self.approved.lock() {
+            approved.insert(target);
+        }
+    }
+
+    /// Refuse a change the scope does not allow, using up a one-off approval if there is one.
+    fn authorize(&self, tool: &str, file_path: &str) -> Result<()> {
+        match self.check(file_path) {
+            WriteAccess::Allowed => Ok(()),
+            WriteAccess::Denied => Err(Error::tool(
+                tool,
+                format!("Refusing to change system path {file_path}"),
+            )),
+            WriteAccess::NeedsApproval => {
+                let target = canonical_target(&resolve_to_cwd(file_path, &self.cwd));
+                let approved = self
+                    .approved
+                    .lock()
+                    .is_ok_and(|mut approved| approved.remove(&target));
+                if approved {
+                    Ok(())
+                } else {
+                    Err(Error::tool(
+                        tool,
+                        format!(
+                            "{file_path} is outside the workspace; add its directory to the \
+                             writableRoots setting or approve the change"
+                        ),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// `path` with `.`/`..` removed and its longest existing ancestor canonicalized, so symlinks
+/// cannot smuggle a change out of a writable root.
+fn canonical_target(path: &Path) -> PathBuf {
+    let path = normalize_dot_segments(path);
+    let mut existing = path.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = std::fs::canonicalize(existing) {
+            return rest
+                .iter()
+                .rev()
+                .fold(canonical, |acc, part| acc.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path,
+        }
+    }
+}
+
 pub(crate) fn detect_supported_image_mime_type_from_bytes(bytes: &[u8]) -> Option<&'static str> {
     // Supported image types match the legacy tool: jpeg/png/gif/webp only.
     if bytes.len() >= 8 && bytes.starts_with(b"\x89PNG\r\n\x1A\n") {
@@ -829,8 +959,13 @@ pub(crate) fn resize_image_if_needed(
 /// The registry is constructed from configuration (enabled tool names + settings) and is used for:
 /// - Looking up a tool implementation by name during tool-call execution.
 /// - Enumerating tool schemas when building provider requests.
+///
+/// Tools can be disabled at runtime (e.g. via `/tools`); disabled tools stay registered but are
+/// neither advertised to the model nor executed.
 pub struct ToolRegistry {
     tools: Vec<Box<dyn Tool>>,
+    disabled: HashSet<String>,
+    write_scope: Option<WriteScope>,
 }
 
 impl ToolRegistry {
@@ -843,21 +978,27 @@ impl ToolRegistry {
         let block_images = config
             .and_then(|c| c.images.as_ref().and_then(|i| i.block_images))
             .unwrap_or(false);
+        let file_content = config.and_then(|c| c.file_content.clone());
+        let write_scope = WriteScope::new(
+            cwd,
+            config
+                .and_then(|c| c.writable_roots.as_deref())
+                .unwrap_or_default(),
+        );
 
         for name in enabled {
             match *name {
-                "read" => tools.push(Box::new(ReadTool::with_settings(
-                    cwd,
-                    image_auto_resize,
-                    block_images,
-                ))),
+                "read" => tools.push(Box::new(
+                    ReadTool::with_settings(cwd, image_auto_resize, block_images)
+                        .with_file_content(file_content.clone()),
+                )),
                 "bash" => tools.push(Box::new(BashTool::with_shell(
                     cwd,
                     shell_path.clone(),
                     shell_command_prefix.clone(),
                 ))),
-                "edit" => tools.push(Box::new(EditTool::new(cwd))),
-                "write" => tools.push(Box::new(WriteTool::new(cwd))),
+                "edit" => tools.push(Box::new(EditTool::with_scope(cwd, write_scope.clone()))),
+                "write" => tools.push(Box::new(WriteTool::with_scope(cwd, write_scope.clone()))),
                 "grep" => tools.push(Box::new(GrepTool::new(cwd))),
                 "find" => tools.push(Box::new(FindTool::new(cwd))),
                 "ls" => tools.push(Box::new(LsTool::new(cwd))),
@@ -865,12 +1006,26 @@ impl ToolRegistry {
             }
         }
 
-        Self { tools }
+        Self {
+            tools,
+            disabled: HashSet::new(),
+            write_scope: Some(write_scope),
+        }
     }
 
     /// Construct a registry from a pre-built tool list.
     pub fn from_tools(tools: Vec<Box<dyn Tool>>) -> Self {
-        Self { tools }
+        Self {
+            tools,
+            disabled: HashSet::new(),
+            write_scope: None,
+        }
+    }
+
+    /// The scope the built-in `write` and `edit` tools enforce; `None` for registries built
+    /// from a tool list.
+    pub const fn write_scope(&self) -> Option<&WriteScope> {
+        self.write_scope.as_ref()
     }
 
     /// Convert the registry into the owned tool list.
@@ -903,6 +1058,32 @@ impl ToolRegistry {
             .find(|t| t.name() == name)
             .map(std::convert::AsRef::as_ref)
     }
+
+    /// Get the tools that are currently enabled.
+    pub fn enabled_tools(&self) -> impl Iterator<Item = &dyn Tool> {
+        self.tools
+            .iter()
+            .filter(|t| !self.disabled.contains(t.name()))
+            .map(std::convert::AsRef::as_ref)
+    }
+
+    /// Whether `name` is registered and enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.get(name).is_some() && !self.disabled.contains(name)
+    }
+
+    /// Enable or disable a registered tool. Returns `false` if no tool is named `name`.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        if self.get(name).is_none() {
+            return false;
+        }
+        if enabled {
+            self.disabled.remove(name);
+        } else {
+            self.disabled.insert(name.to_string());
+        }
+        true
+    }
 }
 
 // ============================================================================
@@ -922,6 +1103,7 @@ pub struct ReadTool {
     cwd: PathBuf,
     auto_resize: bool,
     block_images: bool,
+    file_content: Option<FileContentSettings>,
 }
 
 impl ReadTool {
@@ -930,6 +1112,7 @@ impl ReadTool {
             cwd: cwd.to_path_buf(),
             auto_resize: true,
             block_images: false,
+            file_content: None,
         }
     }
 
@@ -938,8 +1121,16 @@ impl ReadTool {
             cwd: cwd.to_path_buf(),
             auto_resize,
             block_images,
+            file_content: None,
         }
     }
+
+    /// Shape text output (strip comments, collapse blank and repeated lines) per settings.
+    #[must_use]
+    pub fn with_file_content(mut self, settings: Option<FileContentSettings>) -> Self {
+        self.file_content = settings;
+        self
+    }
 }
 
 #[async_trait]
@@ -1090,16 +1281,33 @@ impl Tool for ReadTool {
         // Format: "     N→content" where N is right-aligned
         let max_line_num = end_line;
         let line_num_width = max_line_num.to_string().len().max(5);
-        let selected_content: String = all_lines[start_line..end_line]
-            .iter()
-            .enumerate()
-            .map(|(i, line)| {
-                let line_num = start_line + i + 1;
-                let line = line.strip_suffix('\r').unwrap_or(line);
-                format!("{line_num:>line_num_width$}→{line}")
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+        let shaping = FileContentOptions::resolve(self.file_content.as_ref(), &path);
+        // When shaping drops lines, output line i covers original lines up to shaped_lines[i].
+        let mut shaped_lines: Option<Vec<usize>> = None;
+        let mut shaped_note: Option<String> = None;
+        let selected_content: String = if shaping.is_enabled() {
+            let shaped = file_content::shape_lines(
+                &all_lines[start_line..end_line],
+                start_line_display,
+                file_content::language_for_path(&path),
+                shaping,
+            );
+            shaped_note = shaped.summary();
+            let (text, covered) = shaped.render(line_num_width);
+            shaped_lines = Some(covered);
+            text
+        } else {
+            all_lines[start_line..end_line]
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let line_num = start_line + i + 1;
+                    let line = line.strip_suffix('\r').unwrap_or(line);
+                    format!("{line_num:>line_num_width$}→{line}")
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
 
         let truncation = truncate_head(&selected_content, DEFAULT_MAX_LINES, DEFAULT_MAX_BYTES);
 
@@ -1117,9 +1325,15 @@ impl Tool for ReadTool {
             );
             details = Some(serde_json::json!({ "truncation": truncation }));
         } else if truncation.truncated {
-            let end_line_display = start_line_display
-                .saturating_add(truncation.output_lines)
-                .saturating_sub(1);
+            let end_line_display = shaped_lines
+                .as_ref()
+                .and_then(|covered| covered.get(truncation.output_lines.saturating_sub(1)))
+                .copied()
+                .unwrap_or_else(|| {
+                    start_line_display
+                        .saturating_add(truncation.output_lines)
+                        .saturating_sub(1)
+                });
             let next_offset = end_line_display.saturating_add(1);
 
             if truncation.truncated_by == Some(TruncatedBy::Lines) {
@@ -1149,6 +1363,10 @@ impl Tool for ReadTool {
             }
         }
 
+        if let Some(note) = shaped_note {
+            let _ = write!(output_text, "\n\n{note}");
+        }
+
         Ok(ToolOutput {
             content: vec![ContentBlock::Text(TextContent::new(output_text))],
             details,
@@ -1165,8 +1383,12 @@ impl Tool for ReadTool {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct BashInput {
-    command: String,
+    command: Option<String>,
     timeout: Option<u64>,
+    yield_after: Option<u64>,
+    job: Option<String>,
+    #[serde(default)]
+    kill: bool,
 }
 
 pub struct BashTool {
@@ -1178,11 +1400,14 @@ pub struct BashTool {
 #[derive(Debug, Clone)]
 pub struct BashRunResult {
     pub output: String,
-    pub exit_code: i32,
+    /// `None` while the command is still running as a background job.
+    pub exit_code: Option<i32>,
     pub cancelled: bool,
     pub truncated: bool,
     pub full_output_path: Option<String>,
     pub truncation: Option<TruncationResult>,
+    /// Set when the command is still running as a background job.
+    pub job_id: Option<String>,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -1192,6 +1417,7 @@ pub(crate) async fn run_bash_command(
     command_prefix: Option<&str>,
     command: &str,
     timeout_secs: Option<u64>,
+    yield_after_secs: Option<u64>,
     on_update: Option<&(dyn Fn(ToolUpdate) + Send + Sync)>,
 ) -> Result<BashRunResult> {
     let timeout_secs = match timeout_secs {
@@ -1295,6 +1521,15 @@ pub(crate) async fn run_bash_command(
             }
         }
 
+        if let Some(yield_after) = yield_after_secs.filter(|_| terminate_deadline.is_none()) {
+            if start.elapsed() >= Duration::from_secs(yield_after) {
+                let output =
+                    String::from_utf8_lossy(&concat_chunks(&bash_output.chunks)).to_string();
+                let job_id = detach_bash_job(guard, rx, timeout.map(|timeout| start + timeout));
+                return Ok(BashJob::still_running(&output, &job_id, start.elapsed()));
+            }
+        }
+
         sleep(wall_now(), tick).await;
     }
 
@@ -1387,7 +1622,7 @@ pub(crate) async fn run_bash_command(
 
     Ok(BashRunResult {
         output: output_text,
-        exit_code,
+        exit_code: Some(exit_code),
         cancelled,
         truncated: truncation.truncated,
         full_output_path,
@@ -1396,6 +1631,217 @@ pub(crate) async fn run_bash_command(
         } else {
             None
         },
+        job_id: None,
+    })
+}
+
+/// Commands that outlived their `yieldAfter` window, keyed by job id.
+fn bash_jobs() -> &'static Mutex<HashMap<String, Arc<Mutex<BashJob>>>> {
+    static JOBS: OnceLock<Mutex<HashMap<String, Arc<Mutex<BashJob>>>>> = OnceLock::new();
+    JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn lock_job(job: &Mutex<BashJob>) -> std::sync::MutexGuard<'_, BashJob> {
+    job.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// A bash command running in the background. Output is buffered until the model reads it
+/// with a follow-up `{"job": id}` call, so long builds can be checked chunk by chunk.
+#[derive(Debug)]
+struct BashJob {
+    started: Instant,
+    /// Output produced since the last read (only the tail is kept).
+    unread: VecDeque<u8>,
+    /// Bytes dropped from the front of `unread` since the last read.
+    dropped: usize,
+    /// Process id of the shell, so the job can be stopped when pi exits.
+    pid: Option<u32>,
+    exit_code: Option<i32>,
+    timed_out: bool,
+    kill_requested: bool,
+}
+
+impl BashJob {
+    fn still_running(output: &str, job_id: &str, elapsed: Duration) -> BashRunResult {
+        let truncation = truncate_tail(output, DEFAULT_MAX_LINES, DEFAULT_MAX_BYTES);
+        let mut output_text = if truncation.content.is_empty() {
+            "(no output yet)".to_string()
+        } else {
+            truncation.content.clone()
+        };
+        let _ = write!(
+            output_text,
+            "\n\n[Still running after {}s as job {job_id}. Call bash with {{\"job\": \"{job_id}\"}} to read further output (add \"yieldAfter\" to wait for it), or {{\"job\": \"{job_id}\", \"kill\": true}} to stop it.]",
+            elapsed.as_secs()
+        );
+        BashRunResult {
+            output: output_text,
+            exit_code: None,
+            cancelled: false,
+            truncated: truncation.truncated,
+            full_output_path: None,
+            truncation: truncation.truncated.then_some(truncation),
+            job_id: Some(job_id.to_string()),
+        }
+    }
+}
+
+/// Move a running command to a background thread that keeps collecting its output.
+fn detach_bash_job(
+    mut guard: ProcessGuard,
+    rx: mpsc::Receiver<Vec<u8>>,
+    deadline: Option<Instant>,
+) -> String {
+    let job_id = Uuid::new_v4().simple().to_string()[..8].to_string();
+    let job = Arc::new(Mutex::new(BashJob {
+        started: Instant::now(),
+        unread: VecDeque::new(),
+        dropped: 0,
+        pid: guard.child.as_ref().map(std::process::Child::id),
+        exit_code: None,
+        timed_out: false,
+        kill_requested: false,
+    }));
+    bash_jobs()
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(job_id.clone(), Arc::clone(&job));
+
+    thread::spawn(move || {
+        let collect = |chunk: Vec<u8>| {
+            let mut job = lock_job(&job);
+            job.unread.extend(chunk);
+            let excess = job
+                .unread
+                .len()
+                .saturating_sub(DEFAULT_MAX_BYTES.saturating_mul(2));
+            job.unread.drain(..excess);
+            job.dropped += excess;
+        };
+        let exit_code = loop {
+            while let Ok(chunk) = rx.try_recv() {
+                collect(chunk);
+            }
+            let Some(child) = guard.child.as_mut() else {
+                break None;
+            };
+            match child.try_wait() {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) => {}
+                Err(_) => break None,
+            }
+            let timed_out = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if timed_out || lock_job(&job).kill_requested {
+                lock_job(&job).timed_out = timed_out;
+                break guard.kill().ok().flatten().and_then(|status| status.code());
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+        while let Ok(chunk) = rx.recv_timeout(Duration::from_millis(200)) {
+            collect(chunk);
+        }
+        lock_job(&job).exit_code = Some(exit_code.unwrap_or(-1));
+    });
+
+    job_id
+}
+
+/// Stop every background job, e.g. when pi exits, so no command outlives it.
+pub fn kill_bash_jobs() {
+    let jobs = bash_jobs()
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .drain()
+        .map(|(_, job)| job)
+        .collect::<Vec<_>>();
+    for job in jobs {
+        let mut job = lock_job(&job);
+        job.kill_requested = true;
+        if job.exit_code.is_none() {
+            kill_process_tree(job.pid);
+        }
+    }
+}
+
+/// Return the output a background job produced since the last read, waiting up to
+/// `yield_after_secs` for it to finish. Finished jobs are removed.
+pub(crate) async fn read_bash_job(
+    job_id: &str,
+    kill: bool,
+    yield_after_secs: Option<u64>,
+) -> Result<BashRunResult> {
+    let job = bash_jobs()
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(job_id)
+        .cloned()
+        .ok_or_else(|| Error::tool("bash", format!("No running bash job {job_id}")))?;
+    if kill {
+        lock_job(&job).kill_requested = true;
+    }
+    let wait = if kill {
+        Duration::from_secs(BASH_TERMINATE_GRACE_SECS)
+    } else {
+        Duration::from_secs(yield_after_secs.unwrap_or(0))
+    };
+    let deadline = Instant::now() + wait;
+    while lock_job(&job).exit_code.is_none() && Instant::now() < deadline {
+        sleep(wall_now(), Duration::from_millis(50)).await;
+    }
+
+    let (unread, dropped, exit_code, timed_out, killed, elapsed) = {
+        let mut job = lock_job(&job);
+        let unread: Vec<u8> = job.unread.drain(..).collect();
+        (
+            unread,
+            std::mem::take(&mut job.dropped),
+            job.exit_code,
+            job.timed_out,
+            job.kill_requested,
+            job.started.elapsed(),
+        )
+    };
+    let output = String::from_utf8_lossy(&unread).to_string();
+    // Noted after truncation so the marker is never cut off with the head of the output.
+    let dropped_note = (dropped > 0).then(|| format!("[{dropped} earlier bytes dropped]\n"));
+    let Some(exit_code) = exit_code else {
+        let mut result = BashJob::still_running(&output, job_id, elapsed);
+        if let Some(note) = &dropped_note {
+            result.output.insert_str(0, note);
+        }
+        return Ok(result);
+    };
+    bash_jobs()
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .remove(job_id);
+
+    let truncation = truncate_tail(&output, DEFAULT_MAX_LINES, DEFAULT_MAX_BYTES);
+    let mut output_text = if truncation.content.is_empty() {
+        "(no new output)".to_string()
+    } else {
+        truncation.content.clone()
+    };
+    if let Some(note) = &dropped_note {
+        output_text.insert_str(0, note);
+    }
+    let cancelled = timed_out || killed;
+    if timed_out {
+        output_text.push_str("\n\nCommand timed out");
+    } else if killed {
+        let _ = write!(output_text, "\n\nJob {job_id} stopped");
+    } else if exit_code != 0 {
+        let _ = write!(output_text, "\n\nCommand exited with code {exit_code}");
+    }
+    Ok(BashRunResult {
+        output: output_text,
+        exit_code: Some(exit_code),
+        cancelled,
+        truncated: truncation.truncated,
+        full_output_path: None,
+        truncation: truncation.truncated.then_some(truncation),
+        job_id: None,
     })
 }
 
@@ -1431,7 +1877,7 @@ impl Tool for BashTool {
         "bash"
     }
     fn description(&self) -> &str {
-        "Execute a bash command in the current working directory. Returns stdout and stderr. Output is truncated to last 2000 lines or 50KB (whichever is hit first). If truncated, full output is saved to a temp file. `timeout` defaults to 120 seconds; set `timeout: 0` to disable."
+        "Execute a bash command in the current working directory. Returns stdout and stderr. Output is truncated to last 2000 lines or 50KB (whichever is hit first). If truncated, full output is saved to a temp file. `timeout` defaults to 120 seconds; set `timeout: 0` to disable. For long builds or test runs, set `yieldAfter` to get the output so far after that many seconds while the command keeps running as a job; read later output with `job`, or stop it with `job` and `kill: true`."
     }
 
     fn parameters(&self) -> serde_json::Value {
@@ -1445,9 +1891,24 @@ impl Tool for BashTool {
                 "timeout": {
                     "type": "number",
                     "description": "Timeout in seconds (default 120; set 0 to disable)"
+                },
+                "yieldAfter": {
+                    "type": "number",
+                    "description": "Return the output so far after this many seconds if the command is still running; with `job`, wait up to this long for more output"
+                },
+                "job": {
+                    "type": "string",
+                    "description": "Id of a running job to read further output from (instead of `command`)"
+                },
+                "kill": {
+                    "type": "boolean",
+                    "description": "With `job`, stop the job"
                 }
             },
-            "required": ["command"]
+            "oneOf": [
+                { "required": ["command"] },
+                { "required": ["job"] }
+            ]
         })
     }
 
@@ -1461,15 +1922,24 @@ impl Tool for BashTool {
         let input: BashInput =
             serde_json::from_value(input).map_err(|e| Error::validation(e.to_string()))?;
 
-        let result = run_bash_command(
-            &self.cwd,
-            self.shell_path.as_deref(),
-            self.command_prefix.as_deref(),
-            &input.command,
-            input.timeout,
-            on_update.as_deref(),
-        )
-        .await?;
+        let result = if let Some(job_id) = input.job.as_deref() {
+            read_bash_job(job_id, input.kill, input.yield_after).await?
+        } else {
+            let command = input
+                .command
+                .as_deref()
+                .ok_or_else(|| Error::validation("bash requires `command` or `job`"))?;
+            run_bash_command(
+                &self.cwd,
+                self.shell_path.as_deref(),
+                self.command_prefix.as_deref(),
+                command,
+                input.timeout,
+                input.yield_after,
+                on_update.as_deref(),
+            )
+            .await?
+        };
 
         let mut details_map = serde_json::Map::new();
         if let Some(truncation) = result.truncation.as_ref() {
@@ -1481,6 +1951,13 @@ impl Tool for BashTool {
                 serde_json::Value::String(path.clone()),
             );
         }
+        if let Some(job_id) = result.job_id.as_ref() {
+            details_map.insert("job".to_string(), serde_json::Value::String(job_id.clone()));
+        }
+        if result.job_id.is_some() || input.job.is_some() {
+            // `null` until the job exits.
+            details_map.insert("exitCode".to_string(), serde_json::json!(result.exit_code));
+        }
 
         let details = if details_map.is_empty() {
             None
@@ -1488,7 +1965,7 @@ impl Tool for BashTool {
             Some(serde_json::Value::Object(details_map))
         };
 
-        if result.cancelled || result.exit_code != 0 {
+        if result.cancelled || result.exit_code.is_some_and(|code| code != 0) {
             return Err(Error::tool("bash", result.output));
         }
 
@@ -1515,12 +1992,18 @@ struct EditInput {
 
 pub struct EditTool {
     cwd: PathBuf,
+    scope: WriteScope,
 }
 
 impl EditTool {
     pub fn new(cwd: &Path) -> Self {
+        Self::with_scope(cwd, WriteScope::new(cwd, &[]))
+    }
+
+    pub fn with_scope(cwd: &Path, scope: WriteScope) -> Self {
         Self {
             cwd: cwd.to_path_buf(),
+            scope,
         }
     }
 }
@@ -1855,6 +2338,74 @@ fn generate_diff_string(old_content: &str, new_content: &str) -> (String, Option
     (output.join("\n"), first_changed_line)
 }
 
+/// Context lines around each hunk offered for hunk-by-hunk review.
+const HUNK_CONTEXT_LINES: usize = 3;
+
+/// One reviewable hunk of a file change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    /// The `@@ -a,b +c,d @@` header.
+    pub header: String,
+    /// The hunk's context, `-` and `+` lines.
+    pub text: String,
+}
+
+/// Split the change from `old_content` to `new_content` into unified diff hunks.
+pub fn diff_hunks(old_content: &str, new_content: &str) -> Vec<DiffHunk> {
+    use similar::ChangeTag;
+
+    let diff = similar::TextDiff::from_lines(old_content, new_content);
+    diff.unified_diff()
+        .context_radius(HUNK_CONTEXT_LINES)
+        .iter_hunks()
+        .map(|hunk| {
+            let mut text = String::new();
+            for change in hunk.iter_changes() {
+                text.push(match change.tag() {
+                    ChangeTag::Delete => '-',
+                    ChangeTag::Insert => '+',
+                    ChangeTag::Equal => ' ',
+                });
+                text.push_str(change.value());
+                if !change.value().ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+            DiffHunk {
+                header: hunk.header().to_string(),
+                text,
+            }
+        })
+        .collect()
+}
+
+/// Rebuild the file keeping only the accepted hunks of [`diff_hunks`]; rejected hunks keep
+/// `old_content`'s lines. Hunks past the end of `accepted` are kept.
+pub fn apply_accepted_hunks(old_content: &str, new_content: &str, accepted: &[bool]) -> String {
+    let diff = similar::TextDiff::from_lines(old_content, new_content);
+    let old_lines = diff.old_slices();
+    let new_lines = diff.new_slices();
+    let mut output = String::with_capacity(new_content.len().max(old_content.len()));
+    let mut old_pos = 0;
+
+    for (index, group) in diff.grouped_ops(HUNK_CONTEXT_LINES).iter().enumerate() {
+        let keep_new = accepted.get(index).copied().unwrap_or(true);
+        for op in group {
+            let old_range = op.old_range();
+            output.extend(old_lines[old_pos..old_range.start].iter().copied());
+            let lines = if keep_new && op.tag() != similar::DiffTag::Equal {
+                &new_lines[op.new_range()]
+            } else {
+                &old_lines[old_range.clone()]
+            };
+            output.extend(lines.iter().copied());
+            old_pos = old_range.end;
+        }
+    }
+    output.extend(old_lines[old_pos..].iter().copied());
+    output
+}
+
 #[async_trait]
 #[allow(clippy::unnecessary_literal_bound)]
 impl Tool for EditTool {
@@ -1899,6 +2450,7 @@ impl Tool for EditTool {
         let input: EditInput =
             serde_json::from_value(input).map_err(|e| Error::validation(e.to_string()))?;
 
+        self.scope.authorize("edit", &input.path)?;
         let absolute_path = resolve_path(&input.path, &self.cwd);
 
         // Match legacy behavior: any access failure is reported as "File not found".
@@ -2034,12 +2586,18 @@ struct WriteInput {
 
 pub struct WriteTool {
     cwd: PathBuf,
+    scope: WriteScope,
 }
 
 impl WriteTool {
     pub fn new(cwd: &Path) -> Self {
+        Self::with_scope(cwd, WriteScope::new(cwd, &[]))
+    }
+
+    pub fn with_scope(cwd: &Path, scope: WriteScope) -> Self {
         Self {
             cwd: cwd.to_path_buf(),
+            scope,
         }
     }
 }
@@ -2084,6 +2642,7 @@ impl Tool for WriteTool {
         let input: WriteInput =
             serde_json::from_value(input).map_err(|e| Error::validation(e.to_string()))?;
 
+        self.scope.authorize("write", &input.path)?;
         let path = resolve_path(&input.path, &self.cwd);
 
         // Create parent directories if needed
@@ -3293,6 +3852,24 @@ mod tests {
         assert_eq!(result.truncated_by, Some(TruncatedBy::Bytes));
     }
 
+    #[test]
+    fn test_registry_toggles_tools() {
+        let cwd = PathBuf::from("/tmp");
+        let mut registry = ToolRegistry::new(&["read", "bash"], &cwd, None);
+        assert!(registry.is_enabled("bash"));
+
+        assert!(registry.set_enabled("bash", false));
+        assert!(!registry.is_enabled("bash"));
+        assert!(registry.get("bash").is_some());
+        let enabled = registry.enabled_tools().map(Tool::name).collect::<Vec<_>>();
+        assert_eq!(enabled, vec!["read"]);
+
+        assert!(registry.set_enabled("bash", true));
+        assert!(registry.is_enabled("bash"));
+        assert!(!registry.set_enabled("missing", false));
+        assert!(!registry.is_enabled("missing"));
+    }
+
     #[test]
     fn test_resolve_path_absolute() {
         let cwd = PathBuf::from("/home/user/project");
@@ -3352,6 +3929,25 @@ mod tests {
         assert_eq!(format_size(1_073_741_824), "1024.0MB");
     }
 
+    #[test]
+    fn test_apply_accepted_hunks_reverts_rejected_hunks() {
+        let old: String = (1..=20).map(|n| format!("line {n}\n")).collect();
+        let new = old
+            .replace("line 2\n", "line two\n")
+            .replace("line 18\n", "line eighteen\n");
+
+        let hunks = diff_hunks(&old, &new);
+        assert_eq!(hunks.len(), 2);
+        assert!(hunks[0].header.starts_with("@@ -1,"));
+        assert!(hunks[0].text.contains("-line 2\n+line two\n"));
+
+        let kept_second = apply_accepted_hunks(&old, &new, &[false, true]);
+        assert!(kept_second.contains("line 2\n"));
+        assert!(kept_second.contains("line eighteen\n"));
+        assert_eq!(apply_accepted_hunks(&old, &new, &[true, true]), new);
+        assert_eq!(apply_accepted_hunks(&old, &new, &[false, false]), old);
+    }
+
     #[test]
     fn test_js_string_length() {
         assert_eq!(js_string_length("hello"), 5);
@@ -0,0 +1,257 @@
+//! Git snapshots of the working tree for `/save` and `/restore` checkpoints.
+//!
+//! A snapshot is a commit built from a scratch index, so capturing never touches the user's
+//! index, worktree, or stash list. The commit is kept reachable under
+//! `refs/pi/snapshots/<commit>`; list them with `git for-each-ref refs/pi/snapshots`.
+
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Ref namespace that keeps snapshot commits from being garbage collected.
+const SNAPSHOT_REF_PREFIX: &str = "refs/pi/snapshots/";
+
+/// Git state captured with a `/save` checkpoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorktreeSnapshot {
+    /// `HEAD` at save time (absent in a repository without commits).
+    pub head: Option<String>,
+    /// Commit holding uncommitted changes and untracked files, if there were any.
+    pub stash: Option<String>,
+}
+
+impl WorktreeSnapshot {
+    /// Snapshot the files in `cwd` (tracked plus untracked, minus ignored) without touching
+    /// the worktree or index.
+    ///
+    /// Returns `Ok(None)` outside a git work tree.
+    pub fn capture(cwd: &Path, message: &str) -> std::result::Result<Option<Self>, String> {
+        if run_git(cwd, &["rev-parse", "--is-inside-work-tree"]).is_err() {
+            return Ok(None);
+        }
+        let root = PathBuf::from(run_git(cwd, &["rev-parse", "--show-toplevel"])?);
+        let head = run_git(&root, &["rev-parse", "--verify", "--quiet", "HEAD"]).ok();
+
+        let scratch =
+            tempfile::tempdir().map_err(|err| format!("failed to create index: {err}"))?;
+        let index = scratch.path().join("index");
+        let git = |args: &[&str]| run_git_with_index(&root, &index, args);
+        match &head {
+            Some(head) => git(&["read-tree", head])?,
+            None => git(&["read-tree", "--empty"])?,
+        };
+        git(&["add", "--all", "--", "."])?;
+        let tree = git(&["write-tree"])?;
+
+        let head_tree = head
+            .as_ref()
+            .map(|head| run_git(&root, &["rev-parse", &format!("{head}^{{tree}}")]))
+            .transpose()?;
+        let stash = if head_tree.as_deref() == Some(tree.as_str()) {
+            None
+        } else {
+            let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+            if let Some(head) = &head {
+                args.extend(["-p", head.as_str()]);
+            }
+            Some(run_git(&root, &args)?)
+        };
+
+        let snapshot = Self { head, stash };
+        if let Some(commit) = snapshot.commit() {
+            let name = format!("{SNAPSHOT_REF_PREFIX}{commit}");
+            run_git(&root, &["update-ref", &name, commit])?;
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// Make the worktree match this snapshot, snapshotting the current files first.
+    ///
+    /// Files the snapshot does not contain are removed (ignored files are left alone), and the
+    /// index is reset to `HEAD` so nothing is left staged.
+    pub fn restore(&self, cwd: &Path, name: &str) -> std::result::Result<(), String> {
+        let Some(commit) = self.commit() else {
+            return Ok(());
+        };
+        Self::capture(cwd, &format!("pi: before restoring {name}"))?;
+        let root = PathBuf::from(run_git(cwd, &["rev-parse", "--show-toplevel"])?);
+
+        let snapshot_files = run_git(&root, &["ls-tree", "-r", "-z", "--name-only", commit])?;
+        let snapshot_files: HashSet<&str> = split_nul(&snapshot_files).collect();
+        let current_files = run_git(
+            &root,
+            &[
+                "ls-files",
+                "-z",
+                "--cached",
+                "--others",
+                "--exclude-standard",
+            ],
+        )?;
+        for file in split_nul(&current_files).filter(|file| !snapshot_files.contains(file)) {
+            let path = root.join(file);
+            if path.symlink_metadata().is_ok_and(|meta| !meta.is_dir()) {
+                std::fs::remove_file(&path)
+                    .map_err(|err| format!("failed to remove {file}: {err}"))?;
+            }
+        }
+
+        run_git(&root, &["read-tree", commit])?;
+        run_git(&root, &["checkout-index", "--all", "--force"])?;
+        if run_git(&root, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok() {
+            run_git(&root, &["reset", "--quiet"])?;
+        } else {
+            run_git(&root, &["read-tree", "--empty"])?;
+        }
+        Ok(())
+    }
+
+    /// The commit whose tree [`Self::restore`] checks out.
+    fn commit(&self) -> Option<&str> {
+        self.stash.as_deref().or(self.head.as_deref())
+    }
+
+    pub fn to_value(&self) -> Value {
+        json!({ "head": self.head, "stash": self.stash })
+    }
+
+    pub fn from_value(value: &Value) -> Self {
+        let field = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+        };
+        Self {
+            head: field("head"),
+            stash: field("stash"),
+        }
+    }
+}
+
+fn split_nul(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\0').filter(|part| !part.is_empty())
+}
+
+/// Run git in `cwd`, returning trimmed stdout or stderr on failure.
+fn run_git(cwd: &Path, args: &[&str]) -> std::result::Result<String, String> {
+    git_output(
+        std::process::Command::new("git")
+            .args(args)
+            .current_dir(cwd),
+    )
+}
+
+/// Like [`run_git`], against the index file at `index` instead of the repository's own.
+fn run_git_with_index(
+    cwd: &Path,
+    index: &Path,
+    args: &[&str],
+) -> std::result::Result<String, String> {
+    git_output(
+        std::process::Command::new("git")
+            .args(args)
+            .current_dir(cwd)
+            .env("GIT_INDEX_FILE", index),
+    )
+}
+
+fn git_output(command: &mut std::process::Command) -> std::result::Result<String, String> {
+    let output = command
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|err| format!("failed to run git: {err}"))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worktree_snapshot_restores_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cwd = dir.path();
+        let git = |args: &[&str]| run_git(cwd, args);
+        if git(&["init", "-q"]).is_err() {
+            return; // git unavailable
+        }
+        git(&["config", "user.email", "pi@example.com"]).unwrap();
+        git(&["config", "user.name", "pi"]).unwrap();
+        std::fs::write(cwd.join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]).unwrap();
+        git(&["commit", "-q", "-m", "init"]).unwrap();
+
+        std::fs::write(cwd.join("a.txt"), "two\n").unwrap();
+        std::fs::write(cwd.join("notes.txt"), "untracked\n").unwrap();
+        let snapshot = WorktreeSnapshot::capture(cwd, "pi checkpoint: test")
+            .unwrap()
+            .expect("inside a work tree");
+        assert!(snapshot.head.is_some() && snapshot.stash.is_some());
+        assert_eq!(
+            std::fs::read_to_string(cwd.join("a.txt")).unwrap(),
+            "two\n",
+            "capture must not touch the worktree"
+        );
+        assert_eq!(git(&["diff", "--cached", "--name-only"]).unwrap(), "");
+        assert_eq!(git(&["stash", "list"]).unwrap(), "");
+        let refs = git(&[
+            "for-each-ref",
+            "--format=%(objectname)",
+            "refs/pi/snapshots",
+        ])
+        .unwrap();
+        assert!(refs.contains(snapshot.stash.as_deref().unwrap()), "{refs}");
+        assert_eq!(WorktreeSnapshot::from_value(&snapshot.to_value()), snapshot);
+
+        std::fs::write(cwd.join("a.txt"), "three\n").unwrap();
+        std::fs::remove_file(cwd.join("notes.txt")).unwrap();
+        std::fs::write(cwd.join("later.txt"), "created after\n").unwrap();
+        git(&["add", "later.txt"]).unwrap();
+        snapshot.restore(cwd, "test").unwrap();
+        assert_eq!(std::fs::read_to_string(cwd.join("a.txt")).unwrap(), "two\n");
+        assert_eq!(
+            std::fs::read_to_string(cwd.join("notes.txt")).unwrap(),
+            "untracked\n"
+        );
+        assert!(!cwd.join("later.txt").exists());
+        assert_eq!(git(&["diff", "--cached", "--name-only"]).unwrap(), "");
+        // The pre-restore state is kept as another snapshot.
+        let refs = git(&["for-each-ref", "refs/pi/snapshots"]).unwrap();
+        assert_eq!(refs.lines().count(), 2, "{refs}");
+
+        let outside = tempfile::tempdir().expect("tempdir");
+        assert!(
+            WorktreeSnapshot::capture(outside.path(), "x")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn clean_worktree_snapshot_pins_head() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cwd = dir.path();
+        let git = |args: &[&str]| run_git(cwd, args);
+        if git(&["init", "-q"]).is_err() {
+            return; // git unavailable
+        }
+        git(&["config", "user.email", "pi@example.com"]).unwrap();
+        git(&["config", "user.name", "pi"]).unwrap();
+        std::fs::write(cwd.join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]).unwrap();
+        git(&["commit", "-q", "-m", "init"]).unwrap();
+
+        let snapshot = WorktreeSnapshot::capture(cwd, "pi checkpoint: clean")
+            .unwrap()
+            .expect("inside a work tree");
+        assert!(snapshot.stash.is_none());
+        let head = snapshot.head.clone().unwrap();
+        let pinned = git(&["rev-parse", &format!("{SNAPSHOT_REF_PREFIX}{head}")]).unwrap();
+        assert_eq!(pinned, head);
+    }
+}
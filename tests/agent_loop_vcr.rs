@@ -11,6 +11,7 @@ use pi::session::Session;
 use pi::tools::ToolRegistry;
 use pi::vcr::{VcrMode, VcrRecorder};
 use serde_json::json;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write as _;
 use std::path::PathBuf;
@@ -111,6 +112,13 @@ fn agent_loop_openai_vcr_basic() {
                 api_key: Some("test-key".to_string()),
                 ..Default::default()
             },
+            tool_retry_diagnostics: None,
+            request_injection: None,
+            tool_output_limits: HashMap::new(),
+            tool_timeouts: HashMap::new(),
+            pricing: None,
+            dry_run: false,
+            loop_detection: None,
         };
         let agent = Agent::new(Arc::new(provider), tools, agent_config);
 
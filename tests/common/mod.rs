@@ -4,11 +4,13 @@
 //! - Verbose logging infrastructure with auto-dump on test failure
 //! - Test harness for consistent setup/teardown
 //! - Timing utilities for performance analysis
+//! - JSON normalization for comparing logs and transcripts across runs
 
 use std::future::Future;
 
 pub mod harness;
 pub mod logging;
+pub mod normalize;
 
 #[allow(unused_imports)]
 pub use harness::TestHarness;
@@ -4,7 +4,10 @@
 mod common;
 
 use common::{TestHarness, run_async};
-use pi::compaction::{CompactionPreparation, CompactionResult, compact, prepare_compaction};
+use pi::compaction::{
+    CompactionPreparation, CompactionResult, clean_session_title, compact, generate_session_title,
+    prepare_compaction, should_compact,
+};
 use pi::model::{
     AssistantMessage, ContentBlock, ImageContent, Message, StopReason, TextContent,
     ThinkingContent, ToolCall, Usage, UserContent, UserMessage,
@@ -81,6 +84,7 @@ impl Provider for ScriptedProvider {
             model: "scripted-model".to_string(),
             usage: Usage::default(),
             stop_reason: StopReason::Stop,
+            abort_reason: None,
             error_message: None,
             timestamp: 0,
         };
@@ -123,6 +127,7 @@ fn message_entry(id: &str, parent_id: Option<&str>, message: SessionMessage) ->
     SessionEntry::Message(MessageEntry {
         base: base(Some(id), parent_id),
         message,
+        redaction: None,
     })
 }
 
@@ -193,6 +198,7 @@ fn assistant_message(
             model: "test-model".to_string(),
             usage,
             stop_reason,
+            abort_reason: None,
             error_message: None,
             timestamp: 0,
         },
@@ -232,6 +238,7 @@ fn assistant_with_thinking_and_text(thinking: &str, text: &str) -> SessionMessag
             ContentBlock::Thinking(ThinkingContent {
                 thinking: thinking.to_string(),
                 thinking_signature: None,
+                redacted: false,
             }),
             ContentBlock::Text(TextContent::new(text)),
         ],
@@ -312,6 +319,7 @@ fn describe_entry(entry: &SessionEntry) -> String {
                         ContentBlock::Thinking(_) => "thinking",
                         ContentBlock::Image(_) => "image",
                         ContentBlock::ToolCall(_) => "tool_call",
+                        ContentBlock::HostedTool(_) => "hosted_tool",
                     })
                     .collect::<Vec<_>>()
                     .join(",");
@@ -478,6 +486,7 @@ fn prepare_compaction_returns_none_when_first_kept_entry_missing_id() {
     let entries = vec![SessionEntry::Message(MessageEntry {
         base: base(None, None),
         message: user_text("hello"),
+        redaction: None,
     })];
 
     dump_timeline(&harness, "setup", &entries);
@@ -641,6 +650,33 @@ fn compact_non_split_turn_calls_provider_once() {
     assert_eq!(provider.prompts().len(), 1);
 }
 
+#[test]
+fn session_title_is_generated_from_the_conversation_and_cleaned() {
+    let provider = Arc::new(ScriptedProvider::new([
+        "Title: \"Debug flaky CI parser tests in the lexer crate.\"\n\nThis names the task.",
+    ]));
+    let provider_dyn: Arc<dyn Provider> = provider.clone();
+    let messages = vec![
+        user_text("CI fails in the parser tests"),
+        assistant_text("The lexer drops a token.", 0),
+    ];
+
+    let title =
+        run_async(async move { generate_session_title(&messages, provider_dyn, "test-key").await })
+            .expect("title");
+
+    assert_eq!(title, "Debug flaky CI parser tests in");
+    let prompts = provider.prompts();
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].contains("CI fails in the parser tests"));
+
+    assert_eq!(
+        clean_session_title("  \n**Fix login bug**."),
+        Some("Fix login bug".to_string())
+    );
+    assert_eq!(clean_session_title("\"\""), None);
+}
+
 #[test]
 fn compact_split_turn_calls_provider_twice_and_formats_sections() {
     let harness = TestHarness::new("compact_split_turn_calls_provider_twice_and_formats_sections");
@@ -1337,6 +1373,7 @@ fn compact_returns_error_when_provider_stops_with_error() {
                 model: "error-provider".to_string(),
                 usage: Usage::default(),
                 stop_reason: StopReason::Error,
+                abort_reason: None,
                 error_message: Some("provider failed".to_string()),
                 timestamp: 0,
             };
@@ -1417,6 +1454,20 @@ fn prepare_compaction_considers_branch_summary_as_turn_start() {
     assert_eq!(prep.turn_prefix_messages.len(), 2);
 }
 
+#[test]
+fn should_compact_triggers_only_inside_reserve_headroom() {
+    let mut settings = make_settings(0);
+    settings.reserve_tokens = 1_000;
+
+    assert!(!should_compact(9_000, 10_000, &settings));
+    assert!(should_compact(9_001, 10_000, &settings));
+    // A reserve larger than the window compacts anything non-empty.
+    assert!(should_compact(1, 500, &settings));
+
+    settings.enabled = false;
+    assert!(!should_compact(9_999, 10_000, &settings));
+}
+
 fn model_message_text(message: &Message) -> String {
     match message {
         Message::User(user) => match &user.content {
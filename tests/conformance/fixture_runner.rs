@@ -269,7 +269,7 @@ fn command_value(command: Option<&Commands>) -> Value {
         Some(Commands::List) => json!({
             "name": "list",
         }),
-        Some(Commands::Config) => json!({
+        Some(Commands::Config { .. }) => json!({
             "name": "config",
         }),
         None => Value::Null,
@@ -845,8 +845,12 @@ fn e2e_cli_print_mode_with_stdin_does_not_create_session_files() {
 
     harness
         .harness
-        .assert_log("assert exit_code != 0 for missing API key in print mode");
-    assert_ne!(result.exit_code, 0);
+        .assert_log("assert exit_code is auth (3) or validation (6) for missing API key");
+    assert!(
+        matches!(result.exit_code, 3 | 6),
+        "unexpected exit code {}",
+        result.exit_code
+    );
     let stderr_lower = result.stderr.to_lowercase();
     assert!(
         stderr_lower.contains("no api key") || stderr_lower.contains("no models"),
@@ -860,6 +864,76 @@ fn e2e_cli_print_mode_with_stdin_does_not_create_session_files() {
     assert_eq!(jsonl_count, 0, "expected no session jsonl files");
 }
 
+#[test]
+fn e2e_cli_print_mode_exits_with_tool_failure_code() {
+    let harness = CliTestHarness::new("e2e_cli_print_mode_exits_with_tool_failure_code");
+    let server = harness.harness.start_mock_http_server();
+    // Every response asks for the same missing file, so `read` keeps failing.
+    let tool_call = json!({ "choices": [{
+        "index": 0,
+        "delta": { "tool_calls": [{
+            "index": 0,
+            "id": "call-1",
+            "type": "function",
+            "function": { "name": "read", "arguments": "{\"path\":\"missing.txt\"}" }
+        }] },
+        "finish_reason": null
+    }] });
+    let finish = json!({ "choices": [{ "index": 0, "delta": {}, "finish_reason": "tool_calls" }] });
+    let body = format!("data: {tool_call}\n\ndata: {finish}\n\ndata: [DONE]\n\n");
+    server.add_route(
+        "POST",
+        "/v1/chat/completions",
+        common::harness::MockHttpResponse {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "text/event-stream".to_string())],
+            body: body.into_bytes(),
+        },
+    );
+
+    let agent_dir = PathBuf::from(&harness.env["PI_CODING_AGENT_DIR"]);
+    fs::create_dir_all(&agent_dir).expect("create agent dir");
+    fs::write(
+        agent_dir.join("models.json"),
+        json!({
+            "providers": {
+                "openai": {
+                    "baseUrl": format!("{}/v1", server.base_url()),
+                    "api": "openai-completions",
+                    "apiKey": "test-key",
+                    "models": [{ "id": "gpt-test", "name": "Test" }]
+                }
+            }
+        })
+        .to_string(),
+    )
+    .expect("write models.json");
+    fs::write(
+        harness.global_settings_path(),
+        json!({
+            "tool_retry": { "enabled": true, "max_retries": 1 },
+            "loop_detection": 0
+        })
+        .to_string(),
+    )
+    .expect("write settings");
+
+    let result = harness.run(&[
+        "--provider",
+        "openai",
+        "--model",
+        "gpt-test",
+        "--no-session",
+        "-p",
+        "read missing.txt",
+    ]);
+
+    // One try plus one retry get diagnostics; the third failure ends the run.
+    assert_exit_code(&harness.harness, &result, 5);
+    assert_contains(&harness.harness, &result.stderr, "kept failing");
+    assert_eq!(server.requests().len(), 3);
+}
+
 #[test]
 fn e2e_cli_config_paths_honor_env_overrides() {
     let mut harness = CliTestHarness::new("e2e_cli_config_paths_honor_env_overrides");
@@ -967,6 +1041,26 @@ fn e2e_cli_list_subcommand_works_offline() {
     assert_contains_case_insensitive(&harness.harness, &result.stdout, "packages");
 }
 
+#[test]
+fn e2e_cli_errors_list_prints_registry_as_json() {
+    let harness = CliTestHarness::new("e2e_cli_errors_list_prints_registry_as_json");
+    let result = harness.run(&["errors", "list", "--json"]);
+
+    assert_exit_code(&harness.harness, &result, 0);
+    let entries: Vec<serde_json::Value> =
+        serde_json::from_str(&result.stdout).expect("errors list --json output");
+    assert_eq!(entries.len(), pi::error::ErrorCode::ALL.len());
+    assert!(
+        entries
+            .iter()
+            .any(|entry| entry["code"] == "PI_AUTH_001" && entry["summary"].is_string())
+    );
+
+    let result = harness.run(&["errors", "list"]);
+    assert_exit_code(&harness.harness, &result, 0);
+    assert_contains(&harness.harness, &result.stdout, "PI_CONFIG_001");
+}
+
 #[cfg(unix)]
 #[test]
 fn e2e_cli_packages_install_list_remove_offline() {
@@ -7,7 +7,7 @@
 
 mod common;
 
-use pi::error::{Error, Result};
+use pi::error::{Error, ErrorCode, ExitStatus, Result};
 use std::fs;
 use std::io::{self, ErrorKind};
 
@@ -491,3 +491,122 @@ fn test_error_with_special_characters() {
     assert!(display.contains('"'));
     assert!(display.contains('\''));
 }
+
+// =============================================================================
+// Stable error codes
+// =============================================================================
+
+#[test]
+fn test_error_code_registry_is_unique_and_well_formed() {
+    let mut seen = std::collections::HashSet::new();
+    for entry in ErrorCode::ALL {
+        assert!(
+            seen.insert(entry.code),
+            "duplicate error code {}",
+            entry.code
+        );
+        let parts = entry.code.split('_').collect::<Vec<_>>();
+        assert_eq!(parts.len(), 3, "malformed code {}", entry.code);
+        assert_eq!(parts[0], "PI");
+        assert!(parts[1].chars().all(|c| c.is_ascii_uppercase()));
+        assert!(parts[2].len() == 3 && parts[2].chars().all(|c| c.is_ascii_digit()));
+        assert!(!entry.summary.is_empty());
+        assert_eq!(ErrorCode::lookup(entry.code), Some(*entry));
+    }
+}
+
+#[test]
+fn test_every_error_maps_to_a_registered_code() {
+    let errors = vec![
+        Error::config("settings.json parse failed"),
+        Error::config("missing file"),
+        Error::config("bad value"),
+        Error::session("Empty session file"),
+        Error::session("failed to read session"),
+        Error::session("other"),
+        Error::SessionNotFound {
+            path: "/tmp/x.jsonl".to_string(),
+        },
+        Error::provider("anthropic", "401 unauthorized"),
+        Error::provider("anthropic", "403 forbidden"),
+        Error::provider("anthropic", "429 rate limit"),
+        Error::provider("anthropic", "529 overloaded"),
+        Error::provider("anthropic", "request timed out"),
+        Error::provider("anthropic", "400 bad request"),
+        Error::provider("anthropic", "500 internal server error"),
+        Error::provider("anthropic", "connection reset"),
+        Error::auth("missing authorization code"),
+        Error::auth("token exchange failed"),
+        Error::auth("no credentials"),
+        Error::tool("bash", "command not found"),
+        Error::tool("bash", "exit 1"),
+        Error::validation("bad"),
+        Error::extension("boom"),
+        Error::from(io::Error::new(ErrorKind::NotFound, "nope")),
+        Error::from(io::Error::new(ErrorKind::PermissionDenied, "nope")),
+        Error::from(io::Error::new(ErrorKind::TimedOut, "nope")),
+        Error::from(io::Error::new(ErrorKind::ConnectionRefused, "nope")),
+        Error::from(io::Error::other("nope")),
+        Error::from(serde_json::from_str::<serde_json::Value>("{").unwrap_err()),
+        Error::Aborted,
+        Error::budget_exceeded("Maximum tool iterations (50) exceeded"),
+        Error::api("boom"),
+    ];
+
+    let mut codes = std::collections::HashSet::new();
+    for err in &errors {
+        let hints = err.hints();
+        let entry = ErrorCode::lookup(hints.code)
+            .unwrap_or_else(|| panic!("unregistered code {} for {err}", hints.code));
+        assert_eq!(err.code(), hints.code);
+        assert_eq!(entry.summary, hints.summary);
+        codes.insert(hints.code);
+    }
+    // Every hint branch above has its own code.
+    assert_eq!(codes.len(), errors.len());
+    assert_eq!(Error::auth("no credentials").code(), "PI_AUTH_001");
+}
+
+#[test]
+fn test_exit_status_from_error_classifies_ci_outcomes() {
+    let cases = [
+        (Error::auth("no credentials"), ExitStatus::ProviderAuth),
+        (
+            Error::provider("anthropic", "401 unauthorized"),
+            ExitStatus::ProviderAuth,
+        ),
+        (
+            Error::provider("anthropic", "429 rate limit"),
+            ExitStatus::Failure,
+        ),
+        (
+            Error::budget_exceeded("Maximum tool iterations (50) exceeded"),
+            ExitStatus::BudgetExceeded,
+        ),
+        (Error::tool("bash", "exit 1"), ExitStatus::ToolFailure),
+        (Error::validation("bad"), ExitStatus::Validation),
+        (Error::config("bad settings"), ExitStatus::Validation),
+        (Error::Aborted, ExitStatus::Aborted),
+        (Error::api("boom"), ExitStatus::Failure),
+    ];
+    for (err, expected) in cases {
+        assert_eq!(ExitStatus::from_error(&err), expected, "{err}");
+    }
+}
+
+#[test]
+fn test_exit_status_codes_are_distinct_and_documented_in_help() {
+    use clap::CommandFactory;
+
+    let codes: std::collections::HashSet<i32> =
+        ExitStatus::ALL.iter().map(|status| status.code()).collect();
+    assert_eq!(codes.len(), ExitStatus::ALL.len());
+    assert_eq!(ExitStatus::Success.code(), 0);
+
+    let help = pi::cli::Cli::command().render_help().to_string();
+    assert!(help.contains("Exit codes:"), "help: {help}");
+    for status in ExitStatus::ALL {
+        let line = format!("  {:<5}{}", status.code(), status.description());
+        assert!(help.contains(&line), "missing `{line}` in help");
+    }
+}
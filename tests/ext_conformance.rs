@@ -10,7 +10,13 @@
 //! - Diffs are grouped by `event` and correlation IDs to speed triage.
 #![forbid(unsafe_code)]
 
-use regex::Regex;
+mod common;
+
+use common::normalize::{
+    NormalizationContext, PLACEHOLDER_ARTIFACT_ID, PLACEHOLDER_HOST, PLACEHOLDER_PI_MONO_ROOT,
+    PLACEHOLDER_RUN_ID, PLACEHOLDER_SESSION_ID, PLACEHOLDER_SPAN_ID, PLACEHOLDER_TIMESTAMP,
+    PLACEHOLDER_TRACE_ID, normalize_json, normalize_string,
+};
 use serde_json::{Value, json};
 use similar::ChangeTag;
 use std::collections::{BTreeMap, BTreeSet};
@@ -18,203 +24,11 @@ use std::fmt::Write as _;
 use std::io::Write as _;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::OnceLock;
 use tempfile::NamedTempFile;
 use tracing::trace;
 
-const PLACEHOLDER_TIMESTAMP: &str = "<TIMESTAMP>";
-const PLACEHOLDER_HOST: &str = "<HOST>";
-const PLACEHOLDER_SESSION_ID: &str = "<SESSION_ID>";
-const PLACEHOLDER_RUN_ID: &str = "<RUN_ID>";
-const PLACEHOLDER_ARTIFACT_ID: &str = "<ARTIFACT_ID>";
-const PLACEHOLDER_TRACE_ID: &str = "<TRACE_ID>";
-const PLACEHOLDER_SPAN_ID: &str = "<SPAN_ID>";
-const PLACEHOLDER_UUID: &str = "<UUID>";
-const PLACEHOLDER_PI_MONO_ROOT: &str = "<PI_MONO_ROOT>";
-const PLACEHOLDER_PROJECT_ROOT: &str = "<PROJECT_ROOT>";
-const PLACEHOLDER_PORT: &str = "<PORT>";
-
-static ANSI_REGEX: OnceLock<Regex> = OnceLock::new();
-static RUN_ID_REGEX: OnceLock<Regex> = OnceLock::new();
-static UUID_REGEX: OnceLock<Regex> = OnceLock::new();
-static OPENAI_BASE_REGEX: OnceLock<Regex> = OnceLock::new();
-
-fn ansi_regex() -> &'static Regex {
-    ANSI_REGEX.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").expect("ansi regex"))
-}
-
-#[derive(Debug, Clone)]
-struct NormalizationContext {
-    project_root: String,
-    pi_mono_root: String,
-    cwd: String,
-}
-
-impl NormalizationContext {
-    fn from_cwd(cwd: &Path) -> Self {
-        let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .canonicalize()
-            .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")))
-            .display()
-            .to_string();
-        let pi_mono_root = PathBuf::from(&project_root)
-            .join("legacy_pi_mono_code")
-            .join("pi-mono")
-            .canonicalize()
-            .unwrap_or_else(|_| {
-                PathBuf::from(&project_root)
-                    .join("legacy_pi_mono_code")
-                    .join("pi-mono")
-            })
-            .display()
-            .to_string();
-        let cwd = cwd
-            .canonicalize()
-            .unwrap_or_else(|_| cwd.to_path_buf())
-            .display()
-            .to_string();
-        Self {
-            project_root,
-            pi_mono_root,
-            cwd,
-        }
-    }
-}
-
-fn normalize_ext_log_line(mut value: Value, ctx: &NormalizationContext) -> Value {
-    normalize_value(&mut value, None, ctx);
-    canonicalize_json_keys(&value)
-}
-
-fn normalize_value(value: &mut Value, key: Option<&str>, ctx: &NormalizationContext) {
-    match value {
-        Value::Null | Value::Bool(_) => {}
-        Value::String(s) => {
-            if matches!(
-                key,
-                Some(
-                    "timestamp" | "started_at" | "finished_at" | "created_at" | "createdAt" | "ts"
-                )
-            ) {
-                *s = PLACEHOLDER_TIMESTAMP.to_string();
-                return;
-            }
-            if matches!(key, Some("cwd")) {
-                *s = PLACEHOLDER_PI_MONO_ROOT.to_string();
-                return;
-            }
-            if matches!(key, Some("host")) {
-                *s = PLACEHOLDER_HOST.to_string();
-                return;
-            }
-            if matches!(key, Some("session_id" | "sessionId")) {
-                *s = PLACEHOLDER_SESSION_ID.to_string();
-                return;
-            }
-            if matches!(key, Some("run_id" | "runId")) {
-                *s = PLACEHOLDER_RUN_ID.to_string();
-                return;
-            }
-            if matches!(key, Some("artifact_id" | "artifactId")) {
-                *s = PLACEHOLDER_ARTIFACT_ID.to_string();
-                return;
-            }
-            if matches!(key, Some("trace_id" | "traceId")) {
-                *s = PLACEHOLDER_TRACE_ID.to_string();
-                return;
-            }
-            if matches!(key, Some("span_id" | "spanId")) {
-                *s = PLACEHOLDER_SPAN_ID.to_string();
-                return;
-            }
-            *s = normalize_string(s, ctx);
-        }
-        Value::Array(items) => {
-            for item in items {
-                normalize_value(item, None, ctx);
-            }
-        }
-        Value::Object(map) => {
-            for (key, item) in map.iter_mut() {
-                normalize_value(item, Some(key.as_str()), ctx);
-            }
-        }
-        Value::Number(_) => {
-            if matches!(
-                key,
-                Some(
-                    "timestamp"
-                        | "started_at"
-                        | "finished_at"
-                        | "created_at"
-                        | "createdAt"
-                        | "ts"
-                        | "pid"
-                )
-            ) {
-                *value = Value::Number(0.into());
-            }
-        }
-    }
-}
-
-fn normalize_string(input: &str, ctx: &NormalizationContext) -> String {
-    let run_id_re =
-        RUN_ID_REGEX.get_or_init(|| Regex::new(r"\brun-[0-9a-fA-F-]{36}\b").expect("run id regex"));
-    let uuid_re = UUID_REGEX.get_or_init(|| {
-        Regex::new(
-            r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b",
-        )
-        .expect("uuid regex")
-    });
-    let openai_base_re = OPENAI_BASE_REGEX
-        .get_or_init(|| Regex::new(r"http://127\.0\.0\.1:\d+/v1").expect("openai base regex"));
-
-    // 1) Strip ANSI escape sequences (keeps plain text).
-    // Covers CSI sequences like: ESC[31m, ESC[0m, ESC[2K, etc.
-    let without_ansi = ansi_regex().replace_all(input, "");
-
-    // 2) Normalize absolute paths under cwd to "<cwd>/...".
-    let mut out = without_ansi.to_string();
-    out = replace_path_variants(&out, &ctx.pi_mono_root, PLACEHOLDER_PI_MONO_ROOT);
-    out = replace_path_variants(&out, &ctx.cwd, PLACEHOLDER_PI_MONO_ROOT);
-    out = replace_path_variants(&out, &ctx.project_root, PLACEHOLDER_PROJECT_ROOT);
-    out = run_id_re.replace_all(&out, PLACEHOLDER_RUN_ID).into_owned();
-    out = openai_base_re
-        .replace_all(&out, format!("http://127.0.0.1:{PLACEHOLDER_PORT}/v1"))
-        .into_owned();
-    out = uuid_re.replace_all(&out, PLACEHOLDER_UUID).into_owned();
-    out
-}
-
-fn replace_path_variants(input: &str, path: &str, placeholder: &str) -> String {
-    if path.is_empty() {
-        return input.to_string();
-    }
-    let mut out = input.replace(path, placeholder);
-    let path_backslashes = path.replace('/', "\\");
-    if path_backslashes != path {
-        out = out.replace(&path_backslashes, placeholder);
-    }
-    out
-}
-
-fn canonicalize_json_keys(value: &Value) -> Value {
-    match value {
-        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => value.clone(),
-        Value::Array(items) => Value::Array(items.iter().map(canonicalize_json_keys).collect()),
-        Value::Object(map) => {
-            let mut keys = map.keys().cloned().collect::<Vec<_>>();
-            keys.sort();
-            let mut out = serde_json::Map::new();
-            for key in keys {
-                if let Some(value) = map.get(&key) {
-                    out.insert(key, canonicalize_json_keys(value));
-                }
-            }
-            Value::Object(out)
-        }
-    }
+fn normalize_ext_log_line(value: Value, ctx: &NormalizationContext) -> Value {
+    normalize_json(value, ctx)
 }
 
 fn diff_key(value: &Value) -> String {
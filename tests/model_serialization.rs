@@ -55,6 +55,7 @@ fn make_assistant_message(content: Vec<ContentBlock>) -> AssistantMessage {
         model: "claude-3-5-sonnet-20241022".to_string(),
         usage: Usage::default(),
         stop_reason: StopReason::Stop,
+        abort_reason: None,
         error_message: None,
         timestamp: 1_700_000_000,
     }
@@ -134,6 +135,7 @@ fn test_assistant_message_round_trip() {
             ContentBlock::Thinking(ThinkingContent {
                 thinking: "Let me think...".to_string(),
                 thinking_signature: Some("sig123".to_string()),
+                redacted: false,
             }),
         ],
         api: "anthropic-messages".to_string(),
@@ -151,9 +153,12 @@ fn test_assistant_message_round_trip() {
                 cache_read: 0.0001,
                 cache_write: 0.0001,
                 total: 0.0032,
+                gateway: None,
+                gateway_budget_remaining: None,
             },
         },
         stop_reason: StopReason::Stop,
+        abort_reason: None,
         error_message: None,
         timestamp: 1_700_000_000,
     });
@@ -189,6 +194,7 @@ fn test_assistant_message_with_error() {
         model: "claude-3-5-sonnet-20241022".to_string(),
         usage: Usage::default(),
         stop_reason: StopReason::Error,
+        abort_reason: None,
         error_message: Some("Rate limit exceeded".to_string()),
         timestamp: 1_700_000_000,
     });
@@ -342,6 +348,7 @@ fn test_content_block_thinking() {
     let block = ContentBlock::Thinking(ThinkingContent {
         thinking: "Analyzing the problem...".to_string(),
         thinking_signature: Some("think_sig".to_string()),
+        redacted: false,
     });
 
     let json = serde_json::to_string(&block).unwrap();
@@ -655,6 +662,8 @@ fn test_usage_round_trip() {
             cache_read: 0.001,
             cache_write: 0.001,
             total: 0.032,
+            gateway: None,
+            gateway_budget_remaining: None,
         },
     };
 
@@ -775,6 +784,7 @@ fn test_empty_content_blocks() {
         model: "claude-3-5-sonnet-20241022".to_string(),
         usage: Usage::default(),
         stop_reason: StopReason::Stop,
+        abort_reason: None,
         error_message: None,
         timestamp: 1_700_000_000,
     });
@@ -913,6 +923,7 @@ fn test_mixed_content_sequence() {
             ContentBlock::Thinking(ThinkingContent {
                 thinking: "Let me analyze this...".to_string(),
                 thinking_signature: None,
+                redacted: false,
             }),
             ContentBlock::Text(TextContent::new("Based on my analysis:")),
             ContentBlock::ToolCall(ToolCall {
@@ -933,6 +944,7 @@ fn test_mixed_content_sequence() {
         model: "claude-3-5-sonnet-20241022".to_string(),
         usage: Usage::default(),
         stop_reason: StopReason::ToolUse,
+        abort_reason: None,
         error_message: None,
         timestamp: 1_700_000_000,
     });
@@ -231,7 +231,9 @@ fn create_provider_rejects_azure_without_deployment() {
     });
 
     match err {
-        Error::Provider { provider, message } => {
+        Error::Provider {
+            provider, message, ..
+        } => {
             assert_eq!(provider, "azure-openai");
             assert!(message.contains("resource+deployment"));
         }
@@ -251,7 +253,9 @@ fn create_provider_rejects_unknown_provider() {
     });
 
     match err {
-        Error::Provider { provider, message } => {
+        Error::Provider {
+            provider, message, ..
+        } => {
             assert_eq!(provider, "mystery");
             assert!(message.contains("not implemented"));
         }
@@ -344,6 +344,7 @@ pub(crate) fn assistant_tool_call_message(
         model: model.to_string(),
         usage: Usage::default(),
         stop_reason: StopReason::ToolUse,
+        abort_reason: None,
         error_message: None,
         timestamp: 0,
     })
@@ -167,6 +167,7 @@ fn tool_echo() -> ToolDef {
             },
             "required": ["text"]
         }),
+        hosted: None,
     }
 }
 
@@ -182,6 +183,7 @@ fn tool_add() -> ToolDef {
             },
             "required": ["a", "b"]
         }),
+        hosted: None,
     }
 }
 
@@ -197,6 +199,7 @@ fn tool_multiply() -> ToolDef {
             },
             "required": ["a", "b"]
         }),
+        hosted: None,
     }
 }
 
@@ -212,6 +215,7 @@ fn tool_store_blob() -> ToolDef {
             },
             "required": ["payload"]
         }),
+        hosted: None,
     }
 }
 
@@ -151,6 +151,7 @@ fn tool_echo() -> ToolDef {
             },
             "required": ["text"]
         }),
+        hosted: None,
     }
 }
 
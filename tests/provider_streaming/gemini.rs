@@ -147,6 +147,7 @@ fn tool_echo() -> ToolDef {
             },
             "required": ["text"]
         }),
+        hosted: None,
     }
 }
 
@@ -162,6 +163,7 @@ fn tool_add() -> ToolDef {
             },
             "required": ["a", "b"]
         }),
+        hosted: None,
     }
 }
 
@@ -177,6 +179,7 @@ fn tool_multiply() -> ToolDef {
             },
             "required": ["a", "b"]
         }),
+        hosted: None,
     }
 }
 
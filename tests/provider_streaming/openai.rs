@@ -149,6 +149,7 @@ fn tool_echo() -> ToolDef {
             },
             "required": ["text"]
         }),
+        hosted: None,
     }
 }
 
@@ -164,6 +165,7 @@ fn tool_add() -> ToolDef {
             },
             "required": ["a", "b"]
         }),
+        hosted: None,
     }
 }
 
@@ -179,6 +181,7 @@ fn tool_multiply() -> ToolDef {
             },
             "required": ["a", "b"]
         }),
+        hosted: None,
     }
 }
 
@@ -453,6 +453,7 @@ fn rpc_session_stats_counts_tool_calls_and_results() {
                     ..Usage::default()
                 },
                 stop_reason: StopReason::ToolUse,
+                abort_reason: None,
                 error_message: None,
                 timestamp: now,
             },
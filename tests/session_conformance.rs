@@ -7,7 +7,8 @@ use common::TestHarness;
 use pi::Error;
 use pi::model::{AssistantMessage, ContentBlock, StopReason, TextContent, Usage, UserContent};
 use pi::session::{
-    CustomEntry, EntryBase, Session, SessionEntry, SessionHeader, SessionMessage, encode_cwd,
+    CustomEntry, EntryBase, SESSION_MIGRATIONS, SESSION_VERSION, Session, SessionEntry,
+    SessionHeader, SessionMessage, SessionMigration, encode_cwd, migrate_session_values,
 };
 use pi::session_index::SessionIndex;
 use proptest::prelude::*;
@@ -42,6 +43,7 @@ fn make_assistant_message(text: &str) -> SessionMessage {
             model: "test".to_string(),
             usage: Usage::default(),
             stop_reason: StopReason::Stop,
+            abort_reason: None,
             error_message: None,
             timestamp: 0,
         },
@@ -116,6 +118,7 @@ fn proptest_session_header() -> impl Strategy<Value = SessionHeader> {
                 model_id,
                 thinking_level,
                 parent_session,
+                template: None,
             },
         )
 }
@@ -157,15 +160,231 @@ fn proptest_message_entries() -> impl Strategy<Value = Vec<SessionEntry>> {
                 entries.push(SessionEntry::Message(pi::session::MessageEntry {
                     base,
                     message,
+                    redaction: None,
                 }));
             }
             entries
         })
 }
 
+/// One mutation applied through the public `Session` API. The `u8` selects a target
+/// among the entries that exist at the time the op runs.
+#[derive(Debug, Clone)]
+enum TreeOp {
+    Message(u8),
+    Navigate(u8),
+    Compact(u8),
+    Label(u8),
+    BranchSummary(u8),
+}
+
+fn proptest_tree_ops() -> impl Strategy<Value = Vec<TreeOp>> {
+    let op = prop_oneof![
+        4 => any::<u8>().prop_map(TreeOp::Message),
+        2 => any::<u8>().prop_map(TreeOp::Navigate),
+        1 => any::<u8>().prop_map(TreeOp::Compact),
+        1 => any::<u8>().prop_map(TreeOp::Label),
+        1 => any::<u8>().prop_map(TreeOp::BranchSummary),
+    ];
+    proptest::collection::vec(op, 1..48)
+}
+
+fn pick<T: Clone>(items: &[T], selector: u8) -> Option<T> {
+    if items.is_empty() {
+        None
+    } else {
+        Some(items[usize::from(selector) % items.len()].clone())
+    }
+}
+
+fn entry_ids(session: &Session) -> Vec<String> {
+    session
+        .entries
+        .iter()
+        .filter_map(SessionEntry::base_id)
+        .cloned()
+        .collect()
+}
+
+/// Replay `ops` against a fresh session, returning it along with every
+/// `(compaction_id, first_kept_entry_id)` pair that was appended.
+fn apply_tree_ops(ops: &[TreeOp]) -> (Session, Vec<(String, String)>) {
+    let mut session = Session::create();
+    let mut compactions = Vec::new();
+    for (step, op) in ops.iter().enumerate() {
+        match *op {
+            TreeOp::Message(selector) => {
+                if selector % 2 == 0 {
+                    session.append_message(make_user_message(&format!("user-{step}")));
+                } else {
+                    session.append_message(make_assistant_message(&format!("assistant-{step}")));
+                }
+            }
+            TreeOp::Navigate(selector) => {
+                if let Some(target) = pick(&entry_ids(&session), selector) {
+                    assert!(session.navigate_to(&target));
+                }
+            }
+            TreeOp::Compact(selector) => {
+                let Some(leaf) = session.leaf_id.clone() else {
+                    continue;
+                };
+                let path = session.get_path_to_entry(&leaf);
+                if let Some(first_kept) = pick(&path, selector) {
+                    let id = session.append_compaction(
+                        format!("summary-{step}"),
+                        first_kept.clone(),
+                        u64::from(selector) * 100,
+                        None,
+                        None,
+                    );
+                    compactions.push((id, first_kept));
+                }
+            }
+            TreeOp::Label(selector) => {
+                if let Some(target) = pick(&entry_ids(&session), selector) {
+                    let label = (selector % 3 != 0).then(|| format!("label-{step}"));
+                    assert!(session.add_label(&target, label).is_some());
+                }
+            }
+            TreeOp::BranchSummary(selector) => {
+                if let Some(from_id) = pick(&entry_ids(&session), selector) {
+                    session.append_branch_summary(from_id, format!("branch-{step}"), None, None);
+                }
+            }
+        }
+    }
+    (session, compactions)
+}
+
+/// Entries that no other entry names as its parent, computed independently of
+/// `Session::list_leaves`.
+fn reachable_leaves(session: &Session) -> std::collections::BTreeSet<String> {
+    let parents = session
+        .entries
+        .iter()
+        .filter_map(|entry| entry.base().parent_id.clone())
+        .collect::<std::collections::HashSet<_>>();
+    entry_ids(session)
+        .into_iter()
+        .filter(|id| !parents.contains(id))
+        .collect()
+}
+
+fn assert_tree_invariants(session: &Session) -> Result<(), TestCaseError> {
+    let ids = entry_ids(session);
+    let id_set = ids
+        .iter()
+        .cloned()
+        .collect::<std::collections::HashSet<_>>();
+    prop_assert_eq!(ids.len(), session.entries.len(), "every entry has an id");
+    prop_assert_eq!(id_set.len(), ids.len(), "entry ids are unique");
+
+    // Every entry's parent exists.
+    for entry in &session.entries {
+        if let Some(parent_id) = entry.base().parent_id.as_deref() {
+            prop_assert!(
+                id_set.contains(parent_id),
+                "dangling parent {parent_id} on {:?}",
+                entry.base_id()
+            );
+        }
+    }
+
+    // Leaf paths are acyclic and run from a root down to the leaf.
+    for leaf in session.list_leaves() {
+        let path = session.get_path_to_entry(&leaf);
+        let unique = path.iter().collect::<std::collections::HashSet<_>>();
+        prop_assert_eq!(unique.len(), path.len(), "cycle on path to {}", leaf);
+        prop_assert_eq!(path.last(), Some(&leaf));
+        let root = session.get_entry(&path[0]).expect("root entry exists");
+        prop_assert!(root.base().parent_id.is_none());
+    }
+
+    // branch_summary agrees with the leaves reachable from the parent links.
+    let info = session.branch_summary();
+    let leaves = info
+        .leaves
+        .iter()
+        .cloned()
+        .collect::<std::collections::BTreeSet<_>>();
+    prop_assert_eq!(&leaves, &reachable_leaves(session));
+    prop_assert_eq!(info.leaf_count, leaves.len());
+    prop_assert_eq!(info.total_entries, session.entries.len());
+    prop_assert_eq!(info.current_leaf.as_ref(), session.leaf_id.as_ref());
+    Ok(())
+}
+
+/// A stand-in v3 -> v4 step: tags the header and every entry.
+fn tag_migrated(value: &mut serde_json::Value) {
+    value["migratedFromV3"] = json!(true);
+}
+
+const TEST_V4_MIGRATIONS: &[SessionMigration] = &[SessionMigration {
+    from: 3,
+    header: tag_migrated,
+    entry: tag_migrated,
+}];
+
 proptest! {
     #![proptest_config(ProptestConfig { cases: 64, .. ProptestConfig::default() })]
 
+    #[test]
+    fn proptest_v3_files_need_no_migration_today(
+        header in proptest_session_header(),
+        entries in proptest_message_entries(),
+    ) {
+        let mut header_value = serde_json::to_value(&header).expect("header to value");
+        let mut values = entries
+            .iter()
+            .map(|entry| serde_json::to_value(entry).expect("entry to value"))
+            .collect::<Vec<_>>();
+        let original = (header_value.clone(), values.clone());
+
+        let migrated_from = migrate_session_values(
+            &mut header_value,
+            &mut values,
+            SESSION_MIGRATIONS,
+            SESSION_VERSION,
+        )
+        .expect("migrate");
+        prop_assert_eq!(migrated_from, None);
+        prop_assert_eq!((header_value, values), original);
+    }
+
+    #[test]
+    fn proptest_v3_to_v4_migration_runs_once_and_keeps_entries_readable(
+        header in proptest_session_header(),
+        entries in proptest_message_entries(),
+    ) {
+        let mut header_value = serde_json::to_value(&header).expect("header to value");
+        let mut values = entries
+            .iter()
+            .map(|entry| serde_json::to_value(entry).expect("entry to value"))
+            .collect::<Vec<_>>();
+
+        let migrated_from =
+            migrate_session_values(&mut header_value, &mut values, TEST_V4_MIGRATIONS, 4)
+                .expect("migrate");
+        prop_assert_eq!(migrated_from, Some(3));
+        prop_assert_eq!(&header_value["version"], &json!(4));
+        prop_assert_eq!(&header_value["migratedFromV3"], &json!(true));
+
+        // Already at v4: nothing left to run.
+        let again = migrate_session_values(&mut header_value, &mut values, TEST_V4_MIGRATIONS, 4)
+            .expect("migrate again");
+        prop_assert_eq!(again, None);
+
+        for (value, entry) in values.into_iter().zip(&entries) {
+            prop_assert_eq!(&value["migratedFromV3"], &json!(true));
+            let decoded: SessionEntry = serde_json::from_value(value).expect("decode migrated");
+            prop_assert_eq!(
+                serde_json::to_value(&decoded).expect("to value"),
+                serde_json::to_value(entry).expect("to value"),
+            );
+        }
+    }
+
     #[test]
     fn proptest_session_header_json_roundtrip(header in proptest_session_header()) {
         let encoded = serde_json::to_string(&header).expect("serialize header");
@@ -217,6 +436,7 @@ proptest! {
             model_id: None,
             thinking_level: None,
             parent_session: None,
+            template: None,
         };
         session.entries = decoded_entries;
         session.leaf_id = leaf_id;
@@ -230,6 +450,87 @@ proptest! {
         let _ = session.entries_for_current_path();
         let _ = session.list_leaves();
     }
+
+    #[test]
+    fn proptest_session_tree_invariants_hold_under_random_ops(ops in proptest_tree_ops()) {
+        let (session, _) = apply_tree_ops(&ops);
+        assert_tree_invariants(&session)?;
+    }
+
+    #[test]
+    fn proptest_compaction_never_orphans_kept_entry(ops in proptest_tree_ops()) {
+        let (session, compactions) = apply_tree_ops(&ops);
+        for (compaction_id, first_kept) in &compactions {
+            prop_assert!(session.get_entry(first_kept).is_some(), "kept entry {} missing", first_kept);
+            // The kept entry must stay an ancestor of the compaction that references it.
+            let path = session.get_path_to_entry(compaction_id);
+            prop_assert!(
+                path.contains(first_kept),
+                "kept entry {} not on path to compaction {}",
+                first_kept,
+                compaction_id
+            );
+        }
+
+        // Whatever leaf we end on, context rebuilt from the current path still resolves.
+        let messages = session.to_messages_for_current_path();
+        let path_len = session
+            .leaf_id
+            .as_deref()
+            .map_or(0, |leaf| session.get_path_to_entry(leaf).len());
+        prop_assert!(messages.len() <= path_len + compactions.len());
+    }
+
+    #[test]
+    fn proptest_session_tree_invariants_survive_jsonl_roundtrip(ops in proptest_tree_ops()) {
+        let (session, _) = apply_tree_ops(&ops);
+        let mut jsonl = serde_json::to_string(&session.header).expect("serialize header");
+        for entry in &session.entries {
+            jsonl.push('\n');
+            jsonl.push_str(&serde_json::to_string(entry).expect("serialize entry"));
+        }
+        jsonl.push('\n');
+
+        let (reopened, diagnostics) = Session::from_jsonl_str(&jsonl).expect("reopen session");
+        prop_assert!(diagnostics.skipped_entries.is_empty());
+        prop_assert_eq!(reopened.entries.len(), session.entries.len());
+        assert_tree_invariants(&reopened)?;
+        prop_assert_eq!(reachable_leaves(&reopened), reachable_leaves(&session));
+    }
+
+    #[test]
+    fn proptest_get_path_to_entry_terminates_on_cyclic_parents(
+        parents in proptest::collection::vec(any::<u8>(), 1..24)
+    ) {
+        // Parents may point forward, backward, or at the entry itself, so corrupted
+        // files with cycles are covered alongside well-formed trees.
+        let ids = (0..parents.len()).map(|i| format!("{i:08x}")).collect::<Vec<_>>();
+        let mut session = Session::create();
+        session.entries = parents
+            .iter()
+            .enumerate()
+            .map(|(i, selector)| {
+                let choice = usize::from(*selector) % (ids.len() + 1);
+                SessionEntry::Message(pi::session::MessageEntry {
+                    base: EntryBase {
+                        id: Some(ids[i].clone()),
+                        parent_id: ids.get(choice).cloned(),
+                        timestamp: "2026-02-03T00:00:01.000Z".to_string(),
+                    },
+                    message: make_user_message(&format!("msg-{i}")),
+                    redaction: None,
+                })
+            })
+            .collect();
+
+        for id in &ids {
+            let path = session.get_path_to_entry(id);
+            let unique = path.iter().collect::<std::collections::HashSet<_>>();
+            prop_assert_eq!(unique.len(), path.len());
+            prop_assert!(path.len() <= ids.len());
+            prop_assert!(path.contains(id));
+        }
+    }
 }
 
 #[test]
@@ -320,6 +621,44 @@ fn open_empty_session_file_errors() {
     });
 }
 
+#[test]
+fn open_rejects_sessions_from_a_newer_format() {
+    run_async_test(async {
+        let harness = TestHarness::new("open_rejects_sessions_from_a_newer_format");
+        let mut header = serde_json::to_value(SessionHeader::new()).expect("header to value");
+        header["version"] = json!(u32::from(SESSION_VERSION) + 1);
+        let path = write_session_file(&harness, &format!("{header}\n"));
+
+        let err = open_session(&path).await.expect_err("expected error");
+        assert!(
+            err.to_string()
+                .contains(&format!("version {} is newer", SESSION_VERSION + 1)),
+            "unexpected error: {err}"
+        );
+    });
+}
+
+#[test]
+fn open_accepts_headers_from_before_the_version_field() {
+    run_async_test(async {
+        let harness = TestHarness::new("open_accepts_headers_from_before_the_version_field");
+        let path = write_session_file(
+            &harness,
+            concat!(
+                r#"{"type":"session","id":"sess-legacy","timestamp":"2025-01-01T00:00:00.000Z","cwd":"/tmp"}"#,
+                "\n",
+                r#"{"type":"message","timestamp":"2025-01-01T00:00:01.000Z","message":{"role":"user","content":"hi"}}"#,
+                "\n",
+            ),
+        );
+
+        let loaded = open_session(&path).await.expect("open legacy session");
+        assert_eq!(loaded.header.version, None);
+        assert_eq!(loaded.entries.len(), 1);
+        assert!(loaded.leaf_id.is_some());
+    });
+}
+
 #[test]
 fn open_header_only_session_succeeds_with_no_entries() {
     run_async_test(async {
@@ -429,6 +768,126 @@ fn plan_fork_from_non_user_message_errors() {
     assert!(matches!(err, Error::Session(_)), "unexpected error: {err}");
 }
 
+#[test]
+fn incognito_entries_stay_in_memory_and_are_never_saved() {
+    run_async_test(async {
+        let harness = TestHarness::new("incognito_entries_stay_in_memory_and_are_never_saved");
+        let base_dir = harness.temp_path("sessions");
+        let mut session = Session::create_with_dir(Some(base_dir));
+        let before = session.append_message(make_user_message("Public question"));
+
+        session.set_incognito(true);
+        session.append_message(make_user_message("my secret token is hunter2"));
+        session.append_message(make_assistant_message("Noted the secret"));
+        session.set_incognito(false);
+        let after = session.append_message(make_user_message("Back to public work"));
+
+        assert_eq!(session.ephemeral_entry_count(), 2);
+        // The agent still sees everything in memory.
+        assert_eq!(session.to_messages_for_current_path().len(), 4);
+
+        let reopened = save_and_reopen(&harness, &mut session).await;
+        let raw = pi::session::read_jsonl_session_file(session.path.as_ref().unwrap())
+            .expect("read saved session");
+        assert!(!raw.contains("hunter2"), "secret leaked to disk");
+
+        assert_eq!(reopened.entries.len(), 2);
+        let after_entry = reopened.get_entry(&after).expect("persisted entry");
+        assert_eq!(
+            after_entry.base().parent_id.as_deref(),
+            Some(before.as_str())
+        );
+        assert_eq!(reopened.leaf_id.as_deref(), Some(after.as_str()));
+    });
+}
+
+#[test]
+fn compaction_summarizing_incognito_entries_is_not_saved() {
+    run_async_test(async {
+        let harness = TestHarness::new("compaction_summarizing_incognito_entries_is_not_saved");
+        let base_dir = harness.temp_path("sessions");
+        let mut session = Session::create_with_dir(Some(base_dir));
+        session.append_message(make_user_message("Public question"));
+        session.set_incognito(true);
+        session.append_message(make_user_message("my secret token is hunter2"));
+        session.set_incognito(false);
+        let kept = session.append_message(make_user_message("Back to public work"));
+
+        // Compacted after incognito was turned off, over a range that includes the secret.
+        let compaction = session.append_compaction(
+            "The user shared the token hunter2".to_string(),
+            kept.clone(),
+            100,
+            None,
+            None,
+        );
+        assert!(session.is_ephemeral(&compaction));
+
+        let reopened = save_and_reopen(&harness, &mut session).await;
+        let raw = pi::session::read_jsonl_session_file(session.path.as_ref().unwrap())
+            .expect("read saved session");
+        assert!(!raw.contains("hunter2"), "summary leaked to disk");
+        assert!(reopened.get_entry(&compaction).is_none());
+        assert!(reopened.get_entry(&kept).is_some());
+    });
+}
+
+#[test]
+fn compaction_keeping_incognito_entries_survives_save_and_reopen() {
+    run_async_test(async {
+        let harness =
+            TestHarness::new("compaction_keeping_incognito_entries_survives_save_and_reopen");
+        let base_dir = harness.temp_path("sessions");
+        let mut session = Session::create_with_dir(Some(base_dir));
+        session.append_message(make_user_message("Public question"));
+        session.append_message(make_assistant_message("Public answer"));
+
+        session.set_incognito(true);
+        let secret = session.append_message(make_user_message("my secret token is hunter2"));
+        let secret_reply = session.append_message(make_assistant_message("Noted the secret"));
+        // Compacting while incognito keeps nothing on disk.
+        session.append_compaction(
+            "Incognito summary".to_string(),
+            secret.clone(),
+            10,
+            None,
+            None,
+        );
+        session.set_incognito(false);
+
+        // Compacting afterwards keeps a region that starts inside the incognito stretch.
+        let compaction = session.append_compaction(
+            "Summary of the public exchange".to_string(),
+            secret.clone(),
+            100,
+            None,
+            None,
+        );
+        session.add_label(&secret_reply, Some("secret".to_string()));
+        let after = session.append_message(make_user_message("Back to public work"));
+
+        let reopened = save_and_reopen(&harness, &mut session).await;
+        let raw = pi::session::read_jsonl_session_file(session.path.as_ref().unwrap())
+            .expect("read saved session");
+        assert!(!raw.contains("hunter2"), "secret leaked to disk");
+        assert!(!raw.contains("Incognito summary"));
+        assert!(reopened.find_label_target("secret").is_none());
+
+        let Some(SessionEntry::Compaction(saved)) = reopened.get_entry(&compaction) else {
+            panic!("compaction entry missing after reopen");
+        };
+        assert!(reopened.get_entry(&saved.first_kept_entry_id).is_some());
+
+        let messages = reopened.to_messages_for_current_path();
+        assert_eq!(messages.len(), 2, "summary plus the message after it");
+        let last = reopened
+            .messages_with_entry_ids_for_current_path()
+            .pop()
+            .and_then(|(id, _)| id);
+        assert_eq!(last.as_deref(), Some(after.as_str()));
+    });
+}
+
 #[test]
 fn save_updates_session_index_for_override_dir() {
     run_async_test(async {
@@ -887,6 +1346,7 @@ fn make_assistant_with_tool_use(
             model: "test".to_string(),
             usage: Usage::default(),
             stop_reason: StopReason::ToolUse,
+            abort_reason: None,
             error_message: None,
             timestamp: 0,
         },
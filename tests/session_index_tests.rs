@@ -36,6 +36,7 @@ fn make_assistant_message(text: &str) -> SessionMessage {
             model: "test".to_string(),
             usage: Usage::default(),
             stop_reason: StopReason::Stop,
+            abort_reason: None,
             error_message: None,
             timestamp: 0,
         },
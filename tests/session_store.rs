@@ -0,0 +1,106 @@
+//! WebDAV session store against a local mock server.
+
+mod common;
+
+use asupersync::runtime::RuntimeBuilder;
+use common::TestHarness;
+use common::harness::MockHttpResponse;
+use pi::Error;
+use pi::session_store::{SessionStore, WebDavSessionStore};
+
+#[test]
+fn webdav_store_puts_and_gets_session_bytes_with_basic_auth() {
+    let harness = TestHarness::new("webdav_store_puts_and_gets_session_bytes_with_basic_auth");
+    let server = harness.start_mock_http_server();
+    server.add_route("PUT", "/dav/s.jsonl", MockHttpResponse::text(201, ""));
+    server.add_route(
+        "GET",
+        "/dav/s.jsonl",
+        MockHttpResponse::text(200, "{\"type\":\"session\"}\n"),
+    );
+    // The mock server only speaks plain HTTP.
+    let store = WebDavSessionStore::new(
+        &format!("{}/dav/", server.base_url()),
+        Some(("me", "secret")),
+        true,
+    )
+    .expect("store");
+
+    let runtime = RuntimeBuilder::current_thread()
+        .build()
+        .expect("runtime build");
+    runtime.block_on(async {
+        store
+            .write("s.jsonl", b"{\"type\":\"session\"}\n".to_vec())
+            .await
+            .expect("upload");
+        let bytes = store.read("s.jsonl").await.expect("download");
+        assert_eq!(bytes, b"{\"type\":\"session\"}\n");
+
+        let err = store.read("missing.jsonl").await.expect_err("missing");
+        assert!(matches!(err, Error::SessionNotFound { .. }), "{err}");
+    });
+
+    let requests = server.requests();
+    let put = requests
+        .iter()
+        .find(|request| request.method == "PUT")
+        .expect("PUT request");
+    assert_eq!(put.path, "/dav/s.jsonl");
+    assert_eq!(put.body, b"{\"type\":\"session\"}\n");
+    assert!(put.headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("authorization") && value == "Basic bWU6c2VjcmV0"
+    }));
+}
+
+#[test]
+fn webdav_store_sends_if_match_and_reports_conflicts() {
+    let harness = TestHarness::new("webdav_store_sends_if_match_and_reports_conflicts");
+    let server = harness.start_mock_http_server();
+    let mut get = MockHttpResponse::text(200, "{}\n");
+    get.headers.push(("ETag".to_string(), "\"v1\"".to_string()));
+    server.add_route("GET", "/dav/s.jsonl", get);
+    let mut put = MockHttpResponse::text(204, "");
+    put.headers.push(("ETag".to_string(), "\"v2\"".to_string()));
+    server.add_route("PUT", "/dav/s.jsonl", put);
+    let store =
+        WebDavSessionStore::new(&format!("{}/dav/", server.base_url()), None, true).expect("store");
+
+    let runtime = RuntimeBuilder::current_thread()
+        .build()
+        .expect("runtime build");
+    runtime.block_on(async {
+        store.read("s.jsonl").await.expect("download");
+        store
+            .write("s.jsonl", b"{}\n".to_vec())
+            .await
+            .expect("upload");
+        server.add_route("PUT", "/dav/s.jsonl", MockHttpResponse::text(412, ""));
+        let err = store
+            .write("s.jsonl", b"{}\n".to_vec())
+            .await
+            .expect_err("conflict");
+        assert!(matches!(err, Error::SessionConflict { .. }), "{err}");
+        assert!(
+            err.to_string().contains("changed by another client"),
+            "{err}"
+        );
+    });
+
+    let if_match = server
+        .requests()
+        .iter()
+        .filter(|request| request.method == "PUT")
+        .map(|request| {
+            request
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("if-match"))
+                .map(|(_, value)| value.clone())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        if_match,
+        vec![Some("\"v1\"".to_string()), Some("\"v2\"".to_string())]
+    );
+}
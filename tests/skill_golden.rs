@@ -0,0 +1,312 @@
+//! Golden transcripts for skills and prompt templates.
+//!
+//! Every skill under `tests/fixtures/golden/skills` and every template under
+//! `tests/fixtures/golden/prompts` is run through the agent loop with a VCR-backed
+//! provider, and the resulting session is compared to a canonical transcript.
+//!
+//! - `VCR_MODE=record cargo test --test skill_golden` runs each case against the
+//!   live API (`OPENAI_API_KEY`) and rewrites its cassette and transcript.
+//! - Otherwise cassettes are replayed and the normalized session must match the
+//!   recorded transcript. A case without a cassette or transcript is skipped with a
+//!   warning, or fails under `VCR_MODE=playback`.
+//!
+//! Transcripts are normalized with the same helpers as the extension conformance
+//! harness, plus entry IDs rewritten to stable ordinals.
+
+mod common;
+
+use common::normalize::{NormalizationContext, normalize_json};
+use common::{TestHarness, run_async};
+use pi::agent::{Agent, AgentConfig, AgentSession};
+use pi::http::client::Client;
+use pi::provider::StreamOptions;
+use pi::providers::openai::OpenAIProvider;
+use pi::resources::{
+    LoadPromptTemplatesOptions, LoadSkillsOptions, expand_prompt_template, expand_skill_command,
+    load_prompt_templates, load_skills,
+};
+use pi::session::Session;
+use pi::tools::ToolRegistry;
+use pi::vcr::{VcrMode, VcrRecorder};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Relative to the package root (the working directory of `cargo test`) so skill
+/// locations embedded in prompts, and therefore cassette request bodies, are the
+/// same on every checkout.
+const GOLDEN_DIR: &str = "tests/fixtures/golden";
+const GOLDEN_MODEL: &str = "gpt-4o-mini";
+
+/// Keys holding session entry IDs; rewritten to `<ENTRY:n>` in order of first use.
+const ENTRY_ID_KEYS: &[&str] = &["id", "parentId", "firstKeptEntryId", "fromId", "targetId"];
+
+#[derive(Debug)]
+struct GoldenCase {
+    name: String,
+    input: String,
+}
+
+fn golden_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_DIR)
+}
+
+fn golden_cases() -> Vec<GoldenCase> {
+    let cwd = PathBuf::from(".");
+    let agent_dir = PathBuf::from(GOLDEN_DIR);
+
+    let skills = load_skills(LoadSkillsOptions {
+        cwd: cwd.clone(),
+        agent_dir: agent_dir.clone(),
+        skill_paths: vec![agent_dir.join("skills")],
+        include_defaults: false,
+    })
+    .skills;
+    let templates = load_prompt_templates(LoadPromptTemplatesOptions {
+        cwd,
+        agent_dir: agent_dir.clone(),
+        prompt_paths: vec![agent_dir.join("prompts")],
+        include_defaults: false,
+    });
+
+    let mut cases = Vec::new();
+    for skill in &skills {
+        let command = format!("/skill:{}", skill.name);
+        cases.push(GoldenCase {
+            name: format!("skill-{}", skill.name),
+            input: expand_skill_command(&command, &skills),
+        });
+    }
+    for template in &templates {
+        let command = format!("/{}", template.name);
+        cases.push(GoldenCase {
+            name: format!("prompt-{}", template.name),
+            input: expand_prompt_template(&command, &templates),
+        });
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    cases
+}
+
+/// Normalize a session JSONL file into one canonical JSON value per line.
+fn normalize_transcript(jsonl: &str, cwd: &Path) -> Result<String, String> {
+    let ctx = NormalizationContext::from_cwd(cwd);
+    let mut entry_ids: HashMap<String, String> = HashMap::new();
+    let mut out = String::new();
+    for (idx, line) in jsonl.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut value: Value = serde_json::from_str(line)
+            .map_err(|err| format!("line {}: JSON parse error: {err}", idx + 1))?;
+        relabel_entry_ids(&mut value, &mut entry_ids);
+        let normalized = normalize_json(value, &ctx);
+        let rendered = serde_json::to_string(&normalized).map_err(|err| err.to_string())?;
+        let _ = writeln!(out, "{rendered}");
+    }
+    Ok(out)
+}
+
+fn relabel_entry_ids(value: &mut Value, ids: &mut HashMap<String, String>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                relabel_entry_ids(item, ids);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if ENTRY_ID_KEYS.contains(&key.as_str()) {
+                    if let Value::String(id) = item {
+                        let next = ids.len();
+                        *id = ids
+                            .entry(id.clone())
+                            .or_insert_with(|| format!("<ENTRY:{next}>"))
+                            .clone();
+                        continue;
+                    }
+                }
+                relabel_entry_ids(item, ids);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn find_session_file(dir: &Path) -> Option<PathBuf> {
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_session_file(&path) {
+                return Some(found);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "jsonl") {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Run one case through the agent loop and return its raw session JSONL.
+fn run_case(harness: &TestHarness, case: &GoldenCase, mode: VcrMode) -> String {
+    let cassette_dir = golden_root().join("cassettes");
+    let session_dir = harness.temp_path(format!("{}-sessions", case.name));
+    std::fs::create_dir_all(&session_dir).expect("create session dir");
+    let cwd = harness.temp_dir().to_path_buf();
+    let cassette_name = case.name.clone();
+    let input = case.input.clone();
+
+    run_async(async move {
+        let recorder = VcrRecorder::new_with(&cassette_name, mode, &cassette_dir);
+        let client = Client::new().with_vcr(recorder);
+        let provider = OpenAIProvider::new(GOLDEN_MODEL).with_client(client);
+
+        let tools = ToolRegistry::new(&[], &cwd, None);
+        let agent_config = AgentConfig {
+            system_prompt: None,
+            max_tool_iterations: 2,
+            stream_options: StreamOptions {
+                // Recording falls back to OPENAI_API_KEY.
+                api_key: (mode == VcrMode::Playback).then(|| "test-key".to_string()),
+                ..Default::default()
+            },
+            tool_retry_diagnostics: None,
+            request_injection: None,
+            tool_output_limits: HashMap::new(),
+            tool_timeouts: HashMap::new(),
+            pricing: None,
+            dry_run: false,
+            loop_detection: None,
+        };
+        let agent = Agent::new(Arc::new(provider), tools, agent_config);
+        let session = Session::create_with_dir(Some(session_dir.clone()));
+        let mut agent_session = AgentSession::new(agent, session, true);
+
+        agent_session
+            .run_text(input, |_| {})
+            .await
+            .expect("agent loop run");
+        agent_session
+            .persist_session()
+            .await
+            .expect("persist session");
+
+        let path = find_session_file(&session_dir).expect("session file written");
+        std::fs::read_to_string(path).expect("read session file")
+    })
+}
+
+#[test]
+fn skill_and_template_transcripts_match_golden() {
+    let harness = TestHarness::new("skill_and_template_transcripts_match_golden");
+    let mode = VcrMode::from_env().expect("valid VCR_MODE");
+    let record = mode == Some(VcrMode::Record);
+    let strict = mode == Some(VcrMode::Playback);
+    let cases = golden_cases();
+    assert!(!cases.is_empty(), "no golden cases under {GOLDEN_DIR}");
+
+    let mut failures = String::new();
+    let mut unrecorded = Vec::new();
+    for case in &cases {
+        let cassette_path = golden_root()
+            .join("cassettes")
+            .join(format!("{}.json", case.name));
+        let transcript_path = golden_root()
+            .join("transcripts")
+            .join(format!("{}.jsonl", case.name));
+
+        if record {
+            if cassette_path.exists() {
+                std::fs::remove_file(&cassette_path).expect("replace cassette");
+            }
+            let raw = run_case(&harness, case, VcrMode::Record);
+            let normalized =
+                normalize_transcript(&raw, harness.temp_dir()).expect("normalize transcript");
+            std::fs::create_dir_all(transcript_path.parent().expect("transcripts dir"))
+                .expect("create transcripts dir");
+            std::fs::write(&transcript_path, normalized).expect("write transcript");
+            harness
+                .log()
+                .info("golden", format!("Recorded {}", case.name));
+            continue;
+        }
+
+        if !cassette_path.exists() || !transcript_path.exists() {
+            unrecorded.push(case.name.as_str());
+            continue;
+        }
+
+        let raw = run_case(&harness, case, VcrMode::Playback);
+        let actual = normalize_transcript(&raw, harness.temp_dir()).expect("normalize transcript");
+        let expected = std::fs::read_to_string(&transcript_path).expect("read transcript");
+        if actual != expected {
+            let diff = similar::TextDiff::from_lines(&expected, &actual)
+                .unified_diff()
+                .header("golden", "actual")
+                .to_string();
+            let _ = writeln!(failures, "\n=== {} ===\n{diff}", case.name);
+        }
+    }
+
+    if !unrecorded.is_empty() {
+        let message = format!(
+            "no recording for {}; record with VCR_MODE=record and OPENAI_API_KEY",
+            unrecorded.join(", ")
+        );
+        assert!(!strict, "{message}");
+        harness.log().warn("golden", message);
+    }
+    assert!(
+        failures.is_empty(),
+        "transcripts drifted from golden (re-record with VCR_MODE=record):{failures}"
+    );
+}
+
+#[test]
+fn golden_cases_cover_fixture_skills_and_templates() {
+    let names = golden_cases()
+        .into_iter()
+        .map(|case| case.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["prompt-explain-file", "skill-commit-message"]);
+
+    let skill = golden_cases()
+        .into_iter()
+        .find(|case| case.name == "skill-commit-message")
+        .expect("skill case");
+    assert!(
+        skill.input.contains(&format!(
+            "location=\"{GOLDEN_DIR}/skills/commit-message/SKILL.md\""
+        )),
+        "skill location should stay relative: {}",
+        skill.input
+    );
+}
+
+#[test]
+fn normalize_transcript_ignores_ids_timestamps_and_paths() {
+    let cwd = Path::new("/tmp/pi_skill_golden");
+    let first = r#"
+{"type":"session","version":3,"id":"0b7e6f7e-1c2d-4e5f-8a9b-0c1d2e3f4a5b","timestamp":"2026-02-03T03:01:02.123Z","cwd":"/tmp/pi_skill_golden"}
+{"type":"message","id":"a1b2c3d4","parentId":null,"timestamp":"2026-02-03T03:01:02.200Z","message":{"role":"user","content":"read /tmp/pi_skill_golden/notes.md","timestamp":1770087662200}}
+{"type":"message","id":"e5f6a7b8","parentId":"a1b2c3d4","timestamp":"2026-02-03T03:01:03.000Z","message":{"role":"assistant","content":[{"type":"text","text":"done"}],"timestamp":1770087663000}}
+"#;
+    let second = r#"
+{"type":"session","version":3,"id":"9f8e7d6c-5b4a-4392-8170-6f5e4d3c2b1a","timestamp":"2026-03-01T10:00:00.000Z","cwd":"/tmp/pi_skill_golden"}
+{"type":"message","id":"0f0f0f0f","parentId":null,"timestamp":"2026-03-01T10:00:00.100Z","message":{"role":"user","content":"read /tmp/pi_skill_golden/notes.md","timestamp":1772359200100}}
+{"type":"message","id":"1e1e1e1e","parentId":"0f0f0f0f","timestamp":"2026-03-01T10:00:01.000Z","message":{"role":"assistant","content":[{"type":"text","text":"done"}],"timestamp":1772359201000}}
+"#;
+
+    let a = normalize_transcript(first, cwd).expect("normalize first");
+    let b = normalize_transcript(second, cwd).expect("normalize second");
+    assert_eq!(a, b);
+    assert!(a.contains("\"parentId\":\"<ENTRY:1>\""), "{a}");
+    assert!(!a.contains("/tmp/pi_skill_golden"), "{a}");
+
+    let changed = second.replace("\"text\":\"done\"", "\"text\":\"not done\"");
+    let c = normalize_transcript(&changed, cwd).expect("normalize changed");
+    assert_ne!(a, c);
+}
@@ -196,6 +196,45 @@ mod read_tool {
         });
     }
 
+    #[test]
+    fn test_read_file_content_shaping_keeps_original_line_numbers() {
+        asupersync::test_utils::run_test(|| async {
+            let harness = TestHarness::new("read_file_content_shaping_keeps_original_line_numbers");
+            let path = harness.create_file(
+                "lib.rs",
+                b"// license header\n// more license\nfn main() {\n\n\n\n    run();   \n}\n",
+            );
+            let settings = pi::config::FileContentSettings {
+                strip_whitespace: Some(true),
+                languages: Some(std::collections::HashMap::from([(
+                    "rust".to_string(),
+                    pi::config::FileContentSettings {
+                        strip_comments: Some(true),
+                        ..Default::default()
+                    },
+                )])),
+                ..Default::default()
+            };
+            let tool =
+                pi::tools::ReadTool::new(harness.temp_dir()).with_file_content(Some(settings));
+            let input = serde_json::json!({
+                "path": path.to_string_lossy()
+            });
+
+            let result = tool
+                .execute("test-id", input, None)
+                .await
+                .expect("should succeed");
+
+            let text = get_text_content(&result.content);
+            assert!(
+                text.starts_with("    3→fn main() {\n    4→\n    7→    run();\n    8→}"),
+                "{text}"
+            );
+            assert!(text.contains("[Shaped for context: 2 comment, 2 blank lines omitted."));
+        });
+    }
+
     #[cfg(unix)]
     #[test]
     fn test_read_permission_denied_is_reported() {
@@ -553,6 +592,105 @@ mod bash_tool {
         });
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_bash_yield_after_returns_chunks_of_a_running_job() {
+        asupersync::test_utils::run_test(|| async {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let tool = pi::tools::BashTool::new(temp_dir.path());
+            let input = serde_json::json!({
+                "command": "echo first; sleep 2; echo second",
+                "yieldAfter": 1
+            });
+
+            let result = tool
+                .execute("test-id", input, None)
+                .await
+                .expect("should yield");
+            let text = get_text_content(&result.content);
+            assert!(text.contains("first"));
+            assert!(text.contains("Still running"));
+            let job = result
+                .details
+                .as_ref()
+                .and_then(|details| details.get("job"))
+                .and_then(serde_json::Value::as_str)
+                .expect("job id")
+                .to_string();
+            let details = result.details.as_ref().expect("details");
+            assert_eq!(details["exitCode"], serde_json::Value::Null);
+
+            let result = tool
+                .execute(
+                    "test-id",
+                    serde_json::json!({ "job": job, "yieldAfter": 10 }),
+                    None,
+                )
+                .await
+                .expect("job should finish");
+            let text = get_text_content(&result.content);
+            assert!(text.contains("second"));
+            assert!(!text.contains("first"));
+            assert!(!text.contains("Still running"));
+            assert_eq!(result.details.as_ref().expect("details")["exitCode"], 0);
+
+            let err = tool
+                .execute("test-id", serde_json::json!({ "job": job }), None)
+                .await
+                .expect_err("finished jobs are removed");
+            assert!(err.to_string().contains("No running bash job"));
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bash_job_notes_dropped_output_and_stops_on_shutdown() {
+        asupersync::test_utils::run_test(|| async {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let tool = pi::tools::BashTool::new(temp_dir.path());
+            let input = serde_json::json!({
+                "command": "sleep 1.5; head -c 150000 /dev/zero | tr '\\0' 'a'; echo; sleep 30",
+                "yieldAfter": 1,
+                "timeout": 0
+            });
+            let result = tool
+                .execute("test-id", input, None)
+                .await
+                .expect("should yield");
+            let job = result.details.as_ref().expect("details")["job"]
+                .as_str()
+                .expect("job id")
+                .to_string();
+
+            std::thread::sleep(std::time::Duration::from_secs(2));
+            let result = tool
+                .execute("test-id", serde_json::json!({ "job": job }), None)
+                .await
+                .expect("job still running");
+            let text = get_text_content(&result.content);
+            assert!(text.starts_with('['), "{text}");
+            assert!(text.contains("earlier bytes dropped]"), "{text}");
+
+            pi::tools::kill_bash_jobs();
+            let err = tool
+                .execute("test-id", serde_json::json!({ "job": job }), None)
+                .await
+                .expect_err("jobs are gone after shutdown");
+            assert!(err.to_string().contains("No running bash job"));
+        });
+    }
+
+    #[test]
+    fn test_bash_schema_requires_command_or_job() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tool = pi::tools::BashTool::new(temp_dir.path());
+        let schema = tool.parameters();
+        assert_eq!(
+            schema["oneOf"],
+            serde_json::json!([{ "required": ["command"] }, { "required": ["job"] }])
+        );
+    }
+
     #[test]
     fn test_bash_working_directory() {
         asupersync::test_utils::run_test(|| async {
@@ -510,6 +510,7 @@ fn tui_snapshot_footer_with_usage() {
             cache_read: 0.0,
             cache_write: 0.0,
             total: 0.003,
+            ..Cost::default()
         },
         ..Usage::default()
     };
@@ -6,19 +6,22 @@ use asupersync::channel::mpsc;
 use bubbletea::{Cmd, KeyMsg, KeyType, Message, Model as BubbleteaModel, QuitMsg};
 use common::TestHarness;
 use futures::stream;
-use pi::agent::{Agent, AgentConfig};
-use pi::config::{Config, TerminalSettings};
+use pi::agent::{Agent, AgentConfig, HunkReviewRequest, ToolApprovalRequest};
+use pi::config::{BudgetSettings, Config, TerminalSettings, UiSettings};
 use pi::extensions::{
     ExtensionManager, ExtensionUiRequest, JsExtensionLoadSpec, JsExtensionRuntimeHandle,
 };
 use pi::extensions_js::PiJsRuntimeConfig;
-use pi::interactive::{ConversationMessage, MessageRole, PendingInput, PiApp, PiMsg};
+use pi::interactive::{
+    BashPreview, ConversationMessage, ErrorPanel, MessageRole, PendingInput, PiApp, PiMsg,
+};
 use pi::keybindings::KeyBindings;
 use pi::model::{
     ContentBlock, Cost, ImageContent, StopReason, StreamEvent, TextContent, Usage, UserContent,
 };
 use pi::models::ModelEntry;
 use pi::provider::{Context, InputType, Model, ModelCost, Provider, StreamOptions};
+use pi::provider_health::{HealthStatus, ProviderHealth};
 use pi::resources::{ResourceCliOptions, ResourceLoader};
 use pi::session::Session;
 use pi::session::SessionMessage;
@@ -717,6 +720,15 @@ fn press_tab(harness: &TestHarness, app: &mut PiApp) -> StepOutcome {
     apply_key(harness, app, "key:Tab", KeyMsg::from_type(KeyType::Tab))
 }
 
+fn press_shift_tab(harness: &TestHarness, app: &mut PiApp) -> StepOutcome {
+    apply_key(
+        harness,
+        app,
+        "key:ShiftTab",
+        KeyMsg::from_type(KeyType::ShiftTab),
+    )
+}
+
 fn user_msg(text: &str) -> ConversationMessage {
     ConversationMessage {
         role: MessageRole::User,
@@ -947,6 +959,53 @@ fn tui_view_normalizes_newlines_to_crlf_after_multiline_and_resize() {
     assert_all_newlines_are_crlf(&view);
 }
 
+#[test]
+fn tui_view_rewraps_cached_messages_after_resize_and_reset() {
+    let harness = TestHarness::new("tui_view_rewraps_cached_messages_after_resize_and_reset");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let text = format!("alpha {} omega", "filler ".repeat(14).trim_end());
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ConversationReset",
+        PiMsg::ConversationReset {
+            messages: vec![assistant_msg(&text)],
+            usage: Usage::default(),
+            status: None,
+        },
+    );
+    let on_one_line = |app: &PiApp| {
+        strip_ansi(&BubbleteaModel::view(app))
+            .lines()
+            .any(|line| line.contains("alpha") && line.contains("omega"))
+    };
+
+    app.set_terminal_size(200, 40);
+    assert!(on_one_line(&app));
+    app.set_terminal_size(60, 40);
+    assert!(
+        !on_one_line(&app),
+        "narrow terminal should re-wrap the message"
+    );
+    app.set_terminal_size(200, 40);
+    assert!(on_one_line(&app));
+
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ConversationReset",
+        PiMsg::ConversationReset {
+            messages: vec![assistant_msg("replacement answer")],
+            usage: Usage::default(),
+            status: None,
+        },
+    );
+    assert_after_contains(&harness, &step, "replacement answer");
+    assert_after_not_contains(&harness, &step, "alpha");
+}
+
 #[test]
 fn tui_state_alt_enter_enables_multiline_mode() {
     let harness = TestHarness::new("tui_state_alt_enter_enables_multiline_mode");
@@ -1100,6 +1159,136 @@ fn tui_state_pagedown_restores_scroll_percent_when_scrollable() {
     assert_eq!(percent, 100, "Expected PgDn to return to bottom (100%)");
 }
 
+#[test]
+fn tui_state_streaming_keeps_scroll_anchor_and_counts_new_lines() {
+    let harness = TestHarness::new("tui_state_streaming_keeps_scroll_anchor_and_counts_new_lines");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let messages = (0..40)
+        .map(|idx| user_msg(&format!("line {idx}")))
+        .collect::<Vec<_>>();
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ConversationReset(many)",
+        PiMsg::ConversationReset {
+            messages,
+            usage: Usage::default(),
+            status: None,
+        },
+    );
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+
+    // At the bottom, streamed output is followed.
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::TextDelta(first)",
+        PiMsg::TextDelta("first streamed paragraph\n\n".to_string()),
+    );
+    assert_eq!(parse_scroll_percent(&step.after), Some(100));
+    assert_after_not_contains(&harness, &step, "new line");
+
+    // Scrolled up, the position is kept and new lines are counted.
+    let scrolled = press_pgup(&harness, &mut app);
+    let anchored_percent = parse_scroll_percent(&scrolled.after).expect("no percent");
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::TextDelta(more)",
+        PiMsg::TextDelta("second\n\nthird\n\nfourth".to_string()),
+    );
+    assert_after_contains(&harness, &step, "new lines ↓");
+    let percent = parse_scroll_percent(&step.after).expect("no percent");
+    assert!(
+        percent <= anchored_percent,
+        "Expected anchored scroll position ({percent} <= {anchored_percent})"
+    );
+
+    // Paging back to the bottom clears the indicator and resumes following.
+    for _ in 0..5 {
+        press_pgdown(&harness, &mut app);
+    }
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::TextDelta(tail)",
+        PiMsg::TextDelta("\n\nfifth".to_string()),
+    );
+    assert_eq!(parse_scroll_percent(&step.after), Some(100));
+    assert_after_not_contains(&harness, &step, "new line");
+}
+
+#[test]
+fn tui_state_agent_done_shows_gateway_reported_spend() {
+    let harness = TestHarness::new("tui_state_agent_done_shows_gateway_reported_spend");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    let mut usage = sample_usage(120, 45);
+    usage.cost.gateway = Some(0.0125);
+    usage.cost.gateway_budget_remaining = Some(5.5);
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::AgentDone(gateway)",
+        PiMsg::AgentDone {
+            usage: Some(usage),
+            stop_reason: StopReason::Stop,
+            error_message: None,
+        },
+    );
+    assert_after_contains(&harness, &step, "($0.0125 gateway)");
+
+    type_text(&harness, &mut app, "/session");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "gatewayCost: $0.0125");
+    assert_after_contains(&harness, &step, "gatewayBudgetRemaining: $5.5000");
+}
+
+#[test]
+fn tui_state_settings_keybindings_rebind_page_up() {
+    let harness = TestHarness::new("tui_state_settings_keybindings_rebind_page_up");
+    let config: Config = serde_json::from_value(json!({
+        "keybindings": { "pageUp": "ctrl+x" }
+    }))
+    .expect("config");
+    let mut app =
+        build_app_with_session_and_config(&harness, Vec::new(), Session::in_memory(), config);
+    log_initial_state(&harness, &app);
+
+    let messages = (0..40)
+        .map(|idx| user_msg(&format!("line {idx}")))
+        .collect::<Vec<_>>();
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ConversationReset(many)",
+        PiMsg::ConversationReset {
+            messages,
+            usage: Usage::default(),
+            status: None,
+        },
+    );
+
+    let step = press_pgup(&harness, &mut app);
+    assert_eq!(parse_scroll_percent(&step.after), Some(100));
+
+    let step = apply_key(
+        &harness,
+        &mut app,
+        "key:CtrlX",
+        KeyMsg::from_type(KeyType::CtrlX),
+    );
+    let percent = parse_scroll_percent(&step.after).expect("no percent");
+    assert!(
+        percent < 100,
+        "Expected ctrl+x to scroll up, got {percent}%"
+    );
+}
+
 #[test]
 fn tui_state_agent_start_enters_processing() {
     let harness = TestHarness::new("tui_state_agent_start_enters_processing");
@@ -1111,18 +1300,79 @@ fn tui_state_agent_start_enters_processing() {
 }
 
 #[test]
-fn tui_state_pending_message_queue_shows_steering_preview_while_busy() {
+fn tui_state_enter_while_busy_queues_typeahead_preview() {
+    let harness = TestHarness::new("tui_state_enter_while_busy_queues_typeahead_preview");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "queued typeahead");
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "queued: 1");
+    assert_after_contains(&harness, &step, "queued typeahead");
+    assert_after_contains(&harness, &step, "Queued message (1 queued)");
+}
+
+#[test]
+fn tui_state_typeahead_while_busy_runs_after_turn_and_keeps_draft() {
     let harness =
-        TestHarness::new("tui_state_pending_message_queue_shows_steering_preview_while_busy");
+        TestHarness::new("tui_state_typeahead_while_busy_runs_after_turn_and_keeps_draft");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    type_text(&harness, &mut app, "next question");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "queued: 1");
+    assert_after_contains(&harness, &step, "Processing...");
+
+    type_text(&harness, &mut app, "draft in progress");
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::AgentDone(stop)",
+        PiMsg::AgentDone {
+            usage: None,
+            stop_reason: StopReason::Stop,
+            error_message: None,
+        },
+    );
+    let step = apply_pi(&harness, &mut app, "PiMsg::RunPending", PiMsg::RunPending);
+    assert_after_contains(&harness, &step, "You: next question");
+    assert_after_contains(&harness, &step, "draft in progress");
+    assert_after_not_contains(&harness, &step, "queued: 1");
+}
+
+#[test]
+fn tui_state_ctrl_s_steering_prompt_queues_steering_while_busy() {
+    let harness = TestHarness::new("tui_state_ctrl_s_steering_prompt_queues_steering_while_busy");
     let mut app = build_app(&harness, Vec::new());
     log_initial_state(&harness, &app);
 
-    type_text(&harness, &mut app, "queued steering");
+    let step = apply_key(
+        &harness,
+        &mut app,
+        "key:Ctrl+S",
+        KeyMsg::from_type(KeyType::CtrlS),
+    );
+    assert_after_contains(&harness, &step, "Nothing to steer: the agent is idle");
+
     apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    let step = apply_key(
+        &harness,
+        &mut app,
+        "key:Ctrl+S",
+        KeyMsg::from_type(KeyType::CtrlS),
+    );
+    assert_after_contains(&harness, &step, "steer ›");
 
+    type_text(&harness, &mut app, "use rg instead");
     let step = press_enter(&harness, &mut app);
-    assert_after_contains(&harness, &step, "Pending:");
-    assert_after_contains(&harness, &step, "queued steering");
+    assert_after_contains(&harness, &step, "Steering sent");
+    assert_after_contains(&harness, &step, "1 steering");
+    assert_after_contains(&harness, &step, "use rg instead");
+    assert_after_not_contains(&harness, &step, "steer ›");
 }
 
 #[test]
@@ -1200,6 +1450,77 @@ fn tui_state_hide_thinking_block_hides_thinking_until_toggled() {
     assert_after_contains(&harness, &step, "hmm");
 }
 
+#[test]
+fn tui_state_thinking_full_mode_shows_every_line_and_ctrlt_cycles() {
+    let harness =
+        TestHarness::new("tui_state_thinking_full_mode_shows_every_line_and_ctrlt_cycles");
+    let config = Config {
+        ui: Some(UiSettings {
+            thinking: Some("full".to_string()),
+            ..UiSettings::default()
+        }),
+        ..Config::default()
+    };
+    let mut app =
+        build_app_with_session_and_config(&harness, Vec::new(), Session::in_memory(), config);
+    log_initial_state(&harness, &app);
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ThinkingDelta(multi-line)",
+        PiMsg::ThinkingDelta("first idea\nsecond idea".to_string()),
+    );
+    assert_after_contains(&harness, &step, "│ first idea");
+    assert_after_contains(&harness, &step, "│ second idea");
+
+    let step = press_ctrlt(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Thinking: off");
+    assert_after_not_contains(&harness, &step, "second idea");
+
+    let step = press_ctrlt(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Thinking: first idea");
+    assert_after_not_contains(&harness, &step, "second idea");
+
+    let session_handle = app.session_handle();
+    let session_guard = session_handle.try_lock().expect("session lock");
+    let saved = session_guard
+        .latest_custom_data("thinking_display")
+        .and_then(|data| data.get("mode"))
+        .and_then(serde_json::Value::as_str);
+    assert_eq!(saved, Some("summary"));
+}
+
+#[test]
+fn tui_state_agent_failure_renders_error_panel_with_hints() {
+    let harness = TestHarness::new("tui_state_agent_failure_renders_error_panel_with_hints");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    let err = pi::Error::provider("anthropic", "401 unauthorized");
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::AgentFailure",
+        PiMsg::AgentFailure(ErrorPanel::from_error(&err)),
+    );
+    assert_after_contains(
+        &harness,
+        &step,
+        "│ Error: Provider error: anthropic: 401 unauthorized",
+    );
+    assert_after_contains(
+        &harness,
+        &step,
+        "│ Provider authentication failed. (PI_PROVIDER_001)",
+    );
+    assert_after_contains(&harness, &step, "│ • Set `ANTHROPIC_API_KEY`");
+    assert_after_contains(&harness, &step, "│ provider: anthropic");
+    assert_after_not_contains(&harness, &step, "details:");
+}
+
 #[test]
 fn tui_state_tool_start_shows_running_tool_status() {
     let harness = TestHarness::new("tui_state_tool_start_shows_running_tool_status");
@@ -1698,6 +2019,29 @@ fn tui_state_slash_theme_lists_and_switches() {
     assert_after_contains(&harness, &step, "* light");
 }
 
+#[test]
+fn tui_state_slash_theme_preview_does_not_persist_and_reverts() {
+    let harness = TestHarness::new("tui_state_slash_theme_preview_does_not_persist_and_reverts");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/theme preview light");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Previewing theme: light");
+    assert!(
+        !harness.temp_path(".pi/settings.json").exists(),
+        "preview must not persist the theme"
+    );
+
+    type_text(&harness, &mut app, "/theme");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "* light");
+
+    type_text(&harness, &mut app, "/theme revert");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Theme: dark");
+}
+
 #[test]
 fn tui_state_slash_hotkeys_shows_dynamic_keybindings() {
     let harness = TestHarness::new("tui_state_slash_hotkeys_shows_dynamic_keybindings");
@@ -1722,46 +2066,309 @@ fn tui_state_slash_model_no_args_reports_current_model() {
 
     type_text(&harness, &mut app, "/model");
     let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Select a model");
+
+    let step = press_esc(&harness, &mut app);
+    assert_after_not_contains(&harness, &step, "Select a model");
     assert_after_contains(&harness, &step, "Current model: dummy/dummy-model");
 }
 
 #[test]
-fn tui_state_slash_scoped_models_set_persists_and_scopes_ctrlp() {
-    let harness = TestHarness::new("tui_state_slash_scoped_models_set_persists_and_scopes_ctrlp");
-
-    let anthropic = make_model_entry(
-        "anthropic",
-        "claude-a",
-        "https://api.anthropic.com/v1/messages",
-    );
-    let openai = make_model_entry("openai", "gpt-a", "https://api.openai.com/v1");
-    let google = make_model_entry(
-        "google",
-        "gemini-a",
-        "https://generativeai.googleapis.com/v1beta/models",
+fn tui_state_bang_command_streams_output_and_shows_result_without_agent_turn() {
+    let harness = TestHarness::new(
+        "tui_state_bang_command_streams_output_and_shows_result_without_agent_turn",
     );
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
 
-    let model_scope = Vec::new();
-    let available_models = vec![anthropic.clone(), openai, google];
+    type_text(&harness, &mut app, "!printf 'first\\nsecond\\n'");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Running:");
 
-    let mut app = build_app_with_models(
+    let step = apply_pi(
         &harness,
-        Session::in_memory(),
-        Config::default(),
-        anthropic,
-        model_scope,
-        available_models,
-        KeyBindings::new(),
+        &mut app,
+        "PiMsg::BashOutput",
+        PiMsg::BashOutput("first\nsecond\n".to_string()),
     );
+    assert_after_contains(&harness, &step, "first");
+    assert_after_contains(&harness, &step, "second");
 
-    type_text(&harness, &mut app, "/scoped-models openai/*");
-    let step = press_enter(&harness, &mut app);
-    assert_after_contains(&harness, &step, "Scoped models updated: 1 matched");
-
-    let settings = read_project_settings_json(&harness);
-    assert_eq!(
-        settings
-            .get("enabled_models")
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::BashResult",
+        PiMsg::BashResult {
+            display: "Ran `printf 'first\\nsecond\\n'`\nfirst\nsecond".to_string(),
+        },
+    );
+    assert_after_not_contains(&harness, &step, "Running:");
+    assert_after_contains(&harness, &step, "Ran `printf");
+    assert_after_not_contains(&harness, &step, "Assistant:");
+}
+
+fn bash_preview(command: &str, output: &str) -> PiMsg {
+    PiMsg::BashPreview(Box::new(BashPreview {
+        command: command.to_string(),
+        output: output.to_string(),
+        exit_code: 0,
+        cancelled: false,
+        truncated: false,
+        full_output_path: None,
+        elapsed: Duration::from_millis(1500),
+        scroll: 0,
+    }))
+}
+
+#[test]
+fn tui_state_bang_command_preview_sends_or_discards_output() {
+    let harness = TestHarness::new("tui_state_bang_command_preview_sends_or_discards_output");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "!seq 20");
+    press_enter(&harness, &mut app);
+    let output = (1..=20).map(|n| format!("line {n}\n")).collect::<String>();
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::BashPreview",
+        bash_preview("seq 20", &output),
+    );
+    assert_after_not_contains(&harness, &step, "Running:");
+    assert_after_contains(&harness, &step, "Ran `seq 20` in 1.5s (exit 0)");
+    assert_after_contains(&harness, &step, "8 lines above");
+    assert_after_contains(&harness, &step, "line 20");
+    assert_after_contains(&harness, &step, "Enter/s: send to model");
+
+    let step = press_esc(&harness, &mut app);
+    assert_after_not_contains(&harness, &step, "Enter/s: send to model");
+    assert_after_contains(&harness, &step, "Discarded; not sent to the model");
+
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::BashPreview",
+        bash_preview("echo ok", "ok\n"),
+    );
+    let step = type_text(&harness, &mut app, "s");
+    assert_after_not_contains(&harness, &step, "Enter/s: send to model");
+    assert_after_contains(&harness, &step, "Sent output of `echo ok` to the model");
+}
+
+#[test]
+fn tui_state_slash_system_shows_and_edits_system_prompt() {
+    let harness = TestHarness::new("tui_state_slash_system_shows_and_edits_system_prompt");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/system");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "No system prompt is set.");
+
+    type_text(&harness, &mut app, "/system edit");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Editing system prompt");
+    assert_after_contains(&harness, &step, "Alt+Enter: send");
+
+    type_text(&harness, &mut app, "Answer in one sentence.");
+    let step = press_alt_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "System prompt updated");
+    assert_after_not_contains(&harness, &step, "Alt+Enter: send");
+
+    type_text(&harness, &mut app, "/system");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "System prompt:");
+    assert_after_contains(&harness, &step, "Answer in one sentence.");
+
+    type_text(&harness, &mut app, "/system edit");
+    press_enter(&harness, &mut app);
+    let step = press_esc(&harness, &mut app);
+    assert_after_contains(&harness, &step, "System prompt unchanged");
+}
+
+#[test]
+fn tui_state_multiline_paste_switches_to_multiline_without_submitting() {
+    let harness =
+        TestHarness::new("tui_state_multiline_paste_switches_to_multiline_without_submitting");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let pasted = "fn main() {\r\n    println!(\"hi\");\r\n}\r\n";
+    let step = apply_key(
+        &harness,
+        &mut app,
+        "paste:code",
+        KeyMsg::from_runes(pasted.chars().collect()).with_paste(),
+    );
+    assert_after_contains(&harness, &step, "Pasted 3 lines");
+    assert_after_contains(&harness, &step, "Alt+Enter: send");
+    assert_after_contains(&harness, &step, "println!(\"hi\");");
+
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Alt+Enter: send");
+    assert_after_contains(&harness, &step, "println!(\"hi\");");
+}
+
+#[test]
+fn tui_state_dropped_file_path_offers_attachment() {
+    let harness = TestHarness::new("tui_state_dropped_file_path_offers_attachment");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+    let path = harness.temp_dir().join("notes.txt");
+    std::fs::write(&path, "hello\n").expect("write notes");
+
+    let dropped = format!("'{}'", path.display());
+    let step = apply_key(
+        &harness,
+        &mut app,
+        "paste:dropped-path",
+        KeyMsg::from_runes(dropped.chars().collect()).with_paste(),
+    );
+    assert_after_contains(&harness, &step, "Attach notes.txt (inline)?");
+    assert_after_not_contains(&harness, &step, "@notes.txt");
+
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Attached 1 file");
+    assert_after_contains(&harness, &step, "@notes.txt");
+}
+
+#[test]
+fn tui_state_dropped_file_offer_esc_keeps_raw_text() {
+    let harness = TestHarness::new("tui_state_dropped_file_offer_esc_keeps_raw_text");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+    std::fs::write(harness.temp_dir().join("notes.txt"), "hello\n").expect("write notes");
+
+    apply_key(
+        &harness,
+        &mut app,
+        "paste:dropped-path",
+        KeyMsg::from_runes("notes.txt".chars().collect()).with_paste(),
+    );
+    let step = press_esc(&harness, &mut app);
+    assert_after_contains(&harness, &step, "> notes.txt");
+    assert_after_not_contains(&harness, &step, "@notes.txt");
+}
+
+#[test]
+fn tui_state_submitting_bare_large_file_path_offers_reference() {
+    let harness = TestHarness::new("tui_state_submitting_bare_large_file_path_offers_reference");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+    std::fs::write(harness.temp_dir().join("big.log"), "x".repeat(300 * 1024))
+        .expect("write big log");
+
+    type_text(&harness, &mut app, "big.log");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Attach big.log (reference)?");
+
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "`big.log`");
+    assert_after_contains(&harness, &step, "referenced by path");
+}
+
+#[test]
+fn tui_state_shift_tab_cycles_thinking_level_and_records_it() {
+    let harness = TestHarness::new("tui_state_shift_tab_cycles_thinking_level_and_records_it");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let step = press_shift_tab(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Thinking level: minimal");
+    assert_after_contains(&harness, &step, "thinking: minimal");
+
+    for _ in 0..4 {
+        press_shift_tab(&harness, &mut app);
+    }
+    let step = press_shift_tab(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Thinking level: off");
+    assert_after_contains(&harness, &step, "[thinking: off]");
+
+    type_text(&harness, &mut app, "/thinking");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Thinking level: off");
+}
+
+#[test]
+fn tui_state_slash_model_picker_filters_and_switches_model() {
+    let harness = TestHarness::new("tui_state_slash_model_picker_filters_and_switches_model");
+
+    let anthropic = make_model_entry(
+        "anthropic",
+        "claude-a",
+        "https://api.anthropic.com/v1/messages",
+    );
+    let openai = make_model_entry("openai", "gpt-a", "https://api.openai.com/v1");
+    let google = make_model_entry(
+        "google",
+        "gemini-a",
+        "https://generativeai.googleapis.com/v1beta/models",
+    );
+    let available_models = vec![anthropic.clone(), openai.clone(), google];
+
+    let mut app = build_app_with_models(
+        &harness,
+        Session::in_memory(),
+        Config::default(),
+        anthropic,
+        vec![openai],
+        available_models,
+        KeyBindings::new(),
+    );
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/model");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Select a model");
+    assert_after_contains(&harness, &step, "gemini-a");
+
+    let step = type_text(&harness, &mut app, "gem");
+    assert_after_not_contains(&harness, &step, "gpt-a");
+    assert_after_contains(&harness, &step, "gemini-a");
+
+    let step = press_enter(&harness, &mut app);
+    assert_after_not_contains(&harness, &step, "Select a model");
+    assert_after_contains(&harness, &step, "Switched model: google/gemini-a");
+}
+
+#[test]
+fn tui_state_slash_scoped_models_set_persists_and_scopes_ctrlp() {
+    let harness = TestHarness::new("tui_state_slash_scoped_models_set_persists_and_scopes_ctrlp");
+
+    let anthropic = make_model_entry(
+        "anthropic",
+        "claude-a",
+        "https://api.anthropic.com/v1/messages",
+    );
+    let openai = make_model_entry("openai", "gpt-a", "https://api.openai.com/v1");
+    let google = make_model_entry(
+        "google",
+        "gemini-a",
+        "https://generativeai.googleapis.com/v1beta/models",
+    );
+
+    let model_scope = Vec::new();
+    let available_models = vec![anthropic.clone(), openai, google];
+
+    let mut app = build_app_with_models(
+        &harness,
+        Session::in_memory(),
+        Config::default(),
+        anthropic,
+        model_scope,
+        available_models,
+        KeyBindings::new(),
+    );
+
+    type_text(&harness, &mut app, "/scoped-models openai/*");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Scoped models updated: 1 matched");
+
+    let settings = read_project_settings_json(&harness);
+    assert_eq!(
+        settings
+            .get("enabled_models")
             .and_then(|value| value.as_array())
             .map(|array| { array.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>() }),
         Some(vec!["openai/*"])
@@ -2282,6 +2889,85 @@ fn tui_state_slash_resume_selects_latest_session_and_loads_messages() {
     assert_after_contains(&harness, &step, "Newer session message");
 }
 
+#[test]
+fn tui_state_welcome_dashboard_lists_recent_sessions_and_resume_by_number() {
+    let harness =
+        TestHarness::new("tui_state_welcome_dashboard_lists_recent_sessions_and_resume_by_number");
+    let base_dir = harness.temp_path("sessions");
+    let cwd = harness.temp_dir().to_path_buf();
+
+    create_session_on_disk(&base_dir, &cwd, "older", "Older session message");
+    thread::sleep(Duration::from_millis(10));
+    create_session_on_disk(&base_dir, &cwd, "newer", "Newer session message");
+
+    let mut session = Session::create_with_dir(Some(base_dir));
+    session.header.cwd = cwd.display().to_string();
+    let (mut app, event_rx) = build_app_with_session_and_events(&harness, Vec::new(), session);
+    log_initial_state(&harness, &app);
+
+    let view = normalize_view(&BubbleteaModel::view(&app));
+    assert!(view.contains("Model: dummy/dummy-model"), "view:\n{view}");
+    assert!(view.contains("1. newer"), "view:\n{view}");
+    assert!(view.contains("2. older"), "view:\n{view}");
+    assert!(view.contains("/resume [n]: resume"), "view:\n{view}");
+
+    type_text(&harness, &mut app, "/resume 2");
+    press_enter(&harness, &mut app);
+    let events = wait_for_pi_msgs(&event_rx, Duration::from_millis(500), |msgs| {
+        msgs.iter()
+            .any(|msg| matches!(msg, PiMsg::ConversationReset { .. }))
+    });
+    let reset = events
+        .into_iter()
+        .find(|msg| matches!(msg, PiMsg::ConversationReset { .. }))
+        .expect("expected ConversationReset after /resume 2");
+    let step = apply_pi(&harness, &mut app, "PiMsg::ConversationReset", reset);
+    assert_after_contains(&harness, &step, "Older session message");
+    assert_after_not_contains(&harness, &step, "Recent sessions:");
+}
+
+#[test]
+fn tui_state_slash_resume_number_out_of_range_sets_status() {
+    let harness = TestHarness::new("tui_state_slash_resume_number_out_of_range_sets_status");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/resume 3");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "No recent session #3");
+}
+
+#[test]
+fn tui_state_slash_sessions_marks_current_session_and_skips_reload() {
+    let harness =
+        TestHarness::new("tui_state_slash_sessions_marks_current_session_and_skips_reload");
+    let base_dir = harness.temp_path("sessions");
+    let cwd = harness.temp_dir().to_path_buf();
+
+    create_session_on_disk(&base_dir, &cwd, "older", "Older session message");
+    thread::sleep(Duration::from_millis(10));
+    let current_path = create_session_on_disk(&base_dir, &cwd, "newer", "Newer session message");
+
+    let mut session = Session::create_with_dir(Some(base_dir));
+    session.header.cwd = cwd.display().to_string();
+    session.path = Some(current_path);
+    let (mut app, event_rx) = build_app_with_session_and_events(&harness, Vec::new(), session);
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/sessions");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Select a session to resume");
+    assert_after_contains(&harness, &step, "(current)");
+
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Already in this session");
+    assert_after_not_contains(&harness, &step, "Loading session...");
+    assert!(
+        event_rx.try_recv().is_err(),
+        "selecting the open session should not reload it"
+    );
+}
+
 #[test]
 fn tui_state_slash_resume_can_be_cancelled_by_extension() {
     let harness = TestHarness::new("tui_state_slash_resume_can_be_cancelled_by_extension");
@@ -2636,33 +3322,294 @@ fn tui_state_slash_tree_select_root_user_message_prefills_editor_and_resets_leaf
 }
 
 #[test]
-fn tui_state_slash_fork_creates_session_and_prefills_editor() {
-    let harness = TestHarness::new("tui_state_slash_fork_creates_session_and_prefills_editor");
-    let base_dir = harness.temp_path("sessions");
-    let cwd = harness.temp_dir().to_path_buf();
-
-    let mut session = Session::create_with_dir(Some(base_dir.clone()));
-    session.header.cwd = cwd.display().to_string();
-    session.append_message(SessionMessage::User {
-        content: UserContent::Text("Root message".to_string()),
-        timestamp: Some(0),
-    });
-    session.append_message(SessionMessage::User {
-        content: UserContent::Text("Child message".to_string()),
-        timestamp: Some(0),
-    });
-
-    let (mut app, event_rx) = build_app_with_session_and_events(&harness, Vec::new(), session);
+fn tui_state_slash_timeline_inspects_entries_and_branches_from_one() {
+    let harness =
+        TestHarness::new("tui_state_slash_timeline_inspects_entries_and_branches_from_one");
+    let mut app = build_app(&harness, Vec::new());
     log_initial_state(&harness, &app);
 
-    type_text(&harness, &mut app, "/fork");
+    let root_id = {
+        let session = app.session_handle();
+        let mut session_guard = session.try_lock().expect("session try_lock");
+        let root_id = session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Root".to_string()),
+            timestamp: Some(0),
+        });
+        session_guard.append_model_change("openai".to_string(), "gpt-4o".to_string());
+        session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Child line one\nChild line two".to_string()),
+            timestamp: Some(0),
+        });
+        root_id
+    };
+
+    type_text(&harness, &mut app, "/timeline");
     let step = press_enter(&harness, &mut app);
-    assert_after_contains(&harness, &step, "Forking session...");
+    assert_after_contains(&harness, &step, "Session Timeline");
+    // Settings entries such as model changes are part of the timeline.
+    assert_after_contains(&harness, &step, "[model: openai/gpt-4o]");
 
-    let events = wait_for_pi_msgs(&event_rx, Duration::from_millis(700), |msgs| {
-        let has_reset = msgs
-            .iter()
-            .any(|msg| matches!(msg, PiMsg::ConversationReset { .. }));
+    let step = press_tab(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Child line two");
+
+    // The detail pane follows the selection.
+    press_up(&harness, &mut app);
+    let step = press_up(&harness, &mut app);
+    assert_after_contains(&harness, &step, &format!("{root_id} · "));
+    assert_after_not_contains(&harness, &step, "Child line two");
+
+    let step = type_text(&harness, &mut app, "b");
+    assert_after_contains(&harness, &step, &format!("Branched from {root_id}"));
+    assert_after_not_contains(&harness, &step, "Session Timeline");
+}
+
+#[test]
+fn tui_state_slash_compact_without_api_key_reports_status_and_stays_idle() {
+    let harness =
+        TestHarness::new("tui_state_slash_compact_without_api_key_reports_status_and_stays_idle");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/compact keep the API notes");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(
+        &harness,
+        &step,
+        "No API key configured; cannot run compaction",
+    );
+    assert_after_not_contains(&harness, &step, "Compacting session...");
+
+    // Automatic compaction belongs to the agent loop; a persisted turn never starts one here.
+    let step = apply_pi(&harness, &mut app, "PiMsg::TurnSaved", PiMsg::TurnSaved);
+    assert_after_not_contains(&harness, &step, "Compacting session...");
+}
+
+#[test]
+fn tui_state_slash_branch_branches_from_selected_entry_and_records_summary() {
+    let harness =
+        TestHarness::new("tui_state_slash_branch_branches_from_selected_entry_and_records_summary");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let root_id = {
+        let session = app.session_handle();
+        let mut session_guard = session.try_lock().expect("session try_lock");
+        let root_id = session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Root".to_string()),
+            timestamp: Some(0),
+        });
+        session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Abandoned idea".to_string()),
+            timestamp: Some(0),
+        });
+        root_id
+    };
+
+    type_text(&harness, &mut app, "/branch");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Branch From Entry");
+
+    // Move selection from the child to the root, then branch without a summary prompt.
+    press_up(&harness, &mut app);
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, &format!("Branched from {root_id}"));
+    assert_after_not_contains(&harness, &step, "Branch Summary");
+
+    let session = app.session_handle();
+    let session_guard = session.try_lock().expect("session try_lock");
+    let Some(pi::session::SessionEntry::BranchSummary(summary)) = session_guard.entries.last()
+    else {
+        panic!("expected a branch summary entry as the new leaf");
+    };
+    assert_eq!(summary.base.parent_id.as_deref(), Some(root_id.as_str()));
+    assert!(summary.summary.contains("Abandoned idea"));
+    assert_eq!(session_guard.branch_summary().leaf_count, 2);
+}
+
+#[test]
+fn tui_state_slash_branch_restores_thinking_level_recorded_on_branch() {
+    let harness =
+        TestHarness::new("tui_state_slash_branch_restores_thinking_level_recorded_on_branch");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let deep_id = {
+        let session = app.session_handle();
+        let mut session_guard = session.try_lock().expect("session try_lock");
+        let root_id = session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Root".to_string()),
+            timestamp: Some(0),
+        });
+        session_guard.append_thinking_level_change("high".to_string());
+        let deep_id = session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Deep idea".to_string()),
+            timestamp: Some(0),
+        });
+        session_guard.create_branch_from(&root_id);
+        session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Quick idea".to_string()),
+            timestamp: Some(0),
+        });
+        deep_id
+    };
+
+    type_text(&harness, &mut app, &format!("/branch {deep_id}"));
+    press_enter(&harness, &mut app);
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, &format!("Branched from {deep_id}"));
+    assert_after_contains(&harness, &step, "Branch settings: thinking high");
+
+    let session = app.session_handle();
+    let session_guard = session.try_lock().expect("session try_lock");
+    assert_eq!(session_guard.header.thinking_level.as_deref(), Some("high"));
+}
+
+#[test]
+fn tui_state_slash_label_labels_and_goto_round_trip_a_checkpoint() {
+    let harness = TestHarness::new("tui_state_slash_label_labels_and_goto_round_trip_a_checkpoint");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let root_id = {
+        let session = app.session_handle();
+        let mut session_guard = session.try_lock().expect("session try_lock");
+        session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Checkpoint prompt".to_string()),
+            timestamp: Some(0),
+        })
+    };
+
+    type_text(&harness, &mut app, "/label before-refactor");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(
+        &harness,
+        &step,
+        &format!("Labeled {root_id} as before-refactor"),
+    );
+
+    {
+        let session = app.session_handle();
+        let mut session_guard = session.try_lock().expect("session try_lock");
+        session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("Later work".to_string()),
+            timestamp: Some(0),
+        });
+    }
+
+    type_text(&harness, &mut app, "/labels");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "before-refactor");
+    assert_after_contains(&harness, &step, "Checkpoint prompt");
+
+    type_text(&harness, &mut app, "/goto missing");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "No checkpoint labeled missing");
+
+    type_text(&harness, &mut app, "/goto before-refactor");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, &format!("Switched to {root_id}"));
+
+    let session = app.session_handle();
+    let session_guard = session.try_lock().expect("session try_lock");
+    assert_eq!(session_guard.leaf_id.as_deref(), Some(root_id.as_str()));
+}
+
+#[test]
+fn tui_state_slash_incognito_toggles_persistence_and_header_badge() {
+    let harness =
+        TestHarness::new("tui_state_slash_incognito_toggles_persistence_and_header_badge");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/incognito on");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Incognito on");
+    assert_after_contains(&harness, &step, "incognito");
+
+    {
+        let session = app.session_handle();
+        let mut session_guard = session.try_lock().expect("session try_lock");
+        assert!(session_guard.is_incognito());
+        session_guard.append_message(SessionMessage::User {
+            content: UserContent::Text("secret".to_string()),
+            timestamp: Some(0),
+        });
+    }
+
+    type_text(&harness, &mut app, "/incognito off");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(
+        &harness,
+        &step,
+        "Incognito off: 1 earlier entries remain unsaved",
+    );
+
+    type_text(&harness, &mut app, "/incognito maybe");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Usage: /incognito [on|off]");
+}
+
+#[test]
+fn tui_state_slash_pin_and_pins_manage_pinned_files() {
+    let harness = TestHarness::new("tui_state_slash_pin_and_pins_manage_pinned_files");
+    std::fs::write(harness.temp_dir().join("notes.md"), "key facts").expect("write notes");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/pin missing.md");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Not a file: missing.md");
+
+    type_text(&harness, &mut app, "/pin notes.md");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Pinned notes.md (1 pinned)");
+
+    type_text(&harness, &mut app, "/pins");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(
+        &harness,
+        &step,
+        "Pinned files (use /pins remove <n> or /pins clear):",
+    );
+    assert_after_contains(&harness, &step, "1. notes.md (9 bytes)");
+
+    type_text(&harness, &mut app, "/pins remove 1");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Unpinned notes.md");
+
+    type_text(&harness, &mut app, "/pins");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "No pinned files");
+}
+
+#[test]
+fn tui_state_slash_fork_creates_session_and_prefills_editor() {
+    let harness = TestHarness::new("tui_state_slash_fork_creates_session_and_prefills_editor");
+    let base_dir = harness.temp_path("sessions");
+    let cwd = harness.temp_dir().to_path_buf();
+
+    let mut session = Session::create_with_dir(Some(base_dir.clone()));
+    session.header.cwd = cwd.display().to_string();
+    session.append_message(SessionMessage::User {
+        content: UserContent::Text("Root message".to_string()),
+        timestamp: Some(0),
+    });
+    session.append_message(SessionMessage::User {
+        content: UserContent::Text("Child message".to_string()),
+        timestamp: Some(0),
+    });
+
+    let (mut app, event_rx) = build_app_with_session_and_events(&harness, Vec::new(), session);
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/fork");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Forking session...");
+
+    let events = wait_for_pi_msgs(&event_rx, Duration::from_millis(700), |msgs| {
+        let has_reset = msgs
+            .iter()
+            .any(|msg| matches!(msg, PiMsg::ConversationReset { .. }));
         let has_editor = msgs
             .iter()
             .any(|msg| matches!(msg, PiMsg::SetEditorText(_)));
@@ -2795,7 +3742,579 @@ fn tui_state_status_message_clears_on_any_keypress() {
 
     type_text(&harness, &mut app, "/model");
     press_enter(&harness, &mut app);
+    press_esc(&harness, &mut app);
 
     let step = type_text(&harness, &mut app, "x");
     assert_after_not_contains(&harness, &step, "Current model: dummy/dummy-model");
 }
+
+#[test]
+fn tui_state_extension_status_segments_render_in_footer() {
+    let harness = TestHarness::new("tui_state_extension_status_segments_render_in_footer");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::StatusSegment",
+        PiMsg::StatusSegment {
+            id: "ci".to_string(),
+            text: "CI: passing".to_string(),
+        },
+    );
+    assert_after_contains(&harness, &step, "CI: passing");
+
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::StatusSegment",
+        PiMsg::StatusSegment {
+            id: "ci".to_string(),
+            text: "CI: failing".to_string(),
+        },
+    );
+    assert_after_contains(&harness, &step, "CI: failing");
+    assert_after_not_contains(&harness, &step, "CI: passing");
+
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::StatusSegment",
+        PiMsg::StatusSegment {
+            id: "ci".to_string(),
+            text: String::new(),
+        },
+    );
+    assert_after_not_contains(&harness, &step, "CI:");
+}
+
+#[test]
+fn tui_state_slash_tools_lists_and_rejects_unknown_tools() {
+    let harness = TestHarness::new("tui_state_slash_tools_lists_and_rejects_unknown_tools");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/tools");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Tools:");
+    assert_after_contains(&harness, &step, "(no tools registered)");
+
+    type_text(&harness, &mut app, "/tools disable bash");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Unknown tool: bash");
+
+    type_text(&harness, &mut app, "/tools disable");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Usage: /tools");
+}
+
+#[test]
+fn tui_state_save_and_restore_report_missing_checkpoints() {
+    let harness = TestHarness::new("tui_state_save_and_restore_report_missing_checkpoints");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/save");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "No checkpoints yet");
+
+    type_text(&harness, &mut app, "/save before-refactor");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Nothing to checkpoint yet");
+
+    type_text(&harness, &mut app, "/restore before-refactor");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "No checkpoint named before-refactor");
+}
+
+#[test]
+fn tui_state_slash_note_adds_and_lists_notes() {
+    let harness = TestHarness::new("tui_state_slash_note_adds_and_lists_notes");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/notes");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "No notes yet");
+
+    type_text(&harness, &mut app, "/note retry logic looks off");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Note: retry logic looks off");
+    assert_after_contains(&harness, &step, "Note saved (not sent to the model)");
+
+    type_text(&harness, &mut app, "/notes");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Notes:");
+    assert_after_contains(&harness, &step, ": retry logic looks off");
+}
+
+#[test]
+fn tui_state_slash_tour_steps_through_the_onboarding_tour() {
+    let harness = TestHarness::new("tui_state_slash_tour_steps_through_the_onboarding_tour");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    type_text(&harness, &mut app, "/tour");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Welcome to pi");
+    assert_after_contains(&harness, &step, "(1/5)");
+    assert_after_contains(&harness, &step, "[ ] Don't show again");
+
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Keys");
+    assert_after_contains(&harness, &step, "(2/5)");
+
+    let step = press_left(&harness, &mut app);
+    assert_after_contains(&harness, &step, "(1/5)");
+
+    let step = type_text(&harness, &mut app, "d");
+    assert_after_contains(&harness, &step, "[x] Don't show again");
+    let step = type_text(&harness, &mut app, "d");
+    assert_after_contains(&harness, &step, "[ ] Don't show again");
+
+    let step = press_esc(&harness, &mut app);
+    assert_after_not_contains(&harness, &step, "Welcome to pi");
+    assert_after_contains(&harness, &step, "Tour closed. /tour shows it again.");
+}
+
+#[test]
+fn tui_state_tool_approval_prompt_remembers_always_in_project_settings() {
+    let harness =
+        TestHarness::new("tui_state_tool_approval_prompt_remembers_always_in_project_settings");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let request = |id: &str, path: &str| ToolApprovalRequest {
+        tool_call_id: id.to_string(),
+        tool_name: "edit".to_string(),
+        args: json!({ "path": path }),
+        path: Some(path.to_string()),
+    };
+
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ToolApprovalRequest",
+        PiMsg::ToolApprovalRequest(request("call-1", "src/lib.rs")),
+    );
+    assert_after_contains(
+        &harness,
+        &step,
+        "Allow edit `src/lib.rs`? y: yes, n: no, a: always",
+    );
+
+    let step = type_text(&harness, &mut app, "n");
+    assert_after_contains(&harness, &step, "Denied edit");
+
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ToolApprovalRequest",
+        PiMsg::ToolApprovalRequest(request("call-2", "src/lib.rs")),
+    );
+    let step = type_text(&harness, &mut app, "a");
+    assert_after_contains(
+        &harness,
+        &step,
+        "Always allowing edit:src/lib.rs (saved to project settings)",
+    );
+
+    let settings = read_project_settings_json(&harness);
+    assert_eq!(
+        settings["tool_approval"]["always_allow"],
+        json!(["edit:src/lib.rs"])
+    );
+}
+
+#[test]
+fn tui_state_hunk_review_reverts_rejected_hunks() {
+    let harness = TestHarness::new("tui_state_hunk_review_reverts_rejected_hunks");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    let path = harness.temp_path("notes.txt");
+    let original: String = (1..=20).map(|n| format!("line {n}\n")).collect();
+    fs::write(&path, &original).expect("write notes");
+
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ToolApprovalRequest",
+        PiMsg::ToolApprovalRequest(ToolApprovalRequest {
+            tool_call_id: "call-1".to_string(),
+            tool_name: "edit".to_string(),
+            args: json!({ "path": "notes.txt" }),
+            path: Some("notes.txt".to_string()),
+        }),
+    );
+    let step = type_text(&harness, &mut app, "r");
+    assert_after_contains(&harness, &step, "review its hunks when it finishes");
+
+    // The edit runs, changing two distant lines.
+    let edited = original
+        .replace("line 2\n", "line two\n")
+        .replace("line 18\n", "line eighteen\n");
+    fs::write(&path, &edited).expect("apply edit");
+
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::HunkReview",
+        PiMsg::HunkReview(HunkReviewRequest {
+            tool_call_id: "call-1".to_string(),
+            tool_name: "edit".to_string(),
+            path: "notes.txt".to_string(),
+        }),
+    );
+    assert_after_contains(&harness, &step, "Review notes.txt · hunk 1 of 2");
+    assert_after_contains(&harness, &step, "+line two");
+
+    let step = type_text(&harness, &mut app, "n");
+    assert_after_contains(&harness, &step, "Review notes.txt · hunk 2 of 2");
+    let step = type_text(&harness, &mut app, "y");
+    assert_after_contains(&harness, &step, "Kept 1 of 2 hunks in notes.txt");
+    assert_after_not_contains(&harness, &step, "Review notes.txt");
+
+    let content = fs::read_to_string(&path).expect("read notes");
+    assert!(
+        content.contains("line 2\n"),
+        "rejected hunk reverted:\n{content}"
+    );
+    assert!(
+        content.contains("line eighteen\n"),
+        "kept hunk stays:\n{content}"
+    );
+}
+
+#[test]
+fn tui_state_header_shows_project_pinned_default_model() {
+    let harness = TestHarness::new("tui_state_header_shows_project_pinned_default_model");
+    let settings_path = harness.temp_path(".pi/settings.json");
+    fs::create_dir_all(settings_path.parent().expect("settings dir")).expect("create .pi");
+    fs::write(
+        &settings_path,
+        r#"{ "default_provider": "dummy", "default_model": "dummy-model" }"#,
+    )
+    .expect("write project settings");
+
+    let config = Config {
+        default_provider: Some("dummy".to_string()),
+        default_model: Some("dummy-model".to_string()),
+        ..Config::default()
+    };
+    let app = build_app_with_session_and_config(&harness, Vec::new(), Session::in_memory(), config);
+    log_initial_state(&harness, &app);
+
+    let view = normalize_view(&BubbleteaModel::view(&app));
+    assert!(
+        view.contains("(dummy/dummy-model · project default)"),
+        "expected project default marker in header:\n{view}"
+    );
+}
+
+#[test]
+fn tui_state_thinking_panel_streams_thinking_beside_conversation() {
+    let harness = TestHarness::new("tui_state_thinking_panel_streams_thinking_beside_conversation");
+    let mut app = build_app(&harness, Vec::new());
+    app.set_terminal_size(100, 24);
+    log_initial_state(&harness, &app);
+
+    let step = apply_key(
+        &harness,
+        &mut app,
+        "key:Alt+T",
+        KeyMsg::from_runes(vec!['t']).with_alt(),
+    );
+    assert_after_contains(&harness, &step, "Thinking panel shown");
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    let long_thought = "Compare both parsers before touching the lexer; the second one already handles escapes and nested quotes correctly";
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ThinkingDelta",
+        PiMsg::ThinkingDelta(long_thought.to_string()),
+    );
+    // Streamed into the panel in full (wrapped) rather than as a truncated inline summary.
+    assert_after_not_contains(&harness, &step, "Thinking: Compare");
+    assert_after_contains(&harness, &step, "│ Thinking");
+    assert_after_contains(&harness, &step, "nested quotes");
+
+    let step = apply_key(
+        &harness,
+        &mut app,
+        "key:Alt+T",
+        KeyMsg::from_runes(vec!['t']).with_alt(),
+    );
+    assert_after_contains(&harness, &step, "Thinking panel hidden");
+    assert_after_contains(&harness, &step, "Thinking: Compare");
+}
+
+#[test]
+fn tui_state_resize_reflows_long_transcript_lines() {
+    let harness = TestHarness::new("tui_state_resize_reflows_long_transcript_lines");
+    let mut app = build_app(&harness, Vec::new());
+    app.set_terminal_size(140, 30);
+    log_initial_state(&harness, &app);
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    let long_line = "alphastart then a long streamed sentence that easily overflows a narrow terminal window omegaend";
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::TextDelta",
+        PiMsg::TextDelta(long_line.to_string()),
+    );
+    let wide = normalize_view(&BubbleteaModel::view(&app));
+    let line_with = |view: &str, needle: &str| {
+        view.lines()
+            .find(|line| line.contains(needle))
+            .map(str::to_string)
+            .unwrap_or_default()
+    };
+    assert!(line_with(&wide, "alphastart").contains("omegaend"));
+
+    app.set_terminal_size(50, 30);
+    let narrow = normalize_view(&BubbleteaModel::view(&app));
+    let first = line_with(&narrow, "alphastart");
+    let last = line_with(&narrow, "omegaend");
+    assert!(!first.is_empty() && !last.is_empty(), "view:\n{narrow}");
+    assert_ne!(first, last);
+    for line in [&first, &last] {
+        assert!(line.chars().count() <= 50, "line too wide: {line:?}");
+    }
+}
+
+#[test]
+fn tui_state_model_picker_labels_unhealthy_providers() {
+    let harness = TestHarness::new("tui_state_model_picker_labels_unhealthy_providers");
+    let anthropic = make_model_entry(
+        "anthropic",
+        "claude-a",
+        "https://api.anthropic.com/v1/messages",
+    );
+    let openai = make_model_entry("openai", "gpt-a", "https://api.openai.com/v1");
+    let available_models = vec![anthropic.clone(), openai];
+    let mut app = build_app_with_models(
+        &harness,
+        Session::in_memory(),
+        Config::default(),
+        anthropic,
+        Vec::new(),
+        available_models,
+        KeyBindings::new(),
+    );
+    log_initial_state(&harness, &app);
+
+    let health = |provider: &str, status| ProviderHealth {
+        provider: provider.to_string(),
+        url: format!("https://{provider}.invalid"),
+        status,
+        latency_ms: 5000,
+        detail: None,
+    };
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ProviderHealth",
+        PiMsg::ProviderHealth(vec![
+            health("anthropic", HealthStatus::Healthy),
+            health("openai", HealthStatus::Unreachable),
+        ]),
+    );
+
+    type_text(&harness, &mut app, "/model");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "openai/gpt-a [unreachable]");
+    assert_after_not_contains(&harness, &step, "[healthy]");
+}
+
+#[test]
+fn tui_state_budget_downgrade_switches_to_cheaper_model() {
+    let harness = TestHarness::new("tui_state_budget_downgrade_switches_to_cheaper_model");
+    let expensive = make_model_entry(
+        "anthropic",
+        "claude-big",
+        "https://api.anthropic.com/v1/messages",
+    );
+    let cheap = make_model_entry(
+        "anthropic",
+        "claude-small",
+        "https://api.anthropic.com/v1/messages",
+    );
+    let config = Config {
+        budget: Some(BudgetSettings {
+            downgrade_after_usd: Some(1.0),
+            downgrade_model: Some("anthropic/claude-small".to_string()),
+        }),
+        ..Config::default()
+    };
+    let mut app = build_app_with_models(
+        &harness,
+        Session::in_memory(),
+        config,
+        expensive.clone(),
+        Vec::new(),
+        vec![expensive, cheap],
+        KeyBindings::new(),
+    );
+    app.set_terminal_size(200, 40);
+    log_initial_state(&harness, &app);
+
+    let done = |total: f64| PiMsg::AgentDone {
+        usage: Some(Usage {
+            cost: Cost {
+                total,
+                ..Cost::default()
+            },
+            ..Usage::default()
+        }),
+        stop_reason: StopReason::Stop,
+        error_message: None,
+    };
+    apply_pi(&harness, &mut app, "PiMsg::AgentDone(0.60)", done(0.6));
+    let step = apply_pi(&harness, &mut app, "PiMsg::TurnSaved", PiMsg::TurnSaved);
+    assert_after_not_contains(&harness, &step, "budget");
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentDone(0.55)", done(0.55));
+    let step = apply_pi(&harness, &mut app, "PiMsg::TurnSaved", PiMsg::TurnSaved);
+    assert_after_contains(
+        &harness,
+        &step,
+        "Session spend $1.15 passed the $1.00 budget; switched from anthropic/claude-big to anthropic/claude-small.",
+    );
+    assert_after_contains(
+        &harness,
+        &step,
+        "Budget reached: switched model to anthropic/claude-small",
+    );
+}
+
+#[test]
+fn tui_state_exit_summary_counts_this_run() {
+    let harness = TestHarness::new("tui_state_exit_summary_counts_this_run");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    for id in ["tool-1", "tool-2"] {
+        apply_pi(
+            &harness,
+            &mut app,
+            "PiMsg::ToolStart",
+            PiMsg::ToolStart {
+                name: "read".to_string(),
+                tool_id: id.to_string(),
+            },
+        );
+    }
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::AgentDone",
+        PiMsg::AgentDone {
+            usage: Some(Usage {
+                input: 1200,
+                output: 300,
+                total_tokens: 1500,
+                cost: Cost {
+                    total: 0.0125,
+                    ..Cost::default()
+                },
+                ..Usage::default()
+            }),
+            stop_reason: StopReason::Stop,
+            error_message: None,
+        },
+    );
+
+    let summary = app.exit_summary();
+    assert_eq!(summary.turns, 1);
+    assert_eq!(summary.tool_calls, 2);
+    let content = summary.to_content();
+    harness.log().info_ctx("summary", "exit summary", |ctx| {
+        ctx.push(("content".to_string(), content.clone()));
+    });
+    assert!(content.contains("Tokens:     1500 (1200 in / 300 out)"));
+    assert!(content.contains("Cost:       $0.0125"));
+    assert!(content.contains("Session:    not saved"));
+}
+
+#[test]
+fn tui_state_attach_glob_previews_and_truncates_files() {
+    let harness = TestHarness::new("tui_state_attach_glob_previews_and_truncates_files");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+    let src = harness.temp_dir().join("src");
+    std::fs::create_dir_all(&src).expect("create src");
+    std::fs::write(src.join("a.rs"), "fn a() {}\n").expect("write a.rs");
+    let long: String = (0..1500).map(|i| format!("line {i}\n")).collect();
+    std::fs::write(src.join("b.rs"), long).expect("write b.rs");
+    std::fs::write(harness.temp_dir().join("notes.md"), "notes\n").expect("write notes");
+
+    type_text(&harness, &mut app, "/attach src/*.rs");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Attached 2 files to your next message");
+    assert_after_contains(&harness, &step, "src/a.rs");
+    assert_after_contains(&harness, &step, "truncated to 1000 of 1501 lines");
+    assert_after_not_contains(&harness, &step, "notes.md");
+
+    type_text(&harness, &mut app, "/attach src/*.rs");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "already attached");
+
+    type_text(&harness, &mut app, "/attach *.txt");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "No files match *.txt");
+
+    type_text(&harness, &mut app, "/attach clear");
+    let step = press_enter(&harness, &mut app);
+    assert_after_contains(&harness, &step, "Removed 2 attachments");
+}
+
+#[test]
+fn tui_state_plain_input_answers_prompts_and_queues_while_busy() {
+    let harness = TestHarness::new("tui_state_plain_input_answers_prompts_and_queues_while_busy");
+    let mut app = build_app(&harness, Vec::new());
+    log_initial_state(&harness, &app);
+
+    apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::ToolApprovalRequest",
+        PiMsg::ToolApprovalRequest(ToolApprovalRequest {
+            tool_call_id: "call-1".to_string(),
+            tool_name: "bash".to_string(),
+            args: json!({ "command": "cargo test" }),
+            path: None,
+        }),
+    );
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::PlainInput(maybe)",
+        PiMsg::PlainInput("maybe".to_string()),
+    );
+    assert_after_contains(&harness, &step, "Allow bash `cargo test`?");
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::PlainInput(yes)",
+        PiMsg::PlainInput("yes".to_string()),
+    );
+    assert_after_contains(&harness, &step, "Allowed bash");
+
+    apply_pi(&harness, &mut app, "PiMsg::AgentStart", PiMsg::AgentStart);
+    let step = apply_pi(
+        &harness,
+        &mut app,
+        "PiMsg::PlainInput(follow-up)",
+        PiMsg::PlainInput("and the docs too".to_string()),
+    );
+    assert_after_contains(
+        &harness,
+        &step,
+        "Queued; it runs when the current reply finishes",
+    );
+}